@@ -3,15 +3,34 @@
 //! Abbreviations are similar to mappings but trigger only in Insert, Replace, and Command-line modes,
 //! and only when a non-keyword character is typed after the abbreviation.
 //!
+//! # Abbreviation Kinds
+//!
+//! Which characters of the left-hand side are keyword characters, as set
+//! with 'iskeyword', makes three kinds of abbreviation:
+//!
+//! - full-id: only keyword characters, as `foo` or `g3`
+//! - end-id: ends in a keyword character, the others are not, as `#i`
+//!   or `..f`
+//! - non-id: ends in a non-keyword character, the others can be any but
+//!   white space, as `def#` or `4/7$`
+//!
+//! Others, such as `a.b` or `#def`, are not valid ([`AbbreviationKind::of`]).
+//!
 //! # Key Behavioral Contracts
 //!
 //! - Abbreviations are mode-specific (iabbrev, cabbrev).
-//! - Abbreviations are triggered by non-keyword characters.
-//! - Abbreviations replace the typed word with the expansion.
+//! - Abbreviations are triggered by a non-keyword character, `<Esc>`, `<CR>`
+//!   and `Ctrl-]`, which triggers without inserting anything.  `Ctrl-V`
+//!   before the character inserts it without expanding.
+//! - Abbreviations replace the typed word with the expansion.  The word is
+//!   found as [`abbreviation_start`] tells; in Insert mode it does not
+//!   start before where inserting started.
+//! - Buffer-local abbreviations are found before global ones.
+//! - The right-hand side of an `<expr>` abbreviation is an expression,
+//!   evaluated when expanding.
 
-use crate::modes::Mode;
+use crate::modes::{CommandLineMode, Mode};
 use crate::types::*;
-use std::collections::HashMap;
 
 // ============================================================================
 // Abbreviation Types
@@ -30,6 +49,83 @@ pub struct Abbreviation {
     pub silent: bool,
     /// Whether it's buffer-local
     pub buffer_local: bool,
+    /// Whether the right-hand side is an expression (`<expr>`)
+    pub expr: bool,
+}
+
+/// The kind of an abbreviation, by the keyword characters of its
+/// left-hand side
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AbbreviationKind {
+    /// Only keyword characters
+    FullId,
+    /// Ends in a keyword character, the others are not
+    EndId,
+    /// Ends in a non-keyword character
+    NonId,
+}
+
+impl AbbreviationKind {
+    /// The kind of the left-hand side `lhs`, with `is_keyword` telling the
+    /// keyword characters.  E474 when it is not a valid abbreviation.
+    pub fn of(lhs: &str, is_keyword: impl Fn(char) -> bool) -> VimResult<Self> {
+        let invalid = || VimError::Error(474, "Invalid argument".to_string());
+        let chars: Vec<char> = lhs.chars().collect();
+        let Some((&last, rest)) = chars.split_last() else {
+            return Err(invalid());
+        };
+        if chars.iter().any(|c| c.is_whitespace()) {
+            return Err(invalid());
+        }
+        if !is_keyword(last) {
+            return Ok(AbbreviationKind::NonId);
+        }
+        match rest.iter().filter(|&&c| is_keyword(c)).count() {
+            n if n == rest.len() => Ok(AbbreviationKind::FullId),
+            0 => Ok(AbbreviationKind::EndId),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// The start of the word before byte `col` of `line` that can be an
+/// abbreviation, not before `mincol`.  When the word ends in a keyword
+/// character it goes back over characters of the kind of the one before
+/// that, otherwise back to white space.  `None` when there is no word.
+pub fn abbreviation_start(
+    line: &str,
+    col: usize,
+    mincol: usize,
+    is_keyword: impl Fn(char) -> bool,
+) -> Option<usize> {
+    let before = line.get(..col)?;
+    let mut chars = before.char_indices().rev();
+    let (mut start, last) = chars.next()?;
+    // The kind of the characters an abbreviation ending in a keyword
+    // character consists of, besides the last one
+    let kind = if is_keyword(last) {
+        Some(chars.clone().next().is_none_or(|(_, c)| is_keyword(c)))
+    } else {
+        None
+    };
+    for (idx, c) in chars {
+        if idx < mincol || c.is_whitespace() || kind.is_some_and(|kind| is_keyword(c) != kind) {
+            break;
+        }
+        start = idx;
+    }
+    (start >= mincol && !last.is_whitespace()).then_some(start)
+}
+
+/// A line of the listing of `:abbreviate`: the modes (`i`, `c`, or `!`
+/// for both), the left-hand side, `*` for noremap, `@` for buffer-local
+/// and the right-hand side
+pub fn list_line(modes: char, abbrev: &Abbreviation) -> String {
+    let mut line = format!("{}  {:<11} ", modes, abbrev.lhs);
+    line.push(if abbrev.noremap { '*' } else { ' ' });
+    line.push(if abbrev.buffer_local { '@' } else { ' ' });
+    line.push_str(&abbrev.rhs);
+    line
 }
 
 // ============================================================================
@@ -41,18 +137,26 @@ pub trait AbbreviationManager {
     /// Add an abbreviation
     fn add(&mut self, mode: Mode, lhs: &str, rhs: &str, noremap: bool, buffer_local: bool) -> VimResult<()>;
 
+    /// Add an abbreviation whose right-hand side is an expression
+    fn add_expr(&mut self, mode: Mode, lhs: &str, rhs: &str, noremap: bool, buffer_local: bool) -> VimResult<()>;
+
     /// Remove an abbreviation
     fn remove(&mut self, mode: Mode, lhs: &str) -> VimResult<()>;
 
+    /// Remove all abbreviations of a mode
+    fn clear(&mut self, mode: Mode);
+
     /// Get a specific abbreviation
     fn get(&self, mode: Mode, lhs: &str) -> Option<&Abbreviation>;
 
+    /// The abbreviations of a mode, the last defined first
+    fn list(&self, mode: Mode) -> Vec<&Abbreviation>;
+
     /// Check if the word before cursor triggers an abbreviation.
     ///
     /// # Arguments
     /// * `mode` - Current mode (Insert or Cmdline)
     /// * `word` - The word immediately before the cursor (excluding trigger char)
-    /// * `trigger_char` - The character just typed that might trigger expansion
     ///
     /// # Returns
     /// * `Some(Abbreviation)` if expansion should occur
@@ -64,79 +168,148 @@ pub trait AbbreviationManager {
 // Simple Implementation
 // ============================================================================
 
-/// A HashMap-based abbreviation manager
+/// A list-based abbreviation manager
 #[derive(Debug, Default, Clone)]
 pub struct SimpleAbbreviationManager {
-    /// Abbreviations storage: Mode -> LHS -> Abbreviation
-    abbrevs: HashMap<Mode, HashMap<String, Abbreviation>>,
+    /// Abbreviations with their mode, in the order they were defined
+    entries: Vec<(Mode, Abbreviation)>,
+}
+
+/// The mode abbreviations of `mode` are defined for: Replace mode uses the
+/// ones of Insert mode
+fn abbreviation_mode(mode: Mode) -> Mode {
+    match mode {
+        Mode::Replace => Mode::Insert,
+        Mode::CommandLine(_) => Mode::CommandLine(CommandLineMode::Normal),
+        mode => mode,
+    }
 }
 
 impl SimpleAbbreviationManager {
     /// Create a new empty abbreviation manager
     pub fn new() -> Self {
         SimpleAbbreviationManager {
-            abbrevs: HashMap::new(),
+            entries: Vec::new(),
         }
     }
 
-    fn get_mode_map(&self, mode: Mode) -> Option<&HashMap<String, Abbreviation>> {
-        self.abbrevs.get(&mode)
+    fn insert(&mut self, mode: Mode, abbrev: Abbreviation) -> VimResult<()> {
+        if abbrev.lhs.is_empty() {
+            return Err(VimError::Error(474, "Invalid argument".to_string()));
+        }
+        let mode = abbreviation_mode(mode);
+        match self
+            .entries
+            .iter_mut()
+            .find(|(m, a)| *m == mode && a.lhs == abbrev.lhs)
+        {
+            Some(entry) => entry.1 = abbrev,
+            None => self.entries.push((mode, abbrev)),
+        }
+        Ok(())
     }
 
-    fn get_mode_map_mut(&mut self, mode: Mode) -> &mut HashMap<String, Abbreviation> {
-        self.abbrevs.entry(mode).or_default()
+    /// The lines of the listing of `:abbreviate` for the abbreviations of
+    /// `modes` whose left-hand side starts with `prefix`, the last defined
+    /// first.  One defined the same for Insert and Command-line mode is
+    /// one line.
+    pub fn listing(&self, modes: &[Mode], prefix: &str) -> Vec<String> {
+        let modes: Vec<Mode> = modes.iter().map(|&mode| abbreviation_mode(mode)).collect();
+        let mut shown = vec![false; self.entries.len()];
+        let mut lines = Vec::new();
+        for (idx, (mode, abbrev)) in self.entries.iter().enumerate().rev() {
+            if shown[idx] || !abbrev.lhs.starts_with(prefix) {
+                continue;
+            }
+            let twin = self
+                .entries
+                .iter()
+                .position(|(m, a)| m != mode && a == abbrev);
+            let mut listed = modes.contains(mode);
+            let modes_char = match twin {
+                Some(twin) => {
+                    shown[twin] = true;
+                    listed |= modes.contains(&self.entries[twin].0);
+                    '!'
+                }
+                None if *mode == Mode::Insert => 'i',
+                None => 'c',
+            };
+            if listed {
+                lines.push(list_line(modes_char, abbrev));
+            }
+        }
+        lines
     }
 }
 
 impl AbbreviationManager for SimpleAbbreviationManager {
     fn add(&mut self, mode: Mode, lhs: &str, rhs: &str, noremap: bool, buffer_local: bool) -> VimResult<()> {
-        if lhs.is_empty() {
-            return Err(VimError::Error(0, "Abbreviation LHS cannot be empty".to_string()));
-        }
-        
         let abbrev = Abbreviation {
             lhs: lhs.to_string(),
             rhs: rhs.to_string(),
             noremap,
             silent: false,
             buffer_local,
+            expr: false,
         };
+        self.insert(mode, abbrev)
+    }
 
-        self.get_mode_map_mut(mode).insert(lhs.to_string(), abbrev);
-        Ok(())
+    fn add_expr(&mut self, mode: Mode, lhs: &str, rhs: &str, noremap: bool, buffer_local: bool) -> VimResult<()> {
+        let abbrev = Abbreviation {
+            lhs: lhs.to_string(),
+            rhs: rhs.to_string(),
+            noremap,
+            silent: false,
+            buffer_local,
+            expr: true,
+        };
+        self.insert(mode, abbrev)
     }
 
     fn remove(&mut self, mode: Mode, lhs: &str) -> VimResult<()> {
-        if let Some(map) = self.abbrevs.get_mut(&mode) {
-            if map.remove(lhs).is_some() {
+        let mode = abbreviation_mode(mode);
+        match self
+            .entries
+            .iter()
+            .position(|(m, a)| *m == mode && a.lhs == lhs)
+        {
+            Some(idx) => {
+                self.entries.remove(idx);
                 Ok(())
-            } else {
-                Err(VimError::Error(0, format!("No such abbreviation: {}", lhs)))
             }
-        } else {
-            Err(VimError::Error(0, format!("No such abbreviation: {}", lhs)))
+            None => Err(VimError::Error(24, "No such abbreviation".to_string())),
         }
     }
 
+    fn clear(&mut self, mode: Mode) {
+        let mode = abbreviation_mode(mode);
+        self.entries.retain(|(m, _)| *m != mode);
+    }
+
     fn get(&self, mode: Mode, lhs: &str) -> Option<&Abbreviation> {
-        self.get_mode_map(mode).and_then(|m| m.get(lhs))
+        let mode = abbreviation_mode(mode);
+        self.entries
+            .iter()
+            .find(|(m, a)| *m == mode && a.lhs == lhs)
+            .map(|(_, a)| a)
+    }
+
+    fn list(&self, mode: Mode) -> Vec<&Abbreviation> {
+        let mode = abbreviation_mode(mode);
+        self.entries
+            .iter()
+            .rev()
+            .filter(|(m, _)| *m == mode)
+            .map(|(_, a)| a)
+            .collect()
     }
 
     fn check(&self, mode: Mode, word: &str) -> Option<&Abbreviation> {
-        // Vim's abbreviation logic:
-        // 1. The typed character must be a non-keyword character (handled by caller usually)
-        // 2. The word before the cursor must match the lhs of an abbreviation
-        // 3. The character *before* the word must be a non-keyword character (or start of line)
-        //    (This ensures we don't expand "foobar" when "bar" is the abbreviation)
-        //
-        // However, `word` passed here is assumed to be the candidate string.
-        // The caller is responsible for extracting the "word" candidates.
-        // Vim checks: is there an abbrev for `word`?
-        
-        // Note: Insert mode and Replace mode share abbreviations.
-        let check_mode = if mode == Mode::Replace { Mode::Insert } else { mode };
-        
-        self.get_mode_map(check_mode).and_then(|m| m.get(word))
+        // The caller extracts the word with `abbreviation_start`, which
+        // ensures we don't expand "foobar" when "bar" is the abbreviation
+        self.get(mode, word)
     }
 }
 
@@ -144,11 +317,15 @@ impl AbbreviationManager for SimpleAbbreviationManager {
 mod tests {
     use super::*;
 
+    fn is_keyword(c: char) -> bool {
+        c.is_alphanumeric() || c == '_'
+    }
+
     #[test]
     fn test_add_get_abbrev() {
         let mut mgr = SimpleAbbreviationManager::new();
         mgr.add(Mode::Insert, "ad", "advertisement", false, false).unwrap();
-        
+
         let a = mgr.get(Mode::Insert, "ad").unwrap();
         assert_eq!(a.rhs, "advertisement");
     }
@@ -157,9 +334,84 @@ mod tests {
     fn test_check_abbrev() {
         let mut mgr = SimpleAbbreviationManager::new();
         mgr.add(Mode::Insert, "ad", "advertisement", false, false).unwrap();
-        
+
         assert!(mgr.check(Mode::Insert, "ad").is_some());
+        assert!(mgr.check(Mode::Replace, "ad").is_some());
         assert!(mgr.check(Mode::Insert, "bad").is_none());
         assert!(mgr.check(Mode::Normal, "ad").is_none()); // Wrong mode
+        assert!(mgr.check(Mode::CommandLine(CommandLineMode::Normal), "ad").is_none());
+    }
+
+    #[test]
+    fn test_abbreviation_kind() {
+        let kind = |lhs| AbbreviationKind::of(lhs, is_keyword);
+        assert_eq!(kind("foo"), Ok(AbbreviationKind::FullId));
+        assert_eq!(kind("g3"), Ok(AbbreviationKind::FullId));
+        assert_eq!(kind("#i"), Ok(AbbreviationKind::EndId));
+        assert_eq!(kind("..f"), Ok(AbbreviationKind::EndId));
+        assert_eq!(kind("$/7"), Ok(AbbreviationKind::EndId));
+        assert_eq!(kind("def#"), Ok(AbbreviationKind::NonId));
+        assert_eq!(kind("4/7$"), Ok(AbbreviationKind::NonId));
+        for invalid in ["a.b", "#def", "a b", "_$r", ""] {
+            assert_eq!(
+                kind(invalid).unwrap_err().to_string(),
+                "E474: Invalid argument"
+            );
+        }
+    }
+
+    #[test]
+    fn test_abbreviation_start() {
+        let start = |line: &str, mincol| abbreviation_start(line, line.len(), mincol, is_keyword);
+        assert_eq!(start("say foo", 0), Some(4));
+        assert_eq!(start("a#ad", 0), Some(2));
+        assert_eq!(start("x #i", 0), Some(2));
+        assert_eq!(start("x..#i", 0), Some(1));
+        assert_eq!(start("say 4/7$", 0), Some(4));
+        assert_eq!(start("a def#", 0), Some(2));
+        assert_eq!(start("foo", 2), Some(2));
+        assert_eq!(start("foo ", 0), None);
+        assert_eq!(start("", 0), None);
+    }
+
+    #[test]
+    fn test_remove_and_clear() {
+        let mut mgr = SimpleAbbreviationManager::new();
+        let cmdline = Mode::CommandLine(CommandLineMode::Insert);
+        mgr.add(Mode::Insert, "a", "b", false, false).unwrap();
+        mgr.add(cmdline, "c", "d", false, false).unwrap();
+        assert_eq!(
+            mgr.remove(Mode::Insert, "c").unwrap_err().to_string(),
+            "E24: No such abbreviation"
+        );
+        mgr.clear(Mode::Insert);
+        assert!(mgr.get(Mode::Insert, "a").is_none());
+        assert!(mgr.get(cmdline, "c").is_some());
+        mgr.remove(cmdline, "c").unwrap();
+        assert!(mgr.list(cmdline).is_empty());
+    }
+
+    #[test]
+    fn test_listing() {
+        let mut mgr = SimpleAbbreviationManager::new();
+        let cmdline = Mode::CommandLine(CommandLineMode::Normal);
+        mgr.add(Mode::Insert, "foo", "bar", false, false).unwrap();
+        mgr.add(cmdline, "cx", "cy", false, false).unwrap();
+        mgr.add(Mode::Insert, "teh", "the", false, false).unwrap();
+        mgr.add(cmdline, "teh", "the", false, false).unwrap();
+        mgr.add_expr(Mode::Insert, "ex", "1+1", true, true).unwrap();
+        assert_eq!(
+            mgr.listing(&[Mode::Insert, cmdline], ""),
+            [
+                "i  ex          *@1+1",
+                "!  teh           the",
+                "c  cx            cy",
+                "i  foo           bar",
+            ]
+        );
+        assert_eq!(
+            mgr.listing(&[cmdline], "t"),
+            ["!  teh           the"]
+        );
     }
 }
@@ -10,9 +10,30 @@ use crate::types::*;
 // Autocommand Events
 // ============================================================================
 
-/// Autocommand event types
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub enum AutocmdEvent {
+/// Declare the event enum together with its name table
+macro_rules! autocmd_events {
+    ($($(#[$attr:meta])* $name:ident,)*) => {
+        /// Autocommand event types
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum AutocmdEvent {
+            $($(#[$attr])* $name,)*
+        }
+
+        impl AutocmdEvent {
+            /// Every event, in declaration order
+            pub const ALL: &'static [AutocmdEvent] = &[$(AutocmdEvent::$name,)*];
+
+            /// Get the event name
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(AutocmdEvent::$name => stringify!($name),)*
+                }
+            }
+        }
+    };
+}
+
+autocmd_events! {
     // Buffer events
     BufAdd,
     BufDelete,
@@ -144,50 +165,18 @@ pub enum AutocmdEvent {
 }
 
 impl AutocmdEvent {
-    /// Parse an event name
+    /// Parse an event name, ignoring case
     pub fn from_name(name: &str) -> Option<Self> {
-        // This would be a large match statement
-        // For brevity, showing a few examples
+        // Vim treats these as synonyms of the Post/Pre events
         match name.to_lowercase().as_str() {
-            "bufenter" => Some(AutocmdEvent::BufEnter),
-            "bufleave" => Some(AutocmdEvent::BufLeave),
-            "bufread" | "bufreadpost" => Some(AutocmdEvent::BufReadPost),
-            "bufwrite" | "bufwritepre" => Some(AutocmdEvent::BufWritePre),
-            "cursorhold" => Some(AutocmdEvent::CursorHold),
-            "cursormoved" => Some(AutocmdEvent::CursorMoved),
-            "insertenter" => Some(AutocmdEvent::InsertEnter),
-            "insertleave" => Some(AutocmdEvent::InsertLeave),
-            "textchanged" => Some(AutocmdEvent::TextChanged),
-            "filetype" => Some(AutocmdEvent::FileType),
-            "vimenter" => Some(AutocmdEvent::VimEnter),
-            "vimleave" => Some(AutocmdEvent::VimLeave),
-            "winenter" => Some(AutocmdEvent::WinEnter),
-            "winleave" => Some(AutocmdEvent::WinLeave),
-            "modechanged" => Some(AutocmdEvent::ModeChanged),
-            _ => None,
-        }
-    }
-
-    /// Get the event name
-    pub fn name(&self) -> &'static str {
-        match self {
-            AutocmdEvent::BufEnter => "BufEnter",
-            AutocmdEvent::BufLeave => "BufLeave",
-            AutocmdEvent::BufReadPost => "BufReadPost",
-            AutocmdEvent::BufWritePre => "BufWritePre",
-            AutocmdEvent::CursorHold => "CursorHold",
-            AutocmdEvent::CursorMoved => "CursorMoved",
-            AutocmdEvent::InsertEnter => "InsertEnter",
-            AutocmdEvent::InsertLeave => "InsertLeave",
-            AutocmdEvent::TextChanged => "TextChanged",
-            AutocmdEvent::FileType => "FileType",
-            AutocmdEvent::VimEnter => "VimEnter",
-            AutocmdEvent::VimLeave => "VimLeave",
-            AutocmdEvent::WinEnter => "WinEnter",
-            AutocmdEvent::WinLeave => "WinLeave",
-            AutocmdEvent::ModeChanged => "ModeChanged",
-            _ => "Unknown",
+            "bufread" => return Some(AutocmdEvent::BufReadPost),
+            "bufwrite" => return Some(AutocmdEvent::BufWritePre),
+            _ => {}
         }
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|event| event.name().eq_ignore_ascii_case(name))
     }
 }
 
@@ -312,6 +301,21 @@ mod tests {
         assert_eq!(AutocmdEvent::from_name("invalid"), None);
     }
 
+    #[test]
+    fn test_event_names_round_trip() {
+        for event in AutocmdEvent::ALL {
+            if matches!(event, AutocmdEvent::BufRead | AutocmdEvent::BufWrite) {
+                continue;
+            }
+            assert_eq!(AutocmdEvent::from_name(event.name()), Some(*event));
+        }
+        assert_eq!(AutocmdEvent::CompleteDone.name(), "CompleteDone");
+        assert_eq!(
+            AutocmdEvent::from_name("bufread"),
+            Some(AutocmdEvent::BufReadPost)
+        );
+    }
+
     #[allow(dead_code)]
     mod behavioral_tests {
         //! # Autocommand Behavioral Tests
@@ -139,6 +139,87 @@ pub struct BufferChange {
     pub new_last_line: usize,
     /// The new changedtick value
     pub changedtick: u64,
+    /// The same change at byte level
+    pub splice: TextSplice,
+}
+
+/// A byte-level description of a change: the text from `start` to
+/// `old_end` was replaced by text that now ends at `new_end`.
+///
+/// Positions are 0-indexed `(row, byte column)` pairs, as in Neovim's
+/// `on_bytes` buffer event.  A change that ends at the start of a line
+/// has an end of `(row, 0)`, which may be one past the last line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextSplice {
+    /// Where the change starts
+    pub start: (usize, usize),
+    /// End of the replaced text, before the change
+    pub old_end: (usize, usize),
+    /// End of the new text, after the change
+    pub new_end: (usize, usize),
+}
+
+impl TextSplice {
+    /// The change that turns `old` into `new`, both starting at `row`.
+    /// Each line is taken to end with a newline, so lines added or removed
+    /// before the next line end at column zero.  Only the text that
+    /// differs is reported, so replacing a line with an edited copy
+    /// describes the edit within the line.
+    pub fn between(row: usize, old: &[String], new: &[String]) -> Self {
+        let old_text: String = old.iter().map(|line| format!("{}\n", line)).collect();
+        let new_text: String = new.iter().map(|line| format!("{}\n", line)).collect();
+        let prefix: usize = old_text
+            .chars()
+            .zip(new_text.chars())
+            .take_while(|(a, b)| a == b)
+            .map(|(a, _)| a.len_utf8())
+            .sum();
+        let max_suffix = old_text.len().min(new_text.len()) - prefix;
+        let suffix: usize = old_text[prefix..]
+            .chars()
+            .rev()
+            .zip(new_text[prefix..].chars().rev())
+            .take_while(|(a, b)| a == b)
+            .map(|(a, _)| a.len_utf8())
+            .scan(0, |len, ch| {
+                *len += ch;
+                (*len <= max_suffix).then_some(ch)
+            })
+            .sum();
+        let position = |text: &str, offset: usize| {
+            let before = &text[..offset];
+            let rows = before.matches('\n').count();
+            let col = before.rfind('\n').map_or(offset, |nl| offset - nl - 1);
+            (row + rows, col)
+        };
+        TextSplice {
+            start: position(&old_text, prefix),
+            old_end: position(&old_text, old_text.len() - suffix),
+            new_end: position(&new_text, new_text.len() - suffix),
+        }
+    }
+
+    /// Where `pos` is after the change.  Positions inside the replaced
+    /// text move to its start.  A position at the end of the replaced
+    /// text, such as the point of an insertion, stays before the new text
+    /// with left gravity and moves after it with right gravity.
+    pub fn map(&self, pos: (usize, usize), right_gravity: bool) -> (usize, usize) {
+        if pos < self.start {
+            pos
+        } else if pos < self.old_end {
+            self.start
+        } else if pos == self.old_end {
+            if right_gravity {
+                self.new_end
+            } else {
+                self.start
+            }
+        } else if pos.0 == self.old_end.0 {
+            (self.new_end.0, self.new_end.1 + pos.1 - self.old_end.1)
+        } else {
+            (pos.0 + self.new_end.0 - self.old_end.0, pos.1)
+        }
+    }
 }
 
 // ============================================================================
@@ -603,4 +684,49 @@ mod tests {
         //!
         //! 6. **Changedtick**: Increments on every modification.
     }
+
+    fn lines(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn test_splice_between_finds_edit_within_line() {
+        let splice = TextSplice::between(3, &lines(&["hello"]), &lines(&["heXllo"]));
+        assert_eq!(splice.start, (3, 2));
+        assert_eq!(splice.old_end, (3, 2));
+        assert_eq!(splice.new_end, (3, 3));
+
+        // Splitting a line and joining it back
+        let split = TextSplice::between(0, &lines(&["abcdef"]), &lines(&["abc", "def"]));
+        assert_eq!((split.start, split.old_end, split.new_end), ((0, 3), (0, 3), (1, 0)));
+        let join = TextSplice::between(0, &lines(&["abc", "def"]), &lines(&["abc def"]));
+        assert_eq!((join.start, join.old_end, join.new_end), ((0, 3), (1, 0), (0, 4)));
+
+        // Deleting whole lines
+        let delete = TextSplice::between(2, &lines(&["one", "two"]), &[]);
+        assert_eq!((delete.start, delete.old_end, delete.new_end), ((2, 0), (4, 0), (2, 0)));
+    }
+
+    #[test]
+    fn test_splice_map_gravity() {
+        let insert = TextSplice {
+            start: (0, 2),
+            old_end: (0, 2),
+            new_end: (0, 5),
+        };
+        assert_eq!(insert.map((0, 1), true), (0, 1));
+        assert_eq!(insert.map((0, 2), true), (0, 5));
+        assert_eq!(insert.map((0, 2), false), (0, 2));
+        assert_eq!(insert.map((0, 4), false), (0, 7));
+        assert_eq!(insert.map((1, 4), false), (1, 4));
+
+        let join = TextSplice {
+            start: (0, 3),
+            old_end: (1, 0),
+            new_end: (0, 4),
+        };
+        assert_eq!(join.map((1, 2), true), (0, 6));
+        assert_eq!(join.map((0, 3), false), (0, 3));
+        assert_eq!(join.map((2, 1), true), (1, 1));
+    }
 }
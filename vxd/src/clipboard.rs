@@ -0,0 +1,213 @@
+//! Clipboard providers for the `+` and `*` registers.
+//!
+//! The `+` (clipboard) and `*` (selection) registers are backed by the
+//! system clipboard through a [`ClipboardProvider`]: a tool such as
+//! xclip, wl-copy or tmux, or the terminal itself with OSC 52.  The
+//! system clipboard only holds text, so the register type would be lost on
+//! a copy and paste.  [`RegisterTypeChannel`] remembers the type of what
+//! was copied and restores it when the same text comes back.
+//!
+//! # Key Behavioral Contracts
+//!
+//! - Linewise text is copied with a trailing newline
+//! - Pasted text that was not copied from the editor is linewise when it
+//!   ends in a newline, characterwise otherwise
+//! - With 'clipboard' "unnamed" yanks, deletes and puts that use the
+//!   unnamed register use `*` instead; with "unnamedplus" they use `+`
+
+use std::collections::HashMap;
+
+use crate::registers::{Register, RegisterContent, RegisterType};
+use crate::types::*;
+
+// ============================================================================
+// Clipboard Provider
+// ============================================================================
+
+/// Something that stores text for the `+` and `*` registers
+pub trait ClipboardProvider: std::fmt::Debug + Send {
+    /// Name of the provider, such as "xclip" or "OSC 52"
+    fn name(&self) -> &str;
+
+    /// Put `text` on the clipboard for `reg` (`+` or `*`)
+    fn copy(&mut self, reg: Register, text: &str) -> VimResult<()>;
+
+    /// The text on the clipboard for `reg`, `None` when it cannot be read
+    fn paste(&mut self, reg: Register) -> VimResult<Option<String>>;
+}
+
+// ============================================================================
+// Register Type Side Channel
+// ============================================================================
+
+/// Remembers the register type of text copied to the clipboard, so that
+/// pasting it back gives the same kind of register content
+#[derive(Debug, Clone, Default)]
+pub struct RegisterTypeChannel {
+    copied: HashMap<Register, (String, RegisterType)>,
+}
+
+impl RegisterTypeChannel {
+    /// Create an empty side channel
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The text to put on the clipboard for `content`, remembering its type
+    pub fn copy(&mut self, reg: Register, content: &RegisterContent) -> String {
+        let text = content.as_string();
+        self.copied.insert(reg, (text.clone(), content.reg_type));
+        text
+    }
+
+    /// Register content for `text` pasted from the clipboard of `reg`: the
+    /// copied type when `text` is what was copied, otherwise guessed.  Some
+    /// tools drop the trailing newline, the text is still what was copied.
+    pub fn paste(&self, reg: Register, text: &str) -> RegisterContent {
+        match self.copied.get(&reg) {
+            Some((copied, reg_type))
+                if copied == text || copied.strip_suffix('\n') == Some(text) =>
+            {
+                content_from_text(copied, *reg_type)
+            }
+            _ if text.ends_with('\n') => content_from_text(text, RegisterType::Linewise),
+            _ => content_from_text(text, RegisterType::Characterwise),
+        }
+    }
+}
+
+/// Register content of type `reg_type` for clipboard `text`
+pub fn content_from_text(text: &str, reg_type: RegisterType) -> RegisterContent {
+    match reg_type {
+        RegisterType::Characterwise => RegisterContent::characterwise(text),
+        RegisterType::Linewise => {
+            let text = text.strip_suffix('\n').unwrap_or(text);
+            RegisterContent::linewise(text.split('\n').map(str::to_string).collect())
+        }
+        RegisterType::Blockwise { width } => {
+            RegisterContent::blockwise(text.split('\n').map(str::to_string).collect(), width)
+        }
+    }
+}
+
+// ============================================================================
+// 'clipboard'
+// ============================================================================
+
+/// The 'clipboard' option
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ClipboardFlags {
+    /// "unnamed": use `*` for the unnamed register
+    pub unnamed: bool,
+    /// "unnamedplus": use `+` for the unnamed register
+    pub unnamedplus: bool,
+}
+
+impl ClipboardFlags {
+    /// Parse the comma-separated value of 'clipboard'.  Unknown items give
+    /// E474.  The items for the X11 selection, "autoselect",
+    /// "autoselectplus", "autoselectml", "html" and "exclude:{pattern}",
+    /// are accepted and ignored, as in Vim built without X11.
+    pub fn parse(value: &str) -> VimResult<Self> {
+        let mut flags = ClipboardFlags::default();
+        let mut rest = value;
+        // The pattern of "exclude:" is the rest of the value, commas too
+        while !rest.is_empty() && !rest.starts_with("exclude:") {
+            let (item, next) = rest.split_once(',').unwrap_or((rest, ""));
+            match item {
+                "unnamed" => flags.unnamed = true,
+                "unnamedplus" => flags.unnamedplus = true,
+                "" | "autoselect" | "autoselectplus" | "autoselectml" | "html" => {}
+                _ => {
+                    return Err(VimError::Error(
+                        474,
+                        format!("Invalid argument: clipboard={}", value),
+                    ))
+                }
+            }
+            rest = next;
+        }
+        Ok(flags)
+    }
+
+    /// The register to put from when no register is given
+    pub fn put_register(&self) -> Option<Register> {
+        match (self.unnamedplus, self.unnamed) {
+            (true, _) => Some(Register::Clipboard),
+            (false, true) => Some(Register::Selection),
+            (false, false) => None,
+        }
+    }
+
+    /// The registers a yank or delete without a register is copied to
+    pub fn store_registers(&self) -> Vec<Register> {
+        let mut regs = Vec::new();
+        if self.unnamedplus {
+            regs.push(Register::Clipboard);
+        }
+        if self.unnamed {
+            regs.push(Register::Selection);
+        }
+        regs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_side_channel_keeps_type() {
+        let mut channel = RegisterTypeChannel::new();
+        let block = RegisterContent::blockwise(vec!["ab".into(), "cd".into()], 2);
+        let text = channel.copy(Register::Clipboard, &block);
+        assert_eq!(text, "ab\ncd");
+        assert_eq!(channel.paste(Register::Clipboard, &text), block);
+        // The selection has its own copy
+        let pasted = channel.paste(Register::Selection, &text);
+        assert_eq!(pasted.reg_type, RegisterType::Characterwise);
+    }
+
+    #[test]
+    fn test_side_channel_guesses_type() {
+        let mut channel = RegisterTypeChannel::new();
+        channel.copy(Register::Clipboard, &RegisterContent::characterwise("x"));
+        let pasted = channel.paste(Register::Clipboard, "one\ntwo\n");
+        assert_eq!(
+            pasted,
+            RegisterContent::linewise(vec!["one".into(), "two".into()])
+        );
+        let pasted = channel.paste(Register::Clipboard, "word");
+        assert_eq!(pasted, RegisterContent::characterwise("word"));
+    }
+
+    #[test]
+    fn test_clipboard_flags() {
+        let flags = ClipboardFlags::parse("unnamed,unnamedplus").unwrap();
+        assert_eq!(flags.put_register(), Some(Register::Clipboard));
+        assert_eq!(
+            flags.store_registers(),
+            vec![Register::Clipboard, Register::Selection]
+        );
+        let flags = ClipboardFlags::parse("unnamed").unwrap();
+        assert_eq!(flags.put_register(), Some(Register::Selection));
+        assert_eq!(ClipboardFlags::parse("").unwrap().put_register(), None);
+        assert!(ClipboardFlags::parse("unnamed,bogus").is_err());
+    }
+
+    #[test]
+    fn test_clipboard_flags_x11_items() {
+        let flags = ClipboardFlags::parse("autoselect,unnamed,html,autoselectml").unwrap();
+        assert_eq!(flags.put_register(), Some(Register::Selection));
+        let flags = ClipboardFlags::parse("unnamedplus,exclude:cons\\|linux,x").unwrap();
+        assert_eq!(flags.put_register(), Some(Register::Clipboard));
+    }
+
+    #[test]
+    fn test_side_channel_stripped_newline() {
+        let mut channel = RegisterTypeChannel::new();
+        let line = RegisterContent::linewise(vec!["one".into()]);
+        assert_eq!(channel.copy(Register::Clipboard, &line), "one\n");
+        assert_eq!(channel.paste(Register::Clipboard, "one"), line);
+    }
+}
@@ -4,7 +4,7 @@
 //! to capture observable behavior from Neovim's command-line tests.
 
 use crate::registers::{RegisterContent, RegisterType};
-use crate::types::VimResult;
+use crate::types::{VimError, VimResult};
 
 /// Command-line history kind.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -22,11 +22,16 @@ pub enum CmdlineHistoryKind {
 }
 
 /// Command-line buffer operations.
+///
+/// The command-line has a cursor, a byte offset into the text.  Text is
+/// inserted at the cursor and the editing keys (`Ctrl-W`, `Ctrl-U`,
+/// `<BS>`, `<Del>`) delete around it.
 pub trait Cmdline {
     /// Get the current command-line text.
     fn getcmdline(&self) -> &str;
 
-    /// Replace the current command-line text.
+    /// Replace the current command-line text.  The cursor moves to the
+    /// end of the new text.
     fn setcmdline(&mut self, text: &str) -> VimResult<()>;
 
     /// Clear the command-line text.
@@ -34,7 +39,79 @@ pub trait Cmdline {
         self.setcmdline("")
     }
 
-    /// Paste register content into the command-line.
+    /// Cursor position as a 1-based byte index; one past the end of the
+    /// text when the cursor is at the end (`getcmdpos()`).
+    fn getcmdpos(&self) -> usize;
+
+    /// Move the cursor to the 1-based byte index `pos` (`setcmdpos()`).
+    /// A position past the end moves the cursor to the end.
+    fn setcmdpos(&mut self, pos: usize) -> VimResult<()>;
+
+    /// Insert `text` at the cursor and move the cursor after it.
+    fn insert_cmdline(&mut self, text: &str) -> VimResult<()> {
+        let pos = self.getcmdpos() - 1;
+        let mut next = String::from(self.getcmdline());
+        next.insert_str(pos, text);
+        self.setcmdline(&next)?;
+        self.setcmdpos(pos + text.len() + 1)
+    }
+
+    /// Delete the text between byte offsets `start` and the cursor.
+    fn delete_cmdline_before(&mut self, start: usize) -> VimResult<()> {
+        let pos = self.getcmdpos() - 1;
+        let mut next = String::from(self.getcmdline());
+        next.replace_range(start..pos, "");
+        self.setcmdline(&next)?;
+        self.setcmdpos(start + 1)
+    }
+
+    /// Delete the character before the cursor (`<BS>`).  Returns false
+    /// when the cursor is at the start.
+    fn delete_char_before(&mut self) -> VimResult<bool> {
+        let pos = self.getcmdpos() - 1;
+        let Some(ch) = self.getcmdline()[..pos].chars().next_back() else {
+            return Ok(false);
+        };
+        self.delete_cmdline_before(pos - ch.len_utf8())?;
+        Ok(true)
+    }
+
+    /// Delete the character under the cursor (`<Del>`).  At the end of the
+    /// text the character before the cursor is deleted instead.
+    fn delete_char_under(&mut self) -> VimResult<()> {
+        let pos = self.getcmdpos() - 1;
+        let Some(ch) = self.getcmdline()[pos..].chars().next() else {
+            self.delete_char_before()?;
+            return Ok(());
+        };
+        let mut next = String::from(self.getcmdline());
+        next.replace_range(pos..pos + ch.len_utf8(), "");
+        self.setcmdline(&next)?;
+        self.setcmdpos(pos + 1)
+    }
+
+    /// Delete the word before the cursor (`Ctrl-W`): blanks first, then
+    /// either keyword characters or other non-blank characters.
+    fn delete_word_before(&mut self) -> VimResult<()> {
+        let pos = self.getcmdpos() - 1;
+        let before = &self.getcmdline()[..pos];
+        let trimmed = before.trim_end();
+        let keyword = trimmed.chars().next_back().is_some_and(is_word_char);
+        let start = trimmed
+            .char_indices()
+            .rev()
+            .take_while(|&(_, c)| !c.is_whitespace() && is_word_char(c) == keyword)
+            .last()
+            .map_or(trimmed.len(), |(idx, _)| idx);
+        self.delete_cmdline_before(start)
+    }
+
+    /// Delete all text before the cursor (`Ctrl-U`).
+    fn delete_line_before(&mut self) -> VimResult<()> {
+        self.delete_cmdline_before(0)
+    }
+
+    /// Paste register content into the command-line at the cursor.
     ///
     /// If `special` is true, the content is inserted verbatim (preserving
     /// both `\n` and `\r`). Otherwise, lines are joined with `\r`.
@@ -49,12 +126,15 @@ pub trait Cmdline {
             }
         };
 
-        let mut next = String::from(self.getcmdline());
-        next.push_str(&insert);
-        self.setcmdline(&next)
+        self.insert_cmdline(&insert)
     }
 }
 
+/// Characters `Ctrl-W` treats as part of a word (default 'iskeyword')
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
 /// Command-line history operations.
 pub trait CmdlineHistory {
     /// Get the history limit.
@@ -76,6 +156,76 @@ pub trait CmdlineHistory {
     fn hist_get(&self, kind: CmdlineHistoryKind, index: i64) -> String;
 }
 
+// ============================================================================
+// Command-line Completion
+// ============================================================================
+
+/// One part of 'wildmode': what one press of 'wildchar' does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct WildMode {
+    /// `full`: complete the next match; with `longest`, only start the
+    /// wildmenu
+    pub full: bool,
+    /// `longest`: complete the longest common prefix of the matches
+    pub longest: bool,
+    /// `list`: list the matches
+    pub list: bool,
+    /// `lastused`: sort buffer names by the time they were last used
+    pub lastused: bool,
+    /// `noselect`: show the matches without selecting one
+    pub noselect: bool,
+}
+
+impl WildMode {
+    /// Parse 'wildmode': up to four comma-separated parts, each a
+    /// colon-separated list of `full`, `longest`, `list`, `lastused` and
+    /// `noselect`.  Each press of 'wildchar' uses the next part; the last
+    /// part is used for any further presses.
+    pub fn parse_list(value: &str) -> VimResult<Vec<WildMode>> {
+        let invalid = || VimError::Error(474, format!("Invalid argument: wildmode={}", value));
+        let mut parts = Vec::new();
+        for part in value.split(',') {
+            let mut mode = WildMode::default();
+            for item in part.split(':') {
+                let flag = match item {
+                    "full" => &mut mode.full,
+                    "longest" => &mut mode.longest,
+                    "list" => &mut mode.list,
+                    "lastused" => &mut mode.lastused,
+                    "noselect" => &mut mode.noselect,
+                    _ => return Err(invalid()),
+                };
+                *flag = true;
+            }
+            parts.push(mode);
+        }
+        if parts.len() > 4 {
+            return Err(invalid());
+        }
+        Ok(parts)
+    }
+}
+
+/// The longest prefix shared by all `matches`, taken from the first one.
+/// With `ignore_case` characters that only differ in case are shared.
+pub fn longest_common_prefix(matches: &[String], ignore_case: bool) -> String {
+    let Some(first) = matches.first() else {
+        return String::new();
+    };
+    let same = |a: char, b: char| a == b || (ignore_case && a.to_lowercase().eq(b.to_lowercase()));
+    let mut len = first.len();
+    for other in &matches[1..] {
+        let shared = first
+            .char_indices()
+            .zip(other.chars())
+            .take_while(|&((_, a), b)| same(a, b))
+            .last()
+            .map_or(0, |((idx, a), _)| idx + a.len_utf8());
+        len = len.min(shared);
+    }
+    first[..len].to_string()
+}
+
 // ============================================================================
 // Behavioral Tests (portable)
 // ============================================================================
@@ -119,6 +269,36 @@ pub mod behavior {
             self.paste_register(&content, true).unwrap();
             assert_eq!(self.getcmdline(), "foo\nbar\rbaz");
         }
+
+        /// Text is inserted and deleted at the cursor.
+        fn test_editing_at_cursor(&mut self) {
+            self.setcmdline("echo foo.bar").unwrap();
+            assert_eq!(self.getcmdpos(), 13);
+
+            self.setcmdpos(6).unwrap();
+            self.insert_cmdline("'x'  ").unwrap();
+            assert_eq!(self.getcmdline(), "echo 'x'  foo.bar");
+            assert_eq!(self.getcmdpos(), 11);
+
+            // Ctrl-W removes the blanks, then the non-keyword characters
+            self.delete_word_before().unwrap();
+            assert_eq!(self.getcmdline(), "echo 'xfoo.bar");
+            self.delete_word_before().unwrap();
+            assert_eq!(self.getcmdline(), "echo 'foo.bar");
+
+            self.setcmdpos(100).unwrap();
+            assert_eq!(self.getcmdpos(), 14);
+            self.delete_word_before().unwrap();
+            assert_eq!(self.getcmdline(), "echo 'foo.");
+
+            self.setcmdpos(6).unwrap();
+            self.delete_line_before().unwrap();
+            assert_eq!(self.getcmdline(), "'foo.");
+            assert_eq!(self.getcmdpos(), 1);
+            assert!(!self.delete_char_before().unwrap());
+            self.delete_char_under().unwrap();
+            assert_eq!(self.getcmdline(), "foo.");
+        }
     }
 
     /// Behavioral tests for cmdline history.
@@ -150,3 +330,32 @@ pub mod behavior {
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_wildmode() {
+        let parts = WildMode::parse_list("longest:full,full").unwrap();
+        assert_eq!(parts.len(), 2);
+        assert!(parts[0].longest && parts[0].full && !parts[0].list);
+        assert!(parts[1].full && !parts[1].longest);
+        assert!(WildMode::parse_list("list:lastused").unwrap()[0].lastused);
+        assert!(matches!(
+            WildMode::parse_list("full,bogus"),
+            Err(VimError::Error(474, _))
+        ));
+        assert!(WildMode::parse_list("full,full,full,full,full").is_err());
+    }
+
+    #[test]
+    fn test_longest_common_prefix() {
+        let words = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(longest_common_prefix(&words(&["foldopen", "foldclose"]), false), "fold");
+        assert_eq!(longest_common_prefix(&words(&["Makefile", "main.rs"]), false), "");
+        assert_eq!(longest_common_prefix(&words(&["Makefile", "main.rs"]), true), "Ma");
+        assert_eq!(longest_common_prefix(&words(&["set", "setlocal"]), false), "set");
+        assert_eq!(longest_common_prefix(&[], false), "");
+    }
+}
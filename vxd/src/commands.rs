@@ -233,6 +233,89 @@ pub struct CommandFlags {
     pub modify: bool,
 }
 
+/// What the arguments of a command complete to, as given with
+/// `-complete={type}` (`:help :command-complete`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum CompleteType {
+    /// No completion
+    #[default]
+    Nothing,
+    /// File and directory names
+    File,
+    /// Directory names
+    Dir,
+    /// Buffer names
+    Buffer,
+    /// Option names, and values after `=`
+    Option,
+    /// Ex command names
+    Command,
+    /// Autocommand event names
+    Event,
+    /// Autocommand group names
+    Augroup,
+    /// Highlight group names
+    Highlight,
+    /// Mapping left-hand sides
+    Mapping,
+    /// Help tags
+    Help,
+    /// User function names
+    Function,
+}
+
+impl CompleteType {
+    /// Every completion type, in `:help :command-complete` order
+    pub const ALL: &'static [CompleteType] = &[
+        CompleteType::Augroup,
+        CompleteType::Buffer,
+        CompleteType::Command,
+        CompleteType::Dir,
+        CompleteType::Event,
+        CompleteType::File,
+        CompleteType::Function,
+        CompleteType::Help,
+        CompleteType::Highlight,
+        CompleteType::Mapping,
+        CompleteType::Option,
+    ];
+
+    /// The name used with `-complete=` and `getcompletion()`
+    pub fn name(&self) -> &'static str {
+        match self {
+            CompleteType::Nothing => "",
+            CompleteType::File => "file",
+            CompleteType::Dir => "dir",
+            CompleteType::Buffer => "buffer",
+            CompleteType::Option => "option",
+            CompleteType::Command => "command",
+            CompleteType::Event => "event",
+            CompleteType::Augroup => "augroup",
+            CompleteType::Highlight => "highlight",
+            CompleteType::Mapping => "mapping",
+            CompleteType::Help => "help",
+            CompleteType::Function => "function",
+        }
+    }
+
+    /// Parse a `-complete=` name
+    pub fn from_name(name: &str) -> Option<Self> {
+        CompleteType::ALL
+            .iter()
+            .copied()
+            .find(|kind| kind.name() == name)
+    }
+
+    /// Whether matches are file names, which have spaces escaped when
+    /// inserted
+    pub fn is_file_name(&self) -> bool {
+        matches!(
+            self,
+            CompleteType::File | CompleteType::Dir | CompleteType::Buffer
+        )
+    }
+}
+
 /// Definition of an ex command
 #[derive(Debug, Clone)]
 pub struct CommandDef {
@@ -242,6 +325,8 @@ pub struct CommandDef {
     pub min_abbrev: usize,
     /// Command flags
     pub flags: CommandFlags,
+    /// What the arguments complete to
+    pub complete: CompleteType,
     /// Help description
     pub description: String,
 }
@@ -272,7 +357,8 @@ pub trait CommandExecutor {
     /// Complete command name
     fn complete_command(&self, prefix: &str) -> Vec<String>;
 
-    /// Complete command arguments
+    /// Complete the last argument of `args` for the command `cmd`, using
+    /// the command's [`CompleteType`]
     fn complete_args(&self, cmd: &str, args: &str) -> Vec<String>;
 }
 
@@ -284,6 +370,16 @@ pub trait CommandExecutor {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_complete_type_names() {
+        for kind in CompleteType::ALL {
+            assert_eq!(CompleteType::from_name(kind.name()), Some(*kind));
+        }
+        assert_eq!(CompleteType::from_name("bogus"), None);
+        assert!(CompleteType::Dir.is_file_name());
+        assert!(!CompleteType::Option.is_file_name());
+    }
+
     #[test]
     fn test_command_range() {
         let whole = CommandRange::whole_file();
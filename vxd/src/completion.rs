@@ -103,6 +103,220 @@ pub struct CompletionState {
     pub kind: Option<CompletionKind>,
 }
 
+// ============================================================================
+// Completion Options
+// ============================================================================
+
+/// Flags of the 'completeopt' option
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CompleteOpt {
+    /// Show a popup menu when there is more than one match
+    pub menu: bool,
+    /// Also show the popup menu when there is only one match
+    pub menuone: bool,
+    /// Do not insert the text of a match until one is selected
+    pub noinsert: bool,
+    /// Do not select a match in the menu automatically
+    pub noselect: bool,
+    /// Only insert the longest common text of the matches
+    pub longest: bool,
+    /// Show extra information about the selected match
+    pub preview: bool,
+    /// Show extra information in a popup next to the menu
+    pub popup: bool,
+}
+
+impl CompleteOpt {
+    /// Parse a 'completeopt' value such as `"menu,preview"`.
+    /// Unknown items are ignored.
+    pub fn parse(value: &str) -> Self {
+        let mut opt = CompleteOpt::default();
+        for item in value.split(',') {
+            match item.trim() {
+                "menu" => opt.menu = true,
+                "menuone" => opt.menuone = true,
+                "noinsert" => opt.noinsert = true,
+                "noselect" => opt.noselect = true,
+                "longest" => opt.longest = true,
+                "preview" => opt.preview = true,
+                "popup" => opt.popup = true,
+                _ => {}
+            }
+        }
+        opt
+    }
+
+    /// Whether the popup menu is shown for `count` matches
+    pub fn shows_menu(&self, count: usize) -> bool {
+        (self.menuone && count >= 1) || (self.menu && count >= 2)
+    }
+}
+
+/// A place scanned by keyword completion, from the 'complete' option
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompleteSource {
+    /// `.` - the current buffer
+    CurrentBuffer,
+    /// `w` - buffers in other windows
+    Windows,
+    /// `b` - other loaded buffers in the buffer list
+    LoadedBuffers,
+    /// `u` - unloaded buffers in the buffer list
+    UnloadedBuffers,
+    /// `t` or `]` - tags
+    Tags,
+    /// `i` - included files
+    Includes,
+    /// `k` - the 'dictionary' files, or the given file
+    Dictionary(Option<String>),
+    /// `kspell` - the spell-checking word list
+    Spell,
+    /// `s` - the 'thesaurus' files, or the given file
+    Thesaurus(Option<String>),
+}
+
+impl CompleteSource {
+    /// Parse a 'complete' value such as `".,w,b,u,t,i"`.
+    /// Unknown items are ignored.
+    pub fn parse_list(value: &str) -> Vec<CompleteSource> {
+        let file = |rest: &str| {
+            let rest = rest.trim();
+            (!rest.is_empty()).then(|| rest.to_string())
+        };
+        value
+            .split(',')
+            .map(str::trim)
+            .filter_map(|item| match item {
+                "." => Some(CompleteSource::CurrentBuffer),
+                "w" => Some(CompleteSource::Windows),
+                "b" => Some(CompleteSource::LoadedBuffers),
+                "u" => Some(CompleteSource::UnloadedBuffers),
+                "t" | "]" => Some(CompleteSource::Tags),
+                "i" => Some(CompleteSource::Includes),
+                "kspell" => Some(CompleteSource::Spell),
+                _ if item.starts_with('k') => Some(CompleteSource::Dictionary(file(&item[1..]))),
+                _ if item.starts_with('s') => Some(CompleteSource::Thesaurus(file(&item[1..]))),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+// ============================================================================
+// Match Collection
+// ============================================================================
+
+/// Whether `c` is a keyword character (the default 'iskeyword')
+pub fn is_keyword_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Byte column where the keyword ending at `col` starts
+pub fn keyword_start(line: &str, col: usize) -> usize {
+    let mut col = col.min(line.len());
+    while !line.is_char_boundary(col) {
+        col -= 1;
+    }
+    line[..col]
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| is_keyword_char(*c))
+        .last()
+        .map_or(col, |(idx, _)| idx)
+}
+
+/// Longest text all `items` start with
+pub fn longest_common_prefix(items: &[CompletionItem]) -> String {
+    let Some(first) = items.first() else {
+        return String::new();
+    };
+    let mut len = first.word.len();
+    for item in &items[1..] {
+        len = first
+            .word
+            .char_indices()
+            .zip(item.word.chars())
+            .take_while(|((idx, a), b)| *idx < len && a == b)
+            .last()
+            .map_or(0, |((idx, a), _)| idx + a.len_utf8());
+    }
+    first.word[..len].to_string()
+}
+
+/// Collects unique matches for the text typed before the cursor
+#[derive(Debug, Clone, Default)]
+pub struct MatchCollector {
+    base: String,
+    ignore_case: bool,
+    seen: std::collections::HashSet<String>,
+    items: Vec<CompletionItem>,
+}
+
+impl MatchCollector {
+    /// Collect matches starting with `base`
+    pub fn new(base: impl Into<String>, ignore_case: bool) -> Self {
+        MatchCollector {
+            base: base.into(),
+            ignore_case,
+            ..Default::default()
+        }
+    }
+
+    /// The text matches must start with
+    pub fn base(&self) -> &str {
+        &self.base
+    }
+
+    /// Whether `word` starts with the base text
+    pub fn matches(&self, word: &str) -> bool {
+        if self.ignore_case {
+            word.to_lowercase().starts_with(&self.base.to_lowercase())
+        } else {
+            word.starts_with(&self.base)
+        }
+    }
+
+    /// Add `item` if it matches and is not a duplicate; the typed base
+    /// itself is never a match.  Returns whether it was added.
+    pub fn add(&mut self, item: CompletionItem) -> bool {
+        if item.word == self.base || !self.matches(&item.word) {
+            return false;
+        }
+        if !item.dup && !self.seen.insert(item.word.clone()) {
+            return false;
+        }
+        self.items.push(item);
+        true
+    }
+
+    /// Add every matching keyword of `line`, tagging it with `menu`
+    pub fn add_keywords(&mut self, line: &str, menu: Option<&str>) {
+        for word in line.split(|c: char| !is_keyword_char(c)) {
+            if word.is_empty() {
+                continue;
+            }
+            let mut item = CompletionItem::new(word);
+            item.menu = menu.map(str::to_string);
+            self.add(item);
+        }
+    }
+
+    /// Number of matches collected so far
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Whether no match was collected
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// The collected matches, in the order they were found
+    pub fn into_items(self) -> Vec<CompletionItem> {
+        self.items
+    }
+}
+
 // ============================================================================
 // Completion Engine Trait
 // ============================================================================
@@ -410,4 +624,54 @@ mod tests {
         //!
         //! 4. **Completeopt**: Controls how completion menu behaves.
     }
+
+    #[test]
+    fn test_completeopt_parse() {
+        let opt = CompleteOpt::parse("menuone,noselect,preview");
+        assert!(opt.menuone && opt.noselect && opt.preview);
+        assert!(!opt.menu && !opt.longest && !opt.noinsert);
+        assert!(opt.shows_menu(1));
+        assert!(!CompleteOpt::parse("menu").shows_menu(1));
+        assert!(CompleteOpt::parse("menu").shows_menu(2));
+    }
+
+    #[test]
+    fn test_complete_sources_parse() {
+        assert_eq!(
+            CompleteSource::parse_list(".,w,b,u,t,i"),
+            vec![
+                CompleteSource::CurrentBuffer,
+                CompleteSource::Windows,
+                CompleteSource::LoadedBuffers,
+                CompleteSource::UnloadedBuffers,
+                CompleteSource::Tags,
+                CompleteSource::Includes,
+            ]
+        );
+        assert_eq!(
+            CompleteSource::parse_list("k/usr/share/dict/words,kspell,s"),
+            vec![
+                CompleteSource::Dictionary(Some("/usr/share/dict/words".to_string())),
+                CompleteSource::Spell,
+                CompleteSource::Thesaurus(None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_match_collector_and_longest() {
+        assert_eq!(keyword_start("let foo_bar", 11), 4);
+        assert_eq!(keyword_start("a.b", 2), 2);
+
+        let mut collector = MatchCollector::new("fo", false);
+        collector.add_keywords("fo foo fob foo Foo", None);
+        let items = collector.into_items();
+        let words: Vec<&str> = items.iter().map(|item| item.word.as_str()).collect();
+        assert_eq!(words, vec!["foo", "fob"]);
+        assert_eq!(longest_common_prefix(&items), "fo");
+
+        let mut collector = MatchCollector::new("fo", true);
+        collector.add_keywords("Football football footnote", None);
+        assert_eq!(collector.len(), 3);
+    }
 }
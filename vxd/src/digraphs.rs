@@ -1,43 +1,660 @@
 //! Digraph system.
 //!
 //! Digraphs are two-character sequences that insert a single character.
+//! They are typed after `Ctrl-K` in Insert and Command-line mode, or as
+//! `{char1}<BS>{char2}` in Insert mode with the 'digraph' option set.
+//! The default table is the RFC1345 one Vim ships; `:digraphs` lists it
+//! and `:digraphs {char1}{char2} {number}` defines more.
+//!
+//! # Key Behavioral Contracts
+//!
+//! - A digraph that is not found is looked up with its characters
+//!   swapped, so `e a` gives `æ` as `a e` does
+//! - User digraphs are found before the default ones
+//! - Without a digraph `Ctrl-K {char1}{char2}` inserts `char2`
+//! - `ga` shows the digraph of the character under the cursor
+
+pub use crate::display::is_composing;
+use crate::types::{VimError, VimResult};
+
+/// The width of an entry in the listing of `:digraphs`
+const LIST_WIDTH: usize = 13;
 
-use std::collections::HashMap;
+/// The headers of the listing of `:digraphs!`, with the first character
+/// of their group
+const HEADERS: &[(u32, &str)] = &[
+    (0xa1, "Latin supplement"),
+    (0x0386, "Greek and Coptic"),
+    (0x0401, "Cyrillic"),
+    (0x05d0, "Hebrew"),
+    (0x060c, "Arabic"),
+    (0x1e02, "Latin extended"),
+    (0x1f00, "Greek extended"),
+    (0x2002, "Punctuation"),
+    (0x2070, "Super- and subscripts"),
+    (0x20a4, "Currency"),
+    (0x2103, "Other"),
+    (0x2160, "Roman numbers"),
+    (0x2190, "Arrows"),
+    (0x2200, "Mathematical operators"),
+    (0x2302, "Technical"),
+    (0x2440, "Other"),
+    (0x2500, "Box drawing"),
+    (0x2580, "Block elements"),
+    (0x25a0, "Geometric shapes"),
+    (0x2600, "Symbols"),
+    (0x2700, "Dingbats"),
+    (0x3000, "CJK symbols and punctuation"),
+    (0x3041, "Hiragana"),
+    (0x30a1, "Katakana"),
+    (0x3105, "Bopomofo"),
+    (0x3220, "Other"),
+];
 
 /// Digraph table with built-in defaults plus user-defined entries.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct DigraphTable {
-    map: HashMap<(char, char), char>,
+    user: Vec<(char, char, char)>,
 }
 
 impl DigraphTable {
-    /// Create a new digraph table with a small built-in set.
+    /// Create a new digraph table with the default digraphs.
     pub fn new() -> Self {
-        let mut map = HashMap::new();
-        map.insert(('a', 'e'), '\u{00E6}'); // ae -> æ
-        map.insert(('A', 'E'), '\u{00C6}'); // AE -> Æ
-        map.insert(('o', 's'), '\u{00F8}'); // os -> ø
-        map.insert(('O', 'S'), '\u{00D8}'); // OS -> Ø
-        DigraphTable { map }
+        DigraphTable { user: Vec::new() }
     }
 
-    /// Look up a digraph by its two-character sequence.
+    /// Look up a digraph by its two-character sequence, in either order.
     pub fn lookup(&self, first: char, second: char) -> Option<char> {
-        self.map.get(&(first, second)).copied()
+        self.lookup_exact(first, second)
+            .or_else(|| self.lookup_exact(second, first))
+    }
+
+    /// Look up a digraph by its two-character sequence, in this order.
+    pub fn lookup_exact(&self, first: char, second: char) -> Option<char> {
+        let user = self
+            .user
+            .iter()
+            .find(|&&(c1, c2, _)| (c1, c2) == (first, second))
+            .map(|&(_, _, output)| output);
+        user.or_else(|| {
+            DEFAULT_DIGRAPHS
+                .iter()
+                .find(|(chars, _)| is_pair(chars, first, second))
+                .map(|&(_, output)| output)
+        })
+    }
+
+    /// The character `Ctrl-K {first}{second}` inserts: the digraph, or
+    /// `second` when there is none.
+    pub fn resolve(&self, first: char, second: char) -> char {
+        self.lookup(first, second).unwrap_or(second)
     }
 
     /// Add or override a digraph mapping.
     pub fn insert(&mut self, first: char, second: char, output: char) {
-        self.map.insert((first, second), output);
+        match self
+            .user
+            .iter_mut()
+            .find(|(c1, c2, _)| (*c1, *c2) == (first, second))
+        {
+            Some(entry) => entry.2 = output,
+            None => self.user.push((first, second, output)),
+        }
+    }
+
+    /// The two characters of the first digraph for `ch`, user digraphs
+    /// first.
+    pub fn digraph_for(&self, ch: char) -> Option<(char, char)> {
+        let user = self
+            .user
+            .iter()
+            .find(|&&(_, _, output)| output == ch)
+            .map(|&(c1, c2, _)| (c1, c2));
+        user.or_else(|| {
+            DEFAULT_DIGRAPHS
+                .iter()
+                .find(|&&(_, output)| output == ch)
+                .and_then(|(chars, _)| pair(chars))
+        })
+    }
+
+    /// Define the digraphs `args` of `:digraphs {char1}{char2} {number}
+    /// ..`.  The ones before an error are defined.
+    pub fn define(&mut self, args: &str) -> VimResult<()> {
+        let mut rest = args.trim_start();
+        while !rest.is_empty() {
+            let mut chars = rest.chars();
+            let (Some(first), Some(second)) = (chars.next(), chars.next()) else {
+                return Err(VimError::Error(
+                    1214,
+                    format!("Digraph must be just two characters: {}", rest),
+                ));
+            };
+            if first == '\x1b' || second == '\x1b' {
+                return Err(VimError::Error(
+                    104,
+                    "Escape not allowed in digraph".to_string(),
+                ));
+            }
+            let after = chars.as_str().trim_start();
+            let digits = after
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(after.len());
+            let output = after[..digits]
+                .parse()
+                .ok()
+                .and_then(char::from_u32)
+                .ok_or_else(|| VimError::Error(39, "Number expected".to_string()))?;
+            self.insert(first, second, output);
+            rest = after[digits..].trim_start();
+        }
+        Ok(())
+    }
+
+    /// The listing of `:digraphs`, for a screen `columns` wide.  With
+    /// `headers`, for `:digraphs!`, the groups have a header.
+    pub fn listing(&self, headers: bool, columns: usize) -> String {
+        let mut out = String::new();
+        let mut col = 0;
+        let mut previous = 0;
+        for &(chars, output) in DEFAULT_DIGRAPHS {
+            let Some((first, second)) = pair(chars) else {
+                continue;
+            };
+            // A default digraph a user digraph overrides is not listed
+            if self.lookup_exact(first, second) != Some(output) {
+                continue;
+            }
+            let code = output as u32;
+            if headers {
+                let header = HEADERS.iter().enumerate().find(|&(idx, &(start, _))| {
+                    let next = HEADERS.get(idx + 1).map_or(u32::MAX, |&(next, _)| next);
+                    previous < start && code >= start && code < next
+                });
+                if let Some((_, &(_, name))) = header {
+                    start_line(&mut out, &mut col);
+                    out.push_str(name);
+                    out.push('\n');
+                }
+            }
+            previous = code;
+            list_entry(&mut out, &mut col, columns, first, second, output);
+        }
+        if headers && !self.user.is_empty() {
+            start_line(&mut out, &mut col);
+            out.push_str("Custom\n");
+        }
+        for &(first, second, output) in &self.user {
+            list_entry(&mut out, &mut col, columns, first, second, output);
+        }
+        out.trim_end_matches('\n').to_string()
+    }
+
+    /// The text `ga` shows for the character `text` under the cursor,
+    /// with its composing characters: the character in decimal, hex and
+    /// octal, and its digraph.  `NUL` for an empty line.
+    pub fn describe_char(&self, text: &str) -> String {
+        let mut chars = text.chars();
+        let Some(ch) = chars.next() else {
+            return "NUL".to_string();
+        };
+        let mut out = String::new();
+        let code = ch as u32;
+        if code < 0x80 {
+            out.push_str(&format!(
+                "<{}>  {},  Hex {:02x},  ",
+                transchar(ch),
+                code,
+                code
+            ));
+            match self.digraph_for(ch) {
+                Some((c1, c2)) => out.push_str(&format!("Oct {:03o}, Digr {}{}", code, c1, c2)),
+                None => out.push_str(&format!("Octal {:03o}", code)),
+            }
+        }
+        let wide = std::iter::once(ch).filter(|_| code >= 0x80);
+        for ch in wide.chain(chars) {
+            let code = ch as u32;
+            if !out.is_empty() {
+                out.push(' ');
+            }
+            out.push('<');
+            if is_composing(ch) {
+                out.push(' ');
+            }
+            out.push(ch);
+            let hex = if code < 0x10000 {
+                format!("{:04x}", code)
+            } else {
+                format!("{:08x}", code)
+            };
+            match self.digraph_for(ch) {
+                Some((c1, c2)) => out.push_str(&format!(
+                    "> {}, Hex {}, Oct {:o}, Digr {}{}",
+                    code, hex, code, c1, c2
+                )),
+                None => out.push_str(&format!("> {}, Hex {}, Octal {:o}", code, hex, code)),
+            }
+        }
+        out
+    }
+}
+
+/// The text `g8` shows for the character `text` under the cursor, with
+/// its composing characters: the hex value of the bytes of each
+/// character.  `NUL` for an empty line.
+pub fn utf8_bytes(text: &str) -> String {
+    if text.is_empty() {
+        return "NUL".to_string();
+    }
+    let mut out = String::new();
+    for (idx, ch) in text.chars().enumerate() {
+        if idx > 0 {
+            out.push_str("+ ");
+        }
+        let mut buf = [0; 4];
+        for byte in ch.encode_utf8(&mut buf).bytes() {
+            out.push_str(&format!("{:02x} ", byte));
+        }
+    }
+    out
+}
+
+/// The two characters of a digraph in the default table
+fn pair(chars: &str) -> Option<(char, char)> {
+    let mut iter = chars.chars();
+    Some((iter.next()?, iter.next()?))
+}
+
+fn is_pair(chars: &str, first: char, second: char) -> bool {
+    pair(chars) == Some((first, second))
+}
+
+/// Start a new line of the listing, unless at the start of one
+fn start_line(out: &mut String, col: &mut usize) {
+    if *col > 0 {
+        out.push('\n');
+        *col = 0;
+    }
+}
+
+/// Add a digraph to the listing, in a column of [`LIST_WIDTH`] cells
+fn list_entry(
+    out: &mut String,
+    col: &mut usize,
+    columns: usize,
+    first: char,
+    second: char,
+    output: char,
+) {
+    if *col + LIST_WIDTH > columns {
+        start_line(out, col);
+    }
+    while !(*col).is_multiple_of(LIST_WIDTH) {
+        out.push(' ');
+        *col += 1;
+    }
+    let mut entry = format!("{}{} ", transchar(first), transchar(second));
+    if is_composing(output) {
+        entry.push(' ');
+    }
+    entry.push_str(&transchar(output));
+    let cells = char_cells(output);
+    if cells == 1 {
+        entry.push(' ');
     }
+    entry.push_str(&format!(" {:3}", output as u32));
+    *col += entry.chars().count() - transchar(output).chars().count() + cells;
+    out.push_str(&entry);
 }
 
-impl Default for DigraphTable {
-    fn default() -> Self {
-        Self::new()
+/// How a character is shown: `^X` for a control character, `<80>` for
+/// the ones from 0x80 to 0x9f
+fn transchar(ch: char) -> String {
+    match ch as u32 {
+        code @ 0..=0x1f => format!("^{}", char::from(code as u8 + b'@')),
+        0x7f => "^?".to_string(),
+        code @ 0x80..=0x9f => format!("<{:x}>", code),
+        _ => ch.to_string(),
     }
 }
 
+/// The number of screen cells `ch` takes, as shown by [`transchar`]
+fn char_cells(ch: char) -> usize {
+    match ch as u32 {
+        0..=0x1f | 0x7f => 2,
+        0x80..=0x9f => 4,
+        0x1100..=0x115f
+        | 0x2329..=0x232a
+        | 0x2e80..=0x303e
+        | 0x3041..=0x33ff
+        | 0x3400..=0x4dbf
+        | 0x4e00..=0x9fff
+        | 0xa000..=0xa4cf
+        | 0xac00..=0xd7a3
+        | 0xf900..=0xfaff
+        | 0xfe30..=0xfe4f
+        | 0xff00..=0xff60
+        | 0xffe0..=0xffe6
+        | 0x1f300..=0x1f64f
+        | 0x20000..=0x3fffd => 2,
+        _ => 1,
+    }
+}
+
+/// The default digraphs of RFC1345, in the order `:digraphs` lists them
+#[rustfmt::skip]
+pub const DEFAULT_DIGRAPHS: &[(&str, char)] = &[
+    ("NU", '\u{0}'), ("SH", '\u{1}'), ("SX", '\u{2}'), ("EX", '\u{3}'), ("ET", '\u{4}'),
+    ("EQ", '\u{5}'), ("AK", '\u{6}'), ("BL", '\u{7}'), ("BS", '\u{8}'), ("HT", '\u{9}'),
+    ("LF", '\u{a}'), ("VT", '\u{b}'), ("FF", '\u{c}'), ("CR", '\u{d}'), ("SO", '\u{e}'),
+    ("SI", '\u{f}'), ("DL", '\u{10}'), ("D1", '\u{11}'), ("D2", '\u{12}'), ("D3", '\u{13}'),
+    ("D4", '\u{14}'), ("NK", '\u{15}'), ("SY", '\u{16}'), ("EB", '\u{17}'), ("CN", '\u{18}'),
+    ("EM", '\u{19}'), ("SB", '\u{1a}'), ("EC", '\u{1b}'), ("FS", '\u{1c}'), ("GS", '\u{1d}'),
+    ("RS", '\u{1e}'), ("US", '\u{1f}'), ("SP", '\u{20}'), ("Nb", '\u{23}'), ("DO", '\u{24}'),
+    ("At", '\u{40}'), ("<(", '\u{5b}'), ("//", '\u{5c}'), (")>", '\u{5d}'), ("'>", '\u{5e}'),
+    ("'!", '\u{60}'), ("(!", '\u{7b}'), ("!!", '\u{7c}'), ("!)", '\u{7d}'), ("'?", '\u{7e}'),
+    ("DT", '\u{7f}'), ("PA", '\u{80}'), ("HO", '\u{81}'), ("BH", '\u{82}'), ("NH", '\u{83}'),
+    ("IN", '\u{84}'), ("NL", '\u{85}'), ("SA", '\u{86}'), ("ES", '\u{87}'), ("HS", '\u{88}'),
+    ("HJ", '\u{89}'), ("VS", '\u{8a}'), ("PD", '\u{8b}'), ("PU", '\u{8c}'), ("RI", '\u{8d}'),
+    ("S2", '\u{8e}'), ("S3", '\u{8f}'), ("DC", '\u{90}'), ("P1", '\u{91}'), ("P2", '\u{92}'),
+    ("TS", '\u{93}'), ("CC", '\u{94}'), ("MW", '\u{95}'), ("SG", '\u{96}'), ("EG", '\u{97}'),
+    ("SS", '\u{98}'), ("GC", '\u{99}'), ("SC", '\u{9a}'), ("CI", '\u{9b}'), ("ST", '\u{9c}'),
+    ("OC", '\u{9d}'), ("PM", '\u{9e}'), ("AC", '\u{9f}'), ("NS", '\u{a0}'), ("!I", '\u{a1}'),
+    ("~!", '\u{a1}'), ("Ct", '\u{a2}'), ("c|", '\u{a2}'), ("Pd", '\u{a3}'), ("$$", '\u{a3}'),
+    ("Cu", '\u{a4}'), ("ox", '\u{a4}'), ("Ye", '\u{a5}'), ("Y-", '\u{a5}'), ("BB", '\u{a6}'),
+    ("||", '\u{a6}'), ("SE", '\u{a7}'), ("':", '\u{a8}'), ("Co", '\u{a9}'), ("cO", '\u{a9}'),
+    ("-a", '\u{aa}'), ("<<", '\u{ab}'), ("NO", '\u{ac}'), ("-,", '\u{ac}'), ("--", '\u{ad}'),
+    ("Rg", '\u{ae}'), ("'m", '\u{af}'), ("-=", '\u{af}'), ("DG", '\u{b0}'), ("~o", '\u{b0}'),
+    ("+-", '\u{b1}'), ("2S", '\u{b2}'), ("22", '\u{b2}'), ("3S", '\u{b3}'), ("33", '\u{b3}'),
+    ("''", '\u{b4}'), ("My", '\u{b5}'), ("PI", '\u{b6}'), ("pp", '\u{b6}'), (".M", '\u{b7}'),
+    ("~.", '\u{b7}'), ("',", '\u{b8}'), ("1S", '\u{b9}'), ("11", '\u{b9}'), ("-o", '\u{ba}'),
+    (">>", '\u{bb}'), ("14", '\u{bc}'), ("12", '\u{bd}'), ("34", '\u{be}'), ("?I", '\u{bf}'),
+    ("~?", '\u{bf}'), ("A!", '\u{c0}'), ("A`", '\u{c0}'), ("A'", '\u{c1}'), ("A>", '\u{c2}'),
+    ("A^", '\u{c2}'), ("A?", '\u{c3}'), ("A~", '\u{c3}'), ("A:", '\u{c4}'), ("A\"", '\u{c4}'),
+    ("AA", '\u{c5}'), ("A@", '\u{c5}'), ("AE", '\u{c6}'), ("C,", '\u{c7}'), ("E!", '\u{c8}'),
+    ("E`", '\u{c8}'), ("E'", '\u{c9}'), ("E>", '\u{ca}'), ("E^", '\u{ca}'), ("E:", '\u{cb}'),
+    ("E\"", '\u{cb}'), ("I!", '\u{cc}'), ("I`", '\u{cc}'), ("I'", '\u{cd}'), ("I>", '\u{ce}'),
+    ("I^", '\u{ce}'), ("I:", '\u{cf}'), ("I\"", '\u{cf}'), ("D-", '\u{d0}'), ("N?", '\u{d1}'),
+    ("N~", '\u{d1}'), ("O!", '\u{d2}'), ("O`", '\u{d2}'), ("O'", '\u{d3}'), ("O>", '\u{d4}'),
+    ("O^", '\u{d4}'), ("O?", '\u{d5}'), ("O~", '\u{d5}'), ("O:", '\u{d6}'), ("*X", '\u{d7}'),
+    ("/\\", '\u{d7}'), ("O/", '\u{d8}'), ("U!", '\u{d9}'), ("U`", '\u{d9}'), ("U'", '\u{da}'),
+    ("U>", '\u{db}'), ("U^", '\u{db}'), ("U:", '\u{dc}'), ("Y'", '\u{dd}'), ("TH", '\u{de}'),
+    ("Ip", '\u{de}'), ("ss", '\u{df}'), ("a!", '\u{e0}'), ("a`", '\u{e0}'), ("a'", '\u{e1}'),
+    ("a>", '\u{e2}'), ("a^", '\u{e2}'), ("a?", '\u{e3}'), ("a~", '\u{e3}'), ("a:", '\u{e4}'),
+    ("a\"", '\u{e4}'), ("aa", '\u{e5}'), ("a@", '\u{e5}'), ("ae", '\u{e6}'), ("c,", '\u{e7}'),
+    ("e!", '\u{e8}'), ("e`", '\u{e8}'), ("e'", '\u{e9}'), ("e>", '\u{ea}'), ("e^", '\u{ea}'),
+    ("e:", '\u{eb}'), ("e\"", '\u{eb}'), ("i!", '\u{ec}'), ("i`", '\u{ec}'), ("i'", '\u{ed}'),
+    ("i>", '\u{ee}'), ("i^", '\u{ee}'), ("i:", '\u{ef}'), ("d-", '\u{f0}'), ("n?", '\u{f1}'),
+    ("n~", '\u{f1}'), ("o!", '\u{f2}'), ("o`", '\u{f2}'), ("o'", '\u{f3}'), ("o>", '\u{f4}'),
+    ("o^", '\u{f4}'), ("o?", '\u{f5}'), ("o~", '\u{f5}'), ("o:", '\u{f6}'), ("-:", '\u{f7}'),
+    ("o/", '\u{f8}'), ("u!", '\u{f9}'), ("u`", '\u{f9}'), ("u'", '\u{fa}'), ("u>", '\u{fb}'),
+    ("u^", '\u{fb}'), ("u:", '\u{fc}'), ("y'", '\u{fd}'), ("th", '\u{fe}'), ("y:", '\u{ff}'),
+    ("y\"", '\u{ff}'), ("A-", '\u{100}'), ("a-", '\u{101}'), ("A(", '\u{102}'), ("a(", '\u{103}'),
+    ("A;", '\u{104}'), ("a;", '\u{105}'), ("C'", '\u{106}'), ("c'", '\u{107}'), ("C>", '\u{108}'),
+    ("c>", '\u{109}'), ("C.", '\u{10a}'), ("c.", '\u{10b}'), ("C<", '\u{10c}'), ("c<", '\u{10d}'),
+    ("D<", '\u{10e}'), ("d<", '\u{10f}'), ("D/", '\u{110}'), ("d/", '\u{111}'), ("E-", '\u{112}'),
+    ("e-", '\u{113}'), ("E(", '\u{114}'), ("e(", '\u{115}'), ("E.", '\u{116}'), ("e.", '\u{117}'),
+    ("E;", '\u{118}'), ("e;", '\u{119}'), ("E<", '\u{11a}'), ("e<", '\u{11b}'), ("G>", '\u{11c}'),
+    ("g>", '\u{11d}'), ("G(", '\u{11e}'), ("g(", '\u{11f}'), ("G.", '\u{120}'), ("g.", '\u{121}'),
+    ("G,", '\u{122}'), ("g,", '\u{123}'), ("H>", '\u{124}'), ("h>", '\u{125}'), ("H/", '\u{126}'),
+    ("h/", '\u{127}'), ("I?", '\u{128}'), ("i?", '\u{129}'), ("I-", '\u{12a}'), ("i-", '\u{12b}'),
+    ("I(", '\u{12c}'), ("i(", '\u{12d}'), ("I;", '\u{12e}'), ("i;", '\u{12f}'), ("I.", '\u{130}'),
+    ("i.", '\u{131}'), ("IJ", '\u{132}'), ("ij", '\u{133}'), ("J>", '\u{134}'), ("j>", '\u{135}'),
+    ("K,", '\u{136}'), ("k,", '\u{137}'), ("kk", '\u{138}'), ("L'", '\u{139}'), ("l'", '\u{13a}'),
+    ("L,", '\u{13b}'), ("l,", '\u{13c}'), ("L<", '\u{13d}'), ("l<", '\u{13e}'), ("L.", '\u{13f}'),
+    ("l.", '\u{140}'), ("L/", '\u{141}'), ("l/", '\u{142}'), ("N'", '\u{143}'), ("n'", '\u{144}'),
+    ("N,", '\u{145}'), ("n,", '\u{146}'), ("N<", '\u{147}'), ("n<", '\u{148}'), ("'n", '\u{149}'),
+    ("NG", '\u{14a}'), ("ng", '\u{14b}'), ("O-", '\u{14c}'), ("o-", '\u{14d}'), ("O(", '\u{14e}'),
+    ("o(", '\u{14f}'), ("O\"", '\u{150}'), ("o\"", '\u{151}'), ("OE", '\u{152}'),
+    ("oe", '\u{153}'), ("R'", '\u{154}'), ("r'", '\u{155}'), ("R,", '\u{156}'), ("r,", '\u{157}'),
+    ("R<", '\u{158}'), ("r<", '\u{159}'), ("S'", '\u{15a}'), ("s'", '\u{15b}'), ("S>", '\u{15c}'),
+    ("s>", '\u{15d}'), ("S,", '\u{15e}'), ("s,", '\u{15f}'), ("S<", '\u{160}'), ("s<", '\u{161}'),
+    ("T,", '\u{162}'), ("t,", '\u{163}'), ("T<", '\u{164}'), ("t<", '\u{165}'), ("T/", '\u{166}'),
+    ("t/", '\u{167}'), ("U?", '\u{168}'), ("u?", '\u{169}'), ("U-", '\u{16a}'), ("u-", '\u{16b}'),
+    ("U(", '\u{16c}'), ("u(", '\u{16d}'), ("U0", '\u{16e}'), ("u0", '\u{16f}'), ("U\"", '\u{170}'),
+    ("u\"", '\u{171}'), ("U;", '\u{172}'), ("u;", '\u{173}'), ("W>", '\u{174}'), ("w>", '\u{175}'),
+    ("Y>", '\u{176}'), ("y>", '\u{177}'), ("Y:", '\u{178}'), ("Z'", '\u{179}'), ("z'", '\u{17a}'),
+    ("Z.", '\u{17b}'), ("z.", '\u{17c}'), ("Z<", '\u{17d}'), ("z<", '\u{17e}'), ("O9", '\u{1a0}'),
+    ("o9", '\u{1a1}'), ("OI", '\u{1a2}'), ("oi", '\u{1a3}'), ("yr", '\u{1a6}'), ("U9", '\u{1af}'),
+    ("u9", '\u{1b0}'), ("Z/", '\u{1b5}'), ("z/", '\u{1b6}'), ("ED", '\u{1b7}'), ("A<", '\u{1cd}'),
+    ("a<", '\u{1ce}'), ("I<", '\u{1cf}'), ("i<", '\u{1d0}'), ("O<", '\u{1d1}'), ("o<", '\u{1d2}'),
+    ("U<", '\u{1d3}'), ("u<", '\u{1d4}'), ("A1", '\u{1de}'), ("a1", '\u{1df}'), ("A7", '\u{1e0}'),
+    ("a7", '\u{1e1}'), ("A3", '\u{1e2}'), ("a3", '\u{1e3}'), ("G/", '\u{1e4}'), ("g/", '\u{1e5}'),
+    ("G<", '\u{1e6}'), ("g<", '\u{1e7}'), ("K<", '\u{1e8}'), ("k<", '\u{1e9}'), ("O;", '\u{1ea}'),
+    ("o;", '\u{1eb}'), ("O1", '\u{1ec}'), ("o1", '\u{1ed}'), ("EZ", '\u{1ee}'), ("ez", '\u{1ef}'),
+    ("j<", '\u{1f0}'), ("G'", '\u{1f4}'), ("g'", '\u{1f5}'), (";S", '\u{2bf}'), ("'<", '\u{2c7}'),
+    ("'(", '\u{2d8}'), ("'.", '\u{2d9}'), ("'0", '\u{2da}'), ("';", '\u{2db}'), ("'\"", '\u{2dd}'),
+    ("A%", '\u{386}'), ("E%", '\u{388}'), ("Y%", '\u{389}'), ("I%", '\u{38a}'), ("O%", '\u{38c}'),
+    ("U%", '\u{38e}'), ("W%", '\u{38f}'), ("i3", '\u{390}'), ("A*", '\u{391}'), ("B*", '\u{392}'),
+    ("G*", '\u{393}'), ("D*", '\u{394}'), ("E*", '\u{395}'), ("Z*", '\u{396}'), ("Y*", '\u{397}'),
+    ("H*", '\u{398}'), ("I*", '\u{399}'), ("K*", '\u{39a}'), ("L*", '\u{39b}'), ("M*", '\u{39c}'),
+    ("N*", '\u{39d}'), ("C*", '\u{39e}'), ("O*", '\u{39f}'), ("P*", '\u{3a0}'), ("R*", '\u{3a1}'),
+    ("S*", '\u{3a3}'), ("T*", '\u{3a4}'), ("U*", '\u{3a5}'), ("F*", '\u{3a6}'), ("X*", '\u{3a7}'),
+    ("Q*", '\u{3a8}'), ("W*", '\u{3a9}'), ("J*", '\u{3aa}'), ("V*", '\u{3ab}'), ("a%", '\u{3ac}'),
+    ("e%", '\u{3ad}'), ("y%", '\u{3ae}'), ("i%", '\u{3af}'), ("u3", '\u{3b0}'), ("a*", '\u{3b1}'),
+    ("b*", '\u{3b2}'), ("g*", '\u{3b3}'), ("d*", '\u{3b4}'), ("e*", '\u{3b5}'), ("z*", '\u{3b6}'),
+    ("y*", '\u{3b7}'), ("h*", '\u{3b8}'), ("i*", '\u{3b9}'), ("k*", '\u{3ba}'), ("l*", '\u{3bb}'),
+    ("m*", '\u{3bc}'), ("n*", '\u{3bd}'), ("c*", '\u{3be}'), ("o*", '\u{3bf}'), ("p*", '\u{3c0}'),
+    ("r*", '\u{3c1}'), ("*s", '\u{3c2}'), ("s*", '\u{3c3}'), ("t*", '\u{3c4}'), ("u*", '\u{3c5}'),
+    ("f*", '\u{3c6}'), ("x*", '\u{3c7}'), ("q*", '\u{3c8}'), ("w*", '\u{3c9}'), ("j*", '\u{3ca}'),
+    ("v*", '\u{3cb}'), ("o%", '\u{3cc}'), ("u%", '\u{3cd}'), ("w%", '\u{3ce}'), ("'G", '\u{3d8}'),
+    (",G", '\u{3d9}'), ("T3", '\u{3da}'), ("t3", '\u{3db}'), ("M3", '\u{3dc}'), ("m3", '\u{3dd}'),
+    ("K3", '\u{3de}'), ("k3", '\u{3df}'), ("P3", '\u{3e0}'), ("p3", '\u{3e1}'), ("'%", '\u{3f4}'),
+    ("j3", '\u{3f5}'), ("IO", '\u{401}'), ("D%", '\u{402}'), ("G%", '\u{403}'), ("IE", '\u{404}'),
+    ("DS", '\u{405}'), ("II", '\u{406}'), ("YI", '\u{407}'), ("J%", '\u{408}'), ("LJ", '\u{409}'),
+    ("NJ", '\u{40a}'), ("Ts", '\u{40b}'), ("KJ", '\u{40c}'), ("V%", '\u{40e}'), ("DZ", '\u{40f}'),
+    ("A=", '\u{410}'), ("B=", '\u{411}'), ("V=", '\u{412}'), ("G=", '\u{413}'), ("D=", '\u{414}'),
+    ("E=", '\u{415}'), ("Z%", '\u{416}'), ("Z=", '\u{417}'), ("I=", '\u{418}'), ("J=", '\u{419}'),
+    ("K=", '\u{41a}'), ("L=", '\u{41b}'), ("M=", '\u{41c}'), ("N=", '\u{41d}'), ("O=", '\u{41e}'),
+    ("P=", '\u{41f}'), ("R=", '\u{420}'), ("S=", '\u{421}'), ("T=", '\u{422}'), ("U=", '\u{423}'),
+    ("F=", '\u{424}'), ("H=", '\u{425}'), ("C=", '\u{426}'), ("C%", '\u{427}'), ("S%", '\u{428}'),
+    ("Sc", '\u{429}'), ("=\"", '\u{42a}'), ("Y=", '\u{42b}'), ("%\"", '\u{42c}'),
+    ("JE", '\u{42d}'), ("JU", '\u{42e}'), ("JA", '\u{42f}'), ("a=", '\u{430}'), ("b=", '\u{431}'),
+    ("v=", '\u{432}'), ("g=", '\u{433}'), ("d=", '\u{434}'), ("e=", '\u{435}'), ("z%", '\u{436}'),
+    ("z=", '\u{437}'), ("i=", '\u{438}'), ("j=", '\u{439}'), ("k=", '\u{43a}'), ("l=", '\u{43b}'),
+    ("m=", '\u{43c}'), ("n=", '\u{43d}'), ("o=", '\u{43e}'), ("p=", '\u{43f}'), ("r=", '\u{440}'),
+    ("s=", '\u{441}'), ("t=", '\u{442}'), ("u=", '\u{443}'), ("f=", '\u{444}'), ("h=", '\u{445}'),
+    ("c=", '\u{446}'), ("c%", '\u{447}'), ("s%", '\u{448}'), ("sc", '\u{449}'), ("='", '\u{44a}'),
+    ("y=", '\u{44b}'), ("%'", '\u{44c}'), ("je", '\u{44d}'), ("ju", '\u{44e}'), ("ja", '\u{44f}'),
+    ("io", '\u{451}'), ("d%", '\u{452}'), ("g%", '\u{453}'), ("ie", '\u{454}'), ("ds", '\u{455}'),
+    ("ii", '\u{456}'), ("yi", '\u{457}'), ("j%", '\u{458}'), ("lj", '\u{459}'), ("nj", '\u{45a}'),
+    ("ts", '\u{45b}'), ("kj", '\u{45c}'), ("v%", '\u{45e}'), ("dz", '\u{45f}'), ("Y3", '\u{462}'),
+    ("y3", '\u{463}'), ("O3", '\u{46a}'), ("o3", '\u{46b}'), ("F3", '\u{472}'), ("f3", '\u{473}'),
+    ("V3", '\u{474}'), ("v3", '\u{475}'), ("C3", '\u{480}'), ("c3", '\u{481}'), ("G3", '\u{490}'),
+    ("g3", '\u{491}'), ("A+", '\u{5d0}'), ("B+", '\u{5d1}'), ("G+", '\u{5d2}'), ("D+", '\u{5d3}'),
+    ("H+", '\u{5d4}'), ("W+", '\u{5d5}'), ("Z+", '\u{5d6}'), ("X+", '\u{5d7}'), ("Tj", '\u{5d8}'),
+    ("J+", '\u{5d9}'), ("K%", '\u{5da}'), ("K+", '\u{5db}'), ("L+", '\u{5dc}'), ("M%", '\u{5dd}'),
+    ("M+", '\u{5de}'), ("N%", '\u{5df}'), ("N+", '\u{5e0}'), ("S+", '\u{5e1}'), ("E+", '\u{5e2}'),
+    ("P%", '\u{5e3}'), ("P+", '\u{5e4}'), ("Zj", '\u{5e5}'), ("ZJ", '\u{5e6}'), ("Q+", '\u{5e7}'),
+    ("R+", '\u{5e8}'), ("Sh", '\u{5e9}'), ("T+", '\u{5ea}'), (",+", '\u{60c}'), (";+", '\u{61b}'),
+    ("?+", '\u{61f}'), ("H'", '\u{621}'), ("aM", '\u{622}'), ("aH", '\u{623}'), ("wH", '\u{624}'),
+    ("ah", '\u{625}'), ("yH", '\u{626}'), ("a+", '\u{627}'), ("b+", '\u{628}'), ("tm", '\u{629}'),
+    ("t+", '\u{62a}'), ("tk", '\u{62b}'), ("g+", '\u{62c}'), ("hk", '\u{62d}'), ("x+", '\u{62e}'),
+    ("d+", '\u{62f}'), ("dk", '\u{630}'), ("r+", '\u{631}'), ("z+", '\u{632}'), ("s+", '\u{633}'),
+    ("sn", '\u{634}'), ("c+", '\u{635}'), ("dd", '\u{636}'), ("tj", '\u{637}'), ("zH", '\u{638}'),
+    ("e+", '\u{639}'), ("i+", '\u{63a}'), ("++", '\u{640}'), ("f+", '\u{641}'), ("q+", '\u{642}'),
+    ("k+", '\u{643}'), ("l+", '\u{644}'), ("m+", '\u{645}'), ("n+", '\u{646}'), ("h+", '\u{647}'),
+    ("w+", '\u{648}'), ("j+", '\u{649}'), ("y+", '\u{64a}'), (":+", '\u{64b}'), ("\"+", '\u{64c}'),
+    ("=+", '\u{64d}'), ("/+", '\u{64e}'), ("'+", '\u{64f}'), ("1+", '\u{650}'), ("3+", '\u{651}'),
+    ("0+", '\u{652}'), ("aS", '\u{670}'), ("p+", '\u{67e}'), ("v+", '\u{6a4}'), ("gf", '\u{6af}'),
+    ("0a", '\u{6f0}'), ("1a", '\u{6f1}'), ("2a", '\u{6f2}'), ("3a", '\u{6f3}'), ("4a", '\u{6f4}'),
+    ("5a", '\u{6f5}'), ("6a", '\u{6f6}'), ("7a", '\u{6f7}'), ("8a", '\u{6f8}'), ("9a", '\u{6f9}'),
+    ("B.", '\u{1e02}'), ("b.", '\u{1e03}'), ("B_", '\u{1e06}'), ("b_", '\u{1e07}'),
+    ("D.", '\u{1e0a}'), ("d.", '\u{1e0b}'), ("D_", '\u{1e0e}'), ("d_", '\u{1e0f}'),
+    ("D,", '\u{1e10}'), ("d,", '\u{1e11}'), ("F.", '\u{1e1e}'), ("f.", '\u{1e1f}'),
+    ("G-", '\u{1e20}'), ("g-", '\u{1e21}'), ("H.", '\u{1e22}'), ("h.", '\u{1e23}'),
+    ("H:", '\u{1e26}'), ("h:", '\u{1e27}'), ("H,", '\u{1e28}'), ("h,", '\u{1e29}'),
+    ("K'", '\u{1e30}'), ("k'", '\u{1e31}'), ("K_", '\u{1e34}'), ("k_", '\u{1e35}'),
+    ("L_", '\u{1e3a}'), ("l_", '\u{1e3b}'), ("M'", '\u{1e3e}'), ("m'", '\u{1e3f}'),
+    ("M.", '\u{1e40}'), ("m.", '\u{1e41}'), ("N.", '\u{1e44}'), ("n.", '\u{1e45}'),
+    ("N_", '\u{1e48}'), ("n_", '\u{1e49}'), ("P'", '\u{1e54}'), ("p'", '\u{1e55}'),
+    ("P.", '\u{1e56}'), ("p.", '\u{1e57}'), ("R.", '\u{1e58}'), ("r.", '\u{1e59}'),
+    ("R_", '\u{1e5e}'), ("r_", '\u{1e5f}'), ("S.", '\u{1e60}'), ("s.", '\u{1e61}'),
+    ("T.", '\u{1e6a}'), ("t.", '\u{1e6b}'), ("T_", '\u{1e6e}'), ("t_", '\u{1e6f}'),
+    ("V?", '\u{1e7c}'), ("v?", '\u{1e7d}'), ("W!", '\u{1e80}'), ("W`", '\u{1e80}'),
+    ("w!", '\u{1e81}'), ("w`", '\u{1e81}'), ("W'", '\u{1e82}'), ("w'", '\u{1e83}'),
+    ("W:", '\u{1e84}'), ("w:", '\u{1e85}'), ("W.", '\u{1e86}'), ("w.", '\u{1e87}'),
+    ("X.", '\u{1e8a}'), ("x.", '\u{1e8b}'), ("X:", '\u{1e8c}'), ("x:", '\u{1e8d}'),
+    ("Y.", '\u{1e8e}'), ("y.", '\u{1e8f}'), ("Z>", '\u{1e90}'), ("z>", '\u{1e91}'),
+    ("Z_", '\u{1e94}'), ("z_", '\u{1e95}'), ("h_", '\u{1e96}'), ("t:", '\u{1e97}'),
+    ("w0", '\u{1e98}'), ("y0", '\u{1e99}'), ("A2", '\u{1ea2}'), ("a2", '\u{1ea3}'),
+    ("E2", '\u{1eba}'), ("e2", '\u{1ebb}'), ("E?", '\u{1ebc}'), ("e?", '\u{1ebd}'),
+    ("I2", '\u{1ec8}'), ("i2", '\u{1ec9}'), ("O2", '\u{1ece}'), ("o2", '\u{1ecf}'),
+    ("U2", '\u{1ee6}'), ("u2", '\u{1ee7}'), ("Y!", '\u{1ef2}'), ("Y`", '\u{1ef2}'),
+    ("y!", '\u{1ef3}'), ("y`", '\u{1ef3}'), ("Y2", '\u{1ef6}'), ("y2", '\u{1ef7}'),
+    ("Y?", '\u{1ef8}'), ("y?", '\u{1ef9}'), (";'", '\u{1f00}'), (",'", '\u{1f01}'),
+    (";!", '\u{1f02}'), (",!", '\u{1f03}'), ("?;", '\u{1f04}'), ("?,", '\u{1f05}'),
+    ("!:", '\u{1f06}'), ("?:", '\u{1f07}'), ("1N", '\u{2002}'), ("1M", '\u{2003}'),
+    ("3M", '\u{2004}'), ("4M", '\u{2005}'), ("6M", '\u{2006}'), ("1T", '\u{2009}'),
+    ("1H", '\u{200a}'), ("-1", '\u{2010}'), ("-N", '\u{2013}'), ("-M", '\u{2014}'),
+    ("-3", '\u{2015}'), ("!2", '\u{2016}'), ("=2", '\u{2017}'), ("'6", '\u{2018}'),
+    ("'9", '\u{2019}'), (".9", '\u{201a}'), ("9'", '\u{201b}'), ("\"6", '\u{201c}'),
+    ("\"9", '\u{201d}'), (":9", '\u{201e}'), ("9\"", '\u{201f}'), ("/-", '\u{2020}'),
+    ("/=", '\u{2021}'), ("oo", '\u{2022}'), ("..", '\u{2025}'), (",.", '\u{2026}'),
+    ("%0", '\u{2030}'), ("1'", '\u{2032}'), ("2'", '\u{2033}'), ("3'", '\u{2034}'),
+    ("1\"", '\u{2035}'), ("2\"", '\u{2036}'), ("3\"", '\u{2037}'), ("Ca", '\u{2038}'),
+    ("<1", '\u{2039}'), (">1", '\u{203a}'), (":X", '\u{203b}'), ("'-", '\u{203e}'),
+    ("/f", '\u{2044}'), ("0S", '\u{2070}'), ("4S", '\u{2074}'), ("5S", '\u{2075}'),
+    ("6S", '\u{2076}'), ("7S", '\u{2077}'), ("8S", '\u{2078}'), ("9S", '\u{2079}'),
+    ("+S", '\u{207a}'), ("-S", '\u{207b}'), ("=S", '\u{207c}'), ("(S", '\u{207d}'),
+    (")S", '\u{207e}'), ("nS", '\u{207f}'), ("0s", '\u{2080}'), ("1s", '\u{2081}'),
+    ("2s", '\u{2082}'), ("3s", '\u{2083}'), ("4s", '\u{2084}'), ("5s", '\u{2085}'),
+    ("6s", '\u{2086}'), ("7s", '\u{2087}'), ("8s", '\u{2088}'), ("9s", '\u{2089}'),
+    ("+s", '\u{208a}'), ("-s", '\u{208b}'), ("=s", '\u{208c}'), ("(s", '\u{208d}'),
+    (")s", '\u{208e}'), ("Li", '\u{20a4}'), ("Pt", '\u{20a7}'), ("W=", '\u{20a9}'),
+    ("=e", '\u{20ac}'), ("Eu", '\u{20ac}'), ("=R", '\u{20bd}'), ("=P", '\u{20bd}'),
+    ("oC", '\u{2103}'), ("co", '\u{2105}'), ("oF", '\u{2109}'), ("N0", '\u{2116}'),
+    ("PO", '\u{2117}'), ("Rx", '\u{211e}'), ("SM", '\u{2120}'), ("TM", '\u{2122}'),
+    ("Om", '\u{2126}'), ("AO", '\u{212b}'), ("13", '\u{2153}'), ("23", '\u{2154}'),
+    ("15", '\u{2155}'), ("25", '\u{2156}'), ("35", '\u{2157}'), ("45", '\u{2158}'),
+    ("16", '\u{2159}'), ("56", '\u{215a}'), ("18", '\u{215b}'), ("38", '\u{215c}'),
+    ("58", '\u{215d}'), ("78", '\u{215e}'), ("1R", '\u{2160}'), ("2R", '\u{2161}'),
+    ("3R", '\u{2162}'), ("4R", '\u{2163}'), ("5R", '\u{2164}'), ("6R", '\u{2165}'),
+    ("7R", '\u{2166}'), ("8R", '\u{2167}'), ("9R", '\u{2168}'), ("aR", '\u{2169}'),
+    ("bR", '\u{216a}'), ("cR", '\u{216b}'), ("1r", '\u{2170}'), ("2r", '\u{2171}'),
+    ("3r", '\u{2172}'), ("4r", '\u{2173}'), ("5r", '\u{2174}'), ("6r", '\u{2175}'),
+    ("7r", '\u{2176}'), ("8r", '\u{2177}'), ("9r", '\u{2178}'), ("ar", '\u{2179}'),
+    ("br", '\u{217a}'), ("cr", '\u{217b}'), ("<-", '\u{2190}'), ("-!", '\u{2191}'),
+    ("->", '\u{2192}'), ("-v", '\u{2193}'), ("<>", '\u{2194}'), ("UD", '\u{2195}'),
+    ("<=", '\u{21d0}'), ("=>", '\u{21d2}'), ("==", '\u{21d4}'), ("FA", '\u{2200}'),
+    ("dP", '\u{2202}'), ("TE", '\u{2203}'), ("/0", '\u{2205}'), ("DE", '\u{2206}'),
+    ("NB", '\u{2207}'), ("(-", '\u{2208}'), ("-)", '\u{220b}'), ("*P", '\u{220f}'),
+    ("+Z", '\u{2211}'), ("-2", '\u{2212}'), ("-+", '\u{2213}'), ("*-", '\u{2217}'),
+    ("Ob", '\u{2218}'), ("Sb", '\u{2219}'), ("RT", '\u{221a}'), ("0(", '\u{221d}'),
+    ("00", '\u{221e}'), ("-L", '\u{221f}'), ("-V", '\u{2220}'), ("PP", '\u{2225}'),
+    ("AN", '\u{2227}'), ("OR", '\u{2228}'), ("(U", '\u{2229}'), (")U", '\u{222a}'),
+    ("In", '\u{222b}'), ("DI", '\u{222c}'), ("Io", '\u{222e}'), (".:", '\u{2234}'),
+    (":.", '\u{2235}'), (":R", '\u{2236}'), ("::", '\u{2237}'), ("?1", '\u{223c}'),
+    ("CG", '\u{223e}'), ("?-", '\u{2243}'), ("?=", '\u{2245}'), ("?2", '\u{2248}'),
+    ("=?", '\u{224c}'), ("HI", '\u{2253}'), ("!=", '\u{2260}'), ("=3", '\u{2261}'),
+    ("=<", '\u{2264}'), (">=", '\u{2265}'), ("<*", '\u{226a}'), ("*>", '\u{226b}'),
+    ("!<", '\u{226e}'), ("!>", '\u{226f}'), ("(C", '\u{2282}'), (")C", '\u{2283}'),
+    ("(_", '\u{2286}'), (")_", '\u{2287}'), ("0.", '\u{2299}'), ("02", '\u{229a}'),
+    ("-T", '\u{22a5}'), (".P", '\u{22c5}'), (":3", '\u{22ee}'), (".3", '\u{22ef}'),
+    ("Eh", '\u{2302}'), ("<7", '\u{2308}'), (">7", '\u{2309}'), ("7<", '\u{230a}'),
+    ("7>", '\u{230b}'), ("NI", '\u{2310}'), ("(A", '\u{2312}'), ("TR", '\u{2315}'),
+    ("Iu", '\u{2320}'), ("Il", '\u{2321}'), ("</", '\u{2329}'), ("/>", '\u{232a}'),
+    ("Vs", '\u{2423}'), ("1h", '\u{2440}'), ("3h", '\u{2441}'), ("2h", '\u{2442}'),
+    ("4h", '\u{2443}'), ("1j", '\u{2446}'), ("2j", '\u{2447}'), ("3j", '\u{2448}'),
+    ("4j", '\u{2449}'), ("1.", '\u{2488}'), ("2.", '\u{2489}'), ("3.", '\u{248a}'),
+    ("4.", '\u{248b}'), ("5.", '\u{248c}'), ("6.", '\u{248d}'), ("7.", '\u{248e}'),
+    ("8.", '\u{248f}'), ("9.", '\u{2490}'), ("hh", '\u{2500}'), ("HH", '\u{2501}'),
+    ("vv", '\u{2502}'), ("VV", '\u{2503}'), ("3-", '\u{2504}'), ("3_", '\u{2505}'),
+    ("3!", '\u{2506}'), ("3/", '\u{2507}'), ("4-", '\u{2508}'), ("4_", '\u{2509}'),
+    ("4!", '\u{250a}'), ("4/", '\u{250b}'), ("dr", '\u{250c}'), ("dR", '\u{250d}'),
+    ("Dr", '\u{250e}'), ("DR", '\u{250f}'), ("dl", '\u{2510}'), ("dL", '\u{2511}'),
+    ("Dl", '\u{2512}'), ("LD", '\u{2513}'), ("ur", '\u{2514}'), ("uR", '\u{2515}'),
+    ("Ur", '\u{2516}'), ("UR", '\u{2517}'), ("ul", '\u{2518}'), ("uL", '\u{2519}'),
+    ("Ul", '\u{251a}'), ("UL", '\u{251b}'), ("vr", '\u{251c}'), ("vR", '\u{251d}'),
+    ("Vr", '\u{2520}'), ("VR", '\u{2523}'), ("vl", '\u{2524}'), ("vL", '\u{2525}'),
+    ("Vl", '\u{2528}'), ("VL", '\u{252b}'), ("dh", '\u{252c}'), ("dH", '\u{252f}'),
+    ("Dh", '\u{2530}'), ("DH", '\u{2533}'), ("uh", '\u{2534}'), ("uH", '\u{2537}'),
+    ("Uh", '\u{2538}'), ("UH", '\u{253b}'), ("vh", '\u{253c}'), ("vH", '\u{253f}'),
+    ("Vh", '\u{2542}'), ("VH", '\u{254b}'), ("FD", '\u{2571}'), ("BD", '\u{2572}'),
+    ("TB", '\u{2580}'), ("LB", '\u{2584}'), ("FB", '\u{2588}'), ("lB", '\u{258c}'),
+    ("RB", '\u{2590}'), (".S", '\u{2591}'), (":S", '\u{2592}'), ("?S", '\u{2593}'),
+    ("fS", '\u{25a0}'), ("OS", '\u{25a1}'), ("RO", '\u{25a2}'), ("Rr", '\u{25a3}'),
+    ("RF", '\u{25a4}'), ("RY", '\u{25a5}'), ("RH", '\u{25a6}'), ("RZ", '\u{25a7}'),
+    ("RK", '\u{25a8}'), ("RX", '\u{25a9}'), ("sB", '\u{25aa}'), ("SR", '\u{25ac}'),
+    ("Or", '\u{25ad}'), ("UT", '\u{25b2}'), ("uT", '\u{25b3}'), ("PR", '\u{25b6}'),
+    ("Tr", '\u{25b7}'), ("Dt", '\u{25bc}'), ("dT", '\u{25bd}'), ("PL", '\u{25c0}'),
+    ("Tl", '\u{25c1}'), ("Db", '\u{25c6}'), ("Dw", '\u{25c7}'), ("LZ", '\u{25ca}'),
+    ("0m", '\u{25cb}'), ("0o", '\u{25ce}'), ("0M", '\u{25cf}'), ("0L", '\u{25d0}'),
+    ("0R", '\u{25d1}'), ("Sn", '\u{25d8}'), ("Ic", '\u{25d9}'), ("Fd", '\u{25e2}'),
+    ("Bd", '\u{25e3}'), ("*2", '\u{2605}'), ("*1", '\u{2606}'), ("<H", '\u{261c}'),
+    (">H", '\u{261e}'), ("0u", '\u{263a}'), ("0U", '\u{263b}'), ("SU", '\u{263c}'),
+    ("Fm", '\u{2640}'), ("Ml", '\u{2642}'), ("cS", '\u{2660}'), ("cH", '\u{2661}'),
+    ("cD", '\u{2662}'), ("cC", '\u{2663}'), ("Md", '\u{2669}'), ("M8", '\u{266a}'),
+    ("M2", '\u{266b}'), ("Mb", '\u{266d}'), ("Mx", '\u{266e}'), ("MX", '\u{266f}'),
+    ("OK", '\u{2713}'), ("XX", '\u{2717}'), ("-X", '\u{2720}'), ("IS", '\u{3000}'),
+    (",_", '\u{3001}'), ("._", '\u{3002}'), ("+\"", '\u{3003}'), ("+_", '\u{3004}'),
+    ("*_", '\u{3005}'), (";_", '\u{3006}'), ("0_", '\u{3007}'), ("<+", '\u{300a}'),
+    (">+", '\u{300b}'), ("<'", '\u{300c}'), (">'", '\u{300d}'), ("<\"", '\u{300e}'),
+    (">\"", '\u{300f}'), ("(\"", '\u{3010}'), (")\"", '\u{3011}'), ("=T", '\u{3012}'),
+    ("=_", '\u{3013}'), ("('", '\u{3014}'), (")'", '\u{3015}'), ("(I", '\u{3016}'),
+    (")I", '\u{3017}'), ("-?", '\u{301c}'), ("A5", '\u{3041}'), ("a5", '\u{3042}'),
+    ("I5", '\u{3043}'), ("i5", '\u{3044}'), ("U5", '\u{3045}'), ("u5", '\u{3046}'),
+    ("E5", '\u{3047}'), ("e5", '\u{3048}'), ("O5", '\u{3049}'), ("o5", '\u{304a}'),
+    ("ka", '\u{304b}'), ("ga", '\u{304c}'), ("ki", '\u{304d}'), ("gi", '\u{304e}'),
+    ("ku", '\u{304f}'), ("gu", '\u{3050}'), ("ke", '\u{3051}'), ("ge", '\u{3052}'),
+    ("ko", '\u{3053}'), ("go", '\u{3054}'), ("sa", '\u{3055}'), ("za", '\u{3056}'),
+    ("si", '\u{3057}'), ("zi", '\u{3058}'), ("su", '\u{3059}'), ("zu", '\u{305a}'),
+    ("se", '\u{305b}'), ("ze", '\u{305c}'), ("so", '\u{305d}'), ("zo", '\u{305e}'),
+    ("ta", '\u{305f}'), ("da", '\u{3060}'), ("ti", '\u{3061}'), ("di", '\u{3062}'),
+    ("tU", '\u{3063}'), ("tu", '\u{3064}'), ("du", '\u{3065}'), ("te", '\u{3066}'),
+    ("de", '\u{3067}'), ("to", '\u{3068}'), ("do", '\u{3069}'), ("na", '\u{306a}'),
+    ("ni", '\u{306b}'), ("nu", '\u{306c}'), ("ne", '\u{306d}'), ("no", '\u{306e}'),
+    ("ha", '\u{306f}'), ("ba", '\u{3070}'), ("pa", '\u{3071}'), ("hi", '\u{3072}'),
+    ("bi", '\u{3073}'), ("pi", '\u{3074}'), ("hu", '\u{3075}'), ("bu", '\u{3076}'),
+    ("pu", '\u{3077}'), ("he", '\u{3078}'), ("be", '\u{3079}'), ("pe", '\u{307a}'),
+    ("ho", '\u{307b}'), ("bo", '\u{307c}'), ("po", '\u{307d}'), ("ma", '\u{307e}'),
+    ("mi", '\u{307f}'), ("mu", '\u{3080}'), ("me", '\u{3081}'), ("mo", '\u{3082}'),
+    ("yA", '\u{3083}'), ("ya", '\u{3084}'), ("yU", '\u{3085}'), ("yu", '\u{3086}'),
+    ("yO", '\u{3087}'), ("yo", '\u{3088}'), ("ra", '\u{3089}'), ("ri", '\u{308a}'),
+    ("ru", '\u{308b}'), ("re", '\u{308c}'), ("ro", '\u{308d}'), ("wA", '\u{308e}'),
+    ("wa", '\u{308f}'), ("wi", '\u{3090}'), ("we", '\u{3091}'), ("wo", '\u{3092}'),
+    ("n5", '\u{3093}'), ("vu", '\u{3094}'), ("\"5", '\u{309b}'), ("05", '\u{309c}'),
+    ("*5", '\u{309d}'), ("+5", '\u{309e}'), ("a6", '\u{30a1}'), ("A6", '\u{30a2}'),
+    ("i6", '\u{30a3}'), ("I6", '\u{30a4}'), ("u6", '\u{30a5}'), ("U6", '\u{30a6}'),
+    ("e6", '\u{30a7}'), ("E6", '\u{30a8}'), ("o6", '\u{30a9}'), ("O6", '\u{30aa}'),
+    ("Ka", '\u{30ab}'), ("Ga", '\u{30ac}'), ("Ki", '\u{30ad}'), ("Gi", '\u{30ae}'),
+    ("Ku", '\u{30af}'), ("Gu", '\u{30b0}'), ("Ke", '\u{30b1}'), ("Ge", '\u{30b2}'),
+    ("Ko", '\u{30b3}'), ("Go", '\u{30b4}'), ("Sa", '\u{30b5}'), ("Za", '\u{30b6}'),
+    ("Si", '\u{30b7}'), ("Zi", '\u{30b8}'), ("Su", '\u{30b9}'), ("Zu", '\u{30ba}'),
+    ("Se", '\u{30bb}'), ("Ze", '\u{30bc}'), ("So", '\u{30bd}'), ("Zo", '\u{30be}'),
+    ("Ta", '\u{30bf}'), ("Da", '\u{30c0}'), ("Ti", '\u{30c1}'), ("Di", '\u{30c2}'),
+    ("TU", '\u{30c3}'), ("Tu", '\u{30c4}'), ("Du", '\u{30c5}'), ("Te", '\u{30c6}'),
+    ("De", '\u{30c7}'), ("To", '\u{30c8}'), ("Do", '\u{30c9}'), ("Na", '\u{30ca}'),
+    ("Ni", '\u{30cb}'), ("Nu", '\u{30cc}'), ("Ne", '\u{30cd}'), ("No", '\u{30ce}'),
+    ("Ha", '\u{30cf}'), ("Ba", '\u{30d0}'), ("Pa", '\u{30d1}'), ("Hi", '\u{30d2}'),
+    ("Bi", '\u{30d3}'), ("Pi", '\u{30d4}'), ("Hu", '\u{30d5}'), ("Bu", '\u{30d6}'),
+    ("Pu", '\u{30d7}'), ("He", '\u{30d8}'), ("Be", '\u{30d9}'), ("Pe", '\u{30da}'),
+    ("Ho", '\u{30db}'), ("Bo", '\u{30dc}'), ("Po", '\u{30dd}'), ("Ma", '\u{30de}'),
+    ("Mi", '\u{30df}'), ("Mu", '\u{30e0}'), ("Me", '\u{30e1}'), ("Mo", '\u{30e2}'),
+    ("YA", '\u{30e3}'), ("Ya", '\u{30e4}'), ("YU", '\u{30e5}'), ("Yu", '\u{30e6}'),
+    ("YO", '\u{30e7}'), ("Yo", '\u{30e8}'), ("Ra", '\u{30e9}'), ("Ri", '\u{30ea}'),
+    ("Ru", '\u{30eb}'), ("Re", '\u{30ec}'), ("Ro", '\u{30ed}'), ("WA", '\u{30ee}'),
+    ("Wa", '\u{30ef}'), ("Wi", '\u{30f0}'), ("We", '\u{30f1}'), ("Wo", '\u{30f2}'),
+    ("N6", '\u{30f3}'), ("Vu", '\u{30f4}'), ("KA", '\u{30f5}'), ("KE", '\u{30f6}'),
+    ("Va", '\u{30f7}'), ("Vi", '\u{30f8}'), ("Ve", '\u{30f9}'), ("Vo", '\u{30fa}'),
+    (".6", '\u{30fb}'), ("-6", '\u{30fc}'), ("*6", '\u{30fd}'), ("+6", '\u{30fe}'),
+    ("b4", '\u{3105}'), ("p4", '\u{3106}'), ("m4", '\u{3107}'), ("f4", '\u{3108}'),
+    ("d4", '\u{3109}'), ("t4", '\u{310a}'), ("n4", '\u{310b}'), ("l4", '\u{310c}'),
+    ("g4", '\u{310d}'), ("k4", '\u{310e}'), ("h4", '\u{310f}'), ("j4", '\u{3110}'),
+    ("q4", '\u{3111}'), ("x4", '\u{3112}'), ("zh", '\u{3113}'), ("ch", '\u{3114}'),
+    ("sh", '\u{3115}'), ("r4", '\u{3116}'), ("z4", '\u{3117}'), ("c4", '\u{3118}'),
+    ("s4", '\u{3119}'), ("a4", '\u{311a}'), ("o4", '\u{311b}'), ("e4", '\u{311c}'),
+    ("ai", '\u{311e}'), ("ei", '\u{311f}'), ("au", '\u{3120}'), ("ou", '\u{3121}'),
+    ("an", '\u{3122}'), ("en", '\u{3123}'), ("aN", '\u{3124}'), ("eN", '\u{3125}'),
+    ("er", '\u{3126}'), ("i4", '\u{3127}'), ("u4", '\u{3128}'), ("iu", '\u{3129}'),
+    ("v4", '\u{312a}'), ("nG", '\u{312b}'), ("gn", '\u{312c}'), ("1c", '\u{3220}'),
+    ("2c", '\u{3221}'), ("3c", '\u{3222}'), ("4c", '\u{3223}'), ("5c", '\u{3224}'),
+    ("6c", '\u{3225}'), ("7c", '\u{3226}'), ("8c", '\u{3227}'), ("9c", '\u{3228}'),
+    ("ff", '\u{fb00}'), ("fi", '\u{fb01}'), ("fl", '\u{fb02}'), ("ft", '\u{fb05}'),
+    ("st", '\u{fb06}'),
+];
+
 // ============================================================================
 // Tests
 // ============================================================================
@@ -50,8 +667,23 @@ mod tests {
     fn test_digraph_defaults() {
         let table = DigraphTable::new();
         assert_eq!(table.lookup('a', 'e'), Some('\u{00E6}'));
-        assert_eq!(table.lookup('O', 'S'), Some('\u{00D8}'));
+        assert_eq!(table.lookup('O', '/'), Some('\u{00D8}'));
         assert_eq!(table.lookup('x', 'y'), None);
+        assert_eq!(table.lookup('E', 'u'), Some('€'));
+        assert_eq!(table.lookup('-', '>'), Some('→'));
+        assert_eq!(table.lookup('a', '*'), Some('α'));
+        assert_eq!(table.lookup('k', 'a'), Some('か'));
+        assert_eq!(DEFAULT_DIGRAPHS.len(), 1362);
+    }
+
+    #[test]
+    fn test_digraph_reverse_lookup() {
+        let table = DigraphTable::new();
+        assert_eq!(table.lookup('e', 'a'), Some('æ'));
+        assert_eq!(table.lookup_exact('e', 'a'), None);
+        // The order given is found first
+        assert_eq!(table.lookup('!', 'a'), Some('à'));
+        assert_eq!(table.resolve('x', 'y'), 'y');
     }
 
     #[test]
@@ -59,5 +691,95 @@ mod tests {
         let mut table = DigraphTable::new();
         table.insert('x', 'y', '\u{2192}');
         assert_eq!(table.lookup('x', 'y'), Some('\u{2192}'));
+        table.insert('a', 'e', 'Ӓ');
+        assert_eq!(table.lookup('a', 'e'), Some('Ӓ'));
+        assert_eq!(table.lookup('e', 'a'), Some('Ӓ'));
+        assert_eq!(table.digraph_for('Ӓ'), Some(('a', 'e')));
+        assert_eq!(table.digraph_for('æ'), Some(('a', 'e')));
+    }
+
+    #[test]
+    fn test_define() {
+        let mut table = DigraphTable::new();
+        table.define("xx 8594  yy 65").unwrap();
+        assert_eq!(table.lookup('x', 'x'), Some('→'));
+        assert_eq!(table.lookup('y', 'y'), Some('A'));
+        assert_eq!(
+            table.define("a").unwrap_err().to_string(),
+            "E1214: Digraph must be just two characters: a"
+        );
+        assert_eq!(
+            table.define("ab").unwrap_err().to_string(),
+            "E39: Number expected"
+        );
+        assert_eq!(
+            table.define("zz 1 abc 1").unwrap_err().to_string(),
+            "E39: Number expected"
+        );
+        assert_eq!(table.lookup('z', 'z'), Some('\u{1}'));
+    }
+
+    #[test]
+    fn test_listing() {
+        let mut table = DigraphTable::new();
+        let listing = table.listing(false, 80);
+        let lines: Vec<&str> = listing.lines().collect();
+        assert_eq!(
+            lines[0],
+            "NU ^@   0    SH ^A   1    SX ^B   2    EX ^C   3    ET ^D   4    EQ ^E   5"
+        );
+        assert_eq!(
+            lines[6],
+            "<( [   91    // \\   92    )> ]   93    '> ^   94    '! `   96    (! {  123"
+        );
+        assert_eq!(
+            lines[8],
+            "BH <82> 130  NH <83> 131  IN <84> 132  NL <85> 133  SA <86> 134  ES <87> 135"
+        );
+        assert!(!listing.contains("Latin supplement"));
+
+        let listing = table.listing(true, 80);
+        assert!(listing.contains("AC <9f> 159\nNS \u{a0}  160\nLatin supplement\n!I ¡  161"));
+
+        table.define("xx 8594 ae 1234").unwrap();
+        let listing = table.listing(true, 80);
+        assert!(listing.ends_with("\nCustom\nxx →  8594   ae Ӓ  1234"));
+        assert!(!listing.contains("ae æ"));
+    }
+
+    #[test]
+    fn test_describe_char() {
+        let mut table = DigraphTable::new();
+        assert_eq!(table.describe_char("a"), "<a>  97,  Hex 61,  Octal 141");
+        assert_eq!(
+            table.describe_char("#"),
+            "<#>  35,  Hex 23,  Oct 043, Digr Nb"
+        );
+        assert_eq!(
+            table.describe_char("\u{1}"),
+            "<^A>  1,  Hex 01,  Oct 001, Digr SH"
+        );
+        assert_eq!(
+            table.describe_char("æ"),
+            "<æ> 230, Hex 00e6, Oct 346, Digr ae"
+        );
+        assert_eq!(
+            table.describe_char("e\u{301}"),
+            "<e>  101,  Hex 65,  Octal 145 < \u{301}> 769, Hex 0301, Octal 1401"
+        );
+        assert_eq!(table.describe_char(""), "NUL");
+        table.insert('x', 'x', '→');
+        assert_eq!(
+            table.describe_char("→"),
+            "<→> 8594, Hex 2192, Oct 20622, Digr xx"
+        );
+    }
+
+    #[test]
+    fn test_utf8_bytes() {
+        assert_eq!(utf8_bytes("a"), "61 ");
+        assert_eq!(utf8_bytes("æ"), "c3 a6 ");
+        assert_eq!(utf8_bytes("e\u{301}"), "65 + cc 81 ");
+        assert_eq!(utf8_bytes(""), "NUL");
     }
 }
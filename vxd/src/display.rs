@@ -0,0 +1,837 @@
+//! Display model: the cells the characters of a line take on the screen.
+//!
+//! Every character of a buffer line is shown in one or more screen cells,
+//! counted in virtual columns from 0:
+//!
+//! - A tab takes the cells up to the next tab stop, from 'tabstop' or
+//!   'vartabstop'; with 'list' it is drawn with the "tab" item of
+//!   'listchars', or as `^I` when that has none
+//! - A control character is shown as `^X`, other unprintable ones as
+//!   `<xx>`; with "uhex" in 'display' all of them are `<xx>`
+//! - East Asian wide characters take two cells, the ambiguous ones too
+//!   with 'ambiwidth' "double"; composing characters take none
+//! - With 'list' spaces, non-breaking spaces and the end of the line are
+//!   shown with 'listchars'
+//!
+//! A window shows the cells in rows of its width.  The last line that
+//! does not fit is shown as `@` rows, or cut off with `@@@` for "lastline"
+//! and "truncate" in 'display'.
+//!
+//! # Key Behavioral Contracts
+//!
+//! - The virtual column of a character is the number of cells before it
+//! - In Normal mode the cursor is shown on the last cell of a tab, unless
+//!   'list' is set; otherwise on the first cell of the character
+//! - A double-width character that does not fit at the end of a row is
+//!   shown on the next one, leaving a `>` cell
+
+use crate::types::{VimError, VimResult};
+
+/// The 'ambiwidth' option: the cells of East Asian ambiguous characters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AmbiWidth {
+    /// One cell
+    #[default]
+    Single,
+    /// Two cells
+    Double,
+}
+
+impl AmbiWidth {
+    /// Parse the value of 'ambiwidth'
+    pub fn parse(value: &str) -> VimResult<Self> {
+        match value {
+            "single" => Ok(AmbiWidth::Single),
+            "double" => Ok(AmbiWidth::Double),
+            _ => Err(invalid_argument(value)),
+        }
+    }
+}
+
+/// The "tab" item of 'listchars': the first and filling characters, and
+/// the one always shown last
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ListTab {
+    /// The first cell
+    pub first: char,
+    /// The other cells
+    pub fill: char,
+    /// The last cell, shown even for a tab of one cell
+    pub last: Option<char>,
+}
+
+/// The 'listchars' option: how 'list' shows characters
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListChars {
+    /// After the end of the line
+    pub eol: Option<char>,
+    /// For a tab; without it tabs are shown as `^I`
+    pub tab: Option<ListTab>,
+    /// For a space
+    pub space: Option<char>,
+    /// For a space before the first non-blank
+    pub lead: Option<char>,
+    /// For a space after the last non-blank
+    pub trail: Option<char>,
+    /// For a non-breaking space
+    pub nbsp: Option<char>,
+    /// In the last column when the line goes on past the window, without
+    /// 'wrap'
+    pub extends: Option<char>,
+    /// In the first column when the line starts before the window, without
+    /// 'wrap'
+    pub precedes: Option<char>,
+}
+
+impl Default for ListChars {
+    /// The default "eol:$"
+    fn default() -> Self {
+        ListChars {
+            eol: Some('$'),
+            ..ListChars::none()
+        }
+    }
+}
+
+impl ListChars {
+    /// No item set, as for an empty 'listchars'
+    pub fn none() -> Self {
+        ListChars {
+            eol: None,
+            tab: None,
+            space: None,
+            lead: None,
+            trail: None,
+            nbsp: None,
+            extends: None,
+            precedes: None,
+        }
+    }
+
+    /// Parse the value of 'listchars', comma-separated `{item}:{chars}`.
+    /// Each character must take one cell.
+    pub fn parse(value: &str) -> VimResult<Self> {
+        let mut chars = ListChars::none();
+        for part in value.split(',').filter(|part| !part.is_empty()) {
+            let (name, item) = part
+                .split_once(':')
+                .ok_or_else(|| invalid_argument(value))?;
+            let item: Vec<char> = item.chars().collect();
+            if item
+                .iter()
+                .any(|&ch| !is_printable(ch) || char_width(ch, AmbiWidth::Single) != 1)
+            {
+                return Err(invalid_argument(value));
+            }
+            let single = match item.as_slice() {
+                [ch] => Some(*ch),
+                _ => None,
+            };
+            match name {
+                "tab" => {
+                    chars.tab = match item.as_slice() {
+                        [first, fill] => Some(ListTab {
+                            first: *first,
+                            fill: *fill,
+                            last: None,
+                        }),
+                        [first, fill, last] => Some(ListTab {
+                            first: *first,
+                            fill: *fill,
+                            last: Some(*last),
+                        }),
+                        _ => return Err(invalid_argument(value)),
+                    }
+                }
+                "eol" | "space" | "lead" | "trail" | "nbsp" | "extends" | "precedes" => {
+                    let ch = single.ok_or_else(|| invalid_argument(value))?;
+                    let field = match name {
+                        "eol" => &mut chars.eol,
+                        "space" => &mut chars.space,
+                        "lead" => &mut chars.lead,
+                        "trail" => &mut chars.trail,
+                        "nbsp" => &mut chars.nbsp,
+                        "extends" => &mut chars.extends,
+                        _ => &mut chars.precedes,
+                    };
+                    *field = Some(ch);
+                }
+                _ => return Err(invalid_argument(value)),
+            }
+        }
+        Ok(chars)
+    }
+}
+
+/// The flags of the 'display' option
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DisplayFlags {
+    /// "lastline": show as much of the last line as fits, with `@@@` at
+    /// the end of the last row
+    pub lastline: bool,
+    /// "truncate": like "lastline", with `@@@` at the start of the last
+    /// row
+    pub truncate: bool,
+    /// "uhex": show unprintable characters as `<xx>`, never `^X`
+    pub uhex: bool,
+}
+
+impl DisplayFlags {
+    /// Parse the value of 'display', comma-separated flags
+    pub fn parse(value: &str) -> VimResult<Self> {
+        let mut flags = DisplayFlags::default();
+        for flag in value.split(',').filter(|flag| !flag.is_empty()) {
+            match flag {
+                "lastline" => flags.lastline = true,
+                "truncate" => flags.truncate = true,
+                "uhex" => flags.uhex = true,
+                _ => return Err(invalid_argument(value)),
+            }
+        }
+        Ok(flags)
+    }
+}
+
+/// Parse the value of 'vartabstop', comma-separated widths
+pub fn parse_vartabstop(value: &str) -> VimResult<Vec<usize>> {
+    if value.is_empty() {
+        return Ok(Vec::new());
+    }
+    value
+        .split(',')
+        .map(|width| match width.parse::<usize>() {
+            Ok(width) if width > 0 => Ok(width),
+            _ => Err(invalid_argument(value)),
+        })
+        .collect()
+}
+
+/// What a cell shows, for highlighting it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CellKind {
+    /// A character of the text, or a blank for a tab
+    Text,
+    /// The second cell of a double-width character, drawn with it
+    Continuation,
+    /// An unprintable character as `^X` or `<xx>`, or a character from
+    /// 'listchars' for a tab or a space (SpecialKey)
+    Special,
+    /// Something that is not in the text: the 'listchars' "eol",
+    /// "extends" and "precedes", and the `>` and `@` fillers (NonText)
+    NonText,
+}
+
+/// One cell on the screen
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cell {
+    /// What is drawn, with the composing characters of the character;
+    /// empty for a continuation cell
+    pub text: String,
+    /// The byte column of the character the cell is for; the length of
+    /// the line for one after the end
+    pub col: usize,
+    /// What the cell shows
+    pub kind: CellKind,
+}
+
+impl Cell {
+    /// A cell that is not for a character of the line
+    pub fn non_text(ch: char, col: usize) -> Self {
+        Cell {
+            text: ch.to_string(),
+            col,
+            kind: CellKind::NonText,
+        }
+    }
+}
+
+/// The options that decide the cells characters take on the screen
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DisplayOptions {
+    /// 'tabstop'
+    pub tabstop: usize,
+    /// 'vartabstop': the widths of the tab stops, the last one repeated;
+    /// empty to use 'tabstop'
+    pub vartabstop: Vec<usize>,
+    /// 'list'
+    pub list: bool,
+    /// 'listchars'
+    pub listchars: ListChars,
+    /// 'display'
+    pub display: DisplayFlags,
+    /// 'ambiwidth'
+    pub ambiwidth: AmbiWidth,
+}
+
+impl Default for DisplayOptions {
+    fn default() -> Self {
+        DisplayOptions {
+            tabstop: 8,
+            vartabstop: Vec::new(),
+            list: false,
+            listchars: ListChars::default(),
+            display: DisplayFlags::default(),
+            ambiwidth: AmbiWidth::Single,
+        }
+    }
+}
+
+impl DisplayOptions {
+    /// The cells of a tab at virtual column `vcol`, up to the next tab stop
+    pub fn tab_width(&self, vcol: usize) -> usize {
+        let Some(&last) = self.vartabstop.last() else {
+            let tabstop = self.tabstop.max(1);
+            return tabstop - vcol % tabstop;
+        };
+        let mut stop = 0;
+        for &width in &self.vartabstop {
+            stop += width;
+            if vcol < stop {
+                return stop - vcol;
+            }
+        }
+        last - (vcol - stop) % last
+    }
+
+    /// Whether a tab is shown as `^I`: with 'list' and no "tab" in
+    /// 'listchars'
+    fn tab_as_control(&self) -> bool {
+        self.list && self.listchars.tab.is_none()
+    }
+
+    /// How the unprintable `ch` is shown: `^X` for a control character,
+    /// `<xx>` or `<xxxx>` for others and with "uhex"
+    pub fn transchar(&self, ch: char) -> String {
+        let code = ch as u32;
+        match code {
+            0..=0x1f | 0x7f if !self.display.uhex => {
+                format!("^{}", char::from((code ^ 0x40) as u8))
+            }
+            0..=0xff => format!("<{:02x}>", code),
+            _ => format!("<{:04x}>", code),
+        }
+    }
+
+    /// The cells `ch` takes at virtual column `vcol`
+    pub fn char_cells(&self, ch: char, vcol: usize) -> usize {
+        match ch {
+            '\t' if !self.tab_as_control() => self.tab_width(vcol),
+            _ if !is_printable(ch) => self.transchar(ch).len(),
+            _ => char_width(ch, self.ambiwidth),
+        }
+    }
+
+    /// The cells `text` takes when it starts at virtual column `vcol`
+    pub fn text_width(&self, text: &str, vcol: usize) -> usize {
+        text.chars()
+            .fold(vcol, |vcol, ch| vcol + self.char_cells(ch, vcol))
+            - vcol
+    }
+
+    /// The first and last virtual column of the character at byte `col`
+    /// of `line`.  Past the end of the line both are its width.
+    pub fn virtcol(&self, line: &str, col: usize) -> (usize, usize) {
+        let mut vcol = 0;
+        for (idx, ch) in line.char_indices() {
+            let cells = self.char_cells(ch, vcol);
+            if idx + ch.len_utf8() > col {
+                return (vcol, vcol + cells.max(1) - 1);
+            }
+            vcol += cells;
+        }
+        (vcol, vcol)
+    }
+
+    /// The byte column of the character on virtual column `vcol` of
+    /// `line`; the length of the line past its end
+    pub fn vcol_to_col(&self, line: &str, vcol: usize) -> usize {
+        let mut start = 0;
+        for (idx, ch) in line.char_indices() {
+            let cells = self.char_cells(ch, start);
+            if cells > 0 && vcol < start + cells {
+                return idx;
+            }
+            start += cells;
+        }
+        line.len()
+    }
+
+    /// The virtual column the cursor is shown on when on byte `col` of
+    /// `line`: the last cell of a tab in Normal mode without 'list', the
+    /// first cell of the character otherwise
+    pub fn cursor_vcol(&self, line: &str, col: usize, normal: bool) -> usize {
+        let (start, end) = self.virtcol(line, col);
+        let on_tab = line.get(col..).is_some_and(|rest| rest.starts_with('\t'));
+        if on_tab && normal && !self.list {
+            end
+        } else {
+            start
+        }
+    }
+
+    /// The cells of `line`, one for each virtual column, with the
+    /// 'listchars' "eol" after them
+    pub fn line_cells(&self, line: &str) -> Vec<Cell> {
+        let lead_end = line.len() - line.trim_start_matches(' ').len();
+        let trail_start = line.trim_end_matches(' ').len();
+        let lcs = &self.listchars;
+        let mut cells: Vec<Cell> = Vec::new();
+        // The cell of the last character, for its composing characters
+        let mut base: Option<usize> = None;
+        for (col, ch) in line.char_indices() {
+            let vcol = cells.len();
+            let cell = |text: String, kind: CellKind| Cell { text, col, kind };
+            if is_composing(ch) {
+                match base {
+                    Some(idx) => cells[idx].text.push(ch),
+                    None => cells.push(cell(format!(" {}", ch), CellKind::Text)),
+                }
+                continue;
+            }
+            base = Some(vcol);
+            match ch {
+                '\t' if !self.tab_as_control() => {
+                    let width = self.tab_width(vcol);
+                    for idx in 0..width {
+                        let shown = match (self.list, lcs.tab) {
+                            (true, Some(tab)) => Some(match tab.last {
+                                Some(last) if idx == width - 1 => last,
+                                _ if idx == 0 => tab.first,
+                                _ => tab.fill,
+                            }),
+                            _ => None,
+                        };
+                        cells.push(match shown {
+                            Some(shown) => cell(shown.to_string(), CellKind::Special),
+                            None => cell(" ".to_string(), CellKind::Text),
+                        });
+                    }
+                }
+                _ if !is_printable(ch) => {
+                    for shown in self.transchar(ch).chars() {
+                        cells.push(cell(shown.to_string(), CellKind::Special));
+                    }
+                }
+                ' ' | '\u{a0}' | '\u{202f}' if self.list => {
+                    let shown = match ch {
+                        ' ' if col >= trail_start && lcs.trail.is_some() => lcs.trail,
+                        ' ' if col < lead_end && lcs.lead.is_some() => lcs.lead,
+                        ' ' => lcs.space,
+                        _ => lcs.nbsp,
+                    };
+                    cells.push(match shown {
+                        Some(shown) => cell(shown.to_string(), CellKind::Special),
+                        None => cell(ch.to_string(), CellKind::Text),
+                    });
+                }
+                _ => {
+                    cells.push(cell(ch.to_string(), CellKind::Text));
+                    if char_width(ch, self.ambiwidth) == 2 {
+                        cells.push(cell(String::new(), CellKind::Continuation));
+                    }
+                }
+            }
+        }
+        if let (true, Some(eol)) = (self.list, lcs.eol) {
+            cells.push(Cell::non_text(eol, line.len()));
+        }
+        cells
+    }
+
+    /// The cells of a line shown from virtual column `leftcol` in a window
+    /// `width` cells wide without 'wrap'.  With 'list' the "precedes" and
+    /// "extends" of 'listchars' show that the line goes on.
+    pub fn visible_cells(&self, cells: &[Cell], leftcol: usize, width: usize) -> Vec<Cell> {
+        let end = cells.len().min(leftcol + width);
+        let mut visible: Vec<Cell> = cells.get(leftcol..end).unwrap_or_default().to_vec();
+        // Half of a double-width character is not drawn
+        if let Some(first) = visible.first_mut() {
+            if first.kind == CellKind::Continuation {
+                *first = Cell::non_text('<', first.col);
+            }
+        }
+        let cut = cells
+            .get(end)
+            .is_some_and(|next| next.kind == CellKind::Continuation);
+        if let (true, Some(last)) = (cut, visible.last_mut()) {
+            *last = Cell::non_text('>', last.col);
+        }
+        if !self.list {
+            return visible;
+        }
+        if let (Some(precedes), true) = (self.listchars.precedes, leftcol > 0) {
+            if let Some(first) = visible.first_mut() {
+                *first = Cell::non_text(precedes, first.col);
+            }
+        }
+        if let (Some(extends), true) = (self.listchars.extends, cells.len() > end) {
+            if let Some(last) = visible.last_mut() {
+                *last = Cell::non_text(extends, last.col);
+            }
+        }
+        visible
+    }
+
+    /// The rows shown of a line with `rows` when only `height` rows of a
+    /// window `width` cells wide are left for it.  Without "lastline" or
+    /// "truncate" in 'display' they are `@` rows.
+    pub fn partial_rows(&self, rows: &[Vec<Cell>], height: usize, width: usize) -> Vec<Vec<Cell>> {
+        let col = rows
+            .first()
+            .and_then(|row| row.first())
+            .map_or(0, |cell| cell.col);
+        let at = |count: usize| -> Vec<Cell> {
+            (0..count.min(width))
+                .map(|_| Cell::non_text('@', col))
+                .collect()
+        };
+        if height == 0 {
+            return Vec::new();
+        }
+        if self.display.truncate {
+            let mut shown = rows[..(height - 1).min(rows.len())].to_vec();
+            shown.push(at(3));
+            return shown;
+        }
+        if !self.display.lastline {
+            return (0..height).map(|_| at(1)).collect();
+        }
+        let mut shown = rows[..height.min(rows.len())].to_vec();
+        if let Some(last) = shown.last_mut() {
+            while last.len() < width {
+                last.push(Cell {
+                    text: " ".to_string(),
+                    col,
+                    kind: CellKind::Text,
+                });
+            }
+            let keep = width.saturating_sub(3);
+            // A double-width character cut in half is not drawn
+            let keep = match last.get(keep) {
+                Some(cell) if cell.kind == CellKind::Continuation => keep - 1,
+                _ => keep,
+            };
+            last.truncate(keep);
+            last.extend(at(width - keep));
+        }
+        shown
+    }
+}
+
+/// Split the cells of a line into rows of `width` cells.  A double-width
+/// character that does not fit at the end of a row starts the next one,
+/// leaving a `>` cell.  An empty line has one empty row.
+pub fn wrap_cells(cells: &[Cell], width: usize) -> Vec<Vec<Cell>> {
+    let width = width.max(2);
+    let mut rows = vec![Vec::new()];
+    let mut idx = 0;
+    while idx < cells.len() {
+        let row = rows.last_mut().expect("a row");
+        if row.len() == width {
+            rows.push(Vec::new());
+            continue;
+        }
+        let cell = &cells[idx];
+        let wide = cells
+            .get(idx + 1)
+            .is_some_and(|next| next.kind == CellKind::Continuation);
+        if wide && row.len() + 1 == width {
+            row.push(Cell::non_text('>', cell.col));
+            continue;
+        }
+        row.push(cell.clone());
+        idx += 1;
+    }
+    rows
+}
+
+/// Whether `ch` is shown as itself, not as `^X` or `<xx>`
+pub fn is_printable(ch: char) -> bool {
+    let code = ch as u32;
+    !(code < 0x20 || (0x7f..=0x9f).contains(&code) || in_table(code, UNPRINTABLE))
+}
+
+/// The cells the printable `ch` takes: none for a composing character, two
+/// for a double-width one
+pub fn char_width(ch: char, ambiwidth: AmbiWidth) -> usize {
+    let code = ch as u32;
+    if code < 0x100 && !(ambiwidth == AmbiWidth::Double && in_table(code, AMBIGUOUS)) {
+        return 1;
+    }
+    if is_composing(ch) {
+        0
+    } else if in_table(code, DOUBLE_WIDTH)
+        || (ambiwidth == AmbiWidth::Double && in_table(code, AMBIGUOUS))
+    {
+        2
+    } else {
+        1
+    }
+}
+
+/// Whether `ch` is a composing character, drawn on the one before it
+pub fn is_composing(ch: char) -> bool {
+    matches!(
+        ch as u32,
+        0x0300..=0x036f | 0x1ab0..=0x1aff | 0x1dc0..=0x1dff | 0x20d0..=0x20ff | 0xfe20..=0xfe2f
+    )
+}
+
+fn in_table(code: u32, table: &[(u32, u32)]) -> bool {
+    table
+        .binary_search_by(|&(first, last)| {
+            if last < code {
+                std::cmp::Ordering::Less
+            } else if first > code {
+                std::cmp::Ordering::Greater
+            } else {
+                std::cmp::Ordering::Equal
+            }
+        })
+        .is_ok()
+}
+
+fn invalid_argument(value: &str) -> VimError {
+    VimError::Error(474, format!("Invalid argument: {}", value))
+}
+
+/// Characters shown as `<xxxx>`: formatting characters and noncharacters
+const UNPRINTABLE: &[(u32, u32)] = &[
+    (0x070f, 0x070f),
+    (0x180b, 0x180e),
+    (0x200b, 0x200f),
+    (0x202a, 0x202e),
+    (0x2060, 0x206f),
+    (0xd800, 0xdfff),
+    (0xfeff, 0xfeff),
+    (0xfff9, 0xfffb),
+    (0xfffe, 0xffff),
+];
+
+/// East Asian wide and fullwidth characters, and emoji
+#[rustfmt::skip]
+const DOUBLE_WIDTH: &[(u32, u32)] = &[
+    (0x1100, 0x115f), (0x231a, 0x231b), (0x2329, 0x232a), (0x23e9, 0x23ec),
+    (0x23f0, 0x23f0), (0x23f3, 0x23f3), (0x25fd, 0x25fe), (0x2614, 0x2615),
+    (0x2648, 0x2653), (0x267f, 0x267f), (0x2693, 0x2693), (0x26a1, 0x26a1),
+    (0x26aa, 0x26ab), (0x26bd, 0x26be), (0x26c4, 0x26c5), (0x26ce, 0x26ce),
+    (0x26d4, 0x26d4), (0x26ea, 0x26ea), (0x26f2, 0x26f3), (0x26f5, 0x26f5),
+    (0x26fa, 0x26fa), (0x26fd, 0x26fd), (0x2705, 0x2705), (0x270a, 0x270b),
+    (0x2728, 0x2728), (0x274c, 0x274c), (0x274e, 0x274e), (0x2753, 0x2755),
+    (0x2757, 0x2757), (0x2795, 0x2797), (0x27b0, 0x27b0), (0x27bf, 0x27bf),
+    (0x2b1b, 0x2b1c), (0x2b50, 0x2b50), (0x2b55, 0x2b55), (0x2e80, 0x303e),
+    (0x3041, 0x33ff), (0x3400, 0x4dbf), (0x4e00, 0x9fff), (0xa000, 0xa4cf),
+    (0xa960, 0xa97f), (0xac00, 0xd7a3), (0xf900, 0xfaff), (0xfe10, 0xfe19),
+    (0xfe30, 0xfe6f), (0xff00, 0xff60), (0xffe0, 0xffe6), (0x16fe0, 0x16fe4),
+    (0x17000, 0x18aff), (0x1b000, 0x1b2ff), (0x1f004, 0x1f004), (0x1f0cf, 0x1f0cf),
+    (0x1f18e, 0x1f18e), (0x1f191, 0x1f19a), (0x1f200, 0x1f202), (0x1f210, 0x1f23b),
+    (0x1f240, 0x1f248), (0x1f250, 0x1f251), (0x1f260, 0x1f265), (0x1f300, 0x1f64f),
+    (0x1f680, 0x1f6ff), (0x1f900, 0x1f9ff), (0x1fa70, 0x1faff), (0x20000, 0x2fffd),
+    (0x30000, 0x3fffd),
+];
+
+/// East Asian ambiguous characters, two cells with 'ambiwidth' "double"
+#[rustfmt::skip]
+const AMBIGUOUS: &[(u32, u32)] = &[
+    (0x00a1, 0x00a1), (0x00a4, 0x00a4), (0x00a7, 0x00a8), (0x00aa, 0x00aa),
+    (0x00ad, 0x00ae), (0x00b0, 0x00b4), (0x00b6, 0x00ba), (0x00bc, 0x00bf),
+    (0x00c6, 0x00c6), (0x00d0, 0x00d0), (0x00d7, 0x00d8), (0x00de, 0x00e1),
+    (0x00e6, 0x00e6), (0x00e8, 0x00ea), (0x00ec, 0x00ed), (0x00f0, 0x00f0),
+    (0x00f2, 0x00f3), (0x00f7, 0x00fa), (0x00fc, 0x00fc), (0x00fe, 0x00fe),
+    (0x0101, 0x0101), (0x0111, 0x0111), (0x0113, 0x0113), (0x011b, 0x011b),
+    (0x0126, 0x0127), (0x012b, 0x012b), (0x0131, 0x0133), (0x0138, 0x0138),
+    (0x013f, 0x0142), (0x0144, 0x0144), (0x0148, 0x014b), (0x014d, 0x014d),
+    (0x0152, 0x0153), (0x0166, 0x0167), (0x016b, 0x016b), (0x01ce, 0x01ce),
+    (0x01d0, 0x01d0), (0x01d2, 0x01d2), (0x01d4, 0x01d4), (0x01d6, 0x01d6),
+    (0x01d8, 0x01d8), (0x01da, 0x01da), (0x01dc, 0x01dc), (0x0251, 0x0251),
+    (0x0261, 0x0261), (0x02c4, 0x02c4), (0x02c7, 0x02c7), (0x02c9, 0x02cb),
+    (0x02cd, 0x02cd), (0x02d0, 0x02d0), (0x02d8, 0x02db), (0x02dd, 0x02dd),
+    (0x02df, 0x02df), (0x0391, 0x03a1), (0x03a3, 0x03a9), (0x03b1, 0x03c1),
+    (0x03c3, 0x03c9), (0x0401, 0x0401), (0x0410, 0x044f), (0x0451, 0x0451),
+    (0x2010, 0x2010), (0x2013, 0x2016), (0x2018, 0x2019), (0x201c, 0x201d),
+    (0x2020, 0x2022), (0x2024, 0x2027), (0x2030, 0x2030), (0x2032, 0x2033),
+    (0x2035, 0x2035), (0x203b, 0x203b), (0x203e, 0x203e), (0x2074, 0x2074),
+    (0x207f, 0x207f), (0x2081, 0x2084), (0x20ac, 0x20ac), (0x2103, 0x2103),
+    (0x2105, 0x2105), (0x2109, 0x2109), (0x2113, 0x2113), (0x2116, 0x2116),
+    (0x2121, 0x2122), (0x2126, 0x2126), (0x212b, 0x212b), (0x2153, 0x2154),
+    (0x215b, 0x215e), (0x2160, 0x216b), (0x2170, 0x2179), (0x2189, 0x2189),
+    (0x2190, 0x2199), (0x21b8, 0x21b9), (0x21d2, 0x21d2), (0x21d4, 0x21d4),
+    (0x21e7, 0x21e7), (0x2200, 0x2200), (0x2202, 0x2203), (0x2207, 0x2208),
+    (0x220b, 0x220b), (0x220f, 0x220f), (0x2211, 0x2211), (0x2215, 0x2215),
+    (0x221a, 0x221a), (0x221d, 0x2220), (0x2223, 0x2223), (0x2225, 0x2225),
+    (0x2227, 0x222c), (0x222e, 0x222e), (0x2234, 0x2237), (0x223c, 0x223d),
+    (0x2248, 0x2248), (0x224c, 0x224c), (0x2252, 0x2252), (0x2260, 0x2261),
+    (0x2264, 0x2267), (0x226a, 0x226b), (0x226e, 0x226f), (0x2282, 0x2283),
+    (0x2286, 0x2287), (0x2295, 0x2295), (0x2299, 0x2299), (0x22a5, 0x22a5),
+    (0x22bf, 0x22bf), (0x2312, 0x2312), (0x2460, 0x24e9), (0x24eb, 0x254b),
+    (0x2550, 0x2573), (0x2580, 0x258f), (0x2592, 0x2595), (0x25a0, 0x25a1),
+    (0x25a3, 0x25a9), (0x25b2, 0x25b3), (0x25b6, 0x25b7), (0x25bc, 0x25bd),
+    (0x25c0, 0x25c1), (0x25c6, 0x25c8), (0x25cb, 0x25cb), (0x25ce, 0x25d1),
+    (0x25e2, 0x25e5), (0x25ef, 0x25ef), (0x2605, 0x2606), (0x2609, 0x2609),
+    (0x260e, 0x260f), (0x261c, 0x261c), (0x261e, 0x261e), (0x2640, 0x2640),
+    (0x2642, 0x2642), (0x2660, 0x2661), (0x2663, 0x2665), (0x2667, 0x266a),
+    (0x266c, 0x266d), (0x266f, 0x266f), (0x269e, 0x269f), (0x26bf, 0x26bf),
+    (0x26c6, 0x26cd), (0x26cf, 0x26d3), (0x26d5, 0x26e1), (0x26e3, 0x26e3),
+    (0x26e8, 0x26e9), (0x26eb, 0x26f1), (0x26f4, 0x26f4), (0x26f6, 0x26f9),
+    (0x26fb, 0x26fc), (0x26fe, 0x26ff), (0x273d, 0x273d), (0x2776, 0x277f),
+    (0x2b56, 0x2b59), (0x3248, 0x324f), (0xe000, 0xf8ff), (0xfffd, 0xfffd),
+    (0xf0000, 0xffffd), (0x100000, 0x10fffd),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(cells: &[Cell]) -> String {
+        cells.iter().map(|cell| cell.text.as_str()).collect()
+    }
+
+    #[test]
+    fn test_tab_stops() {
+        let opts = DisplayOptions::default();
+        assert_eq!(opts.tab_width(0), 8);
+        assert_eq!(opts.tab_width(5), 3);
+        let opts = DisplayOptions {
+            vartabstop: vec![4, 2, 8],
+            ..DisplayOptions::default()
+        };
+        assert_eq!(opts.tab_width(0), 4);
+        assert_eq!(opts.tab_width(4), 2);
+        assert_eq!(opts.tab_width(7), 7);
+        assert_eq!(opts.tab_width(14), 8);
+        assert_eq!(opts.tab_width(15), 7);
+    }
+
+    #[test]
+    fn test_virtcol() {
+        let opts = DisplayOptions::default();
+        let line = "a\tb漢\u{1}c";
+        assert_eq!(opts.virtcol(line, 0), (0, 0));
+        assert_eq!(opts.virtcol(line, 1), (1, 7));
+        assert_eq!(opts.virtcol(line, 2), (8, 8));
+        assert_eq!(opts.virtcol(line, 3), (9, 10));
+        assert_eq!(opts.virtcol(line, 6), (11, 12));
+        assert_eq!(opts.virtcol(line, 7), (13, 13));
+        assert_eq!(opts.virtcol(line, 8), (14, 14));
+        assert_eq!(opts.vcol_to_col(line, 5), 1);
+        assert_eq!(opts.vcol_to_col(line, 10), 3);
+        assert_eq!(opts.vcol_to_col(line, 20), 8);
+        assert_eq!(opts.text_width(line, 0), 14);
+    }
+
+    #[test]
+    fn test_cursor_vcol() {
+        let opts = DisplayOptions::default();
+        assert_eq!(opts.cursor_vcol("\tx", 0, true), 7);
+        assert_eq!(opts.cursor_vcol("\tx", 0, false), 0);
+        let opts = DisplayOptions {
+            list: true,
+            ..DisplayOptions::default()
+        };
+        assert_eq!(opts.cursor_vcol("\tx", 0, true), 0);
+        assert_eq!(opts.cursor_vcol("\tx", 1, true), 2);
+    }
+
+    #[test]
+    fn test_unprintable() {
+        let opts = DisplayOptions::default();
+        assert_eq!(
+            texts(&opts.line_cells("\u{0}\u{1b}\u{7f}\u{85}\u{feff}")),
+            "^@^[^?<85><feff>"
+        );
+        let opts = DisplayOptions {
+            display: DisplayFlags::parse("uhex").unwrap(),
+            ..DisplayOptions::default()
+        };
+        assert_eq!(texts(&opts.line_cells("\u{1}")), "<01>");
+    }
+
+    #[test]
+    fn test_list() {
+        let opts = DisplayOptions {
+            list: true,
+            listchars: ListChars::parse("tab:>-,trail:~,lead:.,nbsp:+,eol:$").unwrap(),
+            ..DisplayOptions::default()
+        };
+        assert_eq!(texts(&opts.line_cells("  a\tb\u{a0}c  ")), "..a>----b+c~~$");
+        // Without "tab" a tab is ^I
+        let opts = DisplayOptions {
+            list: true,
+            ..DisplayOptions::default()
+        };
+        assert_eq!(texts(&opts.line_cells("\ta ")), "^Ia $");
+        let opts = DisplayOptions {
+            list: true,
+            listchars: ListChars::parse("tab:<->").unwrap(),
+            ..DisplayOptions::default()
+        };
+        assert_eq!(texts(&opts.line_cells("abcdefg\tx\t")), "abcdefg>x<----->");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(ListChars::parse("tab:x").is_err());
+        assert!(ListChars::parse("eol:ab").is_err());
+        assert!(ListChars::parse("eol:漢").is_err());
+        assert!(ListChars::parse("foo:x").is_err());
+        assert_eq!(ListChars::parse("").unwrap(), ListChars::none());
+        assert!(DisplayFlags::parse("lastline,bogus").is_err());
+        assert!(parse_vartabstop("4,0").is_err());
+        assert_eq!(parse_vartabstop("4,8").unwrap(), vec![4, 8]);
+        assert!(AmbiWidth::parse("wide").is_err());
+    }
+
+    #[test]
+    fn test_wide_and_ambiguous() {
+        assert_eq!(char_width('漢', AmbiWidth::Single), 2);
+        assert_eq!(char_width('ｱ', AmbiWidth::Single), 1);
+        assert_eq!(char_width('é', AmbiWidth::Single), 1);
+        assert_eq!(char_width('\u{301}', AmbiWidth::Single), 0);
+        assert_eq!(char_width('→', AmbiWidth::Single), 1);
+        assert_eq!(char_width('→', AmbiWidth::Double), 2);
+        let opts = DisplayOptions::default();
+        let cells = opts.line_cells("e\u{301}漢");
+        assert_eq!(cells.len(), 3);
+        assert_eq!(cells[0].text, "e\u{301}");
+        assert_eq!(cells[2].kind, CellKind::Continuation);
+    }
+
+    #[test]
+    fn test_wrap_cells() {
+        let opts = DisplayOptions::default();
+        let rows = wrap_cells(&opts.line_cells("abc漢字"), 4);
+        let rows: Vec<String> = rows.iter().map(|row| texts(row)).collect();
+        assert_eq!(rows, ["abc>", "漢字"]);
+        assert_eq!(wrap_cells(&[], 4).len(), 1);
+    }
+
+    #[test]
+    fn test_visible_cells() {
+        let opts = DisplayOptions {
+            list: true,
+            listchars: ListChars::parse("extends:>,precedes:<").unwrap(),
+            ..DisplayOptions::default()
+        };
+        let cells = opts.line_cells("abcdefgh");
+        assert_eq!(texts(&opts.visible_cells(&cells, 0, 4)), "abc>");
+        assert_eq!(texts(&opts.visible_cells(&cells, 2, 4)), "<de>");
+        assert_eq!(texts(&opts.visible_cells(&cells, 4, 4)), "<fgh");
+    }
+
+    #[test]
+    fn test_partial_rows() {
+        let mut opts = DisplayOptions::default();
+        let rows = wrap_cells(&opts.line_cells("abcdefghij"), 4);
+        let shown = |opts: &DisplayOptions| -> Vec<String> {
+            opts.partial_rows(&rows, 2, 4)
+                .iter()
+                .map(|row| texts(row))
+                .collect()
+        };
+        assert_eq!(shown(&opts), ["@", "@"]);
+        opts.display = DisplayFlags::parse("lastline").unwrap();
+        assert_eq!(shown(&opts), ["abcd", "e@@@"]);
+        opts.display = DisplayFlags::parse("truncate").unwrap();
+        assert_eq!(shown(&opts), ["abcd", "@@@"]);
+    }
+}
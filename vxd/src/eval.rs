@@ -0,0 +1,2059 @@
+//! Expressions.
+//!
+//! An expression is parsed into an [`Expr`] tree and evaluated to a
+//! [`Value`]: a Number, Float, String, List, Dictionary or Funcref.  Lists and
+//! Dictionaries are shared, so that `is` can tell whether two values are
+//! the same List and a function changing a List changes it for every
+//! value referring to it.
+//!
+//! What an expression sees of the editor, options (`&tabstop`),
+//! registers (`@a`), environment variables (`$HOME`), variables and
+//! functions such as `line()`, comes through an [`EvalContext`].  The
+//! functions that only work on values are in [`crate::evalfunc`].
+//!
+//! # Key Behavioral Contracts
+//!
+//! - A String used as a Number is converted from its leading digits
+//!   (`"12abc" + 1` is 13, `"abc" == 0` is true); a Float, List or
+//!   Dictionary is never converted, that is an error
+//! - `.` and `..` concatenate as Strings, `+` adds as Numbers, or joins
+//!   two Lists
+//! - `==`, `=~` and the other comparisons follow 'ignorecase' for
+//!   Strings; with `#` appended they match case, with `?` they ignore it
+//! - Indexing a String gives a byte; an index past the end gives an empty
+//!   String, but is an error for a List
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::rc::Rc;
+
+use crate::evalfunc;
+use crate::types::{VimError, VimResult};
+
+// ============================================================================
+// Values
+// ============================================================================
+
+/// A List, shared by the values referring to it
+pub type List = Rc<RefCell<Vec<Value>>>;
+
+/// A Dictionary, shared by the values referring to it
+pub type Dict = Rc<RefCell<BTreeMap<String, Value>>>;
+
+/// The value of an expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// A 64 bit signed Number
+    Number(i64),
+    /// A Float
+    Float(f64),
+    /// A String of bytes
+    String(String),
+    /// A List of values
+    List(List),
+    /// A Dictionary from String keys to values
+    Dict(Dict),
+    /// A reference to the function with this name
+    Funcref(String),
+}
+
+/// Nesting past which a List or Dictionary is shown as `[...]` or
+/// `{...}`, as it may contain itself
+const MAX_DISPLAY_DEPTH: usize = 100;
+
+impl Value {
+    /// A String value
+    pub fn string(text: impl Into<String>) -> Value {
+        Value::String(text.into())
+    }
+
+    /// A new List holding `items`
+    pub fn list(items: Vec<Value>) -> Value {
+        Value::List(Rc::new(RefCell::new(items)))
+    }
+
+    /// A new Dictionary holding `items`
+    pub fn dict(items: BTreeMap<String, Value>) -> Value {
+        Value::Dict(Rc::new(RefCell::new(items)))
+    }
+
+    /// A new List of Strings
+    pub fn string_list<S: Into<String>>(items: impl IntoIterator<Item = S>) -> Value {
+        Value::list(items.into_iter().map(Value::string).collect())
+    }
+
+    /// The Number 1 for true and 0 for false
+    pub fn bool(value: bool) -> Value {
+        Value::Number(value as i64)
+    }
+
+    /// The number `type()` gives: 0 for a Number, 1 for a String, 2 for
+    /// a Funcref, 3 for a List, 4 for a Dictionary and 5 for a Float
+    pub fn type_nr(&self) -> i64 {
+        match self {
+            Value::Number(_) => 0,
+            Value::String(_) => 1,
+            Value::Funcref(_) => 2,
+            Value::List(_) => 3,
+            Value::Dict(_) => 4,
+            Value::Float(_) => 5,
+        }
+    }
+
+    /// The value used as a Number.  A String is converted from its
+    /// leading digits; other types are an error.
+    pub fn as_number(&self) -> VimResult<i64> {
+        match self {
+            Value::Number(n) => Ok(*n),
+            Value::String(s) => Ok(str2nr(s, 0)),
+            Value::Float(_) => Err(VimError::Error(
+                805,
+                "Using a Float as a Number".to_string(),
+            )),
+            Value::List(_) => Err(VimError::Error(745, "Using a List as a Number".to_string())),
+            Value::Dict(_) => Err(VimError::Error(
+                728,
+                "Using a Dictionary as a Number".to_string(),
+            )),
+            Value::Funcref(_) => Err(VimError::Error(
+                703,
+                "Using a Funcref as a Number".to_string(),
+            )),
+        }
+    }
+
+    /// The value used as a Float: a Number or String is converted
+    pub fn as_float(&self) -> VimResult<f64> {
+        match self {
+            Value::Float(f) => Ok(*f),
+            other => other.as_number().map(|n| n as f64),
+        }
+    }
+
+    /// The value used as a String.  A Number is converted to decimal;
+    /// other types are an error.
+    pub fn as_string(&self) -> VimResult<String> {
+        match self {
+            Value::String(s) => Ok(s.clone()),
+            Value::Number(n) => Ok(n.to_string()),
+            Value::Float(_) => Err(VimError::Error(
+                806,
+                "Using a Float as a String".to_string(),
+            )),
+            Value::List(_) => Err(VimError::Error(730, "Using a List as a String".to_string())),
+            Value::Dict(_) => Err(VimError::Error(
+                731,
+                "Using a Dictionary as a String".to_string(),
+            )),
+            Value::Funcref(_) => Err(VimError::Error(
+                729,
+                "Using Funcref as a String".to_string(),
+            )),
+        }
+    }
+
+    /// Whether the value is true in `:if` or `&&`: a non-zero Number
+    pub fn is_true(&self) -> VimResult<bool> {
+        self.as_number().map(|n| n != 0)
+    }
+
+    /// The value as `:echo` shows it: a String as it is, a Funcref as
+    /// its name, other values as `string()` gives them
+    pub fn to_display(&self) -> String {
+        match self {
+            Value::String(s) | Value::Funcref(s) => s.clone(),
+            other => other.to_repr(),
+        }
+    }
+
+    /// The value as `string()` gives it, which reads back as the same
+    /// value: `'it''s'`, `[1, 'a']`, `{'key': 2.5}`
+    pub fn to_repr(&self) -> String {
+        let mut out = String::new();
+        self.write_repr(&mut out, 0);
+        out
+    }
+
+    fn write_repr(&self, out: &mut String, depth: usize) {
+        match self {
+            Value::Number(n) => out.push_str(&n.to_string()),
+            Value::Float(f) => out.push_str(&format_float(*f)),
+            Value::String(s) => {
+                out.push('\'');
+                out.push_str(&s.replace('\'', "''"));
+                out.push('\'');
+            }
+            Value::List(_) if depth > MAX_DISPLAY_DEPTH => out.push_str("[...]"),
+            Value::List(list) => {
+                out.push('[');
+                for (idx, item) in list.borrow().iter().enumerate() {
+                    if idx > 0 {
+                        out.push_str(", ");
+                    }
+                    item.write_repr(out, depth + 1);
+                }
+                out.push(']');
+            }
+            Value::Dict(_) if depth > MAX_DISPLAY_DEPTH => out.push_str("{...}"),
+            Value::Dict(dict) => {
+                out.push('{');
+                for (idx, (key, item)) in dict.borrow().iter().enumerate() {
+                    if idx > 0 {
+                        out.push_str(", ");
+                    }
+                    Value::string(key.as_str()).write_repr(out, depth + 1);
+                    out.push_str(": ");
+                    item.write_repr(out, depth + 1);
+                }
+                out.push('}');
+            }
+            Value::Funcref(name) => {
+                out.push_str("function(");
+                Value::string(name.as_str()).write_repr(out, depth);
+                out.push(')');
+            }
+        }
+    }
+
+    /// A copy sharing nothing with the value, like `deepcopy()`
+    pub fn deep_copy(&self) -> Value {
+        match self {
+            Value::List(list) => Value::list(list.borrow().iter().map(Value::deep_copy).collect()),
+            Value::Dict(dict) => Value::dict(
+                dict.borrow()
+                    .iter()
+                    .map(|(key, item)| (key.clone(), item.deep_copy()))
+                    .collect(),
+            ),
+            other => other.clone(),
+        }
+    }
+
+    /// Whether the value is empty, like `empty()`
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Value::Number(n) => *n == 0,
+            Value::Float(f) => *f == 0.0,
+            Value::String(s) => s.is_empty(),
+            Value::List(list) => list.borrow().is_empty(),
+            Value::Dict(dict) => dict.borrow().is_empty(),
+            Value::Funcref(_) => false,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_display())
+    }
+}
+
+/// The Number at the start of `text`, like `str2nr()`.  With `base` 0 the
+/// base follows the prefix: `0x` for hexadecimal, `0b` for binary, `0o`
+/// or a leading zero for octal; with base 2, 8 or 16 the prefix is
+/// optional.  Text that does not start with a number gives 0.
+pub fn str2nr(text: &str, base: u32) -> i64 {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text.strip_prefix('+').unwrap_or(text)),
+    };
+    let lower = digits.get(..2).map(str::to_ascii_lowercase);
+    let (base, digits) = match (base, lower.as_deref()) {
+        (0 | 16, Some("0x")) => (16, &digits[2..]),
+        (0 | 2, Some("0b")) => (2, &digits[2..]),
+        (0 | 8, Some("0o")) => (8, &digits[2..]),
+        (0, _) if digits.starts_with('0') && octal_digits(digits) => (8, digits),
+        (0, _) => (10, digits),
+        (base, _) => (base, digits),
+    };
+    let mut value: i64 = 0;
+    for c in digits.chars() {
+        let Some(digit) = c.to_digit(base) else {
+            break;
+        };
+        value = value
+            .saturating_mul(base as i64)
+            .saturating_add(digit as i64);
+    }
+    if negative {
+        -value
+    } else {
+        value
+    }
+}
+
+/// A Number literal too big for a Number is `v:numbermax`
+fn saturate(value: u64) -> i64 {
+    i64::try_from(value).unwrap_or(i64::MAX)
+}
+
+/// Whether the digits at the start of `text` are all octal digits
+fn octal_digits(text: &str) -> bool {
+    text.chars()
+        .take_while(char::is_ascii_digit)
+        .all(|c| c < '8')
+}
+
+/// The Float at the start of `text`, like `str2float()`: `1.5`, `-1e3`,
+/// `inf` or `nan`.  Text that does not start with a number gives 0.0.
+pub fn str2float(text: &str) -> f64 {
+    let text = text.trim_start();
+    let lower = text.to_ascii_lowercase();
+    let unsigned = lower.trim_start_matches(['+', '-']);
+    let sign = if lower.starts_with('-') { -1.0 } else { 1.0 };
+    if unsigned.starts_with("inf") {
+        return sign * f64::INFINITY;
+    }
+    if unsigned.starts_with("nan") {
+        return f64::NAN;
+    }
+    let bytes = text.as_bytes();
+    let mut end = 0;
+    if matches!(bytes.first(), Some(b'+' | b'-')) {
+        end = 1;
+    }
+    let digits = |from: usize| {
+        bytes[from..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count()
+    };
+    end += digits(end);
+    if bytes.get(end) == Some(&b'.') && digits(end + 1) > 0 {
+        end += 1 + digits(end + 1);
+    }
+    if matches!(bytes.get(end), Some(b'e' | b'E')) {
+        let mut exp = end + 1;
+        if matches!(bytes.get(exp), Some(b'+' | b'-')) {
+            exp += 1;
+        }
+        if digits(exp) > 0 {
+            end = exp + digits(exp);
+        }
+    }
+    text[..end].parse().unwrap_or(0.0)
+}
+
+/// A Float as `:echo` and `string()` show it: like `printf("%g")`, always
+/// with a decimal point, and without `+` or leading zeros in the
+/// exponent (`1.0`, `0.333333`, `1.0e20`, `1.5e-5`)
+pub fn format_float(f: f64) -> String {
+    if f.is_nan() {
+        return "nan".to_string();
+    }
+    if f.is_infinite() {
+        return if f < 0.0 { "-inf" } else { "inf" }.to_string();
+    }
+    let text = format_g(f, 6, false);
+    let (mantissa, exponent) = match text.split_once('e') {
+        Some((mantissa, exponent)) => (mantissa.to_string(), Some(exponent)),
+        None => (text.clone(), None),
+    };
+    let mut out = mantissa;
+    if !out.contains('.') {
+        out.push_str(".0");
+    }
+    if let Some(exponent) = exponent {
+        let (sign, digits) = match exponent.strip_prefix('-') {
+            Some(digits) => ("-", digits),
+            None => ("", exponent.trim_start_matches('+')),
+        };
+        out.push('e');
+        out.push_str(sign);
+        out.push_str(digits.trim_start_matches('0'));
+    }
+    out
+}
+
+/// `f` in C's `%e` format with `precision` digits after the point
+pub fn format_e(f: f64, precision: usize) -> String {
+    if !f.is_finite() {
+        return format_float(f);
+    }
+    let text = format!("{:.*e}", precision, f);
+    let (mantissa, exponent) = text.split_once('e').unwrap_or((&text, "0"));
+    let (sign, digits) = match exponent.strip_prefix('-') {
+        Some(digits) => ('-', digits),
+        None => ('+', exponent),
+    };
+    format!("{}e{}{:0>2}", mantissa, sign, digits)
+}
+
+/// `f` in C's `%g` format with `precision` significant digits; with
+/// `alternate` (`%#g`) trailing zeros are kept
+pub fn format_g(f: f64, precision: usize, alternate: bool) -> String {
+    if !f.is_finite() {
+        return format_float(f);
+    }
+    let precision = precision.max(1);
+    let exponent = if f == 0.0 {
+        0
+    } else {
+        let text = format!("{:.*e}", precision - 1, f);
+        text.split_once('e')
+            .and_then(|(_, exp)| exp.parse::<i32>().ok())
+            .unwrap_or(0)
+    };
+    let mut text = if exponent < -4 || exponent >= precision as i32 {
+        format_e(f, precision - 1)
+    } else {
+        let decimals = (precision as i32 - 1 - exponent).max(0) as usize;
+        format!("{:.*}", decimals, f)
+    };
+    if !alternate {
+        let (mantissa, exp) = match text.find('e') {
+            Some(idx) => (text[..idx].to_string(), text[idx..].to_string()),
+            None => (text.clone(), String::new()),
+        };
+        let mantissa = if mantissa.contains('.') {
+            mantissa
+                .trim_end_matches('0')
+                .trim_end_matches('.')
+                .to_string()
+        } else {
+            mantissa
+        };
+        text = mantissa + &exp;
+    }
+    text
+}
+
+// ============================================================================
+// Context
+// ============================================================================
+
+/// What an expression can see of the editor
+pub trait EvalContext {
+    /// The value of option `name`: `&name`, or `&l:name` and `&g:name`
+    /// with `scope` `'l'` or `'g'`.  An unknown option is an error.
+    fn option(&self, name: &str, scope: Option<char>) -> VimResult<Value>;
+
+    /// The text of register `reg` (`@r`); a linewise register ends in a
+    /// line break
+    fn register(&mut self, reg: char) -> VimResult<String>;
+
+    /// The value of environment variable `name` (`$NAME`)
+    fn env(&self, name: &str) -> Option<String> {
+        std::env::var(name).ok()
+    }
+
+    /// The value of variable `name`, with its scope as typed (`g:x` or
+    /// `x`)
+    fn variable(&self, _name: &str) -> Option<Value> {
+        None
+    }
+
+    /// Call the editor's function `name`.  Returns `None` when the
+    /// editor has no such function, so that a builtin one is used.
+    fn call(&mut self, _name: &str, _args: &[Value]) -> Option<VimResult<Value>> {
+        None
+    }
+
+    /// Call the editor's function `name` with `dict` as `self`, for a
+    /// Funcref called as an entry of `dict`.  Returns `None` when the
+    /// editor has no such function.
+    fn call_dict(&mut self, _name: &str, _args: &[Value], _dict: &Dict) -> Option<VimResult<Value>> {
+        None
+    }
+
+    /// Whether the editor has a function `name`, for `exists('*name')`
+    fn has_function(&self, _name: &str) -> bool {
+        false
+    }
+}
+
+/// A context keeping options, registers, variables and environment
+/// variables in maps, without functions of its own
+#[derive(Debug, Clone, Default)]
+pub struct SimpleEvalContext {
+    /// Option values by full name
+    pub options: HashMap<String, Value>,
+    /// Register text by name
+    pub registers: HashMap<char, String>,
+    /// Variables by name as typed
+    pub variables: HashMap<String, Value>,
+    /// Environment variables; other ones are unset
+    pub env: HashMap<String, String>,
+}
+
+impl SimpleEvalContext {
+    /// Create a context with nothing set
+    pub fn new() -> Self {
+        SimpleEvalContext::default()
+    }
+}
+
+impl EvalContext for SimpleEvalContext {
+    fn option(&self, name: &str, _scope: Option<char>) -> VimResult<Value> {
+        self.options
+            .get(name)
+            .cloned()
+            .ok_or_else(|| unknown_option(name))
+    }
+
+    fn register(&mut self, reg: char) -> VimResult<String> {
+        Ok(self.registers.get(&reg).cloned().unwrap_or_default())
+    }
+
+    fn env(&self, name: &str) -> Option<String> {
+        self.env.get(name).cloned()
+    }
+
+    fn variable(&self, name: &str) -> Option<Value> {
+        self.variables.get(name).cloned()
+    }
+}
+
+/// The error for `&name` naming no option
+pub fn unknown_option(name: &str) -> VimError {
+    VimError::Error(113, format!("Unknown option: &{}", name))
+}
+
+/// A context for the expression of `\=` in a substitute: `submatch(0)`
+/// gives the matched text, anything else comes from `inner`
+pub struct SubmatchContext<'a> {
+    /// The context the expression is evaluated in
+    pub inner: &'a mut dyn EvalContext,
+    /// The matched text and the text of each group
+    pub submatches: Vec<String>,
+}
+
+impl EvalContext for SubmatchContext<'_> {
+    fn option(&self, name: &str, scope: Option<char>) -> VimResult<Value> {
+        self.inner.option(name, scope)
+    }
+
+    fn register(&mut self, reg: char) -> VimResult<String> {
+        self.inner.register(reg)
+    }
+
+    fn env(&self, name: &str) -> Option<String> {
+        self.inner.env(name)
+    }
+
+    fn variable(&self, name: &str) -> Option<Value> {
+        self.inner.variable(name)
+    }
+
+    fn call(&mut self, name: &str, args: &[Value]) -> Option<VimResult<Value>> {
+        if name != "submatch" {
+            return self.inner.call(name, args);
+        }
+        let submatch = || -> VimResult<Value> {
+            let [nr, ..] = args else {
+                return Err(not_enough_arguments(name));
+            };
+            let nr = nr.as_number()?;
+            if !(0..=9).contains(&nr) {
+                return Err(VimError::Error(
+                    935,
+                    format!("Invalid submatch number: {}", nr),
+                ));
+            }
+            let text = self
+                .submatches
+                .get(nr as usize)
+                .cloned()
+                .unwrap_or_default();
+            match args.get(1).map(Value::is_true).transpose()? {
+                Some(true) => Ok(Value::string_list(text.split('\n'))),
+                _ => Ok(Value::String(text)),
+            }
+        };
+        Some(submatch())
+    }
+
+    fn call_dict(&mut self, name: &str, args: &[Value], dict: &Dict) -> Option<VimResult<Value>> {
+        self.inner.call_dict(name, args, dict)
+    }
+
+    fn has_function(&self, name: &str) -> bool {
+        name == "submatch" || self.inner.has_function(name)
+    }
+}
+
+// ============================================================================
+// Expression Tree
+// ============================================================================
+
+/// An operator taking two Numbers or Floats, or two Strings for `..`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    /// `+`, also joining Lists
+    Add,
+    /// `-`
+    Sub,
+    /// `*`
+    Mul,
+    /// `/`
+    Div,
+    /// `%`
+    Mod,
+    /// `.` or `..`
+    Concat,
+}
+
+/// A comparison
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp {
+    /// `==`
+    Equal,
+    /// `!=`
+    NotEqual,
+    /// `>`
+    Greater,
+    /// `>=`
+    GreaterEqual,
+    /// `<`
+    Less,
+    /// `<=`
+    LessEqual,
+    /// `=~`: the pattern on the right matches
+    Match,
+    /// `!~`: the pattern on the right does not match
+    NoMatch,
+    /// `is`: the same List or Dictionary, or an equal value of the same
+    /// type
+    Is,
+    /// `isnot`
+    IsNot,
+}
+
+/// An operator taking one value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    /// `!`
+    Not,
+    /// `-`
+    Negate,
+    /// `+`
+    Plus,
+}
+
+/// A parsed expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// A Number constant
+    Number(i64),
+    /// A Float constant
+    Float(f64),
+    /// A String constant
+    String(String),
+    /// `[item, ...]`
+    List(Vec<Expr>),
+    /// `{key: value, ...}`
+    Dict(Vec<(Expr, Expr)>),
+    /// `&name`, `&l:name` or `&g:name`
+    Option {
+        /// Full or short option name
+        name: String,
+        /// `l` or `g`
+        scope: Option<char>,
+    },
+    /// `@r`
+    Register(char),
+    /// `$NAME`
+    Env(String),
+    /// A variable, with its scope as typed
+    Variable(String),
+    /// `name(args)`, or `first->name(args)` with `first` as the first
+    /// argument
+    Call {
+        /// Function name
+        name: String,
+        /// Arguments
+        args: Vec<Expr>,
+    },
+    /// `base[index]`
+    Index(Box<Expr>, Box<Expr>),
+    /// `base[start : end]`, with both ends included
+    Slice(Box<Expr>, Option<Box<Expr>>, Option<Box<Expr>>),
+    /// `base.key`: an entry of a Dictionary.  When `base` is not a
+    /// Dictionary this is the concatenation of `base` and variable `key`.
+    Member(Box<Expr>, String),
+    /// `base.name(args)`: a call of the Funcref entry `name` of a
+    /// Dictionary with the Dictionary as `self`.  When `base` is not a
+    /// Dictionary this is the concatenation of `base` and `name(args)`.
+    DictCall {
+        /// The Dictionary
+        base: Box<Expr>,
+        /// Key of the entry
+        name: String,
+        /// Arguments
+        args: Vec<Expr>,
+    },
+    /// `!expr`, `-expr` or `+expr`
+    Unary(UnaryOp, Box<Expr>),
+    /// `left op right`
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    /// `left op right` with `op` a comparison.  `ignore_case` is
+    /// `Some(false)` for `op#`, `Some(true)` for `op?` and `None` to
+    /// follow 'ignorecase'.
+    Compare {
+        /// The comparison
+        op: CompareOp,
+        /// Whether case is ignored
+        ignore_case: Option<bool>,
+        /// Left side
+        left: Box<Expr>,
+        /// Right side
+        right: Box<Expr>,
+    },
+    /// `left && right`
+    And(Box<Expr>, Box<Expr>),
+    /// `left || right`
+    Or(Box<Expr>, Box<Expr>),
+    /// `cond ? then : else`
+    Ternary(Box<Expr>, Box<Expr>, Box<Expr>),
+}
+
+// ============================================================================
+// Parsing
+// ============================================================================
+
+/// Parse the expression at the start of `text`.  Returns the expression
+/// and the number of bytes it takes, with the white space after it, so
+/// that a command can look at what follows.
+pub fn parse_expr(text: &str) -> VimResult<(Expr, usize)> {
+    let mut parser = Parser { text, pos: 0 };
+    parser.skip_white();
+    if parser.at_end() {
+        return Err(invalid_expression(text));
+    }
+    let expr = parser.expr1()?;
+    parser.skip_white();
+    Ok((expr, parser.pos))
+}
+
+/// Parse `text`, which must be one expression and nothing more
+pub fn parse_complete(text: &str) -> VimResult<Expr> {
+    let (expr, len) = parse_expr(text)?;
+    if len < text.len() {
+        return Err(VimError::Error(
+            15,
+            format!("Invalid expression: \"{}\"", &text[len..]),
+        ));
+    }
+    Ok(expr)
+}
+
+/// Parse and evaluate `text`, which must be one expression
+pub fn eval_expr(text: &str, ctx: &mut dyn EvalContext) -> VimResult<Value> {
+    eval(&parse_complete(text)?, ctx)
+}
+
+fn invalid_expression(text: &str) -> VimError {
+    VimError::Error(15, format!("Invalid expression: \"{}\"", text))
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn rest(&self) -> &str {
+        &self.text[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.rest()[offset.min(self.rest().len())..].chars().next()
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.text.len()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_white(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start_matches([' ', '\t']).len();
+    }
+
+    /// Skip white space and `token` when it comes next
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_white();
+        if self.rest().starts_with(token) {
+            self.pos += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn error_here(&self) -> VimError {
+        invalid_expression(self.rest())
+    }
+
+    /// `expr2 ? expr1 : expr1`
+    fn expr1(&mut self) -> VimResult<Expr> {
+        let cond = self.expr2()?;
+        if !self.eat("?") {
+            return Ok(cond);
+        }
+        let then = self.expr1()?;
+        if !self.eat(":") {
+            return Err(VimError::Error(
+                109,
+                format!("Missing ':' after '?': {}", self.rest()),
+            ));
+        }
+        let otherwise = self.expr1()?;
+        Ok(Expr::Ternary(
+            Box::new(cond),
+            Box::new(then),
+            Box::new(otherwise),
+        ))
+    }
+
+    /// `expr3 || expr3 ...`
+    fn expr2(&mut self) -> VimResult<Expr> {
+        let mut left = self.expr3()?;
+        while self.eat("||") {
+            let right = self.expr3()?;
+            left = Expr::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    /// `expr4 && expr4 ...`
+    fn expr3(&mut self) -> VimResult<Expr> {
+        let mut left = self.expr4()?;
+        while self.eat("&&") {
+            let right = self.expr4()?;
+            left = Expr::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    /// `expr5 {cmp} expr5`; comparisons do not chain
+    fn expr4(&mut self) -> VimResult<Expr> {
+        let left = self.expr5()?;
+        self.skip_white();
+        let Some((op, len)) = self.compare_op() else {
+            return Ok(left);
+        };
+        self.pos += len;
+        let ignore_case = match self.peek() {
+            Some('#') => Some(false),
+            Some('?') => Some(true),
+            _ => None,
+        };
+        if ignore_case.is_some() {
+            self.pos += 1;
+        }
+        let right = self.expr5()?;
+        Ok(Expr::Compare {
+            op,
+            ignore_case,
+            left: Box::new(left),
+            right: Box::new(right),
+        })
+    }
+
+    fn compare_op(&self) -> Option<(CompareOp, usize)> {
+        let rest = self.rest();
+        const SYMBOLS: [(&str, CompareOp); 8] = [
+            ("==", CompareOp::Equal),
+            ("!=", CompareOp::NotEqual),
+            (">=", CompareOp::GreaterEqual),
+            ("<=", CompareOp::LessEqual),
+            ("=~", CompareOp::Match),
+            ("!~", CompareOp::NoMatch),
+            (">", CompareOp::Greater),
+            ("<", CompareOp::Less),
+        ];
+        if let Some((symbol, op)) = SYMBOLS.iter().find(|(symbol, _)| rest.starts_with(symbol)) {
+            return Some((*op, symbol.len()));
+        }
+        for (word, op) in [("isnot", CompareOp::IsNot), ("is", CompareOp::Is)] {
+            let Some(after) = rest.strip_prefix(word) else {
+                continue;
+            };
+            if !after.starts_with(is_name_char) {
+                return Some((op, word.len()));
+            }
+        }
+        None
+    }
+
+    /// `expr6 + expr6 ...`, with `-`, `.` and `..`
+    fn expr5(&mut self) -> VimResult<Expr> {
+        let mut left = self.expr6()?;
+        loop {
+            self.skip_white();
+            let rest = self.rest();
+            let (op, len) = if rest.starts_with("..") {
+                (BinaryOp::Concat, 2)
+            } else if rest.starts_with('.') {
+                (BinaryOp::Concat, 1)
+            } else if rest.starts_with('+') {
+                (BinaryOp::Add, 1)
+            } else if rest.starts_with('-') && !rest.starts_with("->") {
+                (BinaryOp::Sub, 1)
+            } else {
+                return Ok(left);
+            };
+            self.pos += len;
+            let right = self.expr6()?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+    }
+
+    /// `expr7 * expr7 ...`, with `/` and `%`
+    fn expr6(&mut self) -> VimResult<Expr> {
+        let mut left = self.expr7()?;
+        loop {
+            self.skip_white();
+            let op = match self.peek() {
+                Some('*') => BinaryOp::Mul,
+                Some('/') => BinaryOp::Div,
+                Some('%') => BinaryOp::Mod,
+                _ => return Ok(left),
+            };
+            self.pos += 1;
+            let right = self.expr7()?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+    }
+
+    /// `!expr7`, `-expr7`, `+expr7` or `expr8`
+    fn expr7(&mut self) -> VimResult<Expr> {
+        self.skip_white();
+        let op = match self.peek() {
+            Some('!') => UnaryOp::Not,
+            Some('-') => UnaryOp::Negate,
+            Some('+') => UnaryOp::Plus,
+            _ => return self.expr8(),
+        };
+        self.pos += 1;
+        let operand = self.expr7()?;
+        Ok(Expr::Unary(op, Box::new(operand)))
+    }
+
+    /// `expr9` followed by `[index]`, `[start : end]`, `.key` or
+    /// `->name(args)`
+    fn expr8(&mut self) -> VimResult<Expr> {
+        let mut base = self.expr9()?;
+        loop {
+            match self.peek() {
+                Some('[') => {
+                    self.pos += 1;
+                    base = self.subscript(base)?;
+                }
+                Some('.') if self.is_member_access(&base) => {
+                    self.pos += 1;
+                    let key = self
+                        .take_while(|c| c.is_ascii_alphanumeric() || c == '_')
+                        .to_string();
+                    if self.peek() != Some('(') {
+                        base = Expr::Member(Box::new(base), key);
+                        continue;
+                    }
+                    self.pos += 1;
+                    let args = self.arguments(&key)?;
+                    base = Expr::DictCall {
+                        base: Box::new(base),
+                        name: key,
+                        args,
+                    };
+                }
+                _ => {
+                    let white = self.rest().len() - self.rest().trim_start().len();
+                    if !self.rest()[white..].starts_with("->") {
+                        return Ok(base);
+                    }
+                    self.pos += white + 2;
+                    self.skip_white();
+                    let name = self.name();
+                    if name.is_empty() || self.peek() != Some('(') {
+                        return Err(self.error_here());
+                    }
+                    self.pos += 1;
+                    let mut args = vec![base];
+                    args.extend(self.arguments(&name)?);
+                    base = Expr::Call { name, args };
+                }
+            }
+        }
+    }
+
+    /// Whether a `.` after `base` is a Dictionary entry rather than a
+    /// concatenation: it is followed by a key and `base` is not a
+    /// constant
+    fn is_member_access(&self, base: &Expr) -> bool {
+        if matches!(
+            base,
+            Expr::Number(_) | Expr::Float(_) | Expr::String(_) | Expr::List(_)
+        ) {
+            return false;
+        }
+        let key = &self.rest()[1..];
+        let len = key
+            .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+            .unwrap_or(key.len());
+        len > 0 && !key.starts_with('.')
+    }
+
+    /// After `[`: `index]` or `start : end]`
+    fn subscript(&mut self, base: Expr) -> VimResult<Expr> {
+        self.skip_white();
+        let start = match self.peek() {
+            Some(':') => None,
+            _ => Some(Box::new(self.expr1()?)),
+        };
+        if self.eat(":") {
+            self.skip_white();
+            let end = match self.peek() {
+                Some(']') => None,
+                _ => Some(Box::new(self.expr1()?)),
+            };
+            self.expect_bracket()?;
+            return Ok(Expr::Slice(Box::new(base), start, end));
+        }
+        self.expect_bracket()?;
+        match start {
+            Some(index) => Ok(Expr::Index(Box::new(base), index)),
+            None => Err(self.error_here()),
+        }
+    }
+
+    fn expect_bracket(&mut self) -> VimResult<()> {
+        if self.eat("]") {
+            Ok(())
+        } else {
+            Err(VimError::Error(111, "Missing ']'".to_string()))
+        }
+    }
+
+    /// A constant, a List or Dictionary, `&option`, `@r`, `$NAME`,
+    /// `(expr)`, a variable or a function call
+    fn expr9(&mut self) -> VimResult<Expr> {
+        self.skip_white();
+        let Some(c) = self.peek() else {
+            return Err(self.error_here());
+        };
+        match c {
+            '0'..='9' => self.number(),
+            '"' => self.double_quoted(),
+            '\'' => self.single_quoted(),
+            '[' => self.list(),
+            '{' => self.dict(false),
+            '#' if self.peek_at(1) == Some('{') => {
+                self.pos += 1;
+                self.dict(true)
+            }
+            '&' => self.option(),
+            '@' => {
+                self.pos += 1;
+                Ok(Expr::Register(self.bump().unwrap_or('"')))
+            }
+            '$' => {
+                self.pos += 1;
+                let name = self.take_while(|c| c.is_ascii_alphanumeric() || c == '_');
+                if name.is_empty() {
+                    return Err(self.error_here());
+                }
+                Ok(Expr::Env(name.to_string()))
+            }
+            '(' => {
+                self.pos += 1;
+                let expr = self.expr1()?;
+                if !self.eat(")") {
+                    return Err(VimError::Error(110, "Missing ')'".to_string()));
+                }
+                Ok(expr)
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let name = self.name();
+                if self.peek() == Some('(') {
+                    self.pos += 1;
+                    let args = self.arguments(&name)?;
+                    return Ok(Expr::Call { name, args });
+                }
+                Ok(Expr::Variable(name))
+            }
+            _ => Err(self.error_here()),
+        }
+    }
+
+    fn take_while(&mut self, pred: impl Fn(char) -> bool) -> &str {
+        let start = self.pos;
+        while self.peek().is_some_and(&pred) {
+            self.bump();
+        }
+        &self.text[start..self.pos]
+    }
+
+    /// A variable or function name, with a scope such as `g:`
+    fn name(&mut self) -> String {
+        let start = self.pos;
+        let rest = self.rest();
+        let scoped = rest.len() > 2
+            && rest.as_bytes()[1] == b':'
+            && b"gbwtlsav".contains(&rest.as_bytes()[0])
+            && rest[2..].starts_with(is_name_char);
+        if scoped {
+            self.pos += 2;
+        }
+        self.take_while(is_name_char);
+        self.text[start..self.pos].to_string()
+    }
+
+    /// After `(`: the arguments up to `)`
+    fn arguments(&mut self, name: &str) -> VimResult<Vec<Expr>> {
+        let mut args = Vec::new();
+        if self.eat(")") {
+            return Ok(args);
+        }
+        loop {
+            let arg = self.expr1().map_err(|_| {
+                VimError::Error(116, format!("Invalid arguments for function {}", name))
+            })?;
+            args.push(arg);
+            if self.eat(")") {
+                return Ok(args);
+            }
+            if !self.eat(",") {
+                return Err(VimError::Error(
+                    116,
+                    format!("Invalid arguments for function {}", name),
+                ));
+            }
+            if self.eat(")") {
+                return Ok(args);
+            }
+        }
+    }
+
+    /// A decimal, hexadecimal (`0x`), binary (`0b`) or octal (`0o` or a
+    /// leading zero) Number, or a Float such as `1.5e3`
+    fn number(&mut self) -> VimResult<Expr> {
+        let start = self.pos;
+        let rest = self.rest().to_ascii_lowercase();
+        let prefixed = |prefix: &str, radix: u32| {
+            rest.strip_prefix(prefix)
+                .filter(|digits| digits.starts_with(|c: char| c.is_digit(radix)))
+                .map(|_| radix)
+        };
+        if let Some(radix) = prefixed("0x", 16)
+            .or_else(|| prefixed("0b", 2))
+            .or_else(|| prefixed("0o", 8))
+        {
+            self.pos += 2;
+            let digits = self.take_while(|c| c.is_digit(radix));
+            let value = u64::from_str_radix(digits, radix).unwrap_or(u64::MAX);
+            return Ok(Expr::Number(saturate(value)));
+        }
+        let digits = self.take_while(|c| c.is_ascii_digit()).to_string();
+        let fraction =
+            self.peek() == Some('.') && self.peek_at(1).is_some_and(|c| c.is_ascii_digit());
+        if fraction {
+            self.pos += 1;
+            self.take_while(|c| c.is_ascii_digit());
+            if matches!(self.peek(), Some('e' | 'E')) {
+                let sign = usize::from(matches!(self.peek_at(1), Some('+' | '-')));
+                if self.peek_at(1 + sign).is_some_and(|c| c.is_ascii_digit()) {
+                    self.pos += 1 + sign;
+                    self.take_while(|c| c.is_ascii_digit());
+                }
+            }
+            let value = self.text[start..self.pos]
+                .parse()
+                .map_err(|_| self.error_here())?;
+            return Ok(Expr::Float(value));
+        }
+        if digits.len() > 1 && digits.starts_with('0') && octal_digits(&digits) {
+            let value = u64::from_str_radix(&digits, 8).unwrap_or(u64::MAX);
+            return Ok(Expr::Number(saturate(value)));
+        }
+        Ok(Expr::Number(digits.parse().unwrap_or(i64::MAX)))
+    }
+
+    /// `"text"`, with backslash escapes
+    fn double_quoted(&mut self) -> VimResult<Expr> {
+        let start = self.pos;
+        self.pos += 1;
+        let mut text = String::new();
+        loop {
+            let Some(c) = self.bump() else {
+                return Err(VimError::Error(
+                    114,
+                    format!("Missing quote: {}", &self.text[start..]),
+                ));
+            };
+            match c {
+                '"' => return Ok(Expr::String(text)),
+                '\\' => self.escape(&mut text),
+                c => text.push(c),
+            }
+        }
+    }
+
+    /// After a backslash in a double quoted String
+    fn escape(&mut self, text: &mut String) {
+        let Some(c) = self.bump() else {
+            return;
+        };
+        match c {
+            'n' => text.push('\n'),
+            't' => text.push('\t'),
+            'r' => text.push('\r'),
+            'e' => text.push('\x1b'),
+            'b' => text.push('\x08'),
+            'f' => text.push('\x0c'),
+            'x' | 'X' | 'u' | 'U' => {
+                let max = match c {
+                    'u' => 4,
+                    'U' => 8,
+                    _ => 2,
+                };
+                let digits: String = self
+                    .rest()
+                    .chars()
+                    .take(max)
+                    .take_while(char::is_ascii_hexdigit)
+                    .collect();
+                if digits.is_empty() {
+                    text.push(c);
+                    return;
+                }
+                self.pos += digits.len();
+                let code = u32::from_str_radix(&digits, 16).unwrap_or(0);
+                text.extend(char::from_u32(code));
+            }
+            '0'..='7' => {
+                let mut code = c.to_digit(8).unwrap_or(0);
+                for _ in 0..2 {
+                    match self.peek().and_then(|c| c.to_digit(8)) {
+                        Some(digit) => {
+                            code = code * 8 + digit;
+                            self.pos += 1;
+                        }
+                        None => break,
+                    }
+                }
+                text.extend(char::from_u32(code));
+            }
+            '<' => match self.rest().find('>') {
+                Some(end) if end > 0 => {
+                    let name = self.rest()[..end].to_string();
+                    self.pos += end + 1;
+                    text.push_str(&special_key(&name));
+                }
+                _ => text.push('<'),
+            },
+            c => text.push(c),
+        }
+    }
+
+    /// `'text'`, where `''` stands for a quote
+    fn single_quoted(&mut self) -> VimResult<Expr> {
+        let start = self.pos;
+        self.pos += 1;
+        let mut text = String::new();
+        loop {
+            let Some(c) = self.bump() else {
+                return Err(VimError::Error(
+                    115,
+                    format!("Missing quote: {}", &self.text[start..]),
+                ));
+            };
+            if c != '\'' {
+                text.push(c);
+            } else if self.peek() == Some('\'') {
+                self.pos += 1;
+                text.push('\'');
+            } else {
+                return Ok(Expr::String(text));
+            }
+        }
+    }
+
+    /// `[item, ...]`, a trailing comma allowed
+    fn list(&mut self) -> VimResult<Expr> {
+        let start = self.pos;
+        self.pos += 1;
+        let mut items = Vec::new();
+        loop {
+            if self.eat("]") {
+                return Ok(Expr::List(items));
+            }
+            if self.at_end() {
+                break;
+            }
+            items.push(self.expr1()?);
+            if self.eat("]") {
+                return Ok(Expr::List(items));
+            }
+            if !self.eat(",") {
+                if self.at_end() {
+                    break;
+                }
+                return Err(VimError::Error(
+                    696,
+                    format!("Missing comma in List: {}", self.rest()),
+                ));
+            }
+        }
+        Err(VimError::Error(
+            697,
+            format!("Missing end of List ']': {}", &self.text[start..]),
+        ))
+    }
+
+    /// `{key: value, ...}`, or with `literal` `#{key: value}` where the
+    /// keys are not expressions
+    fn dict(&mut self, literal: bool) -> VimResult<Expr> {
+        let start = self.pos;
+        self.pos += 1;
+        let mut items = Vec::new();
+        loop {
+            if self.eat("}") {
+                return Ok(Expr::Dict(items));
+            }
+            if self.at_end() {
+                break;
+            }
+            let key = if literal {
+                self.skip_white();
+                let key = self.take_while(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+                if key.is_empty() {
+                    return Err(self.error_here());
+                }
+                Expr::String(key.to_string())
+            } else {
+                self.expr1()?
+            };
+            if !self.eat(":") {
+                return Err(VimError::Error(
+                    720,
+                    format!("Missing colon in Dictionary: {}", self.rest()),
+                ));
+            }
+            let value = self.expr1()?;
+            items.push((key, value));
+            if self.eat("}") {
+                return Ok(Expr::Dict(items));
+            }
+            if !self.eat(",") {
+                if self.at_end() {
+                    break;
+                }
+                return Err(VimError::Error(
+                    722,
+                    format!("Missing comma in Dictionary: {}", self.rest()),
+                ));
+            }
+        }
+        Err(VimError::Error(
+            723,
+            format!("Missing end of Dictionary '}}': {}", &self.text[start..]),
+        ))
+    }
+
+    /// `&name`, `&l:name` or `&g:name`
+    fn option(&mut self) -> VimResult<Expr> {
+        let start = self.pos;
+        self.pos += 1;
+        let rest = self.rest();
+        let scope = match rest.get(..2) {
+            Some("l:") => Some('l'),
+            Some("g:") => Some('g'),
+            _ => None,
+        };
+        if scope.is_some() {
+            self.pos += 2;
+        }
+        let name = self.take_while(|c| c.is_ascii_alphanumeric() || c == '_');
+        if name.is_empty() {
+            return Err(VimError::Error(
+                112,
+                format!("Option name missing: {}", &self.text[start..]),
+            ));
+        }
+        Ok(Expr::Option {
+            name: name.to_string(),
+            scope,
+        })
+    }
+}
+
+/// Whether `c` can be part of a variable or function name
+pub fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '#'
+}
+
+/// The text `"\<name>"` stands for.  Keys that are characters give the
+/// character (`<CR>` a carriage return, `<C-W>` a control character);
+/// other keys are kept in key notation after a 0x80 byte, the way a
+/// register holds them.
+fn special_key(name: &str) -> String {
+    let lower = name.to_ascii_lowercase();
+    let text = match lower.as_str() {
+        "cr" | "enter" | "return" => "\r",
+        "nl" | "newline" | "linefeed" | "lf" => "\n",
+        "esc" => "\x1b",
+        "tab" => "\t",
+        "bs" | "backspace" => "\x08",
+        "space" => " ",
+        "lt" => "<",
+        "bslash" => "\\",
+        "bar" => "|",
+        "nul" => "\0",
+        _ => {
+            let control = lower
+                .strip_prefix("c-")
+                .and_then(|key| {
+                    let mut chars = key.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(c), None) => Some(c),
+                        _ => None,
+                    }
+                })
+                .filter(|c| c.is_ascii_lowercase() || "@[\\]^_".contains(*c));
+            return match control {
+                Some(c) => char::from(c.to_ascii_uppercase() as u8 & 0x1f).to_string(),
+                None => format!("\u{80}<{}>", name),
+            };
+        }
+    };
+    text.to_string()
+}
+
+// ============================================================================
+// Evaluation
+// ============================================================================
+
+/// Evaluate `expr`
+pub fn eval(expr: &Expr, ctx: &mut dyn EvalContext) -> VimResult<Value> {
+    match expr {
+        Expr::Number(n) => Ok(Value::Number(*n)),
+        Expr::Float(f) => Ok(Value::Float(*f)),
+        Expr::String(s) => Ok(Value::String(s.clone())),
+        Expr::List(items) => {
+            let items = items
+                .iter()
+                .map(|item| eval(item, ctx))
+                .collect::<VimResult<_>>()?;
+            Ok(Value::list(items))
+        }
+        Expr::Dict(items) => {
+            let mut dict = BTreeMap::new();
+            for (key, value) in items {
+                let key = eval(key, ctx)?.as_string()?;
+                if dict.contains_key(&key) {
+                    return Err(VimError::Error(
+                        721,
+                        format!("Duplicate key in Dictionary: \"{}\"", key),
+                    ));
+                }
+                let value = eval(value, ctx)?;
+                dict.insert(key, value);
+            }
+            Ok(Value::dict(dict))
+        }
+        Expr::Option { name, scope } => ctx.option(name, *scope),
+        Expr::Register(reg) => ctx.register(*reg).map(Value::String),
+        Expr::Env(name) => Ok(Value::String(ctx.env(name).unwrap_or_default())),
+        Expr::Variable(name) => ctx
+            .variable(name)
+            .ok_or_else(|| VimError::Error(121, format!("Undefined variable: {}", name))),
+        Expr::Call { name, args } => {
+            let args = args
+                .iter()
+                .map(|arg| eval(arg, ctx))
+                .collect::<VimResult<Vec<_>>>()?;
+            call_function(name, &args, ctx)
+        }
+        Expr::Index(base, index) => {
+            let base = eval(base, ctx)?;
+            let index = eval(index, ctx)?;
+            index_value(&base, &index)
+        }
+        Expr::Slice(base, start, end) => {
+            let base = eval(base, ctx)?;
+            let start = start.as_ref().map(|e| eval(e, ctx)).transpose()?;
+            let end = end.as_ref().map(|e| eval(e, ctx)).transpose()?;
+            slice_value(&base, start.as_ref(), end.as_ref())
+        }
+        Expr::Member(base, key) => match eval(base, ctx)? {
+            Value::Dict(dict) => dict
+                .borrow()
+                .get(key)
+                .cloned()
+                .ok_or_else(|| missing_key(key)),
+            base => {
+                let right = eval(&Expr::Variable(key.clone()), ctx)?;
+                binary(BinaryOp::Concat, &base, &right)
+            }
+        },
+        Expr::DictCall { base, name, args } => {
+            let base = eval(base, ctx)?;
+            let args = args
+                .iter()
+                .map(|arg| eval(arg, ctx))
+                .collect::<VimResult<Vec<_>>>()?;
+            match base {
+                Value::Dict(dict) => {
+                    let entry = dict.borrow().get(name).cloned();
+                    let entry = entry.ok_or_else(|| missing_key(name))?;
+                    call_value(&entry, &args, Some(&dict), ctx)
+                }
+                base => {
+                    let right = call_function(name, &args, ctx)?;
+                    binary(BinaryOp::Concat, &base, &right)
+                }
+            }
+        }
+        Expr::Unary(op, operand) => unary(*op, &eval(operand, ctx)?),
+        Expr::Binary(op, left, right) => {
+            let left = eval(left, ctx)?;
+            let right = eval(right, ctx)?;
+            binary(*op, &left, &right)
+        }
+        Expr::Compare {
+            op,
+            ignore_case,
+            left,
+            right,
+        } => {
+            let left = eval(left, ctx)?;
+            let right = eval(right, ctx)?;
+            let ignore_case = match ignore_case {
+                Some(ignore_case) => *ignore_case,
+                None => ignorecase(ctx),
+            };
+            compare(*op, ignore_case, &left, &right).map(Value::bool)
+        }
+        Expr::And(left, right) => {
+            let result = eval(left, ctx)?.is_true()? && eval(right, ctx)?.is_true()?;
+            Ok(Value::bool(result))
+        }
+        Expr::Or(left, right) => {
+            let result = eval(left, ctx)?.is_true()? || eval(right, ctx)?.is_true()?;
+            Ok(Value::bool(result))
+        }
+        Expr::Ternary(cond, then, otherwise) => {
+            if eval(cond, ctx)?.is_true()? {
+                eval(then, ctx)
+            } else {
+                eval(otherwise, ctx)
+            }
+        }
+    }
+}
+
+/// Call function `name`: the editor's function if it has one, otherwise
+/// a builtin function, otherwise the function a Funcref variable `name`
+/// refers to
+pub fn call_function(name: &str, args: &[Value], ctx: &mut dyn EvalContext) -> VimResult<Value> {
+    if let Some(result) = ctx.call(name, args) {
+        return result;
+    }
+    if let Some(result) = evalfunc::call_builtin(name, args, ctx) {
+        return result;
+    }
+    match ctx.variable(name) {
+        Some(Value::Funcref(function)) if function != name => call_function(&function, args, ctx),
+        _ => Err(unknown_function(name)),
+    }
+}
+
+/// Call the function `func` names, a Funcref or a String, with `dict`
+/// as `self` when given
+pub fn call_value(
+    func: &Value,
+    args: &[Value],
+    dict: Option<&Dict>,
+    ctx: &mut dyn EvalContext,
+) -> VimResult<Value> {
+    let name = match func {
+        Value::Funcref(name) | Value::String(name) => name,
+        other => return Err(unknown_function(&other.to_display())),
+    };
+    if let Some(result) = dict.and_then(|dict| ctx.call_dict(name, args, dict)) {
+        return result;
+    }
+    call_function(name, args, ctx)
+}
+
+/// The error for calling a function that does not exist
+pub fn unknown_function(name: &str) -> VimError {
+    VimError::Error(117, format!("Unknown function: {}", name))
+}
+
+/// Whether 'ignorecase' is set in `ctx`
+pub fn ignorecase(ctx: &dyn EvalContext) -> bool {
+    ctx.option("ignorecase", None)
+        .and_then(|value| value.is_true())
+        .unwrap_or(false)
+}
+
+/// The error for a function called with too few arguments
+pub fn not_enough_arguments(name: &str) -> VimError {
+    VimError::Error(119, format!("Not enough arguments for function: {}", name))
+}
+
+/// The error for a Dictionary key that is not there
+pub fn missing_key(key: &str) -> VimError {
+    VimError::Error(716, format!("Key not present in Dictionary: \"{}\"", key))
+}
+
+fn unary(op: UnaryOp, value: &Value) -> VimResult<Value> {
+    match (op, value) {
+        (UnaryOp::Not, Value::Float(f)) => Ok(Value::Float(if *f == 0.0 { 1.0 } else { 0.0 })),
+        (UnaryOp::Not, value) => Ok(Value::bool(!value.is_true()?)),
+        (UnaryOp::Negate, Value::Float(f)) => Ok(Value::Float(-f)),
+        (UnaryOp::Negate, value) => Ok(Value::Number(value.as_number()?.wrapping_neg())),
+        (UnaryOp::Plus, Value::Float(f)) => Ok(Value::Float(*f)),
+        (UnaryOp::Plus, value) => Ok(Value::Number(value.as_number()?)),
+    }
+}
+
+/// `left op right`
+pub fn binary(op: BinaryOp, left: &Value, right: &Value) -> VimResult<Value> {
+    if op == BinaryOp::Concat {
+        return Ok(Value::String(left.as_string()? + &right.as_string()?));
+    }
+    if let (BinaryOp::Add, Value::List(a), Value::List(b)) = (op, left, right) {
+        let mut items = a.borrow().clone();
+        items.extend(b.borrow().iter().cloned());
+        return Ok(Value::list(items));
+    }
+    if matches!(left, Value::Float(_)) || matches!(right, Value::Float(_)) {
+        if op == BinaryOp::Mod {
+            return Err(VimError::Error(
+                804,
+                "Cannot use '%' with Float".to_string(),
+            ));
+        }
+        let (a, b) = (left.as_float()?, right.as_float()?);
+        return Ok(Value::Float(match op {
+            BinaryOp::Add => a + b,
+            BinaryOp::Sub => a - b,
+            BinaryOp::Mul => a * b,
+            _ => a / b,
+        }));
+    }
+    let (a, b) = (left.as_number()?, right.as_number()?);
+    Ok(Value::Number(match op {
+        BinaryOp::Add => a.wrapping_add(b),
+        BinaryOp::Sub => a.wrapping_sub(b),
+        BinaryOp::Mul => a.wrapping_mul(b),
+        // Division by zero gives the largest number with the sign of the
+        // dividend, and the smallest one for 0 / 0
+        BinaryOp::Div if b == 0 => match a {
+            0 => i64::MIN,
+            a if a > 0 => i64::MAX,
+            _ => -i64::MAX,
+        },
+        BinaryOp::Div => a.wrapping_div(b),
+        BinaryOp::Mod if b == 0 => 0,
+        _ => a.wrapping_rem(b),
+    }))
+}
+
+/// `left op right` for a comparison
+pub fn compare(op: CompareOp, ignore_case: bool, left: &Value, right: &Value) -> VimResult<bool> {
+    use CompareOp::*;
+    match op {
+        Is | IsNot => {
+            let same = match (left, right) {
+                (Value::List(a), Value::List(b)) => Rc::ptr_eq(a, b),
+                (Value::Dict(a), Value::Dict(b)) => Rc::ptr_eq(a, b),
+                (Value::List(_) | Value::Dict(_), _) | (_, Value::List(_) | Value::Dict(_)) => {
+                    false
+                }
+                (a, b) => a.type_nr() == b.type_nr() && values_equal(a, b, ignore_case),
+            };
+            return Ok(same == (op == Is));
+        }
+        Match | NoMatch => {
+            let text = left.as_string()?;
+            let pattern = right.as_string()?;
+            let found = crate::search::match_text(&text, &pattern, 0, ignore_case).is_some();
+            return Ok(found == (op == Match));
+        }
+        _ => {}
+    }
+    let ordering = match (left, right) {
+        (Value::List(_), Value::List(_)) | (Value::Dict(_), Value::Dict(_)) => {
+            let equal = values_equal(left, right, ignore_case);
+            return match op {
+                Equal => Ok(equal),
+                NotEqual => Ok(!equal),
+                _ if matches!(left, Value::List(_)) => Err(VimError::Error(
+                    692,
+                    "Invalid operation for List".to_string(),
+                )),
+                _ => Err(VimError::Error(
+                    736,
+                    "Invalid operation for Dictionary".to_string(),
+                )),
+            };
+        }
+        (Value::Funcref(_), _) | (_, Value::Funcref(_)) => {
+            let equal = values_equal(left, right, false);
+            return match op {
+                Equal => Ok(equal),
+                NotEqual => Ok(!equal),
+                _ => Err(VimError::Error(
+                    694,
+                    "Invalid operation for Funcrefs".to_string(),
+                )),
+            };
+        }
+        (Value::List(_), _) | (_, Value::List(_)) => {
+            return Err(VimError::Error(
+                691,
+                "Can only compare List with List".to_string(),
+            ));
+        }
+        (Value::Dict(_), _) | (_, Value::Dict(_)) => {
+            return Err(VimError::Error(
+                735,
+                "Can only compare Dictionary with Dictionary".to_string(),
+            ));
+        }
+        (Value::String(a), Value::String(b)) => compare_strings(a, b, ignore_case),
+        (Value::Float(_), _) | (_, Value::Float(_)) => {
+            let (a, b) = (left.as_float()?, right.as_float()?);
+            match a.partial_cmp(&b) {
+                Some(ordering) => ordering,
+                None => return Ok(op == NotEqual),
+            }
+        }
+        _ => left.as_number()?.cmp(&right.as_number()?),
+    };
+    Ok(match op {
+        Equal => ordering.is_eq(),
+        NotEqual => ordering.is_ne(),
+        Greater => ordering.is_gt(),
+        GreaterEqual => ordering.is_ge(),
+        Less => ordering.is_lt(),
+        _ => ordering.is_le(),
+    })
+}
+
+fn compare_strings(a: &str, b: &str, ignore_case: bool) -> std::cmp::Ordering {
+    if ignore_case {
+        a.to_lowercase().cmp(&b.to_lowercase())
+    } else {
+        a.cmp(b)
+    }
+}
+
+/// Whether two values are equal as items of a List: of the same type
+/// and with the same value
+pub fn values_equal(a: &Value, b: &Value, ignore_case: bool) -> bool {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a == b,
+        (Value::Float(a), Value::Float(b)) => a == b,
+        (Value::String(a), Value::String(b)) => compare_strings(a, b, ignore_case).is_eq(),
+        (Value::Funcref(a), Value::Funcref(b)) => a == b,
+        (Value::List(a), Value::List(b)) => {
+            if Rc::ptr_eq(a, b) {
+                return true;
+            }
+            let (a, b) = (a.borrow(), b.borrow());
+            a.len() == b.len()
+                && a.iter()
+                    .zip(b.iter())
+                    .all(|(a, b)| values_equal(a, b, ignore_case))
+        }
+        (Value::Dict(a), Value::Dict(b)) => {
+            if Rc::ptr_eq(a, b) {
+                return true;
+            }
+            let (a, b) = (a.borrow(), b.borrow());
+            a.len() == b.len()
+                && a.iter().all(|(key, value)| {
+                    b.get(key)
+                        .is_some_and(|other| values_equal(value, other, ignore_case))
+                })
+        }
+        _ => false,
+    }
+}
+
+/// `base[index]`
+pub fn index_value(base: &Value, index: &Value) -> VimResult<Value> {
+    match base {
+        Value::List(list) => {
+            let list = list.borrow();
+            let nr = index.as_number()?;
+            list_index(nr, list.len())
+                .map(|idx| list[idx].clone())
+                .ok_or_else(|| VimError::Error(684, format!("List index out of range: {}", nr)))
+        }
+        Value::Dict(dict) => {
+            let key = index.as_string()?;
+            dict.borrow()
+                .get(&key)
+                .cloned()
+                .ok_or_else(|| missing_key(&key))
+        }
+        base => {
+            let text = base.as_string()?;
+            let nr = index.as_number()?;
+            let byte = usize::try_from(nr)
+                .ok()
+                .and_then(|idx| text.as_bytes().get(idx..=idx));
+            Ok(Value::String(
+                byte.map(|byte| String::from_utf8_lossy(byte).into_owned())
+                    .unwrap_or_default(),
+            ))
+        }
+    }
+}
+
+/// The index of item `nr` in a List of `len` items, counting from the
+/// end when negative
+pub fn list_index(nr: i64, len: usize) -> Option<usize> {
+    let idx = if nr < 0 { len as i64 + nr } else { nr };
+    (0..len as i64).contains(&idx).then_some(idx as usize)
+}
+
+/// `base[start : end]`, both ends included and counting from the end
+/// when negative
+pub fn slice_value(base: &Value, start: Option<&Value>, end: Option<&Value>) -> VimResult<Value> {
+    if matches!(base, Value::Dict(_)) {
+        return Err(VimError::Error(
+            719,
+            "Cannot slice a Dictionary".to_string(),
+        ));
+    }
+    let len = match base {
+        Value::List(list) => list.borrow().len(),
+        base => base.as_string()?.len(),
+    } as i64;
+    let start = start.map(Value::as_number).transpose()?.unwrap_or(0);
+    let end = end.map(Value::as_number).transpose()?.unwrap_or(-1);
+    let start = if start < 0 {
+        (len + start).max(0)
+    } else {
+        start
+    };
+    let end = if end < 0 { len + end } else { end.min(len - 1) };
+    let range = (start <= end && start < len).then(|| start as usize..end as usize + 1);
+    match base {
+        Value::List(list) => {
+            let list = list.borrow();
+            let items = range.map(|range| list[range].to_vec()).unwrap_or_default();
+            Ok(Value::list(items))
+        }
+        base => {
+            let text = base.as_string()?;
+            let bytes = range
+                .map(|range| &text.as_bytes()[range])
+                .unwrap_or_default();
+            Ok(Value::String(String::from_utf8_lossy(bytes).into_owned()))
+        }
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctx() -> SimpleEvalContext {
+        let mut ctx = SimpleEvalContext::new();
+        ctx.options.insert("tabstop".to_string(), Value::Number(8));
+        ctx.options
+            .insert("ignorecase".to_string(), Value::Number(0));
+        ctx.registers.insert('a', "hello".to_string());
+        ctx.env.insert("HOME".to_string(), "/home/vim".to_string());
+        ctx.variables
+            .insert("g:count".to_string(), Value::Number(3));
+        ctx
+    }
+
+    fn echo(text: &str) -> String {
+        eval_expr(text, &mut ctx()).unwrap().to_display()
+    }
+
+    fn error(text: &str) -> String {
+        eval_expr(text, &mut ctx()).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn test_number_overflow() {
+        let max = i64::MAX.to_string();
+        assert_eq!(echo("0x7fffffffffffffffff"), max);
+        assert_eq!(echo("0xffffffffffffffff"), max);
+        assert_eq!(echo(&format!("0b{}", "1".repeat(70))), max);
+        assert_eq!(echo("07777777777777777777777777"), max);
+        assert_eq!(echo("99999999999999999999"), max);
+        assert_eq!(echo("-0x7fffffffffffffffff"), (-i64::MAX).to_string());
+    }
+
+    #[test]
+    fn test_numbers_and_floats() {
+        assert_eq!(echo("0x1F + 0b101 + 017 + 0o10"), "59");
+        assert_eq!(echo("019"), "19");
+        assert_eq!(echo("7 / 2"), "3");
+        assert_eq!(echo("-7 % 3"), "-1");
+        assert_eq!(echo("1 / 0"), i64::MAX.to_string());
+        assert_eq!(echo("1.0 / 3"), "0.333333");
+        assert_eq!(echo("1.5e3"), "1500.0");
+        assert_eq!(echo("1.0e20"), "1.0e20");
+        assert_eq!(echo("-1.5e-5"), "-1.5e-5");
+        assert_eq!(echo("2 * 3 + 4 * 5"), "26");
+        assert_eq!(echo("-(2 + 3)"), "-5");
+        assert_eq!(error("1.5 % 2"), "E804: Cannot use '%' with Float");
+    }
+
+    #[test]
+    fn test_strings() {
+        assert_eq!(echo(r#""a\tb\x41€""#), "a\tbA\u{20ac}");
+        assert_eq!(echo("'it''s'"), "it's");
+        assert_eq!(echo("'abc' . 12 .. 'd'"), "abc12d");
+        assert_eq!(echo("'12abc' + 1"), "13");
+        assert_eq!(echo(r#""\<CR>\<C-W>\<Left>""#), "\r\x17\u{80}<Left>");
+        assert_eq!(error("'abc"), "E115: Missing quote: 'abc");
+        assert_eq!(error("1 . 1.5"), "E806: Using a Float as a String");
+    }
+
+    #[test]
+    fn test_comparisons() {
+        assert_eq!(echo("'abc' == 'ABC'"), "0");
+        assert_eq!(echo("'abc' ==? 'ABC'"), "1");
+        assert_eq!(echo("'abc' ==# 'abc'"), "1");
+        assert_eq!(echo("'abc' == 0"), "1");
+        assert_eq!(echo("'foobar' =~ 'oba'"), "1");
+        assert_eq!(echo("'foobar' =~ '^oba'"), "0");
+        assert_eq!(echo("'foobar' !~ 'bar$'"), "0");
+        assert_eq!(echo("2 > 1 && 1 >= 1 && 1 < 2.5"), "1");
+        assert_eq!(echo("[1, 'a'] == [1, 'a']"), "1");
+        assert_eq!(echo("[1] == ['1']"), "0");
+        assert_eq!(error("[1] < [2]"), "E692: Invalid operation for List");
+        assert_eq!(error("[1] == 1"), "E691: Can only compare List with List");
+    }
+
+    #[test]
+    fn test_comparisons_follow_ignorecase() {
+        let mut ctx = ctx();
+        ctx.options
+            .insert("ignorecase".to_string(), Value::Number(1));
+        assert_eq!(
+            eval_expr("'abc' == 'ABC'", &mut ctx).unwrap(),
+            Value::Number(1)
+        );
+        assert_eq!(
+            eval_expr("'abc' ==# 'ABC'", &mut ctx).unwrap(),
+            Value::Number(0)
+        );
+        assert_eq!(
+            eval_expr("'FOO' =~ 'fo'", &mut ctx).unwrap(),
+            Value::Number(1)
+        );
+    }
+
+    #[test]
+    fn test_is_compares_identity() {
+        let mut ctx = ctx();
+        let list = Value::list(vec![Value::Number(1)]);
+        ctx.variables.insert("l".to_string(), list.clone());
+        ctx.variables.insert("m".to_string(), list);
+        assert_eq!(eval_expr("l is m", &mut ctx).unwrap(), Value::Number(1));
+        assert_eq!(eval_expr("l is [1]", &mut ctx).unwrap(), Value::Number(0));
+        assert_eq!(
+            eval_expr("l isnot [1]", &mut ctx).unwrap(),
+            Value::Number(1)
+        );
+        assert_eq!(eval_expr("1 is '1'", &mut ctx).unwrap(), Value::Number(0));
+        assert_eq!(eval_expr("'a' is 'a'", &mut ctx).unwrap(), Value::Number(1));
+    }
+
+    #[test]
+    fn test_lists_and_dicts() {
+        assert_eq!(echo("[1, 'two', [3]]"), "[1, 'two', [3]]");
+        assert_eq!(echo("[1, 2] + [3]"), "[1, 2, 3]");
+        assert_eq!(echo("[1, 2, 3][-1]"), "3");
+        assert_eq!(echo("{'b': 2, 'a': 'x'}"), "{'a': 'x', 'b': 2}");
+        assert_eq!(echo("{'a': {'b': 5}}.a.b"), "5");
+        assert_eq!(echo("#{one: 1}['one']"), "1");
+        assert_eq!(echo("[1, 2,]"), "[1, 2]");
+        assert_eq!(error("[1, 2][5]"), "E684: List index out of range: 5");
+        assert_eq!(
+            error("{'a': 1}.b"),
+            "E716: Key not present in Dictionary: \"b\""
+        );
+        assert_eq!(error("[1 2]"), "E696: Missing comma in List: 2]");
+        assert_eq!(error("[1, 2"), "E697: Missing end of List ']': [1, 2");
+        assert_eq!(error("{'a' 1}"), "E720: Missing colon in Dictionary: 1}");
+    }
+
+    #[test]
+    fn test_slices() {
+        assert_eq!(echo("'abcdef'[1:3]"), "bcd");
+        assert_eq!(echo("'abcdef'[-2:]"), "ef");
+        assert_eq!(echo("'abcdef'[:1]"), "ab");
+        assert_eq!(echo("'abc'[5]"), "");
+        assert_eq!(echo("'abc'[2:1]"), "");
+        assert_eq!(echo("[1, 2, 3, 4][1:-2]"), "[2, 3]");
+        assert_eq!(echo("[1, 2, 3][5:]"), "[]");
+    }
+
+    #[test]
+    fn test_options_registers_env_variables() {
+        assert_eq!(echo("&tabstop * 2"), "16");
+        assert_eq!(echo("@a . '!'"), "hello!");
+        assert_eq!(echo("$HOME"), "/home/vim");
+        assert_eq!(echo("$UNSET_VAR"), "");
+        assert_eq!(echo("g:count + 1"), "4");
+        assert_eq!(error("&nosuch"), "E113: Unknown option: &nosuch");
+        assert_eq!(error("nosuch"), "E121: Undefined variable: nosuch");
+        assert_eq!(error("&"), "E112: Option name missing: &");
+    }
+
+    #[test]
+    fn test_logic_and_ternary() {
+        assert_eq!(echo("1 ? 'yes' : 'no'"), "yes");
+        assert_eq!(echo("0 ? 'yes' : 0 ? 'maybe' : 'no'"), "no");
+        assert_eq!(echo("!0 || nosuch"), "1");
+        assert_eq!(echo("0 && nosuch"), "0");
+        assert_eq!(error("1 ? 2"), "E109: Missing ':' after '?': ");
+    }
+
+    #[test]
+    fn test_parse_expr_stops_after_expression() {
+        let (expr, len) = parse_expr("1 + 2  'rest'").unwrap();
+        assert_eq!(len, 7);
+        assert_eq!(eval(&expr, &mut ctx()).unwrap(), Value::Number(3));
+        assert_eq!(error("1 2"), "E15: Invalid expression: \"2\"");
+        assert_eq!(error(""), "E15: Invalid expression: \"\"");
+    }
+
+    #[test]
+    fn test_method_calls() {
+        assert_eq!(echo("'abc'->toupper()"), "ABC");
+        assert_eq!(echo("[1, 2, 3]->len() + 1"), "4");
+        assert_eq!(error("nosuch(1)"), "E117: Unknown function: nosuch");
+    }
+
+    #[test]
+    fn test_str2nr_and_str2float() {
+        assert_eq!(str2nr("0x10", 0), 16);
+        assert_eq!(str2nr("10", 16), 16);
+        assert_eq!(str2nr("-12abc", 10), -12);
+        assert_eq!(str2nr("abc", 10), 0);
+        assert_eq!(str2float("1.5e2xyz"), 150.0);
+        assert_eq!(str2float("  -3"), -3.0);
+    }
+}
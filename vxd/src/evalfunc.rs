@@ -0,0 +1,1317 @@
+//! Builtin functions of expressions.
+//!
+//! These are the functions that work on values alone, such as `strlen()`,
+//! `printf()` or `substitute()`.  Functions that look at the editor, such
+//! as `line()` or `getline()`, are the editor's, called through
+//! [`EvalContext::call`].
+//!
+//! Patterns given to `match()`, `substitute()` and friends are matched
+//! with [`crate::search::match_text`].
+
+use std::collections::BTreeMap;
+
+use crate::eval::{
+    self, format_e, format_float, format_g, list_index, missing_key, str2float, str2nr,
+    values_equal, EvalContext, SubmatchContext, Value,
+};
+use crate::search::match_text;
+use crate::types::{VimError, VimResult};
+
+/// The builtin functions with the least and most arguments they take
+const FUNCTIONS: &[(&str, usize, usize)] = &[
+    ("abs", 1, 1),
+    ("add", 2, 2),
+    ("and", 2, 2),
+    ("call", 2, 3),
+    ("ceil", 1, 1),
+    ("char2nr", 1, 2),
+    ("copy", 1, 1),
+    ("count", 2, 4),
+    ("deepcopy", 1, 2),
+    ("empty", 1, 1),
+    ("escape", 2, 2),
+    ("exists", 1, 1),
+    ("extend", 2, 3),
+    ("float2nr", 1, 1),
+    ("floor", 1, 1),
+    ("funcref", 1, 1),
+    ("function", 1, 1),
+    ("get", 2, 3),
+    ("getenv", 1, 1),
+    ("getreg", 0, 3),
+    ("has_key", 2, 2),
+    ("index", 2, 4),
+    ("insert", 2, 3),
+    ("invert", 1, 1),
+    ("items", 1, 1),
+    ("join", 1, 2),
+    ("keys", 1, 1),
+    ("len", 1, 1),
+    ("match", 2, 4),
+    ("matchend", 2, 4),
+    ("matchstr", 2, 4),
+    ("max", 1, 1),
+    ("min", 1, 1),
+    ("nr2char", 1, 2),
+    ("or", 2, 2),
+    ("printf", 1, 20),
+    ("range", 1, 3),
+    ("remove", 2, 3),
+    ("repeat", 2, 2),
+    ("reverse", 1, 1),
+    ("round", 1, 1),
+    ("sort", 1, 3),
+    ("split", 1, 3),
+    ("str2float", 1, 1),
+    ("str2nr", 1, 2),
+    ("strcharpart", 2, 3),
+    ("strchars", 1, 2),
+    ("stridx", 2, 3),
+    ("string", 1, 1),
+    ("strlen", 1, 1),
+    ("strpart", 2, 4),
+    ("strridx", 2, 3),
+    ("submatch", 1, 2),
+    ("substitute", 4, 4),
+    ("tolower", 1, 1),
+    ("toupper", 1, 1),
+    ("tr", 3, 3),
+    ("trim", 1, 3),
+    ("trunc", 1, 1),
+    ("type", 1, 1),
+    ("uniq", 1, 1),
+    ("values", 1, 1),
+    ("xor", 2, 2),
+];
+
+/// Whether `name` is a builtin function
+pub fn is_builtin(name: &str) -> bool {
+    FUNCTIONS.iter().any(|(function, _, _)| *function == name)
+}
+
+/// Call builtin function `name`.  Returns `None` when there is no such
+/// function.
+pub fn call_builtin(
+    name: &str,
+    args: &[Value],
+    ctx: &mut dyn EvalContext,
+) -> Option<VimResult<Value>> {
+    let &(_, min, max) = FUNCTIONS
+        .iter()
+        .find(|(function, _, _)| *function == name)?;
+    if args.len() < min {
+        return Some(Err(eval::not_enough_arguments(name)));
+    }
+    if args.len() > max {
+        return Some(Err(VimError::Error(
+            118,
+            format!("Too many arguments for function: {}", name),
+        )));
+    }
+    Some(call(name, args, ctx))
+}
+
+fn call(name: &str, args: &[Value], ctx: &mut dyn EvalContext) -> VimResult<Value> {
+    let arg = |idx: usize| args.get(idx);
+    let number = |idx: usize, default: i64| -> VimResult<i64> {
+        arg(idx).map_or(Ok(default), Value::as_number)
+    };
+    let string =
+        |idx: usize| -> VimResult<String> { arg(idx).map_or(Ok(String::new()), Value::as_string) };
+    let flag = |idx: usize| -> VimResult<bool> { arg(idx).map_or(Ok(false), Value::is_true) };
+    match name {
+        "abs" => match &args[0] {
+            Value::Float(f) => Ok(Value::Float(f.abs())),
+            value => Ok(Value::Number(value.as_number()?.wrapping_abs())),
+        },
+        "add" => {
+            let list = list_arg(&args[0], "add")?;
+            list.borrow_mut().push(args[1].clone());
+            Ok(args[0].clone())
+        }
+        "and" => Ok(Value::Number(number(0, 0)? & number(1, 0)?)),
+        "or" => Ok(Value::Number(number(0, 0)? | number(1, 0)?)),
+        "xor" => Ok(Value::Number(number(0, 0)? ^ number(1, 0)?)),
+        "invert" => Ok(Value::Number(!number(0, 0)?)),
+        "ceil" | "floor" | "round" | "trunc" => {
+            let f = float_arg(&args[0])?;
+            Ok(Value::Float(match name {
+                "ceil" => f.ceil(),
+                "floor" => f.floor(),
+                "round" => f.round(),
+                _ => f.trunc(),
+            }))
+        }
+        "float2nr" => {
+            let f = float_arg(&args[0])?;
+            Ok(Value::Number(if f.is_nan() { 0 } else { f as i64 }))
+        }
+        "char2nr" => Ok(Value::Number(
+            string(0)?.chars().next().map_or(0, |c| c as i64),
+        )),
+        "nr2char" => {
+            let code = number(0, 0)?;
+            let c = u32::try_from(code).ok().and_then(char::from_u32);
+            Ok(Value::String(c.map(String::from).unwrap_or_default()))
+        }
+        "copy" => Ok(match &args[0] {
+            Value::List(list) => Value::list(list.borrow().clone()),
+            Value::Dict(dict) => Value::dict(dict.borrow().clone()),
+            value => value.clone(),
+        }),
+        "deepcopy" => Ok(args[0].deep_copy()),
+        "count" => {
+            let ignore_case = flag(2)?;
+            let count = match &args[0] {
+                Value::List(list) => {
+                    let list = list.borrow();
+                    let start = match arg(3) {
+                        Some(start) => list_index(start.as_number()?, list.len())
+                            .ok_or_else(|| index_error(start))?,
+                        None => 0,
+                    };
+                    list[start..]
+                        .iter()
+                        .filter(|item| values_equal(item, &args[1], ignore_case))
+                        .count()
+                }
+                Value::Dict(dict) => dict
+                    .borrow()
+                    .values()
+                    .filter(|item| values_equal(item, &args[1], ignore_case))
+                    .count(),
+                value => {
+                    let (text, needle) = fold(&value.as_string()?, &string(1)?, ignore_case);
+                    if needle.is_empty() {
+                        0
+                    } else {
+                        text.matches(&needle).count()
+                    }
+                }
+            };
+            Ok(Value::Number(count as i64))
+        }
+        "call" => {
+            let list = list_arg(&args[1], "call")?;
+            let call_args = list.borrow().clone();
+            let dict = match arg(2) {
+                Some(value) => Some(dict_arg(value)?.clone()),
+                None => None,
+            };
+            eval::call_value(&args[0], &call_args, dict.as_ref(), ctx)
+        }
+        "empty" => Ok(Value::bool(args[0].is_empty())),
+        "escape" => {
+            let chars = string(1)?;
+            let mut out = String::new();
+            for c in string(0)?.chars() {
+                if chars.contains(c) {
+                    out.push('\\');
+                }
+                out.push(c);
+            }
+            Ok(Value::String(out))
+        }
+        "exists" => Ok(Value::bool(exists(&string(0)?, ctx))),
+        "extend" => extend(args),
+        "get" => {
+            let default = arg(2).cloned().unwrap_or(Value::Number(0));
+            match &args[0] {
+                Value::List(list) => {
+                    let list = list.borrow();
+                    let idx = list_index(args[1].as_number()?, list.len());
+                    Ok(idx.map(|idx| list[idx].clone()).unwrap_or(default))
+                }
+                Value::Dict(dict) => Ok(dict
+                    .borrow()
+                    .get(&args[1].as_string()?)
+                    .cloned()
+                    .unwrap_or(default)),
+                _ => Err(invalid_argument("get()")),
+            }
+        }
+        "function" | "funcref" => {
+            let name = match &args[0] {
+                Value::Funcref(name) => name.clone(),
+                value => value.as_string()?,
+            };
+            if !is_builtin(&name) && !ctx.has_function(&name) {
+                return Err(VimError::Error(700, format!("Unknown function: {}", name)));
+            }
+            Ok(Value::Funcref(name))
+        }
+        "getenv" => Ok(Value::String(ctx.env(&string(0)?).unwrap_or_default())),
+        "getreg" => {
+            let name = string(0)?;
+            let reg = name.chars().next().unwrap_or('"');
+            let text = ctx.register(reg)?;
+            if flag(2)? {
+                Ok(Value::string_list(
+                    text.strip_suffix('\n').unwrap_or(&text).split('\n'),
+                ))
+            } else {
+                Ok(Value::String(text))
+            }
+        }
+        "has_key" => {
+            let dict = dict_arg(&args[0])?;
+            let key = string(1)?;
+            let found = dict.borrow().contains_key(&key);
+            Ok(Value::bool(found))
+        }
+        "index" => {
+            let list = list_arg(&args[0], "index")?;
+            let list = list.borrow();
+            let start = match arg(2) {
+                Some(start) => match list_index(start.as_number()?, list.len()) {
+                    Some(start) => start,
+                    None => return Ok(Value::Number(-1)),
+                },
+                None => 0,
+            };
+            let ignore_case = flag(3)?;
+            let found = list[start..]
+                .iter()
+                .position(|item| values_equal(item, &args[1], ignore_case));
+            Ok(Value::Number(found.map_or(-1, |idx| (start + idx) as i64)))
+        }
+        "insert" => {
+            let list = list_arg(&args[0], "insert")?;
+            let len = list.borrow().len();
+            let nr = number(2, 0)?;
+            let idx = if nr == len as i64 {
+                len
+            } else {
+                list_index(nr, len).ok_or_else(|| index_error(&Value::Number(nr)))?
+            };
+            list.borrow_mut().insert(idx, args[1].clone());
+            Ok(args[0].clone())
+        }
+        "items" => {
+            let dict = dict_arg(&args[0])?;
+            let items = dict
+                .borrow()
+                .iter()
+                .map(|(key, value)| Value::list(vec![Value::string(key.as_str()), value.clone()]))
+                .collect();
+            Ok(Value::list(items))
+        }
+        "keys" => {
+            let dict = dict_arg(&args[0])?;
+            let keys: Vec<String> = dict.borrow().keys().cloned().collect();
+            Ok(Value::string_list(keys))
+        }
+        "values" => {
+            let dict = dict_arg(&args[0])?;
+            let values = dict.borrow().values().cloned().collect();
+            Ok(Value::list(values))
+        }
+        "join" => {
+            let list = list_arg(&args[0], "join")?;
+            let sep = match arg(1) {
+                Some(sep) => sep.as_string()?,
+                None => " ".to_string(),
+            };
+            let items: Vec<String> = list.borrow().iter().map(Value::to_display).collect();
+            Ok(Value::String(items.join(&sep)))
+        }
+        "len" => Ok(Value::Number(match &args[0] {
+            Value::List(list) => list.borrow().len(),
+            Value::Dict(dict) => dict.borrow().len(),
+            Value::Float(_) => {
+                return Err(VimError::Error(701, "Invalid type for len()".to_string()))
+            }
+            value => value.as_string()?.len(),
+        } as i64)),
+        "match" | "matchend" | "matchstr" => match_function(name, args, ctx),
+        "max" | "min" => {
+            let items: Vec<Value> = match &args[0] {
+                Value::List(list) => list.borrow().clone(),
+                Value::Dict(dict) => dict.borrow().values().cloned().collect(),
+                _ => return Err(invalid_argument(&format!("{}()", name))),
+            };
+            let numbers = items
+                .iter()
+                .map(Value::as_number)
+                .collect::<VimResult<Vec<_>>>()?;
+            let found = match name {
+                "max" => numbers.into_iter().max(),
+                _ => numbers.into_iter().min(),
+            };
+            Ok(Value::Number(found.unwrap_or(0)))
+        }
+        "printf" => printf(&string(0)?, &args[1..]).map(Value::String),
+        "range" => range(
+            number(0, 0)?,
+            arg(1).map(Value::as_number).transpose()?,
+            number(2, 1)?,
+        ),
+        "remove" => remove(args),
+        "repeat" => {
+            let count = number(1, 0)?.max(0) as usize;
+            match &args[0] {
+                Value::List(list) => {
+                    let list = list.borrow();
+                    Ok(Value::list(
+                        std::iter::repeat_n(list.iter().cloned(), count)
+                            .flatten()
+                            .collect(),
+                    ))
+                }
+                value => Ok(Value::String(value.as_string()?.repeat(count))),
+            }
+        }
+        "reverse" => {
+            let list = list_arg(&args[0], "reverse")?;
+            list.borrow_mut().reverse();
+            Ok(args[0].clone())
+        }
+        "sort" => sort(args),
+        "uniq" => {
+            let list = list_arg(&args[0], "uniq")?;
+            list.borrow_mut().dedup_by(|a, b| values_equal(a, b, false));
+            Ok(args[0].clone())
+        }
+        "split" => {
+            let text = string(0)?;
+            let pattern = arg(1).map(Value::as_string).transpose()?;
+            let keepempty = flag(2)?;
+            Ok(Value::string_list(split(
+                &text,
+                pattern.as_deref(),
+                keepempty,
+                ctx,
+            )))
+        }
+        "str2float" => Ok(Value::Float(str2float(&string(0)?))),
+        "str2nr" => {
+            let base = number(1, 10)?;
+            if ![2, 8, 10, 16].contains(&base) {
+                return Err(invalid_argument(&base.to_string()));
+            }
+            Ok(Value::Number(str2nr(string(0)?.trim_start(), base as u32)))
+        }
+        "strlen" => Ok(Value::Number(string(0)?.len() as i64)),
+        "strchars" => Ok(Value::Number(string(0)?.chars().count() as i64)),
+        "strpart" => {
+            let text = string(0)?;
+            let start = number(1, 0)?;
+            let len = number(2, text.len() as i64)?;
+            let (from, to) = clamp_range(start, len, text.len());
+            let bytes = &text.as_bytes()[from..to];
+            Ok(Value::String(String::from_utf8_lossy(bytes).into_owned()))
+        }
+        "strcharpart" => {
+            let chars: Vec<char> = string(0)?.chars().collect();
+            let start = number(1, 0)?;
+            let len = number(2, chars.len() as i64)?;
+            let (from, to) = clamp_range(start, len, chars.len());
+            Ok(Value::String(chars[from..to].iter().collect()))
+        }
+        "stridx" => {
+            let haystack = string(0)?;
+            let needle = string(1)?;
+            let start = number(2, 0)?.max(0) as usize;
+            let found = haystack
+                .get(start..)
+                .and_then(|rest| rest.find(&needle))
+                .map(|idx| idx + start);
+            Ok(Value::Number(found.map_or(-1, |idx| idx as i64)))
+        }
+        "strridx" => {
+            let haystack = string(0)?;
+            let needle = string(1)?;
+            let start = number(2, haystack.len() as i64)?;
+            if start < 0 {
+                return Ok(Value::Number(-1));
+            }
+            let end = (start as usize + needle.len()).min(haystack.len());
+            let found = haystack.get(..end).and_then(|text| text.rfind(&needle));
+            Ok(Value::Number(found.map_or(-1, |idx| idx as i64)))
+        }
+        "string" => Ok(Value::String(args[0].to_repr())),
+        "submatch" => Err(VimError::Error(
+            935,
+            "submatch() used outside of a substitute".to_string(),
+        )),
+        "substitute" => {
+            let text = string(0)?;
+            let pattern = string(1)?;
+            let flags = string(3)?;
+            substitute(&text, &pattern, &args[2], flags.contains('g'), ctx).map(Value::String)
+        }
+        "tolower" => Ok(Value::String(string(0)?.to_lowercase())),
+        "toupper" => Ok(Value::String(string(0)?.to_uppercase())),
+        "tr" => {
+            let from: Vec<char> = string(1)?.chars().collect();
+            let to: Vec<char> = string(2)?.chars().collect();
+            if from.len() != to.len() {
+                return Err(invalid_argument(&string(1)?));
+            }
+            let out = string(0)?
+                .chars()
+                .map(|c| from.iter().position(|&f| f == c).map_or(c, |idx| to[idx]))
+                .collect();
+            Ok(Value::String(out))
+        }
+        "trim" => {
+            let text = string(0)?;
+            let mask = match arg(1) {
+                Some(mask) => mask.as_string()?,
+                None => String::new(),
+            };
+            let trimmed = |c: char| {
+                if mask.is_empty() {
+                    c.is_whitespace() || c.is_control() || c == '\u{a0}'
+                } else {
+                    mask.contains(c)
+                }
+            };
+            Ok(Value::string(match number(2, 0)? {
+                1 => text.trim_start_matches(trimmed),
+                2 => text.trim_end_matches(trimmed),
+                _ => text.trim_matches(trimmed),
+            }))
+        }
+        "type" => Ok(Value::Number(args[0].type_nr())),
+        _ => Err(VimError::Error(117, format!("Unknown function: {}", name))),
+    }
+}
+
+fn invalid_argument(arg: &str) -> VimError {
+    VimError::Error(475, format!("Invalid argument: {}", arg))
+}
+
+fn index_error(index: &Value) -> VimError {
+    VimError::Error(
+        684,
+        format!("List index out of range: {}", index.to_display()),
+    )
+}
+
+fn list_arg<'a>(value: &'a Value, name: &str) -> VimResult<&'a eval::List> {
+    match value {
+        Value::List(list) => Ok(list),
+        _ => Err(VimError::Error(
+            714,
+            format!("List required for argument of {}()", name),
+        )),
+    }
+}
+
+fn dict_arg(value: &Value) -> VimResult<&eval::Dict> {
+    match value {
+        Value::Dict(dict) => Ok(dict),
+        _ => Err(VimError::Error(715, "Dictionary required".to_string())),
+    }
+}
+
+fn float_arg(value: &Value) -> VimResult<f64> {
+    match value {
+        Value::Float(f) => Ok(*f),
+        Value::Number(n) => Ok(*n as f64),
+        _ => Err(VimError::Error(808, "Number or Float required".to_string())),
+    }
+}
+
+fn fold(text: &str, needle: &str, ignore_case: bool) -> (String, String) {
+    if ignore_case {
+        (text.to_lowercase(), needle.to_lowercase())
+    } else {
+        (text.to_string(), needle.to_string())
+    }
+}
+
+/// The part of `len` items from `start` for `len` items, both clamped to
+/// the items there are
+fn clamp_range(start: i64, len: i64, total: usize) -> (usize, usize) {
+    let end = start.saturating_add(len.max(0)).clamp(0, total as i64) as usize;
+    let start = start.clamp(0, total as i64) as usize;
+    (start, end.max(start))
+}
+
+/// `exists()`: `&option`, `$NAME`, `*function` or a variable
+fn exists(what: &str, ctx: &mut dyn EvalContext) -> bool {
+    if let Some(option) = what.strip_prefix('&') {
+        let (scope, name) = match option.get(..2) {
+            Some("l:") => (Some('l'), &option[2..]),
+            Some("g:") => (Some('g'), &option[2..]),
+            _ => (None, option),
+        };
+        return ctx.option(name, scope).is_ok();
+    }
+    if let Some(name) = what.strip_prefix('$') {
+        return ctx.env(name).is_some();
+    }
+    if let Some(name) = what.strip_prefix('*') {
+        return is_builtin(name) || ctx.has_function(name);
+    }
+    ctx.variable(what).is_some()
+}
+
+/// `extend({list}, {list} [, {idx}])` or `extend({dict}, {dict} [, {how}])`
+fn extend(args: &[Value]) -> VimResult<Value> {
+    match (&args[0], &args[1]) {
+        (Value::List(list), Value::List(other)) => {
+            let items = other.borrow().clone();
+            let len = list.borrow().len();
+            let idx = match args.get(2) {
+                Some(idx) => {
+                    let nr = idx.as_number()?;
+                    if nr == len as i64 {
+                        len
+                    } else {
+                        list_index(nr, len).ok_or_else(|| index_error(idx))?
+                    }
+                }
+                None => len,
+            };
+            list.borrow_mut().splice(idx..idx, items);
+            Ok(args[0].clone())
+        }
+        (Value::Dict(dict), Value::Dict(other)) => {
+            let how = match args.get(2) {
+                Some(how) => how.as_string()?,
+                None => "force".to_string(),
+            };
+            let items: Vec<(String, Value)> = other
+                .borrow()
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect();
+            let mut dict = dict.borrow_mut();
+            for (key, value) in items {
+                if dict.contains_key(&key) {
+                    match how.as_str() {
+                        "keep" => continue,
+                        "error" => {
+                            return Err(VimError::Error(
+                                737,
+                                format!("Key already exists: {}", key),
+                            ))
+                        }
+                        "force" => {}
+                        _ => return Err(invalid_argument(&how)),
+                    }
+                }
+                dict.insert(key, value);
+            }
+            drop(dict);
+            Ok(args[0].clone())
+        }
+        _ => Err(VimError::Error(
+            712,
+            "Argument of extend() must be a List or Dictionary".to_string(),
+        )),
+    }
+}
+
+/// `range({expr})` or `range({start}, {end} [, {stride}])`
+fn range(first: i64, end: Option<i64>, stride: i64) -> VimResult<Value> {
+    let (start, end) = match end {
+        Some(end) => (first, end),
+        None => (0, first - 1),
+    };
+    if stride == 0 {
+        return Err(VimError::Error(726, "Stride is zero".to_string()));
+    }
+    if (stride > 0 && end + 1 < start) || (stride < 0 && end - 1 > start) {
+        return Err(VimError::Error(727, "Start past end".to_string()));
+    }
+    let mut items = Vec::new();
+    let mut n = start;
+    while (stride > 0 && n <= end) || (stride < 0 && n >= end) {
+        items.push(Value::Number(n));
+        n += stride;
+    }
+    Ok(Value::list(items))
+}
+
+/// `remove({list}, {idx} [, {end}])` or `remove({dict}, {key})`
+fn remove(args: &[Value]) -> VimResult<Value> {
+    match &args[0] {
+        Value::List(list) => {
+            let len = list.borrow().len();
+            let start =
+                list_index(args[1].as_number()?, len).ok_or_else(|| index_error(&args[1]))?;
+            let Some(end) = args.get(2) else {
+                return Ok(list.borrow_mut().remove(start));
+            };
+            let end = list_index(end.as_number()?, len).ok_or_else(|| index_error(end))?;
+            if end < start {
+                return Err(invalid_argument(&args[2].to_display()));
+            }
+            let removed = list.borrow_mut().drain(start..=end).collect();
+            Ok(Value::list(removed))
+        }
+        Value::Dict(dict) => {
+            if args.len() > 2 {
+                return Err(VimError::Error(
+                    118,
+                    "Too many arguments for function: remove".to_string(),
+                ));
+            }
+            let key = args[1].as_string()?;
+            let removed = dict.borrow_mut().remove(&key);
+            removed.ok_or_else(|| missing_key(&key))
+        }
+        _ => Err(VimError::Error(
+            896,
+            "Argument of remove() must be a List, Dictionary or Blob".to_string(),
+        )),
+    }
+}
+
+/// `sort({list} [, {how}])`: by `string()` of the items, with `how` `i`
+/// or 1 ignoring case, `n` numerically and `f` as Floats
+fn sort(args: &[Value]) -> VimResult<Value> {
+    let list = list_arg(&args[0], "sort")?;
+    let how = match args.get(1) {
+        Some(Value::Number(1)) => "i".to_string(),
+        Some(Value::Number(_)) | None => String::new(),
+        Some(how) => how.as_string()?,
+    };
+    let mut items = list.borrow().clone();
+    match how.as_str() {
+        "n" => {
+            let key = |value: &Value| match value {
+                Value::Number(n) => *n,
+                _ => i64::MIN,
+            };
+            items.sort_by_key(key);
+        }
+        "N" => {
+            let keys = items
+                .iter()
+                .map(Value::as_number)
+                .collect::<VimResult<Vec<_>>>()?;
+            let mut keyed: Vec<(i64, Value)> = keys.into_iter().zip(items).collect();
+            keyed.sort_by_key(|(key, _)| *key);
+            items = keyed.into_iter().map(|(_, value)| value).collect();
+        }
+        "f" => {
+            let keys = items.iter().map(float_arg).collect::<VimResult<Vec<_>>>()?;
+            let mut keyed: Vec<(f64, Value)> = keys.into_iter().zip(items).collect();
+            keyed.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+            items = keyed.into_iter().map(|(_, value)| value).collect();
+        }
+        "i" => items.sort_by_key(|value| value.to_display().to_lowercase()),
+        "" => items.sort_by_key(Value::to_display),
+        _ => return Err(invalid_argument(&how)),
+    }
+    *list.borrow_mut() = items;
+    Ok(args[0].clone())
+}
+
+/// `split({text} [, {pattern} [, {keepempty}]])`.  Without a pattern, or
+/// with `\s\+`, the text is split at runs of white space.
+fn split(
+    text: &str,
+    pattern: Option<&str>,
+    keepempty: bool,
+    ctx: &mut dyn EvalContext,
+) -> Vec<String> {
+    let pattern = pattern.filter(|pattern| !pattern.is_empty() && *pattern != "\\s\\+");
+    let Some(pattern) = pattern else {
+        let parts = text.split([' ', '\t']).map(str::to_string);
+        return parts.filter(|part| keepempty || !part.is_empty()).collect();
+    };
+    let ignore_case = eval::ignorecase(ctx);
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut from = 0;
+    while let Some((match_start, match_end)) = match_text(text, pattern, from, ignore_case) {
+        if match_end == match_start {
+            // An empty match splits between characters
+            let Some(c) = text[match_start..].chars().next() else {
+                break;
+            };
+            from = match_start + c.len_utf8();
+            if match_start == start {
+                continue;
+            }
+        } else {
+            from = match_end;
+        }
+        parts.push(text[start..match_start].to_string());
+        start = match_end;
+    }
+    parts.push(text[start..].to_string());
+    if !keepempty {
+        let last = parts.len() - 1;
+        parts = parts
+            .into_iter()
+            .enumerate()
+            .filter(|(idx, part)| !part.is_empty() || (*idx != 0 && *idx != last))
+            .map(|(_, part)| part)
+            .collect();
+    }
+    parts
+}
+
+/// `match()`, `matchend()` and `matchstr()` on a String, or on the items
+/// of a List
+fn match_function(name: &str, args: &[Value], ctx: &mut dyn EvalContext) -> VimResult<Value> {
+    let pattern = args[1].as_string()?;
+    let start = args.get(2).map(Value::as_number).transpose()?.unwrap_or(0);
+    let count = args
+        .get(3)
+        .map(Value::as_number)
+        .transpose()?
+        .unwrap_or(1)
+        .max(1);
+    let ignore_case = eval::ignorecase(ctx);
+    let not_found = || match name {
+        "matchstr" => Value::string(""),
+        _ => Value::Number(-1),
+    };
+    if let Value::List(list) = &args[0] {
+        let list = list.borrow();
+        let start = start.max(0) as usize;
+        let mut found = 0;
+        for (idx, item) in list.iter().enumerate().skip(start) {
+            let text = item.to_display();
+            let Some((from, to)) = match_text(&text, &pattern, 0, ignore_case) else {
+                continue;
+            };
+            found += 1;
+            if found == count {
+                return Ok(match name {
+                    "matchstr" => Value::String(text[from..to].to_string()),
+                    _ => Value::Number(idx as i64),
+                });
+            }
+        }
+        return Ok(not_found());
+    }
+    let text = args[0].as_string()?;
+    let mut from = start.max(0) as usize;
+    let mut found = None;
+    for _ in 0..count {
+        let Some((match_start, match_end)) = match_text(&text, &pattern, from, ignore_case) else {
+            return Ok(not_found());
+        };
+        found = Some((match_start, match_end));
+        from = if match_end > match_start {
+            match_end
+        } else {
+            match_end + text[match_end..].chars().next().map_or(1, char::len_utf8)
+        };
+    }
+    let Some((match_start, match_end)) = found else {
+        return Ok(not_found());
+    };
+    Ok(match name {
+        "match" => Value::Number(match_start as i64),
+        "matchend" => Value::Number(match_end as i64),
+        _ => Value::String(text[match_start..match_end].to_string()),
+    })
+}
+
+/// `substitute()`: replace the first match of `pattern` in `text`, or
+/// with `global` all of them, by `sub`.  In `sub`, `&` and `\0` stand for
+/// the matched text, `\u` and `\l` make the next character upper or
+/// lower case and `\U` and `\L` the text up to `\E`.  When `sub` starts
+/// with `\=` the rest is an expression evaluated for each match, in
+/// which `submatch(0)` is the matched text.
+pub fn substitute(
+    text: &str,
+    pattern: &str,
+    sub: &Value,
+    global: bool,
+    ctx: &mut dyn EvalContext,
+) -> VimResult<String> {
+    let sub = sub.as_string()?;
+    let expr = match sub.strip_prefix("\\=") {
+        Some(expr) => Some(eval::parse_complete(expr)?),
+        None => None,
+    };
+    let ignore_case = eval::ignorecase(ctx);
+    let mut out = String::new();
+    let mut copied = 0;
+    let mut from = 0;
+    while let Some((start, end)) = match_text(text, pattern, from, ignore_case) {
+        out.push_str(&text[copied..start]);
+        let matched = &text[start..end];
+        match &expr {
+            Some(expr) => {
+                let mut sub_ctx = SubmatchContext {
+                    inner: &mut *ctx,
+                    submatches: vec![matched.to_string()],
+                };
+                out.push_str(&expression_text(&eval::eval(expr, &mut sub_ctx)?)?);
+            }
+            None => out.push_str(&expand_replacement(&sub, matched)),
+        }
+        copied = end;
+        from = end;
+        if end == start {
+            // Go past an empty match, keeping the character
+            let Some(c) = text[end..].chars().next() else {
+                break;
+            };
+            out.push(c);
+            copied += c.len_utf8();
+            from = copied;
+        }
+        if !global {
+            break;
+        }
+    }
+    out.push_str(&text[copied.min(text.len())..]);
+    Ok(out)
+}
+
+/// The text a `\=` expression gives: a List is joined with line breaks
+pub fn expression_text(value: &Value) -> VimResult<String> {
+    match value {
+        Value::List(list) => Ok(list
+            .borrow()
+            .iter()
+            .map(Value::as_string)
+            .collect::<VimResult<Vec<_>>>()?
+            .join("\n")),
+        Value::Float(f) => Ok(format_float(*f)),
+        value => value.as_string(),
+    }
+}
+
+/// The replacement `sub` stands for with `matched` as the matched text
+fn expand_replacement(sub: &str, matched: &str) -> String {
+    #[derive(Clone, Copy, PartialEq)]
+    enum Case {
+        Keep,
+        Upper,
+        Lower,
+    }
+    let mut out = String::new();
+    let mut one = Case::Keep;
+    let mut all = Case::Keep;
+    let mut chars = sub.chars();
+    while let Some(c) = chars.next() {
+        let literal;
+        let text = match c {
+            '&' => matched,
+            '\\' => match chars.next() {
+                Some('0') => matched,
+                Some('1'..='9') => continue,
+                Some('n') => "\n",
+                Some('r') => "\r",
+                Some('t') => "\t",
+                Some('u') => {
+                    one = Case::Upper;
+                    continue;
+                }
+                Some('l') => {
+                    one = Case::Lower;
+                    continue;
+                }
+                Some('U') => {
+                    all = Case::Upper;
+                    continue;
+                }
+                Some('L') => {
+                    all = Case::Lower;
+                    continue;
+                }
+                Some('E' | 'e') => {
+                    all = Case::Keep;
+                    continue;
+                }
+                Some(other) => {
+                    literal = other.to_string();
+                    &literal
+                }
+                None => "\\",
+            },
+            c => {
+                literal = c.to_string();
+                &literal
+            }
+        };
+        for c in text.chars() {
+            let case = if one != Case::Keep { one } else { all };
+            one = Case::Keep;
+            match case {
+                Case::Upper => out.extend(c.to_uppercase()),
+                Case::Lower => out.extend(c.to_lowercase()),
+                Case::Keep => out.push(c),
+            }
+        }
+    }
+    out
+}
+
+/// `printf()`: `fmt` with each `%` item replaced by the next argument
+pub fn printf(fmt: &str, args: &[Value]) -> VimResult<String> {
+    let mut args = args.iter();
+    let mut next_arg = || {
+        args.next()
+            .ok_or_else(|| VimError::Error(766, "Insufficient arguments for printf()".to_string()))
+    };
+    let mut out = String::new();
+    let mut chars = fmt.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        if chars.peek() == Some(&'%') {
+            chars.next();
+            out.push('%');
+            continue;
+        }
+        let mut left = false;
+        let mut plus = false;
+        let mut space = false;
+        let mut zero = false;
+        let mut alternate = false;
+        while let Some(&flag) = chars.peek() {
+            match flag {
+                '-' => left = true,
+                '+' => plus = true,
+                ' ' => space = true,
+                '0' => zero = true,
+                '#' => alternate = true,
+                _ => break,
+            }
+            chars.next();
+        }
+        let mut width = 0;
+        if chars.peek() == Some(&'*') {
+            chars.next();
+            let n = next_arg()?.as_number()?;
+            left |= n < 0;
+            width = n.unsigned_abs() as usize;
+        } else {
+            while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+                width = width * 10 + digit as usize;
+                chars.next();
+            }
+        }
+        let mut precision = None;
+        if chars.peek() == Some(&'.') {
+            chars.next();
+            if chars.peek() == Some(&'*') {
+                chars.next();
+                precision = Some(next_arg()?.as_number()?.max(0) as usize);
+            } else {
+                let mut p = 0;
+                while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+                    p = p * 10 + digit as usize;
+                    chars.next();
+                }
+                precision = Some(p);
+            }
+        }
+        // Length modifiers make no difference with 64 bit Numbers
+        while matches!(chars.peek(), Some('h' | 'l' | 'L')) {
+            chars.next();
+        }
+        let Some(conversion) = chars.next() else {
+            break;
+        };
+        let sign = |negative: bool| match (negative, plus, space) {
+            (true, _, _) => "-",
+            (false, true, _) => "+",
+            (false, false, true) => " ",
+            _ => "",
+        };
+        let (prefix, body, numeric) = match conversion {
+            'd' | 'i' => {
+                let n = next_arg()?.as_number()?;
+                let mut digits = n.unsigned_abs().to_string();
+                if let Some(p) = precision {
+                    digits = format!("{:0>1$}", digits, p);
+                }
+                (sign(n < 0).to_string(), digits, precision.is_none())
+            }
+            'x' | 'X' | 'o' | 'b' | 'B' => {
+                let n = next_arg()?.as_number()? as u64;
+                let (digits, prefix) = match conversion {
+                    'x' => (format!("{:x}", n), "0x"),
+                    'X' => (format!("{:X}", n), "0X"),
+                    'o' => (format!("{:o}", n), "0"),
+                    'b' => (format!("{:b}", n), "0b"),
+                    _ => (format!("{:b}", n), "0B"),
+                };
+                let mut digits = digits;
+                if let Some(p) = precision {
+                    digits = format!("{:0>1$}", digits, p);
+                }
+                let prefix = if alternate && n != 0 { prefix } else { "" };
+                (prefix.to_string(), digits, precision.is_none())
+            }
+            'c' => {
+                let code = next_arg()?.as_number()?;
+                let c = u32::try_from(code).ok().and_then(char::from_u32);
+                (
+                    String::new(),
+                    c.map(String::from).unwrap_or_default(),
+                    false,
+                )
+            }
+            's' | 'S' => {
+                let mut text = next_arg()?.to_display();
+                if let Some(p) = precision {
+                    text = match conversion {
+                        's' => {
+                            let mut end = p.min(text.len());
+                            while !text.is_char_boundary(end) {
+                                end -= 1;
+                            }
+                            text[..end].to_string()
+                        }
+                        _ => text.chars().take(p).collect(),
+                    };
+                }
+                (String::new(), text, false)
+            }
+            'f' | 'F' | 'e' | 'E' | 'g' | 'G' => {
+                let f = match next_arg()? {
+                    Value::Float(f) => *f,
+                    Value::Number(n) => *n as f64,
+                    value => value.as_float()?,
+                };
+                let p = precision.unwrap_or(6);
+                let mut body = match conversion {
+                    'f' | 'F' if f.is_finite() => format!("{:.*}", p, f.abs()),
+                    'e' | 'E' => format_e(f.abs(), p),
+                    'g' | 'G' => format_g(f.abs(), p, alternate),
+                    _ => format_float(f.abs()),
+                };
+                if conversion.is_ascii_uppercase() {
+                    body = body.to_uppercase();
+                }
+                let negative = f.is_sign_negative() && !f.is_nan();
+                (sign(negative).to_string(), body, f.is_finite())
+            }
+            other => {
+                return Err(VimError::Error(
+                    767,
+                    format!("Invalid format specifier: %{}", other),
+                ))
+            }
+        };
+        let len = prefix.chars().count() + body.chars().count();
+        let pad = width.saturating_sub(len);
+        if left {
+            out.push_str(&prefix);
+            out.push_str(&body);
+            out.push_str(&" ".repeat(pad));
+        } else if zero && numeric {
+            out.push_str(&prefix);
+            out.push_str(&"0".repeat(pad));
+            out.push_str(&body);
+        } else {
+            out.push_str(&" ".repeat(pad));
+            out.push_str(&prefix);
+            out.push_str(&body);
+        }
+    }
+    if args.next().is_some() {
+        return Err(VimError::Error(
+            767,
+            "Too many arguments for printf()".to_string(),
+        ));
+    }
+    Ok(out)
+}
+
+/// A Dictionary built from `(key, value)` pairs
+pub fn dict_from<K: Into<String>>(items: impl IntoIterator<Item = (K, Value)>) -> Value {
+    Value::dict(
+        items
+            .into_iter()
+            .map(|(key, value)| (key.into(), value))
+            .collect::<BTreeMap<_, _>>(),
+    )
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::{eval_expr, SimpleEvalContext};
+
+    fn echo(text: &str) -> String {
+        let mut ctx = SimpleEvalContext::new();
+        ctx.registers.insert('a', "line\n".to_string());
+        eval_expr(text, &mut ctx).unwrap().to_display()
+    }
+
+    fn error(text: &str) -> String {
+        eval_expr(text, &mut SimpleEvalContext::new())
+            .unwrap_err()
+            .to_string()
+    }
+
+    #[test]
+    fn test_string_functions() {
+        assert_eq!(echo("strlen('héllo')"), "6");
+        assert_eq!(echo("strchars('héllo')"), "5");
+        assert_eq!(echo("toupper('abc') . tolower('DEF')"), "ABCdef");
+        assert_eq!(echo("repeat('ab', 3)"), "ababab");
+        assert_eq!(echo("repeat([1], 2)"), "[1, 1]");
+        assert_eq!(echo("strpart('abcdef', 2, 3)"), "cde");
+        assert_eq!(echo("strpart('abcdef', -1, 3)"), "ab");
+        assert_eq!(echo("stridx('abcabc', 'c', 3)"), "5");
+        assert_eq!(echo("strridx('abcabc', 'b')"), "4");
+        assert_eq!(echo("tr('hello', 'el', 'ip')"), "hippo");
+        assert_eq!(echo("trim('  x  ') . '|'"), "x|");
+        assert_eq!(echo("escape('a b', ' ')"), "a\\ b");
+        assert_eq!(echo("string(['a', 1.5])"), "['a', 1.5]");
+        assert_eq!(echo("char2nr('A') . nr2char(66)"), "65B");
+        assert_eq!(echo("str2nr('ff', 16)"), "255");
+    }
+
+    #[test]
+    fn test_printf() {
+        assert_eq!(
+            echo("printf('%d|%5d|%-5d|%05d', 1, 2, 3, -4)"),
+            "1|    2|3    |-0004"
+        );
+        assert_eq!(
+            echo("printf('%x %X %#x %o %b', 255, 255, 255, 8, 5)"),
+            "ff FF 0xff 10 101"
+        );
+        assert_eq!(echo("printf('%s=%S', 'a', [1])"), "a=[1]");
+        assert_eq!(
+            echo("printf('%.3s|%6.2f|%e', 'abcdef', 3.14159, 1500)"),
+            "abc|  3.14|1.500000e+03"
+        );
+        assert_eq!(echo("printf('%g %g %c%%', 0.5, 1.0e20, 65)"), "0.5 1e+20 A%");
+        assert_eq!(echo("printf('%*d', 4, 7)"), "   7");
+        assert_eq!(
+            error("printf('%d')"),
+            "E766: Insufficient arguments for printf()"
+        );
+        assert_eq!(
+            error("printf('x', 1)"),
+            "E767: Too many arguments for printf()"
+        );
+    }
+
+    #[test]
+    fn test_substitute() {
+        assert_eq!(echo("substitute('aaa', 'a', 'b', '')"), "baa");
+        assert_eq!(echo("substitute('aaa', 'a', 'b', 'g')"), "bbb");
+        assert_eq!(echo("substitute('foo bar', 'bar', '[&]', '')"), "foo [bar]");
+        assert_eq!(echo("substitute('foo', 'foo', '\\u&', '')"), "Foo");
+        assert_eq!(
+            echo("substitute('foo', 'o', '\\=toupper(submatch(0))', 'g')"),
+            "fOO"
+        );
+        assert_eq!(echo("substitute('abc', '^', '>', 'g')"), ">abc");
+        assert_eq!(echo("substitute('abc', '$', '<', '')"), "abc<");
+    }
+
+    #[test]
+    fn test_list_functions() {
+        assert_eq!(echo("len([1, 2]) + len('abc') + len({'a': 1})"), "6");
+        assert_eq!(echo("add([1], 2)"), "[1, 2]");
+        assert_eq!(echo("insert([1], 0)"), "[0, 1]");
+        assert_eq!(echo("extend([1, 2], [3], 1)"), "[1, 3, 2]");
+        assert_eq!(echo("remove([1, 2, 3], 1)"), "2");
+        assert_eq!(echo("remove([1, 2, 3], 0, 1)"), "[1, 2]");
+        assert_eq!(echo("index([1, 2, 3], 3)"), "2");
+        assert_eq!(echo("join([1, 'a', [2]], '-')"), "1-a-[2]");
+        assert_eq!(echo("sort([3, 10, 2])"), "[10, 2, 3]");
+        assert_eq!(echo("sort([3, 10, 2], 'n')"), "[2, 3, 10]");
+        assert_eq!(echo("reverse(range(3))"), "[2, 1, 0]");
+        assert_eq!(echo("range(2, 8, 3)"), "[2, 5, 8]");
+        assert_eq!(echo("uniq([1, 1, 2, 1])"), "[1, 2, 1]");
+        assert_eq!(echo("max([3, 9, 2]) . min([3, 9, 2])"), "92");
+        assert_eq!(echo("count([1, 2, 1], 1) . count('abab', 'ab')"), "22");
+        assert_eq!(echo("split(' a  b ')"), "['a', 'b']");
+        assert_eq!(echo("split('a,b,,c', ',')"), "['a', 'b', '', 'c']");
+        assert_eq!(echo("split(',a,', ',', 1)"), "['', 'a', '']");
+        assert_eq!(error("range(0, 1, 0)"), "E726: Stride is zero");
+    }
+
+    #[test]
+    fn test_dict_functions() {
+        assert_eq!(echo("keys({'b': 1, 'a': 2})"), "['a', 'b']");
+        assert_eq!(echo("values({'b': 1, 'a': 2})"), "[2, 1]");
+        assert_eq!(echo("items({'a': 1})"), "[['a', 1]]");
+        assert_eq!(
+            echo("has_key({'a': 1}, 'a') . has_key({'a': 1}, 'b')"),
+            "10"
+        );
+        assert_eq!(echo("get({'a': 1}, 'b', 'none')"), "none");
+        assert_eq!(
+            echo("extend({'a': 1}, {'a': 2, 'b': 3}, 'keep')"),
+            "{'a': 1, 'b': 3}"
+        );
+        assert_eq!(echo("remove({'a': 1}, 'a')"), "1");
+    }
+
+    #[test]
+    fn test_match_functions() {
+        assert_eq!(echo("match('foobar', 'ob')"), "2");
+        assert_eq!(echo("matchend('foobar', 'ob')"), "4");
+        assert_eq!(echo("matchstr('foobar', 'bar$')"), "bar");
+        assert_eq!(echo("match('foobar', 'x')"), "-1");
+        assert_eq!(echo("match(['a', 'xb', 'b'], 'b')"), "1");
+        assert_eq!(echo("match('abab', 'ab', 0, 2)"), "2");
+    }
+
+    #[test]
+    fn test_other_functions() {
+        assert_eq!(
+            echo("type(1) . type('') . type([]) . type({}) . type(1.0)"),
+            "01345"
+        );
+        assert_eq!(echo("empty('') . empty([1]) . empty(0)"), "101");
+        assert_eq!(echo("abs(-3) . float2nr(2.7)"), "32");
+        assert_eq!(echo("floor(2.7)"), "2.0");
+        assert_eq!(echo("and(12, 10) . or(12, 10) . xor(12, 10)"), "8146");
+        assert_eq!(echo("getreg('a')"), "line\n");
+        assert_eq!(echo("getreg('a', 1, 1)"), "['line']");
+        assert_eq!(
+            echo("exists('*strlen') . exists('*nosuch') . exists('$NOSUCH')"),
+            "100"
+        );
+        assert_eq!(
+            error("strlen()"),
+            "E119: Not enough arguments for function: strlen"
+        );
+        assert_eq!(
+            error("strlen(1, 2)"),
+            "E118: Too many arguments for function: strlen"
+        );
+        assert_eq!(
+            error("submatch(0)"),
+            "E935: submatch() used outside of a substitute"
+        );
+    }
+
+    #[test]
+    fn test_copies() {
+        let mut ctx = SimpleEvalContext::new();
+        ctx.variables
+            .insert("l".to_string(), Value::list(vec![Value::list(vec![])]));
+        let copy = eval_expr("copy(l)", &mut ctx).unwrap();
+        let deep = eval_expr("deepcopy(l)", &mut ctx).unwrap();
+        ctx.variables.insert("c".to_string(), copy);
+        ctx.variables.insert("d".to_string(), deep);
+        assert_eq!(
+            eval_expr("c[0] is l[0]", &mut ctx).unwrap(),
+            Value::Number(1)
+        );
+        assert_eq!(
+            eval_expr("d[0] is l[0]", &mut ctx).unwrap(),
+            Value::Number(0)
+        );
+        assert_eq!(eval_expr("c is l", &mut ctx).unwrap(), Value::Number(0));
+    }
+
+    #[test]
+    fn test_dict_from() {
+        let dict = dict_from([("a", Value::Number(1))]);
+        assert_eq!(dict.to_repr(), "{'a': 1}");
+    }
+}
@@ -114,6 +114,984 @@ pub trait FoldManager {
     fn recompute(&mut self);
 }
 
+// ============================================================================
+// Fold Options
+// ============================================================================
+
+/// Fold-related option values used when computing and displaying folds
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FoldOptions {
+    /// 'foldlevel': folds with a higher level are closed
+    pub foldlevel: usize,
+    /// 'foldignore': lines starting with one of these take the level of
+    /// their neighbours (indent method)
+    pub foldignore: String,
+    /// 'foldnestmax': maximum nesting for the indent method
+    pub foldnestmax: usize,
+    /// 'foldminlines': a fold must have more lines than this to be displayed closed
+    pub foldminlines: usize,
+    /// 'foldmarker': start and end markers separated by a comma
+    pub foldmarker: String,
+    /// 'foldopen': kinds of commands that open a fold the cursor moves into
+    pub foldopen: String,
+    /// 'foldclose': "all" closes a fold when the cursor leaves it
+    pub foldclose: String,
+    /// 'shiftwidth': indent per fold level (0 means use 'tabstop')
+    pub shiftwidth: usize,
+    /// 'tabstop': width of a tab when measuring indent
+    pub tabstop: usize,
+}
+
+impl Default for FoldOptions {
+    fn default() -> Self {
+        FoldOptions {
+            foldlevel: 0,
+            foldignore: "#".to_string(),
+            foldnestmax: 20,
+            foldminlines: 1,
+            foldmarker: "{{{,}}}".to_string(),
+            foldopen: "block,hor,mark,percent,quickfix,search,tag,undo".to_string(),
+            foldclose: String::new(),
+            shiftwidth: 8,
+            tabstop: 8,
+        }
+    }
+}
+
+impl FoldOptions {
+    /// Check whether 'foldopen' contains `item` (or "all")
+    pub fn opens_for(&self, item: &str) -> bool {
+        self.foldopen
+            .split(',')
+            .any(|part| part == item || part == "all")
+    }
+
+    /// Check whether 'foldclose' is "all"
+    pub fn closes_on_leave(&self) -> bool {
+        self.foldclose.split(',').any(|part| part == "all")
+    }
+
+    /// Split 'foldmarker' into its start and end markers
+    pub fn markers(&self) -> (&str, &str) {
+        match self.foldmarker.split_once(',') {
+            Some((start, end)) if !start.is_empty() && !end.is_empty() => (start, end),
+            _ => ("{{{", "}}}"),
+        }
+    }
+}
+
+/// A single line's result of evaluating 'foldexpr'
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FoldExprLevel {
+    /// "N": the line has fold level N
+    Level(usize),
+    /// ">N": a fold with level N starts at this line
+    Start(usize),
+    /// "<N": a fold with level N ends at this line
+    End(usize),
+    /// "aN": add N to the level of the previous line
+    Add(usize),
+    /// "sN": subtract N from the level of the previous line
+    Sub(usize),
+    /// "=": use the level of the previous line
+    Same,
+    /// "-1": undefined, use the lower level of the lines around it
+    Undefined,
+}
+
+impl FoldExprLevel {
+    /// Parse the string form returned by 'foldexpr'
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        let number = |s: &str| s.parse::<usize>().ok();
+        match value {
+            "=" => Some(FoldExprLevel::Same),
+            "-1" => Some(FoldExprLevel::Undefined),
+            _ => {
+                let mut chars = value.chars();
+                match chars.next()? {
+                    '>' => number(chars.as_str()).map(FoldExprLevel::Start),
+                    '<' => number(chars.as_str()).map(FoldExprLevel::End),
+                    'a' => number(chars.as_str()).map(FoldExprLevel::Add),
+                    's' => number(chars.as_str()).map(FoldExprLevel::Sub),
+                    _ => number(value).map(FoldExprLevel::Level),
+                }
+            }
+        }
+    }
+}
+
+/// Evaluates 'foldexpr' for a line, given the buffer lines
+pub type FoldExprFn = Box<dyn Fn(LineNr, &[String]) -> String>;
+
+// ============================================================================
+// Buffer Fold Manager
+// ============================================================================
+
+/// Per-line fold information: the line's level, the level from which new
+/// folds start on this line, and the level carried on to the next line.
+#[derive(Debug, Clone, Copy)]
+struct LineLevel {
+    level: usize,
+    start_at: Option<usize>,
+    next: usize,
+}
+
+/// A fold manager over in-memory buffer lines.
+///
+/// Manual folds are kept in a tree and shifted by [`BufferFoldManager::adjust`]
+/// as lines are inserted or deleted. Indent, marker and expr folds are
+/// recomputed from the lines, keeping the open/closed state of folds that
+/// still start at the same line. Syntax and diff folds need highlighting and
+/// diff information that this manager does not have, so they produce no folds.
+#[derive(Default)]
+pub struct BufferFoldManager {
+    lines: Vec<String>,
+    method: FoldMethod,
+    options: FoldOptions,
+    folds: Vec<Fold>,
+    foldexpr: Option<FoldExprFn>,
+}
+
+impl std::fmt::Debug for BufferFoldManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BufferFoldManager")
+            .field("lines", &self.lines)
+            .field("method", &self.method)
+            .field("options", &self.options)
+            .field("folds", &self.folds)
+            .finish()
+    }
+}
+
+impl BufferFoldManager {
+    /// Create a fold manager with the given lines (manual method, no folds).
+    pub fn new(lines: Vec<String>) -> Self {
+        BufferFoldManager {
+            lines,
+            ..Default::default()
+        }
+    }
+
+    /// Replace the buffer lines, recomputing folds for non-manual methods.
+    pub fn set_lines(&mut self, lines: Vec<String>) {
+        self.lines = lines;
+        if self.method != FoldMethod::Manual {
+            self.recompute();
+        }
+    }
+
+    /// Get the fold options
+    pub fn options(&self) -> &FoldOptions {
+        &self.options
+    }
+
+    /// Replace the fold options and recompute folds.
+    ///
+    /// Changing 'foldlevel' re-applies it to every fold, like `:set foldlevel`.
+    pub fn set_options(&mut self, options: FoldOptions) {
+        let level_changed = options.foldlevel != self.options.foldlevel;
+        self.options = options;
+        if self.method != FoldMethod::Manual {
+            self.recompute();
+        }
+        if level_changed {
+            self.apply_foldlevel();
+        }
+    }
+
+    /// Set the function used to evaluate 'foldexpr' for the expr method.
+    pub fn set_foldexpr(&mut self, foldexpr: FoldExprFn) {
+        self.foldexpr = Some(foldexpr);
+        if self.method == FoldMethod::Expr {
+            self.recompute();
+        }
+    }
+
+    /// Get the top-level folds
+    pub fn folds(&self) -> &[Fold] {
+        &self.folds
+    }
+
+    /// Get the current 'foldlevel'
+    pub fn foldlevel(&self) -> usize {
+        self.options.foldlevel
+    }
+
+    /// Set 'foldlevel' and open/close every fold accordingly.
+    pub fn set_foldlevel(&mut self, level: usize) {
+        self.options.foldlevel = level;
+        self.apply_foldlevel();
+    }
+
+    /// Deepest fold nesting in the buffer
+    pub fn max_depth(&self) -> usize {
+        fn depth(folds: &[Fold]) -> usize {
+            folds
+                .iter()
+                .map(|fold| 1 + depth(&fold.nested))
+                .max()
+                .unwrap_or(0)
+        }
+        depth(&self.folds)
+    }
+
+    /// Get the outermost closed fold containing `line`, i.e. the range
+    /// displayed as a single fold line. Folds with no more than
+    /// 'foldminlines' lines are never displayed closed.
+    pub fn closed_fold_at(&self, line: LineNr) -> Option<&Fold> {
+        let mut folds = &self.folds;
+        while let Some(fold) = folds.iter().find(|fold| fold.contains(line)) {
+            if self.displays_closed(fold) {
+                return Some(fold);
+            }
+            folds = &fold.nested;
+        }
+        None
+    }
+
+    /// Open one fold under the cursor, `count` levels deep (`zo`).
+    pub fn open_count(&mut self, line: LineNr, count: usize) -> VimResult<()> {
+        for _ in 0..count.max(1) {
+            self.open(line)?;
+        }
+        Ok(())
+    }
+
+    /// Open the fold under the cursor and everything nested in it (`zO`).
+    pub fn open_recursive(&mut self, line: LineNr) -> VimResult<()> {
+        let path = self.require_path(line)?;
+        if let Some(depth) = self.first_closed(&path) {
+            let fold = self.fold_at_mut(&path[..=depth]);
+            Self::set_state_recursive(std::slice::from_mut(fold), FoldState::Open);
+        }
+        Ok(())
+    }
+
+    /// Close every fold containing the cursor (`zC`).
+    pub fn close_recursive(&mut self, line: LineNr) -> VimResult<()> {
+        let path = self.require_path(line)?;
+        for depth in 0..path.len() {
+            self.fold_at_mut(&path[..=depth]).state = FoldState::Closed;
+        }
+        Ok(())
+    }
+
+    /// Open the fold recursively when closed, close it recursively when open (`zA`).
+    pub fn toggle_recursive(&mut self, line: LineNr) -> VimResult<()> {
+        let path = self.require_path(line)?;
+        if self.first_closed(&path).is_some() {
+            self.open_recursive(line)
+        } else {
+            self.close_recursive(line)
+        }
+    }
+
+    /// Open just enough folds to make `line` visible (`zv`).
+    pub fn view_line(&mut self, line: LineNr) {
+        let path = self.path(line);
+        for depth in 0..path.len() {
+            self.fold_at_mut(&path[..=depth]).state = FoldState::Open;
+        }
+    }
+
+    /// Re-apply 'foldlevel' and then make `line` visible (`zx`).
+    pub fn update(&mut self, line: LineNr) {
+        self.apply_foldlevel();
+        self.view_line(line);
+    }
+
+    /// Subtract `count` from 'foldlevel' (`zm`).
+    pub fn fold_more(&mut self, count: usize) {
+        let level = self.options.foldlevel.min(self.max_depth());
+        self.set_foldlevel(level.saturating_sub(count.max(1)));
+    }
+
+    /// Add `count` to 'foldlevel', up to the deepest fold (`zr`).
+    pub fn reduce_folding(&mut self, count: usize) {
+        let level = (self.options.foldlevel + count.max(1)).min(self.max_depth());
+        self.set_foldlevel(level);
+    }
+
+    /// Start of the current open fold, or of the fold containing it when
+    /// already at the start (`[z`).
+    pub fn fold_start(&self, line: LineNr) -> Option<LineNr> {
+        let line = self.closed_fold_at(line).map_or(line, |fold| fold.start);
+        self.visible_path(line)
+            .into_iter()
+            .rev()
+            .map(|fold| fold.start)
+            .find(|start| *start < line)
+    }
+
+    /// End of the current open fold, or of the fold containing it when
+    /// already at the end (`]z`).
+    pub fn fold_end(&self, line: LineNr) -> Option<LineNr> {
+        let line = self.closed_fold_at(line).map_or(line, |fold| fold.end);
+        self.visible_path(line)
+            .into_iter()
+            .rev()
+            .map(|fold| fold.end)
+            .find(|end| *end > line)
+    }
+
+    /// Start of the next fold below `line`; a closed fold counts as one (`zj`).
+    pub fn next_fold_start(&self, line: LineNr) -> Option<LineNr> {
+        let after = self.closed_fold_at(line).map_or(line, |fold| fold.end);
+        let mut starts = Vec::new();
+        self.collect_visible(&self.folds, &mut |fold| starts.push(fold.start));
+        starts.into_iter().filter(|start| *start > after).min()
+    }
+
+    /// End of the previous fold above `line`; a closed fold counts as one (`zk`).
+    pub fn prev_fold_end(&self, line: LineNr) -> Option<LineNr> {
+        let before = self.closed_fold_at(line).map_or(line, |fold| fold.start);
+        let mut ends = Vec::new();
+        self.collect_visible(&self.folds, &mut |fold| ends.push(fold.end));
+        ends.into_iter().filter(|end| *end < before).max()
+    }
+
+    /// Apply 'foldclose' after the cursor moved from `from` to `to`: with
+    /// "all", folds the cursor left whose level is above 'foldlevel' close.
+    pub fn cursor_moved(&mut self, from: LineNr, to: LineNr) {
+        if !self.options.closes_on_leave() {
+            return;
+        }
+        let foldlevel = self.options.foldlevel;
+        fn close_left(folds: &mut [Fold], from: LineNr, to: LineNr, foldlevel: usize) {
+            for fold in folds.iter_mut().filter(|fold| fold.contains(from)) {
+                if !fold.contains(to) && fold.level > foldlevel {
+                    fold.state = FoldState::Closed;
+                }
+                close_left(&mut fold.nested, from, to, foldlevel);
+            }
+        }
+        close_left(&mut self.folds, from, to, foldlevel);
+    }
+
+    /// Shift folds after lines were inserted (`lines_added > 0`) before
+    /// `line`, or deleted (`lines_added < 0`) starting at `line`.
+    ///
+    /// Folds inside a deleted range disappear; folds that overlap it shrink.
+    pub fn adjust(&mut self, line: LineNr, lines_added: i64) {
+        fn insert(folds: &mut [Fold], at: usize, count: usize) {
+            for fold in folds.iter_mut() {
+                if fold.start.0 >= at {
+                    fold.start.0 += count;
+                    fold.end.0 += count;
+                } else if fold.end.0 >= at {
+                    fold.end.0 += count;
+                }
+                insert(&mut fold.nested, at, count);
+            }
+        }
+        fn delete(folds: &mut Vec<Fold>, first: usize, last: usize) {
+            let count = last - first + 1;
+            let shift = |lnum: usize, is_end: bool| {
+                if lnum < first {
+                    lnum
+                } else if lnum > last {
+                    lnum - count
+                } else if is_end {
+                    first - 1
+                } else {
+                    first
+                }
+            };
+            for fold in folds.iter_mut() {
+                fold.start.0 = shift(fold.start.0, false);
+                fold.end.0 = shift(fold.end.0, true);
+                delete(&mut fold.nested, first, last);
+            }
+            folds.retain(|fold| fold.start.0 >= 1 && fold.end.0 >= fold.start.0);
+        }
+
+        let at = line.0.max(1);
+        match lines_added {
+            n if n > 0 => insert(&mut self.folds, at, n as usize),
+            n if n < 0 => delete(&mut self.folds, at, at + n.unsigned_abs() as usize - 1),
+            _ => {}
+        }
+    }
+
+    fn displays_closed(&self, fold: &Fold) -> bool {
+        fold.state == FoldState::Closed && fold.line_count() > self.options.foldminlines
+    }
+
+    /// Indices of the folds containing `line`, outermost first.
+    fn path(&self, line: LineNr) -> Vec<usize> {
+        let mut path = Vec::new();
+        let mut folds = &self.folds;
+        while let Some(idx) = folds.iter().position(|fold| fold.contains(line)) {
+            path.push(idx);
+            folds = &folds[idx].nested;
+        }
+        path
+    }
+
+    fn require_path(&self, line: LineNr) -> VimResult<Vec<usize>> {
+        let path = self.path(line);
+        if path.is_empty() {
+            return Err(VimError::Error(490, "No fold found".to_string()));
+        }
+        Ok(path)
+    }
+
+    fn fold_at(&self, path: &[usize]) -> &Fold {
+        let mut fold = &self.folds[path[0]];
+        for &idx in &path[1..] {
+            fold = &fold.nested[idx];
+        }
+        fold
+    }
+
+    fn fold_at_mut(&mut self, path: &[usize]) -> &mut Fold {
+        let mut fold = &mut self.folds[path[0]];
+        for &idx in &path[1..] {
+            fold = &mut fold.nested[idx];
+        }
+        fold
+    }
+
+    /// Depth of the outermost closed fold along `path`.
+    fn first_closed(&self, path: &[usize]) -> Option<usize> {
+        (0..path.len()).find(|&depth| self.fold_at(&path[..=depth]).state == FoldState::Closed)
+    }
+
+    /// Folds containing `line` that are not hidden inside a closed fold.
+    fn visible_path(&self, line: LineNr) -> Vec<&Fold> {
+        let mut visible = Vec::new();
+        let mut folds = &self.folds;
+        while let Some(fold) = folds.iter().find(|fold| fold.contains(line)) {
+            visible.push(fold);
+            if self.displays_closed(fold) {
+                break;
+            }
+            folds = &fold.nested;
+        }
+        visible
+    }
+
+    fn collect_visible<'a>(&self, folds: &'a [Fold], visit: &mut dyn FnMut(&'a Fold)) {
+        for fold in folds {
+            visit(fold);
+            if !self.displays_closed(fold) {
+                self.collect_visible(&fold.nested, visit);
+            }
+        }
+    }
+
+    fn set_state_recursive(folds: &mut [Fold], state: FoldState) {
+        for fold in folds {
+            fold.state = state;
+            Self::set_state_recursive(&mut fold.nested, state);
+        }
+    }
+
+    fn apply_foldlevel(&mut self) {
+        fn apply(folds: &mut [Fold], foldlevel: usize) {
+            for fold in folds {
+                fold.state = if fold.level > foldlevel {
+                    FoldState::Closed
+                } else {
+                    FoldState::Open
+                };
+                apply(&mut fold.nested, foldlevel);
+            }
+        }
+        apply(&mut self.folds, self.options.foldlevel);
+    }
+
+    fn set_levels(folds: &mut [Fold], level: usize) {
+        for fold in folds {
+            fold.level = level;
+            Self::set_levels(&mut fold.nested, level + 1);
+        }
+    }
+
+    fn insert_manual(folds: &mut Vec<Fold>, mut start: usize, mut end: usize) {
+        if let Some(parent) = folds
+            .iter_mut()
+            .find(|fold| fold.start.0 <= start && fold.end.0 >= end)
+        {
+            Self::insert_manual(&mut parent.nested, start, end);
+            return;
+        }
+        // Folds overlapping the new range become nested in it, and the new
+        // fold grows to cover them entirely.
+        let (inside, outside): (Vec<Fold>, Vec<Fold>) = std::mem::take(folds)
+            .into_iter()
+            .partition(|fold| fold.end.0 >= start && fold.start.0 <= end);
+        for fold in &inside {
+            start = start.min(fold.start.0);
+            end = end.max(fold.end.0);
+        }
+        *folds = outside;
+        let pos = folds
+            .iter()
+            .position(|fold| fold.start.0 > start)
+            .unwrap_or(folds.len());
+        folds.insert(
+            pos,
+            Fold {
+                start: LineNr(start),
+                end: LineNr(end),
+                level: 0,
+                state: FoldState::Closed,
+                nested: inside,
+            },
+        );
+    }
+
+    fn indent_width(&self, line: &str) -> usize {
+        let tabstop = self.options.tabstop.max(1);
+        let mut width = 0;
+        for ch in line.chars() {
+            match ch {
+                ' ' => width += 1,
+                '\t' => width += tabstop - width % tabstop,
+                _ => break,
+            }
+        }
+        width
+    }
+
+    fn indent_levels(&self) -> Vec<LineLevel> {
+        let shiftwidth = match self.options.shiftwidth {
+            0 => self.options.tabstop.max(1),
+            sw => sw,
+        };
+        let raw: Vec<Option<usize>> = self
+            .lines
+            .iter()
+            .map(|line| {
+                let text = line.trim_start();
+                let ignored = text
+                    .chars()
+                    .next()
+                    .is_none_or(|ch| self.options.foldignore.contains(ch));
+                if ignored {
+                    None
+                } else {
+                    Some((self.indent_width(line) / shiftwidth).min(self.options.foldnestmax))
+                }
+            })
+            .collect();
+        Self::resolve_undefined(&raw)
+            .into_iter()
+            .map(|level| LineLevel {
+                level,
+                start_at: None,
+                next: level,
+            })
+            .collect()
+    }
+
+    /// Undefined levels take the lower of the nearest defined levels above and below.
+    fn resolve_undefined(raw: &[Option<usize>]) -> Vec<usize> {
+        (0..raw.len())
+            .map(|idx| {
+                raw[idx].unwrap_or_else(|| {
+                    let above = raw[..idx].iter().rev().find_map(|level| *level);
+                    let below = raw[idx + 1..].iter().find_map(|level| *level);
+                    match (above, below) {
+                        (Some(a), Some(b)) => a.min(b),
+                        (Some(l), None) | (None, Some(l)) => l,
+                        (None, None) => 0,
+                    }
+                })
+            })
+            .collect()
+    }
+
+    fn marker_levels(&self) -> Vec<LineLevel> {
+        let (start_marker, end_marker) = self.options.markers();
+        let digits = |text: &str| -> (Option<usize>, usize) {
+            let len = text.bytes().take_while(u8::is_ascii_digit).count();
+            (text[..len].parse().ok().filter(|n| *n > 0), len)
+        };
+        let mut prev_next = 0;
+        let mut levels = Vec::with_capacity(self.lines.len());
+        for line in &self.lines {
+            let start_lvl = prev_next;
+            let mut level = start_lvl;
+            let mut next = start_lvl;
+            let mut starts = 0;
+            let mut idx = 0;
+            while idx < line.len() {
+                let rest = &line[idx..];
+                if let Some(after) = rest.strip_prefix(start_marker) {
+                    let (number, len) = digits(after);
+                    match number {
+                        Some(n) => {
+                            level = n;
+                            next = n;
+                            starts = if n <= start_lvl { 1 } else { n - start_lvl };
+                        }
+                        None => {
+                            level += 1;
+                            next += 1;
+                            starts += 1;
+                        }
+                    }
+                    idx += start_marker.len() + len;
+                } else if let Some(after) = rest.strip_prefix(end_marker) {
+                    let (number, len) = digits(after);
+                    match number {
+                        Some(n) => {
+                            level = n;
+                            // Never start a fold with an end marker.
+                            next = (n - 1).min(start_lvl);
+                        }
+                        None => next = next.saturating_sub(1),
+                    }
+                    idx += end_marker.len() + len;
+                } else {
+                    idx += rest.chars().next().map_or(1, char::len_utf8);
+                }
+            }
+            levels.push(LineLevel {
+                level,
+                start_at: (starts > 0).then(|| (level + 1).saturating_sub(starts).max(1)),
+                next,
+            });
+            prev_next = next;
+        }
+        levels
+    }
+
+    fn expr_levels(&self) -> Vec<LineLevel> {
+        let Some(foldexpr) = &self.foldexpr else {
+            return Vec::new();
+        };
+        let results: Vec<FoldExprLevel> = (0..self.lines.len())
+            .map(|idx| {
+                FoldExprLevel::parse(&foldexpr(LineNr::from_zero_indexed(idx), &self.lines))
+                    .unwrap_or(FoldExprLevel::Level(0))
+            })
+            .collect();
+        // Resolve "-1" first, using the explicit levels of neighbouring lines.
+        let explicit: Vec<Option<usize>> = results
+            .iter()
+            .map(|result| match result {
+                FoldExprLevel::Level(n) | FoldExprLevel::Start(n) | FoldExprLevel::End(n) => {
+                    Some(*n)
+                }
+                FoldExprLevel::Undefined => None,
+                _ => Some(usize::MAX),
+            })
+            .collect();
+        let mut prev_next = 0;
+        let mut levels = Vec::with_capacity(results.len());
+        for (idx, result) in results.iter().enumerate() {
+            let line_level = match *result {
+                FoldExprLevel::Level(n) => LineLevel {
+                    level: n,
+                    start_at: None,
+                    next: n,
+                },
+                FoldExprLevel::Start(n) => LineLevel {
+                    level: n,
+                    start_at: Some(n.max(1)),
+                    next: n,
+                },
+                FoldExprLevel::End(n) => LineLevel {
+                    level: n,
+                    start_at: None,
+                    next: n.saturating_sub(1),
+                },
+                FoldExprLevel::Add(n) => {
+                    let level = prev_next + n;
+                    LineLevel {
+                        level,
+                        start_at: None,
+                        next: level,
+                    }
+                }
+                FoldExprLevel::Sub(n) => {
+                    let level = prev_next.saturating_sub(n);
+                    LineLevel {
+                        level,
+                        start_at: None,
+                        next: level,
+                    }
+                }
+                FoldExprLevel::Same => LineLevel {
+                    level: prev_next,
+                    start_at: None,
+                    next: prev_next,
+                },
+                FoldExprLevel::Undefined => {
+                    let below = explicit[idx + 1..]
+                        .iter()
+                        .find_map(|level| *level)
+                        .filter(|level| *level != usize::MAX);
+                    let level = below.map_or(prev_next, |below| below.min(prev_next));
+                    LineLevel {
+                        level,
+                        start_at: None,
+                        next: level,
+                    }
+                }
+            };
+            prev_next = line_level.next;
+            levels.push(line_level);
+        }
+        levels
+    }
+
+    /// Turn per-line levels into (start, end) fold ranges.
+    fn ranges(levels: &[LineLevel]) -> Vec<(usize, usize)> {
+        let mut ranges = Vec::new();
+        let mut open: Vec<usize> = Vec::new();
+        for (idx, line) in levels.iter().enumerate() {
+            let lnum = idx + 1;
+            let keep = line
+                .start_at
+                .map_or(line.level, |start| start.saturating_sub(1).min(line.level));
+            while open.len() > keep {
+                let start = open.pop().unwrap_or(lnum);
+                ranges.push((start, lnum - 1));
+            }
+            while open.len() < line.level {
+                open.push(lnum);
+            }
+            while open.len() > line.next {
+                let start = open.pop().unwrap_or(lnum);
+                ranges.push((start, lnum));
+            }
+        }
+        while let Some(start) = open.pop() {
+            ranges.push((start, levels.len()));
+        }
+        ranges
+    }
+
+    fn build_tree(mut ranges: Vec<(usize, usize)>) -> Vec<Fold> {
+        fn insert(folds: &mut Vec<Fold>, start: usize, end: usize) {
+            match folds.last_mut() {
+                Some(parent) if parent.start.0 <= start && parent.end.0 >= end => {
+                    insert(&mut parent.nested, start, end)
+                }
+                _ => folds.push(Fold {
+                    start: LineNr(start),
+                    end: LineNr(end),
+                    level: 0,
+                    state: FoldState::Open,
+                    nested: Vec::new(),
+                }),
+            }
+        }
+        ranges.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+        let mut folds = Vec::new();
+        for (start, end) in ranges {
+            insert(&mut folds, start, end);
+        }
+        folds
+    }
+
+    fn collect_states(folds: &[Fold], states: &mut Vec<(usize, usize, FoldState)>) {
+        for fold in folds {
+            states.push((fold.start.0, fold.level, fold.state));
+            Self::collect_states(&fold.nested, states);
+        }
+    }
+
+    fn restore_states(folds: &mut [Fold], states: &[(usize, usize, FoldState)], foldlevel: usize) {
+        for fold in folds {
+            fold.state = states
+                .iter()
+                .find(|(start, level, _)| *start == fold.start.0 && *level == fold.level)
+                .map(|(_, _, state)| *state)
+                .unwrap_or(if fold.level > foldlevel {
+                    FoldState::Closed
+                } else {
+                    FoldState::Open
+                });
+            Self::restore_states(&mut fold.nested, states, foldlevel);
+        }
+    }
+
+    /// Text of the fold's first line with comment leaders and markers removed.
+    fn fold_line_text(&self, fold: &Fold) -> String {
+        let Some(line) = self.lines.get(fold.start.to_zero_indexed()) else {
+            return String::new();
+        };
+        let mut text = line.trim_start();
+        for leader in ["/*", "//", "\"", "#"] {
+            if let Some(rest) = text.strip_prefix(leader) {
+                text = rest.trim_start();
+                break;
+            }
+        }
+        let (start_marker, end_marker) = self.options.markers();
+        let mut cleaned = String::with_capacity(text.len());
+        let mut rest = text;
+        while !rest.is_empty() {
+            if let Some(after) = rest
+                .strip_prefix(start_marker)
+                .or_else(|| rest.strip_prefix(end_marker))
+            {
+                rest = after.trim_start_matches(|ch: char| ch.is_ascii_digit());
+            } else if let Some(after) = rest.strip_prefix("*/") {
+                rest = after;
+            } else {
+                let ch = rest.chars().next().unwrap_or(' ');
+                cleaned.push(ch);
+                rest = &rest[ch.len_utf8()..];
+            }
+        }
+        cleaned.trim_end().to_string()
+    }
+}
+
+impl FoldManager for BufferFoldManager {
+    fn method(&self) -> FoldMethod {
+        self.method
+    }
+
+    fn set_method(&mut self, method: FoldMethod) {
+        self.method = method;
+        // Switching to manual keeps the folds that were computed so far.
+        if method != FoldMethod::Manual {
+            self.recompute();
+        }
+    }
+
+    fn create(&mut self, start: LineNr, end: LineNr) -> VimResult<()> {
+        if self.method != FoldMethod::Manual {
+            return Err(VimError::Error(
+                350,
+                "Cannot create fold with current 'foldmethod'".to_string(),
+            ));
+        }
+        let (start, end) = if start <= end {
+            (start, end)
+        } else {
+            (end, start)
+        };
+        let last = self.lines.len().max(1);
+        Self::insert_manual(
+            &mut self.folds,
+            start.0.clamp(1, last),
+            end.0.clamp(1, last),
+        );
+        Self::set_levels(&mut self.folds, 1);
+        Ok(())
+    }
+
+    fn delete(&mut self, line: LineNr) -> VimResult<()> {
+        if self.method != FoldMethod::Manual {
+            return Err(VimError::Error(
+                351,
+                "Cannot delete fold with current 'foldmethod'".to_string(),
+            ));
+        }
+        let mut path = self.require_path(line)?;
+        // Delete the closed fold the cursor is on, or else the innermost fold.
+        if let Some(depth) = self.first_closed(&path) {
+            path.truncate(depth + 1);
+        }
+        let idx = path.pop().unwrap_or(0);
+        let siblings = if path.is_empty() {
+            &mut self.folds
+        } else {
+            &mut self.fold_at_mut(&path).nested
+        };
+        let removed = siblings.remove(idx);
+        for (offset, nested) in removed.nested.into_iter().enumerate() {
+            siblings.insert(idx + offset, nested);
+        }
+        Self::set_levels(&mut self.folds, 1);
+        Ok(())
+    }
+
+    fn delete_all(&mut self) {
+        if self.method == FoldMethod::Manual {
+            self.folds.clear();
+        }
+    }
+
+    fn open(&mut self, line: LineNr) -> VimResult<()> {
+        let path = self.require_path(line)?;
+        if let Some(depth) = self.first_closed(&path) {
+            self.fold_at_mut(&path[..=depth]).state = FoldState::Open;
+        }
+        Ok(())
+    }
+
+    fn close(&mut self, line: LineNr) -> VimResult<()> {
+        let path = self.require_path(line)?;
+        // Close the deepest open fold that is not already inside a closed one.
+        let last_open = self
+            .first_closed(&path)
+            .unwrap_or(path.len())
+            .checked_sub(1);
+        if let Some(depth) = last_open {
+            self.fold_at_mut(&path[..=depth]).state = FoldState::Closed;
+        }
+        Ok(())
+    }
+
+    fn toggle(&mut self, line: LineNr) -> VimResult<()> {
+        let path = self.require_path(line)?;
+        if self.first_closed(&path).is_some() {
+            self.open(line)
+        } else {
+            self.close(line)
+        }
+    }
+
+    fn open_all(&mut self) {
+        let depth = self.max_depth();
+        self.set_foldlevel(depth);
+    }
+
+    fn close_all(&mut self) {
+        self.set_foldlevel(0);
+    }
+
+    fn get(&self, line: LineNr) -> Option<&Fold> {
+        let path = self.path(line);
+        (!path.is_empty()).then(|| self.fold_at(&path))
+    }
+
+    fn level(&self, line: LineNr) -> usize {
+        self.path(line).len()
+    }
+
+    fn is_folded(&self, line: LineNr) -> bool {
+        self.closed_fold_at(line).is_some()
+    }
+
+    fn fold_text(&self, fold: &Fold) -> String {
+        let count = fold.line_count();
+        format!(
+            "+-{}{:>3} {}: {}",
+            "-".repeat(fold.level),
+            count,
+            if count == 1 { "line" } else { "lines" },
+            self.fold_line_text(fold)
+        )
+    }
+
+    fn recompute(&mut self) {
+        let levels = match self.method {
+            FoldMethod::Manual => return,
+            FoldMethod::Indent => self.indent_levels(),
+            FoldMethod::Marker => self.marker_levels(),
+            FoldMethod::Expr => self.expr_levels(),
+            FoldMethod::Syntax | FoldMethod::Diff => Vec::new(),
+        };
+        let mut states = Vec::new();
+        Self::collect_states(&self.folds, &mut states);
+        let mut folds = Self::build_tree(Self::ranges(&levels));
+        Self::set_levels(&mut folds, 1);
+        Self::restore_states(&mut folds, &states, self.options.foldlevel);
+        self.folds = folds;
+    }
+}
+
 // ============================================================================
 // Tests
 // ============================================================================
@@ -139,6 +1117,54 @@ mod tests {
         assert!(!fold.contains(LineNr(11)));
     }
 
+    fn manager(lines: &[&str], method: FoldMethod) -> BufferFoldManager {
+        let mut mgr = BufferFoldManager::new(lines.iter().map(|s| s.to_string()).collect());
+        mgr.set_options(FoldOptions {
+            shiftwidth: 2,
+            ..FoldOptions::default()
+        });
+        mgr.set_method(method);
+        mgr
+    }
+
+    #[test]
+    fn test_indent_folds_nest() {
+        let mgr = manager(&["a", "  b", "    c", "  d", "e"], FoldMethod::Indent);
+        assert_eq!(mgr.folds().len(), 1);
+        assert_eq!(
+            (mgr.folds()[0].start, mgr.folds()[0].end),
+            (LineNr(2), LineNr(4))
+        );
+        assert_eq!(mgr.level(LineNr(3)), 2);
+        assert_eq!(mgr.level(LineNr(5)), 0);
+    }
+
+    #[test]
+    fn test_marker_folds_numbered() {
+        let mgr = manager(&["{{{1", "a", "{{{2", "b", "{{{1", "c"], FoldMethod::Marker);
+        assert_eq!(mgr.folds().len(), 2);
+        assert_eq!(
+            (mgr.folds()[0].start, mgr.folds()[0].end),
+            (LineNr(1), LineNr(4))
+        );
+        assert_eq!(mgr.folds()[0].nested[0].start, LineNr(3));
+        assert_eq!(
+            (mgr.folds()[1].start, mgr.folds()[1].end),
+            (LineNr(5), LineNr(6))
+        );
+    }
+
+    #[test]
+    fn test_fold_expr_level_parse() {
+        assert_eq!(FoldExprLevel::parse(">2"), Some(FoldExprLevel::Start(2)));
+        assert_eq!(FoldExprLevel::parse("<1"), Some(FoldExprLevel::End(1)));
+        assert_eq!(FoldExprLevel::parse("a1"), Some(FoldExprLevel::Add(1)));
+        assert_eq!(FoldExprLevel::parse("="), Some(FoldExprLevel::Same));
+        assert_eq!(FoldExprLevel::parse("-1"), Some(FoldExprLevel::Undefined));
+        assert_eq!(FoldExprLevel::parse("3"), Some(FoldExprLevel::Level(3)));
+        assert_eq!(FoldExprLevel::parse("x"), None);
+    }
+
     #[allow(dead_code)]
     mod behavioral_tests {
         //! # Fold Behavioral Tests
@@ -34,7 +34,13 @@
 //! - `undo` - Undo/redo tree
 //! - `visual` - Visual mode selections
 //! - `textobjects` - Text objects (iw, aw, ip, etc.)
+//! - `display` - Screen cells of characters and virtual columns
 //! - `digraphs` - Digraph table
+//! - `quickfix` - Quickfix and location lists
+//! - `clipboard` - Clipboard providers for the `+` and `*` registers
+//! - `eval` - Expressions and builtin functions
+//! - `vimscript` - Vimscript interpreter
+//! - `startup` - Command line arguments and the user config
 
 #![forbid(unsafe_code)]
 #![warn(missing_docs)]
@@ -80,6 +86,18 @@ pub mod commands;
 #[cfg(feature = "commands")]
 pub mod global;
 
+#[cfg(feature = "eval")]
+pub mod eval;
+
+#[cfg(feature = "eval")]
+pub mod evalfunc;
+
+#[cfg(feature = "vimscript")]
+pub mod vimscript;
+
+#[cfg(feature = "startup")]
+pub mod startup;
+
 #[cfg(feature = "cmdline")]
 pub mod cmdline;
 
@@ -131,6 +149,9 @@ pub mod move_text;
 #[cfg(feature = "showmatch")]
 pub mod showmatch;
 
+#[cfg(feature = "display")]
+pub mod display;
+
 #[cfg(feature = "autocmd")]
 pub mod autocmd;
 
@@ -164,6 +185,12 @@ pub mod mappings;
 #[cfg(feature = "abbreviations")]
 pub mod abbreviations;
 
+#[cfg(feature = "quickfix")]
+pub mod quickfix;
+
+#[cfg(feature = "clipboard")]
+pub mod clipboard;
+
 // ============================================================================
 // Prelude - convenient imports for implementors
 // ============================================================================
@@ -250,6 +277,9 @@ pub mod prelude {
     #[cfg(feature = "showmatch")]
     pub use crate::showmatch::find_matching_bracket;
 
+    #[cfg(feature = "display")]
+    pub use crate::display::DisplayOptions;
+
     #[cfg(feature = "autocmd")]
     pub use crate::autocmd::AutocmdManager;
 
@@ -276,4 +306,9 @@ pub mod prelude {
 
     #[cfg(feature = "digraphs")]
     pub use crate::digraphs::DigraphTable;
+
+    #[cfg(feature = "quickfix")]
+    pub use crate::quickfix::QuickfixList;
+    #[cfg(feature = "clipboard")]
+    pub use crate::clipboard::ClipboardProvider;
 }
@@ -8,6 +8,8 @@
 //! - Mappings can be recursive (map) or non-recursive (noremap)
 //! - Mappings are matched against a prefix of pending input
 //! - Longest match wins
+//! - An `<expr>` mapping's right-hand side is an expression, evaluated
+//!   when the mapping is used; the keys are its result
 
 use crate::modes::Mode;
 use crate::types::*;
@@ -30,6 +32,8 @@ pub struct Mapping {
     pub silent: bool,
     /// Whether to wait for more keys (nowait=false means wait if ambiguity)
     pub nowait: bool,
+    /// Whether `rhs` is an expression giving the keys (`<expr>`)
+    pub expr: bool,
 }
 
 /// Result of checking input against mappings
@@ -52,12 +56,18 @@ pub trait MappingManager {
     /// Add a mapping
     fn add(&mut self, mode: Mode, lhs: &str, rhs: &str, noremap: bool) -> VimResult<()>;
 
+    /// Add an `<expr>` mapping: `rhs` is an expression giving the keys
+    fn add_expr(&mut self, mode: Mode, lhs: &str, rhs: &str, noremap: bool) -> VimResult<()>;
+
     /// Remove a mapping
     fn remove(&mut self, mode: Mode, lhs: &str) -> VimResult<()>;
 
     /// Get a specific mapping
     fn get(&self, mode: Mode, lhs: &str) -> Option<&Mapping>;
 
+    /// All mappings for `mode`, sorted by `lhs`
+    fn list(&self, mode: Mode) -> Vec<&Mapping>;
+
     /// Check if input matches any mapping
     ///
     /// # Returns
@@ -105,10 +115,8 @@ impl SimpleMappingManager {
     fn get_mode_map_mut(&mut self, mode: Mode) -> &mut HashMap<String, Mapping> {
         self.mappings.entry(mode).or_default()
     }
-}
 
-impl MappingManager for SimpleMappingManager {
-    fn add(&mut self, mode: Mode, lhs: &str, rhs: &str, noremap: bool) -> VimResult<()> {
+    fn insert(&mut self, mode: Mode, lhs: &str, rhs: &str, noremap: bool, expr: bool) -> VimResult<()> {
         if lhs.is_empty() {
             return Err(VimError::Error(0, "Mapping LHS cannot be empty".to_string()));
         }
@@ -119,11 +127,22 @@ impl MappingManager for SimpleMappingManager {
             noremap,
             silent: false,
             nowait: false,
+            expr,
         };
 
         self.get_mode_map_mut(mode).insert(lhs.to_string(), mapping);
         Ok(())
     }
+}
+
+impl MappingManager for SimpleMappingManager {
+    fn add(&mut self, mode: Mode, lhs: &str, rhs: &str, noremap: bool) -> VimResult<()> {
+        self.insert(mode, lhs, rhs, noremap, false)
+    }
+
+    fn add_expr(&mut self, mode: Mode, lhs: &str, rhs: &str, noremap: bool) -> VimResult<()> {
+        self.insert(mode, lhs, rhs, noremap, true)
+    }
 
     fn remove(&mut self, mode: Mode, lhs: &str) -> VimResult<()> {
         if let Some(map) = self.mappings.get_mut(&mode) {
@@ -141,6 +160,15 @@ impl MappingManager for SimpleMappingManager {
         self.get_mode_map(mode).and_then(|m| m.get(lhs))
     }
 
+    fn list(&self, mode: Mode) -> Vec<&Mapping> {
+        let mut mappings: Vec<&Mapping> = self
+            .get_mode_map(mode)
+            .map(|map| map.values().collect())
+            .unwrap_or_default();
+        mappings.sort_by(|a, b| a.lhs.cmp(&b.lhs));
+        mappings
+    }
+
     fn check(&self, mode: Mode, input: &str) -> MappingCheckResult {
         let Some(map) = self.get_mode_map(mode) else {
             return MappingCheckResult::NoMatch;
@@ -179,6 +207,18 @@ mod tests {
         assert_eq!(m.noremap, false);
     }
 
+    #[test]
+    fn test_add_expr_mapping() {
+        let mut mgr = SimpleMappingManager::new();
+        mgr.add_expr(Mode::Insert, "<F2>", "toupper('x')", true).unwrap();
+        mgr.add(Mode::Insert, "<F3>", "x", true).unwrap();
+
+        let m = mgr.get(Mode::Insert, "<F2>").unwrap();
+        assert_eq!(m.rhs, "toupper('x')");
+        assert!(m.expr);
+        assert!(!mgr.get(Mode::Insert, "<F3>").unwrap().expr);
+    }
+
     #[test]
     fn test_check_partial_match() {
         let mut mgr = SimpleMappingManager::new();
@@ -213,4 +253,15 @@ mod tests {
         mgr.remove(Mode::Normal, "a").unwrap();
         assert!(mgr.get(Mode::Normal, "a").is_none());
     }
+
+    #[test]
+    fn test_list_mappings_sorted() {
+        let mut mgr = SimpleMappingManager::new();
+        mgr.add(Mode::Normal, "zz", "a", false).unwrap();
+        mgr.add(Mode::Normal, "<F2>", "b", false).unwrap();
+        mgr.add(Mode::Insert, "jk", "<Esc>", true).unwrap();
+        let lhs: Vec<&str> = mgr.list(Mode::Normal).iter().map(|m| m.lhs.as_str()).collect();
+        assert_eq!(lhs, vec!["<F2>", "zz"]);
+        assert_eq!(mgr.list(Mode::Insert).len(), 1);
+    }
 }
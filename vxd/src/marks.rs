@@ -9,9 +9,11 @@
 //! - Local marks (a-z) are per-buffer
 //! - Global marks (A-Z, 0-9) can span files
 //! - Special marks are set automatically
-//! - Marks adjust when text is inserted/deleted above them
+//! - Marks adjust when text is inserted/deleted above them, and follow
+//!   text that moves within a line
+//! - Extmarks are positions tracked for embedders, grouped by namespace
 
-use crate::buffer::BufHandle;
+use crate::buffer::{BufHandle, TextSplice};
 use crate::cursor::CursorPosition;
 use crate::types::*;
 
@@ -272,7 +274,10 @@ pub trait MarkManager {
 
     /// Adjust marks after buffer modification
     ///
-    /// Called when text is inserted or deleted to keep marks valid.
+    /// Called when text is inserted or deleted at `line`, `col` to keep
+    /// marks valid.  Positive counts describe inserted text and negative
+    /// counts deleted text: `lines_added` line breaks, followed by
+    /// `bytes_added` bytes on the last line (see [`adjust_splice`]).
     fn adjust(&mut self, line: LineNr, col: usize, lines_added: i64, bytes_added: i64);
 
     /// Get the jump list
@@ -300,6 +305,171 @@ pub trait MarkManager {
     fn set_change_marks(&mut self, start: CursorPosition, end: CursorPosition);
 }
 
+/// The change [`MarkManager::adjust`] describes.
+///
+/// Inserting five lines above line 5 is `adjust(5, 0, 5, 0)`, splitting
+/// line 3 at column 4 is `adjust(3, 4, 1, 0)` and deleting two bytes at
+/// column 4 is `adjust(3, 4, 0, -2)`.
+pub fn adjust_splice(line: LineNr, col: usize, lines_added: i64, bytes_added: i64) -> TextSplice {
+    let start = (line.to_zero_indexed(), col);
+    let lines = lines_added.unsigned_abs() as usize;
+    let bytes = bytes_added.unsigned_abs() as usize;
+    let end = if lines == 0 {
+        (start.0, col + bytes)
+    } else {
+        (start.0 + lines, bytes)
+    };
+    if lines_added > 0 || (lines_added == 0 && bytes_added >= 0) {
+        TextSplice {
+            start,
+            old_end: start,
+            new_end: end,
+        }
+    } else {
+        TextSplice {
+            start,
+            old_end: end,
+            new_end: start,
+        }
+    }
+}
+
+/// Move `pos` for a change; `pos` uses 1-indexed lines like marks do
+pub fn splice_position(
+    splice: &TextSplice,
+    pos: CursorPosition,
+    right_gravity: bool,
+) -> CursorPosition {
+    let (row, col) = splice.map((pos.line.to_zero_indexed(), pos.col), right_gravity);
+    CursorPosition {
+        line: LineNr(row + 1),
+        col,
+        coladd: pos.coladd,
+    }
+}
+
+// ============================================================================
+// Extmarks
+// ============================================================================
+
+/// A namespace grouping extmarks, as returned by
+/// [`ExtmarkManager::create_namespace`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NamespaceId(pub u32);
+
+/// Identifies an extmark within its namespace
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ExtmarkId(pub u32);
+
+/// Options for placing an extmark
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExtmarkOpts {
+    /// End of the range the extmark covers, if any
+    pub end: Option<CursorPosition>,
+    /// Text inserted at the start position goes before the extmark
+    pub right_gravity: bool,
+    /// Text inserted at the end position goes before the end
+    pub end_right_gravity: bool,
+}
+
+impl Default for ExtmarkOpts {
+    /// Neovim's defaults: the start moves with text inserted at it, the
+    /// end does not
+    fn default() -> Self {
+        ExtmarkOpts {
+            end: None,
+            right_gravity: true,
+            end_right_gravity: false,
+        }
+    }
+}
+
+/// A position, or range, that follows the text it was placed on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Extmark {
+    /// Id within the namespace
+    pub id: ExtmarkId,
+    /// Namespace the extmark belongs to
+    pub ns: NamespaceId,
+    /// Buffer the extmark is in
+    pub buffer: BufHandle,
+    /// Current start position
+    pub start: CursorPosition,
+    /// Current end position, for extmarks covering a range
+    pub end: Option<CursorPosition>,
+    /// Gravity of the start position
+    pub right_gravity: bool,
+    /// Gravity of the end position
+    pub end_right_gravity: bool,
+}
+
+impl Extmark {
+    /// Move the extmark for a change in its buffer
+    pub fn splice(&mut self, splice: &TextSplice) {
+        self.start = splice_position(splice, self.start, self.right_gravity);
+        if let Some(end) = self.end {
+            let end = splice_position(splice, end, self.end_right_gravity);
+            // A range never ends before it starts
+            let before_start = (end.line, end.col) < (self.start.line, self.start.col);
+            self.end = Some(if before_start { self.start } else { end });
+        }
+    }
+}
+
+/// Manages extmarks: positions embedders place in buffers that move with
+/// the text around them (Neovim's `nvim_buf_set_extmark()` family)
+pub trait ExtmarkManager {
+    /// The namespace named `name`, created on first use.  An empty name
+    /// creates a new anonymous namespace every time.
+    fn create_namespace(&mut self, name: &str) -> NamespaceId;
+
+    /// Named namespaces and their ids
+    fn namespaces(&self) -> Vec<(String, NamespaceId)>;
+
+    /// Place an extmark at `pos`, replacing the extmark `id` when given.
+    /// Returns the id of the extmark.
+    fn set_extmark(
+        &mut self,
+        buffer: BufHandle,
+        ns: NamespaceId,
+        id: Option<ExtmarkId>,
+        pos: CursorPosition,
+        opts: ExtmarkOpts,
+    ) -> VimResult<ExtmarkId>;
+
+    /// Get an extmark by id
+    fn get_extmark(&self, buffer: BufHandle, ns: NamespaceId, id: ExtmarkId) -> Option<&Extmark>;
+
+    /// Extmarks of `ns` (all namespaces when `None`) starting between
+    /// `start` and `end` inclusive, in position order.  With `overlap`,
+    /// extmarks whose range starts earlier but reaches `start` are
+    /// included too.
+    fn get_extmarks(
+        &self,
+        buffer: BufHandle,
+        ns: Option<NamespaceId>,
+        start: CursorPosition,
+        end: CursorPosition,
+        overlap: bool,
+    ) -> Vec<&Extmark>;
+
+    /// Remove an extmark.  Returns whether it existed.
+    fn del_extmark(&mut self, buffer: BufHandle, ns: NamespaceId, id: ExtmarkId) -> bool;
+
+    /// Remove the extmarks of `ns` (all namespaces when `None`) that start
+    /// on lines `first` to `last` (the last line when `None`)
+    fn clear_namespace(
+        &mut self,
+        buffer: BufHandle,
+        ns: Option<NamespaceId>,
+        first: LineNr,
+        last: Option<LineNr>,
+    );
+
+    /// Move the extmarks of `buffer` for a change
+    fn splice(&mut self, buffer: BufHandle, splice: &TextSplice);
+}
+
 // ============================================================================
 // Tests
 // ============================================================================
@@ -329,6 +499,48 @@ mod tests {
         assert!(Mark::Numbered(0).is_global());
     }
 
+    #[test]
+    fn test_adjust_splice() {
+        let pos = |line, col| CursorPosition::new(LineNr(line), col);
+
+        // Five lines inserted above line 5
+        let splice = adjust_splice(LineNr(5), 0, 5, 0);
+        assert_eq!(splice_position(&splice, pos(10, 3), true), pos(15, 3));
+        assert_eq!(splice_position(&splice, pos(4, 3), true), pos(4, 3));
+
+        // Line 3 split at column 4
+        let splice = adjust_splice(LineNr(3), 4, 1, 0);
+        assert_eq!(splice_position(&splice, pos(3, 6), true), pos(4, 2));
+        assert_eq!(splice_position(&splice, pos(3, 2), true), pos(3, 2));
+
+        // Two bytes deleted at column 4
+        let splice = adjust_splice(LineNr(3), 4, 0, -2);
+        assert_eq!(splice_position(&splice, pos(3, 5), true), pos(3, 4));
+        assert_eq!(splice_position(&splice, pos(3, 8), true), pos(3, 6));
+    }
+
+    #[test]
+    fn test_extmark_end_follows_gravity() {
+        let mut mark = Extmark {
+            id: ExtmarkId(1),
+            ns: NamespaceId(1),
+            buffer: BufHandle(1),
+            start: CursorPosition::new(LineNr(1), 2),
+            end: Some(CursorPosition::new(LineNr(1), 5)),
+            right_gravity: false,
+            end_right_gravity: true,
+        };
+        // Text inserted at both ends is taken into the range
+        mark.splice(&adjust_splice(LineNr(1), 5, 0, 3));
+        mark.splice(&adjust_splice(LineNr(1), 2, 0, 1));
+        assert_eq!(mark.start, CursorPosition::new(LineNr(1), 2));
+        assert_eq!(mark.end, Some(CursorPosition::new(LineNr(1), 9)));
+
+        // Deleting the whole range collapses it
+        mark.splice(&adjust_splice(LineNr(1), 0, 0, -20));
+        assert_eq!(mark.start, mark.end.unwrap());
+    }
+
     /// Behavioral tests for mark implementations
     pub trait MarkBehaviorTests: MarkManager + Sized {
         /// Test: Local marks are buffer-specific
@@ -1,10 +1,62 @@
-//! Modeline parsing for per-file option settings.
+//! Modelines: option settings in the first and last lines of a file.
 //!
-//! This module parses Vim modelines of the form:
-//!   "vim:set {option}={value} ... :"
-//! It extracts option assignments for higher-level application.
+//! A modeline has one of two forms:
+//!   "[text]{white}{vi:|vim:|ex:}[white]{options}"
+//!   "[text]{white}{vi:|vim:|Vim:|ex:}[white]se[t] {options}:[text]"
+//! In the first form the options are separated by white space or `:`; in
+//! the second the `:set` command ends at a `:`, with `\:` for a colon in a
+//! value.  `vim{version}:` only applies from that version on, `vim<`,
+//! `vim=` and `vim>` before, at or after it.
+//!
+//! Only buffer and window options are set, and not the ones that could run
+//! commands; options holding an expression need 'modelineexpr'.
+//! 'modeline' and 'modelines' say whether and in how many lines modelines
+//! are looked for.
+
+use crate::options::{OptionDef, OptionManager, OptionScope, OptionValue};
+use crate::types::{VimError, VimResult};
+
+/// The version `vim{version}:` modelines are compared with, `600` being
+/// Vim 6.0
+pub const MODELINE_VERSION: u32 = 900;
+
+/// Options never set from a modeline, though local: they run programs,
+/// name files to write or control modelines themselves
+pub const SECURE_OPTIONS: &[&str] = &[
+    "modeline",
+    "modelineexpr",
+    "modelines",
+    "keywordprg",
+    "equalprg",
+    "formatprg",
+    "makeprg",
+    "grepprg",
+    "completefunc",
+    "omnifunc",
+    "tagfunc",
+    "thesaurusfunc",
+    "operatorfunc",
+    "spellfile",
+    "undofile",
+    "backupcopy",
+];
 
-use crate::options::OptionValue;
+/// Options holding an expression, set from a modeline only with
+/// 'modelineexpr'
+pub const EXPR_OPTIONS: &[&str] = &[
+    "foldexpr",
+    "foldtext",
+    "formatexpr",
+    "includeexpr",
+    "indentexpr",
+    "statusline",
+    "tabline",
+    "rulerformat",
+    "titlestring",
+    "iconstring",
+    "guitablabel",
+    "guitabtooltip",
+];
 
 /// Parsed modeline setting (name/value).
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -13,108 +65,254 @@ pub struct ModelineSetting {
     pub name: String,
     /// Parsed option value.
     pub value: OptionValue,
+    /// The argument as written, for messages.
+    pub arg: String,
 }
 
 /// Parse a single line for a Vim modeline.
 ///
 /// Returns None if no modeline is found.
 pub fn parse_modeline_line(line: &str) -> Option<Vec<ModelineSetting>> {
-    let marker = "vim:";
-    let mut start = None;
-    let mut search = line;
-    let mut offset = 0usize;
-
-    while let Some(pos) = search.find(marker) {
-        let absolute = offset + pos;
-        let ok_prefix = absolute == 0 || line[..absolute].chars().last().map(|c| c.is_whitespace()).unwrap_or(false);
-        if ok_prefix {
-            start = Some(absolute + marker.len());
-            break;
+    let options = find_modeline(line)?;
+    let settings: Vec<ModelineSetting> = modeline_parts(options)
+        .iter()
+        .flat_map(|part| split_args(part))
+        .map(|arg| parse_setting(&arg))
+        .collect();
+
+    if settings.is_empty() {
+        None
+    } else {
+        Some(settings)
+    }
+}
+
+/// The text after the marker of the modeline in `line`.  The marker
+/// starts the line or follows white space; `ex:` must follow white space.
+fn find_modeline(line: &str) -> Option<&str> {
+    let mut prev: Option<char> = None;
+    for (idx, ch) in line.char_indices() {
+        if prev.is_none_or(char::is_whitespace) {
+            let rest = &line[idx..];
+            if (prev.is_some() && rest.starts_with("ex:")) || rest.starts_with("vi:") {
+                return Some(&rest[3..]);
+            }
+            if let Some(after) = rest
+                .strip_prefix("vim")
+                .or_else(|| rest.strip_prefix("Vim"))
+            {
+                if let Some(options) = version_options(after, ch == 'V') {
+                    return Some(options);
+                }
+            }
         }
-        offset = absolute + marker.len();
-        search = &line[offset..];
+        prev = Some(ch);
     }
+    None
+}
 
-    let start = start?;
-    let mut rest = line[start..].trim_start();
-    if !rest.starts_with("set") {
+/// The options after `vim[<=>][version]:` when the version applies; `Vim`
+/// is only a marker before `set`
+fn version_options(after: &str, capital: bool) -> Option<&str> {
+    let (compare, digits) = match after.chars().next() {
+        Some(c @ ('<' | '=' | '>')) => (Some(c), &after[1..]),
+        _ => (None, after),
+    };
+    let len = digits
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(digits.len());
+    let options = digits[len..].strip_prefix(':')?;
+    if capital && !options.trim_start().starts_with("set") {
         return None;
     }
-    rest = rest["set".len()..].trim_start();
+    let version = match len {
+        0 => 0,
+        _ => digits[..len].parse().unwrap_or(u32::MAX),
+    };
+    let applies = match compare {
+        None => len == 0 || MODELINE_VERSION >= version,
+        Some('<') => MODELINE_VERSION < version,
+        Some('=') => MODELINE_VERSION == version,
+        _ => MODELINE_VERSION > version,
+    };
+    applies.then_some(options)
+}
 
-    let mut cmd = String::new();
-    let mut chars = rest.chars().peekable();
-    while let Some(ch) = chars.next() {
-        if ch == ':' {
+/// Split the options of a modeline at the colons, making `\:` a colon.  A
+/// `set` command needs a colon after it and ends the modeline.
+fn modeline_parts(options: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut rest = options;
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
             break;
         }
-        if ch == '\\' {
-            if let Some(next) = chars.next() {
-                if next == ':' {
-                    cmd.push(':');
-                } else {
-                    cmd.push('\\');
-                    cmd.push(next);
+        let mut part = String::new();
+        let mut end = None;
+        let mut chars = rest.char_indices().peekable();
+        while let Some((idx, ch)) = chars.next() {
+            match ch {
+                ':' => {
+                    end = Some(idx);
+                    break;
+                }
+                '\\' if chars.peek().map(|&(_, next)| next) == Some(':') => {
+                    chars.next();
+                    part.push(':');
                 }
-            } else {
-                cmd.push('\\');
+                _ => part.push(ch),
             }
-        } else {
-            cmd.push(ch);
+        }
+        let set = part
+            .strip_prefix("set ")
+            .or_else(|| part.strip_prefix("se "));
+        if let Some(args) = set {
+            if end.is_some() {
+                parts.push(args.to_string());
+            }
+            break;
+        }
+        parts.push(part);
+        match end {
+            Some(idx) => rest = &rest[idx + 1..],
+            None => break,
         }
     }
+    parts
+}
 
-    let mut settings = Vec::new();
-    for token in cmd.split_whitespace() {
-        if let Some((name, value)) = token.split_once('=') {
-            settings.push(ModelineSetting {
-                name: name.to_string(),
-                value: parse_option_value(value),
-            });
-        } else if token.starts_with("no") && token.len() > 2 {
-            settings.push(ModelineSetting {
-                name: token[2..].to_string(),
-                value: OptionValue::Boolean(false),
-            });
-        } else {
-            settings.push(ModelineSetting {
-                name: token.to_string(),
-                value: OptionValue::Boolean(true),
-            });
+/// Split `:set` arguments at white space, where `\ ` and `\\` are a space
+/// and a backslash
+fn split_args(part: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut arg = String::new();
+    let mut chars = part.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' if chars
+                .peek()
+                .is_some_and(|&next| next == '\\' || next.is_whitespace()) =>
+            {
+                arg.extend(chars.next());
+            }
+            _ if ch.is_whitespace() => {
+                if !arg.is_empty() {
+                    args.push(std::mem::take(&mut arg));
+                }
+            }
+            _ => arg.push(ch),
         }
     }
+    if !arg.is_empty() {
+        args.push(arg);
+    }
+    args
+}
 
-    if settings.is_empty() {
-        None
+fn parse_setting(arg: &str) -> ModelineSetting {
+    let (name, value) = if let Some((name, value)) = arg.split_once('=') {
+        (name, parse_option_value(value))
+    } else if arg.starts_with("no") && arg.len() > 2 {
+        (&arg[2..], OptionValue::Boolean(false))
     } else {
-        Some(settings)
+        (arg, OptionValue::Boolean(true))
+    };
+    ModelineSetting {
+        name: name.to_string(),
+        value,
+        arg: arg.to_string(),
     }
 }
 
+/// The lines of a file with `count` modelines looked for: the first
+/// `count`, then the last `count` from the end, each line once
+fn modeline_lines(lines: &[String], count: usize) -> impl Iterator<Item = &String> {
+    let head = count.min(lines.len());
+    let tail_start = lines.len().saturating_sub(count).max(head);
+    lines[..head].iter().chain(lines[tail_start..].iter().rev())
+}
+
 /// Extract modelines from the first and last `count` lines of a file.
 pub fn extract_modelines(lines: &[String], count: usize) -> Vec<ModelineSetting> {
-    if count == 0 || lines.is_empty() {
-        return Vec::new();
-    }
-
-    let mut settings = Vec::new();
-    let len = lines.len();
-    let head = count.min(len);
-    let tail_start = len.saturating_sub(count);
+    modeline_lines(lines, count)
+        .filter_map(|line| parse_modeline_line(line))
+        .flatten()
+        .collect()
+}
 
-    for line in &lines[..head] {
-        if let Some(found) = parse_modeline_line(line) {
-            settings.extend(found);
-        }
+/// Check that the option of `def` may be set from a modeline by `arg`.
+///
+/// Global options and [`SECURE_OPTIONS`] are refused with E520,
+/// [`EXPR_OPTIONS`] with E992 unless `modelineexpr` is set.
+pub fn check_modeline_option(def: &OptionDef, arg: &str, modelineexpr: bool) -> VimResult<()> {
+    if def.scope == OptionScope::Global || SECURE_OPTIONS.contains(&def.name.as_str()) {
+        return Err(VimError::Error(
+            520,
+            format!("Not allowed in a modeline: {}", arg),
+        ));
+    }
+    if !modelineexpr && EXPR_OPTIONS.contains(&def.name.as_str()) {
+        return Err(VimError::Error(
+            992,
+            format!(
+                "Not allowed in a modeline when 'modelineexpr' is off: {}",
+                arg
+            ),
+        ));
     }
+    Ok(())
+}
 
-    for line in &lines[tail_start..] {
-        if let Some(found) = parse_modeline_line(line) {
-            settings.extend(found);
-        }
+/// Apply the modelines in `lines` to the local values of `options`, as
+/// after reading a file.
+///
+/// Nothing is done with 'modeline' off; 'modelines' is the number of lines
+/// at the start and at the end looked at.  The first setting that fails
+/// stops applying modelines, and its error is returned.
+pub fn apply_modelines<O: OptionManager>(options: &mut O, lines: &[String]) -> VimResult<()> {
+    if !options.get_bool("modeline").unwrap_or(true) {
+        return Ok(());
     }
+    let count = options.get_number("modelines").unwrap_or(5).max(0) as usize;
+    let modelineexpr = options.get_bool("modelineexpr").unwrap_or(false);
+    for setting in extract_modelines(lines, count) {
+        apply_setting(options, &setting, modelineexpr)?;
+    }
+    Ok(())
+}
 
-    settings
+/// Set the option of `setting`, taking its value as the type of the option
+fn apply_setting<O: OptionManager>(
+    options: &mut O,
+    setting: &ModelineSetting,
+    modelineexpr: bool,
+) -> VimResult<()> {
+    let arg = &setting.arg;
+    let def = options
+        .definition(&setting.name)
+        .ok_or_else(|| VimError::Error(518, format!("Unknown option: {}", arg)))?;
+    check_modeline_option(def, arg, modelineexpr)?;
+    let name = def.name.clone();
+    let value = match (&def.default, &setting.value) {
+        (OptionValue::String(_), OptionValue::Number(n)) => OptionValue::String(n.to_string()),
+        (OptionValue::Number(_), OptionValue::String(_)) => {
+            return Err(VimError::Error(
+                521,
+                format!("Number required after =: {}", arg),
+            ))
+        }
+        // A number or string option alone shows its value
+        (OptionValue::Number(_) | OptionValue::String(_), OptionValue::Boolean(true)) => {
+            return Ok(())
+        }
+        (OptionValue::Number(_) | OptionValue::String(_), OptionValue::Boolean(false))
+        | (OptionValue::Boolean(_), OptionValue::Number(_) | OptionValue::String(_)) => {
+            return Err(VimError::Error(474, format!("Invalid argument: {}", arg)))
+        }
+        (_, value) => value.clone(),
+    };
+    options.set_local(&name, value)
 }
 
 fn parse_option_value(value: &str) -> OptionValue {
@@ -128,6 +326,7 @@ fn parse_option_value(value: &str) -> OptionValue {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashMap;
 
     #[test]
     fn test_parse_modeline_simple() {
@@ -161,7 +360,10 @@ mod tests {
         let settings = parse_modeline_line(line).unwrap();
         assert_eq!(settings.len(), 1);
         assert_eq!(settings[0].name, "dir");
-        assert_eq!(settings[0].value, OptionValue::String("c:\\tmp".to_string()));
+        assert_eq!(
+            settings[0].value,
+            OptionValue::String("c:\\tmp".to_string())
+        );
     }
 
     #[test]
@@ -187,4 +389,228 @@ mod tests {
         assert_eq!(settings[0].name, "ts");
         assert_eq!(settings[1].name, "sw");
     }
+
+    fn names(line: &str) -> Vec<String> {
+        parse_modeline_line(line)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|setting| setting.name)
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_modeline_first_form() {
+        assert_eq!(names("vi:noai:sw=3 ts=6"), ["ai", "sw", "ts"]);
+        assert_eq!(names("  ex: ts=2::"), ["ts"]);
+        assert!(parse_modeline_line("ex:ts=2").is_none());
+    }
+
+    #[test]
+    fn test_parse_modeline_set_needs_colon() {
+        assert_eq!(names("/* vim: set ai tw=75: ts=2 */"), ["ai", "tw"]);
+        assert!(parse_modeline_line("# vim: set ts=2").is_none());
+        assert_eq!(names("# vim: sw=2: set ts=4"), ["sw"]);
+    }
+
+    #[test]
+    fn test_parse_modeline_versions() {
+        assert_eq!(names("vim600: ts=2"), ["ts"]);
+        assert!(parse_modeline_line("vim901: ts=2").is_none());
+        assert_eq!(names("vim<901: ts=2"), ["ts"]);
+        assert!(parse_modeline_line("vim<900: ts=2").is_none());
+        assert_eq!(names("vim=900: ts=2"), ["ts"]);
+        assert_eq!(names("vim>800: ts=2"), ["ts"]);
+        assert!(parse_modeline_line("vim>900: ts=2").is_none());
+    }
+
+    #[test]
+    fn test_parse_modeline_capital_needs_set() {
+        assert_eq!(names("Vim: set ts=2:"), ["ts"]);
+        assert!(parse_modeline_line("Vim: ts=2").is_none());
+    }
+
+    struct TestOptions {
+        values: HashMap<String, OptionValue>,
+        defs: Vec<OptionDef>,
+    }
+
+    impl OptionManager for TestOptions {
+        fn get(&self, name: &str) -> Option<&OptionValue> {
+            self.values.get(&self.definition(name)?.name)
+        }
+
+        fn set(&mut self, name: &str, value: OptionValue) -> VimResult<()> {
+            self.values.insert(name.to_string(), value);
+            Ok(())
+        }
+
+        fn reset(&mut self, _name: &str) -> VimResult<()> {
+            Ok(())
+        }
+
+        fn definitions(&self) -> Vec<&OptionDef> {
+            self.defs.iter().collect()
+        }
+
+        fn definition(&self, name: &str) -> Option<&OptionDef> {
+            self.defs
+                .iter()
+                .find(|def| def.name == name || def.short_name.as_deref() == Some(name))
+        }
+    }
+
+    fn test_options() -> TestOptions {
+        let defs: Vec<OptionDef> = [
+            ("tabstop", "ts", OptionScope::Buffer, OptionValue::Number(8)),
+            (
+                "number",
+                "nu",
+                OptionScope::Window,
+                OptionValue::Boolean(false),
+            ),
+            (
+                "colorcolumn",
+                "cc",
+                OptionScope::Window,
+                OptionValue::String(String::new()),
+            ),
+            (
+                "foldexpr",
+                "fde",
+                OptionScope::Window,
+                OptionValue::String("0".into()),
+            ),
+            (
+                "makeprg",
+                "mp",
+                OptionScope::Buffer,
+                OptionValue::String("make".into()),
+            ),
+            (
+                "ignorecase",
+                "ic",
+                OptionScope::Global,
+                OptionValue::Boolean(false),
+            ),
+            (
+                "modeline",
+                "ml",
+                OptionScope::Buffer,
+                OptionValue::Boolean(true),
+            ),
+            (
+                "modelines",
+                "mls",
+                OptionScope::Global,
+                OptionValue::Number(5),
+            ),
+            (
+                "modelineexpr",
+                "mle",
+                OptionScope::Global,
+                OptionValue::Boolean(false),
+            ),
+        ]
+        .into_iter()
+        .map(|(name, short, scope, default)| OptionDef {
+            name: name.to_string(),
+            short_name: Some(short.to_string()),
+            scope,
+            default,
+            hidden: false,
+            description: String::new(),
+        })
+        .collect();
+        let values = defs
+            .iter()
+            .map(|def| (def.name.clone(), def.default.clone()))
+            .collect();
+        TestOptions { values, defs }
+    }
+
+    fn lines(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn test_apply_modelines_local_options() {
+        let mut options = test_options();
+        apply_modelines(&mut options, &lines(&["vim: set ts=4 nu cc=80:"])).unwrap();
+        assert_eq!(options.get_number("ts"), Some(4));
+        assert_eq!(options.get_bool("nu"), Some(true));
+        assert_eq!(options.get_string("cc"), Some("80"));
+    }
+
+    #[test]
+    fn test_apply_modelines_refuses_unsafe() {
+        let mut options = test_options();
+        let err = apply_modelines(&mut options, &lines(&["vim: set mp=rm\\ -rf ts=4:"]));
+        assert_eq!(
+            err.unwrap_err().to_string(),
+            "E520: Not allowed in a modeline: mp=rm -rf"
+        );
+        assert_eq!(options.get_string("mp"), Some("make"));
+        // The error stops the rest of the modeline
+        assert_eq!(options.get_number("ts"), Some(8));
+
+        let err = apply_modelines(&mut options, &lines(&["vim: set ic:"]));
+        assert_eq!(
+            err.unwrap_err().to_string(),
+            "E520: Not allowed in a modeline: ic"
+        );
+        let err = apply_modelines(&mut options, &lines(&["vim: set noml:"]));
+        assert!(err.is_err());
+    }
+
+    #[test]
+    fn test_apply_modelines_stops_at_error() {
+        let mut options = test_options();
+        let text = lines(&["vim: set ic ts=4:", "vim: set nu:", "vim: set mp=x:"]);
+        let err = apply_modelines(&mut options, &text);
+        assert_eq!(
+            err.unwrap_err().to_string(),
+            "E520: Not allowed in a modeline: ic"
+        );
+        assert_eq!(options.get_number("ts"), Some(8));
+        // The modelines after the failed one are not looked at
+        assert_eq!(options.get_bool("nu"), Some(false));
+    }
+
+    #[test]
+    fn test_expression_options() {
+        assert!(EXPR_OPTIONS.contains(&"indentexpr"));
+        assert!(!EXPR_OPTIONS.contains(&"indentkeys"));
+    }
+
+    #[test]
+    fn test_apply_modelines_expressions() {
+        let mut options = test_options();
+        let err = apply_modelines(&mut options, &lines(&["vim: set fde=getline(v\\:lnum):"]));
+        assert_eq!(
+            err.unwrap_err().to_string(),
+            "E992: Not allowed in a modeline when 'modelineexpr' is off: fde=getline(v:lnum)"
+        );
+        options.set_bool("modelineexpr", true).unwrap();
+        apply_modelines(&mut options, &lines(&["vim: set fde=getline(v\\:lnum):"])).unwrap();
+        assert_eq!(options.get_string("fde"), Some("getline(v:lnum)"));
+    }
+
+    #[test]
+    fn test_apply_modelines_options() {
+        let text = lines(&["vim: ts=2", "a", "b", "vim: ts=3"]);
+        let mut options = test_options();
+        options.set_number("modelines", 1).unwrap();
+        apply_modelines(&mut options, &text).unwrap();
+        assert_eq!(options.get_number("ts"), Some(3));
+
+        let mut options = test_options();
+        options.set_number("modelines", 0).unwrap();
+        apply_modelines(&mut options, &text).unwrap();
+        assert_eq!(options.get_number("ts"), Some(8));
+
+        let mut options = test_options();
+        options.set_bool("modeline", false).unwrap();
+        apply_modelines(&mut options, &text).unwrap();
+        assert_eq!(options.get_number("ts"), Some(8));
+    }
 }
@@ -156,6 +156,12 @@ pub trait OptionManager {
     /// Set an option value
     fn set(&mut self, name: &str, value: OptionValue) -> VimResult<()>;
 
+    /// Set only the local value of a window or buffer option, like
+    /// `:setlocal`; a global option is set as with [`set`](Self::set)
+    fn set_local(&mut self, name: &str, value: OptionValue) -> VimResult<()> {
+        self.set(name, value)
+    }
+
     /// Get a boolean option
     fn get_bool(&self, name: &str) -> Option<bool> {
         self.get(name).and_then(|v| v.as_bool())
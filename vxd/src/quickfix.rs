@@ -0,0 +1,1074 @@
+//! Quickfix and location lists.
+//!
+//! A quickfix list is a list of positions in files, each with a line of
+//! text, filled by commands such as `:vimgrep` and `:cexpr`.  A location
+//! list is the same kind of list, local to a window.  Each kind is kept
+//! in a stack of up to ten lists that `:colder` and `:cnewer` go through.
+//!
+//! Compiler output is turned into entries with 'errorformat': a
+//! comma-separated list of scanf-like patterns, where `%f`, `%l`, `%c`,
+//! `%m` and friends pick the fields of an entry out of a line.
+
+use crate::search::take_delimited;
+use crate::types::{LineNr, VimError, VimResult};
+
+/// Vim's default 'errorformat'
+pub const DEFAULT_ERRORFORMAT: &str = concat!(
+    r#"%*[^"]"%f"%*\D%l: %m,"%f"%*\D%l: %m,%-Gg%\?make[%*\d]: *** [%f:%l:%m,"#,
+    r#"%-Gg%\?make: *** [%f:%l:%m,"#,
+    r#"%-G%f:%l: (Each undeclared identifier is reported only once,"#,
+    r#"%-G%f:%l: for each function it appears in.),"#,
+    r#"%-GIn file included from %f:%l:%c:,%-GIn file included from %f:%l:%c\,,"#,
+    r#"%-GIn file included from %f:%l:%c,%-GIn file included from %f:%l,"#,
+    r#"%-G%*[ ]from %f:%l:%c,%-G%*[ ]from %f:%l:,%-G%*[ ]from %f:%l\,,"#,
+    r#"%-G%*[ ]from %f:%l,%f:%l:%c:%m,%f(%l):%m,%f:%l:%m,"#,
+    r#""%f"\, line %l%*\D%c%*[^ ]%m,"#,
+    r#"%D%*\a[%*\d]: Entering directory %*[`']%f',"#,
+    r#"%X%*\a[%*\d]: Leaving directory %*[`']%f',"#,
+    r#"%D%*\a: Entering directory %*[`']%f',%X%*\a: Leaving directory %*[`']%f',"#,
+    r#"%DMaking %*\a in %f,%f|%l| %m"#,
+);
+
+/// Vim's default 'grepformat'
+pub const DEFAULT_GREPFORMAT: &str = "%f:%l:%m,%f:%l%m,%f  %l%m";
+
+/// Most lists a quickfix or location list stack keeps
+pub const MAX_LISTS: usize = 10;
+
+/// One entry of a quickfix or location list
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QuickfixEntry {
+    /// File the entry is in; empty when there is none
+    pub filename: String,
+    /// Line number; 0 when unknown
+    pub lnum: LineNr,
+    /// Byte column, starting at 1; 0 when unknown
+    pub col: usize,
+    /// Error number; 0 when there is none
+    pub nr: usize,
+    /// Type of the entry, such as `E` for an error or `W` for a warning
+    pub kind: Option<char>,
+    /// Text to show for the entry
+    pub text: String,
+    /// Whether the entry was recognized; other entries are lines of
+    /// output that are only shown
+    pub valid: bool,
+}
+
+impl QuickfixEntry {
+    /// The name of the entry's type: "error", "warning", "info" or "note"
+    pub fn kind_name(&self) -> Option<&'static str> {
+        match self.kind?.to_ascii_uppercase() {
+            'E' => Some("error"),
+            'W' => Some("warning"),
+            'I' => Some("info"),
+            'N' => Some("note"),
+            _ => None,
+        }
+    }
+
+    /// Type and error number as shown after the position, such as
+    /// " error   5"
+    fn kind_text(&self) -> String {
+        let mut text = String::new();
+        match (self.kind_name(), self.kind) {
+            (Some(name), _) => text = format!(" {}", name),
+            (None, Some(kind)) if kind != ' ' => text = format!(" {}", kind),
+            _ => {}
+        }
+        if self.nr > 0 {
+            text.push_str(&format!(" {:3}", self.nr));
+        }
+        text
+    }
+
+    /// The entry as a line of the quickfix window:
+    /// `{file}|{lnum} col {col} {type}| {text}`
+    pub fn format(&self) -> String {
+        let text = one_line(&self.text);
+        if !self.valid {
+            return format!("|| {}", text);
+        }
+        let mut pos = String::new();
+        if self.lnum.0 > 0 {
+            pos = self.lnum.0.to_string();
+            if self.col > 0 {
+                pos.push_str(&format!(" col {}", self.col));
+            }
+        }
+        pos.push_str(&self.kind_text());
+        format!("{}|{}| {}", self.filename, pos.trim_start(), text)
+    }
+
+    /// The entry as listed by `:clist`, numbered `nr`
+    pub fn format_list(&self, nr: usize) -> String {
+        let text = one_line(&self.text);
+        if !self.valid {
+            return format!("{:2}: {}", nr, text);
+        }
+        let mut pos = self.filename.clone();
+        if self.lnum.0 > 0 {
+            pos.push_str(&format!(":{}", self.lnum.0));
+            if self.col > 0 {
+                pos.push_str(&format!(" col {}", self.col));
+            }
+        }
+        format!("{:2} {}{}: {}", nr, pos, self.kind_text(), text)
+    }
+}
+
+/// `text` with each line break replaced by a space and leading white
+/// space removed
+fn one_line(text: &str) -> String {
+    text.trim_start().replace('\n', " ")
+}
+
+/// A quickfix or location list
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QuickfixList {
+    /// Title, usually the command that created the list
+    pub title: String,
+    /// The entries
+    pub entries: Vec<QuickfixEntry>,
+    /// Index of the current entry
+    pub idx: usize,
+}
+
+impl QuickfixList {
+    /// Create a list with `title` and `entries`, the first one current
+    pub fn new(title: impl Into<String>, entries: Vec<QuickfixEntry>) -> Self {
+        let mut list = QuickfixList {
+            title: title.into(),
+            entries,
+            idx: 0,
+        };
+        list.idx = list.valid_indices().first().copied().unwrap_or(0);
+        list
+    }
+
+    /// The current entry
+    pub fn current(&self) -> Option<&QuickfixEntry> {
+        self.entries.get(self.idx)
+    }
+
+    /// Number of entries
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the list has no entries
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Indexes of the valid entries
+    pub fn valid_indices(&self) -> Vec<usize> {
+        (0..self.entries.len())
+            .filter(|&idx| self.entries[idx].valid)
+            .collect()
+    }
+
+    fn check_valid(&self) -> VimResult<Vec<usize>> {
+        let valid = self.valid_indices();
+        if valid.is_empty() {
+            Err(no_errors())
+        } else {
+            Ok(valid)
+        }
+    }
+
+    /// Move `count` valid entries forward (backward when `forward` is
+    /// false), stopping at the last (first) one.  With `by_file` each step
+    /// goes to another file, like `:cnfile` and `:cpfile`.  Fails with
+    /// E553 when there is no entry to move to.
+    pub fn go(&mut self, count: usize, forward: bool, by_file: bool) -> VimResult<usize> {
+        let valid = self.check_valid()?;
+        let mut idx = self.idx;
+        let mut moved = false;
+        for _ in 0..count.max(1) {
+            let file = &self.entries[idx].filename;
+            let next = if forward {
+                valid
+                    .iter()
+                    .copied()
+                    .find(|&i| i > idx && (!by_file || self.entries[i].filename != *file))
+            } else {
+                valid
+                    .iter()
+                    .rev()
+                    .copied()
+                    .find(|&i| i < idx && (!by_file || self.entries[i].filename != *file))
+            };
+            match next {
+                Some(next) => {
+                    idx = next;
+                    moved = true;
+                }
+                None => break,
+            }
+        }
+        if !moved {
+            return Err(VimError::Error(553, "No more items".to_string()));
+        }
+        self.idx = idx;
+        Ok(idx)
+    }
+
+    /// Make entry `nr` (starting at 1, limited to the last entry) current
+    /// for `:cc`.  An entry that is not valid is skipped forward, or else
+    /// backward.
+    pub fn select(&mut self, nr: usize) -> VimResult<usize> {
+        let valid = self.check_valid()?;
+        let want = nr.clamp(1, self.entries.len()) - 1;
+        self.idx = valid
+            .iter()
+            .copied()
+            .find(|&i| i >= want)
+            .or_else(|| valid.last().copied())
+            .unwrap_or(want);
+        Ok(self.idx)
+    }
+
+    /// Make the first valid entry current
+    pub fn first(&mut self) -> VimResult<usize> {
+        self.idx = self.check_valid()?[0];
+        Ok(self.idx)
+    }
+
+    /// Make the last valid entry current
+    pub fn last(&mut self) -> VimResult<usize> {
+        self.idx = *self.check_valid()?.last().unwrap_or(&0);
+        Ok(self.idx)
+    }
+}
+
+/// E42: the list has no valid entries
+fn no_errors() -> VimError {
+    VimError::Error(42, "No Errors".to_string())
+}
+
+/// A stack of quickfix or location lists, with one of them current
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QuickfixStack {
+    lists: Vec<QuickfixList>,
+    current: usize,
+}
+
+impl QuickfixStack {
+    /// Create an empty stack
+    pub const fn new() -> Self {
+        QuickfixStack {
+            lists: Vec::new(),
+            current: 0,
+        }
+    }
+
+    /// Add `list` after the current list and make it current.  Newer
+    /// lists are dropped, and the oldest one when the stack is full.
+    pub fn push(&mut self, list: QuickfixList) {
+        if !self.lists.is_empty() {
+            self.lists.truncate(self.current + 1);
+        }
+        self.lists.push(list);
+        if self.lists.len() > MAX_LISTS {
+            self.lists.remove(0);
+        }
+        self.current = self.lists.len() - 1;
+    }
+
+    /// The current list
+    pub fn current(&self) -> Option<&QuickfixList> {
+        self.lists.get(self.current)
+    }
+
+    /// The current list, for changing it
+    pub fn current_mut(&mut self) -> Option<&mut QuickfixList> {
+        self.lists.get_mut(self.current)
+    }
+
+    /// Number of the current list, starting at 1; 0 for an empty stack
+    pub fn current_nr(&self) -> usize {
+        if self.lists.is_empty() {
+            0
+        } else {
+            self.current + 1
+        }
+    }
+
+    /// Number of lists
+    pub fn len(&self) -> usize {
+        self.lists.len()
+    }
+
+    /// Whether there are no lists
+    pub fn is_empty(&self) -> bool {
+        self.lists.is_empty()
+    }
+
+    /// `:colder`: go `count` lists back
+    pub fn older(&mut self, count: usize) -> VimResult<()> {
+        for _ in 0..count.max(1) {
+            if self.current == 0 {
+                return Err(VimError::Error(
+                    380,
+                    "At bottom of quickfix stack".to_string(),
+                ));
+            }
+            self.current -= 1;
+        }
+        Ok(())
+    }
+
+    /// `:cnewer`: go `count` lists forward
+    pub fn newer(&mut self, count: usize) -> VimResult<()> {
+        for _ in 0..count.max(1) {
+            if self.current + 1 >= self.lists.len() {
+                return Err(VimError::Error(381, "At top of quickfix stack".to_string()));
+            }
+            self.current += 1;
+        }
+        Ok(())
+    }
+}
+
+/// Parsed arguments of `:vimgrep` and `:lvimgrep`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VimgrepSpec {
+    /// Pattern to search for; empty for the last search pattern
+    pub pattern: String,
+    /// `g`: add every match, not only the first one in each line
+    pub global: bool,
+    /// `j`: do not jump to the first match
+    pub no_jump: bool,
+    /// File names and patterns to search in
+    pub files: Vec<String>,
+}
+
+impl VimgrepSpec {
+    /// Parse `/{pattern}/[g][j] {file} ...` or `{pattern} {file} ...`.
+    /// Any character other than a letter, digit, `\`, `"` or `|` can
+    /// take the place of `/`.  Without delimiters the pattern ends at the
+    /// first white space and there are no flags.
+    pub fn parse(args: &str) -> VimResult<Self> {
+        let args = args.trim_start();
+        let delim = args.chars().next().ok_or(VimError::ArgumentRequired)?;
+        let (pattern, global, no_jump, rest) =
+            if delim.is_alphanumeric() || matches!(delim, '\\' | '"' | '|' | '_') {
+                let end = args.find(char::is_whitespace).unwrap_or(args.len());
+                (args[..end].to_string(), false, false, &args[end..])
+            } else {
+                let mut chars = args[delim.len_utf8()..].chars();
+                let pattern = take_delimited(&mut chars, delim);
+                let mut rest = chars.as_str();
+                let (mut global, mut no_jump) = (false, false);
+                while let Some(c) = rest.chars().next() {
+                    match c {
+                        'g' => global = true,
+                        'j' => no_jump = true,
+                        c if c.is_whitespace() => break,
+                        _ => return Err(VimError::TrailingCharacters),
+                    }
+                    rest = &rest[1..];
+                }
+                (pattern, global, no_jump, rest)
+            };
+        let files: Vec<String> = rest.split_whitespace().map(str::to_string).collect();
+        if files.is_empty() {
+            return Err(VimError::Error(
+                683,
+                "File name missing or invalid pattern".to_string(),
+            ));
+        }
+        Ok(VimgrepSpec {
+            pattern,
+            global,
+            no_jump,
+            files,
+        })
+    }
+}
+
+/// The shell command `:make` and `:grep` run: `prg` ('makeprg' or
+/// 'grepprg') with `$*` replaced by `args` (or `args` appended when there
+/// is no `$*`), followed by `shellpipe` with `%s` replaced by
+/// `errorfile` (or `errorfile` appended when there is no `%s`)
+pub fn make_command(prg: &str, args: &str, shellpipe: &str, errorfile: &str) -> String {
+    let args = args.trim();
+    let command = if prg.contains("$*") {
+        prg.replace("$*", args)
+    } else if args.is_empty() {
+        prg.to_string()
+    } else {
+        format!("{} {}", prg, args)
+    };
+    let pipe = if shellpipe.contains("%s") {
+        shellpipe.replace("%s", errorfile)
+    } else {
+        format!("{} {}", shellpipe, errorfile)
+    };
+    format!("{} {}", command, pipe)
+}
+
+// ============================================================================
+// 'errorformat'
+// ============================================================================
+
+/// What a line matched by an 'errorformat' pattern is used for, given by
+/// the `%E`, `%C`, `%G` etc. at its start
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EfmKind {
+    /// A complete single-line entry
+    Single,
+    /// `%E`, `%W`, `%I`, `%N`, `%A`: start of a multi-line entry, with
+    /// its type
+    Start(Option<char>),
+    /// `%C`: continuation of a multi-line entry
+    Continue,
+    /// `%Z`: end of a multi-line entry
+    End,
+    /// `%G`: a general message
+    General,
+    /// `%D`: entering a directory
+    EnterDir,
+    /// `%X`: leaving a directory
+    LeaveDir,
+    /// `%P`, `%Q`, `%O`: changes to the file stack, which is not kept
+    FileStack,
+}
+
+/// What a part of a pattern matches
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum EfmAtom {
+    Char(char),
+    Any,
+    Digit,
+    FileChar,
+    Class(Vec<(char, char)>, bool),
+}
+
+impl EfmAtom {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            EfmAtom::Char(want) => c == *want,
+            EfmAtom::Any => true,
+            EfmAtom::Digit => c.is_ascii_digit(),
+            EfmAtom::FileChar => {
+                c.is_alphanumeric() || "/.-_+,#$%~=\\".contains(c) || c as u32 >= 0x80
+            }
+            EfmAtom::Class(ranges, negate) => {
+                ranges.iter().any(|&(lo, hi)| lo <= c && c <= hi) != *negate
+            }
+        }
+    }
+}
+
+/// An atom repeated `min` to `max` times, captured as `field` when set
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct EfmNode {
+    atom: EfmAtom,
+    min: usize,
+    max: usize,
+    lazy: bool,
+    field: Option<char>,
+}
+
+impl EfmNode {
+    fn once(atom: EfmAtom) -> Self {
+        EfmNode {
+            atom,
+            min: 1,
+            max: 1,
+            lazy: false,
+            field: None,
+        }
+    }
+
+    fn many(atom: EfmAtom, min: usize) -> Self {
+        EfmNode {
+            atom,
+            min,
+            max: usize::MAX,
+            lazy: false,
+            field: None,
+        }
+    }
+}
+
+/// One pattern of 'errorformat'
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct EfmPattern {
+    kind: EfmKind,
+    /// `%+`: use the whole line as the message; `%-`: drop the line
+    flag: Option<char>,
+    nodes: Vec<EfmNode>,
+}
+
+/// The fields a pattern picked out of a line
+#[derive(Debug, Default)]
+struct EfmFields {
+    filename: Option<String>,
+    lnum: usize,
+    col: usize,
+    nr: usize,
+    kind: Option<char>,
+    message: Option<String>,
+}
+
+impl EfmPattern {
+    fn parse(pattern: &str) -> VimResult<Self> {
+        let invalid = || {
+            VimError::Error(
+                376,
+                format!("Invalid %-prefix in format string: {}", pattern),
+            )
+        };
+        let chars: Vec<char> = pattern.chars().collect();
+        let mut i = 0;
+        let mut kind = EfmKind::Single;
+        let mut flag = None;
+        if chars.first() == Some(&'%') {
+            let mut j = 1;
+            if matches!(chars.get(1), Some('+' | '-')) {
+                flag = chars.get(1).copied();
+                j = 2;
+            }
+            let prefix = chars.get(j).copied();
+            let prefix_kind = match prefix {
+                Some(c @ ('E' | 'W' | 'I' | 'N')) => Some(EfmKind::Start(Some(c))),
+                Some('A') => Some(EfmKind::Start(None)),
+                Some('C') => Some(EfmKind::Continue),
+                Some('Z') => Some(EfmKind::End),
+                Some('G') => Some(EfmKind::General),
+                Some('D') => Some(EfmKind::EnterDir),
+                Some('X') => Some(EfmKind::LeaveDir),
+                Some('P' | 'Q' | 'O') => Some(EfmKind::FileStack),
+                _ if flag.is_some() => return Err(invalid()),
+                _ => None,
+            };
+            if let Some(prefix_kind) = prefix_kind {
+                kind = prefix_kind;
+                i = j + 1;
+            }
+        }
+
+        let mut nodes: Vec<EfmNode> = Vec::new();
+        while i < chars.len() {
+            let c = chars[i];
+            i += 1;
+            if c == '\\' {
+                if let Some(&next) = chars.get(i) {
+                    nodes.push(EfmNode::once(EfmAtom::Char(next)));
+                    i += 1;
+                }
+                continue;
+            }
+            if c != '%' {
+                nodes.push(EfmNode::once(EfmAtom::Char(c)));
+                continue;
+            }
+            let Some(&conv) = chars.get(i) else {
+                return Err(invalid());
+            };
+            i += 1;
+            let field = |atom, min, lazy| EfmNode {
+                atom,
+                min,
+                max: usize::MAX,
+                lazy,
+                field: Some(conv),
+            };
+            match conv {
+                'f' => {
+                    // Followed by a literal, a file name may contain
+                    // anything up to the shortest match
+                    let literal_next = chars.get(i).is_some_and(|&c| c != '\\' && c != '%');
+                    nodes.push(if literal_next {
+                        field(EfmAtom::Any, 1, true)
+                    } else {
+                        field(EfmAtom::FileChar, 1, false)
+                    });
+                }
+                'l' | 'c' | 'v' | 'n' | 'e' | 'k' => nodes.push(field(EfmAtom::Digit, 1, false)),
+                'm' | 's' | 'o' => nodes.push(field(EfmAtom::Any, 1, false)),
+                'r' => nodes.push(field(EfmAtom::Any, 0, false)),
+                't' => nodes.push(EfmNode {
+                    max: 1,
+                    ..field(EfmAtom::Any, 1, false)
+                }),
+                'p' => nodes.push(field(
+                    EfmAtom::Class(
+                        vec![('-', '-'), (' ', ' '), ('\t', '\t'), ('.', '.')],
+                        false,
+                    ),
+                    0,
+                    false,
+                )),
+                '%' => nodes.push(EfmNode::once(EfmAtom::Char('%'))),
+                '*' => {
+                    let (atom, next) = parse_atom(&chars, i).ok_or_else(invalid)?;
+                    nodes.push(EfmNode::many(atom, 1));
+                    i = next;
+                }
+                '[' => {
+                    let (atom, next) = parse_atom(&chars, i - 1).ok_or_else(invalid)?;
+                    nodes.push(EfmNode::once(atom));
+                    i = next;
+                }
+                '.' => nodes.push(EfmNode::once(EfmAtom::Any)),
+                '#' => {
+                    if let Some(last) = nodes.last_mut() {
+                        last.min = 0;
+                        last.max = usize::MAX;
+                    }
+                }
+                '\\' => match chars.get(i) {
+                    Some('?' | '=') => {
+                        i += 1;
+                        if let Some(last) = nodes.last_mut() {
+                            last.min = 0;
+                        }
+                    }
+                    Some('+') => {
+                        i += 1;
+                        if let Some(last) = nodes.last_mut() {
+                            last.max = usize::MAX;
+                        }
+                    }
+                    _ => {
+                        let (atom, next) = parse_atom(&chars, i - 1).ok_or_else(invalid)?;
+                        nodes.push(EfmNode::once(atom));
+                        i = next;
+                    }
+                },
+                '^' | '$' | '~' => {}
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(EfmPattern { kind, flag, nodes })
+    }
+
+    /// The fields of `line` when the pattern matches all of it
+    fn match_line(&self, line: &str) -> Option<EfmFields> {
+        let chars: Vec<char> = line.chars().collect();
+        let mut captures = Vec::new();
+        if !match_nodes(&self.nodes, &chars, 0, &mut captures) {
+            return None;
+        }
+        let mut fields = EfmFields::default();
+        for (field, text) in captures {
+            let number = text.parse().unwrap_or(0);
+            match field {
+                'f' => fields.filename = Some(text),
+                'l' => fields.lnum = number,
+                'c' | 'v' => fields.col = number,
+                'p' => fields.col = text.chars().count() + 1,
+                'n' => fields.nr = number,
+                't' => fields.kind = text.chars().next(),
+                'm' | 'r' => fields.message = Some(text),
+                _ => {}
+            }
+        }
+        Some(fields)
+    }
+}
+
+/// Parse a character class at `chars[i]`: `[...]` or `\d`, `\D`, `\a`,
+/// `\s`, `\S`, `\w`.  Returns the atom and the index after it.
+fn parse_atom(chars: &[char], i: usize) -> Option<(EfmAtom, usize)> {
+    const DIGITS: (char, char) = ('0', '9');
+    const ALPHA: [(char, char); 2] = [('a', 'z'), ('A', 'Z')];
+    const SPACE: [(char, char); 2] = [(' ', ' '), ('\t', '\t')];
+    match chars.get(i)? {
+        '\\' => {
+            let atom = match chars.get(i + 1)? {
+                'd' => EfmAtom::Digit,
+                'D' => EfmAtom::Class(vec![DIGITS], true),
+                'a' => EfmAtom::Class(ALPHA.to_vec(), false),
+                's' => EfmAtom::Class(SPACE.to_vec(), false),
+                'S' => EfmAtom::Class(SPACE.to_vec(), true),
+                'w' => EfmAtom::Class(vec![ALPHA[0], ALPHA[1], DIGITS, ('_', '_')], false),
+                'f' => EfmAtom::FileChar,
+                &c => EfmAtom::Char(c),
+            };
+            Some((atom, i + 2))
+        }
+        '[' => {
+            let mut j = i + 1;
+            let negate = chars.get(j) == Some(&'^');
+            if negate {
+                j += 1;
+            }
+            let mut ranges = Vec::new();
+            let mut first = true;
+            while let Some(&c) = chars.get(j) {
+                if c == ']' && !first {
+                    return Some((EfmAtom::Class(ranges, negate), j + 1));
+                }
+                first = false;
+                if chars.get(j + 1) == Some(&'-') && chars.get(j + 2).is_some_and(|&c| c != ']') {
+                    ranges.push((c, chars[j + 2]));
+                    j += 3;
+                } else {
+                    ranges.push((c, c));
+                    j += 1;
+                }
+            }
+            None
+        }
+        _ => None,
+    }
+}
+
+/// Whether `nodes` match all of `chars` from `pos`, collecting the
+/// captured fields
+fn match_nodes(
+    nodes: &[EfmNode],
+    chars: &[char],
+    pos: usize,
+    captures: &mut Vec<(char, String)>,
+) -> bool {
+    let Some((node, rest)) = nodes.split_first() else {
+        return pos == chars.len();
+    };
+    let available = chars[pos..]
+        .iter()
+        .take(node.max)
+        .take_while(|&&c| node.atom.matches(c))
+        .count();
+    if available < node.min {
+        return false;
+    }
+    let try_len = |len: usize, captures: &mut Vec<(char, String)>| {
+        let mark = captures.len();
+        if let Some(field) = node.field {
+            captures.push((field, chars[pos..pos + len].iter().collect()));
+        }
+        if match_nodes(rest, chars, pos + len, captures) {
+            return true;
+        }
+        captures.truncate(mark);
+        false
+    };
+    if node.lazy {
+        (node.min..=available).any(|len| try_len(len, captures))
+    } else {
+        (node.min..=available)
+            .rev()
+            .any(|len| try_len(len, captures))
+    }
+}
+
+/// A parsed 'errorformat'
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorFormat {
+    patterns: Vec<EfmPattern>,
+}
+
+impl ErrorFormat {
+    /// Parse an 'errorformat' value: patterns separated by commas, where
+    /// `\,` is a literal comma
+    pub fn parse(efm: &str) -> VimResult<Self> {
+        let mut patterns = Vec::new();
+        let mut current = String::new();
+        let mut chars = efm.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => {
+                    current.push(c);
+                    if let Some(next) = chars.next() {
+                        current.push(next);
+                    }
+                }
+                ',' => {
+                    if !current.is_empty() {
+                        patterns.push(EfmPattern::parse(&current)?);
+                    }
+                    current.clear();
+                }
+                _ => current.push(c),
+            }
+        }
+        if !current.is_empty() {
+            patterns.push(EfmPattern::parse(&current)?);
+        }
+        Ok(ErrorFormat { patterns })
+    }
+
+    /// Turn lines of output into entries.  Lines no pattern matches are
+    /// kept as entries that are not valid; lines matched by a `%-`
+    /// pattern are dropped.
+    pub fn parse_lines<S: AsRef<str>>(&self, lines: &[S]) -> Vec<QuickfixEntry> {
+        let mut entries = Vec::new();
+        let mut pending: Option<QuickfixEntry> = None;
+        let mut dirs: Vec<String> = Vec::new();
+        for line in lines {
+            let line = line.as_ref();
+            let found = self
+                .patterns
+                .iter()
+                .find_map(|pattern| Some((pattern, pattern.match_line(line)?)));
+            let Some((pattern, fields)) = found else {
+                entries.extend(pending.take());
+                entries.push(QuickfixEntry {
+                    text: line.to_string(),
+                    ..QuickfixEntry::default()
+                });
+                continue;
+            };
+            let message = match pattern.flag {
+                Some('+') => Some(line.to_string()),
+                _ => fields.message.clone(),
+            };
+            let filename = fields.filename.clone().map(|name| match dirs.last() {
+                Some(dir) if !name.starts_with('/') => format!("{}/{}", dir, name),
+                _ => name,
+            });
+            match pattern.kind {
+                EfmKind::EnterDir => {
+                    dirs.extend(fields.filename);
+                    continue;
+                }
+                EfmKind::LeaveDir => {
+                    dirs.pop();
+                    continue;
+                }
+                EfmKind::FileStack => continue,
+                EfmKind::Continue | EfmKind::End if pending.is_some() => {
+                    if let Some(entry) = pending.as_mut() {
+                        if pattern.flag != Some('-') {
+                            if let Some(message) = message {
+                                if !entry.text.is_empty() {
+                                    entry.text.push('\n');
+                                }
+                                entry.text.push_str(&message);
+                            }
+                        }
+                        if entry.filename.is_empty() {
+                            entry.filename = filename.unwrap_or_default();
+                        }
+                        if entry.lnum.0 == 0 {
+                            entry.lnum = LineNr(fields.lnum);
+                        }
+                        if entry.col == 0 {
+                            entry.col = fields.col;
+                        }
+                        if entry.nr == 0 {
+                            entry.nr = fields.nr;
+                        }
+                    }
+                    if pattern.kind == EfmKind::End {
+                        entries.extend(pending.take());
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+            entries.extend(pending.take());
+            if pattern.flag == Some('-') {
+                continue;
+            }
+            let entry = QuickfixEntry {
+                filename: filename.unwrap_or_default(),
+                lnum: LineNr(fields.lnum),
+                col: fields.col,
+                nr: fields.nr,
+                kind: fields.kind,
+                text: message.unwrap_or_default(),
+                valid: pattern.kind != EfmKind::General,
+            };
+            match pattern.kind {
+                EfmKind::Start(kind) => {
+                    pending = Some(QuickfixEntry {
+                        kind: entry.kind.or(kind),
+                        ..entry
+                    })
+                }
+                _ => entries.push(entry),
+            }
+        }
+        entries.extend(pending);
+        entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(filename: &str, lnum: usize, text: &str) -> QuickfixEntry {
+        QuickfixEntry {
+            filename: filename.to_string(),
+            lnum: LineNr(lnum),
+            text: text.to_string(),
+            valid: true,
+            ..QuickfixEntry::default()
+        }
+    }
+
+    #[test]
+    fn test_vimgrep_spec_parse() {
+        let spec = VimgrepSpec::parse("/foo bar/gj a.txt **/*.rs").unwrap();
+        assert_eq!(spec.pattern, "foo bar");
+        assert!(spec.global && spec.no_jump);
+        assert_eq!(spec.files, vec!["a.txt", "**/*.rs"]);
+
+        let spec = VimgrepSpec::parse("foo a.txt").unwrap();
+        assert_eq!(spec.pattern, "foo");
+        assert!(!spec.global && !spec.no_jump);
+
+        let spec = VimgrepSpec::parse("#a\\#b# x").unwrap();
+        assert_eq!(spec.pattern, "a#b");
+
+        assert!(VimgrepSpec::parse("/foo/").is_err());
+        assert!(VimgrepSpec::parse("/foo/x a.txt").is_err());
+        assert!(VimgrepSpec::parse("").is_err());
+    }
+
+    #[test]
+    fn test_quickfix_list_current() {
+        let first = entry("a.txt", 2, "foo");
+        let list = QuickfixList::new(":vimgrep foo a.txt", vec![first.clone()]);
+        assert_eq!(list.current(), Some(&first));
+        assert_eq!(list.len(), 1);
+        assert!(QuickfixList::default().current().is_none());
+    }
+
+    #[test]
+    fn test_quickfix_list_navigation() {
+        let invalid = QuickfixEntry {
+            text: "note".to_string(),
+            ..QuickfixEntry::default()
+        };
+        let mut list = QuickfixList::new(
+            "",
+            vec![
+                invalid,
+                entry("a", 1, "x"),
+                entry("a", 2, "y"),
+                entry("b", 1, "z"),
+            ],
+        );
+        assert_eq!(list.idx, 1);
+        assert_eq!(list.go(1, true, false), Ok(2));
+        assert_eq!(list.go(5, true, false), Ok(3));
+        assert!(list.go(1, true, false).is_err());
+        assert_eq!(list.go(1, false, true), Ok(2));
+        assert_eq!(list.select(1), Ok(1));
+        assert_eq!(list.select(99), Ok(3));
+        assert_eq!(list.first(), Ok(1));
+        assert_eq!(list.last(), Ok(3));
+        assert!(QuickfixList::default().first().is_err());
+    }
+
+    #[test]
+    fn test_quickfix_stack() {
+        let mut stack = QuickfixStack::new();
+        for nr in 0..12 {
+            stack.push(QuickfixList::new(nr.to_string(), Vec::new()));
+        }
+        assert_eq!(stack.len(), MAX_LISTS);
+        assert_eq!(stack.current().unwrap().title, "11");
+        stack.older(2).unwrap();
+        assert_eq!(stack.current_nr(), 8);
+        assert!(stack.newer(3).is_err());
+        assert_eq!(stack.current_nr(), 10);
+        stack.older(1).unwrap();
+        stack.push(QuickfixList::new("new", Vec::new()));
+        assert_eq!((stack.current_nr(), stack.len()), (10, 10));
+        assert!(stack.older(10).is_err());
+        assert_eq!(stack.current_nr(), 1);
+    }
+
+    #[test]
+    fn test_entry_format() {
+        let mut e = entry("a.c", 3, "  bad\nthing");
+        e.col = 5;
+        e.kind = Some('e');
+        e.nr = 12;
+        assert_eq!(e.format(), "a.c|3 col 5 error  12| bad thing");
+        assert_eq!(e.format_list(1), " 1 a.c:3 col 5 error  12: bad thing");
+        assert_eq!(entry("a.c", 3, "x").format(), "a.c|3| x");
+        let invalid = QuickfixEntry {
+            text: "make: done".to_string(),
+            ..QuickfixEntry::default()
+        };
+        assert_eq!(invalid.format(), "|| make: done");
+    }
+
+    #[test]
+    fn test_errorformat_default() {
+        let efm = ErrorFormat::parse(DEFAULT_ERRORFORMAT).unwrap();
+        let entries = efm.parse_lines(&[
+            "main.c:10:5: error: unknown type",
+            "lib/x.c:3: warning",
+            "In file included from a.h:2:1,",
+            "make: *** [all] Error 1",
+            "\"y.c\", line 4: oops",
+        ]);
+        assert_eq!(entries.len(), 4);
+        assert_eq!(
+            (
+                entries[0].filename.as_str(),
+                entries[0].lnum,
+                entries[0].col
+            ),
+            ("main.c", LineNr(10), 5)
+        );
+        assert_eq!(entries[0].text, " error: unknown type");
+        assert_eq!(entries[1].filename, "lib/x.c");
+        assert!(!entries[2].valid);
+        assert_eq!(entries[2].text, "make: *** [all] Error 1");
+        assert_eq!(
+            (entries[3].filename.as_str(), entries[3].lnum),
+            ("y.c", LineNr(4))
+        );
+    }
+
+    #[test]
+    fn test_errorformat_multiline() {
+        let efm = ErrorFormat::parse(r"%EError in line %l of %f:,%Z%m").unwrap();
+        let entries = efm.parse_lines(&["Error in line 123 of foo.c:", "unknown variable 'i'"]);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].filename, "foo.c");
+        assert_eq!(entries[0].lnum, LineNr(123));
+        assert_eq!(entries[0].kind, Some('E'));
+        assert_eq!(entries[0].text, "unknown variable 'i'");
+
+        let efm = ErrorFormat::parse(r"%A%f:%l: %m,%C    %m,%-G%.%#").unwrap();
+        let entries = efm.parse_lines(&["a.py:3: bad", "    more", "other"]);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].text, "bad\nmore");
+    }
+
+    #[test]
+    fn test_errorformat_fields() {
+        let efm = ErrorFormat::parse(r"%f:%l:%c:%t%n:%m,%p^,%+Gfatal%.%#").unwrap();
+        let entries = efm.parse_lines(&["x.rs:1:2:W42:unused", "  ^", "fatal error"]);
+        assert_eq!(entries[0].kind, Some('W'));
+        assert_eq!(entries[0].nr, 42);
+        assert_eq!(entries[0].kind_name(), Some("warning"));
+        assert_eq!(entries[1].col, 3);
+        assert_eq!(entries[2].text, "fatal error");
+        assert!(!entries[2].valid);
+
+        let efm = ErrorFormat::parse(r"%DEntering %f,%XLeaving %f,%f:%l:%m").unwrap();
+        let entries = efm.parse_lines(&["Entering sub", "a.c:1:x", "Leaving sub", "b.c:2:y"]);
+        assert_eq!(entries[0].filename, "sub/a.c");
+        assert_eq!(entries[1].filename, "b.c");
+
+        assert!(ErrorFormat::parse("%-Y%f").is_err());
+    }
+
+    #[test]
+    fn test_make_command() {
+        assert_eq!(
+            make_command("make", "all", "2>&1| tee", "/tmp/err"),
+            "make all 2>&1| tee /tmp/err"
+        );
+        assert_eq!(
+            make_command("grep -n $* /dev/null", "foo *.c", ">%s 2>&1", "/tmp/err"),
+            "grep -n foo *.c /dev/null >/tmp/err 2>&1"
+        );
+        assert_eq!(make_command("make", " ", "|", "e"), "make | e");
+    }
+}
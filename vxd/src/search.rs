@@ -3,6 +3,8 @@
 //! Vim's search functionality uses its own regex dialect with options
 //! for case sensitivity, magic mode, and incremental search.
 
+use std::time::{Duration, Instant};
+
 use crate::cursor::CursorPosition;
 use crate::types::*;
 
@@ -28,6 +30,258 @@ pub struct SearchOptions {
     pub incsearch: bool,
     /// Highlight all matches
     pub hlsearch: bool,
+    /// Characters that make up a keyword, for `\<` and `\>`
+    pub iskeyword: KeywordChars,
+}
+
+/// The characters of a keyword, as set with 'iskeyword'
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeywordChars {
+    /// Items in order; a later item overrides an earlier one
+    items: Vec<(bool, KeywordItem)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KeywordItem {
+    /// `@`: alphabetic characters
+    Alpha,
+    /// A character or a range of character codes
+    Range(u32, u32),
+}
+
+impl KeywordChars {
+    /// The default 'iskeyword'
+    pub const DEFAULT: &'static str = "@,48-57,_,192-255";
+
+    /// Parse an 'iskeyword' value such as `@,48-57,_,192-255` or `@,^a-z`
+    pub fn parse(value: &str) -> VimResult<Self> {
+        let invalid = || VimError::Error(474, format!("Invalid argument: iskeyword={}", value));
+        let mut items = Vec::new();
+        for part in value.split(',').filter(|part| !part.is_empty()) {
+            let (exclude, part) = match part.strip_prefix('^') {
+                Some(rest) if !rest.is_empty() => (true, rest),
+                _ => (false, part),
+            };
+            if part == "@" {
+                items.push((exclude, KeywordItem::Alpha));
+                continue;
+            }
+            // The first character may itself be a "-", as in "--@"
+            let split = part
+                .char_indices()
+                .skip(1)
+                .find(|&(_, c)| c == '-')
+                .map(|(idx, _)| idx);
+            let (low, high) = match split {
+                Some(idx) => (&part[..idx], &part[idx + 1..]),
+                None => (part, part),
+            };
+            let low = keyword_char_code(low).ok_or_else(invalid)?;
+            let high = keyword_char_code(high).ok_or_else(invalid)?;
+            if low > high {
+                return Err(invalid());
+            }
+            items.push((exclude, KeywordItem::Range(low, high)));
+        }
+        Ok(KeywordChars { items })
+    }
+
+    /// Whether `c` is a keyword character.  Characters from 256 up are
+    /// keyword characters when they are letters or digits.
+    pub fn contains(&self, c: char) -> bool {
+        let code = c as u32;
+        if code >= 256 {
+            return c.is_alphanumeric();
+        }
+        let mut keyword = false;
+        for &(exclude, item) in &self.items {
+            let matches = match item {
+                KeywordItem::Alpha => c.is_alphabetic(),
+                KeywordItem::Range(low, high) => (low..=high).contains(&code),
+            };
+            if matches {
+                keyword = !exclude;
+            }
+        }
+        keyword
+    }
+}
+
+impl Default for KeywordChars {
+    fn default() -> Self {
+        KeywordChars::parse(KeywordChars::DEFAULT).expect("valid default 'iskeyword'")
+    }
+}
+
+/// A character code in 'iskeyword': a decimal number or a single character
+fn keyword_char_code(text: &str) -> Option<u32> {
+    if !text.is_empty() && text.bytes().all(|b| b.is_ascii_digit()) {
+        return text.parse().ok();
+    }
+    let mut chars = text.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c as u32),
+        _ => None,
+    }
+}
+
+/// A literal pattern, where a leading `\<` and a trailing `\>` require the
+/// match to start and end at a keyword boundary
+#[derive(Debug, Clone, Copy)]
+struct Needle<'a> {
+    text: &'a str,
+    word_start: bool,
+    word_end: bool,
+}
+
+impl<'a> Needle<'a> {
+    fn parse(pattern: &'a str) -> Self {
+        let (word_start, text) = match pattern.strip_prefix("\\<") {
+            Some(rest) => (true, rest),
+            None => (false, pattern),
+        };
+        let (word_end, text) = match text.strip_suffix("\\>") {
+            Some(rest) => (true, rest),
+            None => (false, text),
+        };
+        Needle {
+            text,
+            word_start,
+            word_end,
+        }
+    }
+
+    /// Whether a match of the text at `col` in `line` is at the keyword
+    /// boundaries the pattern asks for
+    fn at_boundaries(&self, line: &str, col: usize, iskeyword: &KeywordChars) -> bool {
+        let before = line.get(..col).and_then(|text| text.chars().next_back());
+        let after = line
+            .get(col + self.text.len()..)
+            .and_then(|text| text.chars().next());
+        let first = self.text.chars().next();
+        let last = self.text.chars().next_back();
+        let is_keyword = |c: Option<char>| c.is_some_and(|c| iskeyword.contains(c));
+        (!self.word_start || (is_keyword(first) && !is_keyword(before)))
+            && (!self.word_end || (is_keyword(last) && !is_keyword(after)))
+    }
+
+    /// The first match starting at or after `from` that ends before `to`
+    fn find(
+        &self,
+        line: &str,
+        from: usize,
+        to: usize,
+        case_sensitive: bool,
+        iskeyword: &KeywordChars,
+    ) -> Option<usize> {
+        let (haystack, needle) = fold_case(line, self.text, case_sensitive);
+        let to = to.min(haystack.len());
+        let mut from = from;
+        while from <= to && !self.text.is_empty() {
+            let pos = from + haystack.get(from..to)?.find(needle.as_ref())?;
+            if self.at_boundaries(line, pos, iskeyword) {
+                return Some(pos);
+            }
+            from = pos + needle.chars().next().map_or(1, char::len_utf8);
+        }
+        None
+    }
+
+    /// The last match that starts before `before`
+    fn rfind(
+        &self,
+        line: &str,
+        before: usize,
+        case_sensitive: bool,
+        iskeyword: &KeywordChars,
+    ) -> Option<usize> {
+        let mut last = None;
+        let mut from = 0;
+        while from < before {
+            let Some(pos) = self.find(line, from, line.len(), case_sensitive, iskeyword) else {
+                break;
+            };
+            if pos >= before {
+                break;
+            }
+            last = Some(pos);
+            from = pos
+                + line
+                    .get(pos..)
+                    .and_then(|rest| rest.chars().next())
+                    .map_or(1, char::len_utf8);
+        }
+        last
+    }
+}
+
+fn fold_case<'a>(
+    line: &'a str,
+    needle: &'a str,
+    case_sensitive: bool,
+) -> (std::borrow::Cow<'a, str>, std::borrow::Cow<'a, str>) {
+    if case_sensitive {
+        (line.into(), needle.into())
+    } else {
+        (line.to_lowercase().into(), needle.to_lowercase().into())
+    }
+}
+
+/// The first match of `pattern` in `text` starting at or after byte
+/// `start`, as a byte range.  The pattern is literal like the ones
+/// [`SimpleSearchEngine`] finds, apart from a leading `^` and a trailing
+/// `$` anchoring the match at the start and end of `text`, and `\c` or
+/// `\C` anywhere overriding `ignore_case`.
+pub fn match_text(
+    text: &str,
+    pattern: &str,
+    start: usize,
+    ignore_case: bool,
+) -> Option<(usize, usize)> {
+    let mut case_sensitive = !ignore_case;
+    let mut literal = String::with_capacity(pattern.len());
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            literal.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('c') => case_sensitive = false,
+            Some('C') => case_sensitive = true,
+            Some(next) => {
+                literal.push(c);
+                literal.push(next);
+            }
+            None => literal.push(c),
+        }
+    }
+    let (at_start, rest) = match literal.strip_prefix('^') {
+        Some(rest) => (true, rest),
+        None => (false, literal.as_str()),
+    };
+    let (at_end, rest) = match rest.strip_suffix('$') {
+        Some(rest) if !rest.ends_with('\\') => (true, rest),
+        _ => (false, rest),
+    };
+    let needle = Needle::parse(rest);
+    let len = needle.text.len();
+    if start > text.len() || (at_start && start > 0) {
+        return None;
+    }
+    let from = match (at_start, at_end) {
+        (true, true) if text.len() != len => return None,
+        (true, _) => 0,
+        (false, true) => text.len().checked_sub(len)?.max(start),
+        (false, false) => start,
+    };
+    if len == 0 {
+        return Some((from, from));
+    }
+    let to = if at_start { len } else { text.len() };
+    let iskeyword = KeywordChars::default();
+    let pos = needle.find(text, from, to, case_sensitive, &iskeyword)?;
+    Some((pos, pos + len))
 }
 
 /// A compiled search pattern
@@ -272,44 +526,6 @@ impl SimpleSearchEngine {
         }
     }
 
-    fn match_text(line: &str, start_col: usize, needle: &str, case_sensitive: bool) -> Option<usize> {
-        if start_col > line.len() {
-            return None;
-        }
-        let search_line = if case_sensitive {
-            std::borrow::Cow::Borrowed(line)
-        } else {
-            std::borrow::Cow::Owned(line.to_lowercase())
-        };
-        let needle = if case_sensitive {
-            std::borrow::Cow::Borrowed(needle)
-        } else {
-            std::borrow::Cow::Owned(needle.to_lowercase())
-        };
-        let haystack = &search_line[start_col..];
-        haystack.find(needle.as_ref()).map(|pos| start_col + pos)
-    }
-
-    fn match_text_backward(
-        line: &str,
-        end_col: usize,
-        needle: &str,
-        case_sensitive: bool,
-    ) -> Option<usize> {
-        let end_col = end_col.min(line.len());
-        let search_line = if case_sensitive {
-            std::borrow::Cow::Borrowed(line)
-        } else {
-            std::borrow::Cow::Owned(line.to_lowercase())
-        };
-        let needle = if case_sensitive {
-            std::borrow::Cow::Borrowed(needle)
-        } else {
-            std::borrow::Cow::Owned(needle.to_lowercase())
-        };
-        let haystack = &search_line[..end_col];
-        haystack.rfind(needle.as_ref())
-    }
 
     fn build_match(
         line_idx: usize,
@@ -332,13 +548,14 @@ impl SimpleSearchEngine {
         options: &SearchOptions,
     ) -> Option<SearchMatch> {
         let case_sensitive = Self::case_sensitive(options, &pattern.pattern);
+        let needle = Needle::parse(&pattern.pattern);
         let start_line = from.line.0.saturating_sub(1);
         for (idx, line) in self.lines.iter().enumerate().skip(start_line) {
             let start_col = if idx == start_line { from.col } else { 0 };
             if let Some(match_col) =
-                Self::match_text(line, start_col, &pattern.pattern, case_sensitive)
+                needle.find(line, start_col, line.len(), case_sensitive, &options.iskeyword)
             {
-                return Some(Self::build_match(idx, match_col, pattern.pattern.len(), line));
+                return Some(Self::build_match(idx, match_col, needle.text.len(), line));
             }
         }
         None
@@ -351,13 +568,14 @@ impl SimpleSearchEngine {
         options: &SearchOptions,
     ) -> Option<SearchMatch> {
         let case_sensitive = Self::case_sensitive(options, &pattern.pattern);
+        let needle = Needle::parse(&pattern.pattern);
         let start_line = from.line.0.saturating_sub(1);
         for (idx, line) in self.lines.iter().enumerate().take(start_line + 1).rev() {
             let end_col = if idx == start_line { from.col } else { line.len() };
             if let Some(match_col) =
-                Self::match_text_backward(line, end_col, &pattern.pattern, case_sensitive)
+                needle.rfind(line, end_col, case_sensitive, &options.iskeyword)
             {
-                return Some(Self::build_match(idx, match_col, pattern.pattern.len(), line));
+                return Some(Self::build_match(idx, match_col, needle.text.len(), line));
             }
         }
         None
@@ -413,6 +631,7 @@ impl SearchEngine for SimpleSearchEngine {
             return Err(VimError::PatternNotFound(pattern.pattern.clone()));
         }
         let case_sensitive = Self::case_sensitive(options, &pattern.pattern);
+        let needle = Needle::parse(&pattern.pattern);
         let mut matches = Vec::new();
         let start_line = start.line.0.saturating_sub(1);
         let end_line = end.line.0.saturating_sub(1).min(self.lines.len().saturating_sub(1));
@@ -424,30 +643,13 @@ impl SearchEngine for SimpleSearchEngine {
             line_end = line_end.min(line.len());
             let mut offset = line_start;
             while offset <= line_end {
-                let search_line = if case_sensitive {
-                    std::borrow::Cow::Borrowed(line)
-                } else {
-                    std::borrow::Cow::Owned(line.to_lowercase())
-                };
-                let needle = if case_sensitive {
-                    std::borrow::Cow::Borrowed(pattern.pattern.as_str())
-                } else {
-                    std::borrow::Cow::Owned(pattern.pattern.to_lowercase())
-                };
-                let haystack = &search_line[offset..line_end];
-                if let Some(pos) = haystack.find(needle.as_ref()) {
-                    let match_col = offset + pos;
-                    matches.push(Self::build_match(
-                        idx,
-                        match_col,
-                        pattern.pattern.len(),
-                        line,
-                    ));
-                    let advance = pattern.pattern.len().max(1);
-                    offset = match_col + advance;
-                } else {
+                let Some(match_col) =
+                    needle.find(line, offset, line_end, case_sensitive, &options.iskeyword)
+                else {
                     break;
-                }
+                };
+                matches.push(Self::build_match(idx, match_col, needle.text.len(), line));
+                offset = match_col + needle.text.len().max(1);
             }
         }
 
@@ -465,10 +667,15 @@ impl SearchEngine for SimpleSearchEngine {
         &self,
         word: &str,
         direction: Direction,
-        _whole_word: bool,
+        whole_word: bool,
     ) -> VimResult<SearchPattern> {
+        let pattern = if whole_word {
+            format!("\\<{}\\>", word)
+        } else {
+            word.to_string()
+        };
         Ok(SearchPattern {
-            pattern: word.to_string(),
+            pattern,
             direction,
             offset: SearchOffset::None,
             valid: !word.is_empty(),
@@ -520,12 +727,107 @@ pub struct SubstituteSpec {
     pub range: Option<LineRange>,
 }
 
-/// Apply a substitute operation to the given lines.
-pub fn apply_substitute(
+impl SubstituteSpec {
+    /// Parse the arguments of `:s`: `/{pattern}/{string}/[flags]`, where
+    /// any character other than a letter, digit, `\`, `"` or `|` can take
+    /// the place of `/`.  An empty pattern uses the last search pattern.
+    /// The range is left for the caller to fill in.
+    pub fn parse(args: &str) -> VimResult<Self> {
+        let mut chars = args.chars();
+        let delim = chars.next().ok_or(VimError::ArgumentRequired)?;
+        if delim.is_alphanumeric() || matches!(delim, '\\' | '"' | '|') || delim.is_whitespace() {
+            return Err(VimError::Error(
+                146,
+                "Regular expressions can't be delimited by letters".to_string(),
+            ));
+        }
+        let pattern = take_delimited(&mut chars, delim);
+        let replacement = take_delimited(&mut chars, delim);
+
+        let mut flags = SubstituteFlags {
+            use_last_pattern: pattern.is_empty(),
+            ..SubstituteFlags::default()
+        };
+        for c in chars.by_ref() {
+            match c {
+                '&' => {}
+                'g' => flags.global = !flags.global,
+                'c' => flags.confirm = true,
+                'n' => flags.report = true,
+                'i' => flags.ignore_case = true,
+                'I' => flags.no_ignore_case = true,
+                'p' => flags.print = true,
+                c if c.is_whitespace() => break,
+                _ => return Err(VimError::TrailingCharacters),
+            }
+        }
+        if !chars.as_str().trim().is_empty() {
+            return Err(VimError::TrailingCharacters);
+        }
+        Ok(SubstituteSpec {
+            pattern,
+            replacement,
+            flags,
+            range: None,
+        })
+    }
+}
+
+/// Text up to the next unescaped `delim`.  `\{delim}` stands for the
+/// delimiter itself; other escapes are kept.
+pub(crate) fn take_delimited(chars: &mut std::str::Chars<'_>, delim: char) -> String {
+    let mut text = String::new();
+    while let Some(c) = chars.next() {
+        if c == delim {
+            break;
+        }
+        if c == '\\' {
+            match chars.next() {
+                Some(next) if next == delim => text.push(next),
+                Some(next) => {
+                    text.push(c);
+                    text.push(next);
+                }
+                None => text.push(c),
+            }
+        } else {
+            text.push(c);
+        }
+    }
+    text
+}
+
+/// A line changed by a substitute
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubstitutedLine {
+    /// Line number
+    pub line: LineNr,
+    /// The line after substitution
+    pub text: String,
+    /// Byte ranges of the inserted replacements in `text`
+    pub replaced: Vec<(usize, usize)>,
+}
+
+/// The lines a substitute changes, without changing anything.  This is
+/// what 'inccommand' previews.
+pub fn substitute_lines(
     lines: &[String],
     spec: &SubstituteSpec,
     use_last_pattern: Option<&str>,
-) -> VimResult<Vec<String>> {
+) -> VimResult<Vec<SubstitutedLine>> {
+    substitute_lines_with(lines, spec, use_last_pattern, &mut |_, _| {
+        Ok(spec.replacement.clone())
+    })
+}
+
+/// Like [`substitute_lines`], with `replace` giving the replacement for
+/// each matched text and the line it is in, as a `\=` expression does.
+pub fn substitute_lines_with(
+    lines: &[String],
+    spec: &SubstituteSpec,
+    use_last_pattern: Option<&str>,
+    replace: &mut dyn FnMut(LineNr, &str) -> VimResult<String>,
+) -> VimResult<Vec<SubstitutedLine>> {
     let pattern = if spec.flags.use_last_pattern {
         use_last_pattern.unwrap_or("").to_string()
     } else {
@@ -543,28 +845,185 @@ pub fn apply_substitute(
         .unwrap_or(lines.len())
         .min(lines.len());
 
-    let mut out = lines.to_vec();
-    for idx in start..end {
-        if let Some(line) = out.get_mut(idx) {
-            *line = substitute_line(line, &pattern, &spec.replacement, spec.flags.global);
+    let mut changed = Vec::new();
+    for (idx, line) in lines.iter().enumerate().take(end).skip(start) {
+        let line_nr = LineNr(idx + 1);
+        let (text, replaced) = substitute_line(
+            line,
+            &pattern,
+            &mut |matched| replace(line_nr, matched),
+            spec.flags.global,
+        )?;
+        if !replaced.is_empty() {
+            changed.push(SubstitutedLine {
+                line: line_nr,
+                text,
+                replaced,
+            });
         }
     }
+    Ok(changed)
+}
 
+/// Apply a substitute operation to the given lines.
+pub fn apply_substitute(
+    lines: &[String],
+    spec: &SubstituteSpec,
+    use_last_pattern: Option<&str>,
+) -> VimResult<Vec<String>> {
+    let mut out = lines.to_vec();
+    for changed in substitute_lines(lines, spec, use_last_pattern)? {
+        out[changed.line.0 - 1] = changed.text;
+    }
     Ok(out)
 }
 
-fn substitute_line(line: &str, pattern: &str, replacement: &str, global: bool) -> String {
-    if global {
-        line.replace(pattern, replacement)
-    } else if let Some(pos) = line.find(pattern) {
-        let mut out = String::new();
-        out.push_str(&line[..pos]);
-        out.push_str(replacement);
-        out.push_str(&line[pos + pattern.len()..]);
-        out
-    } else {
-        line.to_string()
+fn substitute_line(
+    line: &str,
+    pattern: &str,
+    replace: &mut dyn FnMut(&str) -> VimResult<String>,
+    global: bool,
+) -> VimResult<(String, Vec<(usize, usize)>)> {
+    let needle = Needle::parse(pattern);
+    let iskeyword = KeywordChars::default();
+    let mut out = String::new();
+    let mut replaced = Vec::new();
+    let mut copied = 0;
+    while let Some(pos) = needle.find(line, copied, line.len(), true, &iskeyword) {
+        out.push_str(&line[copied..pos]);
+        let replacement = replace(&line[pos..pos + needle.text.len()])?;
+        replaced.push((out.len(), out.len() + replacement.len()));
+        out.push_str(&replacement);
+        copied = pos + needle.text.len();
+        if !global {
+            break;
+        }
     }
+    out.push_str(&line[copied..]);
+    Ok((out, replaced))
+}
+
+/// What 'inccommand' shows while typing `:s`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IncCommand {
+    /// No preview (empty)
+    #[default]
+    Off,
+    /// Show the effect in the buffer (`nosplit`)
+    NoSplit,
+    /// Also list the changed lines in a preview window (`split`)
+    Split,
+}
+
+impl IncCommand {
+    /// Parse the 'inccommand' option value
+    pub fn parse(value: &str) -> VimResult<Self> {
+        match value {
+            "" => Ok(IncCommand::Off),
+            "nosplit" => Ok(IncCommand::NoSplit),
+            "split" => Ok(IncCommand::Split),
+            _ => Err(VimError::Error(
+                474,
+                format!("Invalid argument: inccommand={}", value),
+            )),
+        }
+    }
+}
+
+// ============================================================================
+// Search Count
+// ============================================================================
+
+/// Limits for counting matches with [`search_count`], like the dictionary
+/// passed to `searchcount()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchCountOptions {
+    /// Position the current match is counted at, normally the cursor
+    pub pos: CursorPosition,
+    /// Stop counting after this many matches (0 for no limit)
+    pub maxcount: usize,
+    /// Stop counting after this long (`None` for no limit)
+    pub timeout: Option<Duration>,
+}
+
+/// Why a [`SearchCount`] is not the full count
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SearchCountIncomplete {
+    /// All matches were counted
+    #[default]
+    Complete,
+    /// Counting took longer than the timeout
+    TimedOut,
+    /// There are more than `maxcount` matches
+    MaxCountExceeded,
+}
+
+/// The result of counting matches, as returned by `searchcount()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SearchCount {
+    /// Index of the match at or before the position (0 if none)
+    pub current: usize,
+    /// Number of matches, `maxcount + 1` when there are more
+    pub total: usize,
+    /// Whether the position is at the start of a match
+    pub exact_match: bool,
+    /// Whether counting stopped early
+    pub incomplete: SearchCountIncomplete,
+    /// The `maxcount` counting was limited to
+    pub maxcount: usize,
+}
+
+impl SearchCount {
+    /// The `[N/M]` shown after a search unless 'shortmess' contains "S"
+    pub fn display(&self) -> String {
+        let max = self.maxcount;
+        match self.incomplete {
+            SearchCountIncomplete::TimedOut => "[?/??]".to_string(),
+            SearchCountIncomplete::MaxCountExceeded if self.current > max => {
+                format!("[>{}/>{}]", max, max)
+            }
+            SearchCountIncomplete::MaxCountExceeded => format!("[{}/>{}]", self.current, max),
+            SearchCountIncomplete::Complete => format!("[{}/{}]", self.current, self.total),
+        }
+    }
+}
+
+/// Count the matches of `pattern` on lines 1 to `last_line` and find the
+/// index of the one at `count.pos`, stopping at `count.maxcount` matches or
+/// after `count.timeout`
+pub fn search_count<E: SearchEngine + ?Sized>(
+    engine: &E,
+    pattern: &SearchPattern,
+    last_line: LineNr,
+    options: &SearchOptions,
+    count: &SearchCountOptions,
+) -> VimResult<SearchCount> {
+    let started = Instant::now();
+    let pos = (count.pos.line, count.pos.col);
+    let mut result = SearchCount {
+        maxcount: count.maxcount,
+        ..SearchCount::default()
+    };
+    for lnum in 1..=last_line.0 {
+        if count.timeout.is_some_and(|timeout| started.elapsed() > timeout) {
+            result.incomplete = SearchCountIncomplete::TimedOut;
+            break;
+        }
+        let start = CursorPosition::new(LineNr(lnum), 0);
+        let end = CursorPosition::new(LineNr(lnum), usize::MAX);
+        for found in engine.find_all(pattern, start, end, options)? {
+            result.total += 1;
+            if (found.start.line, found.start.col) <= pos {
+                result.current = result.total;
+                result.exact_match = (found.start.line, found.start.col) == pos;
+            }
+            if count.maxcount > 0 && result.total > count.maxcount {
+                result.incomplete = SearchCountIncomplete::MaxCountExceeded;
+                return Ok(result);
+            }
+        }
+    }
+    Ok(result)
 }
 
 // ============================================================================
@@ -652,6 +1111,49 @@ mod tests {
         assert_eq!(out, vec!["alpha omega".to_string()]);
     }
 
+    #[test]
+    fn test_parse_substitute() {
+        let spec = SubstituteSpec::parse("#a\\#b#c#g").unwrap();
+        assert_eq!(spec.pattern, "a#b");
+        assert_eq!(spec.replacement, "c");
+        assert!(spec.flags.global);
+        assert!(!spec.flags.use_last_pattern);
+
+        let spec = SubstituteSpec::parse("//x").unwrap();
+        assert!(spec.flags.use_last_pattern);
+        assert_eq!(spec.replacement, "x");
+        assert!(matches!(
+            SubstituteSpec::parse("/a/b/z"),
+            Err(VimError::TrailingCharacters)
+        ));
+        assert!(matches!(
+            SubstituteSpec::parse("xaxbx"),
+            Err(VimError::Error(146, _))
+        ));
+    }
+
+    #[test]
+    fn test_substitute_lines_reports_replaced_ranges() {
+        let lines = vec!["a-a".to_string(), "b".to_string(), "xa".to_string()];
+        let mut spec = SubstituteSpec::parse("/a/yy/g").unwrap();
+        let changed = substitute_lines(&lines, &spec, None).unwrap();
+        assert_eq!(changed.len(), 2);
+        assert_eq!(changed[0].text, "yy-yy");
+        assert_eq!(changed[0].replaced, vec![(0, 2), (3, 5)]);
+        assert_eq!(changed[1].line, LineNr(3));
+
+        spec.range = Some(LineRange::new(LineNr(1), LineNr(2)));
+        assert_eq!(substitute_lines(&lines, &spec, None).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_inccommand_parse() {
+        assert_eq!(IncCommand::parse("").unwrap(), IncCommand::Off);
+        assert_eq!(IncCommand::parse("nosplit").unwrap(), IncCommand::NoSplit);
+        assert_eq!(IncCommand::parse("split").unwrap(), IncCommand::Split);
+        assert!(IncCommand::parse("yes").is_err());
+    }
+
     #[test]
     fn test_simple_search_ignorecase_matches() {
         let engine = SimpleSearchEngine::new(vec!["Alpha beta".to_string()]);
@@ -862,4 +1364,74 @@ mod tests {
         //!
         //! 5. **Star search**: `*` adds word boundaries automatically (`\<word\>`).
     }
+
+    #[test]
+    fn test_search_count() {
+        let engine =
+            SimpleSearchEngine::new(vec!["a b a".to_string(), "b".to_string(), "a".to_string()]);
+        let options = SearchOptions::default();
+        let pattern = SearchPattern::forward("a");
+        let mut count = SearchCountOptions {
+            pos: CursorPosition::new(LineNr(1), 4),
+            maxcount: 99,
+            timeout: None,
+        };
+        let result = search_count(&engine, &pattern, LineNr(3), &options, &count).unwrap();
+        assert_eq!((result.current, result.total, result.exact_match), (2, 3, true));
+        assert_eq!(result.display(), "[2/3]");
+
+        count.pos = CursorPosition::new(LineNr(2), 0);
+        let result = search_count(&engine, &pattern, LineNr(3), &options, &count).unwrap();
+        assert_eq!((result.current, result.exact_match), (2, false));
+
+        count.maxcount = 2;
+        let result = search_count(&engine, &pattern, LineNr(3), &options, &count).unwrap();
+        assert_eq!(result.incomplete, SearchCountIncomplete::MaxCountExceeded);
+        assert_eq!(result.display(), "[2/>2]");
+    }
+
+    #[test]
+    fn test_keyword_chars() {
+        let default = KeywordChars::default();
+        assert!(default.contains('a'));
+        assert!(default.contains('_'));
+        assert!(default.contains('7'));
+        assert!(!default.contains('-'));
+        assert!(default.contains('é'));
+
+        let dash = KeywordChars::parse("@,48-57,_,-").unwrap();
+        assert!(dash.contains('-'));
+        let no_lower = KeywordChars::parse("@,^a-z").unwrap();
+        assert!(no_lower.contains('A'));
+        assert!(!no_lower.contains('b'));
+        assert!(KeywordChars::parse("z-a").is_err());
+    }
+
+    #[test]
+    fn test_search_word_boundaries() {
+        let engine = SimpleSearchEngine::new(vec!["foobar foo-x foo".to_string()]);
+        let options = SearchOptions::default();
+        let pattern = engine
+            .search_word("foo", Direction::Forward, true)
+            .unwrap();
+        assert_eq!(pattern.pattern, "\\<foo\\>");
+        let found = engine
+            .find_all(
+                &pattern,
+                CursorPosition::ORIGIN,
+                CursorPosition::new(LineNr(1), usize::MAX),
+                &options,
+            )
+            .unwrap();
+        let cols: Vec<usize> = found.iter().map(|found| found.start.col).collect();
+        assert_eq!(cols, vec![7, 13]);
+        assert_eq!(found[0].text, "foo");
+
+        let backward = SearchPattern::backward("\\<foo\\>");
+        let found = engine
+            .search(&backward, CursorPosition::new(LineNr(1), 13), &options)
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.start.col, 7);
+    }
 }
@@ -0,0 +1,403 @@
+//! Startup: the command line arguments and the user config.
+//!
+//! [`parse_args`] reads the arguments the editor is started with, as in
+//! `vxd -u NONE -c 'set ts=4' +10 file.txt`.  An editor then starts in
+//! this order:
+//!
+//! 1. The `--cmd` commands are executed
+//! 2. The user config is sourced: the file given with `-u`, none for
+//!    `-u NONE` and `-u NORC`, otherwise `$VIMINIT` is executed or the
+//!    first of [`config_paths`] that exists is sourced
+//! 3. The files are put in the argument list and the first one is edited,
+//!    or the text read from stdin for `-`
+//! 4. The `+cmd` and `-c` commands are executed, in the order given
+//! 5. The `VimEnter` autocommands are run
+//!
+//! With `-e` the editor starts in Ex mode: it reads command lines from
+//! stdin, or from the file given with `-s {scriptin}`, and executes them
+//! until `:quit` or the end of the input, without a user interface.  In
+//! silent Ex mode (`-es`, or `-` in Ex mode) only the output of `:print`,
+//! `:number` and `:set` is written and no user config is sourced unless
+//! given with `-u`.  The exit status is the one given to `:cquit`,
+//! otherwise 1 when a command failed and 0 when none did.
+//!
+//! # Key Behavioral Contracts
+//!
+//! - `+` alone goes to the last line, `+N` to line N and `+/pat` to the
+//!   first match of `pat`
+//! - At most 10 `+cmd` and `-c` commands, and 10 `--cmd` commands
+//! - Single letter flags can be combined, as in `-nR`; `-c` and `-u` take
+//!   the next argument
+//! - After `--` every argument is a file name
+//! - `-s` is silent mode after `-e`, and takes a `{scriptin}` file of typed
+//!   keys otherwise
+//! - `-S {file}` sources `file` as a `-c` command does, `Session.vim`
+//!   when no file follows
+
+use std::path::PathBuf;
+
+use crate::types::{VimError, VimResult};
+
+/// The most `+cmd` and `-c` commands, and the most `--cmd` commands
+pub const MAX_COMMANDS: usize = 10;
+
+/// The user config to use, given with `-u`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Vimrc {
+    /// `$VIMINIT` or the first of [`config_paths`] that exists
+    #[default]
+    Default,
+    /// `-u NONE`: no config and no plugins
+    None,
+    /// `-u NORC`: no config, but plugins are loaded
+    NoRc,
+    /// `-u {file}`
+    File(String),
+}
+
+/// How the files are shown: `-o` and `-O` in windows split
+/// horizontally and vertically, `-p` in tab pages
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowLayout {
+    /// `-o[N]`
+    Horizontal,
+    /// `-O[N]`
+    Vertical,
+    /// `-p[N]`
+    Tabs,
+}
+
+/// The command line arguments of the editor
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StartupArgs {
+    /// The files to edit
+    pub files: Vec<String>,
+    /// `+cmd` and `-c` commands, run after the first file is loaded
+    pub commands: Vec<String>,
+    /// `--cmd` commands, run before the user config
+    pub pre_commands: Vec<String>,
+    /// `-u`
+    pub vimrc: Vimrc,
+    /// `-C` sets 'compatible' (`Some(true)`), `-N` resets it
+    pub compatible: Option<bool>,
+    /// `-R`: the files are edited with 'readonly' set
+    pub readonly: bool,
+    /// `-n`: no swap file
+    pub no_swap: bool,
+    /// `-o`, `-O` or `-p`, with the number of windows or tab pages
+    pub layout: Option<(WindowLayout, Option<usize>)>,
+    /// `-d`: diff mode
+    pub diff: bool,
+    /// `-r` or `-L`: recover the files from their swap files
+    pub recover: bool,
+    /// `-`: the text to edit is read from stdin
+    pub stdin: bool,
+    /// `-e` or `-E`: Ex mode
+    pub ex_mode: bool,
+    /// `-s` after `-e`: silent Ex mode
+    pub silent: bool,
+    /// `-s {scriptin}`: the keys typed are read from this file
+    pub script_in: Option<String>,
+    /// `--headless`: no user interface
+    pub headless: bool,
+    /// `-h` or `--help`
+    pub help: bool,
+    /// `--version`
+    pub version: bool,
+}
+
+fn arg_error(message: &str, arg: &str) -> VimError {
+    VimError::Argument(format!("{}: \"{}\"", message, arg))
+}
+
+fn too_many_commands() -> VimError {
+    VimError::Argument(
+        "Too many \"+command\", \"-c command\" or \"--cmd command\" arguments".to_string(),
+    )
+}
+
+/// The command `+arg` runs: `+` goes to the last line, `+N` to line N and
+/// `+/pat` searches for `pat`
+fn plus_command(arg: &str) -> String {
+    match arg {
+        "" => "$".to_string(),
+        arg => arg.to_string(),
+    }
+}
+
+/// Parse the arguments `args` the editor is started with, without the
+/// program name
+pub fn parse_args<S: AsRef<str>>(args: &[S]) -> VimResult<StartupArgs> {
+    let mut parsed = StartupArgs::default();
+    let mut args = args.iter().map(AsRef::as_ref);
+    let mut only_files = false;
+    while let Some(arg) = args.next() {
+        if only_files || !arg.starts_with(['-', '+']) {
+            parsed.files.push(arg.to_string());
+            continue;
+        }
+        if arg == "-" {
+            // In Ex mode `-` is silent mode, as for `ex -`
+            if parsed.ex_mode {
+                parsed.silent = true
+            } else {
+                parsed.stdin = true
+            }
+            continue;
+        }
+        if let Some(command) = arg.strip_prefix('+') {
+            push_command(&mut parsed.commands, plus_command(command))?;
+            continue;
+        }
+        if let Some(long) = arg.strip_prefix("--") {
+            match long {
+                "" => only_files = true,
+                "cmd" => {
+                    let command = args
+                        .next()
+                        .ok_or_else(|| arg_error("Argument missing after", arg))?;
+                    push_command(&mut parsed.pre_commands, command.to_string())?;
+                }
+                "headless" => parsed.headless = true,
+                "help" => parsed.help = true,
+                "version" => parsed.version = true,
+                "noplugin" => {}
+                _ => return Err(arg_error("Unknown option argument", arg)),
+            }
+            continue;
+        }
+        let flags = &arg[1..];
+        for (idx, flag) in flags.char_indices() {
+            let rest = &flags[idx + flag.len_utf8()..];
+            match flag {
+                'N' => parsed.compatible = Some(false),
+                'C' => parsed.compatible = Some(true),
+                'R' => parsed.readonly = true,
+                'n' => parsed.no_swap = true,
+                'd' => parsed.diff = true,
+                'r' | 'L' => parsed.recover = true,
+                'h' => parsed.help = true,
+                'e' | 'E' => parsed.ex_mode = true,
+                's' if parsed.ex_mode => parsed.silent = true,
+                'o' | 'O' | 'p' => {
+                    let layout = match flag {
+                        'o' => WindowLayout::Horizontal,
+                        'O' => WindowLayout::Vertical,
+                        _ => WindowLayout::Tabs,
+                    };
+                    // The number takes the rest of the argument
+                    let count = match rest {
+                        "" => None,
+                        rest => Some(
+                            rest.parse()
+                                .map_err(|_| arg_error("Garbage after option argument", arg))?,
+                        ),
+                    };
+                    parsed.layout = Some((layout, count));
+                    break;
+                }
+                'S' => {
+                    if !rest.is_empty() {
+                        return Err(arg_error("Garbage after option argument", arg));
+                    }
+                    let file = match args.clone().next() {
+                        Some(file) if !file.starts_with('-') => {
+                            args.next();
+                            file
+                        }
+                        _ => "Session.vim",
+                    };
+                    push_command(&mut parsed.commands, format!("source {}", file))?;
+                }
+                'c' | 'u' | 's' => {
+                    if !rest.is_empty() {
+                        return Err(arg_error("Garbage after option argument", arg));
+                    }
+                    let value = args
+                        .next()
+                        .ok_or_else(|| arg_error("Argument missing after", arg))?;
+                    match flag {
+                        'c' => push_command(&mut parsed.commands, value.to_string())?,
+                        's' => parsed.script_in = Some(value.to_string()),
+                        _ => {
+                            parsed.vimrc = match value {
+                                "NONE" => Vimrc::None,
+                                "NORC" => Vimrc::NoRc,
+                                file => Vimrc::File(file.to_string()),
+                            }
+                        }
+                    }
+                }
+                _ => return Err(arg_error("Unknown option argument", arg)),
+            }
+        }
+    }
+    if parsed.stdin && !parsed.files.is_empty() {
+        return Err(arg_error("Too many edit arguments", &parsed.files[0]));
+    }
+    Ok(parsed)
+}
+
+fn push_command(commands: &mut Vec<String>, command: String) -> VimResult<()> {
+    if commands.len() >= MAX_COMMANDS {
+        return Err(too_many_commands());
+    }
+    commands.push(command);
+    Ok(())
+}
+
+/// The user config files, in the order they are looked for:
+/// `$XDG_CONFIG_HOME/vxd/init.vim` (`~/.config` when not set), then
+/// `vxd/init.vim` in each of `$XDG_CONFIG_DIRS` (`/etc/xdg` when not
+/// set).  `env` gives the value of an environment variable.
+pub fn config_paths(env: impl Fn(&str) -> Option<String>) -> Vec<PathBuf> {
+    let set = |name: &str| env(name).filter(|value| !value.is_empty());
+    let mut dirs = Vec::new();
+    match set("XDG_CONFIG_HOME") {
+        Some(home) => dirs.push(PathBuf::from(home)),
+        None => dirs.extend(set("HOME").map(|home| PathBuf::from(home).join(".config"))),
+    }
+    let system = set("XDG_CONFIG_DIRS").unwrap_or_else(|| "/etc/xdg".to_string());
+    dirs.extend(
+        system
+            .split(':')
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from),
+    );
+    dirs.into_iter()
+        .map(|dir| dir.join("vxd").join("init.vim"))
+        .collect()
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> StartupArgs {
+        parse_args(args).unwrap()
+    }
+
+    fn error(args: &[&str]) -> String {
+        parse_args(args).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn test_files_and_commands() {
+        let args = parse(&["a.txt", "+10", "-c", "set ts=4", "b.txt", "+", "+/pat"]);
+        assert_eq!(args.files, vec!["a.txt", "b.txt"]);
+        assert_eq!(args.commands, vec!["10", "set ts=4", "$", "/pat"]);
+        assert_eq!(args.vimrc, Vimrc::Default);
+
+        let args = parse(&["--cmd", "let g:x = 1", "-u", "NONE", "-N"]);
+        assert_eq!(args.pre_commands, vec!["let g:x = 1"]);
+        assert_eq!(args.vimrc, Vimrc::None);
+        assert_eq!(args.compatible, Some(false));
+        assert_eq!(parse(&["-u", "NORC"]).vimrc, Vimrc::NoRc);
+        assert_eq!(
+            parse(&["-u", "my.vim"]).vimrc,
+            Vimrc::File("my.vim".to_string())
+        );
+    }
+
+    #[test]
+    fn test_flags() {
+        let args = parse(&["-Rnd", "-r", "--headless", "-"]);
+        assert!(args.readonly && args.no_swap && args.diff && args.recover);
+        assert!(args.headless && args.stdin);
+        assert_eq!(
+            parse(&["-o"]).layout,
+            Some((WindowLayout::Horizontal, None))
+        );
+        assert_eq!(
+            parse(&["-O3"]).layout,
+            Some((WindowLayout::Vertical, Some(3)))
+        );
+        assert_eq!(parse(&["-np2"]).layout, Some((WindowLayout::Tabs, Some(2))));
+        assert!(parse(&["-h"]).help);
+        assert!(parse(&["--version"]).version);
+    }
+
+    #[test]
+    fn test_ex_mode() {
+        let args = parse(&["-es", "file.txt"]);
+        assert!(args.ex_mode && args.silent);
+        assert_eq!(args.script_in, None);
+        let args = parse(&["-e", "-"]);
+        assert!(args.silent && !args.stdin);
+        // Before -e, -s takes a file of typed keys
+        let args = parse(&["-s", "keys.txt", "-e"]);
+        assert!(args.ex_mode && !args.silent);
+        assert_eq!(args.script_in, Some("keys.txt".to_string()));
+
+        let args = parse(&["-S", "my.vim", "-S", "-R", "-S"]);
+        assert_eq!(
+            args.commands,
+            vec!["source my.vim", "source Session.vim", "source Session.vim"]
+        );
+        assert!(args.readonly);
+    }
+
+    #[test]
+    fn test_double_dash() {
+        let args = parse(&["--", "-R", "+5", "-"]);
+        assert_eq!(args.files, vec!["-R", "+5", "-"]);
+        assert!(!args.readonly && !args.stdin);
+        assert!(args.commands.is_empty());
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(error(&["-x"]), "Unknown option argument: \"-x\"");
+        assert_eq!(
+            error(&["--nosuch"]),
+            "Unknown option argument: \"--nosuch\""
+        );
+        assert_eq!(error(&["-c"]), "Argument missing after: \"-c\"");
+        assert_eq!(error(&["--cmd"]), "Argument missing after: \"--cmd\"");
+        assert_eq!(
+            error(&["-cx", "cmd"]),
+            "Garbage after option argument: \"-cx\""
+        );
+        assert_eq!(error(&["-o2x"]), "Garbage after option argument: \"-o2x\"");
+        assert_eq!(error(&["-", "file"]), "Too many edit arguments: \"file\"");
+        let many: Vec<String> = (0..11).map(|nr| format!("+{}", nr)).collect();
+        assert_eq!(
+            parse_args(&many).unwrap_err().to_string(),
+            "Too many \"+command\", \"-c command\" or \"--cmd command\" arguments"
+        );
+    }
+
+    #[test]
+    fn test_config_paths() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(var, _)| *var == name)
+                    .map(|(_, value)| value.to_string())
+            }
+        };
+        assert_eq!(
+            config_paths(env(&[("HOME", "/home/u")])),
+            vec![
+                PathBuf::from("/home/u/.config/vxd/init.vim"),
+                PathBuf::from("/etc/xdg/vxd/init.vim")
+            ]
+        );
+        assert_eq!(
+            config_paths(env(&[
+                ("HOME", "/home/u"),
+                ("XDG_CONFIG_HOME", "/cfg"),
+                ("XDG_CONFIG_DIRS", "/a:/b")
+            ])),
+            vec![
+                PathBuf::from("/cfg/vxd/init.vim"),
+                PathBuf::from("/a/vxd/init.vim"),
+                PathBuf::from("/b/vxd/init.vim")
+            ]
+        );
+    }
+}
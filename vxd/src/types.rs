@@ -235,6 +235,12 @@ pub enum VimError {
     NotEditorCommand(String),
     /// Generic error message (E followed by number)
     Error(u32, String),
+    /// An exception thrown by `:throw`, or an error turned into one
+    /// inside `:try`, with its value
+    Exception(String),
+    /// A wrong command line argument of the editor, such as an unknown
+    /// option
+    Argument(String),
 }
 
 impl fmt::Display for VimError {
@@ -261,6 +267,16 @@ impl fmt::Display for VimError {
             VimError::TrailingCharacters => write!(f, "E488: Trailing characters"),
             VimError::NotEditorCommand(s) => write!(f, "E492: Not an editor command: {}", s),
             VimError::Error(n, s) => write!(f, "E{}: {}", n, s),
+            // An error made into an exception shows as the error
+            VimError::Exception(s) => match s.strip_prefix("Vim:").or_else(|| {
+                s.strip_prefix("Vim(")
+                    .and_then(|rest| rest.split_once("):"))
+                    .map(|(_, error)| error)
+            }) {
+                Some(error) => f.write_str(error),
+                None => write!(f, "E605: Exception not caught: {}", s),
+            },
+            VimError::Argument(s) => f.write_str(s),
         }
     }
 }
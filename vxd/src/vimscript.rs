@@ -0,0 +1,2748 @@
+//! Vimscript.
+//!
+//! A script is a list of Ex commands.  The commands of the language,
+//! `:let`, `:if`, `:while`, `:for`, `:function`, `:try`, `:execute` and
+//! the like, are run by the [`Interpreter`]; any other command is given
+//! to the editor through [`ScriptHost::execute_command`].  Expressions
+//! are evaluated with [`crate::eval`], seeing the variables and user
+//! functions of the interpreter besides what the editor provides.
+//!
+//! Before a script runs, lines starting with a backslash are joined to
+//! the line before them, lines are split at `|` and the commands are
+//! parsed into blocks, so that a missing `:endif` is found before
+//! anything is executed.
+//!
+//! # Key Behavioral Contracts
+//!
+//! - A variable without a scope is global (`g:`) outside a function and
+//!   local (`l:`) inside one; `a:` holds the arguments and is read-only,
+//!   `s:` belongs to the script being sourced, `b:`, `w:` and `t:` to the
+//!   current buffer, window and tab page
+//! - A user function name starts with a capital, or with `s:` for a
+//!   function local to the script
+//! - An error in a command is shown and the next command is executed,
+//!   unless the error is inside `:try` or in a function with `abort`
+//! - Inside `:try` an error becomes an exception such as
+//!   `Vim(let):E121: Undefined variable: x` (`Vim:E492: ...` for a command
+//!   that does not exist), which `:catch /E121/` catches.  An exception not caught is shown as the error it was made
+//!   from, or as `E605: Exception not caught: value` when thrown.
+//! - `:finally` runs however the `:try` block is left
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
+use std::rc::Rc;
+use std::sync::OnceLock;
+
+use crate::commands::{parse_command_range, CommandDef, CommandFlags, CommandRange, CompleteType};
+use crate::eval::{self, is_name_char, BinaryOp, Dict, EvalContext, Expr, Value};
+use crate::search::match_text;
+use crate::types::{LineNr, VimError, VimResult};
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+/// The commands run by the interpreter, with the least characters they
+/// can be abbreviated to
+const COMMANDS: &[(&str, usize, &str)] = &[
+    ("let", 3, "Assign a variable"),
+    ("unlet", 3, "Delete a variable"),
+    ("if", 2, "Execute commands when an expression is true"),
+    ("elseif", 5, "Another condition of :if"),
+    ("else", 2, "Commands for when no condition of :if is true"),
+    ("endif", 2, "End of :if"),
+    ("while", 2, "Repeat commands while an expression is true"),
+    ("endwhile", 4, "End of :while"),
+    ("for", 3, "Repeat commands for each item of a List"),
+    ("endfor", 5, "End of :for"),
+    ("break", 4, "Leave a :while or :for loop"),
+    ("continue", 3, "Go on with the next round of a loop"),
+    ("function", 2, "Define or list user functions"),
+    ("endfunction", 4, "End of :function"),
+    ("delfunction", 4, "Delete a user function"),
+    ("return", 4, "Return from a user function"),
+    ("call", 3, "Call a function"),
+    ("try", 3, "Catch exceptions of commands"),
+    ("catch", 3, "Commands for a matching exception"),
+    ("finally", 4, "Commands run however :try is left"),
+    ("endtry", 4, "End of :try"),
+    ("throw", 2, "Throw an exception"),
+    ("execute", 3, "Execute the result of expressions"),
+    ("echo", 2, "Show the result of expressions"),
+    (
+        "echon",
+        5,
+        "Show the result of expressions on the same line",
+    ),
+    ("echomsg", 5, "Show the result of expressions as a message"),
+    ("echoerr", 5, "Show the result of expressions as an error"),
+    ("finish", 4, "Stop sourcing the script"),
+];
+
+/// Commands other than mappings that see `|` as part of their argument,
+/// with the least characters they can be abbreviated to
+const BAR_COMMANDS: &[(&str, usize)] = &[
+    ("autocmd", 2),
+    ("command", 3),
+    ("global", 1),
+    ("vglobal", 1),
+    ("normal", 4),
+    ("make", 3),
+    ("lmake", 4),
+    ("grep", 2),
+    ("lgrep", 3),
+];
+
+/// Variables of `v:` a script can change
+const WRITABLE_VIM_VARS: &[&str] = &["errmsg"];
+
+/// The definitions of the commands run by the interpreter
+pub fn commands() -> &'static [CommandDef] {
+    static TABLE: OnceLock<Vec<CommandDef>> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        COMMANDS
+            .iter()
+            .map(|&(name, min_abbrev, description)| CommandDef {
+                name: name.to_string(),
+                min_abbrev,
+                flags: CommandFlags {
+                    range: name == "call",
+                    bang: matches!(name, "function" | "delfunction" | "unlet"),
+                    args: true,
+                    ..CommandFlags::default()
+                },
+                complete: match name {
+                    "call" | "function" | "delfunction" => CompleteType::Function,
+                    _ => CompleteType::Nothing,
+                },
+                description: description.to_string(),
+            })
+            .collect()
+    })
+}
+
+/// The command of the interpreter `name` abbreviates
+pub fn lookup(name: &str) -> Option<&'static CommandDef> {
+    let table = commands();
+    table.iter().find(|def| def.name == name).or_else(|| {
+        table
+            .iter()
+            .find(|def| name.len() >= def.min_abbrev && def.name.starts_with(name))
+    })
+}
+
+/// Whether `|` is part of the argument of command `name`, as for
+/// `:normal`, `:global` and mappings
+fn keeps_bar(name: &str) -> bool {
+    let mapping = name.ends_with("map")
+        || name.contains("nore")
+        || name.ends_with("ab")
+        || name.contains("abbr")
+        || matches!(name, "nn" | "no" | "vn" | "xn" | "ino" | "cno" | "ono");
+    mapping
+        || BAR_COMMANDS
+            .iter()
+            .any(|&(full, min)| name.len() >= min && full.starts_with(name))
+}
+
+// ============================================================================
+// Host
+// ============================================================================
+
+/// What a script needs of the editor, besides what expressions see
+pub trait ScriptHost: EvalContext {
+    /// Execute Ex command `command`, one the interpreter does not run
+    fn execute_command(&mut self, command: &str) -> VimResult<()>;
+
+    /// Show `text` for `:echo`: on a new line when `newline` is set,
+    /// otherwise after what is shown already, for `:echon`
+    fn echo(&mut self, text: &str, newline: bool);
+
+    /// Show the error message `text` of a command; the script goes on
+    fn error_message(&mut self, text: &str);
+
+    /// The number of the current buffer, window or tab page for `scope`
+    /// `b`, `w` or `t`, whose variables are the ones of that scope
+    fn scope_id(&self, scope: char) -> usize;
+
+    /// The first and last line of `range`, or `None` for no range
+    fn command_range(&self, range: &CommandRange) -> VimResult<Option<(LineNr, LineNr)>>;
+
+    /// Put the cursor on `line`, for `:call` of a function without the
+    /// `range` attribute, which is called on each line of the range
+    fn set_cursor_line(&mut self, line: LineNr) -> VimResult<()>;
+
+    /// `:let &name = value`, with `scope` `l` or `g` for `&l:name` and
+    /// `&g:name`
+    fn let_option(&mut self, name: &str, scope: Option<char>, value: &Value) -> VimResult<()>;
+
+    /// `:let @r = text`
+    fn let_register(&mut self, reg: char, text: &str) -> VimResult<()>;
+
+    /// `:let $NAME = text`, or `:unlet $NAME` with `None`
+    fn let_env(&mut self, name: &str, value: Option<&str>);
+}
+
+// ============================================================================
+// Parsing
+// ============================================================================
+
+/// A command of a script
+#[derive(Debug, Clone)]
+struct Command {
+    /// The command as written, without leading colons
+    text: String,
+    /// The range before the name
+    range: CommandRange,
+    /// The name as typed
+    name: String,
+    /// The command of the interpreter the name abbreviates
+    def: Option<&'static CommandDef>,
+    /// Whether `!` follows the name of a command of the interpreter
+    bang: bool,
+    /// The arguments, without white space around them
+    args: String,
+}
+
+impl Command {
+    fn parse(text: &str) -> Command {
+        let text = text.trim_start_matches(|c: char| c == ':' || c.is_whitespace());
+        let (range, name, rest) = split_name(text);
+        let def = lookup(name);
+        let (bang, rest) = match rest.strip_prefix('!') {
+            Some(rest) if def.is_some() => (true, rest),
+            _ => (false, rest),
+        };
+        Command {
+            text: text.trim_end().to_string(),
+            range,
+            name: name.to_string(),
+            def,
+            bang,
+            args: rest.trim().to_string(),
+        }
+    }
+
+    /// The full name of a command of the interpreter, empty for others
+    fn kind(&self) -> &'static str {
+        self.def.map_or("", |def| def.name.as_str())
+    }
+
+    /// The name used for the command in an exception
+    fn full_name(&self) -> &str {
+        self.def.map_or(&self.name, |def| &def.name)
+    }
+}
+
+/// The range, the name and what follows the name of command `text`
+fn split_name(text: &str) -> (CommandRange, &str, &str) {
+    let (range, rest) = parse_command_range(text).unwrap_or((CommandRange::default(), text));
+    let rest = rest.trim_start();
+    let len = rest
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(rest.len());
+    (range, &rest[..len], &rest[len..])
+}
+
+fn white(text: &str) -> usize {
+    text.len() - text.trim_start().len()
+}
+
+/// The position of the first `|` not escaped with a backslash
+fn bar_position(text: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (idx, c) in text.char_indices() {
+        match c {
+            '|' if !escaped => return Some(idx),
+            '\\' => escaped = !escaped,
+            _ => escaped = false,
+        }
+    }
+    None
+}
+
+/// The commands of `line`, split at `|`, without a trailing comment
+fn split_bar(line: &str) -> Vec<String> {
+    let mut commands = Vec::new();
+    let mut rest = line;
+    loop {
+        let text = rest.trim_start_matches(|c: char| c == ':' || c.is_whitespace());
+        if text.is_empty() || text.starts_with('"') {
+            return commands;
+        }
+        let end = command_end(text);
+        commands.push(text[..end].trim_end().to_string());
+        match text[end..].trim_start().strip_prefix('|') {
+            Some(next) => rest = next,
+            None => return commands,
+        }
+    }
+}
+
+/// Where command `text` ends: at the `|` or comment after it, or at the
+/// end of the text
+fn command_end(text: &str) -> usize {
+    let (_, name, rest) = split_name(text);
+    let start = text.len() - rest.len();
+    let end = match lookup(name) {
+        Some(def) => {
+            let args = rest.strip_prefix('!').unwrap_or(rest);
+            rest.len() - args.len() + args_end(&def.name, args).unwrap_or(args.len())
+        }
+        None if keeps_bar(name) || (name.is_empty() && rest.starts_with('!')) => rest.len(),
+        None => bar_position(rest).unwrap_or(rest.len()),
+    };
+    start + end
+}
+
+/// Where the arguments `args` of interpreter command `name` end, `None`
+/// when they take the rest of the line
+fn args_end(name: &str, args: &str) -> Option<usize> {
+    match name {
+        "let" => {
+            let (_, len) = parse_targets(args).ok()?;
+            let (_, op_len) = assign_op(&args[len..])?;
+            let start = len + op_len;
+            expressions_end(&args[start..], true).map(|end| start + end)
+        }
+        "if" | "elseif" | "while" | "return" | "throw" | "call" => expressions_end(args, true),
+        "echo" | "echon" | "echomsg" | "echoerr" | "execute" => expressions_end(args, false),
+        "for" => {
+            let (_, len) = parse_targets(args).ok()?;
+            let rest = args[len..].trim_start().strip_prefix("in")?;
+            let start = args.len() - rest.len();
+            expressions_end(rest, true).map(|end| start + end)
+        }
+        "catch" => {
+            let start = white(args);
+            match args[start..].chars().next() {
+                Some(delim) if delim != '|' && delim != '"' && !delim.is_alphanumeric() => {
+                    let pattern = &args[start + 1..];
+                    let len = delimited_len(pattern, delim)?;
+                    Some(start + 1 + len + 1)
+                }
+                _ => Some(start),
+            }
+        }
+        "function" | "delfunction" => None,
+        _ => args.find(['|', '"']),
+    }
+}
+
+/// The length of the text before `delim`, not counting escaped ones
+fn delimited_len(text: &str, delim: char) -> Option<usize> {
+    let mut escaped = false;
+    for (idx, c) in text.char_indices() {
+        match c {
+            c if c == delim && !escaped => return Some(idx),
+            '\\' => escaped = !escaped,
+            _ => escaped = false,
+        }
+    }
+    None
+}
+
+/// Where the expressions at the start of `text` end: after the first
+/// one when `single` is set, otherwise before a `|` or the end
+fn expressions_end(text: &str, single: bool) -> Option<usize> {
+    let mut pos = 0;
+    loop {
+        pos += white(&text[pos..]);
+        let rest = &text[pos..];
+        if rest.is_empty() || rest.starts_with('|') {
+            return Some(pos);
+        }
+        let (_, len) = eval::parse_expr(rest).ok()?;
+        pos += len;
+        if single {
+            return Some(pos);
+        }
+    }
+}
+
+/// The commands of script `lines`, with continuation lines joined
+fn script_commands(lines: &[String]) -> Vec<Command> {
+    let mut joined: Vec<String> = Vec::new();
+    for line in lines {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("\"\\ ") {
+            continue;
+        }
+        match (trimmed.strip_prefix('\\'), joined.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => joined.push(line.clone()),
+        }
+    }
+    joined
+        .iter()
+        .flat_map(|line| split_bar(line))
+        .map(|text| Command::parse(&text))
+        .collect()
+}
+
+/// A command of a script, or a block of them
+#[derive(Debug, Clone)]
+enum Stmt {
+    Command(Command),
+    If {
+        branches: Vec<(Command, Vec<Stmt>)>,
+        otherwise: Option<Vec<Stmt>>,
+    },
+    While {
+        head: Command,
+        body: Vec<Stmt>,
+    },
+    For {
+        head: Command,
+        body: Vec<Stmt>,
+    },
+    Try {
+        body: Vec<Stmt>,
+        catches: Vec<(Command, Vec<Stmt>)>,
+        finally: Option<Vec<Stmt>>,
+    },
+    Function {
+        head: Command,
+        body: Rc<Vec<Stmt>>,
+        lines: Vec<String>,
+    },
+}
+
+struct BlockParser {
+    commands: Vec<Command>,
+    pos: usize,
+}
+
+impl BlockParser {
+    fn parse(commands: Vec<Command>) -> VimResult<Vec<Stmt>> {
+        let mut parser = BlockParser { commands, pos: 0 };
+        let (stmts, _) = parser.block(&[])?;
+        Ok(stmts)
+    }
+
+    /// The statements up to one of the commands `ends`, which is
+    /// returned too; `None` at the end of the script
+    fn block(&mut self, ends: &[&str]) -> VimResult<(Vec<Stmt>, Option<Command>)> {
+        let mut stmts = Vec::new();
+        while let Some(command) = self.commands.get(self.pos).cloned() {
+            self.pos += 1;
+            let kind = command.kind();
+            if ends.contains(&kind) {
+                return Ok((stmts, Some(command)));
+            }
+            stmts.push(match kind {
+                "if" => self.if_block(command)?,
+                "while" => {
+                    let body = self.loop_body("endwhile")?;
+                    Stmt::While {
+                        head: command,
+                        body,
+                    }
+                }
+                "for" => {
+                    let body = self.loop_body("endfor")?;
+                    Stmt::For {
+                        head: command,
+                        body,
+                    }
+                }
+                "try" => self.try_block()?,
+                "function" if command.args.contains('(') => self.function_block(command)?,
+                "elseif" => return Err(VimError::Error(582, ":elseif without :if".to_string())),
+                "else" => return Err(VimError::Error(581, ":else without :if".to_string())),
+                "endif" => return Err(VimError::Error(580, ":endif without :if".to_string())),
+                "endwhile" => {
+                    return Err(VimError::Error(588, ":endwhile without :while".to_string()))
+                }
+                "endfor" => return Err(VimError::Error(588, ":endfor without :for".to_string())),
+                "catch" => return Err(VimError::Error(603, ":catch without :try".to_string())),
+                "finally" => return Err(VimError::Error(606, ":finally without :try".to_string())),
+                "endtry" => return Err(VimError::Error(602, ":endtry without :try".to_string())),
+                "endfunction" => {
+                    return Err(VimError::Error(
+                        193,
+                        ":endfunction not inside a function".to_string(),
+                    ))
+                }
+                _ => Stmt::Command(command),
+            });
+        }
+        Ok((stmts, None))
+    }
+
+    fn loop_body(&mut self, end: &str) -> VimResult<Vec<Stmt>> {
+        match self.block(&[end])? {
+            (body, Some(_)) => Ok(body),
+            (_, None) => Err(VimError::Error(170, format!("Missing :{}", end))),
+        }
+    }
+
+    fn if_block(&mut self, head: Command) -> VimResult<Stmt> {
+        const ENDS: &[&str] = &["elseif", "else", "endif"];
+        let missing = || VimError::Error(171, "Missing :endif".to_string());
+        let mut branches = Vec::new();
+        let mut cond = head;
+        loop {
+            let (body, close) = self.block(ENDS)?;
+            branches.push((cond, body));
+            let close = close.ok_or_else(missing)?;
+            match close.kind() {
+                "elseif" => cond = close,
+                "else" => {
+                    let (body, close) = self.block(ENDS)?;
+                    return match close.as_ref().map(Command::kind) {
+                        Some("endif") => Ok(Stmt::If {
+                            branches,
+                            otherwise: Some(body),
+                        }),
+                        Some("else") => Err(VimError::Error(583, "Multiple :else".to_string())),
+                        Some(_) => Err(VimError::Error(584, ":elseif after :else".to_string())),
+                        None => Err(missing()),
+                    };
+                }
+                _ => {
+                    return Ok(Stmt::If {
+                        branches,
+                        otherwise: None,
+                    })
+                }
+            }
+        }
+    }
+
+    fn try_block(&mut self) -> VimResult<Stmt> {
+        const ENDS: &[&str] = &["catch", "finally", "endtry"];
+        let (body, mut close) = self.block(ENDS)?;
+        let mut catches = Vec::new();
+        let mut finally = None;
+        loop {
+            let Some(command) = close else {
+                return Err(VimError::Error(600, "Missing :endtry".to_string()));
+            };
+            match command.kind() {
+                "catch" if finally.is_some() => {
+                    return Err(VimError::Error(604, ":catch after :finally".to_string()))
+                }
+                "finally" if finally.is_some() => {
+                    return Err(VimError::Error(607, "Multiple :finally".to_string()))
+                }
+                "catch" => {
+                    let (stmts, next) = self.block(ENDS)?;
+                    catches.push((command, stmts));
+                    close = next;
+                }
+                "finally" => {
+                    let (stmts, next) = self.block(ENDS)?;
+                    finally = Some(stmts);
+                    close = next;
+                }
+                _ => {
+                    return Ok(Stmt::Try {
+                        body,
+                        catches,
+                        finally,
+                    })
+                }
+            }
+        }
+    }
+
+    fn function_block(&mut self, head: Command) -> VimResult<Stmt> {
+        let start = self.pos;
+        let (body, close) = self.block(&["endfunction"])?;
+        if close.is_none() {
+            return Err(VimError::Error(126, "Missing :endfunction".to_string()));
+        }
+        let lines = self.commands[start..self.pos - 1]
+            .iter()
+            .map(|command| command.text.clone())
+            .collect();
+        Ok(Stmt::Function {
+            head,
+            body: Rc::new(body),
+            lines,
+        })
+    }
+}
+
+// ============================================================================
+// Assignment Targets
+// ============================================================================
+
+/// Where `:let` or `:for` puts a value
+#[derive(Debug, Clone)]
+enum Target {
+    /// A variable, or an item or entry reached from it
+    Variable { name: String, path: Vec<Accessor> },
+    /// `&name`, `&l:name` or `&g:name`
+    Option { name: String, scope: Option<char> },
+    /// `@r`
+    Register(char),
+    /// `$NAME`
+    Env(String),
+}
+
+/// A step from a List or Dictionary to what is in it
+#[derive(Debug, Clone)]
+enum Accessor {
+    /// `[index]`
+    Index(Expr),
+    /// `[start : end]`
+    Slice(Option<Expr>, Option<Expr>),
+    /// `.key`
+    Key(String),
+}
+
+/// `target` or `[target, ...; rest]`
+#[derive(Debug, Clone)]
+enum Targets {
+    One(Target),
+    List {
+        items: Vec<Target>,
+        rest: Option<Target>,
+    },
+}
+
+fn invalid_argument(text: &str) -> VimError {
+    VimError::Error(475, format!("Invalid argument: {}", text))
+}
+
+fn illegal_name(name: &str) -> VimError {
+    VimError::Error(461, format!("Illegal variable name: {}", name))
+}
+
+fn undefined_variable(name: &str) -> VimError {
+    VimError::Error(121, format!("Undefined variable: {}", name))
+}
+
+fn read_only(name: &str) -> VimError {
+    VimError::Error(46, format!("Cannot change read-only variable \"{}\"", name))
+}
+
+/// The targets at the start of `text` and the bytes they take
+fn parse_targets(text: &str) -> VimResult<(Targets, usize)> {
+    let mut pos = white(text);
+    if !text[pos..].starts_with('[') {
+        let (target, len) = parse_target(&text[pos..])?;
+        return Ok((Targets::One(target), pos + len));
+    }
+    pos += 1;
+    let mut items = Vec::new();
+    loop {
+        let (target, len) = parse_target(&text[pos..])?;
+        items.push(target);
+        pos += len;
+        pos += white(&text[pos..]);
+        match text[pos..].chars().next() {
+            Some(',') => pos += 1,
+            Some(']') => return Ok((Targets::List { items, rest: None }, pos + 1)),
+            Some(';') => {
+                pos += 1;
+                let (rest, len) = parse_target(&text[pos..])?;
+                pos += len;
+                pos += white(&text[pos..]);
+                if !text[pos..].starts_with(']') {
+                    return Err(invalid_argument(text.trim()));
+                }
+                let rest = Some(rest);
+                return Ok((Targets::List { items, rest }, pos + 1));
+            }
+            _ => return Err(invalid_argument(text.trim())),
+        }
+    }
+}
+
+/// The target at the start of `text` and the bytes it takes
+fn parse_target(text: &str) -> VimResult<(Target, usize)> {
+    let start = white(text);
+    let rest = &text[start..];
+    let invalid = || illegal_name(rest.trim_end());
+    if let Some(option) = rest.strip_prefix('&') {
+        let (scope, name) = match option.get(..2) {
+            Some("l:") => (Some('l'), &option[2..]),
+            Some("g:") => (Some('g'), &option[2..]),
+            _ => (None, option),
+        };
+        let len = name
+            .find(|c: char| !c.is_ascii_alphanumeric())
+            .unwrap_or(name.len());
+        if len == 0 {
+            return Err(invalid());
+        }
+        let end = start + rest.len() - name.len() + len;
+        let name = name[..len].to_string();
+        return Ok((Target::Option { name, scope }, end));
+    }
+    if let Some(reg) = rest.strip_prefix('@') {
+        let reg = reg.chars().next().ok_or_else(invalid)?;
+        return Ok((Target::Register(reg), start + 1 + reg.len_utf8()));
+    }
+    if let Some(env) = rest.strip_prefix('$') {
+        let len = env
+            .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+            .unwrap_or(env.len());
+        if len == 0 {
+            return Err(invalid());
+        }
+        return Ok((Target::Env(env[..len].to_string()), start + 1 + len));
+    }
+    let scoped = split_scope(rest).is_some();
+    let name_start = if scoped { 2 } else { 0 };
+    let len = rest[name_start..]
+        .find(|c: char| !is_name_char(c))
+        .unwrap_or(rest.len() - name_start);
+    if len == 0 || (!scoped && rest.starts_with(|c: char| c.is_ascii_digit())) {
+        return Err(invalid());
+    }
+    let mut pos = name_start + len;
+    let name = rest[..pos].to_string();
+    let mut path = Vec::new();
+    loop {
+        let after = &rest[pos..];
+        if let Some(subscript) = after.strip_prefix('[') {
+            let (accessor, len) = parse_subscript(subscript)?;
+            path.push(accessor);
+            pos += 1 + len;
+        } else if let Some(key) = after
+            .strip_prefix('.')
+            .filter(|key| key.starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_'))
+        {
+            let len = key
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .unwrap_or(key.len());
+            path.push(Accessor::Key(key[..len].to_string()));
+            pos += 1 + len;
+        } else {
+            return Ok((Target::Variable { name, path }, start + pos));
+        }
+    }
+}
+
+/// After `[`: `index]` or `start : end]` and the bytes it takes
+fn parse_subscript(text: &str) -> VimResult<(Accessor, usize)> {
+    let missing = || VimError::Error(111, "Missing ']'".to_string());
+    let mut pos = white(text);
+    let start = if text[pos..].starts_with(':') {
+        None
+    } else {
+        let (expr, len) = eval::parse_expr(&text[pos..])?;
+        pos += len;
+        Some(expr)
+    };
+    if text[pos..].starts_with(':') {
+        pos += 1;
+        pos += white(&text[pos..]);
+        let end = if text[pos..].starts_with(']') {
+            None
+        } else {
+            let (expr, len) = eval::parse_expr(&text[pos..])?;
+            pos += len;
+            Some(expr)
+        };
+        if !text[pos..].starts_with(']') {
+            return Err(missing());
+        }
+        return Ok((Accessor::Slice(start, end), pos + 1));
+    }
+    match (start, text[pos..].starts_with(']')) {
+        (Some(index), true) => Ok((Accessor::Index(index), pos + 1)),
+        _ => Err(missing()),
+    }
+}
+
+/// The assignment operator at the start of `text`: `=`, or `+=` and
+/// the like with the operator they apply, and the bytes it takes
+fn assign_op(text: &str) -> Option<(Option<BinaryOp>, usize)> {
+    let start = white(text);
+    let rest = &text[start..];
+    let ops = [
+        ("..=", Some(BinaryOp::Concat)),
+        (".=", Some(BinaryOp::Concat)),
+        ("+=", Some(BinaryOp::Add)),
+        ("-=", Some(BinaryOp::Sub)),
+        ("*=", Some(BinaryOp::Mul)),
+        ("/=", Some(BinaryOp::Div)),
+        ("%=", Some(BinaryOp::Mod)),
+    ];
+    if let Some(&(token, op)) = ops.iter().find(|(token, _)| rest.starts_with(token)) {
+        return Some((op, start + token.len()));
+    }
+    (rest.starts_with('=') && !rest.starts_with("==")).then_some((None, start + 1))
+}
+
+/// The scope of a variable name such as `g:x`, and the name without it
+fn split_scope(name: &str) -> Option<(char, &str)> {
+    let bytes = name.as_bytes();
+    (bytes.len() >= 2 && bytes[1] == b':' && b"gbwtlsav".contains(&bytes[0]))
+        .then(|| (bytes[0] as char, &name[2..]))
+}
+
+// ============================================================================
+// Interpreter
+// ============================================================================
+
+/// A parameter of a user function, with the expression of its default
+#[derive(Debug)]
+struct Param {
+    name: String,
+    default: Option<String>,
+}
+
+/// A function defined with `:function`
+#[derive(Debug)]
+struct UserFunction {
+    /// The name it is called with; `<SNR>1_Name` for `s:Name`
+    name: String,
+    params: Vec<Param>,
+    /// Whether `...` takes more arguments
+    varargs: bool,
+    range: bool,
+    abort: bool,
+    dict: bool,
+    body: Rc<Vec<Stmt>>,
+    /// The commands of the body as written, for `:function Name`
+    lines: Vec<String>,
+    /// The script it was defined in, whose `s:` variables it sees
+    script: Option<usize>,
+}
+
+impl UserFunction {
+    /// `function Name(a, b) abort`, as `:function` lists it
+    fn header(&self) -> String {
+        let mut params: Vec<&str> = self
+            .params
+            .iter()
+            .map(|param| param.name.as_str())
+            .collect();
+        if self.varargs {
+            params.push("...");
+        }
+        let mut header = format!("function {}({})", self.name, params.join(", "));
+        for (set, attr) in [
+            (self.range, " range"),
+            (self.abort, " abort"),
+            (self.dict, " dict"),
+        ] {
+            if set {
+                header.push_str(attr);
+            }
+        }
+        header
+    }
+}
+
+#[derive(Debug)]
+struct Script {
+    name: String,
+    variables: Dict,
+}
+
+#[derive(Debug)]
+struct State {
+    globals: Dict,
+    vim: Dict,
+    /// `b:`, `w:` and `t:` variables by scope and number
+    scoped: HashMap<(char, usize), Dict>,
+    /// Sourced scripts; script `n` is at `n - 1`
+    scripts: Vec<Script>,
+    functions: BTreeMap<String, Rc<UserFunction>>,
+    /// The last number given to a function defined as `dict.name`
+    numbered: usize,
+}
+
+/// The variables and user functions of Vimscript.  Clones share them, so
+/// an editor can hand a clone of its interpreter itself as the host of a
+/// script.
+#[derive(Debug, Clone)]
+pub struct Interpreter {
+    state: Rc<RefCell<State>>,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Interpreter::new()
+    }
+}
+
+impl Interpreter {
+    /// Create an interpreter with only the `v:` variables set
+    pub fn new() -> Self {
+        let vim = [
+            ("version", Value::Number(900)),
+            ("true", Value::Number(1)),
+            ("false", Value::Number(0)),
+            ("exception", Value::string("")),
+            ("errmsg", Value::string("")),
+            ("t_number", Value::Number(0)),
+            ("t_string", Value::Number(1)),
+            ("t_func", Value::Number(2)),
+            ("t_list", Value::Number(3)),
+            ("t_dict", Value::Number(4)),
+            ("t_float", Value::Number(5)),
+        ];
+        let vim = vim
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value))
+            .collect();
+        let state = State {
+            globals: Dict::default(),
+            vim: Rc::new(RefCell::new(vim)),
+            scoped: HashMap::new(),
+            scripts: Vec::new(),
+            functions: BTreeMap::new(),
+            numbered: 0,
+        };
+        Interpreter {
+            state: Rc::new(RefCell::new(state)),
+        }
+    }
+
+    /// Source the script `name` made of `lines`.  A script sourced again
+    /// keeps its `s:` variables.  An error in a command is shown and the
+    /// script goes on; an exception not caught ends it and is returned.
+    pub fn source(&self, name: &str, lines: &[String], host: &mut dyn ScriptHost) -> VimResult<()> {
+        let script = self.script_id(name);
+        let stmts = BlockParser::parse(script_commands(lines))?;
+        Runtime::new(self.clone(), host, Some(script), false).run_top(&stmts)
+    }
+
+    /// Execute the command line `text`, which may hold commands separated
+    /// by `|`.  The first error ends it and is returned.
+    pub fn execute(&self, text: &str, host: &mut dyn ScriptHost) -> VimResult<()> {
+        let stmts = BlockParser::parse(script_commands(&[text.to_string()]))?;
+        Runtime::new(self.clone(), host, None, true).run_top(&stmts)
+    }
+
+    /// Call user function `name`, with `dict` as `self` when given.
+    /// Returns `None` when there is no such function.
+    pub fn call(
+        &self,
+        name: &str,
+        args: &[Value],
+        dict: Option<&Dict>,
+        host: &mut dyn ScriptHost,
+    ) -> Option<VimResult<Value>> {
+        let mut runtime = Runtime::new(self.clone(), host, None, false);
+        let function = runtime.user_function(name)?;
+        Some(runtime.call_user(function, args, dict, None))
+    }
+
+    /// Whether user function `name` is defined
+    pub fn has_function(&self, name: &str) -> bool {
+        let name = name.strip_prefix("g:").unwrap_or(name);
+        self.state.borrow().functions.contains_key(name)
+    }
+
+    /// The names of the user functions, in order
+    pub fn function_names(&self) -> Vec<String> {
+        self.state.borrow().functions.keys().cloned().collect()
+    }
+
+    /// The value of variable `name` outside of scripts and functions,
+    /// where a variable without a scope is global
+    pub fn variable(&self, name: &str, host: &dyn ScriptHost) -> Option<Value> {
+        let (scope, key) = split_scope(name).unwrap_or(('g', name));
+        if matches!(scope, 'l' | 'a' | 's') {
+            return None;
+        }
+        self.scope_dict(scope, host).borrow().get(key).cloned()
+    }
+
+    fn scope_dict(&self, scope: char, host: &dyn ScriptHost) -> Dict {
+        let mut state = self.state.borrow_mut();
+        match scope {
+            'g' => state.globals.clone(),
+            'v' => state.vim.clone(),
+            scope => state
+                .scoped
+                .entry((scope, host.scope_id(scope)))
+                .or_default()
+                .clone(),
+        }
+    }
+
+    fn script_id(&self, name: &str) -> usize {
+        let mut state = self.state.borrow_mut();
+        if let Some(idx) = state.scripts.iter().position(|script| script.name == name) {
+            return idx + 1;
+        }
+        state.scripts.push(Script {
+            name: name.to_string(),
+            variables: Dict::default(),
+        });
+        state.scripts.len()
+    }
+}
+
+// ============================================================================
+// Execution
+// ============================================================================
+
+/// How a statement was left
+#[derive(Debug)]
+enum Flow {
+    Next,
+    Break,
+    Continue,
+    Return(Value),
+    Finish,
+}
+
+/// A running user function
+struct Frame {
+    function: Rc<UserFunction>,
+    locals: Dict,
+    args: Dict,
+}
+
+/// A script or command line being executed
+struct Runtime<'a> {
+    interp: Interpreter,
+    host: &'a mut dyn ScriptHost,
+    frames: Vec<Frame>,
+    /// The script being sourced
+    script: Option<usize>,
+    /// Whether an error outside a function ends everything
+    abort: bool,
+    /// The number of `:try` blocks being executed
+    try_depth: usize,
+}
+
+impl<'a> Runtime<'a> {
+    fn new(
+        interp: Interpreter,
+        host: &'a mut dyn ScriptHost,
+        script: Option<usize>,
+        abort: bool,
+    ) -> Self {
+        Runtime {
+            interp,
+            host,
+            frames: Vec::new(),
+            script,
+            abort,
+            try_depth: 0,
+        }
+    }
+
+    fn run_top(&mut self, stmts: &[Stmt]) -> VimResult<()> {
+        match self.run_block(stmts)? {
+            Flow::Break => Err(VimError::Error(
+                587,
+                ":break without :while or :for".to_string(),
+            )),
+            Flow::Continue => Err(VimError::Error(
+                586,
+                ":continue without :while or :for".to_string(),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether `err` ends the block it happens in, rather than being
+    /// shown
+    fn aborts(&self, err: &VimError) -> bool {
+        matches!(err, VimError::Exception(_))
+            || self.try_depth > 0
+            || self
+                .frames
+                .last()
+                .map_or(self.abort, |frame| frame.function.abort)
+    }
+
+    fn run_block(&mut self, stmts: &[Stmt]) -> VimResult<Flow> {
+        for stmt in stmts {
+            match self.run_stmt(stmt) {
+                Ok(Flow::Next) => {}
+                Ok(flow) => return Ok(flow),
+                Err(err) if self.aborts(&err) => return Err(err),
+                Err(err) => {
+                    let message = err.to_string();
+                    self.interp
+                        .state
+                        .borrow()
+                        .vim
+                        .borrow_mut()
+                        .insert("errmsg".to_string(), Value::string(message.as_str()));
+                    self.host.error_message(&message);
+                }
+            }
+        }
+        Ok(Flow::Next)
+    }
+
+    /// Run `run` for `command`, making an error an exception inside
+    /// `:try`
+    fn guard<T>(
+        &mut self,
+        command: &Command,
+        run: impl FnOnce(&mut Self) -> VimResult<T>,
+    ) -> VimResult<T> {
+        run(self).map_err(|err| match err {
+            VimError::Exception(_) => err,
+            // There is no command to name for a command that does not exist
+            VimError::NotEditorCommand(_) if self.try_depth > 0 => {
+                VimError::Exception(format!("Vim:{}", err))
+            }
+            err if self.try_depth > 0 => {
+                VimError::Exception(format!("Vim({}):{}", command.full_name(), err))
+            }
+            err => err,
+        })
+    }
+
+    fn run_stmt(&mut self, stmt: &Stmt) -> VimResult<Flow> {
+        match stmt {
+            Stmt::Command(command) => self.guard(command, |rt| rt.run_command(command)),
+            Stmt::If {
+                branches,
+                otherwise,
+            } => {
+                for (cond, body) in branches {
+                    if self.guard(cond, |rt| rt.condition(&cond.args))? {
+                        return self.run_block(body);
+                    }
+                }
+                match otherwise {
+                    Some(body) => self.run_block(body),
+                    None => Ok(Flow::Next),
+                }
+            }
+            Stmt::While { head, body } => {
+                while self.guard(head, |rt| rt.condition(&head.args))? {
+                    match self.run_block(body)? {
+                        Flow::Break => break,
+                        Flow::Next | Flow::Continue => {}
+                        flow => return Ok(flow),
+                    }
+                }
+                Ok(Flow::Next)
+            }
+            Stmt::For { head, body } => self.run_for(head, body),
+            Stmt::Try {
+                body,
+                catches,
+                finally,
+            } => self.run_try(body, catches, finally.as_deref()),
+            Stmt::Function { head, body, lines } => {
+                self.guard(head, |rt| rt.define_function(head, body, lines))?;
+                Ok(Flow::Next)
+            }
+        }
+    }
+
+    fn condition(&mut self, text: &str) -> VimResult<bool> {
+        self.eval_text(text)?.is_true()
+    }
+
+    fn eval_text(&mut self, text: &str) -> VimResult<Value> {
+        eval::eval_expr(text, self)
+    }
+
+    fn eval_expr(&mut self, expr: &Expr) -> VimResult<Value> {
+        eval::eval(expr, self)
+    }
+
+    /// The values of the expressions in `text`, separated by white space
+    fn eval_list(&mut self, text: &str) -> VimResult<Vec<Value>> {
+        let mut values = Vec::new();
+        let mut rest = text.trim();
+        while !rest.is_empty() {
+            let (expr, len) = eval::parse_expr(rest)?;
+            values.push(self.eval_expr(&expr)?);
+            rest = &rest[len..];
+        }
+        Ok(values)
+    }
+
+    fn run_for(&mut self, head: &Command, body: &[Stmt]) -> VimResult<Flow> {
+        let (targets, list) = self.guard(head, |rt| {
+            let (targets, len) = parse_targets(&head.args)?;
+            let expr = head.args[len..]
+                .trim_start()
+                .strip_prefix("in")
+                .filter(|expr| expr.starts_with(char::is_whitespace))
+                .ok_or_else(|| VimError::Error(690, "Missing \"in\" after :for".to_string()))?;
+            let list = match rt.eval_text(expr)? {
+                Value::List(list) => list,
+                Value::String(text) => {
+                    let chars = text.chars().map(|c| Value::String(c.to_string()));
+                    Rc::new(RefCell::new(chars.collect()))
+                }
+                _ => return Err(VimError::Error(714, "List required".to_string())),
+            };
+            Ok((targets, list))
+        })?;
+        // Items added or removed while looping are seen
+        let mut idx = 0;
+        loop {
+            let Some(item) = list.borrow().get(idx).cloned() else {
+                break;
+            };
+            idx += 1;
+            self.guard(head, |rt| rt.assign(&targets, item, None))?;
+            match self.run_block(body)? {
+                Flow::Break => break,
+                Flow::Next | Flow::Continue => {}
+                flow => return Ok(flow),
+            }
+        }
+        Ok(Flow::Next)
+    }
+
+    fn run_try(
+        &mut self,
+        body: &[Stmt],
+        catches: &[(Command, Vec<Stmt>)],
+        finally: Option<&[Stmt]>,
+    ) -> VimResult<Flow> {
+        self.try_depth += 1;
+        let mut result = self.run_block(body);
+        if let Err(VimError::Exception(value)) = &result {
+            let value = value.clone();
+            let handler = catches
+                .iter()
+                .find(|(command, _)| catch_matches(&command.args, &value));
+            if let Some((_, handler)) = handler {
+                let caught = self.set_vim_var("exception", Value::String(value));
+                result = self.run_block(handler);
+                self.set_vim_var("exception", caught);
+            }
+        }
+        self.try_depth -= 1;
+        if let Some(finally) = finally {
+            match self.run_block(finally) {
+                Ok(Flow::Next) => {}
+                other => return other,
+            }
+        }
+        result
+    }
+
+    /// Set `v:name` to `value`, returning the value it had
+    fn set_vim_var(&self, name: &str, value: Value) -> Value {
+        let state = self.interp.state.borrow();
+        let old = state.vim.borrow_mut().insert(name.to_string(), value);
+        old.unwrap_or_else(|| Value::string(""))
+    }
+
+    fn run_command(&mut self, command: &Command) -> VimResult<Flow> {
+        let Some(def) = command.def else {
+            self.host.execute_command(&command.text)?;
+            return Ok(Flow::Next);
+        };
+        if command.bang && !def.flags.bang {
+            return Err(VimError::Error(477, "No ! allowed".to_string()));
+        }
+        let range = if command.range == CommandRange::default() {
+            None
+        } else if !def.flags.range {
+            return Err(VimError::Error(481, "No range allowed".to_string()));
+        } else {
+            self.host.command_range(&command.range)?
+        };
+        let args = command.args.as_str();
+        match def.name.as_str() {
+            "let" => self.ex_let(args)?,
+            "unlet" => self.ex_unlet(args, command.bang)?,
+            "break" => return Ok(Flow::Break),
+            "continue" => return Ok(Flow::Continue),
+            "function" => self.ex_function(args)?,
+            "delfunction" => self.ex_delfunction(args, command.bang)?,
+            "return" => {
+                if self.frames.is_empty() {
+                    return Err(VimError::Error(
+                        133,
+                        ":return not inside a function".to_string(),
+                    ));
+                }
+                let value = if args.is_empty() {
+                    Value::Number(0)
+                } else {
+                    self.eval_text(args)?
+                };
+                return Ok(Flow::Return(value));
+            }
+            "call" => self.ex_call(args, range)?,
+            "throw" => {
+                if args.is_empty() {
+                    return Err(VimError::ArgumentRequired);
+                }
+                let value = self.eval_text(args)?.as_string()?;
+                if value.starts_with("Vim") {
+                    return Err(VimError::Error(
+                        608,
+                        "Cannot :throw exceptions with 'Vim' prefix".to_string(),
+                    ));
+                }
+                return Err(VimError::Exception(value));
+            }
+            "execute" => {
+                let texts = self
+                    .eval_list(args)?
+                    .iter()
+                    .map(Value::as_string)
+                    .collect::<VimResult<Vec<_>>>()?;
+                // A line break separates commands too
+                let text = texts.join(" ");
+                let lines: Vec<String> = text.split('\n').map(str::to_string).collect();
+                return self.run_block(&BlockParser::parse(script_commands(&lines))?);
+            }
+            "echo" | "echon" | "echomsg" | "echoerr" => {
+                let texts: Vec<String> = self
+                    .eval_list(args)?
+                    .iter()
+                    .map(Value::to_display)
+                    .collect();
+                match def.name.as_str() {
+                    "echon" => self.host.echo(&texts.concat(), false),
+                    "echoerr" if self.try_depth > 0 => {
+                        return Err(VimError::Exception(format!(
+                            "Vim(echoerr):{}",
+                            texts.join(" ")
+                        )));
+                    }
+                    "echoerr" => self.host.error_message(&texts.join(" ")),
+                    _ => self.host.echo(&texts.join(" "), true),
+                }
+            }
+            "finish" => return Ok(Flow::Finish),
+            // The others start or end blocks and are parsed into them
+            _ => {}
+        }
+        Ok(Flow::Next)
+    }
+
+    // ------------------------------------------------------------------------
+    // Variables
+    // ------------------------------------------------------------------------
+
+    fn current_script(&self) -> Option<usize> {
+        self.frames
+            .last()
+            .map_or(self.script, |frame| frame.function.script)
+    }
+
+    /// The scope of variable `name`, and its name without the scope
+    fn scope_of<'n>(&self, name: &'n str) -> (char, &'n str) {
+        let default = if self.frames.is_empty() { 'g' } else { 'l' };
+        split_scope(name).unwrap_or((default, name))
+    }
+
+    fn scope_dict(&self, scope: char) -> Option<Dict> {
+        match scope {
+            'l' => self.frames.last().map(|frame| frame.locals.clone()),
+            'a' => self.frames.last().map(|frame| frame.args.clone()),
+            's' => {
+                let id = self.current_script()?;
+                Some(self.interp.state.borrow().scripts[id - 1].variables.clone())
+            }
+            scope => Some(self.interp.scope_dict(scope, &*self.host)),
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<Value> {
+        let (scope, key) = self.scope_of(name);
+        self.scope_dict(scope)?.borrow().get(key).cloned()
+    }
+
+    fn set_variable(&mut self, name: &str, value: Value) -> VimResult<()> {
+        let (scope, key) = self.scope_of(name);
+        match scope {
+            'a' => return Err(read_only(name)),
+            'v' if !WRITABLE_VIM_VARS.contains(&key) => return Err(read_only(name)),
+            'g' | 'l'
+                if matches!(value, Value::Funcref(_))
+                    && !key.starts_with(|c: char| c.is_ascii_uppercase()) =>
+            {
+                return Err(VimError::Error(
+                    704,
+                    format!("Funcref variable name must start with a capital: {}", name),
+                ));
+            }
+            _ => {}
+        }
+        let dict = self.scope_dict(scope).ok_or_else(|| illegal_name(name))?;
+        dict.borrow_mut().insert(key.to_string(), value);
+        Ok(())
+    }
+
+    fn ex_let(&mut self, args: &str) -> VimResult<()> {
+        if args.is_empty() {
+            let globals = self.interp.state.borrow().globals.clone();
+            let lines: Vec<String> = globals
+                .borrow()
+                .iter()
+                .map(|(name, value)| variable_line(&format!("g:{}", name), value))
+                .collect();
+            self.host.echo(&lines.join("\n"), true);
+            return Ok(());
+        }
+        let (targets, len) = parse_targets(args)?;
+        let rest = &args[len..];
+        let Some((op, op_len)) = assign_op(rest) else {
+            // `:let name ...` shows the variables
+            let mut lines = Vec::new();
+            for name in args.split_whitespace() {
+                let value = self.lookup(name).ok_or_else(|| undefined_variable(name))?;
+                lines.push(variable_line(name, &value));
+            }
+            self.host.echo(&lines.join("\n"), true);
+            return Ok(());
+        };
+        let value = self.eval_text(&rest[op_len..])?;
+        self.assign(&targets, value, op)
+    }
+
+    fn assign(&mut self, targets: &Targets, value: Value, op: Option<BinaryOp>) -> VimResult<()> {
+        let (items, rest) = match targets {
+            Targets::One(target) => return self.assign_one(target, value, op),
+            Targets::List { items, rest } => (items, rest),
+        };
+        let Value::List(list) = &value else {
+            return Err(VimError::Error(714, "List required".to_string()));
+        };
+        let values = list.borrow().clone();
+        if values.len() < items.len() {
+            return Err(VimError::Error(
+                688,
+                "More targets than List items".to_string(),
+            ));
+        }
+        if values.len() > items.len() && rest.is_none() {
+            return Err(VimError::Error(
+                687,
+                "Less targets than List items".to_string(),
+            ));
+        }
+        for (target, value) in items.iter().zip(&values) {
+            self.assign_one(target, value.clone(), op)?;
+        }
+        match rest {
+            Some(rest) => {
+                let rest_values = Value::list(values[items.len()..].to_vec());
+                self.assign_one(rest, rest_values, op)
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn assign_one(&mut self, target: &Target, value: Value, op: Option<BinaryOp>) -> VimResult<()> {
+        match target {
+            Target::Option { name, scope } => {
+                let value = match op {
+                    Some(op) => compound(op, &self.host.option(name, *scope)?, &value)?,
+                    None => value,
+                };
+                self.host.let_option(name, *scope, &value)
+            }
+            Target::Register(reg) => {
+                let value = match op {
+                    Some(op) => compound(op, &Value::String(self.host.register(*reg)?), &value)?,
+                    None => value,
+                };
+                self.host.let_register(*reg, &value.as_string()?)
+            }
+            Target::Env(name) => {
+                let value = match op {
+                    Some(op) => {
+                        let old = Value::String(self.host.env(name).unwrap_or_default());
+                        compound(op, &old, &value)?
+                    }
+                    None => value,
+                };
+                self.host.let_env(name, Some(&value.as_string()?));
+                Ok(())
+            }
+            Target::Variable { name, path } => {
+                let Some((last, path)) = path.split_last() else {
+                    let value = match op {
+                        Some(op) => {
+                            let old = self.lookup(name).ok_or_else(|| undefined_variable(name))?;
+                            compound(op, &old, &value)?
+                        }
+                        None => value,
+                    };
+                    return self.set_variable(name, value);
+                };
+                let container = self.container(name, path)?;
+                self.set_item(&container, last, value, op)
+            }
+        }
+    }
+
+    /// The List or Dictionary `path` leads to from variable `name`
+    fn container(&mut self, name: &str, path: &[Accessor]) -> VimResult<Value> {
+        let mut value = self.lookup(name).ok_or_else(|| undefined_variable(name))?;
+        for accessor in path {
+            value = match accessor {
+                Accessor::Index(index) => {
+                    let index = self.eval_expr(index)?;
+                    eval::index_value(&value, &index)?
+                }
+                Accessor::Key(key) => eval::index_value(&value, &Value::string(key.as_str()))?,
+                Accessor::Slice(..) => {
+                    return Err(VimError::Error(708, "[:] must come last".to_string()))
+                }
+            };
+        }
+        Ok(value)
+    }
+
+    /// The key `accessor` gives in a Dictionary
+    fn dict_key(&mut self, accessor: &Accessor) -> VimResult<String> {
+        match accessor {
+            Accessor::Index(index) => self.eval_expr(index)?.as_string(),
+            Accessor::Key(key) => Ok(key.clone()),
+            Accessor::Slice(..) => Err(VimError::Error(
+                719,
+                "Cannot use [:] with a Dictionary".to_string(),
+            )),
+        }
+    }
+
+    /// The index `index` gives in a List of `len` items
+    fn list_index(&mut self, index: &Expr, len: usize) -> VimResult<usize> {
+        let nr = self.eval_expr(index)?.as_number()?;
+        eval::list_index(nr, len)
+            .ok_or_else(|| VimError::Error(684, format!("List index out of range: {}", nr)))
+    }
+
+    /// The items `[start : end]` takes of a List of `len` items
+    fn slice_range(
+        &mut self,
+        start: &Option<Expr>,
+        end: &Option<Expr>,
+        len: usize,
+    ) -> VimResult<Range<usize>> {
+        let len = len as i64;
+        let mut bound = |expr: &Option<Expr>, default: i64| -> VimResult<i64> {
+            let nr = match expr {
+                Some(expr) => self.eval_expr(expr)?.as_number()?,
+                None => default,
+            };
+            Ok(if nr < 0 { len + nr } else { nr })
+        };
+        let start = bound(start, 0)?.clamp(0, len);
+        let end = bound(end, len - 1)?.min(len - 1);
+        Ok(start as usize..(end + 1).max(start) as usize)
+    }
+
+    fn set_item(
+        &mut self,
+        container: &Value,
+        accessor: &Accessor,
+        value: Value,
+        op: Option<BinaryOp>,
+    ) -> VimResult<()> {
+        match (container, accessor) {
+            (Value::List(list), Accessor::Index(index)) => {
+                let idx = self.list_index(index, list.borrow().len())?;
+                let value = match op {
+                    Some(op) => compound(op, &list.borrow()[idx].clone(), &value)?,
+                    None => value,
+                };
+                list.borrow_mut()[idx] = value;
+                Ok(())
+            }
+            (Value::List(list), Accessor::Slice(start, end)) => {
+                let Value::List(items) = &value else {
+                    return Err(VimError::Error(
+                        709,
+                        "[:] requires a List value".to_string(),
+                    ));
+                };
+                let range = self.slice_range(start, end, list.borrow().len())?;
+                let items = items.borrow().clone();
+                if items.len() > range.len() {
+                    return Err(VimError::Error(
+                        710,
+                        "List value has more items than target".to_string(),
+                    ));
+                }
+                if items.len() < range.len() {
+                    return Err(VimError::Error(
+                        711,
+                        "List value has not enough items".to_string(),
+                    ));
+                }
+                list.borrow_mut().splice(range, items);
+                Ok(())
+            }
+            (Value::Dict(dict), accessor) => {
+                let key = self.dict_key(accessor)?;
+                let value = match op {
+                    Some(op) => {
+                        let old = dict.borrow().get(&key).cloned();
+                        compound(op, &old.ok_or_else(|| eval::missing_key(&key))?, &value)?
+                    }
+                    None => value,
+                };
+                dict.borrow_mut().insert(key, value);
+                Ok(())
+            }
+            _ => Err(VimError::Error(
+                689,
+                "Can only index a List or Dictionary".to_string(),
+            )),
+        }
+    }
+
+    fn ex_unlet(&mut self, args: &str, bang: bool) -> VimResult<()> {
+        if args.is_empty() {
+            return Err(VimError::ArgumentRequired);
+        }
+        for arg in args.split_whitespace() {
+            let (target, len) = parse_target(arg)?;
+            if len < arg.len() {
+                return Err(VimError::TrailingCharacters);
+            }
+            match target {
+                Target::Variable { name, path } => self.remove_variable(&name, &path, bang)?,
+                Target::Env(name) => self.host.let_env(&name, None),
+                _ => return Err(invalid_argument(arg)),
+            }
+        }
+        Ok(())
+    }
+
+    fn remove_variable(&mut self, name: &str, path: &[Accessor], bang: bool) -> VimResult<()> {
+        let Some((last, path)) = path.split_last() else {
+            let (scope, key) = self.scope_of(name);
+            if matches!(scope, 'a' | 'v') {
+                return Err(read_only(name));
+            }
+            let removed = self
+                .scope_dict(scope)
+                .and_then(|dict| dict.borrow_mut().remove(key));
+            if removed.is_none() && !bang {
+                return Err(VimError::Error(
+                    108,
+                    format!("No such variable: \"{}\"", name),
+                ));
+            }
+            return Ok(());
+        };
+        let container = self.container(name, path)?;
+        match (&container, last) {
+            (Value::List(list), Accessor::Index(index)) => {
+                let idx = self.list_index(index, list.borrow().len())?;
+                list.borrow_mut().remove(idx);
+            }
+            (Value::List(list), Accessor::Slice(start, end)) => {
+                let range = self.slice_range(start, end, list.borrow().len())?;
+                list.borrow_mut().drain(range);
+            }
+            (Value::Dict(dict), accessor) => {
+                let key = self.dict_key(accessor)?;
+                if dict.borrow_mut().remove(&key).is_none() && !bang {
+                    return Err(eval::missing_key(&key));
+                }
+            }
+            _ => {
+                return Err(VimError::Error(
+                    689,
+                    "Can only index a List or Dictionary".to_string(),
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    // ------------------------------------------------------------------------
+    // Functions
+    // ------------------------------------------------------------------------
+
+    /// The name user function `name` is kept under: `s:Name` and
+    /// `<SID>Name` are local to the current script
+    fn function_key(&self, name: &str) -> Option<String> {
+        match name
+            .strip_prefix("s:")
+            .or_else(|| name.strip_prefix("<SID>"))
+        {
+            Some(local) => self
+                .current_script()
+                .map(|id| format!("<SNR>{}_{}", id, local)),
+            None => Some(name.strip_prefix("g:").unwrap_or(name).to_string()),
+        }
+    }
+
+    fn user_function(&self, name: &str) -> Option<Rc<UserFunction>> {
+        let key = self.function_key(name)?;
+        self.interp.state.borrow().functions.get(&key).cloned()
+    }
+
+    fn define_function(
+        &mut self,
+        head: &Command,
+        body: &Rc<Vec<Stmt>>,
+        lines: &[String],
+    ) -> VimResult<()> {
+        let args = head.args.as_str();
+        let open = args.find('(').unwrap_or(args.len());
+        let close = args
+            .rfind(')')
+            .filter(|&close| close > open)
+            .ok_or_else(|| invalid_argument(args))?;
+        let name = args[..open].trim();
+
+        let mut params: Vec<Param> = Vec::new();
+        let mut varargs = false;
+        for param in args[open + 1..close]
+            .split(',')
+            .map(str::trim)
+            .filter(|param| !param.is_empty())
+        {
+            if varargs {
+                return Err(invalid_argument(param));
+            }
+            if param == "..." {
+                varargs = true;
+                continue;
+            }
+            let (param, default) = match param.split_once('=') {
+                Some((param, default)) => (param.trim(), Some(default.trim().to_string())),
+                None => (param, None),
+            };
+            let valid = param.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                && param.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if !valid || param == "firstline" || param == "lastline" {
+                return Err(VimError::Error(125, format!("Illegal argument: {}", param)));
+            }
+            if params.iter().any(|other| other.name == param) {
+                return Err(VimError::Error(
+                    853,
+                    format!("Duplicate argument name: {}", param),
+                ));
+            }
+            if default.is_none() && params.last().is_some_and(|last| last.default.is_some()) {
+                return Err(VimError::Error(
+                    989,
+                    "Non-default argument follows default argument".to_string(),
+                ));
+            }
+            params.push(Param {
+                name: param.to_string(),
+                default,
+            });
+        }
+
+        let (mut range, mut abort, mut dict) = (false, false, false);
+        for attr in args[close + 1..].split_whitespace() {
+            match attr {
+                "range" => range = true,
+                "abort" => abort = true,
+                "dict" => dict = true,
+                "closure" => {}
+                _ => return Err(invalid_argument(attr)),
+            }
+        }
+
+        // `dict.name` defines a numbered function kept in the Dictionary
+        let mut entry = None;
+        let key = match name.rsplit_once('.') {
+            Some((base, key)) if !base.is_empty() => {
+                let Value::Dict(target) = self.eval_text(base)? else {
+                    return Err(VimError::Error(715, "Dictionary required".to_string()));
+                };
+                if target.borrow().contains_key(key) && !head.bang {
+                    return Err(VimError::Error(
+                        717,
+                        format!("Dictionary entry already exists: {}", key),
+                    ));
+                }
+                dict = true;
+                entry = Some((target, key.to_string()));
+                let mut state = self.interp.state.borrow_mut();
+                state.numbered += 1;
+                state.numbered.to_string()
+            }
+            _ => self.function_name(name)?,
+        };
+        if entry.is_none() && !head.bang && self.interp.state.borrow().functions.contains_key(&key)
+        {
+            return Err(VimError::Error(
+                122,
+                format!("Function {} already exists, add ! to replace it", key),
+            ));
+        }
+        let function = UserFunction {
+            name: key.clone(),
+            params,
+            varargs,
+            range,
+            abort,
+            dict,
+            body: body.clone(),
+            lines: lines.to_vec(),
+            script: self.current_script(),
+        };
+        self.interp
+            .state
+            .borrow_mut()
+            .functions
+            .insert(key.clone(), Rc::new(function));
+        if let Some((target, entry)) = entry {
+            target.borrow_mut().insert(entry, Value::Funcref(key));
+        }
+        Ok(())
+    }
+
+    /// The name a function defined as `name` is kept under
+    fn function_name(&self, name: &str) -> VimResult<String> {
+        let local = name
+            .strip_prefix("s:")
+            .or_else(|| name.strip_prefix("<SID>"));
+        let bare = local.unwrap_or_else(|| name.strip_prefix("g:").unwrap_or(name));
+        if bare.is_empty() || !bare.chars().all(is_name_char) {
+            return Err(VimError::Error(
+                129,
+                format!("Function name required: {}", name),
+            ));
+        }
+        if local.is_some() {
+            return self.function_key(name).ok_or_else(|| {
+                VimError::Error(81, "Using <SID> not in a script context".to_string())
+            });
+        }
+        if !bare.starts_with(|c: char| c.is_ascii_uppercase()) && !bare.contains('#') {
+            return Err(VimError::Error(
+                128,
+                format!(
+                    "Function name must start with a capital or \"s:\": {}",
+                    name
+                ),
+            ));
+        }
+        Ok(bare.to_string())
+    }
+
+    /// `:function` lists the functions, `:function Name` the lines of one
+    fn ex_function(&mut self, args: &str) -> VimResult<()> {
+        if args.is_empty() {
+            let headers: Vec<String> = self
+                .interp
+                .state
+                .borrow()
+                .functions
+                .values()
+                .filter(|function| !function.name.starts_with(|c: char| c.is_ascii_digit()))
+                .map(|function| function.header())
+                .collect();
+            self.host.echo(&headers.join("\n"), true);
+            return Ok(());
+        }
+        let function = self
+            .user_function(args)
+            .ok_or_else(|| VimError::Error(123, format!("Undefined function: {}", args)))?;
+        let mut text = format!("   {}", function.header());
+        for (idx, line) in function.lines.iter().enumerate() {
+            text.push_str(&format!("\n{:<3}{}", idx + 1, line));
+        }
+        text.push_str("\n   endfunction");
+        self.host.echo(&text, true);
+        Ok(())
+    }
+
+    fn ex_delfunction(&mut self, args: &str, bang: bool) -> VimResult<()> {
+        if args.is_empty() {
+            return Err(VimError::ArgumentRequired);
+        }
+        let removed = self
+            .function_key(args)
+            .and_then(|key| self.interp.state.borrow_mut().functions.remove(&key));
+        if removed.is_none() && !bang {
+            return Err(VimError::Error(130, format!("Unknown function: {}", args)));
+        }
+        Ok(())
+    }
+
+    fn ex_call(&mut self, args: &str, range: Option<(LineNr, LineNr)>) -> VimResult<()> {
+        let expr = eval::parse_complete(args)?;
+        let (name, arg_exprs) = match &expr {
+            Expr::Call { name, args } => (name, args),
+            Expr::DictCall { .. } => return self.eval_expr(&expr).map(|_| ()),
+            _ => return Err(VimError::Error(129, "Function name required".to_string())),
+        };
+        let Some(function) = self.user_function(name) else {
+            let values = self.eval_args(arg_exprs)?;
+            return eval::call_function(name, &values, self).map(|_| ());
+        };
+        match range {
+            // A function without `range` is called on each line
+            Some((first, last)) if !function.range => {
+                for line in first.0..=last.0 {
+                    self.host.set_cursor_line(LineNr(line))?;
+                    let values = self.eval_args(arg_exprs)?;
+                    self.call_user(function.clone(), &values, None, range)?;
+                }
+                Ok(())
+            }
+            _ => {
+                let values = self.eval_args(arg_exprs)?;
+                self.call_user(function, &values, None, range).map(|_| ())
+            }
+        }
+    }
+
+    fn eval_args(&mut self, args: &[Expr]) -> VimResult<Vec<Value>> {
+        args.iter().map(|arg| self.eval_expr(arg)).collect()
+    }
+
+    fn call_user(
+        &mut self,
+        function: Rc<UserFunction>,
+        args: &[Value],
+        dict: Option<&Dict>,
+        range: Option<(LineNr, LineNr)>,
+    ) -> VimResult<Value> {
+        let name = function.name.as_str();
+        let required = function
+            .params
+            .iter()
+            .take_while(|param| param.default.is_none())
+            .count();
+        if args.len() < required {
+            return Err(eval::not_enough_arguments(name));
+        }
+        if args.len() > function.params.len() && !function.varargs {
+            return Err(VimError::Error(
+                118,
+                format!("Too many arguments for function: {}", name),
+            ));
+        }
+        if function.dict && dict.is_none() {
+            return Err(VimError::Error(
+                725,
+                format!("Calling dict function without Dictionary: {}", name),
+            ));
+        }
+        let max_depth = self
+            .host
+            .option("maxfuncdepth", None)
+            .and_then(|value| value.as_number())
+            .unwrap_or(100);
+        if self.frames.len() as i64 >= max_depth {
+            return Err(VimError::Error(
+                132,
+                "Function call depth is higher than 'maxfuncdepth'".to_string(),
+            ));
+        }
+
+        let (first, last) = match range {
+            Some(range) => range,
+            None => {
+                let cursor = self.host.command_range(&CommandRange::current_line())?;
+                cursor.unwrap_or((LineNr(1), LineNr(1)))
+            }
+        };
+        let mut a = BTreeMap::new();
+        for (param, value) in function.params.iter().zip(args) {
+            a.insert(param.name.clone(), value.clone());
+        }
+        let extra = args.get(function.params.len()..).unwrap_or_default();
+        a.insert("0".to_string(), Value::Number(extra.len() as i64));
+        a.insert("000".to_string(), Value::list(extra.to_vec()));
+        for (idx, value) in extra.iter().enumerate() {
+            a.insert((idx + 1).to_string(), value.clone());
+        }
+        a.insert("firstline".to_string(), Value::Number(first.0 as i64));
+        a.insert("lastline".to_string(), Value::Number(last.0 as i64));
+        let mut locals = BTreeMap::new();
+        if let Some(dict) = dict {
+            locals.insert("self".to_string(), Value::Dict(dict.clone()));
+        }
+
+        self.frames.push(Frame {
+            function: function.clone(),
+            locals: Rc::new(RefCell::new(locals)),
+            args: Rc::new(RefCell::new(a)),
+        });
+        let result = self
+            .set_defaults(&function, args.len())
+            .and_then(|()| self.run_block(&function.body));
+        self.frames.pop();
+        match result? {
+            Flow::Return(value) => Ok(value),
+            Flow::Break => Err(VimError::Error(
+                587,
+                ":break without :while or :for".to_string(),
+            )),
+            Flow::Continue => Err(VimError::Error(
+                586,
+                ":continue without :while or :for".to_string(),
+            )),
+            Flow::Next | Flow::Finish => Ok(Value::Number(0)),
+        }
+    }
+
+    /// Evaluate the defaults of the parameters not given an argument
+    fn set_defaults(&mut self, function: &UserFunction, given: usize) -> VimResult<()> {
+        for param in function.params.iter().skip(given) {
+            let Some(default) = &param.default else {
+                continue;
+            };
+            let value = self.eval_text(default)?;
+            if let Some(frame) = self.frames.last() {
+                frame.args.borrow_mut().insert(param.name.clone(), value);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl EvalContext for Runtime<'_> {
+    fn option(&self, name: &str, scope: Option<char>) -> VimResult<Value> {
+        self.host.option(name, scope)
+    }
+
+    fn register(&mut self, reg: char) -> VimResult<String> {
+        self.host.register(reg)
+    }
+
+    fn env(&self, name: &str) -> Option<String> {
+        self.host.env(name)
+    }
+
+    fn variable(&self, name: &str) -> Option<Value> {
+        self.lookup(name)
+    }
+
+    fn call(&mut self, name: &str, args: &[Value]) -> Option<VimResult<Value>> {
+        match self.user_function(name) {
+            Some(function) => Some(self.call_user(function, args, None, None)),
+            None => self.host.call(name, args),
+        }
+    }
+
+    fn call_dict(&mut self, name: &str, args: &[Value], dict: &Dict) -> Option<VimResult<Value>> {
+        match self.user_function(name) {
+            Some(function) => Some(self.call_user(function, args, Some(dict), None)),
+            None => self.host.call_dict(name, args, dict),
+        }
+    }
+
+    fn has_function(&self, name: &str) -> bool {
+        self.user_function(name).is_some() || self.host.has_function(name)
+    }
+}
+
+/// `old op value` for `:let` with `+=` and the like.  `+=` on a List
+/// adds to the List itself.
+fn compound(op: BinaryOp, old: &Value, value: &Value) -> VimResult<Value> {
+    match (op, old, value) {
+        (BinaryOp::Add, Value::List(list), Value::List(items)) => {
+            let items = items.borrow().clone();
+            list.borrow_mut().extend(items);
+            Ok(old.clone())
+        }
+        _ => eval::binary(op, old, value),
+    }
+}
+
+/// Whether the pattern of `:catch` with `args` matches exception `value`
+fn catch_matches(args: &str, value: &str) -> bool {
+    let mut chars = args.chars();
+    let pattern = match chars.next() {
+        None => return true,
+        Some(delim) if !delim.is_alphanumeric() => {
+            let rest = chars.as_str();
+            &rest[..delimited_len(rest, delim).unwrap_or(rest.len())]
+        }
+        Some(_) => args,
+    };
+    match_text(value, pattern, 0, false).is_some()
+}
+
+/// A line of `:let` listing variable `name`
+fn variable_line(name: &str, value: &Value) -> String {
+    format!("{:<24}{}", name, value.to_display())
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::LineSpec;
+    use crate::eval::SimpleEvalContext;
+
+    #[derive(Default)]
+    struct TestHost {
+        ctx: SimpleEvalContext,
+        output: Vec<String>,
+        errors: Vec<String>,
+        commands: Vec<String>,
+        cursor: usize,
+    }
+
+    impl EvalContext for TestHost {
+        fn option(&self, name: &str, scope: Option<char>) -> VimResult<Value> {
+            self.ctx.option(name, scope)
+        }
+
+        fn register(&mut self, reg: char) -> VimResult<String> {
+            self.ctx.register(reg)
+        }
+
+        fn env(&self, name: &str) -> Option<String> {
+            self.ctx.env(name)
+        }
+    }
+
+    impl ScriptHost for TestHost {
+        fn execute_command(&mut self, command: &str) -> VimResult<()> {
+            if command.starts_with("bad") {
+                return Err(VimError::NotEditorCommand(command.to_string()));
+            }
+            self.commands.push(command.to_string());
+            Ok(())
+        }
+
+        fn echo(&mut self, text: &str, newline: bool) {
+            match (self.output.last_mut(), newline) {
+                (Some(last), false) => last.push_str(text),
+                _ => self.output.push(text.to_string()),
+            }
+        }
+
+        fn error_message(&mut self, text: &str) {
+            self.errors.push(text.to_string());
+        }
+
+        fn scope_id(&self, _scope: char) -> usize {
+            1
+        }
+
+        fn command_range(&self, range: &CommandRange) -> VimResult<Option<(LineNr, LineNr)>> {
+            Ok(match (&range.start, &range.end) {
+                (Some(LineSpec::Absolute(first)), Some(LineSpec::Absolute(last))) => {
+                    Some((*first, *last))
+                }
+                (Some(_), _) => Some((LineNr(self.cursor), LineNr(self.cursor))),
+                _ => None,
+            })
+        }
+
+        fn set_cursor_line(&mut self, line: LineNr) -> VimResult<()> {
+            self.cursor = line.0;
+            Ok(())
+        }
+
+        fn let_option(&mut self, name: &str, _scope: Option<char>, value: &Value) -> VimResult<()> {
+            self.ctx.options.insert(name.to_string(), value.clone());
+            Ok(())
+        }
+
+        fn let_register(&mut self, reg: char, text: &str) -> VimResult<()> {
+            self.ctx.registers.insert(reg, text.to_string());
+            Ok(())
+        }
+
+        fn let_env(&mut self, name: &str, value: Option<&str>) {
+            match value {
+                Some(value) => self.ctx.env.insert(name.to_string(), value.to_string()),
+                None => self.ctx.env.remove(name),
+            };
+        }
+    }
+
+    fn host() -> TestHost {
+        TestHost {
+            cursor: 1,
+            ..TestHost::default()
+        }
+    }
+
+    fn source(interp: &Interpreter, host: &mut TestHost, script: &str) -> VimResult<()> {
+        let lines: Vec<String> = script.lines().map(str::to_string).collect();
+        interp.source("test.vim", &lines, host)
+    }
+
+    fn global(interp: &Interpreter, host: &TestHost, name: &str) -> Option<Value> {
+        interp.variable(name, host)
+    }
+
+    #[test]
+    fn test_lookup_abbreviations() {
+        let name = |abbrev: &str| lookup(abbrev).map(|def| def.name.as_str());
+        assert_eq!(name("let"), Some("let"));
+        assert_eq!(name("endf"), Some("endfunction"));
+        assert_eq!(name("endfo"), Some("endfor"));
+        assert_eq!(name("fu"), Some("function"));
+        assert_eq!(name("exe"), Some("execute"));
+        assert_eq!(name("ec"), Some("echo"));
+        assert_eq!(name("e"), None);
+        assert_eq!(name("set"), None);
+    }
+
+    #[test]
+    fn test_split_bar() {
+        assert_eq!(
+            split_bar("let x = 'a|b' | let y = x \" comment"),
+            vec!["let x = 'a|b'", "let y = x"]
+        );
+        assert_eq!(
+            split_bar("if 1 | set ts=4 | endif"),
+            vec!["if 1", "set ts=4", "endif"]
+        );
+        assert_eq!(split_bar("normal! a|b | echo"), vec!["normal! a|b | echo"]);
+        assert_eq!(
+            split_bar("nnoremap x :echo 1<Bar>echo 2 | y"),
+            vec!["nnoremap x :echo 1<Bar>echo 2 | y"]
+        );
+        assert_eq!(
+            split_bar("try | catch /a|b/ | endtry"),
+            vec!["try", "catch /a|b/", "endtry"]
+        );
+        assert!(split_bar("  \" only a comment").is_empty());
+    }
+
+    #[test]
+    fn test_let_and_scopes() {
+        let interp = Interpreter::new();
+        let mut host = host();
+        source(
+            &interp,
+            &mut host,
+            "let x = 1\nlet g:y = x + 1\nlet s:z = 3\nlet b:w = 'buf'\nlet x += 10\nlet g:s = 'a'\nlet s .= 'b'",
+        )
+        .unwrap();
+        assert_eq!(global(&interp, &host, "x"), Some(Value::Number(11)));
+        assert_eq!(global(&interp, &host, "g:y"), Some(Value::Number(2)));
+        assert_eq!(global(&interp, &host, "z"), None);
+        assert_eq!(global(&interp, &host, "b:w"), Some(Value::string("buf")));
+        assert_eq!(global(&interp, &host, "s"), Some(Value::string("ab")));
+        assert!(host.errors.is_empty());
+
+        // `s:` variables belong to the script
+        source(&interp, &mut host, "let g:t = s:z").unwrap();
+        assert_eq!(global(&interp, &host, "t"), Some(Value::Number(3)));
+    }
+
+    #[test]
+    fn test_let_items_and_unpacking() {
+        let interp = Interpreter::new();
+        let mut host = host();
+        source(
+            &interp,
+            &mut host,
+            "let l = [1, 2, 3]\nlet l[0] = 9\nlet l[1:2] = ['a', 'b']\nlet d = {}\nlet d.k = 1\nlet d['j'] = 2\nlet d.k += 5\nlet [a, b; rest] = [1, 2, 3, 4]",
+        )
+        .unwrap();
+        assert_eq!(
+            global(&interp, &host, "l").unwrap().to_repr(),
+            "[9, 'a', 'b']"
+        );
+        assert_eq!(
+            global(&interp, &host, "d").unwrap().to_repr(),
+            "{'j': 2, 'k': 6}"
+        );
+        assert_eq!(global(&interp, &host, "b"), Some(Value::Number(2)));
+        assert_eq!(global(&interp, &host, "rest").unwrap().to_repr(), "[3, 4]");
+
+        source(&interp, &mut host, "unlet l[0] d.k\nunlet a").unwrap();
+        assert_eq!(global(&interp, &host, "l").unwrap().to_repr(), "['a', 'b']");
+        assert_eq!(global(&interp, &host, "d").unwrap().to_repr(), "{'j': 2}");
+        assert_eq!(global(&interp, &host, "a"), None);
+
+        source(&interp, &mut host, "unlet nosuch\nunlet! nosuch").unwrap();
+        assert_eq!(host.errors, vec!["E108: No such variable: \"nosuch\""]);
+    }
+
+    #[test]
+    fn test_let_options_registers_env() {
+        let interp = Interpreter::new();
+        let mut host = host();
+        host.ctx.options.insert("ts".to_string(), Value::Number(8));
+        source(
+            &interp,
+            &mut host,
+            "let &ts = 4\nlet &ts += 1\nlet @a = 'text'\nlet $FOO = 'bar'\nlet g:r = @a . $FOO . &ts",
+        )
+        .unwrap();
+        assert_eq!(host.ctx.options["ts"], Value::Number(5));
+        assert_eq!(global(&interp, &host, "r"), Some(Value::string("textbar5")));
+    }
+
+    #[test]
+    fn test_if_while_for() {
+        let interp = Interpreter::new();
+        let mut host = host();
+        source(
+            &interp,
+            &mut host,
+            r#"
+            let n = 0
+            let out = []
+            while n < 10
+              let n += 1
+              if n % 2 == 0
+                continue
+              elseif n > 7
+                break
+              else
+                call add(out, n)
+              endif
+            endwhile
+            for [k, v] in [['a', 1], ['b', 2]]
+              call add(out, k . v)
+            endfor
+            for c in 'xy'
+              call add(out, c)
+            endfor
+            if 0 | call add(out, 'no') | else | call add(out, 'yes') | endif
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            global(&interp, &host, "out").unwrap().to_repr(),
+            "[1, 3, 5, 7, 'a1', 'b2', 'x', 'y', 'yes']"
+        );
+    }
+
+    #[test]
+    fn test_block_errors() {
+        let interp = Interpreter::new();
+        let mut host = host();
+        let error = |script: &str| {
+            let mut host = TestHost::default();
+            source(&interp, &mut host, script).unwrap_err().to_string()
+        };
+        assert_eq!(error("if 1\necho 1"), "E171: Missing :endif");
+        assert_eq!(error("endif"), "E580: :endif without :if");
+        assert_eq!(error("while 1"), "E170: Missing :endwhile");
+        assert_eq!(
+            error("for x in []\nendwhile"),
+            "E588: :endwhile without :while"
+        );
+        assert_eq!(error("try\nendif"), "E580: :endif without :if");
+        assert_eq!(error("try\necho 1"), "E600: Missing :endtry");
+        assert_eq!(
+            error("function F()\nlet x = 1"),
+            "E126: Missing :endfunction"
+        );
+        assert_eq!(error("if 1\nelse\nelse\nendif"), "E583: Multiple :else");
+        // Nothing runs when the script does not parse
+        assert!(source(&interp, &mut host, "let g:ran = 1\nif 1").is_err());
+        assert_eq!(global(&interp, &host, "ran"), None);
+    }
+
+    #[test]
+    fn test_line_continuation() {
+        let interp = Interpreter::new();
+        let mut host = host();
+        source(
+            &interp,
+            &mut host,
+            "let l = [\n      \\ 1,\n      \"\\ a comment\n      \\ 2]",
+        )
+        .unwrap();
+        assert_eq!(global(&interp, &host, "l").unwrap().to_repr(), "[1, 2]");
+    }
+
+    #[test]
+    fn test_errors_are_shown_and_script_goes_on() {
+        let interp = Interpreter::new();
+        let mut host = host();
+        source(
+            &interp,
+            &mut host,
+            "let x = nosuch\nbadcommand\nset ts=4 | let g:after = 1",
+        )
+        .unwrap();
+        assert_eq!(
+            host.errors,
+            vec![
+                "E121: Undefined variable: nosuch",
+                "E492: Not an editor command: badcommand"
+            ]
+        );
+        assert_eq!(host.commands, vec!["set ts=4"]);
+        assert_eq!(global(&interp, &host, "after"), Some(Value::Number(1)));
+        assert_eq!(
+            global(&interp, &host, "v:errmsg"),
+            Some(Value::string("E492: Not an editor command: badcommand"))
+        );
+
+        // On the command line the first error ends it
+        let err = interp
+            .execute("let g:a = 1 | let g:b = nosuch | let g:c = 1", &mut host)
+            .unwrap_err();
+        assert_eq!(err.to_string(), "E121: Undefined variable: nosuch");
+        assert_eq!(global(&interp, &host, "a"), Some(Value::Number(1)));
+        assert_eq!(global(&interp, &host, "c"), None);
+    }
+
+    #[test]
+    fn test_user_functions() {
+        let interp = Interpreter::new();
+        let mut host = host();
+        source(
+            &interp,
+            &mut host,
+            r#"
+            function! Add(a, b = 10, ...)
+              let total = a:a + a:b
+              for n in a:000
+                let total += n
+              endfor
+              return total + a:0 * 1000
+            endfunction
+            function s:Twice(x)
+              return a:x * 2
+            endfunction
+            function Fact(n) abort
+              return a:n <= 1 ? 1 : a:n * Fact(a:n - 1)
+            endfunction
+            let g:r = [Add(1), Add(1, 2), Add(1, 2, 3, 4), s:Twice(4), Fact(5)]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            global(&interp, &host, "r").unwrap().to_repr(),
+            "[11, 3, 2010, 8, 120]"
+        );
+        assert!(interp.has_function("Add"));
+        assert!(!interp.has_function("Twice"));
+        assert_eq!(
+            interp.call("Add", &[Value::Number(5)], None, &mut host),
+            Some(Ok(Value::Number(15)))
+        );
+        assert!(interp.call("Nosuch", &[], None, &mut host).is_none());
+
+        let error = |interp: &Interpreter, host: &mut TestHost, text: &str| {
+            interp.execute(text, host).unwrap_err().to_string()
+        };
+        assert_eq!(
+            error(&interp, &mut host, "call Add()"),
+            "E119: Not enough arguments for function: Add"
+        );
+        assert_eq!(
+            error(&interp, &mut host, "call Fact(1, 2)"),
+            "E118: Too many arguments for function: Fact"
+        );
+        assert_eq!(
+            error(&interp, &mut host, "function Add(x)\nendfunction"),
+            "E126: Missing :endfunction"
+        );
+        source(&interp, &mut host, "function Add(x)\nendfunction").unwrap();
+        assert_eq!(
+            host.errors.last().unwrap(),
+            "E122: Function Add already exists, add ! to replace it"
+        );
+        source(&interp, &mut host, "function lower()\nendfunction").unwrap();
+        assert_eq!(
+            host.errors.last().unwrap(),
+            "E128: Function name must start with a capital or \"s:\": lower"
+        );
+        assert_eq!(
+            error(&interp, &mut host, "let g:x = a:x"),
+            "E121: Undefined variable: a:x"
+        );
+        interp.execute("delfunction Add", &mut host).unwrap();
+        assert!(!interp.has_function("Add"));
+    }
+
+    #[test]
+    fn test_locals_and_abort() {
+        let interp = Interpreter::new();
+        let mut host = host();
+        source(
+            &interp,
+            &mut host,
+            r#"
+            let x = 'global'
+            function Local()
+              let x = 'local'
+              let g:seen = [x, g:x]
+              let g:before = 1
+              call nosuch()
+              let g:after = 1
+            endfunction
+            function Abort() abort
+              call nosuch()
+              let g:aborted = 0
+            endfunction
+            call Local()
+            call Abort()
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            global(&interp, &host, "seen").unwrap().to_repr(),
+            "['local', 'global']"
+        );
+        assert_eq!(global(&interp, &host, "x"), Some(Value::string("global")));
+        // Without `abort` the function goes on after an error
+        assert_eq!(global(&interp, &host, "after"), Some(Value::Number(1)));
+        assert_eq!(global(&interp, &host, "aborted"), None);
+        assert_eq!(host.errors.len(), 2);
+    }
+
+    #[test]
+    fn test_range_function() {
+        let interp = Interpreter::new();
+        let mut host = host();
+        source(
+            &interp,
+            &mut host,
+            r#"
+            let g:lines = []
+            function Each()
+              call add(g:lines, a:firstline . '-' . a:lastline)
+            endfunction
+            function Once() range
+              call add(g:lines, a:firstline . ':' . a:lastline)
+            endfunction
+            "#,
+        )
+        .unwrap();
+        interp.execute("2,4call Each()", &mut host).unwrap();
+        interp.execute("2,4call Once()", &mut host).unwrap();
+        assert_eq!(
+            global(&interp, &host, "lines").unwrap().to_repr(),
+            "['2-4', '2-4', '2-4', '2:4']"
+        );
+        assert_eq!(host.cursor, 4);
+    }
+
+    #[test]
+    fn test_dict_functions() {
+        let interp = Interpreter::new();
+        let mut host = host();
+        source(
+            &interp,
+            &mut host,
+            r#"
+            let counter = {'count': 0}
+            function counter.Inc(n)
+              let self.count += a:n
+              return self.count
+            endfunction
+            function Len() dict
+              return len(self.items)
+            endfunction
+            let list = {'items': [1, 2, 3], 'Len': function('Len')}
+            let F = function('Len')
+            call counter.Inc(2)
+            let g:r = [counter.Inc(3), list.Len(), call('Len', [], list), type(F)]
+            "#,
+        )
+        .unwrap();
+        assert!(host.errors.is_empty(), "{:?}", host.errors);
+        assert_eq!(
+            global(&interp, &host, "r").unwrap().to_repr(),
+            "[5, 3, 3, 2]"
+        );
+        assert_eq!(
+            interp
+                .execute("call Len()", &mut host)
+                .unwrap_err()
+                .to_string(),
+            "E725: Calling dict function without Dictionary: Len"
+        );
+    }
+
+    #[test]
+    fn test_funcref_variables() {
+        let interp = Interpreter::new();
+        let mut host = host();
+        source(
+            &interp,
+            &mut host,
+            "let Up = function('toupper')\nlet g:r = [Up('a'), string(Up), call(Up, ['b'])]",
+        )
+        .unwrap();
+        assert_eq!(
+            global(&interp, &host, "r").unwrap().to_repr(),
+            "['A', 'function(''toupper'')', 'B']"
+        );
+        assert_eq!(
+            interp
+                .execute("let f = function('toupper')", &mut host)
+                .unwrap_err()
+                .to_string(),
+            "E704: Funcref variable name must start with a capital: f"
+        );
+        assert_eq!(
+            interp
+                .execute("let F = function('Nosuch')", &mut host)
+                .unwrap_err()
+                .to_string(),
+            "E700: Unknown function: Nosuch"
+        );
+    }
+
+    #[test]
+    fn test_try_catch_finally() {
+        let interp = Interpreter::new();
+        let mut host = host();
+        source(
+            &interp,
+            &mut host,
+            r#"
+            let g:log = []
+            try
+              let x = nosuch
+              call add(g:log, 'not here')
+            catch /E121/
+              call add(g:log, v:exception)
+            finally
+              call add(g:log, 'finally')
+            endtry
+            try
+              throw 'oops'
+            catch /^nomatch/
+              call add(g:log, 'wrong')
+            catch
+              call add(g:log, 'caught ' . v:exception)
+            endtry
+            try
+              try
+                throw 'inner'
+              finally
+                call add(g:log, 'inner finally')
+              endtry
+            catch /inner/
+              call add(g:log, 'outer')
+            endtry
+            call add(g:log, v:exception)
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            global(&interp, &host, "log").unwrap().to_repr(),
+            "['Vim(let):E121: Undefined variable: nosuch', 'finally', 'caught oops', 'inner finally', 'outer', '']"
+        );
+        assert!(host.errors.is_empty());
+    }
+
+    #[test]
+    fn test_try_in_functions() {
+        let interp = Interpreter::new();
+        let mut host = host();
+        source(
+            &interp,
+            &mut host,
+            r#"
+            function Fails()
+              call nosuch()
+              let g:after = 1
+            endfunction
+            function Returns()
+              try
+                return 'try'
+              finally
+                let g:finally = 1
+              endtry
+            endfunction
+            try
+              call Fails()
+            catch /E117/
+              let g:caught = v:exception
+            endtry
+            let g:r = Returns()
+            "#,
+        )
+        .unwrap();
+        // Inside :try an error aborts the function
+        assert_eq!(global(&interp, &host, "after"), None);
+        assert_eq!(
+            global(&interp, &host, "caught"),
+            Some(Value::string("Vim(call):E117: Unknown function: nosuch"))
+        );
+        assert_eq!(global(&interp, &host, "r"), Some(Value::string("try")));
+        assert_eq!(global(&interp, &host, "finally"), Some(Value::Number(1)));
+    }
+
+    #[test]
+    fn test_uncaught_exceptions() {
+        let interp = Interpreter::new();
+        let mut host = host();
+        let err = source(&interp, &mut host, "throw 'oops'\nlet g:after = 1").unwrap_err();
+        assert_eq!(err.to_string(), "E605: Exception not caught: oops");
+        assert_eq!(global(&interp, &host, "after"), None);
+
+        // An error that is not caught shows as itself
+        let err = interp
+            .execute("try | let x = nosuch | catch /E999/ | endtry", &mut host)
+            .unwrap_err();
+        assert_eq!(err.to_string(), "E121: Undefined variable: nosuch");
+        assert_eq!(
+            interp
+                .execute("throw 'Vim:x'", &mut host)
+                .unwrap_err()
+                .to_string(),
+            "E608: Cannot :throw exceptions with 'Vim' prefix"
+        );
+    }
+
+    #[test]
+    fn test_execute_echo_and_host_commands() {
+        let interp = Interpreter::new();
+        let mut host = host();
+        source(
+            &interp,
+            &mut host,
+            r#"
+            let cmd = 'let g:x = '
+            execute cmd . 42
+            execute 'if g:x == 42 |' 'let g:y = 1 |' 'endif'
+            echo 'a' 1 [2]
+            echon 'b'
+            echon 'c'
+            execute 'normal! ix'
+            "#,
+        )
+        .unwrap();
+        assert_eq!(global(&interp, &host, "x"), Some(Value::Number(42)));
+        assert_eq!(global(&interp, &host, "y"), Some(Value::Number(1)));
+        assert_eq!(host.output, vec!["a 1 [2]bc"]);
+        assert_eq!(host.commands, vec!["normal! ix"]);
+    }
+
+    #[test]
+    fn test_finish_and_script_local_functions() {
+        let interp = Interpreter::new();
+        let mut host = host();
+        source(
+            &interp,
+            &mut host,
+            "function s:Hidden()\nreturn 1\nendfunction\nlet g:r = s:Hidden()\nfinish\nlet g:after = 1",
+        )
+        .unwrap();
+        assert_eq!(global(&interp, &host, "r"), Some(Value::Number(1)));
+        assert_eq!(global(&interp, &host, "after"), None);
+        assert_eq!(interp.function_names(), vec!["<SNR>1_Hidden"]);
+        assert_eq!(
+            interp
+                .execute("call s:Hidden()", &mut host)
+                .unwrap_err()
+                .to_string(),
+            "E117: Unknown function: s:Hidden"
+        );
+    }
+
+    #[test]
+    fn test_function_listing() {
+        let interp = Interpreter::new();
+        let mut host = host();
+        source(
+            &interp,
+            &mut host,
+            "function Two(a, ...) abort\n  let x = 1\n  return x\nendfunction\nfunction\nfunction Two",
+        )
+        .unwrap();
+        assert_eq!(
+            host.output,
+            vec![
+                "function Two(a, ...) abort",
+                "   function Two(a, ...) abort\n1  let x = 1\n2  return x\n   endfunction"
+            ]
+        );
+    }
+}
@@ -0,0 +1,281 @@
+//! Abbreviations: expanding them while typing and the `:abbreviate`
+//! commands.
+//!
+//! A non-keyword character, `<Esc>` or `<CR>` typed after an abbreviation
+//! in Insert mode, or after one in Command-line mode, expands it; so does
+//! `Ctrl-]`, which inserts nothing itself.  A character typed after
+//! `Ctrl-V` is inserted without expanding.  Buffer-local abbreviations,
+//! defined with `<buffer>`, are kept for each buffer and found before the
+//! global ones; see [`vxd::abbreviations`].
+
+use vxd::abbreviations::{abbreviation_start, Abbreviation, AbbreviationKind, AbbreviationManager};
+use vxd::buffer::{Buffer, BufferManager};
+use vxd::cmdline::Cmdline;
+use vxd::cursor::Cursor;
+use vxd::modes::{CommandLineMode, Mode};
+use vxd::types::{VimError, VimResult};
+
+use crate::editor::Editor;
+use crate::key::{parse_keys, Key};
+
+/// What an `:abbreviate` command does, by its name
+struct AbbrevCommand {
+    modes: &'static [Mode],
+    action: AbbrevAction,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum AbbrevAction {
+    /// Define or list, with the noremap flag
+    Define(bool),
+    Remove,
+    Clear,
+}
+
+const BOTH: &[Mode] = &[Mode::Insert, Mode::CommandLine(CommandLineMode::Normal)];
+const INSERT: &[Mode] = &[Mode::Insert];
+const CMDLINE: &[Mode] = &[Mode::CommandLine(CommandLineMode::Normal)];
+
+fn abbrev_command(name: &str) -> AbbrevCommand {
+    let (modes, action) = match name {
+        "abbreviate" => (BOTH, AbbrevAction::Define(false)),
+        "iabbrev" => (INSERT, AbbrevAction::Define(false)),
+        "cabbrev" => (CMDLINE, AbbrevAction::Define(false)),
+        "noreabbrev" => (BOTH, AbbrevAction::Define(true)),
+        "inoreabbrev" => (INSERT, AbbrevAction::Define(true)),
+        "cnoreabbrev" => (CMDLINE, AbbrevAction::Define(true)),
+        "unabbreviate" => (BOTH, AbbrevAction::Remove),
+        "iunabbrev" => (INSERT, AbbrevAction::Remove),
+        "cunabbrev" => (CMDLINE, AbbrevAction::Remove),
+        "abclear" => (BOTH, AbbrevAction::Clear),
+        "iabclear" => (INSERT, AbbrevAction::Clear),
+        _ => (CMDLINE, AbbrevAction::Clear),
+    };
+    AbbrevCommand { modes, action }
+}
+
+/// Whether `name` is one of the commands for abbreviations
+pub(crate) fn is_abbrev_command(name: &str) -> bool {
+    ABBREV_COMMANDS.iter().any(|&(full, _, _)| full == name)
+}
+
+/// The commands for abbreviations: the name, the shortest abbreviation
+/// and a description
+pub(crate) const ABBREV_COMMANDS: &[(&str, usize, &str)] = &[
+    ("abbreviate", 2, "Define or list abbreviations"),
+    ("iabbrev", 2, "Define or list Insert mode abbreviations"),
+    (
+        "cabbrev",
+        2,
+        "Define or list Command-line mode abbreviations",
+    ),
+    ("noreabbrev", 5, "Define a non-remappable abbreviation"),
+    (
+        "inoreabbrev",
+        6,
+        "Define a non-remappable Insert mode abbreviation",
+    ),
+    (
+        "cnoreabbrev",
+        6,
+        "Define a non-remappable Command-line mode abbreviation",
+    ),
+    ("unabbreviate", 3, "Remove an abbreviation"),
+    ("iunabbrev", 4, "Remove an Insert mode abbreviation"),
+    ("cunabbrev", 4, "Remove a Command-line mode abbreviation"),
+    ("abclear", 3, "Remove all abbreviations"),
+    ("iabclear", 4, "Remove all Insert mode abbreviations"),
+    ("cabclear", 4, "Remove all Command-line mode abbreviations"),
+];
+
+impl Editor {
+    /// The abbreviation `word` in `mode`: a buffer-local one first
+    fn find_abbreviation(&self, mode: Mode, word: &str) -> Option<Abbreviation> {
+        let handle = self.buffers.current().handle();
+        self.buffer_abbreviations
+            .get(&handle)
+            .and_then(|local| local.check(mode, word))
+            .or_else(|| self.abbreviations.check(mode, word))
+            .cloned()
+    }
+
+    /// The word before `col` in `text`, not before `mincol`, and the
+    /// abbreviation it is, with its start
+    fn abbreviation_before(
+        &self,
+        text: &str,
+        col: usize,
+        mincol: usize,
+    ) -> Option<(usize, Abbreviation)> {
+        let iskeyword = self.search_options().iskeyword;
+        let start = abbreviation_start(text, col, mincol, |c| iskeyword.contains(c))?;
+        let abbrev = self.find_abbreviation(self.mode(), &text[start..col])?;
+        Some((start, abbrev))
+    }
+
+    /// The keys an abbreviation expands to, evaluating the expression of
+    /// an `<expr>` abbreviation
+    fn abbreviation_keys(&mut self, abbrev: &Abbreviation) -> Vec<Key> {
+        if abbrev.expr {
+            self.expr_mapping_keys(&abbrev.rhs)
+        } else {
+            parse_keys(&abbrev.rhs)
+        }
+    }
+
+    /// Expand the abbreviation before the cursor in Insert mode, one
+    /// typed since inserting started.  Returns whether there was one.
+    pub(crate) fn expand_insert_abbreviation(&mut self) -> VimResult<bool> {
+        let line = self.current_line();
+        let cursor = self.cursor.position();
+        let mincol = match self.insert_start {
+            Some(start) if start.line == cursor.line => start.col,
+            _ => 0,
+        };
+        let Some((start, abbrev)) = self.abbreviation_before(&line, cursor.col, mincol) else {
+            return Ok(false);
+        };
+        let word = &line[start..cursor.col];
+        let keys = self.abbreviation_keys(&abbrev);
+        let ctx = self.cursor_context();
+        self.cursor.set_col(start, &ctx)?;
+        for _ in 0..word.chars().count() {
+            self.delete_char()?;
+        }
+        for &key in &keys {
+            match key {
+                Key::Char(c) => self.insert_char_raw(c)?,
+                Key::Tab => self.insert_char_raw('\t')?,
+                Key::Enter => self.insert_newline()?,
+                _ => {}
+            }
+        }
+        self.redo_abbreviation(word, &keys);
+        Ok(true)
+    }
+
+    /// Expand the abbreviation before the cursor in Command-line mode.
+    /// Returns whether there was one.
+    pub(crate) fn expand_cmdline_abbreviation(&mut self) -> VimResult<bool> {
+        let text = self.cmdline.getcmdline().to_string();
+        let col = self.cmdline.getcmdpos() - 1;
+        let Some((start, abbrev)) = self.abbreviation_before(&text, col, 0) else {
+            return Ok(false);
+        };
+        let keys = self.abbreviation_keys(&abbrev);
+        let mut expansion = String::new();
+        for key in keys {
+            match key {
+                Key::Char(c) => expansion.push(c),
+                Key::Tab => expansion.push('\t'),
+                _ => {}
+            }
+        }
+        let before = format!("{}{}", &text[..start], expansion);
+        self.cmdline
+            .setcmdline(&format!("{}{}", before, &text[col..]))?;
+        self.cmdline.setcmdpos(before.len() + 1)?;
+        Ok(true)
+    }
+
+    /// `:abbreviate` and the other commands for abbreviations: define,
+    /// list, remove or clear them.  Returns the listing.
+    pub(crate) fn ex_abbreviate(&mut self, name: &str, args: &str) -> VimResult<Option<String>> {
+        let command = abbrev_command(name);
+        let mut rest = args.trim_start();
+        let mut buffer = false;
+        let mut expr = false;
+        loop {
+            if let Some(after) = rest.strip_prefix("<buffer>") {
+                buffer = true;
+                rest = after.trim_start();
+            } else if let Some(after) = rest.strip_prefix("<expr>") {
+                expr = true;
+                rest = after.trim_start();
+            } else {
+                break;
+            }
+        }
+        let (lhs, rhs) = match rest.find(char::is_whitespace) {
+            Some(idx) => (&rest[..idx], rest[idx..].trim_start()),
+            None => (rest, ""),
+        };
+
+        let handle = self.buffers.current().handle();
+        match command.action {
+            AbbrevAction::Clear => {
+                let manager = if buffer {
+                    self.buffer_abbreviations.entry(handle).or_default()
+                } else {
+                    &mut self.abbreviations
+                };
+                for &mode in command.modes {
+                    manager.clear(mode);
+                }
+                Ok(None)
+            }
+            AbbrevAction::Remove => {
+                if lhs.is_empty() {
+                    return Err(VimError::Error(474, "Invalid argument".to_string()));
+                }
+                let manager = if buffer {
+                    self.buffer_abbreviations.entry(handle).or_default()
+                } else {
+                    &mut self.abbreviations
+                };
+                // The abbreviation may have been expanded on the command
+                // line, so that `lhs` is its right-hand side
+                let mut removed = false;
+                for &mode in command.modes {
+                    let found = match manager.get(mode, lhs) {
+                        Some(_) => Some(lhs.to_string()),
+                        None => manager
+                            .list(mode)
+                            .into_iter()
+                            .find(|abbrev| abbrev.rhs == lhs)
+                            .map(|abbrev| abbrev.lhs.clone()),
+                    };
+                    if let Some(found) = found {
+                        removed |= manager.remove(mode, &found).is_ok();
+                    }
+                }
+                if removed {
+                    Ok(None)
+                } else {
+                    Err(VimError::Error(24, "No such abbreviation".to_string()))
+                }
+            }
+            AbbrevAction::Define(_) if rhs.is_empty() => {
+                let mut lines = Vec::new();
+                if let Some(local) = self.buffer_abbreviations.get(&handle) {
+                    lines.extend(local.listing(command.modes, lhs));
+                }
+                if !buffer {
+                    lines.extend(self.abbreviations.listing(command.modes, lhs));
+                }
+                if lines.is_empty() {
+                    Ok(Some("No abbreviation found".to_string()))
+                } else {
+                    Ok(Some(lines.join("\n")))
+                }
+            }
+            AbbrevAction::Define(noremap) => {
+                let iskeyword = self.search_options().iskeyword;
+                AbbreviationKind::of(lhs, |c| iskeyword.contains(c))?;
+                let manager = if buffer {
+                    self.buffer_abbreviations.entry(handle).or_default()
+                } else {
+                    &mut self.abbreviations
+                };
+                for &mode in command.modes {
+                    if expr {
+                        manager.add_expr(mode, lhs, rhs, noremap, buffer)?
+                    } else {
+                        manager.add(mode, lhs, rhs, noremap, buffer)?
+                    }
+                }
+                Ok(None)
+            }
+        }
+    }
+}
@@ -0,0 +1,536 @@
+//! Autocommand implementation.
+//!
+//! This module provides a concrete implementation of
+//! `vxd::autocmd::AutocmdManager` and the `:autocmd`, `:augroup` and
+//! `:doautocmd` commands.  Triggered commands are queued by the manager and
+//! run by the editor, which owns the command executor.
+
+use vxd::autocmd::{
+    AutocmdEvent, AutocmdEventData, AutocmdGroup, AutocmdManager, AutocmdPattern, Autocommand,
+};
+use vxd::buffer::{Buffer, BufferManager};
+use vxd::commands::CommandExecutor;
+use vxd::types::{VimError, VimResult};
+
+use crate::editor::Editor;
+
+/// A command queued by [`AutocmdManager::exec`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct QueuedCommand {
+    command: String,
+    nested: bool,
+}
+
+/// Concrete autocommand store for the editor
+#[derive(Debug)]
+pub struct TuiAutocmdManager {
+    groups: Vec<AutocmdGroup>,
+    autocmds: Vec<Autocommand>,
+    next_id: usize,
+    enabled: bool,
+    /// Group set by `:augroup {name}`, used for new autocommands
+    current_group: Option<AutocmdGroup>,
+    queued: Vec<QueuedCommand>,
+}
+
+impl TuiAutocmdManager {
+    /// Create an empty autocommand store
+    pub fn new() -> Self {
+        TuiAutocmdManager {
+            groups: Vec::new(),
+            autocmds: Vec::new(),
+            next_id: 1,
+            enabled: true,
+            current_group: None,
+            queued: Vec::new(),
+        }
+    }
+
+    /// The group set with `:augroup`, if any
+    pub fn current_group(&self) -> Option<&AutocmdGroup> {
+        self.current_group.as_ref()
+    }
+
+    /// Set the group used for new autocommands (`None` for `:augroup END`)
+    pub fn set_current_group(&mut self, name: Option<&str>) {
+        self.current_group = name.map(|name| self.augroup(name));
+    }
+
+    /// Find an existing group by name
+    pub fn find_group(&self, name: &str) -> Option<&AutocmdGroup> {
+        self.groups.iter().find(|group| group.name == name)
+    }
+
+    /// Names of all groups, in the order they were defined
+    pub fn group_names(&self) -> Vec<&str> {
+        self.groups.iter().map(|group| group.name.as_str()).collect()
+    }
+
+    fn take_queued(&mut self) -> Vec<QueuedCommand> {
+        std::mem::take(&mut self.queued)
+    }
+}
+
+impl Default for TuiAutocmdManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AutocmdManager for TuiAutocmdManager {
+    fn augroup(&mut self, name: &str) -> AutocmdGroup {
+        if let Some(group) = self.find_group(name) {
+            return group.clone();
+        }
+        let group = AutocmdGroup {
+            name: name.to_string(),
+            id: self.groups.len() + 1,
+        };
+        self.groups.push(group.clone());
+        group
+    }
+
+    fn augroup_delete(&mut self, name: &str) -> VimResult<()> {
+        let Some(idx) = self.groups.iter().position(|group| group.name == name) else {
+            return Err(VimError::Error(367, format!("No such group: \"{}\"", name)));
+        };
+        if self
+            .current_group
+            .as_ref()
+            .is_some_and(|group| group.name == name)
+        {
+            return Err(VimError::Error(
+                936,
+                "Cannot delete the current group".to_string(),
+            ));
+        }
+        let group = self.groups.remove(idx);
+        self.autocmds
+            .retain(|autocmd| autocmd.group.as_ref() != Some(&group));
+        Ok(())
+    }
+
+    fn create(&mut self, mut autocmd: Autocommand) -> VimResult<usize> {
+        autocmd.id = self.next_id;
+        self.next_id += 1;
+        let id = autocmd.id;
+        self.autocmds.push(autocmd);
+        Ok(id)
+    }
+
+    fn delete(
+        &mut self,
+        group: Option<&AutocmdGroup>,
+        event: Option<AutocmdEvent>,
+        pattern: Option<&str>,
+    ) -> VimResult<()> {
+        self.autocmds.retain(|autocmd| {
+            let matches = autocmd.group.as_ref() == group
+                && event.is_none_or(|event| autocmd.event == event)
+                && pattern.is_none_or(|pattern| pattern_text(&autocmd.pattern) == pattern);
+            !matches
+        });
+        Ok(())
+    }
+
+    fn clear_group(&mut self, group: &AutocmdGroup) -> VimResult<()> {
+        self.autocmds
+            .retain(|autocmd| autocmd.group.as_ref() != Some(group));
+        Ok(())
+    }
+
+    fn exec(&mut self, event: AutocmdEvent, data: &AutocmdEventData) -> VimResult<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+        let name = data.match_.as_deref().or(data.file.as_deref());
+        let mut fired_once = Vec::new();
+        for autocmd in &self.autocmds {
+            if autocmd.event != event {
+                continue;
+            }
+            let matches = match &autocmd.pattern {
+                AutocmdPattern::AllBuffers => true,
+                AutocmdPattern::Buffer(handle) => data.buf == Some(*handle),
+                AutocmdPattern::FilePattern(pattern) => match name {
+                    Some(name) => pattern_matches(pattern, name),
+                    None => pattern == "*",
+                },
+            };
+            if matches {
+                self.queued.push(QueuedCommand {
+                    command: autocmd.command.clone(),
+                    nested: autocmd.nested,
+                });
+                if autocmd.once {
+                    fired_once.push(autocmd.id);
+                }
+            }
+        }
+        self.autocmds
+            .retain(|autocmd| !fired_once.contains(&autocmd.id));
+        Ok(())
+    }
+
+    fn exists(&self, event: AutocmdEvent, pattern: Option<&str>) -> bool {
+        self.autocmds.iter().any(|autocmd| {
+            autocmd.event == event
+                && pattern.is_none_or(|pattern| pattern_text(&autocmd.pattern) == pattern)
+        })
+    }
+
+    fn list(&self, group: Option<&AutocmdGroup>, event: Option<AutocmdEvent>) -> Vec<&Autocommand> {
+        self.autocmds
+            .iter()
+            .filter(|autocmd| group.is_none_or(|group| autocmd.group.as_ref() == Some(group)))
+            .filter(|autocmd| event.is_none_or(|event| autocmd.event == event))
+            .collect()
+    }
+
+    fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+}
+
+/// The pattern as typed in `:autocmd`
+fn pattern_text(pattern: &AutocmdPattern) -> String {
+    match pattern {
+        AutocmdPattern::FilePattern(pattern) => pattern.clone(),
+        AutocmdPattern::Buffer(handle) => format!("<buffer={}>", handle.0),
+        AutocmdPattern::AllBuffers => "*".to_string(),
+    }
+}
+
+/// Whether an autocommand file pattern matches `name`.  A pattern without
+/// a `/` only has to match the tail of the file name.
+pub fn pattern_matches(pattern: &str, name: &str) -> bool {
+    let name = if pattern.contains('/') {
+        name
+    } else {
+        name.rsplit('/').next().unwrap_or(name)
+    };
+    glob_match(
+        &pattern.chars().collect::<Vec<_>>(),
+        &name.chars().collect::<Vec<_>>(),
+    )
+}
+
+/// Match `*`, `?`, `[abc]` and `{a,b}` globs
+fn glob_match(pattern: &[char], name: &[char]) -> bool {
+    match pattern.first() {
+        None => name.is_empty(),
+        Some('*') => (0..=name.len()).any(|skip| glob_match(&pattern[1..], &name[skip..])),
+        Some('?') => !name.is_empty() && glob_match(&pattern[1..], &name[1..]),
+        Some('[') => {
+            let Some(close) = pattern.iter().position(|&c| c == ']') else {
+                return name.first() == Some(&'[') && glob_match(&pattern[1..], &name[1..]);
+            };
+            let Some(&c) = name.first() else {
+                return false;
+            };
+            let class = &pattern[1..close];
+            let (negate, class) = match class.first() {
+                Some('!' | '^') => (true, &class[1..]),
+                _ => (false, class),
+            };
+            let mut found = false;
+            let mut i = 0;
+            while i < class.len() {
+                if i + 2 < class.len() && class[i + 1] == '-' {
+                    found |= class[i] <= c && c <= class[i + 2];
+                    i += 3;
+                } else {
+                    found |= class[i] == c;
+                    i += 1;
+                }
+            }
+            found != negate && glob_match(&pattern[close + 1..], &name[1..])
+        }
+        Some('{') => {
+            let Some(close) = pattern.iter().position(|&c| c == '}') else {
+                return name.first() == Some(&'{') && glob_match(&pattern[1..], &name[1..]);
+            };
+            let rest = &pattern[close + 1..];
+            pattern[1..close].split(|&c| c == ',').any(|alt| {
+                let mut candidate = alt.to_vec();
+                candidate.extend_from_slice(rest);
+                glob_match(&candidate, name)
+            })
+        }
+        Some('\\') if pattern.len() > 1 => {
+            name.first() == Some(&pattern[1]) && glob_match(&pattern[2..], &name[1..])
+        }
+        Some(&c) => name.first() == Some(&c) && glob_match(&pattern[1..], &name[1..]),
+    }
+}
+
+impl Editor {
+    /// Fire `event` and run the commands of the matching autocommands.
+    ///
+    /// Autocommands do not trigger further autocommands unless they were
+    /// defined with `++nested`.
+    pub fn apply_autocmds(
+        &mut self,
+        event: AutocmdEvent,
+        data: &AutocmdEventData,
+    ) -> VimResult<()> {
+        self.autocmds.exec(event, data)?;
+        let mut result = Ok(());
+        for queued in self.autocmds.take_queued() {
+            let enabled = self.autocmds.is_enabled();
+            self.autocmds.set_enabled(enabled && queued.nested);
+            let outcome = self.run(&queued.command);
+            self.autocmds.set_enabled(enabled);
+            if let Some(err) = outcome.error {
+                if result.is_ok() {
+                    result = Err(err);
+                }
+            } else if outcome.message.is_some() {
+                self.message = outcome.message;
+            }
+        }
+        result
+    }
+
+    /// Event data for the current buffer
+    pub fn autocmd_data(&self) -> AutocmdEventData {
+        let buffer = self.buffers.current();
+        let name = buffer.name();
+        AutocmdEventData {
+            buf: Some(buffer.handle()),
+            file: (!name.is_empty()).then(|| name.to_string()),
+            match_: None,
+            data: None,
+        }
+    }
+
+    /// `:autocmd[!] [group] [{event}] [{pat}] [++once] [++nested] [{cmd}]`
+    pub(crate) fn ex_autocmd(&mut self, args: &str, bang: bool) -> VimResult<Option<String>> {
+        let mut rest = args.trim();
+        let mut group = self.autocmds.current_group().cloned();
+        if let Some((word, tail)) = split_word(rest) {
+            let is_event = word == "*"
+                || word
+                    .split(',')
+                    .all(|name| AutocmdEvent::from_name(name).is_some());
+            if !is_event {
+                if let Some(found) = self.autocmds.find_group(word) {
+                    group = Some(found.clone());
+                    rest = tail;
+                }
+            }
+        }
+
+        let (events, tail) = match split_word(rest) {
+            Some((word, tail)) => (parse_events(word)?, tail),
+            None => (None, ""),
+        };
+        let (pattern, mut tail) = match split_word(tail) {
+            Some((word, tail)) => (Some(word), tail),
+            None => (None, ""),
+        };
+        let (mut once, mut nested) = (false, false);
+        while let Some((word, next)) = split_word(tail) {
+            match word {
+                "++once" => once = true,
+                "++nested" | "nested" => nested = true,
+                _ => break,
+            }
+            tail = next;
+        }
+        let command = tail.trim();
+
+        if bang {
+            for event in events.clone().unwrap_or_default() {
+                self.autocmds.delete(group.as_ref(), Some(event), pattern)?;
+            }
+            if events.is_none() {
+                match &group {
+                    Some(group) => self.autocmds.clear_group(group)?,
+                    None => self.autocmds.delete(None, None, pattern)?,
+                }
+            }
+        }
+
+        if command.is_empty() {
+            if bang {
+                return Ok(None);
+            }
+            return Ok(Some(self.list_autocmds(events.as_deref(), pattern)));
+        }
+
+        let (Some(events), Some(pattern)) = (events, pattern) else {
+            return Err(VimError::Error(
+                216,
+                "No such group or event: ".to_string() + args.trim(),
+            ));
+        };
+        for event in events {
+            for pattern in pattern.split(',') {
+                let pattern = match pattern {
+                    "<buffer>" => AutocmdPattern::Buffer(self.buffers.current().handle()),
+                    _ => AutocmdPattern::FilePattern(pattern.to_string()),
+                };
+                self.autocmds.create(Autocommand {
+                    id: 0,
+                    group: group.clone(),
+                    event,
+                    pattern,
+                    command: command.to_string(),
+                    once,
+                    nested,
+                    desc: None,
+                })?;
+            }
+        }
+        Ok(None)
+    }
+
+    fn list_autocmds(&self, events: Option<&[AutocmdEvent]>, pattern: Option<&str>) -> String {
+        let mut out = vec!["--- Autocommands ---".to_string()];
+        let mut last_event = None;
+        for autocmd in self.autocmds.list(None, None) {
+            if events.is_some_and(|events| !events.contains(&autocmd.event)) {
+                continue;
+            }
+            let text = pattern_text(&autocmd.pattern);
+            if pattern.is_some_and(|pattern| pattern != text) {
+                continue;
+            }
+            if last_event != Some(autocmd.event) {
+                let group = autocmd
+                    .group
+                    .as_ref()
+                    .map_or(String::new(), |group| format!("{}  ", group.name));
+                out.push(format!("{}{}", group, autocmd.event.name()));
+                last_event = Some(autocmd.event);
+            }
+            out.push(format!("    {:<10}{}", text, autocmd.command));
+        }
+        out.join("\n")
+    }
+
+    /// `:augroup {name}`, `:augroup END` and `:augroup! {name}`
+    pub(crate) fn ex_augroup(&mut self, args: &str, bang: bool) -> VimResult<()> {
+        let name = args.trim();
+        if bang {
+            return self.autocmds.augroup_delete(name);
+        }
+        if name.is_empty() || name.eq_ignore_ascii_case("end") {
+            self.autocmds.set_current_group(None);
+        } else {
+            self.autocmds.set_current_group(Some(name));
+        }
+        Ok(())
+    }
+
+    /// `:doautocmd [<nomodeline>] [group] {event} [fname]`.  As when
+    /// editing a file, the modelines are applied after the autocommands,
+    /// unless `<nomodeline>` is given or there were none for the events.
+    pub(crate) fn ex_doautocmd(&mut self, args: &str) -> VimResult<()> {
+        let mut rest = args.trim();
+        let nomodeline = match rest.strip_prefix("<nomodeline>") {
+            Some(after) => {
+                rest = after.trim_start();
+                true
+            }
+            None => false,
+        };
+        if let Some((word, tail)) = split_word(rest) {
+            if AutocmdEvent::from_name(word).is_none() && self.autocmds.find_group(word).is_some() {
+                rest = tail;
+            }
+        }
+        let Some((word, tail)) = split_word(rest) else {
+            return Err(VimError::Error(471, "Argument required".to_string()));
+        };
+        let events = parse_events(word)?.unwrap_or_default();
+        let mut data = self.autocmd_data();
+        let fname = tail.trim();
+        if !fname.is_empty() {
+            data.match_ = Some(fname.to_string());
+        }
+        let mut found = false;
+        for event in events {
+            found |= self.autocmds.exists(event, None);
+            self.apply_autocmds(event, &data)?;
+        }
+        if found && !nomodeline {
+            self.do_modelines()?;
+        }
+        Ok(())
+    }
+}
+
+/// Split off the first whitespace-separated word
+fn split_word(text: &str) -> Option<(&str, &str)> {
+    let text = text.trim_start();
+    if text.is_empty() {
+        return None;
+    }
+    let end = text.find(char::is_whitespace).unwrap_or(text.len());
+    Some((&text[..end], text[end..].trim_start()))
+}
+
+/// Parse a comma-separated event list; `*` means every event
+fn parse_events(word: &str) -> VimResult<Option<Vec<AutocmdEvent>>> {
+    if word == "*" {
+        return Ok(None);
+    }
+    word.split(',')
+        .map(|name| {
+            AutocmdEvent::from_name(name)
+                .ok_or_else(|| VimError::Error(216, format!("No such group or event: {}", name)))
+        })
+        .collect::<VimResult<Vec<_>>>()
+        .map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pattern_matches() {
+        assert!(pattern_matches("*", "foo.rs"));
+        assert!(pattern_matches("*.rs", "/tmp/src/foo.rs"));
+        assert!(!pattern_matches("*.rs", "foo.txt"));
+        assert!(pattern_matches("*.{c,h}", "x.h"));
+        assert!(pattern_matches("file[0-9].txt", "file7.txt"));
+        assert!(pattern_matches("/tmp/*.rs", "/tmp/foo.rs"));
+        assert!(!pattern_matches("/tmp/*.rs", "/var/foo.rs"));
+    }
+
+    #[test]
+    fn test_exec_queues_matching_commands() {
+        let mut manager = TuiAutocmdManager::new();
+        let autocmd = |event, pattern: &str, once| Autocommand {
+            id: 0,
+            group: None,
+            event,
+            pattern: AutocmdPattern::FilePattern(pattern.to_string()),
+            command: format!("echo {}", pattern),
+            once,
+            nested: false,
+            desc: None,
+        };
+        manager
+            .create(autocmd(AutocmdEvent::BufReadPost, "*.rs", false))
+            .unwrap();
+        manager
+            .create(autocmd(AutocmdEvent::BufReadPost, "*", true))
+            .unwrap();
+        let data = AutocmdEventData {
+            file: Some("main.rs".to_string()),
+            ..Default::default()
+        };
+        manager.exec(AutocmdEvent::BufReadPost, &data).unwrap();
+        assert_eq!(manager.take_queued().len(), 2);
+        manager.exec(AutocmdEvent::BufReadPost, &data).unwrap();
+        assert_eq!(manager.take_queued().len(), 1);
+    }
+}
@@ -3,7 +3,8 @@
 //! This module provides a concrete implementation of the `vxd::buffer::Buffer` trait.
 
 use vxd::buffer::{
-    BufDeleteMode, BufHandle, BufHidden, Buffer, BufferLoadState, BufferManager, BufferType,
+    BufDeleteMode, BufHandle, BufHidden, Buffer, BufferChange, BufferLoadState, BufferManager,
+    BufferType, TextSplice,
 };
 use vxd::types::{VimError, VimResult};
 
@@ -32,6 +33,8 @@ pub struct TuiBuffer {
     listed: bool,
     /// Change tick (version number)
     changedtick: u64,
+    /// Line changes not yet consumed by listeners (folds, marks)
+    changes: Vec<BufferChange>,
 }
 
 impl TuiBuffer {
@@ -49,6 +52,7 @@ impl TuiBuffer {
             load_state: BufferLoadState::Loaded,
             listed: true,
             changedtick: 0,
+            changes: Vec::new(),
         }
     }
 
@@ -78,6 +82,28 @@ impl TuiBuffer {
     fn bump_changedtick(&mut self) {
         self.changedtick += 1;
     }
+
+    /// Record a line change for later consumption by `take_changes`
+    fn record_change(
+        &mut self,
+        first_line: usize,
+        last_line: usize,
+        new_last_line: usize,
+        splice: TextSplice,
+    ) {
+        self.changes.push(BufferChange {
+            first_line,
+            last_line,
+            new_last_line,
+            changedtick: self.changedtick,
+            splice,
+        });
+    }
+
+    /// Drain the line changes made since the last call
+    pub fn take_changes(&mut self) -> Vec<BufferChange> {
+        std::mem::take(&mut self.changes)
+    }
 }
 
 impl Buffer for TuiBuffer {
@@ -174,7 +200,9 @@ impl Buffer for TuiBuffer {
         let end_idx = end_idx.max(start_idx).min(len);
 
         // Replace the lines
-        let _before: Vec<String> = self.lines.drain(start_idx..end_idx).collect();
+        let before: Vec<String> = self.lines.drain(start_idx..end_idx).collect();
+        let splice = TextSplice::between(start_idx, &before, &replacement);
+        let new_end = start_idx + replacement.len();
         for (i, line) in replacement.into_iter().enumerate() {
             self.lines.insert(start_idx + i, line);
         }
@@ -187,6 +215,7 @@ impl Buffer for TuiBuffer {
         // Mark as modified
         self.modified = true;
         self.bump_changedtick();
+        self.record_change(start_idx, end_idx, new_end, splice);
 
         Ok(())
     }
@@ -248,6 +277,8 @@ impl Buffer for TuiBuffer {
         }
 
         // Remove the old lines and insert new ones
+        let splice = TextSplice::between(start_row, &self.lines[start_row..=end_row], &new_lines);
+        let new_end = start_row + new_lines.len();
         for _ in start_row..=end_row {
             if start_row < self.lines.len() {
                 self.lines.remove(start_row);
@@ -264,6 +295,7 @@ impl Buffer for TuiBuffer {
 
         self.modified = true;
         self.bump_changedtick();
+        self.record_change(start_row, end_row + 1, new_end, splice);
 
         Ok(())
     }
@@ -357,6 +389,8 @@ pub struct TuiBufferManager {
     current: usize,
     /// Next handle to assign
     next_handle: usize,
+    /// Buffers in the order they were last made current, most recent first
+    last_used: Vec<BufHandle>,
 }
 
 impl TuiBufferManager {
@@ -366,11 +400,32 @@ impl TuiBufferManager {
             buffers: Vec::new(),
             current: 0,
             next_handle: 1,
+            last_used: Vec::new(),
         };
         // Create initial buffer
         mgr.create().ok();
         mgr
     }
+
+    /// Valid buffers, most recently used first.  Buffers that were never
+    /// made current follow in creation order.
+    pub fn last_used(&self) -> Vec<BufHandle> {
+        let valid = self.list();
+        let current = self.current().handle;
+        let mut handles = vec![current];
+        handles.extend(
+            self.last_used
+                .iter()
+                .copied()
+                .filter(|handle| *handle != current && valid.contains(handle)),
+        );
+        let rest: Vec<BufHandle> = valid
+            .into_iter()
+            .filter(|handle| !handles.contains(handle))
+            .collect();
+        handles.extend(rest);
+        handles
+    }
 }
 
 impl BufferManager for TuiBufferManager {
@@ -418,6 +473,9 @@ impl BufferManager for TuiBufferManager {
 
     fn set_current(&mut self, handle: BufHandle) -> VimResult<()> {
         if let Some(idx) = self.buffers.iter().position(|b| b.handle == handle) {
+            let previous = self.buffers[self.current].handle;
+            self.last_used.retain(|h| *h != previous);
+            self.last_used.insert(0, previous);
             self.current = idx;
             Ok(())
         } else {
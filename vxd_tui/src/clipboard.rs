@@ -0,0 +1,468 @@
+//! Clipboard providers.
+//!
+//! [`CommandClipboard`] runs a copy and a paste command for each of the
+//! `+` and `*` registers, with presets for wl-clipboard, xclip, xsel,
+//! pbcopy and tmux.  [`Osc52Clipboard`] copies by sending an OSC 52
+//! escape sequence to the terminal, which works over SSH; it cannot read
+//! the clipboard, so a put gives what was last copied.  [`FakeClipboard`]
+//! keeps the text in memory, for tests.  [`detect_clipboard`] picks a
+//! provider for the environment the editor runs in.
+//!
+//! The user can give the copy and paste commands in a `g:clipboard`
+//! Dictionary, as in Neovim, which is used instead when it is set after
+//! the config files are sourced:
+//!
+//! ```vim
+//! let g:clipboard = {
+//!       \ 'name': 'myClipboard',
+//!       \ 'copy': {'+': ['tmux', 'load-buffer', '-'], '*': ['tmux', 'load-buffer', '-']},
+//!       \ 'paste': {'+': ['tmux', 'save-buffer', '-'], '*': ['tmux', 'save-buffer', '-']},
+//!       \ }
+//! ```
+//!
+//! Each command is a List of the program and its arguments; without `'*'`
+//! the `'+'` commands are used for both registers.
+//!
+//! With 'clipboard' set to "unnamed" or "unnamedplus" yanks, deletes and
+//! puts that do not name a register go through `*` or `+`.
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+
+use vxd::clipboard::{ClipboardFlags, ClipboardProvider};
+use vxd::eval::Value;
+use vxd::registers::{Register, RegisterBank, RegisterContent};
+use vxd::types::{VimError, VimResult};
+
+use crate::editor::Editor;
+
+// ============================================================================
+// Command Provider
+// ============================================================================
+
+/// A provider that runs external commands: the copy command gets the
+/// text on its standard input, the paste command prints it
+#[derive(Debug, Clone)]
+pub struct CommandClipboard {
+    name: String,
+    copy: Vec<String>,
+    paste: Vec<String>,
+    copy_selection: Vec<String>,
+    paste_selection: Vec<String>,
+}
+
+impl CommandClipboard {
+    /// A provider named `name` running `copy` and `paste` for both the
+    /// `+` and `*` registers
+    pub fn new(name: &str, copy: &[&str], paste: &[&str]) -> Self {
+        let argv = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        CommandClipboard {
+            name: name.to_string(),
+            copy: argv(copy),
+            paste: argv(paste),
+            copy_selection: argv(copy),
+            paste_selection: argv(paste),
+        }
+    }
+
+    /// Use `copy` and `paste` for the `*` register instead
+    pub fn with_selection(mut self, copy: &[&str], paste: &[&str]) -> Self {
+        self.copy_selection = copy.iter().map(|arg| arg.to_string()).collect();
+        self.paste_selection = paste.iter().map(|arg| arg.to_string()).collect();
+        self
+    }
+
+    /// wl-copy and wl-paste, for Wayland
+    pub fn wl_clipboard() -> Self {
+        CommandClipboard::new(
+            "wl-copy",
+            &["wl-copy", "--type", "text/plain"],
+            &["wl-paste", "--no-newline"],
+        )
+        .with_selection(
+            &["wl-copy", "--primary", "--type", "text/plain"],
+            &["wl-paste", "--no-newline", "--primary"],
+        )
+    }
+
+    /// xclip, for X11
+    pub fn xclip() -> Self {
+        CommandClipboard::new(
+            "xclip",
+            &["xclip", "-i", "-selection", "clipboard"],
+            &["xclip", "-o", "-selection", "clipboard"],
+        )
+        .with_selection(
+            &["xclip", "-i", "-selection", "primary"],
+            &["xclip", "-o", "-selection", "primary"],
+        )
+    }
+
+    /// xsel, for X11
+    pub fn xsel() -> Self {
+        CommandClipboard::new("xsel", &["xsel", "-i", "-b"], &["xsel", "-o", "-b"])
+            .with_selection(&["xsel", "-i", "-p"], &["xsel", "-o", "-p"])
+    }
+
+    /// pbcopy and pbpaste, for macOS
+    pub fn pbcopy() -> Self {
+        CommandClipboard::new("pbcopy", &["pbcopy"], &["pbpaste"])
+    }
+
+    /// The tmux paste buffer
+    pub fn tmux() -> Self {
+        CommandClipboard::new(
+            "tmux",
+            &["tmux", "load-buffer", "-"],
+            &["tmux", "save-buffer", "-"],
+        )
+    }
+
+    /// The provider a `g:clipboard` Dictionary describes, see the module
+    /// documentation
+    pub fn from_value(value: &Value) -> VimResult<Self> {
+        let invalid = || VimError::CommandFailed("clipboard: invalid g:clipboard".to_string());
+        let Value::Dict(dict) = value else {
+            return Err(invalid());
+        };
+        let dict = dict.borrow();
+        let commands = |key: &str| -> VimResult<(Vec<String>, Vec<String>)> {
+            let Some(Value::Dict(commands)) = dict.get(key) else {
+                return Err(invalid());
+            };
+            let commands = commands.borrow();
+            let clipboard = commands.get("+").ok_or_else(invalid).and_then(argv)?;
+            let selection = match commands.get("*") {
+                Some(value) => argv(value)?,
+                None => clipboard.clone(),
+            };
+            Ok((clipboard, selection))
+        };
+        let (copy, copy_selection) = commands("copy")?;
+        let (paste, paste_selection) = commands("paste")?;
+        let name = match dict.get("name") {
+            Some(name) => name.as_string()?,
+            None => "g:clipboard".to_string(),
+        };
+        Ok(CommandClipboard {
+            name,
+            copy,
+            paste,
+            copy_selection,
+            paste_selection,
+        })
+    }
+
+    fn commands(&self, reg: Register) -> (&[String], &[String]) {
+        match reg {
+            Register::Selection => (&self.copy_selection, &self.paste_selection),
+            _ => (&self.copy, &self.paste),
+        }
+    }
+}
+
+/// The command of a `g:clipboard` entry: a List of Strings, not empty
+fn argv(value: &Value) -> VimResult<Vec<String>> {
+    let invalid = || VimError::CommandFailed("clipboard: invalid g:clipboard".to_string());
+    let Value::List(list) = value else {
+        return Err(invalid());
+    };
+    let argv = list
+        .borrow()
+        .iter()
+        .map(Value::as_string)
+        .collect::<VimResult<Vec<_>>>()?;
+    if argv.is_empty() {
+        return Err(invalid());
+    }
+    Ok(argv)
+}
+
+/// The process running `argv`, an error when it is empty
+fn command(argv: &[String]) -> VimResult<Command> {
+    let (program, args) = argv
+        .split_first()
+        .ok_or_else(|| VimError::CommandFailed("clipboard: no command given".to_string()))?;
+    let mut command = Command::new(program);
+    command.args(args);
+    Ok(command)
+}
+
+fn invoke_error(argv: &[String]) -> VimError {
+    VimError::CommandFailed(format!("clipboard: error invoking {}", argv[0]))
+}
+
+impl ClipboardProvider for CommandClipboard {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn copy(&mut self, reg: Register, text: &str) -> VimResult<()> {
+        let (argv, _) = self.commands(reg);
+        let failed = || invoke_error(argv);
+        let mut child = command(argv)?
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|_| failed())?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes()).map_err(|_| failed())?;
+        }
+        match child.wait() {
+            Ok(status) if status.success() => Ok(()),
+            _ => Err(failed()),
+        }
+    }
+
+    fn paste(&mut self, reg: Register) -> VimResult<Option<String>> {
+        let (_, argv) = self.commands(reg);
+        let output = command(argv)?
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .map_err(|_| invoke_error(argv))?;
+        if output.status.success() {
+            Ok(Some(String::from_utf8_lossy(&output.stdout).into_owned()))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+// ============================================================================
+// OSC 52 Provider
+// ============================================================================
+
+/// A provider that copies by sending OSC 52 to the terminal
+pub struct Osc52Clipboard {
+    out: Box<dyn Write + Send>,
+    tmux: bool,
+}
+
+impl std::fmt::Debug for Osc52Clipboard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Osc52Clipboard")
+            .field("tmux", &self.tmux)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Osc52Clipboard {
+    /// Send to standard output, wrapped for tmux when running inside it
+    pub fn new() -> Self {
+        Osc52Clipboard::with_writer(
+            Box::new(std::io::stdout()),
+            std::env::var_os("TMUX").is_some(),
+        )
+    }
+
+    /// Send to `out`; with `tmux` the sequence is passed through tmux
+    pub fn with_writer(out: Box<dyn Write + Send>, tmux: bool) -> Self {
+        Osc52Clipboard { out, tmux }
+    }
+}
+
+impl Default for Osc52Clipboard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ClipboardProvider for Osc52Clipboard {
+    fn name(&self) -> &str {
+        "OSC 52"
+    }
+
+    fn copy(&mut self, reg: Register, text: &str) -> VimResult<()> {
+        let selection = match reg {
+            Register::Selection => 'p',
+            _ => 'c',
+        };
+        let mut sequence = format!("\x1b]52;{};{}\x07", selection, base64(text.as_bytes()));
+        if self.tmux {
+            sequence = format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"));
+        }
+        self.out
+            .write_all(sequence.as_bytes())
+            .and_then(|_| self.out.flush())
+            .map_err(|err| VimError::CommandFailed(format!("clipboard: {}", err)))
+    }
+
+    fn paste(&mut self, _reg: Register) -> VimResult<Option<String>> {
+        Ok(None)
+    }
+}
+
+/// `bytes` in base64 with padding
+fn base64(bytes: &[u8]) -> String {
+    const DIGITS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | ((b as u32) << (16 - 8 * i)));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(DIGITS[((n >> (18 - 6 * i)) & 63) as usize] as char)
+            } else {
+                out.push('=')
+            }
+        }
+    }
+    out
+}
+
+// ============================================================================
+// Fake Provider
+// ============================================================================
+
+/// A provider keeping the clipboard in memory, like a system clipboard
+/// that only holds text.  Clones share the same clipboard, so a test can
+/// keep one to look at or change what the editor sees.
+#[derive(Debug, Clone, Default)]
+pub struct FakeClipboard {
+    contents: Arc<Mutex<HashMap<Register, String>>>,
+}
+
+impl FakeClipboard {
+    /// An empty clipboard
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The text on the clipboard for `reg`
+    pub fn text(&self, reg: Register) -> Option<String> {
+        self.contents.lock().ok()?.get(&reg).cloned()
+    }
+
+    /// Put `text` on the clipboard for `reg`, as another program would
+    pub fn set_text(&self, reg: Register, text: &str) {
+        if let Ok(mut contents) = self.contents.lock() {
+            contents.insert(reg, text.to_string());
+        }
+    }
+}
+
+impl ClipboardProvider for FakeClipboard {
+    fn name(&self) -> &str {
+        "fake"
+    }
+
+    fn copy(&mut self, reg: Register, text: &str) -> VimResult<()> {
+        self.set_text(reg, text);
+        Ok(())
+    }
+
+    fn paste(&mut self, reg: Register) -> VimResult<Option<String>> {
+        Ok(self.text(reg))
+    }
+}
+
+// ============================================================================
+// Detection
+// ============================================================================
+
+/// A provider for the environment: wl-clipboard under Wayland, xclip or
+/// xsel under X11, pbcopy on macOS, OSC 52 over SSH and the tmux buffer
+/// inside tmux.  `None` when nothing fits.
+pub fn detect_clipboard() -> Option<Box<dyn ClipboardProvider>> {
+    let env = |name: &str| std::env::var_os(name).is_some_and(|value| !value.is_empty());
+    if env("WAYLAND_DISPLAY") && executable("wl-copy") && executable("wl-paste") {
+        return Some(Box::new(CommandClipboard::wl_clipboard()));
+    }
+    if env("DISPLAY") && executable("xclip") {
+        return Some(Box::new(CommandClipboard::xclip()));
+    }
+    if env("DISPLAY") && executable("xsel") {
+        return Some(Box::new(CommandClipboard::xsel()));
+    }
+    if executable("pbcopy") && executable("pbpaste") {
+        return Some(Box::new(CommandClipboard::pbcopy()));
+    }
+    if env("SSH_TTY") || env("SSH_CONNECTION") {
+        return Some(Box::new(Osc52Clipboard::new()));
+    }
+    if env("TMUX") && executable("tmux") {
+        return Some(Box::new(CommandClipboard::tmux()));
+    }
+    None
+}
+
+/// Whether `name` is an executable in $PATH
+fn executable(name: &str) -> bool {
+    std::env::var_os("PATH").is_some_and(|path| {
+        std::env::split_paths(&path).any(|dir| Path::new(&dir).join(name).is_file())
+    })
+}
+
+// ============================================================================
+// 'clipboard'
+// ============================================================================
+
+impl Editor {
+    /// Use the provider `g:clipboard` gives, when it is set
+    pub fn configure_clipboard(&mut self) -> VimResult<()> {
+        let Some(config) = self.vimscript.variable("g:clipboard", self) else {
+            return Ok(());
+        };
+        let provider = CommandClipboard::from_value(&config)?;
+        self.registers.set_clipboard_provider(Box::new(provider));
+        Ok(())
+    }
+
+    /// The 'clipboard' option
+    pub fn clipboard_flags(&self) -> ClipboardFlags {
+        ClipboardFlags::parse(self.options.string("clipboard")).unwrap_or_default()
+    }
+
+    /// The register a put without a register name uses
+    pub(crate) fn unnamed_put_register(&self) -> Register {
+        self.clipboard_flags()
+            .put_register()
+            .unwrap_or(Register::Unnamed)
+    }
+
+    /// Copy text yanked or deleted without a register name to the
+    /// registers 'clipboard' gives
+    pub(crate) fn copy_unnamed_to_clipboard(&mut self, content: &RegisterContent) -> VimResult<()> {
+        for reg in self.clipboard_flags().store_registers() {
+            self.set_register(reg, content.clone())?;
+        }
+        Ok(())
+    }
+
+    /// Set `reg` to `content`.  When the clipboard provider fails for "+
+    /// or "* the register still has the text and the error is only shown.
+    pub(crate) fn set_register(
+        &mut self,
+        reg: Register,
+        content: RegisterContent,
+    ) -> VimResult<()> {
+        match self.registers.set(reg, content) {
+            Err(err) if reg.is_clipboard() => {
+                self.add_error(&err.to_string());
+                Ok(())
+            }
+            result => result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"hello\n"), "aGVsbG8K");
+    }
+}
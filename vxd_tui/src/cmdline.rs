@@ -1,31 +1,106 @@
 //! Command-line implementation.
+//!
+//! Besides the text and history, this module handles the command-line
+//! editing keys that need more than the text itself: `Ctrl-R` inserting
+//! registers and text from the buffer, `Ctrl-V` and `Ctrl-K` inserting
+//! special characters, and history recall with `<Up>`/`<Down>`.
 
 use std::collections::{HashMap, VecDeque};
 
 use vxd::cmdline::{Cmdline, CmdlineHistory, CmdlineHistoryKind};
+use vxd::completion::is_keyword_char;
+use vxd::cursor::Cursor;
+use vxd::registers::Register;
 use vxd::types::VimResult;
 
+use crate::editor::Editor;
+use crate::eval::ExprReturn;
+use crate::key::Key;
+
 /// Command-line implementation for the TUI.
 #[derive(Debug, Default)]
 pub struct TuiCmdline {
     text: String,
+    /// Cursor, a byte offset into `text`
+    pos: usize,
     history_limit: usize,
     history: HashMap<CmdlineHistoryKind, VecDeque<String>>,
+    /// History entry shown by `<Up>`/`<Down>` and the text they recall for
+    recall: Option<(usize, String)>,
+    pub(crate) pending: CmdlinePending,
+}
+
+/// A command-line key waiting for the keys that complete it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) enum CmdlinePending {
+    #[default]
+    None,
+    /// `Ctrl-R`, or `Ctrl-R Ctrl-R` to insert literally
+    Register { literally: bool },
+    /// `Ctrl-V` followed by the typed code, e.g. `"u20"`
+    Literal(String),
+    /// `Ctrl-K` followed by the first character, if typed
+    Digraph(Option<char>),
 }
 
 impl TuiCmdline {
     /// Create a new command-line instance.
     pub fn new() -> Self {
         TuiCmdline {
-            text: String::new(),
             history_limit: 100,
-            history: HashMap::new(),
+            ..Default::default()
         }
     }
 
     fn history_mut(&mut self, kind: CmdlineHistoryKind) -> &mut VecDeque<String> {
         self.history.entry(kind).or_default()
     }
+
+    /// All entries of a history, oldest first
+    pub fn history_entries(&self, kind: CmdlineHistoryKind) -> Vec<String> {
+        self.history
+            .get(&kind)
+            .map(|deque| deque.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Forget the history entry being recalled, after the text is edited
+    pub fn reset_recall(&mut self) {
+        self.recall = None;
+    }
+
+    /// Recall an older (`older`) or newer history entry.  With `prefix`,
+    /// only entries starting with the text before the cursor when recall
+    /// started are used (`<Up>`/`<Down>`); otherwise any entry is
+    /// (`<S-Up>`, `Ctrl-P`/`Ctrl-N`).  Going past the newest entry brings
+    /// back the text typed before recall.  Returns false when there is no
+    /// entry to move to.
+    pub fn recall_history(&mut self, kind: CmdlineHistoryKind, older: bool, prefix: bool) -> bool {
+        let entries = self.history_entries(kind);
+        let (current, typed) = self
+            .recall
+            .clone()
+            .unwrap_or_else(|| (entries.len(), self.text[..self.pos].to_string()));
+        let matches = |entry: &String| !prefix || entry.starts_with(&typed);
+        let next = if older {
+            entries[..current].iter().rposition(matches)
+        } else {
+            entries
+                .iter()
+                .enumerate()
+                .skip(current + 1)
+                .find(|(_, entry)| matches(entry))
+                .map(|(idx, _)| idx)
+                .or((current < entries.len()).then_some(entries.len()))
+        };
+        let Some(next) = next else {
+            return false;
+        };
+        self.text = entries.get(next).cloned().unwrap_or_else(|| typed.clone());
+        self.pos = self.text.len();
+        self.recall = Some((next, typed));
+        true
+    }
 }
 
 impl Cmdline for TuiCmdline {
@@ -35,6 +110,20 @@ impl Cmdline for TuiCmdline {
 
     fn setcmdline(&mut self, text: &str) -> VimResult<()> {
         self.text = text.to_string();
+        self.pos = self.text.len();
+        Ok(())
+    }
+
+    fn getcmdpos(&self) -> usize {
+        self.pos + 1
+    }
+
+    fn setcmdpos(&mut self, pos: usize) -> VimResult<()> {
+        let mut pos = pos.saturating_sub(1).min(self.text.len());
+        while !self.text.is_char_boundary(pos) {
+            pos -= 1;
+        }
+        self.pos = pos;
         Ok(())
     }
 }
@@ -99,6 +188,230 @@ impl CmdlineHistory for TuiCmdline {
     }
 }
 
+// ============================================================================
+// Editing Keys
+// ============================================================================
+
+impl Editor {
+    /// History the current command-line type uses
+    pub(crate) fn cmdline_history_kind(&self) -> CmdlineHistoryKind {
+        match self.cmdline_type {
+            Some('/') => CmdlineHistoryKind::SearchForward,
+            Some('?') => CmdlineHistoryKind::SearchBackward,
+            Some('=') => CmdlineHistoryKind::Expression,
+            _ => CmdlineHistoryKind::Command,
+        }
+    }
+
+    /// Feed `key` to a pending `Ctrl-R`, `Ctrl-V` or `Ctrl-K`.  Returns the
+    /// key when nothing is pending or the key still has to be handled.
+    pub(crate) fn cmdline_pending_key(&mut self, key: Key) -> VimResult<Option<Key>> {
+        match std::mem::take(&mut self.cmdline.pending) {
+            CmdlinePending::None => Ok(Some(key)),
+            CmdlinePending::Register { literally } => {
+                self.cmdline_register_key(key, literally)?;
+                Ok(None)
+            }
+            CmdlinePending::Literal(code) => self.cmdline_literal_key(code, key),
+            CmdlinePending::Digraph(first) => match (first, key) {
+                (None, Key::Char(c)) => {
+                    self.cmdline.pending = CmdlinePending::Digraph(Some(c));
+                    Ok(None)
+                }
+                (Some(first), Key::Char(second)) => {
+                    let ch = self.digraphs.resolve(first, second);
+                    self.cmdline.insert_cmdline(&ch.to_string())?;
+                    Ok(None)
+                }
+                _ => Ok(Some(key)),
+            },
+        }
+    }
+
+    /// Handle a key that edits the command-line text or moves its cursor.
+    /// Returns false for keys that are not editing keys.
+    pub(crate) fn cmdline_edit_key(&mut self, key: Key) -> VimResult<bool> {
+        let kind = self.cmdline_history_kind();
+        match key {
+            Key::Up | Key::Down => {
+                self.cmdline.recall_history(kind, key == Key::Up, true);
+                return Ok(true);
+            }
+            Key::Ctrl('p') | Key::PageUp => {
+                self.cmdline.recall_history(kind, true, false);
+                return Ok(true);
+            }
+            Key::Ctrl('n') | Key::PageDown => {
+                self.cmdline.recall_history(kind, false, false);
+                return Ok(true);
+            }
+            _ => {}
+        }
+        self.cmdline.reset_recall();
+
+        let pos = self.cmdline.getcmdpos();
+        let text = self.cmdline.getcmdline();
+        match key {
+            Key::Char(c) => self.cmdline.insert_cmdline(&c.to_string())?,
+            Key::Left => {
+                let len = text[..pos - 1].chars().next_back().map_or(0, char::len_utf8);
+                self.cmdline.setcmdpos(pos - len)?;
+            }
+            Key::Right => {
+                let len = text[pos - 1..].chars().next().map_or(0, char::len_utf8);
+                self.cmdline.setcmdpos(pos + len)?;
+            }
+            Key::Home | Key::Ctrl('b') => self.cmdline.setcmdpos(1)?,
+            Key::End | Key::Ctrl('e') => self.cmdline.setcmdpos(text.len() + 1)?,
+            Key::Delete => self.cmdline.delete_char_under()?,
+            Key::Ctrl('w') => self.cmdline.delete_word_before()?,
+            Key::Ctrl('u') => self.cmdline.delete_line_before()?,
+            Key::Ctrl('r') => self.cmdline.pending = CmdlinePending::Register { literally: false },
+            Key::Ctrl('v') | Key::Ctrl('q') => {
+                self.cmdline.pending = CmdlinePending::Literal(String::new())
+            }
+            Key::Ctrl('k') => self.cmdline.pending = CmdlinePending::Digraph(None),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// The key after `Ctrl-R`: a register name, `Ctrl-R`/`Ctrl-O` to insert
+    /// the register literally, or `Ctrl-W`, `Ctrl-A`, `Ctrl-L` and `Ctrl-F`
+    /// to insert text from under the cursor in the buffer.
+    fn cmdline_register_key(&mut self, key: Key, literally: bool) -> VimResult<()> {
+        let text = match key {
+            Key::Ctrl('r' | 'o' | 'p') => {
+                self.cmdline.pending = CmdlinePending::Register { literally: true };
+                return Ok(());
+            }
+            Key::Ctrl('w') => self.text_under_cursor(is_keyword_char),
+            Key::Ctrl('a') => self.text_under_cursor(|c| !c.is_whitespace()),
+            Key::Ctrl('f') => self.text_under_cursor(is_fname_char),
+            Key::Ctrl('l') => Some(self.current_line()),
+            Key::Char('=') if self.cmdline_type != Some('=') => {
+                let target = ExprReturn::Cmdline {
+                    kind: self.cmdline_type.unwrap_or(':'),
+                    text: self.cmdline.getcmdline().to_string(),
+                    pos: self.cmdline.getcmdpos() - 1,
+                };
+                return self.enter_expr_cmdline(target);
+            }
+            Key::Char(c) => {
+                let Ok(reg) = Register::from_char(c) else {
+                    return Ok(());
+                };
+                return match self.fetch_register(reg)? {
+                    Some(content) => self.cmdline.paste_register(&content, literally),
+                    None => Ok(()),
+                };
+            }
+            _ => None,
+        };
+        match text {
+            Some(text) => self.cmdline.insert_cmdline(&text),
+            None => Ok(()),
+        }
+    }
+
+    /// The text under or after the buffer cursor made of characters
+    /// matching `is_part`
+    fn text_under_cursor(&self, is_part: fn(char) -> bool) -> Option<String> {
+        let line = self.current_line();
+        let col = self.cursor.col().min(line.len());
+        let start = line[..col]
+            .char_indices()
+            .rev()
+            .take_while(|&(_, c)| is_part(c))
+            .last()
+            .map_or(col, |(idx, _)| idx);
+        let start = match line[start..].find(is_part) {
+            Some(offset) => start + offset,
+            None => return None,
+        };
+        let end = line[start..]
+            .find(|c: char| !is_part(c))
+            .map_or(line.len(), |len| start + len);
+        Some(line[start..end].to_string())
+    }
+
+    /// The keys after `Ctrl-V`: a decimal, octal (`o`), hex (`x`) or
+    /// Unicode (`u`, `U`) character code, or any key inserted literally
+    fn cmdline_literal_key(&mut self, mut code: String, key: Key) -> VimResult<Option<Key>> {
+        let mut prefix = code.chars().next().filter(|c| !c.is_ascii_digit());
+        if code.is_empty() {
+            match key {
+                Key::Char(c @ ('x' | 'X' | 'u' | 'U' | 'o' | 'O')) => {
+                    self.cmdline.pending = CmdlinePending::Literal(c.to_string());
+                    return Ok(None);
+                }
+                Key::Char(c) if c.is_ascii_digit() => prefix = None,
+                _ => {
+                    if let Some(c) = literal_char(key) {
+                        self.cmdline.insert_cmdline(&c.to_string())?;
+                    }
+                    return Ok(None);
+                }
+            }
+        }
+        let (radix, max_digits) = match prefix {
+            Some('x' | 'X') => (16, 2),
+            Some('u') => (16, 4),
+            Some('U') => (16, 8),
+            Some(_) => (8, 3),
+            None => (10, 3),
+        };
+        let digits_start = usize::from(prefix.is_some());
+        let leftover = match key {
+            Key::Char(c) if c.is_digit(radix) => {
+                code.push(c);
+                if code.len() - digits_start < max_digits {
+                    self.cmdline.pending = CmdlinePending::Literal(code);
+                    return Ok(None);
+                }
+                None
+            }
+            _ => Some(key),
+        };
+        let digits = &code[digits_start..];
+        let ch = if digits.is_empty() {
+            prefix
+        } else {
+            u32::from_str_radix(digits, radix)
+                .ok()
+                .and_then(char::from_u32)
+        };
+        if let Some(ch) = ch {
+            self.cmdline.insert_cmdline(&ch.to_string())?;
+        }
+        Ok(leftover)
+    }
+}
+
+/// The character `Ctrl-V` inserts for a key
+pub(crate) fn literal_char(key: Key) -> Option<char> {
+    match key {
+        Key::Char(c) => Some(c),
+        Key::Escape => Some('\x1b'),
+        Key::Enter => Some('\r'),
+        Key::Tab => Some('\t'),
+        Key::Backspace => Some('\x08'),
+        Key::Delete => Some('\x7f'),
+        Key::Null => Some('\0'),
+        Key::Ctrl(c) => {
+            let code = c.to_ascii_uppercase() as u32;
+            (0x40..0x60).contains(&code).then(|| char::from_u32(code - 0x40))?
+        }
+        _ => None,
+    }
+}
+
+/// Characters `Ctrl-R Ctrl-F` takes as part of a file name (default
+/// 'isfname')
+fn is_fname_char(c: char) -> bool {
+    c.is_alphanumeric() || "/.-_+,#$%~=".contains(c)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -113,6 +426,7 @@ mod tests {
         let mut cmdline = TuiCmdline::new();
         cmdline.test_paste_non_special_register_inserts_cr_between_lines();
         cmdline.test_paste_special_register_preserves_newlines();
+        cmdline.test_editing_at_cursor();
     }
 
     #[test]
@@ -0,0 +1,214 @@
+//! Command-line window.
+//!
+//! `q:`, `q/` and `q?`, or the 'cedit' key in Command-line mode, open a
+//! window on a scratch buffer holding the command-line history, one entry
+//! per line, with the command line being typed as the last line.  It is
+//! edited with the usual Normal and Insert mode commands; `<CR>` executes
+//! the line under the cursor and `Ctrl-C` continues editing it in
+//! Command-line mode.
+//!
+//! The TUI shows one buffer at a time, so the window takes over the text
+//! area while it is open: the buffer, cursor and folds it was opened from
+//! are put aside and restored when it closes.  It is a window of its own,
+//! without the location list of the one it was opened from.  Switching
+//! buffers or opening another command-line window meanwhile fails with
+//! E11.
+
+use vxd::autocmd::AutocmdEvent;
+use vxd::buffer::{BufDeleteMode, BufHandle, Buffer, BufferManager, BufferType};
+use vxd::cmdline::{Cmdline, CmdlineHistoryKind};
+use vxd::cursor::{Cursor, CursorPosition};
+use vxd::folds::BufferFoldManager;
+use vxd::modes::{Mode, ModeManager};
+use vxd::options::OptionManager;
+use vxd::types::{LineNr, VimError, VimResult};
+use vxd::windows::WinHandle;
+
+use crate::editor::Editor;
+use crate::key::{parse_keys, Key};
+use crate::options::LocalValues;
+
+const E11: &str = "Invalid in command-line window; <CR> executes, CTRL-C quits";
+
+/// Name of the command-line window buffer
+pub const CMDWIN_BUFFER_NAME: &str = "[Command Line]";
+
+/// An open command-line window
+#[derive(Debug)]
+pub(crate) struct CmdWindow {
+    /// `:`, `/` or `?`
+    kind: char,
+    /// The scratch buffer shown in the window
+    buffer: BufHandle,
+    /// Buffer the window was opened from
+    previous: BufHandle,
+    /// Cursor in the previous buffer
+    cursor: CursorPosition,
+    /// Folds of the previous buffer
+    folds: BufferFoldManager,
+    /// Local option values of the previous buffer
+    buffer_options: LocalValues,
+    /// Local option values of the window it was opened from
+    window_options: LocalValues,
+    /// Window it was opened from
+    window: WinHandle,
+}
+
+impl Editor {
+    /// The type of the open command-line window (`:`, `/` or `?`), like
+    /// `getcmdwintype()`
+    pub fn cmdwin_type(&self) -> Option<char> {
+        self.cmdwin.as_ref().map(|cmdwin| cmdwin.kind)
+    }
+
+    /// Fail with E11 while the command-line window is open
+    pub fn check_cmdwin(&self) -> VimResult<()> {
+        match self.cmdwin {
+            Some(_) => Err(VimError::Error(11, E11.to_string())),
+            None => Ok(()),
+        }
+    }
+
+    /// Make `handle` the current buffer, with its local option values and
+    /// the folds the window had for it; not allowed from the command-line window
+    pub fn switch_buffer(&mut self, handle: BufHandle) -> VimResult<()> {
+        self.check_cmdwin()?;
+        let previous = self.buffers.current().handle();
+        self.buffers.set_current(handle)?;
+        if previous != handle {
+            self.swap_options(previous);
+            self.swap_folds(previous);
+        }
+        self.sync_cursor_with_buffer();
+        Ok(())
+    }
+
+    /// Open the command-line window for `kind` (`q:`, `q/`, `q?`) with an
+    /// empty last line
+    pub fn open_cmdline_window(&mut self, kind: char) -> VimResult<()> {
+        self.open_cmdwin(kind, "", 0)
+    }
+
+    /// Open the command-line window with `text` as the last line and the
+    /// cursor on byte `col` of it
+    pub(crate) fn open_cmdwin(&mut self, kind: char, text: &str, col: usize) -> VimResult<()> {
+        self.check_cmdwin()?;
+        let history = match kind {
+            '/' => CmdlineHistoryKind::SearchForward,
+            '?' => CmdlineHistoryKind::SearchBackward,
+            _ => CmdlineHistoryKind::Command,
+        };
+        let mut lines = self.cmdline.history_entries(history);
+        lines.push(text.to_string());
+        let last = lines.len();
+
+        let buffer = self.buffers.create_named(CMDWIN_BUFFER_NAME)?;
+        if let Some(buf) = self.buffers.get_mut(buffer) {
+            buf.set_buftype(BufferType::Nofile)?;
+            buf.set_listed(false)?;
+            buf.set_lines(0, -1, false, lines)?;
+            buf.take_changes();
+        }
+        self.cmdwin = Some(CmdWindow {
+            kind,
+            buffer,
+            previous: self.buffers.current().handle(),
+            cursor: self.cursor.position(),
+            folds: std::mem::take(&mut self.folds),
+            buffer_options: self.options.swap_buffer_values(None),
+            window_options: self.options.window_values().clone(),
+            window: self.window,
+        });
+        // The only other window, and there is never more than one
+        self.window = WinHandle(self.window.0 + 1);
+        self.buffers.set_current(buffer)?;
+        self.apply_options();
+        self.sync_cursor_with_buffer();
+        let ctx = self.cursor_context();
+        self.cursor
+            .set_position(CursorPosition::new(LineNr(last), col), &ctx)?;
+        self.cursor.update_curswant();
+
+        let data = self.cmdwin_autocmd_data(kind);
+        self.apply_autocmds(AutocmdEvent::CmdwinEnter, &data)
+    }
+
+    /// Close the command-line window and go back to the buffer it was
+    /// opened from.  Returns the line under the cursor in the window.
+    pub(crate) fn close_cmdwin(&mut self) -> VimResult<Option<(char, String)>> {
+        let Some(kind) = self.cmdwin_type() else {
+            return Ok(None);
+        };
+        let line = self.current_line();
+        let data = self.cmdwin_autocmd_data(kind);
+        let leave = self.apply_autocmds(AutocmdEvent::CmdwinLeave, &data);
+        if self.modes.mode() != Mode::Normal {
+            self.escape()?;
+        }
+
+        let Some(cmdwin) = self.cmdwin.take() else {
+            return Ok(None);
+        };
+        self.buffers.set_current(cmdwin.previous)?;
+        self.buffers
+            .delete(cmdwin.buffer, BufDeleteMode::Wipe, true)?;
+        self.folds = cmdwin.folds;
+        self.options.swap_buffer_values(Some(cmdwin.buffer_options));
+        self.options.set_window_values(cmdwin.window_options);
+        self.apply_options();
+        self.loclists.remove(&self.window);
+        self.window = cmdwin.window;
+        self.sync_cursor_with_buffer();
+        let ctx = self.cursor_context();
+        self.cursor.set_position(cmdwin.cursor, &ctx)?;
+        leave?;
+        Ok(Some((cmdwin.kind, line)))
+    }
+
+    fn cmdwin_autocmd_data(&self, kind: char) -> vxd::autocmd::AutocmdEventData {
+        let mut data = self.autocmd_data();
+        data.match_ = Some(kind.to_string());
+        data
+    }
+
+    /// Whether `key` is handled by the command-line window rather than the
+    /// current mode: `<CR>` and `Ctrl-C` in Normal and Insert mode
+    pub(crate) fn is_cmdwin_key(&self, key: Key) -> bool {
+        if self.cmdwin.is_none() || self.completion_active() {
+            return false;
+        }
+        match self.modes.mode() {
+            Mode::Normal => {
+                !self.has_pending_command() && matches!(key, Key::Enter | Key::Ctrl('c'))
+            }
+            Mode::Insert => matches!(key, Key::Enter | Key::Ctrl('c')),
+            _ => false,
+        }
+    }
+
+    /// `<CR>` executes the line under the cursor, `Ctrl-C` edits it on the
+    /// command line
+    pub(crate) fn cmdwin_key(&mut self, key: Key) -> VimResult<()> {
+        let Some((kind, line)) = self.close_cmdwin()? else {
+            return Ok(());
+        };
+        if key == Key::Enter {
+            return self.execute_cmdline_type(kind, &line);
+        }
+        self.enter_cmdline_type(kind)?;
+        self.cmdline.setcmdline(&line)
+    }
+
+    /// Whether `key` is the 'cedit' key, which opens the command-line
+    /// window from Command-line mode
+    pub(crate) fn is_cedit_key(&self, key: Key) -> bool {
+        let cedit = self.options.get_string("cedit").unwrap_or_default();
+        let cedit_key = match cedit.chars().next() {
+            Some('<') => parse_keys(cedit).first().copied(),
+            Some(c) if c.is_ascii_control() => Some(Key::Ctrl(((c as u8) | 0x60) as char)),
+            Some(c) => Some(Key::Char(c)),
+            None => None,
+        };
+        cedit_key == Some(key)
+    }
+}
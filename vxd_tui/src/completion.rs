@@ -0,0 +1,1206 @@
+//! Insert mode completion.
+//!
+//! [`TuiCompletionEngine`] implements `vxd::completion::CompletionEngine`
+//! over a snapshot of the places to search ([`CompletionSources`]).  The
+//! editor gathers the sources from its options and buffers, drives the
+//! `Ctrl-X` sub-modes and puts the selected match into the buffer
+//! (`:help ins-completion`).
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use vxd::autocmd::AutocmdEvent;
+use vxd::buffer::{Buffer, BufferLoadState, BufferManager};
+use vxd::completion::{
+    is_keyword_char, keyword_start, longest_common_prefix, CompleteOpt, CompleteSource,
+    CompletionEngine, CompletionItem, CompletionKind, CompletionState, MatchCollector,
+};
+use vxd::cursor::Cursor;
+use vxd::marks::MarkManager;
+use vxd::types::{VimError, VimResult};
+
+use crate::editor::Editor;
+use crate::key::Key;
+use crate::popupmenu::{scroll_to, PopupMenu, PumAnchor, PumLimits};
+
+/// A 'completefunc' or 'omnifunc' implementation.  Given the line and the
+/// cursor column it returns the column where the completed text starts and
+/// the matches, or `None` to cancel completion.
+pub type CompleteFunc = fn(line: &str, col: usize) -> Option<(usize, Vec<CompletionItem>)>;
+
+/// A buffer other than the current one, scanned for keywords
+#[derive(Debug, Clone)]
+pub struct SourceBuffer {
+    /// Which 'complete' item the buffer belongs to
+    pub source: CompleteSource,
+    /// Buffer name, shown in the menu column
+    pub name: String,
+    /// Buffer text
+    pub lines: Vec<String>,
+}
+
+/// Everything completion may search, gathered by the editor when
+/// completion starts
+#[derive(Debug, Clone, Default)]
+pub struct CompletionSources {
+    /// Lines of the current buffer
+    pub lines: Vec<String>,
+    /// Cursor line as a zero-based index
+    pub cursor_line: usize,
+    /// Search backwards from the cursor (`Ctrl-P`)
+    pub backward: bool,
+    /// Ignore case when matching ('ignorecase')
+    pub ignore_case: bool,
+    /// Places keyword completion scans, in order ('complete')
+    pub complete: Vec<CompleteSource>,
+    /// Other buffers for the `w`, `b` and `u` items of 'complete'
+    pub buffers: Vec<SourceBuffer>,
+    /// Word lists ('dictionary')
+    pub dictionary: Vec<PathBuf>,
+    /// Thesaurus files ('thesaurus')
+    pub thesaurus: Vec<PathBuf>,
+    /// Spell word lists ('spellfile')
+    pub spell: Vec<PathBuf>,
+    /// Tags files ('tags')
+    pub tags: Vec<PathBuf>,
+    /// Files included by the current buffer
+    pub includes: Vec<PathBuf>,
+    /// Directory relative file names are completed in
+    pub directory: PathBuf,
+    /// Function for `Ctrl-X Ctrl-U`
+    pub user_func: Option<CompleteFunc>,
+    /// Function for `Ctrl-X Ctrl-O`
+    pub omni_func: Option<CompleteFunc>,
+}
+
+/// Completion engine for Insert mode
+#[derive(Debug, Default)]
+pub struct TuiCompletionEngine {
+    sources: CompletionSources,
+    state: CompletionState,
+    /// Every match found when completion started; `state.items` holds the
+    /// ones still matching the typed text
+    matches: Vec<CompletionItem>,
+}
+
+impl TuiCompletionEngine {
+    /// Create an idle completion engine
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the places completion searches
+    pub fn set_sources(&mut self, sources: CompletionSources) {
+        self.sources = sources;
+    }
+
+    /// The selected match, if any
+    pub fn selected_item(&self) -> Option<&CompletionItem> {
+        self.state
+            .selected
+            .and_then(|idx| self.state.items.get(idx))
+    }
+
+    /// Select a match by index, or the original text with `None`
+    pub fn set_selected(&mut self, selected: Option<usize>) {
+        self.state.selected = selected.filter(|&idx| idx < self.state.items.len());
+    }
+
+    /// Set the text that was typed before completion
+    pub fn set_original(&mut self, original: impl Into<String>) {
+        self.state.original = original.into();
+    }
+
+    /// Narrow the matches to those starting with `base`, after the user
+    /// typed or deleted a character
+    pub fn refilter(&mut self, base: &str) {
+        let mut collector = MatchCollector::new(base, self.sources.ignore_case);
+        for item in &self.matches {
+            collector.add(item.clone());
+        }
+        self.state.items = collector.into_items();
+        self.state.original = base.to_string();
+        self.state.selected = None;
+    }
+
+    fn find_start(&self, kind: CompletionKind, line: &str, col: usize) -> VimResult<usize> {
+        let col = col.min(line.len());
+        Ok(match kind {
+            CompletionKind::Line => {
+                let indent = line.len() - line.trim_start().len();
+                indent.min(col)
+            }
+            CompletionKind::File => line[..col]
+                .char_indices()
+                .rev()
+                .take_while(|(_, c)| is_fname_char(*c))
+                .last()
+                .map_or(col, |(idx, _)| idx),
+            CompletionKind::User | CompletionKind::Omni => {
+                match self.complete_func(kind).and_then(|func| func(line, col)) {
+                    Some((start, _)) => start.min(col),
+                    None => col,
+                }
+            }
+            _ => keyword_start(line, col),
+        })
+    }
+
+    fn complete_func(&self, kind: CompletionKind) -> Option<CompleteFunc> {
+        match kind {
+            CompletionKind::User => self.sources.user_func,
+            _ => self.sources.omni_func,
+        }
+    }
+
+    /// Keywords of the current buffer in search order, starting at the
+    /// cursor and wrapping around the end of the buffer
+    fn scan_current_buffer(&self, collector: &mut MatchCollector, start_col: usize) {
+        let sources = &self.sources;
+        let cursor = sources.cursor_line;
+        let mut words = Vec::new();
+        for (idx, line) in sources.lines.iter().enumerate() {
+            for (col, word) in keywords(line) {
+                if idx == cursor && col == start_col {
+                    continue;
+                }
+                let after = idx > cursor || (idx == cursor && col > start_col);
+                words.push((after, word));
+            }
+        }
+        let (after, before): (Vec<_>, Vec<_>) = words.into_iter().partition(|(after, _)| *after);
+        let ordered: Vec<&str> = if sources.backward {
+            before
+                .into_iter()
+                .rev()
+                .chain(after.into_iter().rev())
+                .map(|(_, word)| word)
+                .collect()
+        } else {
+            after
+                .into_iter()
+                .chain(before)
+                .map(|(_, word)| word)
+                .collect()
+        };
+        for word in ordered {
+            collector.add(CompletionItem::new(word));
+        }
+    }
+
+    fn scan_keywords(&self, collector: &mut MatchCollector, start_col: usize) {
+        let sources = &self.sources;
+        for source in &sources.complete {
+            match source {
+                CompleteSource::CurrentBuffer => self.scan_current_buffer(collector, start_col),
+                CompleteSource::Windows
+                | CompleteSource::LoadedBuffers
+                | CompleteSource::UnloadedBuffers => {
+                    for buffer in sources.buffers.iter().filter(|b| &b.source == source) {
+                        for line in &buffer.lines {
+                            collector.add_keywords(line, Some(&buffer.name));
+                        }
+                    }
+                }
+                CompleteSource::Tags => {
+                    for item in read_tags(&sources.tags, collector) {
+                        collector.add(item);
+                    }
+                }
+                CompleteSource::Includes => scan_files(&sources.includes, collector),
+                CompleteSource::Dictionary(Some(file)) | CompleteSource::Thesaurus(Some(file)) => {
+                    scan_files(&[PathBuf::from(file)], collector)
+                }
+                CompleteSource::Dictionary(None) => scan_files(&sources.dictionary, collector),
+                CompleteSource::Thesaurus(None) => scan_files(&sources.thesaurus, collector),
+                CompleteSource::Spell => scan_files(&sources.spell, collector),
+            }
+        }
+    }
+
+    fn complete_lines(&self, base: &str) -> Vec<CompletionItem> {
+        let sources = &self.sources;
+        let mut collector = MatchCollector::new(base, sources.ignore_case);
+        let cursor = sources.cursor_line;
+        let count = sources.lines.len();
+        // Whole lines are searched backwards from the cursor line
+        for offset in 1..count {
+            let idx = (cursor + count - offset) % count;
+            let line = sources.lines[idx].trim_start();
+            if !line.is_empty() {
+                collector.add(CompletionItem::new(line));
+            }
+        }
+        collector.into_items()
+    }
+
+    fn complete_files(&self, base: &str) -> Vec<CompletionItem> {
+        let (dir_part, prefix) = match base.rfind('/') {
+            Some(idx) => (&base[..=idx], &base[idx + 1..]),
+            None => ("", base),
+        };
+        let dir = if dir_part.starts_with('/') {
+            PathBuf::from(dir_part)
+        } else if let Some(rest) = dir_part.strip_prefix("~/") {
+            std::env::var_os("HOME")
+                .map(PathBuf::from)
+                .unwrap_or_default()
+                .join(rest)
+        } else {
+            self.sources.directory.join(dir_part)
+        };
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            return Vec::new();
+        };
+        let mut names: Vec<(String, bool)> = entries
+            .filter_map(Result::ok)
+            .map(|entry| {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                let is_dir = entry.file_type().is_ok_and(|kind| kind.is_dir());
+                (name, is_dir)
+            })
+            .filter(|(name, _)| prefix.starts_with('.') || !name.starts_with('.'))
+            .collect();
+        names.sort();
+        let mut collector = MatchCollector::new(base, self.sources.ignore_case);
+        for (name, is_dir) in names {
+            let mut word = format!("{}{}", dir_part, name);
+            if is_dir {
+                word.push('/');
+            }
+            collector.add(CompletionItem::new(word));
+        }
+        collector.into_items()
+    }
+
+    fn complete_thesaurus(&self, base: &str) -> Vec<CompletionItem> {
+        let probe = MatchCollector::new(base, self.sources.ignore_case);
+        let mut collector = MatchCollector::new("", false);
+        for line in read_lines(&self.sources.thesaurus) {
+            let words: Vec<&str> = line
+                .split(|c: char| !is_keyword_char(c))
+                .filter(|word| !word.is_empty())
+                .collect();
+            if words.iter().any(|word| probe.matches(word)) {
+                for word in words.into_iter().filter(|word| *word != base) {
+                    collector.add(CompletionItem::new(word));
+                }
+            }
+        }
+        collector.into_items()
+    }
+
+    fn complete_defines(&self, base: &str) -> Vec<CompletionItem> {
+        let mut collector = MatchCollector::new(base, self.sources.ignore_case);
+        let included = read_lines(&self.sources.includes);
+        for line in self.sources.lines.iter().chain(included.iter()) {
+            let Some(rest) = line.trim_start().strip_prefix('#') else {
+                continue;
+            };
+            let Some(rest) = rest.trim_start().strip_prefix("define") else {
+                continue;
+            };
+            if let Some((_, name)) = keywords(rest).into_iter().next() {
+                collector.add(CompletionItem::new(name));
+            }
+        }
+        collector.into_items()
+    }
+
+    fn complete_spelling(&self, base: &str) -> Vec<CompletionItem> {
+        let mut files = self.sources.spell.clone();
+        files.extend(self.sources.dictionary.iter().cloned());
+        let lower = base.to_lowercase();
+        let mut seen = HashSet::new();
+        let mut scored: Vec<(usize, String)> = Vec::new();
+        for line in read_lines(&files) {
+            for word in line.split(|c: char| !is_keyword_char(c)) {
+                if word.is_empty() || word == base || !seen.insert(word.to_string()) {
+                    continue;
+                }
+                let distance = edit_distance(&lower, &word.to_lowercase());
+                if distance <= 2 {
+                    scored.push((distance, word.to_string()));
+                }
+            }
+        }
+        scored.sort_by_key(|(distance, _)| *distance);
+        scored
+            .into_iter()
+            .map(|(_, word)| CompletionItem::new(word))
+            .collect()
+    }
+}
+
+impl CompletionEngine for TuiCompletionEngine {
+    fn complete(
+        &self,
+        kind: CompletionKind,
+        line: &str,
+        col: usize,
+    ) -> VimResult<Vec<CompletionItem>> {
+        let col = col.min(line.len());
+        let start = self.find_start(kind, line, col)?;
+        let base = &line[start..col];
+        let sources = &self.sources;
+        let items = match kind {
+            CompletionKind::Keyword | CompletionKind::Buffer => {
+                let mut collector = MatchCollector::new(base, sources.ignore_case);
+                self.scan_keywords(&mut collector, start);
+                collector.into_items()
+            }
+            CompletionKind::Line => self.complete_lines(base),
+            CompletionKind::File => self.complete_files(base),
+            CompletionKind::Dictionary => {
+                let mut collector = MatchCollector::new(base, sources.ignore_case);
+                scan_files(&sources.dictionary, &mut collector);
+                collector.into_items()
+            }
+            CompletionKind::Thesaurus => self.complete_thesaurus(base),
+            CompletionKind::Tag => {
+                let mut collector = MatchCollector::new(base, sources.ignore_case);
+                read_tags(&sources.tags, &collector)
+                    .into_iter()
+                    .filter(|item| collector.add(item.clone()))
+                    .collect()
+            }
+            CompletionKind::Include => {
+                let mut collector = MatchCollector::new(base, sources.ignore_case);
+                self.scan_current_buffer(&mut collector, start);
+                scan_files(&sources.includes, &mut collector);
+                collector.into_items()
+            }
+            CompletionKind::Define => self.complete_defines(base),
+            CompletionKind::User | CompletionKind::Omni => self
+                .complete_func(kind)
+                .and_then(|func| func(line, col))
+                .map(|(_, items)| items)
+                .unwrap_or_default(),
+            CompletionKind::Spelling => self.complete_spelling(base),
+            CompletionKind::Command => Vec::new(),
+        };
+        Ok(items)
+    }
+
+    fn state(&self) -> &CompletionState {
+        &self.state
+    }
+
+    fn start(&mut self, kind: CompletionKind, line: &str, col: usize) -> VimResult<()> {
+        let col = col.min(line.len());
+        let start_col = self.find_start(kind, line, col)?;
+        let mut items = self.complete(kind, line, col)?;
+        let backward = self.sources.backward || kind == CompletionKind::Line;
+        // Matches found searching backwards are listed nearest last, so
+        // that `Ctrl-P` moves up the menu
+        if backward {
+            items.reverse();
+        }
+        let selected = match items.len() {
+            0 => None,
+            len if backward => Some(len - 1),
+            _ => Some(0),
+        };
+        self.matches = items.clone();
+        self.state = CompletionState {
+            items,
+            selected,
+            original: line[start_col..col].to_string(),
+            start_col,
+            kind: (selected.is_some()).then_some(kind),
+        };
+        Ok(())
+    }
+
+    /// Select the next match; after the last match the original text is
+    /// selected again (`None`)
+    fn select_next(&mut self) -> Option<&CompletionItem> {
+        let len = self.state.items.len();
+        self.state.selected = match self.state.selected {
+            None if len > 0 => Some(0),
+            Some(idx) if idx + 1 < len => Some(idx + 1),
+            _ => None,
+        };
+        self.selected_item()
+    }
+
+    /// Select the previous match; before the first match the original text
+    /// is selected again (`None`)
+    fn select_prev(&mut self) -> Option<&CompletionItem> {
+        let len = self.state.items.len();
+        self.state.selected = match self.state.selected {
+            None if len > 0 => Some(len - 1),
+            Some(idx) if idx > 0 => Some(idx - 1),
+            _ => None,
+        };
+        self.selected_item()
+    }
+
+    fn accept(&mut self) -> Option<CompletionItem> {
+        let selected = self.selected_item().cloned();
+        self.cancel();
+        selected
+    }
+
+    fn cancel(&mut self) {
+        self.state.items.clear();
+        self.state.selected = None;
+        self.state.kind = None;
+        self.matches.clear();
+    }
+
+    fn is_active(&self) -> bool {
+        self.state.kind.is_some()
+    }
+}
+
+/// Keywords of `line` with their byte columns
+fn keywords(line: &str) -> Vec<(usize, &str)> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (idx, c) in line.char_indices() {
+        match (is_keyword_char(c), start) {
+            (true, None) => start = Some(idx),
+            (false, Some(begin)) => {
+                words.push((begin, &line[begin..idx]));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(begin) = start {
+        words.push((begin, &line[begin..]));
+    }
+    words
+}
+
+/// Characters of a file name (the default 'isfname')
+fn is_fname_char(c: char) -> bool {
+    c.is_alphanumeric() || "/.-_+,#$%~=".contains(c)
+}
+
+fn read_lines(files: &[PathBuf]) -> Vec<String> {
+    files
+        .iter()
+        .filter_map(|file| std::fs::read_to_string(file).ok())
+        .flat_map(|text| text.lines().map(str::to_string).collect::<Vec<_>>())
+        .collect()
+}
+
+fn scan_files(files: &[PathBuf], collector: &mut MatchCollector) {
+    for file in files {
+        let Ok(text) = std::fs::read_to_string(file) else {
+            continue;
+        };
+        let name = file_tail(file);
+        for line in text.lines() {
+            collector.add_keywords(line, Some(&name));
+        }
+    }
+}
+
+/// Tags matching the collector's base, from ctags-format files
+fn read_tags(files: &[PathBuf], collector: &MatchCollector) -> Vec<CompletionItem> {
+    let mut items = Vec::new();
+    for line in read_lines(files) {
+        if line.starts_with("!_TAG_") {
+            continue;
+        }
+        let mut fields = line.split('\t');
+        let (Some(name), Some(file)) = (fields.next(), fields.next()) else {
+            continue;
+        };
+        if !collector.matches(name) {
+            continue;
+        }
+        let kind = fields
+            .skip(1)
+            .map(|field| field.strip_prefix("kind:").unwrap_or(field))
+            .find(|field| field.chars().count() == 1);
+        let mut item = CompletionItem::new(name).with_menu(file);
+        item.kind = kind.map(str::to_string);
+        items.push(item);
+    }
+    items
+}
+
+fn file_tail(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Levenshtein distance between two words
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut row = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != *cb);
+            row.push((prev[j] + cost).min(prev[j + 1] + 1).min(row[j] + 1));
+        }
+        prev = row;
+    }
+    prev[b.len()]
+}
+
+// ============================================================================
+// Editor Integration
+// ============================================================================
+
+/// Progress of the current completion in Insert mode
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CompletionSession {
+    /// `Ctrl-X` was typed and a sub-mode key is expected
+    ctrl_x: bool,
+    /// The key that started completion; typing it again selects a match
+    key: Option<Key>,
+    /// Matches were searched backwards
+    backward: bool,
+    /// Only the current buffer is searched (`Ctrl-X Ctrl-N`)
+    local: bool,
+    /// Text the user typed before completion started
+    typed: String,
+    /// Text currently in the buffer between the start column and the cursor
+    text: String,
+    /// The match completion ended with (`v:completed_item`)
+    completed_item: Option<CompletionItem>,
+    /// First match shown in the popup menu
+    pum_first: usize,
+    /// Rows of the popup menu when it was last drawn
+    pum_height: usize,
+}
+
+/// How `Ctrl-X {key}` completes: kind, search backwards, current buffer only
+fn ctrl_x_submode(key: Key) -> Option<(CompletionKind, bool, bool)> {
+    Some(match key {
+        Key::Ctrl('n') => (CompletionKind::Keyword, false, true),
+        Key::Ctrl('p') => (CompletionKind::Keyword, true, true),
+        Key::Ctrl('l') => (CompletionKind::Line, true, false),
+        Key::Ctrl('f') => (CompletionKind::File, false, false),
+        Key::Ctrl('k') => (CompletionKind::Dictionary, false, false),
+        Key::Ctrl('t') => (CompletionKind::Thesaurus, false, false),
+        Key::Ctrl('i') | Key::Tab => (CompletionKind::Include, false, false),
+        Key::Ctrl(']') => (CompletionKind::Tag, false, false),
+        Key::Ctrl('d') => (CompletionKind::Define, false, false),
+        Key::Ctrl('u') => (CompletionKind::User, false, false),
+        Key::Ctrl('o') => (CompletionKind::Omni, false, false),
+        Key::Char('s') | Key::Ctrl('s') => (CompletionKind::Spelling, false, false),
+        _ => return None,
+    })
+}
+
+/// The mode message Vim shows for a completion kind
+fn mode_message(kind: CompletionKind, local: bool) -> &'static str {
+    match kind {
+        CompletionKind::Keyword if local => "Keyword Local completion (^N^P)",
+        CompletionKind::Keyword | CompletionKind::Buffer => "Keyword completion (^N^P)",
+        CompletionKind::Line => "Whole line completion (^L^N^P)",
+        CompletionKind::File => "File name completion (^F^N^P)",
+        CompletionKind::Dictionary => "Dictionary completion (^K^N^P)",
+        CompletionKind::Thesaurus => "Thesaurus completion (^T^N^P)",
+        CompletionKind::Tag => "Tag completion (^]^N^P)",
+        CompletionKind::Include => "Path pattern completion (^N^P)",
+        CompletionKind::Define => "Definition completion (^D^N^P)",
+        CompletionKind::Command => "Command-line completion (^V^N^P)",
+        CompletionKind::User => "User defined completion (^U^N^P)",
+        CompletionKind::Omni => "Omni completion (^O^N^P)",
+        CompletionKind::Spelling => "Spelling suggestion (s^N^P)",
+    }
+}
+
+impl Editor {
+    /// Register a function that 'completefunc' or 'omnifunc' can name
+    pub fn register_complete_func(&mut self, name: &str, func: CompleteFunc) {
+        self.complete_funcs.insert(name.to_string(), func);
+    }
+
+    /// Whether Insert mode completion is in progress
+    pub fn completion_active(&self) -> bool {
+        self.completion.is_active()
+    }
+
+    /// Whether the completion popup menu is shown ('completeopt')
+    pub fn pum_visible(&self) -> bool {
+        self.completion.is_active()
+            && CompleteOpt::parse(self.options.string("completeopt"))
+                .shows_menu(self.completion.state().items.len())
+    }
+
+    /// Place the popup menu for the current completion at `anchor`.  The
+    /// height and scroll position are remembered for paging.
+    pub fn popup_menu(&mut self, anchor: PumAnchor) -> Option<PopupMenu> {
+        if !self.pum_visible() {
+            return None;
+        }
+        let opt = CompleteOpt::parse(self.options.string("completeopt"));
+        let limits = PumLimits {
+            max_height: self.options.number("pumheight").max(0) as usize,
+            min_width: self.options.number("pumwidth").max(0) as usize,
+        };
+        let state = self.completion.state();
+        let menu = PopupMenu::layout(
+            &state.items,
+            state.selected,
+            self.completion_session.pum_first,
+            anchor,
+            limits,
+            opt.preview || opt.popup,
+        )?;
+        self.completion_session.pum_first = menu.first;
+        self.completion_session.pum_height = menu.height();
+        Some(menu)
+    }
+
+    /// The match the last completion ended with (`v:completed_item`)
+    pub fn completed_item(&self) -> Option<&CompletionItem> {
+        self.completion_session.completed_item.as_ref()
+    }
+
+    /// Handle completion keys in Insert mode.  Returns `false` when the key
+    /// is not a completion key and should be handled as usual.
+    pub(crate) fn completion_key(&mut self, key: Key) -> VimResult<bool> {
+        if self.completion_session.ctrl_x {
+            self.completion_session.ctrl_x = false;
+            if let Some((kind, backward, local)) = ctrl_x_submode(key) {
+                self.start_completion(kind, backward, local, key)?;
+                return Ok(true);
+            }
+            // Scrolling with Ctrl-X Ctrl-E and Ctrl-X Ctrl-Y is not
+            // supported; other keys end Ctrl-X mode and are used as usual
+            return Ok(matches!(key, Key::Ctrl('e') | Key::Ctrl('y')));
+        }
+
+        if self.completion.is_active() {
+            let session = &self.completion_session;
+            let untouched = session.text == self.completion.state().original;
+            let repeat = session.key == Some(key) && !matches!(key, Key::Ctrl('n' | 'p'));
+            match key {
+                Key::Ctrl('n') => self.select_match(true)?,
+                Key::Ctrl('p') => self.select_match(false)?,
+                _ if repeat => self.select_match(!self.completion_session.backward)?,
+                Key::Ctrl('y') => self.finish_completion(true, false)?,
+                Key::Ctrl('e') => self.finish_completion(false, true)?,
+                Key::Down | Key::Up | Key::PageDown | Key::PageUp if self.pum_visible() => {
+                    self.move_pum_selection(key)?
+                }
+                // Enter inserts a match selected with the arrow keys;
+                // otherwise it ends completion and starts a new line
+                Key::Enter
+                    if self
+                        .completion
+                        .selected_item()
+                        .is_some_and(|item| item.word != self.completion_session.text) =>
+                {
+                    self.finish_completion(true, false)?
+                }
+                Key::Ctrl('x') => {
+                    self.finish_completion(false, false)?;
+                    self.enter_ctrl_x_mode();
+                }
+                Key::Char(c) if untouched && is_keyword_char(c) => {
+                    let mut base = self.completion_session.text.clone();
+                    base.push(c);
+                    self.narrow_completion(base)?;
+                }
+                Key::Backspace if untouched && !self.completion_session.text.is_empty() => {
+                    let mut base = self.completion_session.text.clone();
+                    base.pop();
+                    self.narrow_completion(base)?;
+                }
+                _ => {
+                    self.finish_completion(false, false)?;
+                    return Ok(false);
+                }
+            }
+            return Ok(true);
+        }
+
+        match key {
+            Key::Ctrl('n') => self.start_completion(CompletionKind::Keyword, false, false, key)?,
+            Key::Ctrl('p') => self.start_completion(CompletionKind::Keyword, true, false, key)?,
+            Key::Ctrl('x') => self.enter_ctrl_x_mode(),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    fn enter_ctrl_x_mode(&mut self) {
+        self.completion_session.ctrl_x = true;
+        self.message = Some("-- ^X mode (^]^D^E^F^I^K^L^N^O^Ps^U^V^Y)".to_string());
+    }
+
+    fn start_completion(
+        &mut self,
+        kind: CompletionKind,
+        backward: bool,
+        local: bool,
+        key: Key,
+    ) -> VimResult<()> {
+        let sources = self.completion_sources(kind, backward, local)?;
+        self.completion.set_sources(sources);
+        let line = self.current_line();
+        let col = self.cursor.col().min(line.len());
+        self.completion.start(kind, &line, col)?;
+        let title = mode_message(kind, local);
+        if !self.completion.is_active() {
+            self.message = Some(format!("-- {} Pattern not found", title));
+            return Ok(());
+        }
+
+        let state = self.completion.state();
+        let typed = line[state.start_col..col].to_string();
+        self.completion_session = CompletionSession {
+            key: Some(key),
+            backward: backward || kind == CompletionKind::Line,
+            local,
+            typed: typed.clone(),
+            text: typed.clone(),
+            ..Default::default()
+        };
+
+        let opt = CompleteOpt::parse(self.options.string("completeopt"));
+        let longest = longest_common_prefix(&state.items);
+        let text = if opt.longest {
+            self.completion.set_selected(None);
+            if longest.len() > typed.len() {
+                self.completion.set_original(longest.clone());
+                longest
+            } else {
+                typed
+            }
+        } else if opt.noselect {
+            self.completion.set_selected(None);
+            typed
+        } else if opt.noinsert {
+            typed
+        } else {
+            self.completion
+                .selected_item()
+                .map_or(typed, |item| item.word.clone())
+        };
+        self.replace_completion_text(&text)?;
+        self.show_completion_status(title);
+        self.completion_changed()
+    }
+
+    /// Gather the places completion searches from the options and buffers
+    fn completion_sources(
+        &self,
+        kind: CompletionKind,
+        backward: bool,
+        local: bool,
+    ) -> VimResult<CompletionSources> {
+        let split = |name: &str| -> Vec<PathBuf> {
+            self.options
+                .string(name)
+                .split(',')
+                .filter(|item| !item.is_empty())
+                .map(PathBuf::from)
+                .collect()
+        };
+        let complete = if local {
+            vec![CompleteSource::CurrentBuffer]
+        } else {
+            CompleteSource::parse_list(self.options.string("complete"))
+        };
+        let func = |option: &str| -> VimResult<Option<CompleteFunc>> {
+            let name = self.options.string(option);
+            if name.is_empty() {
+                return Err(VimError::Error(
+                    764,
+                    format!("Option '{}' is not set", option),
+                ));
+            }
+            self.complete_funcs
+                .get(name)
+                .copied()
+                .map(Some)
+                .ok_or_else(|| VimError::Error(117, format!("Unknown function: {}", name)))
+        };
+        let user_func = match kind {
+            CompletionKind::User => func("completefunc")?,
+            _ => None,
+        };
+        let omni_func = match kind {
+            CompletionKind::Omni => func("omnifunc")?,
+            _ => None,
+        };
+        let needs_includes = matches!(kind, CompletionKind::Include | CompletionKind::Define)
+            || (kind == CompletionKind::Keyword && complete.contains(&CompleteSource::Includes));
+
+        Ok(CompletionSources {
+            lines: self
+                .buffers
+                .current()
+                .get_lines(0, -1, false)
+                .unwrap_or_default(),
+            cursor_line: self.cursor.line().to_zero_indexed(),
+            backward,
+            ignore_case: self.options.flag("ignorecase"),
+            buffers: self.completion_buffers(&complete),
+            complete,
+            dictionary: split("dictionary"),
+            thesaurus: split("thesaurus"),
+            spell: split("spellfile"),
+            tags: self.tag_files(),
+            includes: if needs_includes {
+                self.included_files()
+            } else {
+                Vec::new()
+            },
+            directory: PathBuf::from("."),
+            user_func,
+            omni_func,
+        })
+    }
+
+    /// Other buffers scanned for the `w`, `b` and `u` items of 'complete'.
+    /// The editor shows a single window, so `w` finds nothing.
+    fn completion_buffers(&self, complete: &[CompleteSource]) -> Vec<SourceBuffer> {
+        let current = self.buffers.current().handle();
+        let mut buffers = Vec::new();
+        for handle in self.buffers.list_listed() {
+            if handle == current {
+                continue;
+            }
+            let Some(buffer) = self.buffers.get(handle) else {
+                continue;
+            };
+            let loaded = buffer.load_state() == BufferLoadState::Loaded;
+            let source = if loaded {
+                CompleteSource::LoadedBuffers
+            } else {
+                CompleteSource::UnloadedBuffers
+            };
+            if !complete.contains(&source) {
+                continue;
+            }
+            let lines = if loaded {
+                buffer.get_lines(0, -1, false).unwrap_or_default()
+            } else {
+                std::fs::read_to_string(buffer.name())
+                    .map(|text| text.lines().map(str::to_string).collect())
+                    .unwrap_or_default()
+            };
+            buffers.push(SourceBuffer {
+                source,
+                name: buffer.name().to_string(),
+                lines,
+            });
+        }
+        buffers
+    }
+
+    /// Directory of the current buffer's file, for `./` in file options
+    fn buffer_directory(&self) -> PathBuf {
+        Path::new(self.buffers.current().name())
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .map_or_else(|| PathBuf::from("."), Path::to_path_buf)
+    }
+
+    /// Existing files named by the 'tags' option
+    fn tag_files(&self) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        for item in self.options.string("tags").split(',') {
+            let path = match item.strip_prefix("./") {
+                Some(rest) => self.buffer_directory().join(rest),
+                None if item.is_empty() => continue,
+                None => PathBuf::from(item),
+            };
+            if path.is_file() && !files.contains(&path) {
+                files.push(path);
+            }
+        }
+        files
+    }
+
+    /// Files included by the current buffer with C-style `#include`
+    /// lines, found along 'path', recursively
+    fn included_files(&self) -> Vec<PathBuf> {
+        let mut files: Vec<PathBuf> = Vec::new();
+        let mut queue = vec![self
+            .buffers
+            .current()
+            .get_lines(0, -1, false)
+            .unwrap_or_default()];
+        while let Some(lines) = queue.pop() {
+            for line in &lines {
+                let Some(name) = include_target(line) else {
+                    continue;
+                };
+                let Some(path) = self.find_in_path(name) else {
+                    continue;
+                };
+                if files.contains(&path) {
+                    continue;
+                }
+                if let Ok(text) = std::fs::read_to_string(&path) {
+                    queue.push(text.lines().map(str::to_string).collect());
+                }
+                files.push(path);
+            }
+        }
+        files
+    }
+
+    /// Find `name` in the directories of the 'path' option
+    fn find_in_path(&self, name: &str) -> Option<PathBuf> {
+        if Path::new(name).is_absolute() {
+            return Some(PathBuf::from(name)).filter(|path| path.is_file());
+        }
+        self.options
+            .string("path")
+            .split(',')
+            .map(|dir| match dir {
+                "." => self.buffer_directory(),
+                "" => PathBuf::from("."),
+                dir => PathBuf::from(dir),
+            })
+            .map(|dir| dir.join(name))
+            .find(|path| path.is_file())
+    }
+
+    fn select_match(&mut self, forward: bool) -> VimResult<()> {
+        if forward {
+            self.completion.select_next();
+        } else {
+            self.completion.select_prev();
+        }
+        let text = match self.completion.selected_item() {
+            Some(item) => item.word.clone(),
+            None => self.completion.state().original.clone(),
+        };
+        self.replace_completion_text(&text)?;
+        self.selection_changed()
+    }
+
+    /// Move the popup menu selection without inserting the match:
+    /// `<Down>`/`<Up>` by one, `<PageDown>`/`<PageUp>` by a page
+    fn move_pum_selection(&mut self, key: Key) -> VimResult<()> {
+        let state = self.completion.state();
+        let len = state.items.len();
+        let page = match self.completion_session.pum_height {
+            0 => len,
+            height => height,
+        };
+        match (key, state.selected) {
+            (Key::Down, _) => {
+                self.completion.select_next();
+            }
+            (Key::Up, _) => {
+                self.completion.select_prev();
+            }
+            (Key::PageDown, selected) => {
+                let idx = selected.map_or(page - 1, |idx| idx + page);
+                self.completion.set_selected(Some(idx.min(len - 1)));
+            }
+            (_, selected) => {
+                let idx = selected.unwrap_or(len).saturating_sub(page);
+                self.completion.set_selected(Some(idx));
+            }
+        }
+        self.selection_changed()
+    }
+
+    /// Scroll the popup menu to the new selection and report it
+    fn selection_changed(&mut self) -> VimResult<()> {
+        let state = self.completion.state();
+        let session = &mut self.completion_session;
+        if session.pum_height > 0 {
+            session.pum_first = scroll_to(
+                session.pum_first,
+                state.selected,
+                session.pum_height,
+                state.items.len(),
+            );
+        }
+        let kind = state.kind.unwrap_or(CompletionKind::Keyword);
+        self.show_completion_status(mode_message(kind, self.completion_session.local));
+        self.completion_changed()
+    }
+
+    /// The user typed or deleted a character while no match was inserted
+    fn narrow_completion(&mut self, base: String) -> VimResult<()> {
+        self.replace_completion_text(&base)?;
+        self.completion_session.typed = base.clone();
+        self.completion_session.pum_first = 0;
+        self.completion.refilter(&base);
+        if self.completion.state().items.is_empty() {
+            return self.finish_completion(false, false);
+        }
+        let opt = CompleteOpt::parse(self.options.string("completeopt"));
+        if opt.noinsert && !opt.noselect {
+            let first = if self.completion_session.backward {
+                self.completion.state().items.len() - 1
+            } else {
+                0
+            };
+            self.completion.set_selected(Some(first));
+        }
+        self.completion_changed()
+    }
+
+    /// End completion.  `accept` inserts the selected match (`Ctrl-Y`),
+    /// `restore` puts back the typed text (`Ctrl-E`); otherwise the text
+    /// in the buffer is kept.
+    fn finish_completion(&mut self, accept: bool, restore: bool) -> VimResult<()> {
+        let selected = self.completion.selected_item().cloned();
+        if restore {
+            let typed = self.completion_session.typed.clone();
+            self.replace_completion_text(&typed)?;
+        } else if accept {
+            if let Some(item) = &selected {
+                self.replace_completion_text(&item.word)?;
+            }
+        }
+        let text = &self.completion_session.text;
+        let completed = selected.filter(|item| !restore && item.word == *text);
+        self.completion.cancel();
+        self.completion_session = CompletionSession {
+            completed_item: completed.clone(),
+            ..Default::default()
+        };
+        self.message = None;
+
+        let mut data = self.autocmd_data();
+        data.data = completed.map(|item| item.word);
+        self.apply_autocmds(AutocmdEvent::CompleteDonePre, &data)?;
+        self.apply_autocmds(AutocmdEvent::CompleteDone, &data)
+    }
+
+    /// Fire CompleteChanged when the popup menu shows a new selection
+    fn completion_changed(&mut self) -> VimResult<()> {
+        if !self.pum_visible() {
+            return Ok(());
+        }
+        let mut data = self.autocmd_data();
+        data.data = self
+            .completion
+            .selected_item()
+            .map(|item| item.word.clone());
+        self.apply_autocmds(AutocmdEvent::CompleteChanged, &data)
+    }
+
+    fn show_completion_status(&mut self, title: &str) {
+        let state = self.completion.state();
+        let count = state.items.len();
+        let status = match state.selected {
+            _ if count == 1 && state.selected.is_some() => "The only match".to_string(),
+            Some(idx) if self.completion_session.backward => {
+                format!("match {} of {}", count - idx, count)
+            }
+            Some(idx) => format!("match {} of {}", idx + 1, count),
+            None => "Back at original".to_string(),
+        };
+        self.message = Some(format!("-- {} {}", title, status));
+    }
+
+    /// Replace the text between the completion start column and the cursor
+    fn replace_completion_text(&mut self, text: &str) -> VimResult<()> {
+        let line = self.current_line();
+        let start = self.completion.state().start_col.min(line.len());
+        let col = self.cursor.col().clamp(start, line.len());
+        let old = &line[start..col];
+        if old != text {
+            let new_line = format!("{}{}{}", &line[..start], text, &line[col..]);
+            let idx = self.cursor.line().to_zero_indexed() as i64;
+            self.buffers
+                .current_mut()
+                .set_lines(idx, idx + 1, false, vec![new_line])?;
+            self.sync_cursor_with_buffer();
+            if let Some(inserted) = self.current_insert.as_mut() {
+                if inserted.ends_with(old) {
+                    inserted.truncate(inserted.len() - old.len());
+                    inserted.push_str(text);
+                } else if let Some(rest) = text.strip_prefix(old) {
+                    inserted.push_str(rest);
+                }
+            }
+        }
+        let ctx = self.cursor_context();
+        self.cursor.set_col(start + text.len(), &ctx)?;
+        self.marks.record_change(self.cursor.position());
+        self.completion_session.text = text.to_string();
+        Ok(())
+    }
+}
+
+/// The file named by a C-style `#include` line
+fn include_target(line: &str) -> Option<&str> {
+    let rest = line.trim_start().strip_prefix('#')?;
+    let rest = rest.trim_start().strip_prefix("include")?.trim_start();
+    let close = match rest.chars().next()? {
+        '"' => '"',
+        '<' => '>',
+        _ => return None,
+    };
+    let rest = &rest[1..];
+    rest.find(close).map(|end| &rest[..end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn engine(lines: &[&str], cursor_line: usize, backward: bool) -> TuiCompletionEngine {
+        let mut engine = TuiCompletionEngine::new();
+        engine.set_sources(CompletionSources {
+            lines: lines.iter().map(|line| line.to_string()).collect(),
+            cursor_line,
+            backward,
+            complete: vec![CompleteSource::CurrentBuffer],
+            ..Default::default()
+        });
+        engine
+    }
+
+    fn words(engine: &TuiCompletionEngine) -> Vec<&str> {
+        engine
+            .state()
+            .items
+            .iter()
+            .map(|item| item.word.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_keywords_scanned_from_cursor() {
+        let lines = ["foo_a", "fo", "foo_b foo_a"];
+        let mut forward = engine(&lines, 1, false);
+        forward.start(CompletionKind::Keyword, "fo", 2).unwrap();
+        assert_eq!(words(&forward), vec!["foo_b", "foo_a"]);
+        assert_eq!(forward.selected_item().unwrap().word, "foo_b");
+
+        let mut backward = engine(&lines, 1, true);
+        backward.start(CompletionKind::Keyword, "fo", 2).unwrap();
+        assert_eq!(words(&backward), vec!["foo_b", "foo_a"]);
+        assert_eq!(backward.selected_item().unwrap().word, "foo_a");
+    }
+
+    #[test]
+    fn test_selection_cycles_through_original() {
+        let mut engine = engine(&["alpha", "al", "alto"], 1, false);
+        engine.start(CompletionKind::Keyword, "al", 2).unwrap();
+        assert_eq!(engine.selected_item().unwrap().word, "alto");
+        assert_eq!(engine.select_next().unwrap().word, "alpha");
+        assert!(engine.select_next().is_none());
+        assert_eq!(engine.select_next().unwrap().word, "alto");
+        assert!(engine.select_prev().is_none());
+    }
+
+    #[test]
+    fn test_include_target_and_edit_distance() {
+        assert_eq!(include_target("#include \"foo.h\""), Some("foo.h"));
+        assert_eq!(
+            include_target("  #  include <bar/baz.h>"),
+            Some("bar/baz.h")
+        );
+        assert_eq!(include_target("int x;"), None);
+        assert_eq!(edit_distance("teh", "the"), 2);
+        assert_eq!(edit_distance("wrod", "word"), 2);
+        assert_eq!(edit_distance("speling", "spelling"), 1);
+    }
+}
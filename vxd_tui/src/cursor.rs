@@ -3,6 +3,7 @@
 //! This module provides a concrete implementation of cursor management.
 
 use vxd::cursor::{Cursor, CursorContext, CursorManager, CursorPosition, CursorStyle, CursorWant};
+use vxd::display::DisplayOptions;
 use vxd::types::{LineNr, VimResult};
 
 /// Concrete cursor implementation
@@ -16,6 +17,10 @@ pub struct TuiCursor {
     style: CursorStyle,
     /// Line length getter (for validation)
     line_lengths: Vec<usize>,
+    /// The buffer lines, for virtual columns
+    lines: Vec<String>,
+    /// The options for the cells characters take
+    display: DisplayOptions,
 }
 
 impl TuiCursor {
@@ -26,12 +31,15 @@ impl TuiCursor {
             curswant: CursorWant::default(),
             style: CursorStyle::default(),
             line_lengths: vec![0], // At least one empty line
+            lines: Vec::new(),
+            display: DisplayOptions::default(),
         }
     }
 
     /// Set line lengths for validation
     pub fn set_line_lengths(&mut self, lengths: Vec<usize>) {
         self.line_lengths = lengths;
+        self.lines.clear();
         if self.line_lengths.is_empty() {
             self.line_lengths.push(0);
         }
@@ -43,6 +51,12 @@ impl TuiCursor {
         if self.line_lengths.is_empty() {
             self.line_lengths.push(0);
         }
+        self.lines = lines.to_vec();
+    }
+
+    /// Set the options for virtual columns
+    pub fn set_display(&mut self, display: DisplayOptions) {
+        self.display = display;
     }
 
     /// Get line length at given line number
@@ -120,18 +134,21 @@ impl Cursor for TuiCursor {
     }
 
     fn virtcol(&self) -> usize {
-        // For now, treat virtual column as same as byte column
-        // A full implementation would account for tabs and wide chars
-        self.position.col + self.position.coladd
+        self.virtcol_at(self.position)
     }
 
     fn virtcol_at(&self, pos: CursorPosition) -> usize {
-        pos.col + pos.coladd
+        match self.lines.get(pos.line.to_zero_indexed()) {
+            Some(text) => self.display.virtcol(text, pos.col).0 + pos.coladd,
+            None => pos.col + pos.coladd,
+        }
     }
 
-    fn virtcol_to_col(&self, _line: LineNr, vcol: usize) -> usize {
-        // Simple implementation: virtual col = byte col
-        vcol
+    fn virtcol_to_col(&self, line: LineNr, vcol: usize) -> usize {
+        match self.lines.get(line.to_zero_indexed()) {
+            Some(text) => self.display.vcol_to_col(text, vcol),
+            None => vcol,
+        }
     }
 
     fn check_cursor(&mut self, ctx: &CursorContext) {
@@ -0,0 +1,105 @@
+//! Digraph entry and character inspection.
+//!
+//! In Insert mode a digraph is typed as `Ctrl-K {char1}{char2}`, or as
+//! `{char1}<BS>{char2}` with the 'digraph' option set; Command-line mode
+//! handles its `Ctrl-K` in [`crate::cmdline`].  `:digraphs` lists and
+//! defines digraphs, and `ga`, `g8` and `:ascii` show the character under
+//! the cursor.
+
+use vxd::cursor::Cursor;
+use vxd::digraphs::{is_composing, utf8_bytes};
+use vxd::types::VimResult;
+
+use crate::editor::Editor;
+use crate::key::Key;
+
+/// The width of the screen the listing of `:digraphs` is made for
+const LIST_COLUMNS: usize = 80;
+
+/// A digraph being typed in Insert mode
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum DigraphState {
+    #[default]
+    None,
+    /// After `Ctrl-K`, with the first character once typed
+    CtrlK(Option<char>),
+    /// A character was typed, with 'digraph' set
+    Typed(char),
+    /// The character typed was deleted with `<BS>`; the next one makes a
+    /// digraph with it
+    Backspaced(char),
+}
+
+impl Editor {
+    /// Feed an Insert mode `key` to the digraph being typed.  Returns the
+    /// key when it still has to be handled.
+    pub(crate) fn insert_digraph_key(&mut self, key: Key) -> VimResult<Option<Key>> {
+        let typed = self.options.flag("digraph");
+        match (std::mem::take(&mut self.digraph), key) {
+            (DigraphState::CtrlK(None), Key::Char(first)) => {
+                self.digraph = DigraphState::CtrlK(Some(first));
+                Ok(None)
+            }
+            (DigraphState::CtrlK(Some(first)), Key::Char(second)) => {
+                self.insert_char(self.digraphs.resolve(first, second))?;
+                Ok(None)
+            }
+            // <Esc> only cancels the digraph
+            (DigraphState::CtrlK(_), Key::Escape) => Ok(None),
+            (DigraphState::CtrlK(_), key) => Ok(Some(key)),
+            (_, Key::Ctrl('k')) => {
+                self.digraph = DigraphState::CtrlK(None);
+                Ok(None)
+            }
+            (DigraphState::Backspaced(first), Key::Char(second)) if typed => {
+                self.insert_char(self.digraphs.resolve(first, second))?;
+                Ok(None)
+            }
+            (DigraphState::Typed(first), Key::Backspace) if typed => {
+                self.digraph = DigraphState::Backspaced(first);
+                Ok(Some(key))
+            }
+            (_, Key::Char(c)) if typed => {
+                self.digraph = DigraphState::Typed(c);
+                Ok(Some(key))
+            }
+            (_, key) => Ok(Some(key)),
+        }
+    }
+
+    /// `:digraphs[!]` lists the digraphs, with headers for the groups
+    /// with `!`; `:digraphs {char1}{char2} {number} ..` defines them
+    pub(crate) fn ex_digraphs(&mut self, args: &str, bang: bool) -> VimResult<Option<String>> {
+        if args.trim().is_empty() {
+            return Ok(Some(self.digraphs.listing(bang, LIST_COLUMNS)));
+        }
+        self.digraphs.define(args)?;
+        Ok(None)
+    }
+
+    /// The text `ga` and `:ascii` show for the character under the
+    /// cursor, or `g8` with `bytes`
+    pub(crate) fn describe_cursor_char(&self, bytes: bool) -> String {
+        let line = self.current_line();
+        let text = cursor_char(&line, self.cursor.col());
+        if bytes {
+            utf8_bytes(text)
+        } else {
+            self.digraphs.describe_char(text)
+        }
+    }
+}
+
+/// The character at byte `col` of `line` with the composing characters
+/// after it
+fn cursor_char(line: &str, col: usize) -> &str {
+    let Some(rest) = line.get(col..) else {
+        return "";
+    };
+    let mut chars = rest.char_indices();
+    chars.next();
+    let end = chars
+        .find(|&(_, ch)| !is_composing(ch))
+        .map_or(rest.len(), |(idx, _)| idx);
+    &rest[..end]
+}
@@ -0,0 +1,1097 @@
+//! Key dispatch.
+//!
+//! Translates keys into editor commands for each mode, keeping the state of
+//! a partially typed Normal mode command (count, register, operator and
+//! multi-key prefixes such as `g`, `z` or `f`).
+
+use vxd::buffer::{Buffer, BufferManager};
+use vxd::cmdline::{Cmdline, CmdlineHistory, CmdlineHistoryKind};
+use vxd::commands::CommandExecutor;
+use vxd::cursor::{Cursor, CursorPosition};
+use vxd::folds::FoldManager;
+use vxd::marks::MarkManager;
+use vxd::modes::{Mode, ModeManager, VisualMode};
+use vxd::motions::CharFindMotion;
+use vxd::operators::Operator;
+use vxd::options::OptionManager;
+use vxd::registers::{Register, RegisterBank, RegisterType};
+use vxd::search::SearchEngine;
+use vxd::types::{Direction, LineNr, MotionType, VimError, VimResult};
+
+use crate::cmdline::{literal_char, CmdlinePending};
+use crate::editor::Editor;
+use crate::eval::ExprReturn;
+use crate::exit::handle_zz;
+use crate::key::Key;
+use crate::motion::{first_non_blank, MotionKey};
+use crate::operator::MotionRange;
+
+/// A key that waits for the next key to complete the command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Prefix {
+    /// `"` - register name follows
+    Register,
+    /// `g`
+    G,
+    /// `z`
+    Z,
+    /// `Z`
+    BigZ,
+    /// `q`
+    Q,
+    /// `@` - register name follows
+    At,
+    /// `[` or `]`
+    Bracket(char),
+    /// `f`, `F`, `t` or `T` - target character follows
+    Find(char),
+}
+
+/// State of a partially typed Normal mode command
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PendingCommand {
+    count: Option<usize>,
+    register: Option<char>,
+    operator: Option<(Operator, char)>,
+    operator_count: Option<usize>,
+    prefix: Option<Prefix>,
+}
+
+/// What a key adds to the Normal mode command being typed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CommandKeyKind {
+    /// A digit of the count
+    Digit(usize),
+    /// `"` before a register name
+    RegisterPrefix,
+    /// The register name after `"`
+    RegisterName(char),
+    /// Any other key of the command
+    Other,
+}
+
+/// The count given to a command starting Insert mode: the text typed is
+/// inserted `count` times when Insert mode ends with `<Esc>`
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct InsertRepeat {
+    count: usize,
+    /// `o` and `O`: each copy goes in a new line
+    new_line: bool,
+}
+
+/// Insert mode left with `Ctrl-O` for one command
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct InsertRestart {
+    mode: Mode,
+    position: CursorPosition,
+    at_eol: bool,
+}
+
+impl Editor {
+    /// Handle one key (after mapping expansion) in the current mode.
+    ///
+    /// Errors are also stored in [`Editor::message`] so the UI can show
+    /// them; any partially typed command is discarded.
+    pub fn handle_key(&mut self, key: Key) -> VimResult<()> {
+        self.record_key(key);
+        match self.dispatch_key(key) {
+            Ok(()) => self.run_typeahead(),
+            Err(err) => {
+                self.flush_typeahead();
+                Err(err)
+            }
+        }
+    }
+
+    /// Handle one key, typed or executed from a register
+    pub(crate) fn dispatch_key(&mut self, key: Key) -> VimResult<()> {
+        self.message = None;
+        self.redo_before_key(key);
+        let restart = self.insert_restart.is_some();
+        let mut result = match self.modes.mode() {
+            _ if self.is_cmdwin_key(key) => self.cmdwin_key(key),
+            Mode::Normal | Mode::OperatorPending | Mode::Visual(_) => self.normal_key(key),
+            Mode::Insert | Mode::Replace => self.insert_key(key),
+            Mode::CommandLine(_) => {
+                let result = self.cmdline_key(key);
+                self.update_incsearch();
+                result
+            }
+            _ => Ok(()),
+        };
+        if let Err(err) = &result {
+            self.message = Some(err.to_string());
+            self.cancel_pending();
+        }
+        // The command typed after Ctrl-O is done, go back to Insert mode
+        let restarted = restart
+            && self.modes.mode() == Mode::Normal
+            && !self.has_pending_command()
+            && self.insert_restart.is_some();
+        if restarted {
+            result = result.and(self.restart_insert());
+        }
+        self.redo_after_key(result.is_ok(), restarted);
+        result
+    }
+
+    /// What `key` adds to the Normal mode command being typed
+    pub(crate) fn command_key_kind(&self, key: Key) -> CommandKeyKind {
+        match (self.pending.prefix, key) {
+            (Some(Prefix::Register), Key::Char(c)) => CommandKeyKind::RegisterName(c),
+            (Some(_), _) => CommandKeyKind::Other,
+            (None, Key::Char('"')) if self.pending.operator.is_none() => {
+                CommandKeyKind::RegisterPrefix
+            }
+            (None, Key::Char(c @ '0'..='9')) if c != '0' || self.pending.count.is_some() => {
+                CommandKeyKind::Digit(c as usize - '0' as usize)
+            }
+            _ => CommandKeyKind::Other,
+        }
+    }
+
+    /// The command-line type (`:`, `/` or `?`) while in Command-line mode
+    pub fn cmdline_type(&self) -> Option<char> {
+        self.cmdline_type
+    }
+
+    /// Whether a Normal mode command is partially typed
+    pub fn has_pending_command(&self) -> bool {
+        let pending = &self.pending;
+        pending.count.is_some()
+            || pending.register.is_some()
+            || pending.operator.is_some()
+            || pending.prefix.is_some()
+    }
+
+    pub(crate) fn cancel_pending(&mut self) {
+        self.pending = PendingCommand::default();
+        if self.modes.mode() == Mode::OperatorPending {
+            let _ = self.modes.exit_operator_pending();
+        }
+    }
+
+    /// Use register `reg` for the command being typed
+    pub(crate) fn set_pending_register(&mut self, reg: char) {
+        self.pending.register = Some(reg);
+    }
+
+    /// The count typed for the command, multiplied with the count typed
+    /// before the operator (`2d3w` deletes six words).
+    fn take_count(&mut self) -> Option<usize> {
+        match (
+            self.pending.operator_count.take(),
+            self.pending.count.take(),
+        ) {
+            (Some(a), Some(b)) => Some(a * b),
+            (a, b) => a.or(b),
+        }
+    }
+
+    // ========================================================================
+    // Normal and Visual Mode
+    // ========================================================================
+
+    fn normal_key(&mut self, key: Key) -> VimResult<()> {
+        let before = self.cursor.line();
+        let result = self.normal_command(key);
+        let after = self.cursor.line();
+        if after != before {
+            self.folds.cursor_moved(before, after);
+        }
+        if self.modes.mode() == Mode::Normal && !self.has_pending_command() {
+            self.adjust_cursor_for_folds();
+        }
+        result
+    }
+
+    fn normal_command(&mut self, key: Key) -> VimResult<()> {
+        if let Some(prefix) = self.pending.prefix.take() {
+            return self.prefixed_command(prefix, key);
+        }
+        match key {
+            Key::Escape | Key::Ctrl('c') => {
+                self.cancel_pending();
+                if self.modes.mode().is_visual() {
+                    self.escape()?;
+                }
+                return Ok(());
+            }
+            Key::Char(c @ '0'..='9') if c != '0' || self.pending.count.is_some() => {
+                let digit = c as usize - '0' as usize;
+                let count = self.pending.count.unwrap_or(0);
+                self.pending.count = Some(count.saturating_mul(10).saturating_add(digit));
+                return Ok(());
+            }
+            _ => {}
+        }
+        if let Some(motion) = simple_motion(key) {
+            return self.motion_command(motion);
+        }
+        let prefix = match key {
+            Key::Char('"') if self.pending.operator.is_none() => Some(Prefix::Register),
+            Key::Char('g') => Some(Prefix::G),
+            Key::Char('q')
+                if self.modes.mode() == Mode::Normal && self.reg_recording().is_some() =>
+            {
+                self.cancel_pending();
+                return self.stop_recording();
+            }
+            Key::Char('q') if self.modes.mode() == Mode::Normal => Some(Prefix::Q),
+            Key::Char('@') if self.modes.mode() == Mode::Normal => Some(Prefix::At),
+            Key::Char('z') => Some(Prefix::Z),
+            Key::Char('Z') if self.modes.mode() == Mode::Normal => Some(Prefix::BigZ),
+            Key::Char(c @ ('[' | ']')) => Some(Prefix::Bracket(c)),
+            Key::Char(c @ ('f' | 'F' | 't' | 'T')) => Some(Prefix::Find(c)),
+            _ => None,
+        };
+        if prefix.is_some() {
+            self.pending.prefix = prefix;
+            return Ok(());
+        }
+        if self.modes.mode().is_visual() {
+            return self.visual_command(key);
+        }
+        match key {
+            Key::Char(c @ ('d' | 'c' | 'y')) => {
+                let op = match c {
+                    'd' => Operator::Delete,
+                    'c' => Operator::Change,
+                    _ => Operator::Yank,
+                };
+                return self.operator_command(op, c);
+            }
+            _ if self.pending.operator.is_some() => {
+                self.cancel_pending();
+                return Ok(());
+            }
+            _ => {}
+        }
+        self.simple_command(key)
+    }
+
+    fn prefixed_command(&mut self, prefix: Prefix, key: Key) -> VimResult<()> {
+        let Key::Char(c) = key else {
+            self.cancel_pending();
+            return Ok(());
+        };
+        match prefix {
+            Prefix::Register if c == '=' => self.enter_expr_cmdline(ExprReturn::Register),
+            Prefix::Register => {
+                Register::from_char(c)?;
+                self.pending.register = Some(c);
+                Ok(())
+            }
+            Prefix::G => match c {
+                'g' => self.motion_command(MotionKey::FirstLine),
+                '*' => self.motion_command(MotionKey::SearchWord(Direction::Forward, false)),
+                '#' => self.motion_command(MotionKey::SearchWord(Direction::Backward, false)),
+                'd' | 'D' if self.pending.operator.is_none() => self.goto_declaration(c == 'D'),
+                'n' | 'N' => self.search_match_command(c == 'n'),
+                'a' | '8' if self.pending.operator.is_none() => {
+                    self.pending = PendingCommand::default();
+                    self.message = Some(self.describe_cursor_char(c == '8'));
+                    Ok(())
+                }
+                _ => {
+                    self.cancel_pending();
+                    Ok(())
+                }
+            },
+            Prefix::Z => self.z_command(c),
+            Prefix::BigZ => {
+                self.pending = PendingCommand::default();
+                match c {
+                    'Z' => self.quit_requested = handle_zz(self)?,
+                    'Q' => self.quit_requested = true,
+                    _ => {}
+                }
+                Ok(())
+            }
+            Prefix::Q => {
+                self.pending = PendingCommand::default();
+                match c {
+                    ':' | '/' | '?' => self.open_cmdline_window(c),
+                    c => self.start_recording(c),
+                }
+            }
+            Prefix::At => {
+                if self.pending.operator.is_some() {
+                    self.cancel_pending();
+                    return Ok(());
+                }
+                let count = self.take_count().unwrap_or(1).max(1);
+                self.pending = PendingCommand::default();
+                self.execute_register(c, count)
+            }
+            Prefix::Bracket(bracket) => match (bracket, c) {
+                ('[', 'z') => self.motion_command(MotionKey::FoldStart),
+                (']', 'z') => self.motion_command(MotionKey::FoldEnd),
+                _ => {
+                    self.cancel_pending();
+                    Ok(())
+                }
+            },
+            Prefix::Find(kind) => {
+                let find = match kind {
+                    'f' => CharFindMotion::FindForward(c),
+                    'F' => CharFindMotion::FindBackward(c),
+                    't' => CharFindMotion::TillForward(c),
+                    _ => CharFindMotion::TillBackward(c),
+                };
+                self.motion_command(MotionKey::FindChar(find))
+            }
+        }
+    }
+
+    /// Move the cursor, or complete a pending operator, with `motion`.
+    fn motion_command(&mut self, motion: MotionKey) -> VimResult<()> {
+        let count = self.take_count();
+        if let Some((op, _)) = self.pending.operator {
+            let target = match motion {
+                // `cw` changes to the end of the word, like `ce`
+                MotionKey::WordForward(big) if op == Operator::Change => self
+                    .change_word_target(count.unwrap_or(1), big)
+                    .map(Some)
+                    .map_or_else(|| self.evaluate_motion(motion, count, true), Ok)?,
+                _ => self.evaluate_motion(motion, count, true)?,
+            };
+            let Some(target) = target else {
+                self.cancel_pending();
+                self.beep();
+                return Ok(());
+            };
+            let range = MotionRange {
+                start: self.cursor.position(),
+                end: target.position,
+                motion_type: target.motion_type,
+                inclusive: target.inclusive,
+            };
+            return self.finish_operator(range);
+        }
+
+        let register = self.pending.register;
+        self.pending = PendingCommand::default();
+        self.pending.register = register.filter(|_| self.modes.mode().is_visual());
+        let Some(target) = self.evaluate_motion(motion, count, false)? else {
+            self.beep();
+            return Ok(());
+        };
+        if motion.is_jump() {
+            let handle = self.buffers.current().handle();
+            self.marks.push_jump(handle, self.cursor.position());
+        }
+        let ctx = self.cursor_context();
+        self.cursor.set_position(target.position, &ctx)?;
+        if motion == MotionKey::LineEnd {
+            self.cursor.set_curswant_eol();
+        } else if !motion.keeps_curswant() {
+            self.cursor.update_curswant();
+        }
+        if let Some(item) = motion.foldopen_item() {
+            self.open_folds_for(item);
+        }
+        Ok(())
+    }
+
+    /// `gd` and `gD`: jump to the declaration of the keyword under the
+    /// cursor
+    fn goto_declaration(&mut self, global: bool) -> VimResult<()> {
+        self.pending = PendingCommand::default();
+        let Some(target) = self.find_declaration(global)? else {
+            return Ok(());
+        };
+        let handle = self.buffers.current().handle();
+        self.marks.push_jump(handle, self.cursor.position());
+        let ctx = self.cursor_context();
+        self.cursor.set_position(target, &ctx)?;
+        self.cursor.update_curswant();
+        self.open_folds_for("search");
+        Ok(())
+    }
+
+    /// `gn` and `gN`: operate on the next (previous) match of the last
+    /// search pattern, select it, or extend the Visual selection to it
+    fn search_match_command(&mut self, forward: bool) -> VimResult<()> {
+        let count = self.take_count().unwrap_or(1);
+        let Some((start, end)) = self.find_search_match(forward, count)? else {
+            self.cancel_pending();
+            self.beep();
+            return Ok(());
+        };
+        if self.pending.operator.is_some() {
+            return self.finish_operator(MotionRange {
+                start,
+                end,
+                motion_type: MotionType::Characterwise,
+                inclusive: true,
+            });
+        }
+        self.pending = PendingCommand::default();
+        let (anchor, cursor) = if forward { (start, end) } else { (end, start) };
+        let ctx = self.cursor_context();
+        if !self.modes.mode().is_visual() {
+            self.cursor.set_position(anchor, &ctx)?;
+            self.enter_visual()?;
+        }
+        self.cursor.set_position(cursor, &ctx)?;
+        self.cursor.update_curswant();
+        Ok(())
+    }
+
+    fn operator_command(&mut self, op: Operator, key: char) -> VimResult<()> {
+        if let Some((_, pending_key)) = self.pending.operator {
+            if pending_key != key {
+                self.cancel_pending();
+                return Ok(());
+            }
+            // A doubled operator (`dd`, `cc`, `yy`) works on [count] lines
+            let count = self.take_count().unwrap_or(1);
+            let start = self.cursor.line();
+            let end = self.line_below(start, count - 1);
+            return self.finish_operator(MotionRange::lines(start, end));
+        }
+        self.pending.operator = Some((op, key));
+        self.pending.operator_count = self.pending.count.take();
+        self.modes
+            .enter_operator_pending(key)
+            .map_err(|err| VimError::NotAllowedInMode(err.reason))?;
+        Ok(())
+    }
+
+    fn finish_operator(&mut self, range: MotionRange) -> VimResult<()> {
+        let op = self.pending.operator.map(|(op, _)| op);
+        let register = self.pending.register;
+        self.cancel_pending();
+        match op {
+            Some(op) => self.apply_operator(op, register, range),
+            None => Ok(()),
+        }
+    }
+
+    fn simple_command(&mut self, key: Key) -> VimResult<()> {
+        let count = self.take_count();
+        let register = self.pending.register.take();
+        self.pending = PendingCommand::default();
+        let n = count.unwrap_or(1).max(1);
+        if let Key::Char(c @ ('i' | 'a' | 'I' | 'A' | 'o' | 'O')) = key {
+            self.insert_command(c)?;
+            if n > 1 {
+                self.insert_repeat = Some(InsertRepeat {
+                    count: n,
+                    new_line: matches!(c, 'o' | 'O'),
+                });
+            }
+            return Ok(());
+        }
+        match key {
+            Key::Char('v') => self.enter_visual(),
+            Key::Char('V') => self.enter_visual_line(),
+            Key::Ctrl('v') => self.enter_visual_block(),
+            Key::Char('R') => self.enter_replace(),
+            Key::Char(c @ ('p' | 'P')) => {
+                let reg = match register.filter(|&name| name != '"') {
+                    Some(name) => Register::from_char(name)?,
+                    None => self.unnamed_put_register(),
+                };
+                let after = c == 'p';
+                let Some(content) = self.fetch_register(reg)? else {
+                    return Ok(());
+                };
+                if content.reg_type == RegisterType::Linewise {
+                    // Linewise text goes below or above a closed fold
+                    if let Some((start, end)) = self.closed_fold(self.cursor.line()) {
+                        let ctx = self.cursor_context();
+                        self.cursor
+                            .set_line(if after { end } else { start }, &ctx)?;
+                    }
+                }
+                for _ in 0..n {
+                    self.put_content(content.clone(), after)?;
+                }
+                Ok(())
+            }
+            Key::Char(c @ ('x' | 'X' | 'D' | 'C')) => {
+                let (op, motion) = match c {
+                    'x' => (Operator::Delete, MotionKey::Right),
+                    'X' => (Operator::Delete, MotionKey::Left),
+                    'D' => (Operator::Delete, MotionKey::LineEnd),
+                    _ => (Operator::Change, MotionKey::LineEnd),
+                };
+                if self.current_line().is_empty() && c != 'C' {
+                    return Ok(());
+                }
+                let Some(target) = self.evaluate_motion(motion, count, true)? else {
+                    return Ok(());
+                };
+                let range = MotionRange {
+                    start: self.cursor.position(),
+                    end: target.position,
+                    motion_type: target.motion_type,
+                    inclusive: target.inclusive,
+                };
+                self.apply_operator(op, register, range)
+            }
+            Key::Char(c @ (':' | '/' | '?')) => self.enter_cmdline_type(c),
+            Key::Char('.') => self.repeat_last_change(count),
+            // `Q` executes the register last recorded into
+            Key::Char('Q') => match self.reg_recorded() {
+                Some(reg) => self.execute_register(reg, n),
+                None => Err(VimError::Error(
+                    748,
+                    "No previously used register".to_string(),
+                )),
+            },
+            _ => Ok(()),
+        }
+    }
+
+    /// `i`, `a`, `I`, `A`, `o` and `O`: start Insert mode
+    fn insert_command(&mut self, c: char) -> VimResult<()> {
+        match c {
+            'i' => self.enter_insert(),
+            'a' => {
+                self.enter_insert()?;
+                self.cursor_right(1)
+            }
+            'I' => {
+                let line = self.cursor.line();
+                let col = first_non_blank(&self.buffer_lines(), line);
+                self.enter_insert()?;
+                let ctx = self.cursor_context();
+                self.cursor.set_col(col, &ctx)
+            }
+            'A' => {
+                let line_len = self.current_line().len();
+                self.enter_insert()?;
+                let ctx = self.cursor_context();
+                self.cursor.set_col(line_len, &ctx)
+            }
+            _ => self.open_line(c == 'o'),
+        }
+    }
+
+    /// `o` and `O`: open a line below or above, skipping over a closed fold
+    fn open_line(&mut self, below: bool) -> VimResult<()> {
+        let line = self.cursor.line();
+        let (start, end) = self.closed_fold(line).unwrap_or((line, line));
+        let lines = self.buffer_lines();
+        let indent = if self.options.flag("autoindent") {
+            let text = &lines[line.to_zero_indexed()];
+            text[..first_non_blank(&lines, line)].to_string()
+        } else {
+            String::new()
+        };
+        let col = indent.len();
+        let at = if below { end.0 } else { start.0 - 1 };
+        self.buffers
+            .current_mut()
+            .set_lines(at as i64, at as i64, false, vec![indent])?;
+        self.sync_cursor_with_buffer();
+        self.enter_insert()?;
+        let ctx = self.cursor_context();
+        self.cursor
+            .set_position(CursorPosition::new(LineNr(at + 1), col), &ctx)?;
+        self.folds.view_line(self.cursor.line());
+        Ok(())
+    }
+
+    fn z_command(&mut self, c: char) -> VimResult<()> {
+        let line = self.cursor.line();
+        if self.modes.mode().is_visual() {
+            if c == 'f' {
+                let anchor = self.visual_anchor.unwrap_or(self.cursor.position());
+                let (start, end) = (anchor.line.min(line), anchor.line.max(line));
+                self.pending = PendingCommand::default();
+                self.escape()?;
+                self.create_fold(start, end)?;
+                let ctx = self.cursor_context();
+                self.cursor.set_line(start, &ctx)?;
+            } else {
+                self.cancel_pending();
+            }
+            return Ok(());
+        }
+        if c == 'f' {
+            return self.operator_command(Operator::CreateFold, 'z');
+        }
+        if self.pending.operator.is_some() {
+            self.cancel_pending();
+            return Ok(());
+        }
+        let count = self.take_count();
+        self.pending = PendingCommand::default();
+        let n = count.unwrap_or(1).max(1);
+        match c {
+            'F' => {
+                let end = self.line_below(line, n - 1);
+                let end = self.closed_fold(end).map_or(end, |(_, end)| end);
+                self.create_fold(line, end)
+            }
+            'd' => self.delete_fold(line, false),
+            'D' => self.delete_fold(line, true),
+            'E' => self.eliminate_folds(),
+            'o' => self.folds.open_count(line, n),
+            'O' => self.folds.open_recursive(line),
+            'c' => self.folds.close(line),
+            'C' => self.folds.close_recursive(line),
+            'a' => self.folds.toggle(line),
+            'A' => self.folds.toggle_recursive(line),
+            'v' => {
+                self.folds.view_line(line);
+                Ok(())
+            }
+            'x' => {
+                self.folds.update(line);
+                Ok(())
+            }
+            'M' => {
+                self.update_foldlevel(|folds| folds.set_foldlevel(0));
+                Ok(())
+            }
+            'R' => {
+                self.update_foldlevel(|folds| {
+                    let depth = folds.max_depth();
+                    folds.set_foldlevel(depth);
+                });
+                Ok(())
+            }
+            'm' => {
+                self.update_foldlevel(|folds| folds.fold_more(n));
+                Ok(())
+            }
+            'r' => {
+                self.update_foldlevel(|folds| folds.reduce_folding(n));
+                Ok(())
+            }
+            'n' | 'N' | 'i' => {
+                let enable = match c {
+                    'n' => false,
+                    'N' => true,
+                    _ => !self.options.flag("foldenable"),
+                };
+                self.options.set_bool("foldenable", enable)?;
+                Ok(())
+            }
+            'j' => {
+                self.pending.count = count;
+                self.motion_command(MotionKey::NextFold)
+            }
+            'k' => {
+                self.pending.count = count;
+                self.motion_command(MotionKey::PrevFold)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn visual_command(&mut self, key: Key) -> VimResult<()> {
+        let mode = self.modes.mode();
+        let register = self.pending.register.take();
+        self.pending = PendingCommand::default();
+        let block = mode == Mode::Visual(VisualMode::Block);
+        if let Some(anchor) = self.visual_anchor {
+            if matches!(key, Key::Char('x' | 'd' | 'c'))
+                || block && matches!(key, Key::Char('I' | 'A'))
+            {
+                self.redo_visual_operator(anchor, register, key);
+            }
+        }
+        match key {
+            Key::Char('v') => self.escape(),
+            Key::Char('V') | Key::Ctrl('v') => {
+                let target = match key {
+                    Key::Char('V') => VisualMode::Line,
+                    _ => VisualMode::Block,
+                };
+                self.modes
+                    .transition_to(Mode::Visual(target))
+                    .map(|_| ())
+                    .map_err(|err| VimError::NotAllowedInMode(err.reason))
+            }
+            Key::Char('x') | Key::Char('d') if block => self.visual_delete(),
+            Key::Char('c') if block => self.visual_change(),
+            Key::Char('y') if block => self.visual_yank(),
+            Key::Char('I') if block => self.visual_insert(),
+            Key::Char('A') if block => self.visual_append(),
+            Key::Char(c @ ('x' | 'd' | 'c' | 'y')) => {
+                let op = match c {
+                    'c' => Operator::Change,
+                    'y' => Operator::Yank,
+                    _ => Operator::Delete,
+                };
+                let range = self.visual_range()?;
+                self.escape()?;
+                self.apply_operator(op, register, range)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The Visual selection as an operator range; also sets the `'<` and
+    /// `'>` marks.
+    fn visual_range(&mut self) -> VimResult<MotionRange> {
+        let anchor = self
+            .visual_anchor
+            .ok_or_else(|| VimError::Error(1, "No selection".to_string()))?;
+        let cursor = self.cursor.position();
+        let (start, end) = if (anchor.line, anchor.col) <= (cursor.line, cursor.col) {
+            (anchor, cursor)
+        } else {
+            (cursor, anchor)
+        };
+        self.marks.set_visual_marks(start, end);
+        let motion_type = match self.modes.mode() {
+            Mode::Visual(VisualMode::Line) => MotionType::Linewise,
+            _ => MotionType::Characterwise,
+        };
+        Ok(MotionRange {
+            start,
+            end,
+            motion_type,
+            inclusive: true,
+        })
+    }
+
+    // ========================================================================
+    // Insert Mode
+    // ========================================================================
+
+    fn insert_key(&mut self, key: Key) -> VimResult<()> {
+        if self.insert_start.is_none() {
+            self.insert_start = Some(self.cursor.position());
+        }
+        if std::mem::take(&mut self.insert_ctrl_r) {
+            return self.insert_register_key(key);
+        }
+        if std::mem::take(&mut self.insert_ctrl_v) {
+            return match literal_char(key) {
+                Some(c) => self.insert_char_raw(c),
+                None => Ok(()),
+            };
+        }
+        if self.completion_key(key)? {
+            return Ok(());
+        }
+        let Some(key) = self.insert_digraph_key(key)? else {
+            return Ok(());
+        };
+        // The count is dropped once the cursor was moved
+        if matches!(key, Key::Left | Key::Right | Key::Up | Key::Down) {
+            self.insert_repeat = None;
+        }
+        match key {
+            Key::Escape => {
+                self.expand_insert_abbreviation()?;
+                self.repeat_insert()?;
+                self.escape()
+            }
+            Key::Ctrl('c') => self.escape(),
+            Key::Ctrl('o') => self.insert_ctrl_o(),
+            Key::Ctrl('r') => {
+                self.insert_ctrl_r = true;
+                Ok(())
+            }
+            Key::Ctrl('v') | Key::Ctrl('q') => {
+                self.insert_ctrl_v = true;
+                Ok(())
+            }
+            Key::Ctrl(']') => self.expand_insert_abbreviation().map(|_| ()),
+            Key::Ctrl('a') => {
+                if let Some(content) = self.registers.get(Register::LastInserted) {
+                    let text = content.as_string();
+                    self.insert_text(&text)?;
+                }
+                Ok(())
+            }
+            Key::Ctrl('y') => self.insert_from_adjacent_line(-1),
+            Key::Ctrl('e') => self.insert_from_adjacent_line(1),
+            Key::Char(c) => self.insert_char(c),
+            Key::Tab => {
+                if self.options.flag("expandtab") {
+                    let spaces = self.display_options().tab_width(self.cursor.virtcol());
+                    self.insert_text(&" ".repeat(spaces))
+                } else {
+                    self.insert_char('\t')
+                }
+            }
+            Key::Backspace => {
+                let col = self.cursor.col();
+                let line = self.cursor.line();
+                if col > 0 {
+                    self.cursor_left(1)?;
+                    self.delete_char()
+                } else if line.0 > 1 {
+                    // Join with the previous line
+                    let idx = line.to_zero_indexed() as i64;
+                    let lines = self.buffer_lines();
+                    let prev = &lines[line.to_zero_indexed() - 1];
+                    let joined = format!("{}{}", prev, lines[line.to_zero_indexed()]);
+                    let prev_len = prev.len();
+                    self.buffers
+                        .current_mut()
+                        .set_lines(idx - 1, idx + 1, false, vec![joined])?;
+                    self.sync_cursor_with_buffer();
+                    let ctx = self.cursor_context();
+                    self.cursor
+                        .set_position(CursorPosition::new(LineNr(line.0 - 1), prev_len), &ctx)
+                } else {
+                    Ok(())
+                }
+            }
+            Key::Delete => self.delete_char(),
+            Key::Enter => {
+                self.expand_insert_abbreviation()?;
+                self.insert_newline()
+            }
+            Key::Left => self.cursor_left(1),
+            Key::Right => self.cursor_right(1),
+            Key::Up => self.cursor_up(1),
+            Key::Down => self.cursor_down(1),
+            _ => Ok(()),
+        }
+    }
+
+    /// `<Esc>` after a command with a count started Insert mode: insert
+    /// the text typed again, `count - 1` times
+    fn repeat_insert(&mut self) -> VimResult<()> {
+        let Some(repeat) = self.insert_repeat.take() else {
+            return Ok(());
+        };
+        let Some(inserted) = self.current_insert.clone() else {
+            return Ok(());
+        };
+        let text = if repeat.new_line {
+            format!("\n{}", inserted)
+        } else {
+            inserted.clone()
+        };
+        for _ in 1..repeat.count {
+            self.insert_text_raw(&text)?;
+        }
+        // The `.` register holds the text typed once
+        self.current_insert = Some(inserted);
+        Ok(())
+    }
+
+    /// The register name after `Ctrl-R` in Insert mode: insert the
+    /// register's text as if typed; `=` opens the expression command line
+    fn insert_register_key(&mut self, key: Key) -> VimResult<()> {
+        let Key::Char(c) = key else {
+            return Ok(());
+        };
+        if c == '=' {
+            let target = ExprReturn::Insert {
+                mode: self.modes.mode(),
+                position: self.cursor.position(),
+            };
+            return self.enter_expr_cmdline(target);
+        }
+        let Ok(reg) = Register::from_char(c) else {
+            return Ok(());
+        };
+        match self.fetch_register(reg)? {
+            Some(content) => self.insert_text(&content.as_string()),
+            None => Ok(()),
+        }
+    }
+
+    /// `Ctrl-O`: leave Insert mode for one Normal mode command
+    fn insert_ctrl_o(&mut self) -> VimResult<()> {
+        let mode = self.modes.mode();
+        let line_len = self.current_line().len();
+        let at_eol = line_len > 0 && self.cursor.col() >= line_len;
+        self.escape()?;
+        self.insert_restart = Some(InsertRestart {
+            mode,
+            position: self.cursor.position(),
+            at_eol,
+        });
+        Ok(())
+    }
+
+    /// Start Insert mode again after the command typed with `Ctrl-O`.
+    /// When the cursor was at the end of the line and did not move it
+    /// goes back there.
+    fn restart_insert(&mut self) -> VimResult<()> {
+        let Some(restart) = self.insert_restart.take() else {
+            return Ok(());
+        };
+        let at = self.cursor.position();
+        match restart.mode {
+            Mode::Replace => self.enter_replace()?,
+            _ => self.enter_insert()?,
+        }
+        if restart.at_eol && at == restart.position {
+            let line_len = self.current_line().len();
+            let ctx = self.cursor_context();
+            self.cursor.set_col(line_len, &ctx)?;
+        }
+        Ok(())
+    }
+
+    // ========================================================================
+    // Command-line Mode
+    // ========================================================================
+
+    pub(crate) fn enter_cmdline_type(&mut self, c: char) -> VimResult<()> {
+        self.modes
+            .enter_cmdline()
+            .map_err(|err| VimError::NotAllowedInMode(err.reason))?;
+        self.cmdline.setcmdline("")?;
+        self.cmdline.reset_recall();
+        self.cmdline.pending = CmdlinePending::None;
+        self.wild = None;
+        self.cmdline_type = Some(c);
+        match c {
+            '/' | '?' => self.start_incsearch(),
+            _ => self.incsearch = None,
+        }
+        Ok(())
+    }
+
+    fn leave_cmdline(&mut self) -> VimResult<()> {
+        self.cmdline_type = None;
+        self.wild = None;
+        self.end_incsearch();
+        self.modes
+            .escape_to_normal()
+            .map(|_| ())
+            .map_err(|err| VimError::NotAllowedInMode(err.reason))
+    }
+
+    fn cmdline_key(&mut self, key: Key) -> VimResult<()> {
+        let Some(key) = self.cmdline_pending_key(key)? else {
+            return Ok(());
+        };
+        if self.cmdline_type == Some('=') {
+            match key {
+                Key::Enter => return self.finish_expr_cmdline(true),
+                Key::Escape | Key::Ctrl('c') => return self.finish_expr_cmdline(false),
+                Key::Backspace | Key::Ctrl('h') if self.cmdline.getcmdline().is_empty() => {
+                    return self.finish_expr_cmdline(false)
+                }
+                _ => {}
+            }
+        }
+        if self.is_cedit_key(key) && self.cmdline_type != Some('=') {
+            let text = self.cmdline.getcmdline().to_string();
+            let col = self.cmdline.getcmdpos() - 1;
+            let kind = self.cmdline_type.unwrap_or(':');
+            self.leave_cmdline()?;
+            return self.open_cmdwin(kind, &text, col);
+        }
+        match key {
+            Key::Ctrl(']') => return self.expand_cmdline_abbreviation().map(|_| ()),
+            Key::Char(c) if !self.search_options().iskeyword.contains(c) => {
+                self.expand_cmdline_abbreviation()?;
+            }
+            Key::Enter => {
+                self.expand_cmdline_abbreviation()?;
+            }
+            _ => {}
+        }
+        if self.wildmenu_key(key)? || self.incsearch_key(key)? {
+            return Ok(());
+        }
+        if self.cmdline_edit_key(key)? {
+            return Ok(());
+        }
+        match key {
+            Key::Escape | Key::Ctrl('c') => self.leave_cmdline(),
+            Key::Backspace | Key::Ctrl('h') => {
+                // <BS> on an empty command line abandons it
+                if self.cmdline.getcmdline().is_empty() {
+                    return self.leave_cmdline();
+                }
+                self.cmdline.reset_recall();
+                self.cmdline.delete_char_before().map(|_| ())
+            }
+            Key::Enter => {
+                let text = self.cmdline.getcmdline().to_string();
+                let kind = self.cmdline_type.unwrap_or(':');
+                let target = self.incsearch_target();
+                self.leave_cmdline()?;
+                self.execute_cmdline_type(kind, &text)?;
+                // Stay on the match Ctrl-G or Ctrl-T moved to
+                if let Some(target) = target {
+                    let ctx = self.cursor_context();
+                    self.cursor.set_position(target, &ctx)?;
+                    self.cursor.update_curswant();
+                    self.open_folds_for("search");
+                    if let Some(pattern) = self.search.last_pattern().cloned() {
+                        self.message = None;
+                        self.show_search_count(&pattern, target, false);
+                    }
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Execute `text` as typed on a `kind` (`:`, `/` or `?`) command line
+    pub(crate) fn execute_cmdline_type(&mut self, kind: char, text: &str) -> VimResult<()> {
+        let result = match kind {
+            ':' => self.execute_cmdline(text),
+            '/' => self.search_pattern(text, Direction::Forward),
+            _ => self.search_pattern(text, Direction::Backward),
+        };
+        self.adjust_cursor_for_folds();
+        result
+    }
+
+    fn execute_cmdline(&mut self, text: &str) -> VimResult<()> {
+        if text.trim().is_empty() {
+            return Ok(());
+        }
+        self.cmdline.hist_add(CmdlineHistoryKind::Command, text);
+        // Set after running, so that `:@:` repeats the command before it
+        let result = self.run(text);
+        self.registers.set_last_command(text);
+        if let Some(err) = result.error {
+            return Err(err);
+        }
+        if result.message.is_some() {
+            self.message = result.message;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn buffer_lines(&self) -> Vec<String> {
+        self.buffers
+            .current()
+            .get_lines(0, -1, false)
+            .unwrap_or_default()
+    }
+}
+
+/// Motions typed with a single key
+fn simple_motion(key: Key) -> Option<MotionKey> {
+    Some(match key {
+        Key::Char('h') | Key::Left | Key::Backspace => MotionKey::Left,
+        Key::Char('l') | Key::Right | Key::Char(' ') => MotionKey::Right,
+        Key::Char('j') | Key::Down | Key::Ctrl('n') => MotionKey::Down,
+        Key::Char('k') | Key::Up | Key::Ctrl('p') => MotionKey::Up,
+        Key::Char('+') | Key::Enter => MotionKey::NextLineStart,
+        Key::Char('-') => MotionKey::PrevLineStart,
+        Key::Char('0') | Key::Home => MotionKey::LineStart,
+        Key::Char('^') => MotionKey::FirstNonBlank,
+        Key::Char('$') | Key::End => MotionKey::LineEnd,
+        Key::Char('w') => MotionKey::WordForward(false),
+        Key::Char('W') => MotionKey::WordForward(true),
+        Key::Char('b') => MotionKey::WordBackward(false),
+        Key::Char('B') => MotionKey::WordBackward(true),
+        Key::Char('e') => MotionKey::WordEnd(false),
+        Key::Char('E') => MotionKey::WordEnd(true),
+        Key::Char('G') => MotionKey::LastLine,
+        Key::Char(';') => MotionKey::FindChar(CharFindMotion::RepeatForward),
+        Key::Char(',') => MotionKey::FindChar(CharFindMotion::RepeatBackward),
+        Key::Char('%') => MotionKey::MatchPair,
+        Key::Char('n') => MotionKey::SearchNext(false),
+        Key::Char('N') => MotionKey::SearchNext(true),
+        Key::Char('*') => MotionKey::SearchWord(Direction::Forward, true),
+        Key::Char('#') => MotionKey::SearchWord(Direction::Backward, true),
+        _ => return None,
+    })
+}
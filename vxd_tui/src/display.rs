@@ -0,0 +1,225 @@
+//! What the window shows: the rows of screen cells for the buffer lines,
+//! and the cell the cursor is on.
+//!
+//! The cells of a line come from [`vxd::display`], with the current
+//! 'tabstop', 'vartabstop', 'list', 'listchars', 'display' and
+//! 'ambiwidth'.  With 'wrap' a line takes as many rows as it needs;
+//! without it every line is shown from the same virtual column, far enough
+//! right for the cursor to be in view.  A closed fold takes one row.
+
+use vxd::cursor::Cursor;
+use vxd::display::{
+    parse_vartabstop, wrap_cells, AmbiWidth, Cell, CellKind, DisplayFlags, DisplayOptions,
+    ListChars,
+};
+use vxd::modes::Mode;
+use vxd::types::{LineNr, VimResult};
+
+use crate::editor::Editor;
+
+/// One row of the window
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ViewRow {
+    /// The buffer line shown
+    pub line: LineNr,
+    /// Whether this is the first row of the line, the one with its number
+    pub first: bool,
+    /// Whether the row is the text of a closed fold
+    pub fold: bool,
+    /// The cells, at most the width of the window
+    pub cells: Vec<Cell>,
+}
+
+/// What the window shows, for drawing it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WindowView {
+    /// The rows, at most the height of the window
+    pub rows: Vec<ViewRow>,
+    /// The row and cell of the cursor, when it is in the window
+    pub cursor: Option<(usize, usize)>,
+}
+
+/// A buffer line, or a closed fold, and the rows it takes
+struct Entry {
+    line: LineNr,
+    fold: bool,
+    rows: Vec<Vec<Cell>>,
+}
+
+impl Editor {
+    /// The display options of the current window and buffer
+    pub fn display_options(&self) -> DisplayOptions {
+        DisplayOptions {
+            tabstop: self.options.number("tabstop").max(1) as usize,
+            vartabstop: parse_vartabstop(self.options.string("vartabstop")).unwrap_or_default(),
+            list: self.options.flag("list"),
+            listchars: ListChars::parse(self.options.string("listchars")).unwrap_or_default(),
+            display: DisplayFlags::parse(self.options.string("display")).unwrap_or_default(),
+            ambiwidth: AmbiWidth::parse(self.options.string("ambiwidth")).unwrap_or_default(),
+        }
+    }
+
+    /// Check the values of the display options, for `:set`
+    pub(crate) fn check_display_options(&self) -> VimResult<()> {
+        parse_vartabstop(self.options.string("vartabstop"))?;
+        ListChars::parse(self.options.string("listchars"))?;
+        DisplayFlags::parse(self.options.string("display"))?;
+        AmbiWidth::parse(self.options.string("ambiwidth"))?;
+        Ok(())
+    }
+
+    /// Whether the cursor is shown on the last cell of a tab: not while
+    /// inserting or on the command line
+    fn cursor_on_tab_end(&self) -> bool {
+        !matches!(
+            self.mode(),
+            Mode::Insert | Mode::Replace | Mode::CommandLine(_)
+        )
+    }
+
+    /// The rows of a window `height` rows of `width` cells showing
+    /// `lines`, scrolled so that the cursor line is in view
+    pub fn window_view(&self, lines: &[String], height: usize, width: usize) -> WindowView {
+        let width = width.max(1);
+        let opts = self.display_options();
+        let wrap = self.options.flag("wrap");
+        let normal = self.cursor_on_tab_end();
+
+        // The first line of each entry: a line, or a closed fold
+        let mut starts: Vec<(LineNr, bool)> = Vec::new();
+        let mut idx = 0;
+        while idx < lines.len() {
+            let line = LineNr(idx + 1);
+            match self.closed_fold(line) {
+                Some((_, end)) => {
+                    starts.push((line, true));
+                    idx = end.0;
+                }
+                None => {
+                    starts.push((line, false));
+                    idx += 1;
+                }
+            }
+        }
+        if starts.is_empty() || height == 0 {
+            return WindowView::default();
+        }
+
+        let cursor_line = self.cursor.line().to_zero_indexed().min(lines.len() - 1);
+        let cursor_text = &lines[cursor_line];
+        let cursor_col = self.cursor.col().min(cursor_text.len());
+        let leftcol = if wrap {
+            0
+        } else {
+            (opts.virtcol(cursor_text, cursor_col).1 + 1).saturating_sub(width)
+        };
+        let entry = |&(line, fold): &(LineNr, bool)| -> Entry {
+            if fold {
+                let text = self.fold_line(line, width).unwrap_or_default();
+                let cells = text
+                    .chars()
+                    .map(|ch| Cell {
+                        text: ch.to_string(),
+                        col: 0,
+                        kind: CellKind::Text,
+                    })
+                    .collect();
+                return Entry {
+                    line,
+                    fold,
+                    rows: vec![cells],
+                };
+            }
+            let cells = opts.line_cells(&lines[line.to_zero_indexed()]);
+            let rows = if wrap {
+                wrap_cells(&cells, width)
+            } else {
+                vec![opts.visible_cells(&cells, leftcol, width)]
+            };
+            Entry { line, fold, rows }
+        };
+
+        // Scroll back from the entry with the cursor while the entries fit
+        let current = starts
+            .iter()
+            .rposition(|&(line, _)| line.to_zero_indexed() <= cursor_line)
+            .unwrap_or(0);
+        let mut entries = vec![entry(&starts[current])];
+        let mut used = entries[0].rows.len();
+        let mut top = current;
+        while top > 0 {
+            let above = entry(&starts[top - 1]);
+            if used + above.rows.len() > height {
+                break;
+            }
+            used += above.rows.len();
+            entries.insert(0, above);
+            top -= 1;
+        }
+
+        let mut view = WindowView::default();
+        let mut next = top;
+        let mut entries = entries.into_iter();
+        while view.rows.len() < height && next < starts.len() {
+            let entry = entries.next().unwrap_or_else(|| entry(&starts[next]));
+            let left = height - view.rows.len();
+            let mut rows = entry.rows;
+            let mut skip = 0;
+            if next == current && !entry.fold {
+                let text = &lines[entry.line.to_zero_indexed()];
+                let on_tab_end = normal
+                    && !opts.list
+                    && text
+                        .get(cursor_col..)
+                        .is_some_and(|rest| rest.starts_with('\t'));
+                let (row, cell) = cursor_cell(&rows, text, cursor_col, on_tab_end);
+                // Of a line taller than the window the rows up to the
+                // cursor one are shown
+                skip = (row + 1).saturating_sub(left);
+                rows.drain(..skip);
+                view.cursor = Some((view.rows.len() + row - skip, cell.min(width - 1)));
+            } else if next == current {
+                view.cursor = Some((view.rows.len(), 0));
+            } else if rows.len() > left {
+                rows = opts.partial_rows(&rows, left, width);
+            }
+            for (idx, cells) in rows.into_iter().enumerate() {
+                view.rows.push(ViewRow {
+                    line: entry.line,
+                    first: idx + skip == 0,
+                    fold: entry.fold,
+                    cells,
+                });
+            }
+            next += 1;
+        }
+        view.rows.truncate(height);
+        view
+    }
+}
+
+/// The row and cell the cursor on byte `col` of `text` is shown on: the
+/// first cell of its character, or the last one for `on_tab_end`.  Past
+/// the cells it is just after them.
+fn cursor_cell(rows: &[Vec<Cell>], text: &str, col: usize, on_tab_end: bool) -> (usize, usize) {
+    let mut found = None;
+    for (row, cells) in rows.iter().enumerate() {
+        for (idx, cell) in cells.iter().enumerate() {
+            let shown = match cell.kind {
+                CellKind::Continuation => false,
+                CellKind::NonText => col == text.len(),
+                _ => true,
+            };
+            if cell.col == col && shown {
+                found = Some((row, idx));
+                if !on_tab_end {
+                    return (row, idx);
+                }
+            }
+        }
+    }
+    found.unwrap_or_else(|| {
+        let row = rows.len().saturating_sub(1);
+        (row, rows.get(row).map_or(0, Vec::len))
+    })
+}
@@ -3,21 +3,53 @@
 //! This module provides the `Editor` struct which combines buffer, cursor,
 //! mode, register, and mark management into a cohesive editor.
 
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::autocmd::TuiAutocmdManager;
 use crate::buffer::TuiBufferManager;
+use crate::cmdline::TuiCmdline;
+use crate::cmdwin::CmdWindow;
+use crate::completion::{CompleteFunc, CompletionSession, TuiCompletionEngine};
 use crate::cursor::TuiCursor;
+use crate::digraph::DigraphState;
+use crate::dispatch::{InsertRepeat, InsertRestart, PendingCommand};
+use crate::eval::ExprReturn;
+use crate::exmode::ExMode;
+use crate::extmarks::TuiExtmarkManager;
+use crate::incsearch::IncSearch;
+use crate::macros::MacroState;
 use crate::marks::TuiMarkManager;
 use crate::modes::TuiModeManager;
+use crate::options::{LocalValues, SetScope, TuiOptionManager};
+use crate::quickfix::QuickfixWindow;
 use crate::registers::TuiRegisterBank;
+use crate::repeat::RedoState;
+use crate::wildmenu::WildSession;
 
-use vxd::abbreviations::{AbbreviationManager, SimpleAbbreviationManager};
-use vxd::buffer::{Buffer, BufferManager};
+use vxd::abbreviations::SimpleAbbreviationManager;
+use vxd::buffer::{BufHandle, Buffer, BufferManager};
+use vxd::cmdline::{CmdlineHistory, CmdlineHistoryKind};
 use vxd::cursor::{Cursor, CursorContext, CursorPosition, VirtualEdit};
-use vxd::mappings::{MappingManager, SimpleMappingManager};
-use vxd::marks::MarkManager;
+use vxd::digraphs::DigraphTable;
+use vxd::folds::{BufferFoldManager, FoldManager, FoldMethod, FoldOptions};
+use vxd::mappings::SimpleMappingManager;
+use vxd::marks::{ExtmarkManager, MarkManager};
 use vxd::modes::{Mode, ModeManager, VisualMode};
 use vxd::motions::CharFindMotion;
+use vxd::options::OptionManager;
+use vxd::quickfix::QuickfixStack;
 use vxd::registers::{Register, RegisterBank, RegisterContent, RegisterType};
-use vxd::types::{LineNr, VimError, VimResult};
+use vxd::search::{
+    search_count, KeywordChars, SearchCount, SearchCountOptions, SearchEngine, SearchOptions,
+    SearchPattern, SimpleSearchEngine,
+};
+use vxd::types::{Direction, LineNr, VimError, VimResult};
+use vxd::vimscript::Interpreter;
+use vxd::windows::WinHandle;
+
+/// The window the editor starts with, numbered like Vim's window IDs
+const FIRST_WINDOW: WinHandle = WinHandle(1000);
 
 /// The main editor struct combining all components
 #[derive(Debug)]
@@ -32,14 +64,80 @@ pub struct Editor {
     pub registers: TuiRegisterBank,
     /// Mark manager
     pub marks: TuiMarkManager,
+    /// Extmarks placed by embedders
+    pub extmarks: TuiExtmarkManager,
     /// Abbreviation manager
     pub abbreviations: SimpleAbbreviationManager,
+    /// Buffer-local abbreviations, defined with `<buffer>`
+    pub(crate) buffer_abbreviations: HashMap<BufHandle, SimpleAbbreviationManager>,
     /// Mapping manager
     pub mappings: SimpleMappingManager,
     /// Visual selection anchor
     pub visual_anchor: Option<CursorPosition>,
+    /// Option values
+    pub options: TuiOptionManager,
+    /// Local option values of the buffers other than the current one
+    pub(crate) buffer_options: HashMap<BufHandle, LocalValues>,
+    /// Folds of the current window
+    pub folds: BufferFoldManager,
+    /// Folds the window keeps for the buffers it showed before
+    pub(crate) buffer_folds: HashMap<BufHandle, BufferFoldManager>,
+    /// Search engine holding the last search pattern
+    pub search: SimpleSearchEngine,
+    /// Command-line text and history
+    pub cmdline: TuiCmdline,
+    /// Digraphs typed with `Ctrl-K`
+    pub digraphs: DigraphTable,
+    /// Message or error shown in the command-line area
+    pub message: Option<String>,
+    /// Set by `:quit` and friends when the editor should exit
+    pub quit_requested: bool,
+    /// The exit status given with `:cquit`
+    pub exit_status: Option<i32>,
+    /// Autocommands
+    pub autocmds: TuiAutocmdManager,
+    /// Insert mode completion
+    pub completion: TuiCompletionEngine,
+    /// Functions 'completefunc' and 'omnifunc' can name
+    pub complete_funcs: HashMap<String, CompleteFunc>,
+    /// The quickfix list stack
+    pub quickfix: QuickfixStack,
+    /// The location list stacks of the windows
+    pub(crate) loclists: HashMap<WinHandle, QuickfixStack>,
+    /// The window the cursor is in
+    pub(crate) window: WinHandle,
+    /// Variables and user functions of Vimscript
+    pub vimscript: Interpreter,
+    /// Environment variables set (or removed, `None`) in the editor, over
+    /// the environment of the process; commands started get them too
+    pub(crate) env: HashMap<String, Option<String>>,
+    /// The argument list: the files given at startup
+    pub(crate) arglist: Vec<String>,
+    /// The index of the current file in the argument list
+    pub(crate) arg_idx: Option<usize>,
+    /// Set when running in Ex mode
+    pub(crate) exmode: Option<ExMode>,
+    /// Set while `:global` executes its command on the marked lines
+    pub(crate) global_busy: bool,
+    pub(crate) pending: PendingCommand,
+    pub(crate) cmdline_type: Option<char>,
+    pub(crate) expr_return: Option<ExprReturn>,
+    pub(crate) insert_ctrl_r: bool,
+    pub(crate) insert_ctrl_v: bool,
+    /// Where inserting started, set by the first key typed in Insert mode
+    pub(crate) insert_start: Option<CursorPosition>,
+    pub(crate) digraph: DigraphState,
+    pub(crate) cmdwin: Option<CmdWindow>,
+    pub(crate) qf_window: Option<QuickfixWindow>,
+    pub(crate) completion_session: CompletionSession,
+    pub(crate) wild: Option<WildSession>,
+    pub(crate) incsearch: Option<IncSearch>,
+    pub(crate) macros: MacroState,
+    pub(crate) redo: RedoState,
+    pub(crate) insert_restart: Option<InsertRestart>,
+    pub(crate) insert_repeat: Option<InsertRepeat>,
     last_char_find: Option<CharFindMotion>,
-    current_insert: Option<String>,
+    pub(crate) current_insert: Option<String>,
     block_op_context: Option<BlockOpContext>,
 }
 
@@ -60,9 +158,49 @@ impl Editor {
             modes: TuiModeManager::new(),
             registers: TuiRegisterBank::new(),
             marks: TuiMarkManager::new(),
+            extmarks: TuiExtmarkManager::new(),
             abbreviations: SimpleAbbreviationManager::new(),
+            buffer_abbreviations: HashMap::new(),
             mappings: SimpleMappingManager::new(),
             visual_anchor: None,
+            options: TuiOptionManager::new(),
+            buffer_options: HashMap::new(),
+            folds: BufferFoldManager::default(),
+            buffer_folds: HashMap::new(),
+            search: SimpleSearchEngine::default(),
+            cmdline: TuiCmdline::new(),
+            digraphs: DigraphTable::new(),
+            message: None,
+            quit_requested: false,
+            exit_status: None,
+            autocmds: TuiAutocmdManager::new(),
+            completion: TuiCompletionEngine::new(),
+            complete_funcs: HashMap::new(),
+            quickfix: QuickfixStack::new(),
+            loclists: HashMap::new(),
+            window: FIRST_WINDOW,
+            vimscript: Interpreter::new(),
+            env: HashMap::new(),
+            arglist: Vec::new(),
+            arg_idx: None,
+            exmode: None,
+            global_busy: false,
+            pending: PendingCommand::default(),
+            cmdline_type: None,
+            expr_return: None,
+            insert_ctrl_r: false,
+            insert_ctrl_v: false,
+            insert_start: None,
+            digraph: DigraphState::None,
+            cmdwin: None,
+            qf_window: None,
+            completion_session: CompletionSession::default(),
+            wild: None,
+            incsearch: None,
+            macros: MacroState::default(),
+            redo: RedoState::default(),
+            insert_restart: None,
+            insert_repeat: None,
             last_char_find: None,
             current_insert: None,
             block_op_context: None,
@@ -74,14 +212,31 @@ impl Editor {
         editor
     }
 
-    /// Sync cursor line lengths with current buffer
+    /// Sync cursor line lengths, folds and marks with current buffer
     pub fn sync_cursor_with_buffer(&mut self) {
         let lines = self
             .buffers
             .current()
             .get_lines(0, -1, false)
             .unwrap_or_default();
+        let handle = self.buffers.current().handle();
+        self.marks.set_current_buffer(handle);
+        for change in self.buffers.current_mut().take_changes() {
+            self.marks.splice(handle, &change.splice);
+            self.extmarks.splice(handle, &change.splice);
+            let old = change.last_line - change.first_line;
+            let new = change.new_last_line - change.first_line;
+            if new > old {
+                let at = LineNr(change.first_line + old + 1);
+                self.folds.adjust(at, (new - old) as i64);
+            } else if old > new {
+                let at = LineNr(change.first_line + new + 1);
+                self.folds.adjust(at, -((old - new) as i64));
+            }
+        }
+        self.folds.set_lines(lines.clone());
         self.cursor.update_line_lengths(&lines);
+        self.cursor.set_display(self.display_options());
         let pos = self.cursor.position();
         let ctx = self.cursor_context();
         let _ = self.cursor.set_position(pos, &ctx);
@@ -116,6 +271,9 @@ impl Editor {
             .map(|_| ())
             .map_err(|err| VimError::NotAllowedInMode(err.reason))?;
         self.current_insert = Some(String::new());
+        self.insert_start = None;
+        self.insert_repeat = None;
+        self.folds.view_line(self.cursor.line());
         Ok(())
     }
 
@@ -126,6 +284,9 @@ impl Editor {
             .map(|_| ())
             .map_err(|err| VimError::NotAllowedInMode(err.reason))?;
         self.current_insert = Some(String::new());
+        self.insert_start = None;
+        self.insert_repeat = None;
+        self.folds.view_line(self.cursor.line());
         Ok(())
     }
 
@@ -206,11 +367,10 @@ impl Editor {
         Ok(())
     }
 
-    /// Move cursor down
+    /// Move cursor down; a closed fold counts as a single line
     pub fn cursor_down(&mut self, count: usize) -> VimResult<()> {
         let ctx = self.cursor_context();
-        let new_line =
-            LineNr((self.cursor.line().0 + count).min(self.buffers.current().line_count()));
+        let new_line = self.line_below(self.cursor.line(), count);
         let want_col = self.cursor.curswant().value();
         self.cursor
             .set_position(CursorPosition::new(new_line, want_col), &ctx)?;
@@ -218,10 +378,10 @@ impl Editor {
         Ok(())
     }
 
-    /// Move cursor up
+    /// Move cursor up; a closed fold counts as a single line
     pub fn cursor_up(&mut self, count: usize) -> VimResult<()> {
         let ctx = self.cursor_context();
-        let new_line = LineNr(self.cursor.line().0.saturating_sub(count).max(1));
+        let new_line = self.line_above(self.cursor.line(), count);
         let want_col = self.cursor.curswant().value();
         self.cursor
             .set_position(CursorPosition::new(new_line, want_col), &ctx)?;
@@ -229,6 +389,33 @@ impl Editor {
         Ok(())
     }
 
+    /// The line `count` lines below `line`, stepping over closed folds.
+    pub fn line_below(&self, line: LineNr, count: usize) -> LineNr {
+        let line_count = self.buffers.current().line_count().max(1);
+        let mut line = line;
+        for _ in 0..count {
+            let end = self.closed_fold(line).map_or(line, |(_, end)| end);
+            if end.0 >= line_count {
+                break;
+            }
+            line = LineNr(end.0 + 1);
+        }
+        self.closed_fold(line).map_or(line, |(start, _)| start)
+    }
+
+    /// The line `count` lines above `line`, stepping over closed folds.
+    pub fn line_above(&self, line: LineNr, count: usize) -> LineNr {
+        let mut line = self.closed_fold(line).map_or(line, |(start, _)| start);
+        for _ in 0..count {
+            if line.0 <= 1 {
+                break;
+            }
+            let above = LineNr(line.0 - 1);
+            line = self.closed_fold(above).map_or(above, |(start, _)| start);
+        }
+        line
+    }
+
     /// Move cursor left
     pub fn cursor_left(&mut self, count: usize) -> VimResult<()> {
         let ctx = self.cursor_context();
@@ -253,36 +440,9 @@ impl Editor {
             return Ok(()); // Ignore in non-insert modes
         }
 
-        // Check for abbreviations (only on non-keyword chars)
-        // Simplified keyword check: alphanumeric or underscore
-        if !c.is_alphanumeric() && c != '_' {
-            let current_line = self.current_line();
-            let col = self.cursor.col();
-            if let Some((word, start_col)) = self.get_word_before_cursor(&current_line, col) {
-                // Check if an abbreviation exists and clone the RHS to avoid borrow issues
-                let expansion = self.abbreviations.check(self.modes.mode(), &word).map(|a| a.rhs.clone());
-                
-                if let Some(rhs) = expansion {
-                    // Remove the abbreviation
-                    // Move cursor to start of word
-                    let ctx = self.cursor_context();
-                    self.cursor.set_col(start_col, &ctx)?;
-                    
-                    // Delete the word
-                    for _ in 0..word.len() {
-                        self.delete_char()?;
-                    }
-                    
-                    // Insert the expansion (raw, to avoid recursive expansion loops immediately)
-                    // Note: Vim allows remapping in expansion, but here we just insert text.
-                    // If 'noremap' is false, we might need to feed keys? 
-                    // For now, let's just insert text raw.
-                    self.insert_text_raw(&rhs)?;
-                    
-                    // Insert the triggering character
-                    return self.insert_char_raw(c);
-                }
-            }
+        // A non-keyword character expands an abbreviation before it
+        if !self.search_options().iskeyword.contains(c) {
+            self.expand_insert_abbreviation()?;
         }
 
         self.insert_char_raw(c)
@@ -334,34 +494,6 @@ impl Editor {
         Ok(())
     }
 
-    /// Get word before cursor for abbreviation checking
-    fn get_word_before_cursor(&self, line: &str, col: usize) -> Option<(String, usize)> {
-        if col == 0 || col > line.len() {
-            return None;
-        }
-
-        let text_before = &line[..col];
-        // Find end of last non-keyword char
-        // Keyword chars: alphanumeric + '_'
-        let last_non_keyword = text_before.rfind(|c: char| !c.is_alphanumeric() && c != '_');
-        
-        let start = match last_non_keyword {
-            Some(idx) => idx + 1, // Start after the non-keyword
-            None => 0, // Start of line
-        };
-
-        if start >= col {
-            return None; // No word
-        }
-
-        let word = text_before[start..col].to_string();
-        if word.is_empty() {
-            None
-        } else {
-            Some((word, start))
-        }
-    }
-
     /// Insert a character from a nearby line (Ctrl-Y/Ctrl-E behavior).
     pub fn insert_from_adjacent_line(&mut self, line_offset: i64) -> VimResult<()> {
         if !self.modes.mode().allows_insertion() {
@@ -394,10 +526,14 @@ impl Editor {
 
     /// Put the contents of a register into the current buffer.
     pub fn put_register(&mut self, reg: Register, after: bool) -> VimResult<()> {
-        let Some(content) = self.registers.get(reg).cloned() else {
-            return Ok(());
-        };
+        match self.fetch_register(reg)? {
+            Some(content) => self.put_content(content, after),
+            None => Ok(()),
+        }
+    }
 
+    /// Put `content` into the current buffer, after or before the cursor.
+    pub fn put_content(&mut self, content: RegisterContent, after: bool) -> VimResult<()> {
         match content.reg_type {
             RegisterType::Linewise => {
                 let line = self.cursor.line().0 as i64;
@@ -540,21 +676,7 @@ impl Editor {
     }
 
     pub fn find_char(&mut self, motion: CharFindMotion) -> VimResult<bool> {
-        let actual_motion = match motion {
-            CharFindMotion::RepeatForward => match self.last_char_find {
-                Some(last) => last,
-                None => return Ok(false),
-            },
-            CharFindMotion::RepeatBackward => match self.last_char_find {
-                Some(last) => invert_char_find(last),
-                None => return Ok(false),
-            },
-            other => other,
-        };
-
-        let line = self.current_line();
-        let col = self.cursor.col();
-        let target_col = match char_find_target(&line, col, actual_motion) {
+        let target_col = match self.char_find_col(motion, 1) {
             Some(idx) => idx,
             None => return Ok(false),
         };
@@ -562,41 +684,494 @@ impl Editor {
         let ctx = self.cursor_context();
         self.cursor.set_col(target_col, &ctx)?;
         self.cursor.update_curswant();
-        self.last_char_find = Some(actual_motion);
         Ok(true)
     }
 
+    /// Resolve a character find (`f`, `t`, `;`, ...) repeated `count` times
+    /// to a column in the current line, remembering it for `;` and `,`.
+    pub fn char_find_col(&mut self, motion: CharFindMotion, count: usize) -> Option<usize> {
+        let actual_motion = match motion {
+            CharFindMotion::RepeatForward => self.last_char_find?,
+            CharFindMotion::RepeatBackward => invert_char_find(self.last_char_find?),
+            other => other,
+        };
+        if !matches!(
+            motion,
+            CharFindMotion::RepeatForward | CharFindMotion::RepeatBackward
+        ) {
+            self.last_char_find = Some(actual_motion);
+        }
+
+        let line = self.current_line();
+        let mut col = self.cursor.col();
+        for _ in 0..count.max(1) {
+            col = char_find_target(&line, col, actual_motion)?;
+        }
+        Some(col)
+    }
+
     pub fn match_bracket(&mut self) -> VimResult<bool> {
+        if let Some(target) = self.bracket_col() {
+            let ctx = self.cursor_context();
+            self.cursor.set_col(target, &ctx)?;
+            self.cursor.update_curswant();
+            return Ok(true);
+        }
+
+        Ok(false)
+    }
+
+    /// Column of the bracket matching the one under the cursor (`%`)
+    pub fn bracket_col(&self) -> Option<usize> {
         let line = self.current_line();
         let col = self.cursor.col();
-        let ch = match char_at(&line, col) {
-            Some(ch) => ch,
-            None => return Ok(false),
-        };
-        let (open, close, forward) = match ch {
+        let (open, close, forward) = match char_at(&line, col)? {
             '(' => ('(', ')', true),
             '[' => ('[', ']', true),
             '{' => ('{', '}', true),
             ')' => ('(', ')', false),
             ']' => ('[', ']', false),
             '}' => ('{', '}', false),
-            _ => return Ok(false),
+            _ => return None,
         };
 
-        let target_col = if forward {
+        if forward {
             find_matching_forward(&line, col, open, close)
         } else {
             find_matching_backward(&line, col, open, close)
+        }
+    }
+
+    // ========================================================================
+    // Options
+    // ========================================================================
+
+    /// Apply one `:set` argument and propagate the result to the components
+    /// that cache option values.
+    pub fn set_option(&mut self, arg: &str) -> VimResult<Option<String>> {
+        self.set_option_in(arg, SetScope::Both)
+    }
+
+    /// Apply one argument of `:set`, `:setlocal` or `:setglobal` to the
+    /// option values of `scope`, see [`Editor::set_option`]
+    pub fn set_option_in(&mut self, arg: &str, scope: SetScope) -> VimResult<Option<String>> {
+        let previous = self.options.clone();
+        let message = self.options.set_from_str_in(arg, scope)?;
+        if self.check_display_options().is_err() {
+            self.options = previous;
+            return Err(VimError::Error(474, format!("Invalid argument: {}", arg.trim())));
+        }
+        // Setting 'hlsearch' shows the highlighting again after :nohlsearch
+        if matches!(arg.trim(), "hlsearch" | "hls" | "invhlsearch" | "invhls") {
+            self.search.state_mut().highlighting = true;
+        }
+        self.apply_options();
+        Ok(message)
+    }
+
+    /// Push the current option values into folds and other components.
+    pub fn apply_options(&mut self) {
+        let method = match self.options.string("foldmethod") {
+            "indent" => FoldMethod::Indent,
+            "expr" => FoldMethod::Expr,
+            "marker" => FoldMethod::Marker,
+            "syntax" => FoldMethod::Syntax,
+            "diff" => FoldMethod::Diff,
+            _ => FoldMethod::Manual,
+        };
+        if self.folds.method() != method {
+            self.folds.set_method(method);
+        }
+        let fold_options = FoldOptions {
+            foldlevel: self.options.number("foldlevel").max(0) as usize,
+            foldignore: self.options.string("foldignore").to_string(),
+            foldnestmax: self.options.number("foldnestmax").max(0) as usize,
+            foldminlines: self.options.number("foldminlines").max(0) as usize,
+            foldmarker: self.options.string("foldmarker").to_string(),
+            foldopen: self.options.string("foldopen").to_string(),
+            foldclose: self.options.string("foldclose").to_string(),
+            shiftwidth: self.options.number("shiftwidth").max(0) as usize,
+            tabstop: self.options.number("tabstop").max(1) as usize,
         };
+        if *self.folds.options() != fold_options {
+            self.folds.set_options(fold_options);
+        }
+        self.cursor.set_display(self.display_options());
+    }
 
-        if let Some(target) = target_col {
-            let ctx = self.cursor_context();
-            self.cursor.set_col(target, &ctx)?;
-            self.cursor.update_curswant();
-            return Ok(true);
+    /// Search flags derived from the current options
+    pub fn search_options(&self) -> SearchOptions {
+        SearchOptions {
+            ignorecase: self.options.flag("ignorecase"),
+            smartcase: self.options.flag("smartcase"),
+            magic: self.options.flag("magic"),
+            wrapscan: self.options.flag("wrapscan"),
+            incsearch: self.options.flag("incsearch"),
+            hlsearch: self.options.flag("hlsearch"),
+            iskeyword: KeywordChars::parse(self.options.string("iskeyword")).unwrap_or_default(),
         }
+    }
 
-        Ok(false)
+    /// Keep the local option values of the buffer `previous` and take up
+    /// the ones of the current buffer, the global values for a buffer not
+    /// shown before.
+    pub(crate) fn swap_options(&mut self, previous: BufHandle) {
+        let current = self.buffers.current().handle();
+        let values = self.buffer_options.remove(&current);
+        let values = self.options.swap_buffer_values(values);
+        self.buffer_options.insert(previous, values);
+    }
+
+    // ========================================================================
+    // Folding
+    // ========================================================================
+
+    /// Keep the folds of the window for the buffer `previous` and take up
+    /// the ones it had for the current buffer, none for a buffer it did
+    /// not show before.
+    pub(crate) fn swap_folds(&mut self, previous: BufHandle) {
+        let current = self.buffers.current().handle();
+        let folds = self.buffer_folds.remove(&current).unwrap_or_default();
+        let folds = std::mem::replace(&mut self.folds, folds);
+        self.buffer_folds.insert(previous, folds);
+        self.apply_options();
+    }
+
+    /// The closed fold displayed at `line` as (start, end), honouring 'foldenable'
+    pub fn closed_fold(&self, line: LineNr) -> Option<(LineNr, LineNr)> {
+        if !self.options.flag("foldenable") {
+            return None;
+        }
+        self.folds
+            .closed_fold_at(line)
+            .map(|fold| (fold.start, fold.end))
+    }
+
+    /// The text displayed for the closed fold at `line`, padded with the
+    /// 'fillchars' fold item to `width` columns.
+    pub fn fold_line(&self, line: LineNr, width: usize) -> Option<String> {
+        if !self.options.flag("foldenable") {
+            return None;
+        }
+        let fold = self.folds.closed_fold_at(line)?;
+        let fill = self.options.fillchar("fold", '-');
+        let mut text: String = self.folds.fold_text(fold).chars().take(width).collect();
+        let len = text.chars().count();
+        text.extend(std::iter::repeat_n(fill, width - len));
+        Some(text)
+    }
+
+    /// Whether 'foldopen' asks for folds to be opened by `item` commands
+    pub fn fold_opens_for(&self, item: &str) -> bool {
+        self.options.has_item("foldopen", item) || self.options.has_item("foldopen", "all")
+    }
+
+    /// Open folds so the cursor line is visible, if 'foldopen' contains `item`.
+    pub fn open_folds_for(&mut self, item: &str) {
+        if self.fold_opens_for(item) {
+            self.folds.view_line(self.cursor.line());
+        }
+    }
+
+    /// Create a fold over `start..=end` (`zf`, `:fold`).  With the marker
+    /// method the markers are inserted into the text instead.
+    pub fn create_fold(&mut self, start: LineNr, end: LineNr) -> VimResult<()> {
+        if self.folds.method() != FoldMethod::Marker {
+            return self.folds.create(start, end);
+        }
+        let (open, close) = {
+            let (open, close) = self.folds.options().markers();
+            (open.to_string(), close.to_string())
+        };
+        for (line, marker) in [(start, open), (end, close)] {
+            let idx = line.to_zero_indexed() as i64;
+            let text = self.buffers.current().get_line(idx)?;
+            self.buffers
+                .current_mut()
+                .set_lines(idx, idx + 1, false, vec![format!("{}{}", text, marker)])?;
+        }
+        self.sync_cursor_with_buffer();
+        self.folds.view_line(self.cursor.line());
+        Ok(())
+    }
+
+    /// Delete the fold at `line` (`zd`); `recursive` also deletes nested
+    /// folds (`zD`).  With the marker method the markers are removed.
+    pub fn delete_fold(&mut self, line: LineNr, recursive: bool) -> VimResult<()> {
+        if self.folds.method() != FoldMethod::Marker {
+            self.folds.delete(line)?;
+            while recursive && self.folds.get(line).is_some() {
+                self.folds.delete(line)?;
+            }
+            return Ok(());
+        }
+        let fold = self
+            .folds
+            .closed_fold_at(line)
+            .or_else(|| self.folds.get(line))
+            .cloned()
+            .ok_or_else(|| VimError::Error(490, "No fold found".to_string()))?;
+        let mut targets = vec![(fold.start, fold.end)];
+        if recursive {
+            fn nested(folds: &[vxd::folds::Fold], out: &mut Vec<(LineNr, LineNr)>) {
+                for fold in folds {
+                    out.push((fold.start, fold.end));
+                    nested(&fold.nested, out);
+                }
+            }
+            nested(&fold.nested, &mut targets);
+        }
+        self.remove_fold_markers(&targets)
+    }
+
+    /// Delete every fold in the window (`zE`).
+    pub fn eliminate_folds(&mut self) -> VimResult<()> {
+        match self.folds.method() {
+            FoldMethod::Manual => {
+                self.folds.delete_all();
+                Ok(())
+            }
+            FoldMethod::Marker => {
+                let mut targets = Vec::new();
+                fn all(folds: &[vxd::folds::Fold], out: &mut Vec<(LineNr, LineNr)>) {
+                    for fold in folds {
+                        out.push((fold.start, fold.end));
+                        all(&fold.nested, out);
+                    }
+                }
+                all(self.folds.folds(), &mut targets);
+                self.remove_fold_markers(&targets)
+            }
+            _ => Err(VimError::Error(
+                352,
+                "Cannot erase folds with current 'foldmethod'".to_string(),
+            )),
+        }
+    }
+
+    fn remove_fold_markers(&mut self, targets: &[(LineNr, LineNr)]) -> VimResult<()> {
+        let (open, close) = {
+            let (open, close) = self.folds.options().markers();
+            (open.to_string(), close.to_string())
+        };
+        for &(start, end) in targets {
+            for (line, marker) in [(start, &open), (end, &close)] {
+                let idx = line.to_zero_indexed() as i64;
+                let text = self.buffers.current().get_line(idx)?;
+                let Some(pos) = text.find(marker.as_str()) else {
+                    continue;
+                };
+                let mut marker_end = pos + marker.len();
+                while text[marker_end..].starts_with(|c: char| c.is_ascii_digit()) {
+                    marker_end += 1;
+                }
+                let new_text = format!("{}{}", &text[..pos], &text[marker_end..]);
+                self.buffers
+                    .current_mut()
+                    .set_lines(idx, idx + 1, false, vec![new_text])?;
+            }
+        }
+        self.sync_cursor_with_buffer();
+        Ok(())
+    }
+
+    /// Change 'foldlevel' through the fold manager (`zm`, `zr`, `zM`, `zR`)
+    /// and store the result back into the option.
+    pub fn update_foldlevel(&mut self, change: impl FnOnce(&mut BufferFoldManager)) {
+        change(&mut self.folds);
+        let level = self.folds.foldlevel() as i64;
+        let _ = self.options.set_number("foldlevel", level);
+    }
+
+    /// Move the cursor to the first line of the closed fold it is in, like
+    /// Vim does after every Normal mode command.
+    pub fn adjust_cursor_for_folds(&mut self) {
+        if let Some((start, _)) = self.closed_fold(self.cursor.line()) {
+            if start != self.cursor.line() {
+                let ctx = self.cursor_context();
+                let _ = self.cursor.set_line(start, &ctx);
+            }
+        }
+    }
+
+    // ========================================================================
+    // Search
+    // ========================================================================
+
+    /// Search for `pattern` in `direction` from the cursor (`/`, `?`).
+    /// An empty pattern repeats the last one.
+    pub fn search_pattern(&mut self, pattern: &str, direction: Direction) -> VimResult<()> {
+        let pattern = if pattern.is_empty() {
+            self.search
+                .last_pattern()
+                .map(|last| last.pattern.clone())
+                .ok_or_else(no_previous_pattern)?
+        } else {
+            pattern.to_string()
+        };
+        let mut compiled = self.search.compile(&pattern, &self.search_options())?;
+        compiled.direction = direction;
+        self.search.set_last_pattern(compiled.clone());
+        self.search.state_mut().last_direction = direction;
+        self.registers.set_last_search(&compiled.pattern);
+        self.cmdline.hist_add(
+            match direction {
+                Direction::Forward => CmdlineHistoryKind::SearchForward,
+                Direction::Backward => CmdlineHistoryKind::SearchBackward,
+            },
+            &compiled.pattern,
+        );
+        self.search.state_mut().highlighting = true;
+        self.jump_to_match(&compiled)
+    }
+
+    /// Repeat the last search (`n`), or in the opposite direction (`N`).
+    pub fn search_next(&mut self, reverse: bool) -> VimResult<()> {
+        let mut pattern = self
+            .search
+            .last_pattern()
+            .cloned()
+            .ok_or_else(no_previous_pattern)?;
+        if reverse {
+            pattern.direction = pattern.direction.reverse();
+        }
+        self.search.state_mut().highlighting = true;
+        self.jump_to_match(&pattern)
+    }
+
+    /// Find the next match of `pattern` from the cursor without moving it.
+    /// Returns where it starts and whether the search wrapped around the
+    /// end of the buffer.
+    pub fn find_match(&mut self, pattern: &SearchPattern) -> VimResult<(CursorPosition, bool)> {
+        let lines = self
+            .buffers
+            .current()
+            .get_lines(0, -1, false)
+            .unwrap_or_default();
+        let pos = self.cursor.position();
+        let line = lines.get(pos.line.to_zero_indexed()).map_or("", |l| l.as_str());
+        let from = match pattern.direction {
+            Direction::Forward => {
+                let next = line
+                    .get(pos.col..)
+                    .and_then(|rest| rest.chars().next())
+                    .map_or(pos.col + 1, |ch| pos.col + ch.len_utf8());
+                CursorPosition::new(pos.line, next)
+            }
+            Direction::Backward => pos,
+        };
+        self.search.set_lines(lines);
+
+        let mut options = self.search_options();
+        let wrapscan = options.wrapscan;
+        options.wrapscan = false;
+        if let Some(found) = self.search.search(pattern, from, &options)? {
+            return Ok((found.start, false));
+        }
+        if !wrapscan {
+            return Err(match pattern.direction {
+                Direction::Forward => VimError::Error(
+                    385,
+                    format!("Search hit BOTTOM without match for: {}", pattern.pattern),
+                ),
+                Direction::Backward => VimError::Error(
+                    384,
+                    format!("Search hit TOP without match for: {}", pattern.pattern),
+                ),
+            });
+        }
+        options.wrapscan = true;
+        match self.search.search(pattern, from, &options)? {
+            Some(found) => {
+                self.message = Some(match pattern.direction {
+                    Direction::Forward => "search hit BOTTOM, continuing at TOP".to_string(),
+                    Direction::Backward => "search hit TOP, continuing at BOTTOM".to_string(),
+                });
+                Ok((found.start, true))
+            }
+            None => Err(VimError::PatternNotFound(pattern.pattern.clone())),
+        }
+    }
+
+    fn jump_to_match(&mut self, pattern: &SearchPattern) -> VimResult<()> {
+        let (target, wrapped) = self.find_match(pattern)?;
+        let handle = self.buffers.current().handle();
+        self.marks.push_jump(handle, self.cursor.position());
+        let ctx = self.cursor_context();
+        self.cursor.set_position(target, &ctx)?;
+        self.cursor.update_curswant();
+        self.open_folds_for("search");
+        self.show_search_count(pattern, target, wrapped);
+        Ok(())
+    }
+
+    /// Limits for counting matches at the cursor: 'maxsearchcount' and the
+    /// timeout Vim uses for the count shown after a search
+    pub fn search_count_options(&self) -> SearchCountOptions {
+        SearchCountOptions {
+            pos: self.cursor.position(),
+            maxcount: self.options.number("maxsearchcount").max(0) as usize,
+            timeout: Some(Duration::from_millis(40)),
+        }
+    }
+
+    /// Count the matches of the last search pattern like `searchcount()`
+    pub fn searchcount(&mut self, count: &SearchCountOptions) -> VimResult<SearchCount> {
+        let pattern = self
+            .search
+            .last_pattern()
+            .cloned()
+            .ok_or_else(no_previous_pattern)?;
+        self.count_matches(&pattern, count)
+    }
+
+    /// Count the matches of `pattern` in the buffer
+    pub(crate) fn count_matches(
+        &mut self,
+        pattern: &SearchPattern,
+        count: &SearchCountOptions,
+    ) -> VimResult<SearchCount> {
+        let lines = self
+            .buffers
+            .current()
+            .get_lines(0, -1, false)
+            .unwrap_or_default();
+        let last_line = LineNr(lines.len());
+        self.search.set_lines(lines);
+        search_count(&self.search, pattern, last_line, &self.search_options(), count)
+    }
+
+    /// Show the pattern and `[N/M]` for a search that moved to `target`,
+    /// unless 'shortmess' contains "S".  A "W" marks a search that
+    /// `wrapped` around the end of the buffer.
+    pub(crate) fn show_search_count(
+        &mut self,
+        pattern: &SearchPattern,
+        target: CursorPosition,
+        wrapped: bool,
+    ) {
+        if self.options.string("shortmess").contains('S') {
+            return;
+        }
+        let count = SearchCountOptions {
+            pos: target,
+            ..self.search_count_options()
+        };
+        let Ok(count) = self.searchcount(&count) else {
+            return;
+        };
+        let wrapped = if wrapped { "W " } else { "" };
+        let command = match pattern.direction {
+            Direction::Forward => '/',
+            Direction::Backward => '?',
+        };
+        self.message = Some(format!(
+            "{}{}  {}{}",
+            command,
+            pattern.pattern,
+            wrapped,
+            count.display()
+        ));
     }
 
     // ========================================================================
@@ -778,6 +1353,7 @@ impl Editor {
                     reg_type: RegisterType::Blockwise { width },
                 };
                 self.registers.set(Register::Unnamed, content.clone())?;
+                self.copy_unnamed_to_clipboard(&content)?;
                 self.registers.set(Register::Named('0'), content)?;
 
                 self.escape()?;
@@ -803,6 +1379,7 @@ impl Editor {
                     reg_type: RegisterType::Linewise,
                 };
                 self.registers.set(Register::Unnamed, content.clone())?;
+                self.copy_unnamed_to_clipboard(&content)?;
                 self.registers.set(Register::Named('0'), content)?;
                 
                 self.escape()?;
@@ -863,6 +1440,7 @@ impl Editor {
                      reg_type: RegisterType::Characterwise,
                 };
                 self.registers.set(Register::Unnamed, content.clone())?;
+                self.copy_unnamed_to_clipboard(&content)?;
                 self.registers.set(Register::Named('0'), content)?;
                 
                 self.escape()?;
@@ -876,6 +1454,10 @@ impl Editor {
     }
 }
 
+pub(crate) fn no_previous_pattern() -> VimError {
+    VimError::Error(35, "No previous regular expression".to_string())
+}
+
 fn invert_char_find(motion: CharFindMotion) -> CharFindMotion {
     match motion {
         CharFindMotion::FindForward(c) => CharFindMotion::FindBackward(c),
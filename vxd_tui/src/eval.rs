@@ -0,0 +1,411 @@
+//! Expressions in the editor.
+//!
+//! The editor is the [`EvalContext`] of expressions: `&opt` reads its
+//! options, `@r` its registers, and it adds the functions that look at
+//! the buffer and modes, such as `line()`, `getline()`, `mode()` and
+//! `searchcount()`.
+//!
+//! The `=` register holds the last expression typed on the expression
+//! command line and is evaluated each time it is used.  The expression
+//! command line is opened by `"=` in Normal mode, where the register is
+//! then used by the next command, and by `Ctrl-R =` in Insert mode and on
+//! the command line, where the result is inserted.  An empty expression
+//! uses the last one again.
+//!
+//! Variables and user functions come from the Vimscript interpreter.
+
+use std::time::Duration;
+
+use vxd::buffer::{Buffer, BufferManager};
+use vxd::cmdline::{Cmdline, CmdlineHistory, CmdlineHistoryKind};
+use vxd::cursor::{Cursor, CursorPosition};
+use vxd::eval::{self, Dict, EvalContext, Value};
+use vxd::evalfunc::{dict_from, expression_text};
+use vxd::marks::{Mark, MarkManager};
+use vxd::modes::{Mode, ModeManager};
+use vxd::options::{OptionManager, OptionValue};
+use vxd::registers::{Register, RegisterBank, RegisterContent, RegisterType};
+use vxd::search::{SearchCountIncomplete, SearchEngine, SearchPattern};
+use vxd::types::{LineNr, VimError, VimResult};
+
+use crate::editor::Editor;
+use crate::key::{parse_raw_keys, Key};
+
+/// Where the result of the expression command line goes
+#[derive(Debug, Clone)]
+pub(crate) enum ExprReturn {
+    /// `"=`: the `=` register is used by the next command
+    Register,
+    /// `Ctrl-R =` in Insert or Replace mode (`mode`), with the cursor at
+    /// `position`
+    Insert {
+        mode: Mode,
+        position: CursorPosition,
+    },
+    /// `Ctrl-R =` on a `kind` command line with `text` and the cursor at
+    /// byte `pos`
+    Cmdline {
+        kind: char,
+        text: String,
+        pos: usize,
+    },
+}
+
+/// Editor functions with the least and most arguments they take
+const FUNCTIONS: &[(&str, usize, usize)] = &[
+    ("col", 1, 1),
+    ("getline", 1, 2),
+    ("line", 1, 1),
+    ("mode", 0, 1),
+    ("pumvisible", 0, 0),
+    ("reg_executing", 0, 0),
+    ("reg_recorded", 0, 0),
+    ("reg_recording", 0, 0),
+    ("searchcount", 0, 1),
+];
+
+impl Editor {
+    /// Evaluate expression `text`
+    pub fn eval_expression(&mut self, text: &str) -> VimResult<Value> {
+        eval::eval_expr(text, self)
+    }
+
+    /// The keys an `<expr>` mapping gives: its `rhs` evaluated.  An error
+    /// is shown and gives no keys.
+    pub(crate) fn expr_mapping_keys(&mut self, rhs: &str) -> Vec<Key> {
+        match self.eval_expression(rhs).and_then(|value| expression_text(&value)) {
+            Ok(keys) => parse_raw_keys(&keys),
+            Err(err) => {
+                self.message = Some(err.to_string());
+                Vec::new()
+            }
+        }
+    }
+
+    /// The content of `reg`, evaluating the expression of the `=`
+    /// register.  A List result is put as lines, like a String ending in
+    /// a line break.
+    pub(crate) fn fetch_register(&mut self, reg: Register) -> VimResult<Option<RegisterContent>> {
+        if reg != Register::Expression {
+            return Ok(self.registers.fetch(reg));
+        }
+        let Some(expr) = self.registers.last_expression().map(str::to_string) else {
+            return Ok(None);
+        };
+        let value = self.eval_expression(&expr)?;
+        let content = match &value {
+            Value::List(list) => RegisterContent::linewise(
+                list.borrow()
+                    .iter()
+                    .map(Value::as_string)
+                    .collect::<VimResult<_>>()?,
+            ),
+            value => {
+                let text = expression_text(value)?;
+                match text.strip_suffix('\n') {
+                    Some(lines) => {
+                        RegisterContent::linewise(lines.split('\n').map(str::to_string).collect())
+                    }
+                    None => RegisterContent {
+                        text: text.split('\n').map(str::to_string).collect(),
+                        reg_type: RegisterType::Characterwise,
+                    },
+                }
+            }
+        };
+        Ok(Some(content))
+    }
+
+    /// Open the expression command line; its result goes to `target`
+    pub(crate) fn enter_expr_cmdline(&mut self, target: ExprReturn) -> VimResult<()> {
+        match &target {
+            ExprReturn::Register => self.enter_cmdline_type('=')?,
+            ExprReturn::Insert { .. } => {
+                // Insert mode is only left for the expression, without
+                // ending the insert
+                self.modes
+                    .escape_to_normal()
+                    .map_err(|err| VimError::NotAllowedInMode(err.reason))?;
+                self.enter_cmdline_type('=')?;
+            }
+            ExprReturn::Cmdline { .. } => {
+                self.cmdline.setcmdline("")?;
+                self.cmdline.reset_recall();
+                self.cmdline_type = Some('=');
+            }
+        }
+        self.expr_return = Some(target);
+        Ok(())
+    }
+
+    /// `<CR>` (`accept`) or `<Esc>` on the expression command line
+    pub(crate) fn finish_expr_cmdline(&mut self, accept: bool) -> VimResult<()> {
+        let typed = self.cmdline.getcmdline().to_string();
+        let target = self.expr_return.take().unwrap_or(ExprReturn::Register);
+        if accept && !typed.trim().is_empty() {
+            self.cmdline
+                .hist_add(CmdlineHistoryKind::Expression, &typed);
+            self.registers.set_last_expression(&typed);
+        }
+        let has_expr = self.registers.last_expression().is_some();
+        match target {
+            ExprReturn::Register => {
+                self.leave_expr_cmdline()?;
+                if accept && has_expr {
+                    self.set_pending_register('=');
+                } else {
+                    self.cancel_pending();
+                }
+                Ok(())
+            }
+            ExprReturn::Insert { mode, position } => {
+                self.leave_expr_cmdline()?;
+                match mode {
+                    Mode::Replace => self.modes.enter_replace(),
+                    _ => self.modes.enter_insert(),
+                }
+                .map_err(|err| VimError::NotAllowedInMode(err.reason))?;
+                let ctx = self.cursor_context();
+                self.cursor.set_position(position, &ctx)?;
+                if !accept {
+                    return Ok(());
+                }
+                match self.fetch_register(Register::Expression)? {
+                    Some(content) => self.insert_text(&content.as_string()),
+                    None => Ok(()),
+                }
+            }
+            ExprReturn::Cmdline { kind, text, pos } => {
+                self.cmdline_type = Some(kind);
+                self.cmdline.setcmdline(&text)?;
+                self.cmdline.setcmdpos(pos + 1)?;
+                if !accept {
+                    return Ok(());
+                }
+                match self.fetch_register(Register::Expression)? {
+                    Some(content) => self.cmdline.paste_register(&content, false),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+
+    fn leave_expr_cmdline(&mut self) -> VimResult<()> {
+        self.cmdline_type = None;
+        self.modes
+            .escape_to_normal()
+            .map(|_| ())
+            .map_err(|err| VimError::NotAllowedInMode(err.reason))
+    }
+
+    /// Line number `expr` names for `line()` and `getline()`: `.` is the
+    /// cursor line, `$` the last line and `'x` the line of mark `x`.  0
+    /// when there is no such line.
+    fn line_of(&self, expr: &Value) -> VimResult<usize> {
+        let text = match expr {
+            Value::Number(nr) => return Ok((*nr).max(0) as usize),
+            Value::String(text) => text,
+            value => return Ok(value.as_number()?.max(0) as usize),
+        };
+        Ok(match text.as_str() {
+            "." => self.cursor.line().0,
+            "$" => self.buffers.current().line_count(),
+            "v" => self
+                .visual_anchor
+                .filter(|_| self.modes.mode().is_visual())
+                .map_or(self.cursor.line().0, |anchor| anchor.line.0),
+            text => match text.strip_prefix('\'') {
+                Some(mark) => self.mark_position(mark).map_or(0, |pos| pos.line.0),
+                None => eval::str2nr(text, 10).max(0) as usize,
+            },
+        })
+    }
+
+    fn mark_position(&self, name: &str) -> Option<CursorPosition> {
+        let mut chars = name.chars();
+        let (Some(c), None) = (chars.next(), chars.next()) else {
+            return None;
+        };
+        let value = self.marks.get(Mark::from_char(c).ok()?)?;
+        let current = self.buffers.current().handle();
+        value
+            .buffer
+            .is_none_or(|buffer| buffer == current)
+            .then_some(value.position)
+    }
+
+    /// `col()`: the byte column of `.`, `$` (one past the end of the
+    /// line) or `'x`, starting at 1; 0 when there is no such position
+    fn col_of(&self, expr: &Value) -> VimResult<i64> {
+        let text = expr.as_string()?;
+        let line_len = |line: usize| {
+            self.buffers
+                .current()
+                .get_line(line as i64 - 1)
+                .map_or(0, |text| text.len())
+        };
+        Ok(match text.as_str() {
+            "." => self.cursor.col() as i64 + 1,
+            "$" => line_len(self.cursor.line().0) as i64 + 1,
+            text => match text
+                .strip_prefix('\'')
+                .and_then(|mark| self.mark_position(mark))
+            {
+                Some(pos) => pos.col as i64 + 1,
+                None => 0,
+            },
+        })
+    }
+
+    /// `searchcount()`: count the matches of the last search pattern, or
+    /// of "pattern" in `options`, with the current one at the cursor or at
+    /// "pos", up to "maxcount" matches and for "timeout" milliseconds.
+    /// Without a pattern the result is empty.
+    fn searchcount_value(&mut self, options: Option<&Value>) -> VimResult<Value> {
+        let mut count = self.search_count_options();
+        let mut pattern = self.search.last_pattern().cloned();
+        if let Some(options) = options {
+            let Value::Dict(options) = options else {
+                return Err(VimError::Error(715, "Dictionary required".to_string()));
+            };
+            let options = options.borrow();
+            if let Some(text) = options.get("pattern") {
+                pattern = Some(SearchPattern::forward(&text.as_string()?));
+            }
+            if let Some(max) = options.get("maxcount") {
+                count.maxcount = max.as_number()?.max(0) as usize;
+            }
+            if let Some(timeout) = options.get("timeout") {
+                let ms = timeout.as_number()?;
+                count.timeout = (ms > 0).then(|| Duration::from_millis(ms as u64));
+            }
+            if let Some(pos) = options.get("pos") {
+                let Value::List(pos) = pos else {
+                    return Err(VimError::Error(714, "List required".to_string()));
+                };
+                let pos = pos.borrow();
+                if pos.len() != 3 {
+                    let pos = Value::list(pos.clone()).to_display();
+                    return Err(VimError::Error(475, format!("Invalid argument: {}", pos)));
+                }
+                let line = pos[0].as_number()?.max(1) as usize;
+                let col = pos[1].as_number()?.max(1) as usize;
+                count.pos = CursorPosition::new(LineNr(line), col - 1);
+            }
+        }
+        let Some(pattern) = pattern else {
+            return Ok(Value::dict(Default::default()));
+        };
+        let result = self.count_matches(&pattern, &count)?;
+        let incomplete = match result.incomplete {
+            SearchCountIncomplete::Complete => 0,
+            SearchCountIncomplete::TimedOut => 1,
+            SearchCountIncomplete::MaxCountExceeded => 2,
+        };
+        Ok(dict_from([
+            ("current", Value::Number(result.current as i64)),
+            ("total", Value::Number(result.total as i64)),
+            ("exact_match", Value::bool(result.exact_match)),
+            ("incomplete", Value::Number(incomplete)),
+            ("maxcount", Value::Number(result.maxcount as i64)),
+        ]))
+    }
+
+    fn editor_function(&mut self, name: &str, args: &[Value]) -> VimResult<Value> {
+        match name {
+            "line" => Ok(Value::Number(self.line_of(&args[0])? as i64)),
+            "col" => Ok(Value::Number(self.col_of(&args[0])?)),
+            "getline" => {
+                let lines = self.buffer_lines();
+                let line = |nr: usize| {
+                    (1..=lines.len())
+                        .contains(&nr)
+                        .then(|| lines[nr - 1].clone())
+                };
+                let start = self.line_of(&args[0])?;
+                let Some(end) = args.get(1) else {
+                    return Ok(Value::String(line(start).unwrap_or_default()));
+                };
+                let end = self.line_of(end)?;
+                Ok(Value::string_list((start.max(1)..=end).map_while(line)))
+            }
+            "mode" => {
+                let code = self.modes.mode().code();
+                let full = args.first().map_or(Ok(false), Value::is_true)?;
+                Ok(Value::string(if full {
+                    code
+                } else {
+                    &code[..code.chars().next().map_or(0, char::len_utf8)]
+                }))
+            }
+            "pumvisible" => Ok(Value::bool(self.pum_visible())),
+            "searchcount" => self.searchcount_value(args.first()),
+            _ => {
+                let reg = match name {
+                    "reg_executing" => self.reg_executing(),
+                    "reg_recorded" => self.reg_recorded(),
+                    _ => self.reg_recording(),
+                };
+                Ok(Value::String(reg.map(String::from).unwrap_or_default()))
+            }
+        }
+    }
+}
+
+impl EvalContext for Editor {
+    fn option(&self, name: &str, _scope: Option<char>) -> VimResult<Value> {
+        match self.options.get(name) {
+            Some(OptionValue::Boolean(flag)) => Ok(Value::bool(*flag)),
+            Some(OptionValue::Number(nr)) => Ok(Value::Number(*nr)),
+            Some(OptionValue::String(text)) => Ok(Value::string(text.as_str())),
+            None => Err(eval::unknown_option(name)),
+        }
+    }
+
+    fn register(&mut self, reg: char) -> VimResult<String> {
+        let text = match reg {
+            ':' => self.registers.last_command().map(str::to_string),
+            '/' => self.registers.last_search().map(str::to_string),
+            '=' => self.registers.last_expression().map(str::to_string),
+            reg => {
+                let reg = Register::from_char(reg)?;
+                self.registers.fetch(reg).map(|content| content.as_string())
+            }
+        };
+        Ok(text.unwrap_or_default())
+    }
+
+    fn env(&self, name: &str) -> Option<String> {
+        self.env_var(name)
+    }
+
+    fn variable(&self, name: &str) -> Option<Value> {
+        self.vimscript.variable(name, self)
+    }
+
+    fn call(&mut self, name: &str, args: &[Value]) -> Option<VimResult<Value>> {
+        let Some(&(_, min, max)) = FUNCTIONS.iter().find(|(function, _, _)| *function == name)
+        else {
+            return self.vimscript.clone().call(name, args, None, self);
+        };
+        if args.len() < min {
+            return Some(Err(eval::not_enough_arguments(name)));
+        }
+        if args.len() > max {
+            return Some(Err(VimError::Error(
+                118,
+                format!("Too many arguments for function: {}", name),
+            )));
+        }
+        Some(self.editor_function(name, args))
+    }
+
+    fn call_dict(&mut self, name: &str, args: &[Value], dict: &Dict) -> Option<VimResult<Value>> {
+        self.vimscript.clone().call(name, args, Some(dict), self)
+    }
+
+    fn has_function(&self, name: &str) -> bool {
+        FUNCTIONS.iter().any(|(function, _, _)| *function == name)
+            || self.vimscript.has_function(name)
+    }
+}
@@ -0,0 +1,885 @@
+//! Ex command execution.
+//!
+//! This module implements `vxd::commands::CommandExecutor` for the editor:
+//! command lookup by abbreviation, range resolution and the built-in
+//! commands.
+
+use std::sync::OnceLock;
+
+use vxd::buffer::{Buffer, BufferManager};
+use vxd::commands::{
+    parse_command_range, CommandDef, CommandExecutor, CommandFlags, CommandRange, CommandResult,
+    CompleteType, ExCommand, LineSpec,
+};
+use vxd::cursor::{Cursor, CursorPosition};
+use vxd::eval::{self, SubmatchContext};
+use vxd::evalfunc::expression_text;
+use vxd::folds::FoldManager;
+use vxd::marks::{Mark, MarkManager};
+use vxd::operators::Operator;
+use vxd::registers::RegisterBank;
+use vxd::search::{
+    substitute_lines, substitute_lines_with, SearchEngine, SearchPattern, SubstituteSpec,
+};
+use vxd::types::{LineNr, LineRange, VimError, VimResult};
+use vxd::vimscript;
+
+use crate::abbrev::{is_abbrev_command, ABBREV_COMMANDS};
+use crate::editor::{no_previous_pattern, Editor};
+use crate::exit::handle_ex_quit;
+use crate::operator::MotionRange;
+use crate::options::SetScope;
+use crate::quickfix::{is_quickfix_command, QUICKFIX_COMMANDS};
+
+/// Built-in commands, in lookup priority order for ambiguous abbreviations
+fn command_table() -> &'static [CommandDef] {
+    static TABLE: OnceLock<Vec<CommandDef>> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let flags = |range: bool, bang: bool, args: bool, modify: bool| CommandFlags {
+            range,
+            count: false,
+            register: false,
+            bang,
+            args,
+            modify,
+        };
+        let def = |name: &str,
+                   min_abbrev: usize,
+                   flags: CommandFlags,
+                   complete: CompleteType,
+                   description: &str| CommandDef {
+            name: name.to_string(),
+            min_abbrev,
+            flags,
+            complete,
+            description: description.to_string(),
+        };
+        let mut table = vec![
+            def(
+                "quit",
+                1,
+                flags(false, true, false, false),
+                CompleteType::Nothing,
+                "Quit the current window",
+            ),
+            def(
+                "wq",
+                2,
+                flags(false, true, false, false),
+                CompleteType::Nothing,
+                "Write and quit",
+            ),
+            def(
+                "xit",
+                1,
+                flags(false, true, false, false),
+                CompleteType::Nothing,
+                "Write if modified and quit",
+            ),
+            def(
+                "qall",
+                2,
+                flags(false, true, false, false),
+                CompleteType::Nothing,
+                "Quit, unless a buffer has changes",
+            ),
+            def(
+                "wqall",
+                3,
+                flags(false, true, false, false),
+                CompleteType::Nothing,
+                "Write all changed buffers and quit",
+            ),
+            def(
+                "xall",
+                2,
+                flags(false, true, false, false),
+                CompleteType::Nothing,
+                "Write all changed buffers and quit",
+            ),
+            def(
+                "edit",
+                1,
+                flags(false, true, true, false),
+                CompleteType::File,
+                "Edit a file",
+            ),
+            def(
+                "set",
+                2,
+                flags(false, false, true, false),
+                CompleteType::Option,
+                "Set options",
+            ),
+            def(
+                "setlocal",
+                4,
+                flags(false, false, true, false),
+                CompleteType::Option,
+                "Set local options",
+            ),
+            def(
+                "setglobal",
+                4,
+                flags(false, false, true, false),
+                CompleteType::Option,
+                "Set global options",
+            ),
+            def(
+                "fold",
+                2,
+                flags(true, false, false, false),
+                CompleteType::Nothing,
+                "Create a fold",
+            ),
+            def(
+                "foldopen",
+                5,
+                flags(true, true, false, false),
+                CompleteType::Nothing,
+                "Open folds in a range",
+            ),
+            def(
+                "foldclose",
+                5,
+                flags(true, true, false, false),
+                CompleteType::Nothing,
+                "Close folds in a range",
+            ),
+            def(
+                "autocmd",
+                2,
+                flags(false, true, true, false),
+                CompleteType::Event,
+                "Define or list autocommands",
+            ),
+            def(
+                "augroup",
+                3,
+                flags(false, true, true, false),
+                CompleteType::Augroup,
+                "Select the autocommand group",
+            ),
+            def(
+                "doautocmd",
+                2,
+                flags(false, false, true, false),
+                CompleteType::Event,
+                "Apply autocommands for an event",
+            ),
+            def(
+                "substitute",
+                1,
+                flags(true, false, true, true),
+                CompleteType::Nothing,
+                "Find and replace text",
+            ),
+            def(
+                "global",
+                1,
+                flags(true, true, true, true),
+                CompleteType::Nothing,
+                "Execute a command on the lines matching a pattern",
+            ),
+            def(
+                "vglobal",
+                1,
+                flags(true, false, true, true),
+                CompleteType::Nothing,
+                "Execute a command on the lines not matching a pattern",
+            ),
+            def(
+                "delete",
+                1,
+                flags(true, false, true, true),
+                CompleteType::Nothing,
+                "Delete lines",
+            ),
+            def(
+                "move",
+                1,
+                flags(true, false, true, true),
+                CompleteType::Nothing,
+                "Move lines",
+            ),
+            def(
+                "nohlsearch",
+                3,
+                flags(false, false, false, false),
+                CompleteType::Nothing,
+                "Suspend 'hlsearch' highlighting",
+            ),
+            def(
+                "vimgrep",
+                3,
+                flags(false, true, true, false),
+                CompleteType::File,
+                "Search files for a pattern into the quickfix list",
+            ),
+            def(
+                "lvimgrep",
+                2,
+                flags(false, true, true, false),
+                CompleteType::File,
+                "Search files for a pattern into the location list",
+            ),
+            def(
+                "make",
+                3,
+                flags(false, true, true, false),
+                CompleteType::File,
+                "Run 'makeprg' and go to the first error",
+            ),
+            def(
+                "lmake",
+                4,
+                flags(false, true, true, false),
+                CompleteType::File,
+                "Run 'makeprg' into the location list",
+            ),
+            def(
+                "grep",
+                2,
+                flags(false, true, true, false),
+                CompleteType::File,
+                "Run 'grepprg' and go to the first match",
+            ),
+            def(
+                "lgrep",
+                3,
+                flags(false, true, true, false),
+                CompleteType::File,
+                "Run 'grepprg' into the location list",
+            ),
+            def(
+                "normal",
+                4,
+                flags(true, true, true, false),
+                CompleteType::Nothing,
+                "Execute Normal mode commands",
+            ),
+            def(
+                "@",
+                1,
+                flags(true, false, true, false),
+                CompleteType::Nothing,
+                "Execute a register as Ex commands",
+            ),
+            def(
+                "source",
+                2,
+                flags(false, false, true, false),
+                CompleteType::File,
+                "Execute the commands in a file",
+            ),
+            def(
+                "write",
+                1,
+                flags(true, true, true, false),
+                CompleteType::File,
+                "Write the buffer to a file",
+            ),
+            def(
+                "print",
+                1,
+                flags(true, false, false, false),
+                CompleteType::Nothing,
+                "Print lines",
+            ),
+            def(
+                "number",
+                2,
+                flags(true, false, false, false),
+                CompleteType::Nothing,
+                "Print lines with their line number",
+            ),
+            def(
+                "cquit",
+                2,
+                CommandFlags {
+                    count: true,
+                    ..flags(false, true, true, false)
+                },
+                CompleteType::Nothing,
+                "Quit without writing, with an error exit status",
+            ),
+            def(
+                "digraphs",
+                3,
+                flags(false, true, true, false),
+                CompleteType::Nothing,
+                "List or define digraphs",
+            ),
+            def(
+                "ascii",
+                2,
+                flags(false, false, false, false),
+                CompleteType::Nothing,
+                "Show the value of the character under the cursor",
+            ),
+        ];
+        for &(name, min_abbrev, description) in ABBREV_COMMANDS {
+            table.push(def(
+                name,
+                min_abbrev,
+                flags(false, false, true, false),
+                CompleteType::Nothing,
+                description,
+            ));
+        }
+        for &(c_name, c_min, l_name, l_min, description) in QUICKFIX_COMMANDS {
+            let counted = CommandFlags {
+                count: true,
+                ..flags(false, true, true, false)
+            };
+            for (name, min_abbrev) in [(c_name, c_min), (l_name, l_min)] {
+                table.push(def(
+                    name,
+                    min_abbrev,
+                    counted,
+                    CompleteType::Nothing,
+                    description,
+                ));
+            }
+        }
+        table.extend(vimscript::commands().iter().cloned());
+        table
+    })
+}
+
+/// Find the command `name` abbreviates
+pub(crate) fn lookup(name: &str) -> Option<&'static CommandDef> {
+    let table = command_table();
+    table.iter().find(|def| def.name == name).or_else(|| {
+        table
+            .iter()
+            .find(|def| name.len() >= def.min_abbrev && def.name.starts_with(name))
+    })
+}
+
+/// Split the arguments of `:set` at white space.  A backslash before
+/// white space or another backslash is removed, so `makeprg=sh\ x.sh`
+/// is one argument.
+fn split_set_args(args: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut part = String::new();
+    let mut chars = args.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.clone().next() {
+                Some(next) if next.is_whitespace() || next == '\\' => {
+                    part.push(next);
+                    chars.next();
+                }
+                _ => part.push(c),
+            },
+            c if c.is_whitespace() => {
+                if !part.is_empty() {
+                    parts.push(std::mem::take(&mut part));
+                }
+            }
+            c => part.push(c),
+        }
+    }
+    if !part.is_empty() {
+        parts.push(part);
+    }
+    parts
+}
+
+impl Editor {
+    /// Resolve a command range to first and last line, in order.
+    /// `None` when the command has no range.
+    pub(crate) fn resolve_range(
+        &self,
+        range: &CommandRange,
+    ) -> VimResult<Option<(LineNr, LineNr)>> {
+        let line_count = self.buffers.current().line_count().max(1);
+        if range.whole_file {
+            return Ok(Some((LineNr(1), LineNr(line_count))));
+        }
+        let Some(start) = &range.start else {
+            return Ok(None);
+        };
+        let first = self.resolve_line(start)?;
+        let last = match &range.end {
+            Some(end) => self.resolve_line(end)?,
+            None => first,
+        };
+        let (first, last) = if last < first {
+            (last, first)
+        } else {
+            (first, last)
+        };
+        if first.0 == 0 || last.0 > line_count {
+            return Err(VimError::Error(16, "Invalid range".to_string()));
+        }
+        Ok(Some((first, last)))
+    }
+
+    fn resolve_line(&self, spec: &LineSpec) -> VimResult<LineNr> {
+        let line_count = self.buffers.current().line_count().max(1);
+        let current = self.cursor.line();
+        let mark_line = |mark: Mark| {
+            self.marks
+                .get(mark)
+                .map(|value| value.position.line)
+                .ok_or(VimError::MarkNotSet(mark.to_char()))
+        };
+        match spec {
+            LineSpec::Absolute(line) => Ok(*line),
+            LineSpec::Current => Ok(current),
+            LineSpec::Last => Ok(LineNr(line_count)),
+            LineSpec::Relative(offset) => {
+                let line = current.0 as i64 + *offset as i64;
+                if line < 0 {
+                    return Err(VimError::Error(16, "Invalid range".to_string()));
+                }
+                Ok(LineNr(line as usize))
+            }
+            LineSpec::Mark(c) => mark_line(Mark::from_char(*c)?),
+            LineSpec::VisualStart => mark_line(Mark::from_char('<')?),
+            LineSpec::VisualEnd => mark_line(Mark::from_char('>')?),
+            LineSpec::SearchForward(_) | LineSpec::SearchBackward(_) => Err(
+                VimError::InvalidRange("search ranges are not supported".to_string()),
+            ),
+        }
+    }
+
+    /// `:set`, `:setlocal` and `:setglobal`, setting the values of `scope`
+    fn ex_set(&mut self, args: &str, scope: SetScope) -> CommandResult {
+        let mut messages = Vec::new();
+        for arg in split_set_args(args) {
+            match self.set_option_in(&arg, scope) {
+                Ok(Some(message)) => messages.push(message),
+                Ok(None) => {}
+                Err(err) => return CommandResult::error(err),
+            }
+        }
+        if messages.is_empty() {
+            CommandResult::success()
+        } else {
+            CommandResult::with_message(messages.join("\n"))
+        }
+    }
+
+    /// `:s/{pattern}/{string}/[flags]` on lines `first` to `last`.  The
+    /// pattern becomes the last search pattern.
+    fn ex_substitute(&mut self, first: LineNr, last: LineNr, args: &str) -> CommandResult {
+        let mut spec = match SubstituteSpec::parse(args) {
+            Ok(spec) => spec,
+            Err(err) => return CommandResult::error(err),
+        };
+        spec.range = Some(LineRange::new(first, last));
+        let last_pattern = self.search.last_pattern().map(|last| last.pattern.clone());
+        let pattern = if spec.flags.use_last_pattern {
+            match last_pattern.clone() {
+                Some(pattern) => pattern,
+                None => return CommandResult::error(no_previous_pattern()),
+            }
+        } else {
+            spec.pattern.clone()
+        };
+        let lines = self
+            .buffers
+            .current()
+            .get_lines(0, -1, false)
+            .unwrap_or_default();
+        let substituted = match spec.replacement.strip_prefix("\\=") {
+            Some(expr) => match eval::parse_complete(expr) {
+                Ok(expr) => {
+                    let mut replace = |line: LineNr, matched: &str| {
+                        // The expression sees the line as the cursor line
+                        let ctx = self.cursor_context();
+                        self.cursor.set_line(line, &ctx)?;
+                        let mut ctx = SubmatchContext {
+                            inner: &mut *self,
+                            submatches: vec![matched.to_string()],
+                        };
+                        expression_text(&eval::eval(&expr, &mut ctx)?)
+                    };
+                    substitute_lines_with(&lines, &spec, last_pattern.as_deref(), &mut replace)
+                }
+                Err(err) => Err(err),
+            },
+            None => substitute_lines(&lines, &spec, last_pattern.as_deref()),
+        };
+        let changed = match substituted {
+            // A line without a match is no error for :global
+            Ok(changed) if changed.is_empty() && self.global_busy => {
+                return CommandResult::success()
+            }
+            Ok(changed) if changed.is_empty() => {
+                return CommandResult::error(VimError::PatternNotFound(pattern))
+            }
+            Ok(changed) => changed,
+            Err(err) => return CommandResult::error(err),
+        };
+
+        let mut compiled = SearchPattern::forward(pattern);
+        compiled.direction = self.search.state().last_direction;
+        self.registers.set_last_search(&compiled.pattern);
+        self.search.set_last_pattern(compiled);
+        self.search.state_mut().highlighting = true;
+
+        let count: usize = changed.iter().map(|line| line.replaced.len()).sum();
+        let report = |noun: &str, plural: &str| {
+            format!(
+                "{} {} on {} line{}",
+                count,
+                if count == 1 { noun } else { plural },
+                changed.len(),
+                if changed.len() == 1 { "" } else { "s" }
+            )
+        };
+        if spec.flags.report {
+            return CommandResult::with_message(report("match", "matches"));
+        }
+        for line in &changed {
+            let idx = line.line.to_zero_indexed() as i64;
+            if let Err(err) =
+                self.buffers
+                    .current_mut()
+                    .set_lines(idx, idx + 1, true, vec![line.text.clone()])
+            {
+                return CommandResult::error(err);
+            }
+        }
+        self.sync_cursor_with_buffer();
+        let lines = self
+            .buffers
+            .current()
+            .get_lines(0, -1, false)
+            .unwrap_or_default();
+        let target = changed.last().map_or(first, |line| line.line);
+        let col = crate::motion::first_non_blank(&lines, target);
+        let ctx = self.cursor_context();
+        let _ = self
+            .cursor
+            .set_position(CursorPosition::new(target, col), &ctx);
+        self.cursor.update_curswant();
+        if count > self.options.number("report").max(0) as usize && !self.global_busy {
+            CommandResult::with_message(report("substitution", "substitutions"))
+        } else {
+            CommandResult::success()
+        }
+    }
+
+    fn ex_fold_range(
+        &mut self,
+        first: LineNr,
+        last: LineNr,
+        open: bool,
+        bang: bool,
+    ) -> VimResult<()> {
+        let mut line = first;
+        while line <= last {
+            let end = self.closed_fold(line).map_or(line, |(_, end)| end);
+            if self.folds.get(line).is_some() {
+                let _ = match (open, bang) {
+                    (true, false) => self.folds.open(line),
+                    (true, true) => self.folds.open_recursive(line),
+                    (false, false) => self.folds.close(line),
+                    (false, true) => self.folds.close_recursive(line),
+                };
+            }
+            line = LineNr(end.0 + 1);
+        }
+        Ok(())
+    }
+
+    /// `:[range]delete [x] [count]`: delete the lines into register `x`.
+    /// With a count that many lines are deleted, starting with `last`.
+    fn ex_delete(&mut self, first: LineNr, last: LineNr, args: &str) -> VimResult<()> {
+        let (register, count) = match args.chars().next() {
+            Some(c) if !c.is_ascii_digit() => (Some(c), args[c.len_utf8()..].trim()),
+            _ => (None, args),
+        };
+        let (first, last) = match count {
+            "" => (first, last),
+            count => {
+                let count: usize = count.parse().map_err(|_| VimError::TrailingCharacters)?;
+                if count == 0 {
+                    return Err(VimError::Error(939, "Positive count required".to_string()));
+                }
+                (last, LineNr(last.0 + count - 1))
+            }
+        };
+        self.apply_operator(Operator::Delete, register, MotionRange::lines(first, last))
+    }
+
+    /// `:[range]move {address}`: move the lines below line `address`, 0
+    /// for the top.  The cursor ends on the last line moved.
+    fn ex_move(&mut self, first: LineNr, last: LineNr, args: &str) -> VimResult<()> {
+        let (range, rest) = parse_command_range(args)?;
+        if !rest.trim().is_empty() {
+            return Err(VimError::TrailingCharacters);
+        }
+        let dest = match (&range.start, range.whole_file) {
+            (Some(spec), false) => self.resolve_line(spec)?,
+            _ => return Err(VimError::Error(14, "Invalid address".to_string())),
+        };
+        let line_count = self.buffers.current().line_count();
+        if dest.0 > line_count {
+            return Err(VimError::Error(16, "Invalid range".to_string()));
+        }
+        if dest >= first && dest < last {
+            return Err(VimError::Error(
+                134,
+                "Cannot move a range of lines into itself".to_string(),
+            ));
+        }
+        let count = last.0 - first.0 + 1;
+        // Where the lines go once they are taken out
+        let at = if dest >= last { dest.0 - count } else { dest.0 };
+        if at + 1 != first.0 {
+            let buffer = self.buffers.current_mut();
+            let moved = buffer.get_lines(first.0 as i64 - 1, last.0 as i64, true)?;
+            buffer.set_lines(first.0 as i64 - 1, last.0 as i64, true, Vec::new())?;
+            buffer.set_lines(at as i64, at as i64, true, moved)?;
+            self.sync_cursor_with_buffer();
+        }
+        let line = LineNr(at + count);
+        let lines = self.buffer_lines();
+        let col = crate::motion::first_non_blank(&lines, line);
+        let ctx = self.cursor_context();
+        self.cursor
+            .set_position(CursorPosition::new(line, col), &ctx)?;
+        self.cursor.update_curswant();
+        Ok(())
+    }
+}
+
+impl CommandExecutor for Editor {
+    fn parse(&self, cmdline: &str) -> VimResult<ExCommand> {
+        let cmdline = cmdline.trim_start_matches(|c: char| c == ':' || c.is_whitespace());
+        let (range, rest) = parse_command_range(cmdline)?;
+        let rest = rest.trim_start();
+        let name_end = if rest.starts_with('@') {
+            1
+        } else {
+            rest.find(|c: char| !c.is_ascii_alphabetic())
+                .unwrap_or(rest.len())
+        };
+        let (name, rest) = rest.split_at(name_end);
+        let (bang, rest) = match rest.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, rest),
+        };
+        // White space at the end of `:normal` is typed too
+        let args = match lookup(name) {
+            Some(def) if def.name == "normal" => rest.trim_start(),
+            _ => rest.trim(),
+        };
+        Ok(ExCommand {
+            name: name.to_string(),
+            range,
+            bang,
+            args: args.to_string(),
+            count: None,
+            register: None,
+        })
+    }
+
+    fn execute(&mut self, cmd: &ExCommand) -> CommandResult {
+        // The range of a command taking a count is the count, as in `:3cnext`
+        let counted = lookup(&cmd.name).is_some_and(|def| def.flags.count);
+        let range = if counted {
+            Ok(None)
+        } else {
+            self.resolve_range(&cmd.range)
+        };
+        let range = match range {
+            Ok(range) => range,
+            Err(err) => return CommandResult::error(err),
+        };
+
+        // A bare range jumps to its last line
+        if cmd.name.is_empty() {
+            if let Some((_, last)) = range {
+                let handle = self.buffers.current().handle();
+                self.marks.push_jump(handle, self.cursor.position());
+                let lines = self
+                    .buffers
+                    .current()
+                    .get_lines(0, -1, false)
+                    .unwrap_or_default();
+                let col = crate::motion::first_non_blank(&lines, last);
+                let ctx = self.cursor_context();
+                let _ = self
+                    .cursor
+                    .set_position(CursorPosition::new(last, col), &ctx);
+                self.cursor.update_curswant();
+            } else if cmd.bang || !cmd.args.is_empty() {
+                return CommandResult::error(VimError::NotEditorCommand(cmd.args.clone()));
+            }
+            return CommandResult::success();
+        }
+
+        let Some(def) = lookup(&cmd.name) else {
+            return CommandResult::error(VimError::NotEditorCommand(cmd.name.clone()));
+        };
+        if cmd.bang && !def.flags.bang {
+            return CommandResult::error(VimError::Error(477, "No ! allowed".to_string()));
+        }
+        if range.is_some() && !def.flags.range {
+            return CommandResult::error(VimError::Error(481, "No range allowed".to_string()));
+        }
+        if !cmd.args.is_empty() && !def.flags.args {
+            return CommandResult::error(VimError::TrailingCharacters);
+        }
+
+        let current = self.cursor.line();
+        let (first, last) = range.unwrap_or((current, current));
+        let bang = if cmd.bang { "!" } else { "" };
+        let result = match def.name.as_str() {
+            // Quitting the command-line window closes it without
+            // executing anything
+            "quit" if self.cmdwin_type().is_some() => self.close_cmdwin().map(|_| ()),
+            "quit" | "wq" | "xit" | "qall" | "wqall" | "xall" => {
+                handle_ex_quit(self, &format!("{}{}", def.name, bang)).map(|quit| {
+                    self.quit_requested = quit;
+                })
+            }
+            "set" => return self.ex_set(&cmd.args, SetScope::Both),
+            "setlocal" => return self.ex_set(&cmd.args, SetScope::Local),
+            "setglobal" => return self.ex_set(&cmd.args, SetScope::Global),
+            "fold" => self.create_fold(first, last),
+            "foldopen" => self.ex_fold_range(first, last, true, cmd.bang),
+            "foldclose" => self.ex_fold_range(first, last, false, cmd.bang),
+            "autocmd" => match self.ex_autocmd(&cmd.args, cmd.bang) {
+                Ok(Some(message)) => return CommandResult::with_message(message),
+                Ok(None) => Ok(()),
+                Err(err) => Err(err),
+            },
+            "augroup" => self.ex_augroup(&cmd.args, cmd.bang),
+            "doautocmd" => self.ex_doautocmd(&cmd.args),
+            "substitute" => return self.ex_substitute(first, last, &cmd.args),
+            "global" | "vglobal" => {
+                // The whole buffer by default
+                let line_count = self.buffers.current().line_count();
+                let (first, last) = range.unwrap_or((LineNr(1), LineNr(line_count)));
+                let inverse = cmd.bang || def.name == "vglobal";
+                match self.ex_global(first, last, &cmd.args, inverse) {
+                    Ok(Some(message)) => return CommandResult::with_message(message),
+                    Ok(None) => Ok(()),
+                    Err(err) => Err(err),
+                }
+            }
+            "delete" => self.ex_delete(first, last, &cmd.args),
+            "move" => self.ex_move(first, last, &cmd.args),
+            "edit" => self.ex_edit(&cmd.args, cmd.bang),
+            "nohlsearch" => {
+                self.search.state_mut().highlighting = false;
+                Ok(())
+            }
+            "vimgrep" | "lvimgrep" => self.ex_vimgrep(&cmd.name, &cmd.args, def.name == "lvimgrep"),
+            "make" | "lmake" | "grep" | "lgrep" => self.ex_make(&def.name, &cmd.args, cmd.bang),
+            "normal" => self.ex_normal(range, &cmd.args),
+            "@" => self.ex_at(range.map(|(_, last)| last), &cmd.args),
+            "source" => self.ex_source(&cmd.args),
+            "write" => {
+                return match self.write_file(range, &cmd.args, cmd.bang) {
+                    Ok(message) => CommandResult::with_message(message),
+                    Err(err) => CommandResult::error(err),
+                }
+            }
+            "print" | "number" => {
+                return match self.ex_print(first, last, def.name == "number") {
+                    Ok(message) => CommandResult::with_message(message),
+                    Err(err) => CommandResult::error(err),
+                }
+            }
+            "cquit" => self.ex_cquit(cmd),
+            "digraphs" => match self.ex_digraphs(&cmd.args, cmd.bang) {
+                Ok(Some(message)) => return CommandResult::with_message(message),
+                Ok(None) => Ok(()),
+                Err(err) => Err(err),
+            },
+            "ascii" => return CommandResult::with_message(self.describe_cursor_char(false)),
+            name if is_abbrev_command(name) => match self.ex_abbreviate(name, &cmd.args) {
+                Ok(Some(message)) => return CommandResult::with_message(message),
+                Ok(None) => Ok(()),
+                Err(err) => Err(err),
+            },
+            name if vimscript::lookup(name).is_some() => self.ex_script(cmd, range),
+            name if is_quickfix_command(name) => match self.ex_quickfix(name, cmd) {
+                Ok(Some(message)) => return CommandResult::with_message(message),
+                Ok(None) => Ok(()),
+                Err(err) => Err(err),
+            },
+            _ => Err(VimError::NotEditorCommand(cmd.name.clone())),
+        };
+        match result {
+            Ok(()) => CommandResult::success(),
+            Err(err) => CommandResult::error(err),
+        }
+    }
+
+    fn commands(&self) -> Vec<&CommandDef> {
+        command_table().iter().collect()
+    }
+
+    fn complete_command(&self, prefix: &str) -> Vec<String> {
+        let mut names: Vec<String> = command_table()
+            .iter()
+            .filter(|def| def.name.starts_with(prefix))
+            .map(|def| def.name.clone())
+            .collect();
+        names.sort();
+        names
+    }
+
+    fn complete_args(&self, cmd: &str, args: &str) -> Vec<String> {
+        self.complete_command_args(cmd, args)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup_abbreviations() {
+        assert_eq!(lookup("q").map(|def| def.name.as_str()), Some("quit"));
+        assert_eq!(lookup("fold").map(|def| def.name.as_str()), Some("fold"));
+        assert_eq!(
+            lookup("foldo").map(|def| def.name.as_str()),
+            Some("foldopen")
+        );
+        assert_eq!(
+            lookup("setl").map(|def| def.name.as_str()),
+            Some("setlocal")
+        );
+        assert_eq!(lookup("s").map(|def| def.name.as_str()), Some("substitute"));
+        assert_eq!(lookup("g").map(|def| def.name.as_str()), Some("global"));
+        assert_eq!(lookup("d").map(|def| def.name.as_str()), Some("delete"));
+        assert_eq!(lookup("m").map(|def| def.name.as_str()), Some("move"));
+        assert_eq!(lookup("e").map(|def| def.name.as_str()), Some("edit"));
+        assert_eq!(lookup("qa").map(|def| def.name.as_str()), Some("qall"));
+        assert_eq!(lookup("wqa").map(|def| def.name.as_str()), Some("wqall"));
+        assert!(lookup("xyz").is_none());
+    }
+
+    #[test]
+    fn test_parse_range_bang_and_args() {
+        let editor = Editor::new();
+        let cmd = editor.parse(":2,3foldopen!").unwrap();
+        assert_eq!(cmd.name, "foldopen");
+        assert!(cmd.bang);
+        assert_eq!(cmd.range, CommandRange::lines(LineNr(2), LineNr(3)));
+
+        let cmd = editor.parse("set ts=4 sw=4").unwrap();
+        assert_eq!(cmd.name, "set");
+        assert_eq!(cmd.args, "ts=4 sw=4");
+    }
+
+    #[test]
+    fn test_split_set_args() {
+        assert_eq!(split_set_args(" ts=4  sw=2 "), vec!["ts=4", "sw=2"]);
+        assert_eq!(
+            split_set_args(r"mp=sh\ a.sh\\x efm=%f\,%l"),
+            vec![r"mp=sh a.sh\x", r"efm=%f\,%l"]
+        );
+    }
+}
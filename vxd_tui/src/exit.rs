@@ -1,15 +1,18 @@
 //! Exit and quit command handling for the TUI.
 //!
-//! This module models core quit flows like :q, :q!, and ZZ.
+//! This module models core quit flows like :q, :q!, :qa and ZZ.  Writing
+//! with :wq, :x and ZZ writes the file of the buffer, :wqa and :xa the
+//! files of all changed buffers.
 
-use vxd::buffer::{Buffer, BufferManager};
+use vxd::buffer::{BufHandle, Buffer, BufferManager, BufferType};
 use vxd::types::{VimError, VimResult};
 
 use crate::editor::Editor;
 
 const E37_NO_WRITE: &str = "No write since last change (add ! to override)";
 
-/// Execute an ex-style quit command (":q", ":q!", ":quit", ":wq", ":x").
+/// Execute an ex-style quit command (":q", ":q!", ":quit", ":wq", ":x",
+/// ":qa", ":wqa", ":xa").
 ///
 /// Returns true if the editor should quit.
 pub fn handle_ex_quit(editor: &mut Editor, cmdline: &str) -> VimResult<bool> {
@@ -24,6 +27,8 @@ pub fn handle_ex_quit(editor: &mut Editor, cmdline: &str) -> VimResult<bool> {
         "q" | "quit" => quit_if_allowed(editor, bang),
         "wq" => write_then_quit(editor, bang),
         "x" | "xit" => write_if_modified_then_quit(editor, bang),
+        "qa" | "qall" => quit_all_if_allowed(editor, bang),
+        "wqa" | "wqall" | "xa" | "xall" => write_all_then_quit(editor, bang),
         _ => Err(VimError::NotEditorCommand(name.to_string())),
     }
 }
@@ -44,29 +49,75 @@ fn quit_if_allowed(editor: &mut Editor, force: bool) -> VimResult<bool> {
     Ok(true)
 }
 
-fn write_then_quit(editor: &mut Editor, force: bool) -> VimResult<bool> {
-    if force {
-        return Ok(true);
+fn quit_all_if_allowed(editor: &mut Editor, force: bool) -> VimResult<bool> {
+    match changed_buffers(editor).first() {
+        Some(&handle) if !force => {
+            let name = editor
+                .buffers
+                .get(handle)
+                .map(|buf| buf.name())
+                .filter(|name| !name.is_empty())
+                .unwrap_or("[No Name]");
+            Err(VimError::Error(
+                162,
+                format!("No write since last change for buffer \"{}\"", name),
+            ))
+        }
+        _ => Ok(true),
     }
-    write_if_modified(editor)?;
+}
+
+fn write_all_then_quit(editor: &mut Editor, force: bool) -> VimResult<bool> {
+    for handle in changed_buffers(editor) {
+        editor.switch_buffer(handle)?;
+        write(editor, force)?;
+    }
+    Ok(true)
+}
+
+/// The buffers for files with changes that are not written
+fn changed_buffers(editor: &Editor) -> Vec<BufHandle> {
+    editor
+        .buffers
+        .list()
+        .into_iter()
+        .filter(|&handle| {
+            editor
+                .buffers
+                .get(handle)
+                .is_some_and(|buf| buf.is_modified() && buf.buftype() == BufferType::Normal)
+        })
+        .collect()
+}
+
+fn write_then_quit(editor: &mut Editor, force: bool) -> VimResult<bool> {
+    write(editor, force)?;
     Ok(true)
 }
 
 fn write_if_modified_then_quit(editor: &mut Editor, force: bool) -> VimResult<bool> {
-    if force {
-        return Ok(true);
+    if editor.buffers.current().is_modified() {
+        write(editor, force)?;
     }
-    write_if_modified(editor)?;
     Ok(true)
 }
 
 fn write_if_modified(editor: &mut Editor) -> VimResult<()> {
     if editor.buffers.current().is_modified() {
-        editor.buffers.current_mut().set_modified(false)?;
+        write(editor, false)?;
     }
     Ok(())
 }
 
+/// Write the buffer to its file.  A buffer without a name has no file and
+/// is only marked unmodified.
+fn write(editor: &mut Editor, force: bool) -> VimResult<()> {
+    if editor.buffers.current().name().is_empty() {
+        return editor.buffers.current_mut().set_modified(false);
+    }
+    editor.write_file(None, "", force).map(|_| ())
+}
+
 fn parse_bang(cmd: &str) -> (&str, bool) {
     if let Some(stripped) = cmd.strip_suffix('!') {
         (stripped.trim_end(), true)
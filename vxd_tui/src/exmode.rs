@@ -0,0 +1,124 @@
+//! Ex mode: executing command lines without a user interface.
+//!
+//! Started with `-e`, the editor reads command lines and executes them as
+//! typed after `:` until `:quit` or the end of the input; see
+//! [`vxd::startup`].  Messages and errors are written to the output.  In
+//! silent mode only the output of `:print`, `:number` and `:set` is, and
+//! the errors are only remembered for the exit status.  A block such as
+//! `:if` must be on one line, or in a script run with `:source`.
+
+use std::io::{self, BufRead, Write};
+
+use vxd::commands::{CommandExecutor, ExCommand, LineSpec};
+use vxd::cursor::{Cursor, CursorPosition};
+use vxd::types::{LineNr, VimError, VimResult};
+
+use crate::editor::Editor;
+use crate::ex::lookup;
+
+/// The commands whose output is written in silent mode
+const OUTPUT_COMMANDS: &[&str] = &["print", "number", "set"];
+
+/// The state of Ex mode
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ExMode {
+    /// Only the output of [`OUTPUT_COMMANDS`] is written
+    pub(crate) silent: bool,
+    /// A command failed
+    pub(crate) error: bool,
+}
+
+impl Editor {
+    /// Run Ex mode: execute the command lines read from `input`, writing
+    /// the messages to `output`, until `:quit` or the end of the input.
+    /// Returns the exit status.
+    pub fn ex_mode(&mut self, input: impl BufRead, output: &mut impl Write) -> io::Result<i32> {
+        if self.exmode.is_none() {
+            self.exmode = Some(ExMode::default());
+        }
+        self.write_messages(output)?;
+        // A startup command such as `-c qa` may have quit already
+        let mut lines = input.lines();
+        while !self.quit_requested {
+            let Some(line) = lines.next() else {
+                break;
+            };
+            if let Err(err) = self.vimscript.clone().execute(&line?, self) {
+                self.add_error(&err.to_string());
+            }
+            self.write_messages(output)?;
+        }
+        let error = self.exmode.as_ref().is_some_and(|exmode| exmode.error);
+        Ok(self.exit_status.unwrap_or(i32::from(error)))
+    }
+
+    fn write_messages(&mut self, output: &mut impl Write) -> io::Result<()> {
+        match self.message.take() {
+            Some(message) => writeln!(output, "{}", message),
+            None => Ok(()),
+        }
+    }
+
+    /// Whether the message of `command` is shown: in silent Ex mode only
+    /// the output of `:print`, `:number` and `:set` is
+    pub(crate) fn shows_message(&self, command: &str) -> bool {
+        if !self.exmode.as_ref().is_some_and(|exmode| exmode.silent) {
+            return true;
+        }
+        let name = self.parse(command).ok().and_then(|cmd| lookup(&cmd.name));
+        name.is_some_and(|def| OUTPUT_COMMANDS.contains(&def.name.as_str()))
+    }
+
+    /// Show the error `text`; in Ex mode it also sets the exit status
+    pub(crate) fn add_error(&mut self, text: &str) {
+        match &mut self.exmode {
+            Some(exmode) if exmode.silent => {
+                exmode.error = true;
+                return;
+            }
+            Some(exmode) => exmode.error = true,
+            None => {}
+        }
+        self.add_message(text, true);
+    }
+
+    /// `:[range]print` and `:[range]number`: the lines from `first` to
+    /// `last`, with their line number for `number`.  The cursor moves to
+    /// the last line.
+    pub(crate) fn ex_print(
+        &mut self,
+        first: LineNr,
+        last: LineNr,
+        number: bool,
+    ) -> VimResult<String> {
+        let lines = self.buffer_lines();
+        let text: Vec<String> = (first.0..=last.0)
+            .map(|nr| {
+                if number {
+                    format!("{:>3} {}", nr, lines[nr - 1])
+                } else {
+                    lines[nr - 1].clone()
+                }
+            })
+            .collect();
+        let col = crate::motion::first_non_blank(&lines, last);
+        let ctx = self.cursor_context();
+        self.cursor
+            .set_position(CursorPosition::new(last, col), &ctx)?;
+        self.cursor.update_curswant();
+        Ok(text.join("\n"))
+    }
+
+    /// `:cquit[!] [N]`: quit without writing, with exit status `N`, 1 when
+    /// not given
+    pub(crate) fn ex_cquit(&mut self, cmd: &ExCommand) -> VimResult<()> {
+        let status = match &cmd.range.start {
+            Some(LineSpec::Absolute(count)) => count.0 as i32,
+            _ if cmd.args.is_empty() => 1,
+            _ => cmd.args.parse().map_err(|_| VimError::TrailingCharacters)?,
+        };
+        self.exit_status = Some(status);
+        self.quit_requested = true;
+        Ok(())
+    }
+}
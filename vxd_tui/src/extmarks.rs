@@ -0,0 +1,274 @@
+//! Extmarks implementation.
+//!
+//! Extmarks are positions (or ranges) embedders place in a buffer that
+//! follow the text around them as it is edited.  They are grouped by
+//! namespace; each namespace numbers its extmarks from 1.
+
+use std::collections::HashMap;
+
+use vxd::buffer::{BufHandle, Buffer, BufferManager, TextSplice};
+use vxd::cursor::CursorPosition;
+use vxd::marks::{Extmark, ExtmarkId, ExtmarkManager, ExtmarkOpts, NamespaceId};
+use vxd::types::{ColNr, LineNr, VimError, VimResult};
+
+use crate::editor::Editor;
+
+/// Extmark manager implementation
+#[derive(Debug, Clone, Default)]
+pub struct TuiExtmarkManager {
+    /// Named namespaces, in creation order
+    namespaces: Vec<(String, NamespaceId)>,
+    /// Number of namespaces created, named or not
+    namespace_count: u32,
+    /// Extmarks of all buffers
+    marks: Vec<Extmark>,
+    /// Last id used in each namespace
+    last_ids: HashMap<NamespaceId, u32>,
+}
+
+impl TuiExtmarkManager {
+    /// Create a new extmark manager
+    pub fn new() -> Self {
+        TuiExtmarkManager::default()
+    }
+
+    fn find(&self, buffer: BufHandle, ns: NamespaceId, id: ExtmarkId) -> Option<usize> {
+        self.marks
+            .iter()
+            .position(|mark| mark.buffer == buffer && mark.ns == ns && mark.id == id)
+    }
+}
+
+/// Order extmarks by position, then by namespace and id
+fn position_key(mark: &Extmark) -> (LineNr, usize, NamespaceId, ExtmarkId) {
+    (mark.start.line, mark.start.col, mark.ns, mark.id)
+}
+
+fn before(a: CursorPosition, b: CursorPosition) -> bool {
+    (a.line, a.col) < (b.line, b.col)
+}
+
+impl ExtmarkManager for TuiExtmarkManager {
+    fn create_namespace(&mut self, name: &str) -> NamespaceId {
+        if let Some((_, ns)) = self
+            .namespaces
+            .iter()
+            .find(|(n, _)| !name.is_empty() && n == name)
+        {
+            return *ns;
+        }
+        self.namespace_count += 1;
+        let ns = NamespaceId(self.namespace_count);
+        if !name.is_empty() {
+            self.namespaces.push((name.to_string(), ns));
+        }
+        ns
+    }
+
+    fn namespaces(&self) -> Vec<(String, NamespaceId)> {
+        self.namespaces.clone()
+    }
+
+    fn set_extmark(
+        &mut self,
+        buffer: BufHandle,
+        ns: NamespaceId,
+        id: Option<ExtmarkId>,
+        pos: CursorPosition,
+        opts: ExtmarkOpts,
+    ) -> VimResult<ExtmarkId> {
+        if ns.0 == 0 || ns.0 > self.namespace_count {
+            return Err(VimError::Error(
+                475,
+                format!("Invalid argument: ns_id {}", ns.0),
+            ));
+        }
+        if opts.end.is_some_and(|end| before(end, pos)) {
+            return Err(VimError::InvalidRange(
+                "extmark end is before its start".to_string(),
+            ));
+        }
+        let last = self.last_ids.entry(ns).or_insert(0);
+        let id = match id {
+            Some(id) => {
+                *last = (*last).max(id.0);
+                id
+            }
+            None => {
+                *last += 1;
+                ExtmarkId(*last)
+            }
+        };
+        let mark = Extmark {
+            id,
+            ns,
+            buffer,
+            start: pos,
+            end: opts.end,
+            right_gravity: opts.right_gravity,
+            end_right_gravity: opts.end_right_gravity,
+        };
+        match self.find(buffer, ns, id) {
+            Some(idx) => self.marks[idx] = mark,
+            None => self.marks.push(mark),
+        }
+        Ok(id)
+    }
+
+    fn get_extmark(&self, buffer: BufHandle, ns: NamespaceId, id: ExtmarkId) -> Option<&Extmark> {
+        self.find(buffer, ns, id).map(|idx| &self.marks[idx])
+    }
+
+    fn get_extmarks(
+        &self,
+        buffer: BufHandle,
+        ns: Option<NamespaceId>,
+        start: CursorPosition,
+        end: CursorPosition,
+        overlap: bool,
+    ) -> Vec<&Extmark> {
+        let mut marks: Vec<&Extmark> = self
+            .marks
+            .iter()
+            .filter(|mark| mark.buffer == buffer && ns.is_none_or(|ns| mark.ns == ns))
+            .filter(|mark| !before(end, mark.start))
+            .filter(|mark| {
+                let reach = match mark.end {
+                    Some(mark_end) if overlap => mark_end,
+                    _ => mark.start,
+                };
+                !before(reach, start)
+            })
+            .collect();
+        marks.sort_by_key(|mark| position_key(mark));
+        marks
+    }
+
+    fn del_extmark(&mut self, buffer: BufHandle, ns: NamespaceId, id: ExtmarkId) -> bool {
+        match self.find(buffer, ns, id) {
+            Some(idx) => {
+                self.marks.remove(idx);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn clear_namespace(
+        &mut self,
+        buffer: BufHandle,
+        ns: Option<NamespaceId>,
+        first: LineNr,
+        last: Option<LineNr>,
+    ) {
+        self.marks.retain(|mark| {
+            let in_range =
+                mark.start.line >= first && last.is_none_or(|last| mark.start.line <= last);
+            !(mark.buffer == buffer && ns.is_none_or(|ns| mark.ns == ns) && in_range)
+        });
+    }
+
+    fn splice(&mut self, buffer: BufHandle, splice: &TextSplice) {
+        for mark in self.marks.iter_mut().filter(|mark| mark.buffer == buffer) {
+            mark.splice(splice);
+        }
+    }
+}
+
+impl Editor {
+    /// Place an extmark in the current buffer.  The position must be in
+    /// the buffer, at most one past the end of its line.
+    pub fn set_extmark(
+        &mut self,
+        ns: NamespaceId,
+        id: Option<ExtmarkId>,
+        pos: CursorPosition,
+        opts: ExtmarkOpts,
+    ) -> VimResult<ExtmarkId> {
+        self.check_extmark_position(pos)?;
+        if let Some(end) = opts.end {
+            self.check_extmark_position(end)?;
+        }
+        let buffer = self.buffers.current().handle();
+        self.extmarks.set_extmark(buffer, ns, id, pos, opts)
+    }
+
+    fn check_extmark_position(&self, pos: CursorPosition) -> VimResult<()> {
+        let buffer = self.buffers.current();
+        if pos.line.0 == 0 || pos.line.0 > buffer.line_count() {
+            return Err(VimError::InvalidLine(pos.line));
+        }
+        let line = buffer
+            .get_line(pos.line.to_zero_indexed() as i64)
+            .unwrap_or_default();
+        if pos.col > line.len() {
+            return Err(VimError::InvalidColumn(ColNr(pos.col + 1)));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pos(line: usize, col: usize) -> CursorPosition {
+        CursorPosition::new(LineNr(line), col)
+    }
+
+    #[test]
+    fn test_namespaces_and_ids() {
+        let mut mgr = TuiExtmarkManager::new();
+        let ns = mgr.create_namespace("lint");
+        assert_eq!(mgr.create_namespace("lint"), ns);
+        assert_ne!(mgr.create_namespace(""), mgr.create_namespace(""));
+        assert_eq!(mgr.namespaces(), vec![("lint".to_string(), ns)]);
+
+        let buf = BufHandle(1);
+        let opts = ExtmarkOpts::default();
+        let first = mgr.set_extmark(buf, ns, None, pos(1, 0), opts).unwrap();
+        let fixed = mgr
+            .set_extmark(buf, ns, Some(ExtmarkId(7)), pos(2, 0), opts)
+            .unwrap();
+        let next = mgr.set_extmark(buf, ns, None, pos(3, 0), opts).unwrap();
+        assert_eq!(
+            (first, fixed, next),
+            (ExtmarkId(1), ExtmarkId(7), ExtmarkId(8))
+        );
+        assert!(mgr
+            .set_extmark(buf, NamespaceId(99), None, pos(1, 0), opts)
+            .is_err());
+
+        assert!(mgr.del_extmark(buf, ns, fixed));
+        assert!(!mgr.del_extmark(buf, ns, fixed));
+        assert!(mgr.get_extmark(buf, ns, fixed).is_none());
+    }
+
+    #[test]
+    fn test_range_queries() {
+        let mut mgr = TuiExtmarkManager::new();
+        let ns = mgr.create_namespace("test");
+        let buf = BufHandle(1);
+        let ranged = ExtmarkOpts {
+            end: Some(pos(3, 2)),
+            ..ExtmarkOpts::default()
+        };
+        mgr.set_extmark(buf, ns, None, pos(4, 1), ExtmarkOpts::default())
+            .unwrap();
+        mgr.set_extmark(buf, ns, None, pos(1, 5), ranged).unwrap();
+        mgr.set_extmark(buf, ns, None, pos(2, 0), ExtmarkOpts::default())
+            .unwrap();
+
+        let ids = |marks: Vec<&Extmark>| marks.iter().map(|m| m.id.0).collect::<Vec<_>>();
+        let all = mgr.get_extmarks(buf, Some(ns), pos(1, 0), pos(9, 0), false);
+        assert_eq!(ids(all), vec![2, 3, 1]);
+        let some = mgr.get_extmarks(buf, None, pos(2, 0), pos(3, 9), false);
+        assert_eq!(ids(some), vec![3]);
+        let overlapping = mgr.get_extmarks(buf, None, pos(2, 0), pos(3, 9), true);
+        assert_eq!(ids(overlapping), vec![2, 3]);
+
+        mgr.clear_namespace(buf, Some(ns), LineNr(2), None);
+        let left = mgr.get_extmarks(buf, None, pos(1, 0), pos(9, 0), false);
+        assert_eq!(ids(left), vec![2]);
+    }
+}
@@ -1,10 +1,21 @@
 //! Multi-file editing implementation for the TUI.
+//!
+//! [`TuiFileEditor`] keeps an argument list of its own; the editor keeps
+//! the one filled with the files it is started with.  The editor reads
+//! and writes the files of its buffers, applying the modelines of a file
+//! read after its `BufRead` autocommands.
 
-use vxd::buffer::{BufHandle, BufferManager};
+use std::path::Path;
+
+use vxd::autocmd::AutocmdEvent;
+use vxd::buffer::{BufHandle, Buffer, BufferManager};
 use vxd::fileedit::FileEditor;
-use vxd::types::{VimError, VimResult};
+use vxd::modeline::apply_modelines;
+use vxd::types::{LineNr, VimError, VimResult};
 
 use crate::buffer::TuiBufferManager;
+use crate::editor::Editor;
+use crate::quickfix::read_lines;
 
 /// File editor that manages an argument list and buffers.
 #[derive(Debug, Default)]
@@ -90,3 +101,213 @@ impl FileEditor for TuiFileEditor {
         Ok(())
     }
 }
+
+impl Editor {
+    /// Make the buffer for the file `name` current, reading the file into
+    /// a new buffer when there is none.  The empty buffer the editor
+    /// starts with is used for the first file.  A file that does not exist
+    /// is edited in an empty buffer.
+    pub(crate) fn open_file(&mut self, name: &str) -> VimResult<()> {
+        if let Some(handle) = self.buffers.get_by_name(name) {
+            return self.switch_buffer(handle);
+        }
+        let lines = read_lines(name);
+        let current = self.buffers.current();
+        let unused = current.name().is_empty()
+            && !current.is_modified()
+            && self.buffer_lines().iter().all(String::is_empty);
+        let handle = if unused {
+            let handle = current.handle();
+            self.buffers.current_mut().set_name(name)?;
+            handle
+        } else {
+            self.buffers.create_named(name)?
+        };
+        if let Some(buf) = self.buffers.get_mut(handle) {
+            buf.set_lines(0, -1, false, lines.clone().unwrap_or_default())?;
+            buf.take_changes();
+            buf.set_modified(false)?;
+        }
+        self.switch_buffer(handle)?;
+        self.file_read(name, lines.is_some());
+        Ok(())
+    }
+
+    /// `:edit[!] [file]`: edit `file`, or read the file of the current
+    /// buffer again.  Without `!` the changes of the current buffer must
+    /// have been written; with `!` reading it again discards them.
+    pub(crate) fn ex_edit(&mut self, file: &str, force: bool) -> VimResult<()> {
+        let current = self.buffers.current();
+        let name = match file {
+            "" => current.name().to_string(),
+            file => file.to_string(),
+        };
+        if name.is_empty() {
+            return Err(VimError::Error(32, "No file name".to_string()));
+        }
+        if current.is_modified() && !force {
+            return Err(VimError::Error(
+                37,
+                "No write since last change (add ! to override)".to_string(),
+            ));
+        }
+        if name != current.name() {
+            return self.open_file(&name);
+        }
+        let lines = read_lines(&name);
+        let buf = self.buffers.current_mut();
+        buf.set_lines(0, -1, false, lines.clone().unwrap_or_default())?;
+        buf.set_modified(false)?;
+        self.sync_cursor_with_buffer();
+        self.file_read(&name, lines.is_some());
+        Ok(())
+    }
+
+    /// Done after the file `name` is read into the current buffer, or
+    /// found not to exist: the `BufRead` or `BufNewFile` autocommands,
+    /// then the modelines, which overrule what the autocommands set
+    fn file_read(&mut self, name: &str, exists: bool) {
+        let silent = self.exmode.as_ref().is_some_and(|exmode| exmode.silent);
+        if !exists && !silent {
+            self.message = Some(format!("\"{}\" [New]", name));
+        }
+        let event = if exists {
+            AutocmdEvent::BufReadPost
+        } else {
+            AutocmdEvent::BufNewFile
+        };
+        let data = self.autocmd_data();
+        // The modelines are applied also when an autocommand failed
+        let read = self.apply_autocmds(event, &data);
+        let modelines = self.do_modelines();
+        if let Err(err) = read.and(modelines) {
+            self.message = Some(err.to_string());
+        }
+    }
+
+    /// Apply the modelines of the current buffer, see [`vxd::modeline`]
+    pub(crate) fn do_modelines(&mut self) -> VimResult<()> {
+        let lines = self.buffer_lines();
+        let result = apply_modelines(&mut self.options, &lines);
+        self.apply_options();
+        result
+    }
+
+    /// `:[range]write[!] [file]`: write the lines in `range`, all of them
+    /// when `None`, to `file` or to the file of the buffer.  A buffer
+    /// without a name gets the name `file`.  Returns the message telling
+    /// what was written.
+    pub(crate) fn write_file(
+        &mut self,
+        range: Option<(LineNr, LineNr)>,
+        file: &str,
+        force: bool,
+    ) -> VimResult<String> {
+        if self.buffers.current().name().is_empty() && !file.is_empty() {
+            self.buffers.current_mut().set_name(file)?;
+        }
+        let buffer = self.buffers.current();
+        let name = match file {
+            "" => buffer.name().to_string(),
+            file => file.to_string(),
+        };
+        if name.is_empty() {
+            return Err(VimError::Error(32, "No file name".to_string()));
+        }
+        let own = name == buffer.name();
+        let exists = Path::new(&name).exists();
+        if !force {
+            if own && self.options.flag("readonly") {
+                return Err(VimError::Error(
+                    45,
+                    "'readonly' option is set (add ! to override)".to_string(),
+                ));
+            }
+            if !own && exists {
+                return Err(VimError::Error(
+                    13,
+                    "File exists (add ! to override)".to_string(),
+                ));
+            }
+        }
+
+        let all = self.buffer_lines();
+        let (first, last) = range.unwrap_or((LineNr(1), LineNr(all.len())));
+        let lines = &all[first.0 - 1..last.0];
+        // A buffer with only an empty line is an empty file
+        let empty = all.len() == 1 && all[0].is_empty();
+        let text: String = if empty {
+            String::new()
+        } else {
+            lines.iter().map(|line| format!("{}\n", line)).collect()
+        };
+        std::fs::write(&name, &text).map_err(|_| VimError::PermissionDenied(name.clone()))?;
+
+        let whole = lines.len() == all.len();
+        if own && whole {
+            self.buffers.current_mut().set_modified(false)?;
+        }
+        let new = if exists { "" } else { " [New]" };
+        let count = if empty { 0 } else { lines.len() };
+        Ok(format!(
+            "\"{}\"{} {}L, {}B written",
+            name,
+            new,
+            count,
+            text.len()
+        ))
+    }
+
+    fn edit_arg(&mut self, idx: usize) -> VimResult<()> {
+        let name = self.arglist[idx].clone();
+        self.open_file(&name)?;
+        self.arg_idx = Some(idx);
+        Ok(())
+    }
+}
+
+impl FileEditor for Editor {
+    fn edit(&mut self, name: &str) -> VimResult<()> {
+        if name.trim().is_empty() {
+            return Err(VimError::Error(32, "No file name".to_string()));
+        }
+        let idx = match self.arglist.iter().position(|file| file == name) {
+            Some(idx) => idx,
+            None => {
+                self.arglist.push(name.to_string());
+                self.arglist.len() - 1
+            }
+        };
+        self.edit_arg(idx)
+    }
+
+    fn current_file(&self) -> Option<&str> {
+        self.arg_idx
+            .and_then(|idx| self.arglist.get(idx))
+            .map(String::as_str)
+    }
+
+    fn arglist(&self) -> &[String] {
+        &self.arglist
+    }
+
+    fn next_file(&mut self) -> VimResult<()> {
+        match self.arg_idx.map_or(0, |idx| idx + 1) {
+            idx if idx < self.arglist.len() => self.edit_arg(idx),
+            _ => Err(VimError::Error(
+                165,
+                "Cannot go beyond last file".to_string(),
+            )),
+        }
+    }
+
+    fn prev_file(&mut self) -> VimResult<()> {
+        match self.arg_idx {
+            Some(idx) if idx > 0 => self.edit_arg(idx - 1),
+            _ => Err(VimError::Error(
+                164,
+                "Cannot go before first file".to_string(),
+            )),
+        }
+    }
+}
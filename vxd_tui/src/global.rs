@@ -0,0 +1,128 @@
+//! `:global` and `:vglobal`: executing a command on the lines matching a
+//! pattern.
+//!
+//! The matching lines are marked first, then the command is executed
+//! with the cursor on each marked line in turn, see [`vxd::global`].  The
+//! marks are extmarks covering their line, so that they follow the lines
+//! the command adds, deletes or moves; a marked line deleted before its
+//! turn comes is skipped.  The first error stops the command.
+
+use vxd::buffer::{Buffer, BufferManager};
+use vxd::cursor::{Cursor, CursorPosition};
+use vxd::global::GlobalCommand;
+use vxd::marks::{ExtmarkId, ExtmarkManager, ExtmarkOpts, NamespaceId};
+use vxd::registers::RegisterBank;
+use vxd::search::{SearchEngine, SearchPattern, SimpleSearchEngine};
+use vxd::types::{LineNr, VimError, VimResult};
+
+use crate::editor::{no_previous_pattern, Editor};
+
+impl Editor {
+    /// `:[range]global /{pattern}/[cmd]` on lines `first` to `last`, or
+    /// `:vglobal` (`:global!`) with `inverse`: execute `cmd`, `:p` when
+    /// empty, on the lines that match the pattern, or that do not with
+    /// `inverse`.  The pattern becomes the last search pattern.  Returns
+    /// the message when there is no such line.
+    pub(crate) fn ex_global(
+        &mut self,
+        first: LineNr,
+        last: LineNr,
+        args: &str,
+        inverse: bool,
+    ) -> VimResult<Option<String>> {
+        if self.global_busy {
+            return Err(VimError::Error(
+                147,
+                "Cannot do :global recursive".to_string(),
+            ));
+        }
+        let global = GlobalCommand::parse(args, inverse)?;
+        let pattern = if global.pattern.is_empty() {
+            self.search
+                .last_pattern()
+                .map(|last| last.pattern.clone())
+                .ok_or_else(no_previous_pattern)?
+        } else {
+            global.pattern.clone()
+        };
+        let lines = self.buffer_lines();
+        let engine = SimpleSearchEngine::new(lines.clone());
+        let matches = engine.find_all(
+            &SearchPattern::forward(pattern.clone()),
+            CursorPosition::new(first, 0),
+            CursorPosition::new(last, usize::MAX),
+            &self.search_options(),
+        )?;
+        let marked: Vec<LineNr> = (first.0..=last.0)
+            .map(LineNr)
+            .filter(|&line| matches.iter().any(|m| m.start.line == line) != inverse)
+            .collect();
+
+        let mut compiled = SearchPattern::forward(pattern.clone());
+        compiled.direction = self.search.state().last_direction;
+        self.registers.set_last_search(&compiled.pattern);
+        self.search.set_last_pattern(compiled);
+        if marked.is_empty() {
+            return Ok(Some(if inverse {
+                format!("Pattern found in every line: {}", pattern)
+            } else {
+                format!("Pattern not found: {}", pattern)
+            }));
+        }
+
+        // Each mark reaches to the start of the next line, which it only
+        // does while its line is there
+        let ns = self.extmarks.create_namespace("");
+        let buffer = self.buffers.current().handle();
+        let mut ids = Vec::new();
+        for line in marked {
+            let opts = ExtmarkOpts {
+                end: Some(CursorPosition::new(LineNr(line.0 + 1), 0)),
+                right_gravity: true,
+                end_right_gravity: false,
+            };
+            let start = CursorPosition::new(line, 0);
+            ids.push(self.extmarks.set_extmark(buffer, ns, None, start, opts)?);
+        }
+        let command = match global.command.trim() {
+            "" => "p",
+            command => command,
+        };
+        self.global_busy = true;
+        let result = self.global_execute(ns, &ids, command);
+        self.global_busy = false;
+        self.extmarks
+            .clear_namespace(buffer, Some(ns), LineNr(1), None);
+        result.map(|()| None)
+    }
+
+    /// Execute `command` on each line still marked by an extmark of `ids`
+    fn global_execute(
+        &mut self,
+        ns: NamespaceId,
+        ids: &[ExtmarkId],
+        command: &str,
+    ) -> VimResult<()> {
+        let buffer = self.buffers.current().handle();
+        for &id in ids {
+            // The extmarks follow the changes made so far
+            self.sync_cursor_with_buffer();
+            if self.buffers.current().handle() != buffer {
+                break;
+            }
+            let Some(mark) = self.extmarks.get_extmark(buffer, ns, id) else {
+                continue;
+            };
+            if mark.end == Some(mark.start) {
+                continue;
+            }
+            let line = mark.start.line;
+            let ctx = self.cursor_context();
+            self.cursor
+                .set_position(CursorPosition::new(line, 0), &ctx)?;
+            self.cursor.update_curswant();
+            self.vimscript.clone().execute(command, self)?;
+        }
+        Ok(())
+    }
+}
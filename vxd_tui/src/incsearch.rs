@@ -0,0 +1,306 @@
+//! Incremental search, search highlighting and the `:s` preview.
+//!
+//! With 'incsearch' the cursor moves to the first match while a `/` or `?`
+//! pattern is typed.  `Ctrl-G` and `Ctrl-T` step to the next and previous
+//! match, `<Esc>` goes back to where the search started and `<CR>` stays
+//! on the match shown.  With 'hlsearch' all matches of the last search
+//! pattern are highlighted until `:nohlsearch`.
+//!
+//! 'inccommand' shows the effect of a `:s` command while it is typed: in
+//! the buffer itself ("nosplit"), and also as a list of the changed lines
+//! in a preview window ("split").  Nothing is changed until `<CR>`.
+
+use vxd::buffer::{Buffer, BufferManager};
+use vxd::cmdline::Cmdline;
+use vxd::commands::CommandExecutor;
+use vxd::cursor::{Cursor, CursorPosition};
+use vxd::search::{
+    substitute_lines, IncCommand, SearchEngine, SearchMatch, SearchPattern, SubstituteSpec,
+};
+use vxd::types::{Direction, LineNr, LineRange, VimResult};
+
+use crate::editor::Editor;
+use crate::ex::lookup;
+use crate::key::Key;
+
+/// A `/` or `?` command line being typed with 'incsearch'
+#[derive(Debug, Clone)]
+pub(crate) struct IncSearch {
+    /// Cursor where the search started
+    start: CursorPosition,
+    /// The pattern the cursor was last moved for
+    pattern: String,
+    /// The match the cursor was moved to
+    current: Option<SearchMatch>,
+    /// Whether `Ctrl-G` or `Ctrl-T` moved away from the first match
+    stepped: bool,
+}
+
+/// A highlighted match on the screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchHighlight {
+    /// Line of the match
+    pub line: LineNr,
+    /// Byte column where the match starts
+    pub start: usize,
+    /// Byte column after the match
+    pub end: usize,
+    /// The match incremental search is on (IncSearch rather than Search)
+    pub current: bool,
+}
+
+/// What 'inccommand' shows for the `:s` command being typed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubstitutePreview {
+    /// The buffer lines as they would be after the command
+    pub lines: Vec<String>,
+    /// The replaced text in `lines`
+    pub highlights: Vec<SearchHighlight>,
+    /// With "split", the changed lines as `|{lnum}| {text}` for the preview
+    /// window
+    pub split: Option<Vec<String>>,
+}
+
+impl Editor {
+    /// Remember where a `/` or `?` search starts
+    pub(crate) fn start_incsearch(&mut self) {
+        self.incsearch = Some(IncSearch {
+            start: self.cursor.position(),
+            pattern: String::new(),
+            current: None,
+            stepped: false,
+        });
+    }
+
+    /// Leave incremental search and put the cursor back where it started
+    pub(crate) fn end_incsearch(&mut self) {
+        if let Some(inc) = self.incsearch.take() {
+            self.set_cursor_quietly(inc.start);
+        }
+    }
+
+    /// The match `Ctrl-G` or `Ctrl-T` moved to, where `<CR>` leaves the
+    /// cursor instead of the first match
+    pub(crate) fn incsearch_target(&self) -> Option<CursorPosition> {
+        let inc = self.incsearch.as_ref().filter(|inc| inc.stepped)?;
+        inc.current.as_ref().map(|found| found.start)
+    }
+
+    fn incsearch_direction(&self) -> Direction {
+        match self.cmdline_type {
+            Some('?') => Direction::Backward,
+            _ => Direction::Forward,
+        }
+    }
+
+    /// Move the cursor to the first match of the pattern typed so far, or
+    /// back to the start when nothing matches
+    pub(crate) fn update_incsearch(&mut self) {
+        if !self.options.flag("incsearch") || !matches!(self.cmdline_type, Some('/' | '?')) {
+            return;
+        }
+        let typed = self.cmdline.getcmdline().to_string();
+        let Some(inc) = self.incsearch.as_mut().filter(|inc| inc.pattern != typed) else {
+            return;
+        };
+        inc.pattern = typed.clone();
+        inc.stepped = false;
+        let start = inc.start;
+        let found = if typed.is_empty() {
+            None
+        } else {
+            self.incsearch_find(&typed, start, self.incsearch_direction())
+        };
+        self.set_cursor_quietly(found.as_ref().map_or(start, |found| found.start));
+        if let Some(inc) = self.incsearch.as_mut() {
+            inc.current = found;
+        }
+    }
+
+    /// `Ctrl-G` and `Ctrl-T` move to the next and previous match while
+    /// typing a search pattern.  Returns `false` for other keys.
+    pub(crate) fn incsearch_key(&mut self, key: Key) -> VimResult<bool> {
+        let forward = match key {
+            Key::Ctrl('g') => true,
+            Key::Ctrl('t') => false,
+            _ => return Ok(false),
+        };
+        if !self.options.flag("incsearch") {
+            return Ok(true);
+        }
+        let typed = self.cmdline.getcmdline().to_string();
+        let Some(current) = self.incsearch.as_ref().and_then(|inc| inc.current.clone()) else {
+            return Ok(true);
+        };
+        let direction = if forward {
+            self.incsearch_direction()
+        } else {
+            self.incsearch_direction().reverse()
+        };
+        if let Some(found) = self.incsearch_find(&typed, current.start, direction) {
+            self.set_cursor_quietly(found.start);
+            if let Some(inc) = self.incsearch.as_mut() {
+                inc.current = Some(found);
+                inc.stepped = true;
+            }
+        }
+        Ok(true)
+    }
+
+    /// The first match of `pattern` after (or before) `from`, wrapping
+    /// around with 'wrapscan'
+    fn incsearch_find(
+        &mut self,
+        pattern: &str,
+        from: CursorPosition,
+        direction: Direction,
+    ) -> Option<SearchMatch> {
+        let options = self.search_options();
+        let mut compiled = self.search.compile(pattern, &options).ok()?;
+        compiled.direction = direction;
+        let lines = self
+            .buffers
+            .current()
+            .get_lines(0, -1, false)
+            .unwrap_or_default();
+        let line = lines
+            .get(from.line.to_zero_indexed())
+            .map_or("", String::as_str);
+        let from = match direction {
+            Direction::Forward => {
+                let next = line
+                    .get(from.col..)
+                    .and_then(|rest| rest.chars().next())
+                    .map_or(from.col + 1, |c| from.col + c.len_utf8());
+                CursorPosition::new(from.line, next)
+            }
+            Direction::Backward => from,
+        };
+        self.search.set_lines(lines);
+        self.search.search(&compiled, from, &options).ok().flatten()
+    }
+
+    fn set_cursor_quietly(&mut self, pos: CursorPosition) {
+        let ctx = self.cursor_context();
+        let _ = self.cursor.set_position(pos, &ctx);
+    }
+
+    /// Matches to highlight on lines `first` to `last`: the match
+    /// incremental search is on, and with 'hlsearch' all matches of the
+    /// pattern being typed or of the last search pattern
+    pub fn search_highlights(&mut self, first: LineNr, last: LineNr) -> Vec<SearchHighlight> {
+        let options = self.search_options();
+        let typing = options.incsearch && matches!(self.cmdline_type, Some('/' | '?'));
+        let (pattern, current) = match &self.incsearch {
+            Some(inc) if typing => (self.cmdline.getcmdline().to_string(), inc.current.clone()),
+            _ if options.hlsearch && self.search.state().highlighting => {
+                match self.search.last_pattern() {
+                    Some(last) => (last.pattern.clone(), None),
+                    None => return Vec::new(),
+                }
+            }
+            _ => return Vec::new(),
+        };
+        if pattern.is_empty() {
+            return Vec::new();
+        }
+
+        let mut matches = Vec::new();
+        if options.hlsearch {
+            let lines = self
+                .buffers
+                .current()
+                .get_lines(0, -1, false)
+                .unwrap_or_default();
+            self.search.set_lines(lines);
+            let compiled = SearchPattern::forward(pattern);
+            matches = self
+                .search
+                .find_all(
+                    &compiled,
+                    CursorPosition::new(first, 0),
+                    CursorPosition::new(last, usize::MAX),
+                    &options,
+                )
+                .unwrap_or_default();
+        }
+        let mut highlights: Vec<SearchHighlight> = matches
+            .iter()
+            .map(|found| highlight(found, current.as_ref() == Some(found)))
+            .collect();
+        if let Some(current) = current {
+            let visible = first <= current.start.line && current.start.line <= last;
+            if visible && !matches.contains(&current) {
+                highlights.push(highlight(&current, true));
+            }
+        }
+        highlights
+    }
+
+    /// The preview of the `:s` command being typed, with 'inccommand' set
+    pub fn substitute_preview(&self) -> Option<SubstitutePreview> {
+        if self.cmdline_type != Some(':') {
+            return None;
+        }
+        let mode = IncCommand::parse(self.options.string("inccommand")).unwrap_or_default();
+        if mode == IncCommand::Off {
+            return None;
+        }
+        let cmd = CommandExecutor::parse(self, self.cmdline.getcmdline()).ok()?;
+        if cmd.name.is_empty() || lookup(&cmd.name)?.name != "substitute" {
+            return None;
+        }
+        let current = self.cursor.line();
+        let (first, last) = self
+            .resolve_range(&cmd.range)
+            .ok()?
+            .unwrap_or((current, current));
+        let mut spec = SubstituteSpec::parse(&cmd.args).ok()?;
+        // A `\=` expression is only evaluated when the command is executed
+        if spec.replacement.starts_with("\\=") {
+            return None;
+        }
+        spec.range = Some(LineRange::new(first, last));
+        let last_pattern = self.search.last_pattern().map(|last| last.pattern.as_str());
+        let mut lines = self
+            .buffers
+            .current()
+            .get_lines(0, -1, false)
+            .unwrap_or_default();
+        let changed = substitute_lines(&lines, &spec, last_pattern).ok()?;
+        if changed.is_empty() {
+            return None;
+        }
+
+        let mut highlights = Vec::new();
+        for line in &changed {
+            highlights.extend(line.replaced.iter().map(|&(start, end)| SearchHighlight {
+                line: line.line,
+                start,
+                end,
+                current: false,
+            }));
+            lines[line.line.to_zero_indexed()] = line.text.clone();
+        }
+        let split = (mode == IncCommand::Split).then(|| {
+            changed
+                .iter()
+                .map(|line| format!("|{}| {}", line.line.0, line.text))
+                .collect()
+        });
+        Some(SubstitutePreview {
+            lines,
+            highlights,
+            split,
+        })
+    }
+}
+
+fn highlight(found: &SearchMatch, current: bool) -> SearchHighlight {
+    SearchHighlight {
+        line: found.start.line,
+        start: found.start.col,
+        end: found.end.col,
+        current,
+    }
+}
@@ -17,12 +17,12 @@ impl InputHandler {
     }
 
     /// Process a key and return a sequence of keys to be executed.
-    pub fn handle_key(&mut self, key: Key, editor: &Editor) -> Vec<Key> {
+    pub fn handle_key(&mut self, key: Key, editor: &mut Editor) -> Vec<Key> {
         self.buffer.push_str(&key.to_string());
         self.process_buffer(editor)
     }
 
-    fn process_buffer(&mut self, editor: &Editor) -> Vec<Key> {
+    fn process_buffer(&mut self, editor: &mut Editor) -> Vec<Key> {
         let mut output = Vec::new();
         let mode = editor.mode();
 
@@ -46,7 +46,11 @@ impl InputHandler {
             match editor.mappings.check(mode, &self.buffer) {
                 MappingCheckResult::FullMatch(m) => {
                     self.buffer.clear();
-                    let rhs = parse_keys(&m.rhs);
+                    let rhs = if m.expr {
+                        editor.expr_mapping_keys(&m.rhs)
+                    } else {
+                        parse_keys(&m.rhs)
+                    };
                     // For now, treat all as noremap (return keys to execute)
                     // TODO: Handle recursive mappings by feeding them back?
                     // But that requires changing the return type or interface.
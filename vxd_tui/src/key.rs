@@ -1,5 +1,5 @@
-use std::fmt;
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::fmt;
 
 /// Parsed key representation.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -51,6 +51,28 @@ impl fmt::Display for Key {
     }
 }
 
+/// Lead character of a key that has no character of its own in register
+/// text, like Vim's K_SPECIAL.  The key follows in `<>` notation.
+const SPECIAL_KEY: char = '\u{80}';
+
+impl Key {
+    /// The key as text in a register, the way a recorded macro stores it:
+    /// a control character for `<Esc>`, `<CR>`, `<BS>`, `<Tab>` and
+    /// `Ctrl` keys, the character itself for a typed character.
+    pub fn to_raw(&self) -> String {
+        match *self {
+            Key::Char(c) => c.to_string(),
+            Key::Escape => "\x1b".to_string(),
+            Key::Enter => "\r".to_string(),
+            Key::Backspace => "\x08".to_string(),
+            Key::Tab => "\t".to_string(),
+            Key::Ctrl(c @ ('a'..='z' | '@'..='_')) => ((c as u8 & 0x1f) as char).to_string(),
+            Key::Null => String::new(),
+            key => format!("{}{}", SPECIAL_KEY, key),
+        }
+    }
+}
+
 impl From<KeyEvent> for Key {
     fn from(event: KeyEvent) -> Self {
         match event.code {
@@ -129,13 +151,16 @@ pub fn parse_keys(input: &str) -> Vec<Key> {
                     let ch = s.chars().nth(2).unwrap_or('?');
                     Key::Alt(ch)
                 }
+                s if s.starts_with('f') && s[1..].parse::<u8>().is_ok() => {
+                    Key::F(s[1..].parse().unwrap_or(0))
+                }
                 _ => {
                     // Fallback for unknown special keys or literal <foo>
                     // Actually, if it's not recognized, it might be literal text wrapped in <>.
                     // But standard Vim notation expects <Esc>.
                     // For now, treat unknown as Null or handle gracefully?
                     // Let's treat as Null for now to avoid complexity.
-                    Key::Null 
+                    Key::Null
                 }
             };
             if key != Key::Null {
@@ -148,3 +173,45 @@ pub fn parse_keys(input: &str) -> Vec<Key> {
 
     keys
 }
+
+/// Parse register text, as executed with `@` or `:normal`, into keys.
+/// The reverse of [`Key::to_raw`]; a newline is `<CR>`.
+pub fn parse_raw_keys(text: &str) -> Vec<Key> {
+    let mut keys = Vec::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        let key = match c {
+            '\x1b' => Key::Escape,
+            '\r' | '\n' => Key::Enter,
+            '\x08' | '\x7f' => Key::Backspace,
+            '\t' => Key::Tab,
+            '\x01'..='\x1a' => Key::Ctrl((c as u8 - 1 + b'a') as char),
+            '\x1c'..='\x1f' => Key::Ctrl((c as u8 | 0x40) as char),
+            SPECIAL_KEY => {
+                let notation: String = chars.by_ref().take_while(|&c| c != '>').collect();
+                keys.extend(parse_keys(&format!("{}>", notation)));
+                continue;
+            }
+            c => Key::Char(c),
+        };
+        keys.push(key);
+    }
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_raw_keys_round_trip() {
+        let keys = parse_keys("ihi<Esc><C-r>a<CR><Left><F2><lt>x");
+        let raw: String = keys.iter().map(Key::to_raw).collect();
+        assert!(raw.starts_with("ihi\x1b\x12a\r"));
+        assert_eq!(parse_raw_keys(&raw), keys);
+        assert_eq!(
+            parse_raw_keys("dd\n\x16"),
+            vec![Key::Char('d'), Key::Char('d'), Key::Enter, Key::Ctrl('v')]
+        );
+    }
+}
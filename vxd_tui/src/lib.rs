@@ -6,7 +6,10 @@
 //! The implementation aims for exact Vim compatibility, validated against
 //! tests derived from Neovim's test suite.
 
+pub mod abbrev;
+pub mod autocmd;
 pub mod buffer;
+pub mod clipboard;
 pub mod cwd;
 pub mod cursor;
 pub mod filefinder;
@@ -14,13 +17,37 @@ pub mod fileedit;
 pub mod filebrowser;
 pub mod suspend;
 pub mod cmdline;
+pub mod cmdwin;
+pub mod completion;
+pub mod digraph;
+pub mod dispatch;
+pub mod display;
 pub mod editor;
+pub mod eval;
+pub mod ex;
 pub mod exit;
+pub mod exmode;
+pub mod extmarks;
+pub mod global;
+pub mod incsearch;
 pub mod input;
 pub mod key;
+pub mod macros;
+pub mod make;
 pub mod marks;
 pub mod modes;
+pub mod motion;
+pub mod operator;
+pub mod options;
+pub mod popupmenu;
+pub mod quickfix;
 pub mod registers;
+pub mod repeat;
+pub mod startup;
+pub mod vimgrep;
+pub mod vimscript;
+pub mod wildmenu;
+pub mod wordsearch;
 
 pub use editor::Editor;
 
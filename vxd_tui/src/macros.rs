@@ -0,0 +1,266 @@
+//! Macros: recording keys into a register and executing registers.
+//!
+//! `q{reg}` records the keys typed into a register until `q` is typed
+//! again; `q{A-Z}` appends to the register.  `@{reg}` executes the keys
+//! in a register, `@@` the last executed register again and `@:` the last
+//! command line.  `:{range}normal {keys}` executes keys for each line and
+//! `:{range}@{reg}` executes the lines of a register as Ex commands.
+//!
+//! Keys to execute are put in a typeahead queue, in front of the keys
+//! that are still waiting, so that a macro executing another macro works
+//! like typing.  A command that fails, with an error or a beep such as a
+//! motion that cannot move, flushes the queue and so aborts the macro.
+
+use std::collections::VecDeque;
+
+use vxd::autocmd::AutocmdEvent;
+use vxd::buffer::{Buffer, BufferManager};
+use vxd::cursor::{Cursor, CursorPosition};
+use vxd::modes::{Mode, ModeManager};
+use vxd::registers::{Register, RegisterBank, RegisterContent};
+use vxd::types::{LineNr, VimError, VimResult};
+
+use crate::editor::Editor;
+use crate::key::{parse_raw_keys, Key};
+
+/// State of macro recording and execution
+#[derive(Debug, Default)]
+pub(crate) struct MacroState {
+    /// Register being recorded into, as typed, and the keys so far
+    recording: Option<(char, String)>,
+    /// Register last recorded into
+    recorded: Option<char>,
+    /// Register last executed, for `@@`
+    executed: Option<char>,
+    /// Register whose keys are being executed
+    executing: Option<char>,
+    /// Keys waiting to be executed
+    typeahead: VecDeque<Key>,
+    /// Set when a command fails without an error
+    beeped: bool,
+}
+
+impl Editor {
+    /// The register being recorded into, like `reg_recording()`
+    pub fn reg_recording(&self) -> Option<char> {
+        self.macros
+            .recording
+            .as_ref()
+            .map(|(reg, _)| reg.to_ascii_lowercase())
+    }
+
+    /// The register last recorded into, like `reg_recorded()`
+    pub fn reg_recorded(&self) -> Option<char> {
+        self.macros.recorded
+    }
+
+    /// The register being executed, like `reg_executing()`
+    pub fn reg_executing(&self) -> Option<char> {
+        self.macros.executing
+    }
+
+    /// Fail the current command without an error.  This aborts the macro
+    /// or `:normal` being executed, like a beep in Vim.
+    pub(crate) fn beep(&mut self) {
+        self.macros.beeped = true;
+    }
+
+    /// Start handling a typed key: clear the beep and record the key
+    pub(crate) fn record_key(&mut self, key: Key) {
+        self.macros.beeped = false;
+        if let Some((_, keys)) = &mut self.macros.recording {
+            keys.push_str(&key.to_raw());
+        }
+    }
+
+    // ========================================================================
+    // Recording
+    // ========================================================================
+
+    /// `q{reg}`: start recording into `reg`
+    pub(crate) fn start_recording(&mut self, reg: char) -> VimResult<()> {
+        if !matches!(reg, 'a'..='z' | 'A'..='Z' | '0'..='9' | '"') {
+            self.beep();
+            return Ok(());
+        }
+        self.macros.recording = Some((reg, String::new()));
+        let mut data = self.autocmd_data();
+        data.data = Some(reg.to_ascii_lowercase().to_string());
+        self.apply_autocmds(AutocmdEvent::RecordingEnter, &data)
+    }
+
+    /// `q` while recording: store the recorded keys, without the `q`
+    pub(crate) fn stop_recording(&mut self) -> VimResult<()> {
+        let Some(reg) = self.reg_recording() else {
+            return Ok(());
+        };
+        let mut data = self.autocmd_data();
+        data.data = Some(reg.to_string());
+        let result = self.apply_autocmds(AutocmdEvent::RecordingLeave, &data);
+
+        let Some((name, mut keys)) = self.macros.recording.take() else {
+            return result;
+        };
+        keys.pop();
+        self.macros.recorded = Some(reg);
+        let register = Register::from_char(name)?;
+        if name.is_ascii_uppercase() {
+            // Appended keys continue the recorded line
+            if let Some(existing) = self.registers.get(register) {
+                keys = format!("{}{}", existing.as_string(), keys);
+            }
+        }
+        self.registers
+            .set(register, RegisterContent::characterwise(&keys))?;
+        result
+    }
+
+    // ========================================================================
+    // Execution
+    // ========================================================================
+
+    /// `@{reg}` executed `count` times.  `@@` executes the last executed
+    /// register, `@:` repeats the last command line.
+    pub(crate) fn execute_register(&mut self, reg: char, count: usize) -> VimResult<()> {
+        let reg = self.register_to_execute(reg)?;
+        self.macros.executed = Some(reg);
+        if reg == ':' {
+            let command = self
+                .registers
+                .last_command()
+                .map(str::to_string)
+                .ok_or_else(|| VimError::Error(30, "No previous command line".to_string()))?;
+            for _ in 0..count {
+                self.execute_cmdline_type(':', &command)?;
+            }
+            return Ok(());
+        }
+        if matches!(reg, '_' | '=') {
+            return Err(VimError::InvalidRegister(reg));
+        }
+        let text = self
+            .registers
+            .fetch(Register::from_char(reg)?)
+            .map(|content| content.as_string())
+            .unwrap_or_default();
+        if text.is_empty() {
+            self.beep();
+            return Ok(());
+        }
+        let keys = parse_raw_keys(&text);
+        for _ in 0..count {
+            self.feed_typeahead(&keys);
+        }
+        self.macros.executing = Some(reg);
+        Ok(())
+    }
+
+    /// Put `keys` in front of the keys waiting to be executed
+    pub(crate) fn feed_typeahead(&mut self, keys: &[Key]) {
+        for &key in keys.iter().rev() {
+            self.macros.typeahead.push_front(key);
+        }
+    }
+
+    /// The register `reg` names for `@`: `@` is the last executed one
+    fn register_to_execute(&self, reg: char) -> VimResult<char> {
+        match reg {
+            '@' => self
+                .macros
+                .executed
+                .ok_or_else(|| VimError::Error(748, "No previously used register".to_string())),
+            reg => Ok(reg.to_ascii_lowercase()),
+        }
+    }
+
+    /// Execute the keys waiting in the typeahead queue.  A key that fails
+    /// flushes the rest.
+    pub(crate) fn run_typeahead(&mut self) -> VimResult<()> {
+        while let Some(key) = self.macros.typeahead.pop_front() {
+            self.macros.beeped = false;
+            let result = self.dispatch_key(key);
+            if result.is_err() || self.macros.beeped {
+                self.flush_typeahead();
+                return result;
+            }
+        }
+        self.macros.executing = None;
+        Ok(())
+    }
+
+    /// Drop the keys waiting to be executed
+    pub(crate) fn flush_typeahead(&mut self) {
+        self.macros.typeahead.clear();
+        self.macros.executing = None;
+    }
+
+    /// Execute `keys` to the end, apart from the keys waiting, finishing
+    /// an incomplete command as if `<Esc>` was typed
+    fn exec_normal(&mut self, keys: Vec<Key>) -> VimResult<()> {
+        let waiting = std::mem::replace(&mut self.macros.typeahead, keys.into());
+        let executing = self.macros.executing.take();
+        let result = self.run_typeahead();
+        self.macros.typeahead = waiting;
+        self.macros.executing = executing;
+        self.macros.beeped = false;
+        for _ in 0..2 {
+            if self.modes.mode() == Mode::Normal && !self.has_pending_command() {
+                break;
+            }
+            self.dispatch_key(Key::Escape)?;
+        }
+        result
+    }
+
+    /// `:{range}normal {keys}`: execute `keys` in Normal mode, with a
+    /// range once on each line with the cursor at its start.  A key that
+    /// fails aborts the keys for that line only.
+    pub(crate) fn ex_normal(
+        &mut self,
+        range: Option<(LineNr, LineNr)>,
+        args: &str,
+    ) -> VimResult<()> {
+        if args.is_empty() {
+            return Err(VimError::Error(471, "Argument required".to_string()));
+        }
+        let keys = parse_raw_keys(args);
+        let Some((first, last)) = range else {
+            return self.exec_normal(keys);
+        };
+        for line in first.0..=last.0 {
+            if line > self.buffers.current().line_count() {
+                break;
+            }
+            let ctx = self.cursor_context();
+            self.cursor
+                .set_position(CursorPosition::new(LineNr(line), 0), &ctx)?;
+            self.cursor.update_curswant();
+            self.exec_normal(keys.clone())?;
+        }
+        Ok(())
+    }
+
+    /// `:[addr]@{reg}`: execute the lines of `reg` as Ex commands, with
+    /// the cursor on `addr`.  `:@:` repeats the last command line and
+    /// `:@@` or `:@` the last executed register.
+    pub(crate) fn ex_at(&mut self, line: Option<LineNr>, args: &str) -> VimResult<()> {
+        if let Some(line) = line {
+            let ctx = self.cursor_context();
+            self.cursor.set_line(line, &ctx)?;
+        }
+        let reg = self.register_to_execute(args.chars().next().unwrap_or('@'))?;
+        if reg == ':' {
+            return self.execute_register(reg, 1);
+        }
+        self.macros.executed = Some(reg);
+        let lines = self
+            .registers
+            .fetch(Register::from_char(reg)?)
+            .map(|content| content.text)
+            .unwrap_or_default();
+        for line in lines {
+            self.execute_cmdline_type(':', &line)?;
+        }
+        Ok(())
+    }
+}
@@ -1,8 +1,9 @@
 //! VXD TUI - Interactive terminal-based Vim editor
 //!
-//! Run with: cargo run
+//! Run with: cargo run -- [arguments] [file ..]
 
-use std::io::{self, stdout};
+use std::fs::File;
+use std::io::{self, stdout, BufReader, Read};
 
 use crossterm::{
     event::{self, Event, KeyEventKind},
@@ -11,16 +12,27 @@ use crossterm::{
 };
 use ratatui::{
     prelude::*,
-    widgets::{Block, Borders, Paragraph},
+    widgets::{Block, Borders, Clear, Paragraph},
 };
 
 use vxd::buffer::{Buffer, BufferManager};
+use vxd::cmdline::Cmdline;
+use vxd::completion::CompletionEngine;
 use vxd::cursor::Cursor;
+use vxd::display::{char_width, AmbiWidth, CellKind};
 use vxd::modes::Mode;
-use vxd::registers::{Register, RegisterBank};
+use vxd::search::{SearchCount, SearchEngine};
+use vxd::startup::{parse_args, StartupArgs};
+use vxd_tui::clipboard::detect_clipboard;
+use vxd_tui::cmdwin::CMDWIN_BUFFER_NAME;
+use vxd_tui::display::ViewRow;
 use vxd_tui::editor::Editor;
+use vxd_tui::incsearch::{SearchHighlight, SubstitutePreview};
 use vxd_tui::input::InputHandler;
-use vxd_tui::key::Key;
+use vxd_tui::key::{parse_raw_keys, Key};
+use vxd_tui::popupmenu::{PopupMenu, PumAnchor};
+use vxd_tui::startup::check_args;
+use vxd_tui::wildmenu::WildMenu;
 
 /// Application state
 struct App {
@@ -30,378 +42,174 @@ struct App {
 }
 
 impl App {
-    fn new() -> Self {
+    fn new(args: &StartupArgs, stdin: Option<&str>) -> Self {
         let mut editor = Editor::new();
-        // Set some initial content
-        editor
-            .buffers
-            .current_mut()
-            .set_lines(
-                0,
-                -1,
-                false,
-                vec![
-                    "Welcome to VXD - a Vim-compatible editor".to_string(),
-                    "".to_string(),
-                    "Commands:".to_string(),
-                    "  i     - Enter insert mode".to_string(),
-                    "  Esc   - Return to normal mode".to_string(),
-                    "  h/j/k/l or arrows - Move cursor".to_string(),
-                    "  x     - Delete character".to_string(),
-                    "  q     - Quit (in normal mode)".to_string(),
-                    "".to_string(),
-                    "Start editing below:".to_string(),
-                    "".to_string(),
-                ],
-            )
-            .unwrap();
-        editor.sync_cursor_with_buffer();
+        // Without a user interface there is no terminal to send OSC 52 to
+        if !args.headless {
+            if let Some(provider) = detect_clipboard() {
+                editor.registers.set_clipboard_provider(provider);
+            }
+        }
+        // Some initial content when there is nothing to edit
+        if args.files.is_empty() && stdin.is_none() {
+            editor
+                .buffers
+                .current_mut()
+                .set_lines(
+                    0,
+                    -1,
+                    false,
+                    vec![
+                        "Welcome to VXD - a Vim-compatible editor".to_string(),
+                        "".to_string(),
+                        "Commands:".to_string(),
+                        "  i     - Enter insert mode".to_string(),
+                        "  Esc   - Return to normal mode".to_string(),
+                        "  h/j/k/l or arrows - Move cursor".to_string(),
+                        "  x     - Delete character".to_string(),
+                        "  :q    - Quit".to_string(),
+                        "".to_string(),
+                        "Start editing below:".to_string(),
+                        "".to_string(),
+                    ],
+                )
+                .unwrap();
+            editor.buffers.current_mut().set_modified(false).unwrap();
+            editor.sync_cursor_with_buffer();
+        }
+        editor.startup(args, stdin);
 
         App {
+            should_quit: editor.quit_requested,
             editor,
             input_handler: InputHandler::new(),
-            should_quit: false,
         }
     }
 
     fn handle_key(&mut self, key: Key) {
-        let keys = self.input_handler.handle_key(key, &self.editor);
+        let keys = self.input_handler.handle_key(key, &mut self.editor);
         for k in keys {
             self.process_key_internal(k);
         }
     }
 
     fn process_key_internal(&mut self, key: Key) {
-        match self.editor.mode() {
-            Mode::Normal => self.handle_normal_key(key),
-            Mode::Insert => self.handle_insert_key(key),
-            _ => {}
-        }
-    }
-
-    fn handle_normal_key(&mut self, key: Key) {
-        match key {
-            // Quit
-            Key::Char('q') => self.should_quit = true,
-
-            // Enter insert mode
-            Key::Char('i') => {
-                let _ = self.editor.enter_insert();
-            }
-
-            // Append after cursor
-            Key::Char('a') => {
-                let _ = self.editor.cursor_right(1);
-                let _ = self.editor.enter_insert();
-            }
-
-            // Insert at beginning of line
-            Key::Char('I') => {
-                let ctx = self.editor.cursor_context();
-                let _ = self.editor.cursor.set_col(0, &ctx);
-                let _ = self.editor.enter_insert();
-            }
-
-            // Append at end of line
-            Key::Char('A') => {
-                let line_len = self.editor.current_line().len();
-                let ctx = self.editor.cursor_context();
-                let _ = self.editor.cursor.set_col(line_len, &ctx);
-                let _ = self.editor.enter_insert();
-            }
-
-            // Open line below
-            Key::Char('o') => {
-                let line = self.editor.cursor.line().0 as i64;
-                let _ = self.editor.buffers.current_mut().set_lines(
-                    line,
-                    line,
-                    false,
-                    vec!["".to_string()],
-                );
-                self.editor.sync_cursor_with_buffer();
-                let _ = self.editor.cursor_down(1);
-                let ctx = self.editor.cursor_context();
-                let _ = self.editor.cursor.set_col(0, &ctx);
-                let _ = self.editor.enter_insert();
-            }
-
-            // Open line above
-            Key::Char('O') => {
-                let line = self.editor.cursor.line().0 as i64 - 1;
-                let _ = self.editor.buffers.current_mut().set_lines(
-                    line,
-                    line,
-                    false,
-                    vec!["".to_string()],
-                );
-                self.editor.sync_cursor_with_buffer();
-                let ctx = self.editor.cursor_context();
-                let _ = self.editor.cursor.set_col(0, &ctx);
-                let _ = self.editor.enter_insert();
-            }
-
-            // Movement
-            Key::Char('h') | Key::Left => {
-                let _ = self.editor.cursor_left(1);
-            }
-            Key::Char('j') | Key::Down => {
-                let _ = self.editor.cursor_down(1);
-            }
-            Key::Char('k') | Key::Up => {
-                let _ = self.editor.cursor_up(1);
-            }
-            Key::Char('l') | Key::Right => {
-                let _ = self.editor.cursor_right(1);
-            }
-
-            // Beginning/end of line
-            Key::Char('0') => {
-                let ctx = self.editor.cursor_context();
-                let _ = self.editor.cursor.set_col(0, &ctx);
-            }
-            Key::Char('$') => {
-                let line_len = self.editor.current_line().len();
-                let ctx = self.editor.cursor_context();
-                let col = if line_len > 0 { line_len - 1 } else { 0 };
-                let _ = self.editor.cursor.set_col(col, &ctx);
-            }
-
-            // Word movement (simplified)
-            Key::Char('w') => {
-                // Move to next word start
-                let line = self.editor.current_line();
-                let col = self.editor.cursor.col();
-                if let Some(next_word) = find_next_word(&line, col) {
-                    let ctx = self.editor.cursor_context();
-                    let _ = self.editor.cursor.set_col(next_word, &ctx);
-                }
-            }
-            Key::Char('b') => {
-                // Move to previous word start
-                let line = self.editor.current_line();
-                let col = self.editor.cursor.col();
-                if let Some(prev_word) = find_prev_word(&line, col) {
-                    let ctx = self.editor.cursor_context();
-                    let _ = self.editor.cursor.set_col(prev_word, &ctx);
-                }
-            }
-
-            // Delete character
-            Key::Char('x') => {
-                let _ = self.editor.delete_char();
-            }
-
-            // Page up/down
-            Key::PageDown => {
-                let _ = self.editor.cursor_down(20);
+        match (self.editor.mode(), key) {
+            // Quit, unless Ctrl-C is for the command-line window
+            (Mode::Insert, Key::Ctrl('c')) if self.editor.cmdwin_type().is_none() => {
+                self.should_quit = true
             }
-            Key::PageUp => {
-                let _ = self.editor.cursor_up(20);
+            _ => {
+                let _ = self.editor.handle_key(key);
             }
-
-            // Go to first/last line
-            Key::Char('g') => {
-                // gg goes to first line (simplified, just 'g')
-                let ctx = self.editor.cursor_context();
-                let _ = self.editor.cursor.set_line(vxd::types::LineNr(1), &ctx);
-            }
-            Key::Char('G') => {
-                let line_count = self.editor.buffers.current().line_count();
-                let ctx = self.editor.cursor_context();
-                let _ = self
-                    .editor
-                    .cursor
-                    .set_line(vxd::types::LineNr(line_count), &ctx);
-            }
-
-            _ => {}
         }
-    }
-
-    fn handle_insert_key(&mut self, key: Key) {
-        match key {
-            // Exit insert mode
-            Key::Escape => {
-                let _ = self.editor.escape();
-            }
-
-            // Character input
-            Key::Char(c) => {
-                let _ = self.editor.insert_char(c);
-            }
-            Key::Ctrl(c) => {
-                // Handle Ctrl+C to exit
-                if c == 'c' {
-                    self.should_quit = true;
-                    return;
-                }
-                if c == 'a' {
-                    if let Some(content) = self.editor.registers.get(Register::LastInserted) {
-                        let _ = self.editor.insert_text(&content.as_string());
-                    }
-                    return;
-                }
-                if c == 'y' {
-                    let _ = self.editor.insert_from_adjacent_line(-1);
-                    return;
-                }
-                if c == 'e' {
-                    let _ = self.editor.insert_from_adjacent_line(1);
-                    return;
-                }
-            }
-
-            // Backspace
-            Key::Backspace => {
-                let col = self.editor.cursor.col();
-                if col > 0 {
-                    let _ = self.editor.cursor_left(1);
-                    let _ = self.editor.delete_char();
-                } else {
-                    // Join with previous line if at column 0
-                    let line = self.editor.cursor.line().0;
-                    if line > 1 {
-                        let prev_line = self
-                            .editor
-                            .buffers
-                            .current()
-                            .get_line(line as i64 - 2)
-                            .unwrap_or_default();
-                        let curr_line = self.editor.current_line();
-                        let prev_len = prev_line.len();
-                        let joined = format!("{}{}", prev_line, curr_line);
-
-                        let _ = self.editor.buffers.current_mut().set_lines(
-                            line as i64 - 2,
-                            line as i64,
-                            false,
-                            vec![joined],
-                        );
-
-                        self.editor.sync_cursor_with_buffer();
-                        let ctx = self.editor.cursor_context();
-                        let _ = self
-                            .editor
-                            .cursor
-                            .set_line(vxd::types::LineNr(line - 1), &ctx);
-                        let _ = self.editor.cursor.set_col(prev_len, &ctx);
-                    }
-                }
-            }
-
-            // Delete (forward)
-            Key::Delete => {
-                let _ = self.editor.delete_char();
-            }
-
-            // Enter
-            Key::Enter => {
-                let _ = self.editor.insert_newline();
-            }
-
-            // Tab
-            Key::Tab => {
-                // Insert 4 spaces
-                for _ in 0..4 {
-                    let _ = self.editor.insert_char(' ');
-                }
-            }
-
-            // Arrow keys
-            Key::Left => {
-                let _ = self.editor.cursor_left(1);
-            }
-            Key::Right => {
-                let _ = self.editor.cursor_right(1);
-            }
-            Key::Up => {
-                let _ = self.editor.cursor_up(1);
-            }
-            Key::Down => {
-                let _ = self.editor.cursor_down(1);
-            }
-
-            _ => {}
+        if self.editor.quit_requested {
+            self.should_quit = true;
         }
     }
 }
 
-/// Find the start of the next word
-fn find_next_word(line: &str, col: usize) -> Option<usize> {
-    let chars: Vec<char> = line.chars().collect();
-    let len = chars.len();
-
-    if col >= len {
-        return None;
-    }
+const USAGE: &str = "\
+usage: vxd [arguments] [file ..]       edit specified file(s)
+   or: vxd [arguments] -               read text from stdin
+
+Arguments:
+   --                   Only file names after this
+   +                    Start at end of file
+   +<lnum>              Start at line <lnum>
+   +/<pattern>          Start at first occurrence of <pattern>
+   -c <command>         Execute <command> after loading the first file
+   --cmd <command>      Execute <command> before loading any vimrc
+   -u <vimrc>           Use <vimrc> instead of any init.vim, NONE for none
+   -N                   Not fully Vi compatible: 'nocompatible'
+   -C                   Compatible with Vi: 'compatible'
+   -R                   Readonly mode
+   -n                   No swap file
+   -d                   Diff mode
+   -e                   Ex mode: execute command lines read from stdin
+   -s                   Silent (batch) mode (only after -e)
+   -s <scriptin>        Read Normal mode commands from file <scriptin>
+   -S <session>         Source file <session> after loading the first file
+   --headless           Don't start a user interface
+   -h  or  --help       Print Help (this message) and exit
+   --version            Print version information and exit";
 
-    // Skip current word (non-space characters)
-    let mut i = col;
-    while i < len && !chars[i].is_whitespace() {
-        i += 1;
+fn main() -> io::Result<()> {
+    let argv: Vec<String> = std::env::args().skip(1).collect();
+    let args = match parse_args(&argv).and_then(|args| check_args(&args).map(|_| args)) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{}", err);
+            eprintln!("More info with: \"vxd -h\"");
+            std::process::exit(1);
+        }
+    };
+    if args.help {
+        println!("{}", USAGE);
+        return Ok(());
     }
-
-    // Skip whitespace
-    while i < len && chars[i].is_whitespace() {
-        i += 1;
+    if args.version {
+        println!("VXD {}", env!("CARGO_PKG_VERSION"));
+        return Ok(());
     }
-
-    if i < len {
-        // Convert char index to byte offset
-        Some(chars[..i].iter().map(|c| c.len_utf8()).sum())
+    let stdin = if args.stdin {
+        let mut text = String::new();
+        io::stdin().read_to_string(&mut text)?;
+        Some(text)
     } else {
         None
-    }
-}
+    };
 
-/// Find the start of the previous word
-fn find_prev_word(line: &str, col: usize) -> Option<usize> {
-    let chars: Vec<char> = line.chars().collect();
+    let script_in = match &args.script_in {
+        Some(file) => match File::open(file) {
+            Ok(file) => Some(BufReader::new(file)),
+            Err(_) => {
+                eprintln!("Cannot open for reading: \"{}\"", file);
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
 
-    if col == 0 {
-        return None;
+    // Ex mode reads the command lines from the scriptin file or stdin
+    if args.ex_mode {
+        let mut editor = Editor::new();
+        editor.startup(&args, None);
+        let status = match script_in {
+            Some(input) => editor.ex_mode(input, &mut stdout().lock())?,
+            None => editor.ex_mode(io::stdin().lock(), &mut stdout().lock())?,
+        };
+        std::process::exit(status);
     }
 
-    // Convert byte offset to char index
-    let mut char_col = 0;
-    let mut byte_count = 0;
-    for (i, c) in chars.iter().enumerate() {
-        if byte_count >= col {
-            break;
+    // Create app
+    let mut app = App::new(&args, stdin.as_deref());
+    if let Some(mut input) = script_in {
+        let mut keys = String::new();
+        input.read_to_string(&mut keys)?;
+        for key in parse_raw_keys(&keys) {
+            app.handle_key(key);
         }
-        char_col = i + 1;
-        byte_count += c.len_utf8();
-    }
-    let mut i = char_col.saturating_sub(1);
-
-    // Skip whitespace backwards
-    while i > 0 && chars[i].is_whitespace() {
-        i -= 1;
     }
 
-    // Skip word backwards
-    while i > 0 && !chars[i - 1].is_whitespace() {
-        i -= 1;
+    // Without a user interface the messages of the startup commands are
+    // printed and the editor exits
+    if args.headless {
+        if let Some(message) = &app.editor.message {
+            eprintln!("{}", message);
+        }
+        std::process::exit(app.editor.exit_status.unwrap_or(0));
     }
 
-    // Convert back to byte offset
-    Some(chars[..i].iter().map(|c| c.len_utf8()).sum())
-}
-
-fn main() -> io::Result<()> {
     // Setup terminal
     enable_raw_mode()?;
     stdout().execute(EnterAlternateScreen)?;
     let mut terminal = Terminal::new(CrosstermBackend::new(stdout()))?;
 
-    // Create app
-    let mut app = App::new();
-
     // Main loop
-    loop {
+    while !app.should_quit {
         // Render
-        terminal.draw(|frame| render(frame, &app))?;
+        terminal.draw(|frame| render(frame, &mut app))?;
 
         // Handle events
         if event::poll(std::time::Duration::from_millis(16))? {
@@ -412,89 +220,358 @@ fn main() -> io::Result<()> {
                 }
             }
         }
-
-        if app.should_quit {
-            break;
-        }
     }
 
     // Cleanup terminal
     disable_raw_mode()?;
     stdout().execute(LeaveAlternateScreen)?;
 
+    if let Some(status) = app.editor.exit_status {
+        std::process::exit(status);
+    }
     Ok(())
 }
 
-fn render(frame: &mut Frame, app: &App) {
+fn render(frame: &mut Frame, app: &mut App) {
     let area = frame.area();
 
-    // Layout: main area + status line
+    // Layout: main area + status line + command line
     let chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Min(1), Constraint::Length(1)])
+        .constraints([
+            Constraint::Min(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
         .split(area);
 
+    // The 'inccommand' preview of a :s being typed, with the changed
+    // lines listed in a window below the text for "split"
+    let preview = app.editor.substitute_preview();
+    let split = preview.as_ref().and_then(|preview| preview.split.as_ref());
+    let (text_area, preview_area) = match split {
+        Some(split) => {
+            let height = (split.len() as u16 + 2).min(chunks[0].height / 2);
+            let parts = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Min(1), Constraint::Length(height)])
+                .split(chunks[0]);
+            (parts[0], Some(parts[1]))
+        }
+        None => (chunks[0], None),
+    };
+
+    // The quickfix window below the text, with the current entry in the
+    // QuickFixLine colors
+    let quickfix = app.editor.quickfix_view();
+    let (text_area, quickfix_area) = match &quickfix {
+        Some(view) => {
+            let height = (view.height as u16 + 2).min(text_area.height / 2);
+            let parts = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Min(1), Constraint::Length(height)])
+                .split(text_area);
+            (parts[0], Some(parts[1]))
+        }
+        None => (text_area, None),
+    };
+    if let (Some(area), Some(view)) = (quickfix_area, &quickfix) {
+        let rows = area.height.saturating_sub(2).max(1) as usize;
+        let top = (view.current + 1).saturating_sub(rows);
+        let lines: Vec<Line> = view
+            .lines
+            .iter()
+            .enumerate()
+            .skip(top)
+            .take(rows)
+            .map(|(idx, line)| {
+                if idx == view.current {
+                    Line::styled(
+                        line.as_str(),
+                        Style::default().bg(Color::Yellow).fg(Color::Black),
+                    )
+                } else {
+                    Line::raw(line.as_str())
+                }
+            })
+            .collect();
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(format!(" {} ", view.title));
+        frame.render_widget(Paragraph::new(lines).block(block), area);
+    }
+
     // Render buffer content
-    render_buffer(frame, chunks[0], app);
+    let cursor_cell = render_buffer(frame, text_area, app, preview.as_ref());
+    if let (Some(area), Some(split)) = (preview_area, split) {
+        let lines: Vec<Line> = split.iter().map(|line| Line::raw(line.as_str())).collect();
+        let block = Block::default().borders(Borders::ALL).title(" [Preview] ");
+        frame.render_widget(Paragraph::new(lines).block(block), area);
+    }
+
+    // The [N/M] search count, while search matches are highlighted
+    let search_count = if app.editor.search.state().highlighting {
+        let options = app.editor.search_count_options();
+        app.editor.searchcount(&options).ok()
+    } else {
+        None
+    };
 
-    // Render status line
-    render_status(frame, chunks[1], app);
+    // Render status line and command line
+    render_status(frame, chunks[1], app, search_count);
+    render_cmdline(frame, chunks[2], app);
 
     // Set cursor position
-    let cursor_line = app.editor.cursor.line().0 as u16;
-    let cursor_col = app.editor.cursor.col() as u16;
-    // Account for the border (1 row, 1 col offset)
-    let cursor_x = chunks[0].x + 1 + cursor_col;
-    let cursor_y = chunks[0].y + cursor_line; // line is 1-indexed, border adds 1
+    if let Mode::CommandLine(_) = app.editor.mode() {
+        let text = app.editor.cmdline.getcmdline();
+        let pos = app.editor.cmdline.getcmdpos() - 1;
+        let col = cmdline_width(&text[..pos]) as u16;
+        frame.set_cursor_position((chunks[2].x + 1 + col, chunks[2].y));
+
+        // Command-line completion matches, in a menu above the command
+        // line or in a bar over the status line
+        let Some(wildmenu) = app.editor.wildmenu() else {
+            return;
+        };
+        let start = cmdline_width(&text[..wildmenu.start.min(text.len())]);
+        let anchor = PumAnchor {
+            row: (chunks[2].y - area.y) as usize,
+            col: (chunks[2].x - area.x) as usize + 1 + start,
+            screen_rows: area.height as usize,
+            screen_cols: area.width as usize,
+        };
+        match app.editor.wildmenu_popup(anchor) {
+            Some(menu) => render_popup_menu(frame, area, &menu),
+            None if !wildmenu.pum => render_wildmenu_bar(frame, chunks[1], &wildmenu),
+            None => {}
+        }
+        return;
+    }
+    let Some((cursor_row, cursor_col)) = cursor_cell else {
+        return;
+    };
+    let line = app.editor.current_line();
+    let col = app.editor.cursor.col().min(line.len());
+    // Account for the border (1 row, 1 col offset) and the line number column
+    let cursor_x = text_area.x + 1 + NUMBER_WIDTH + cursor_col;
+    let cursor_y = text_area.y + 1 + cursor_row;
 
     // Ensure cursor is within bounds
-    if cursor_x < chunks[0].x + chunks[0].width - 1 && cursor_y < chunks[0].y + chunks[0].height - 1
+    if cursor_x < text_area.x + text_area.width - 1 && cursor_y < text_area.y + text_area.height - 1
     {
         frame.set_cursor_position((cursor_x, cursor_y));
     }
+
+    // The completion popup menu may cover any part of the screen
+    let start = app.editor.completion.state().start_col.min(col);
+    let display = app.editor.display_options();
+    let start_vcol = display.virtcol(&line, start).0;
+    let start_x = cursor_x.saturating_sub((display.virtcol(&line, col).0 - start_vcol) as u16);
+    let anchor = PumAnchor {
+        row: (cursor_y - area.y) as usize,
+        col: (start_x - area.x) as usize,
+        screen_rows: area.height as usize,
+        screen_cols: area.width as usize,
+    };
+    if let Some(menu) = app.editor.popup_menu(anchor) {
+        render_popup_menu(frame, area, &menu);
+    }
 }
 
-fn render_buffer(frame: &mut Frame, area: Rect, app: &App) {
-    let lines = app
-        .editor
-        .buffers
-        .current()
-        .get_lines(0, -1, false)
-        .unwrap_or_default();
+/// Draw the completion popup menu with Vim's default Pmenu colors
+fn render_popup_menu(frame: &mut Frame, area: Rect, menu: &PopupMenu) {
+    let normal = Style::default().bg(Color::LightMagenta).fg(Color::Black);
+    let selected = Style::default().bg(Color::Gray).fg(Color::Black);
+    let rows: Vec<Line> = menu
+        .rows
+        .iter()
+        .enumerate()
+        .map(|(idx, row)| {
+            let style = if row.selected { selected } else { normal };
+            let mut spans = vec![Span::styled(row.text.as_str(), style)];
+            if let Some((top, len)) = menu.scrollbar {
+                let thumb = (top..top + len).contains(&idx);
+                let color = if thumb { Color::White } else { Color::Gray };
+                spans.push(Span::styled(" ", Style::default().bg(color)));
+            }
+            Line::from(spans)
+        })
+        .collect();
+    let rect = Rect::new(
+        area.x + menu.col as u16,
+        area.y + menu.row as u16,
+        menu.total_width() as u16,
+        menu.height() as u16,
+    );
+    frame.render_widget(Clear, rect);
+    frame.render_widget(Paragraph::new(rows), rect);
+
+    if let Some(info) = &menu.info {
+        let rect = Rect::new(
+            area.x + info.col as u16,
+            area.y + info.row as u16,
+            info.width as u16,
+            info.lines.len() as u16,
+        );
+        let lines: Vec<Line> = info
+            .lines
+            .iter()
+            .map(|line| Line::raw(line.as_str()))
+            .collect();
+        frame.render_widget(Clear, rect);
+        frame.render_widget(Paragraph::new(lines).style(normal), rect);
+    }
+}
 
-    // Calculate visible range based on cursor position
-    let cursor_line = app.editor.cursor.line().0 as usize;
+/// Draw command-line completion matches in a bar, scrolled to show the
+/// selected one
+fn render_wildmenu_bar(frame: &mut Frame, area: Rect, menu: &WildMenu) {
+    let normal = Style::default().bg(Color::Blue).fg(Color::White);
+    let selected = Style::default().bg(Color::Yellow).fg(Color::Black);
+    let width = area.width as usize;
+    let first = menu.selected.map_or(0, |selected| {
+        // Drop matches from the left until the selected one fits
+        let mut first = 0;
+        let used = |first: usize| -> usize {
+            menu.items[first..=selected]
+                .iter()
+                .map(|item| cmdline_width(item) + 2)
+                .sum()
+        };
+        while first < selected && used(first) > width {
+            first += 1;
+        }
+        first
+    });
+    let mut spans = Vec::new();
+    if first > 0 {
+        spans.push(Span::styled("< ", normal));
+    }
+    for (idx, item) in menu.items.iter().enumerate().skip(first) {
+        let style = if Some(idx) == menu.selected {
+            selected
+        } else {
+            normal
+        };
+        spans.push(Span::styled(item.as_str(), style));
+        spans.push(Span::styled("  ", normal));
+    }
+    frame.render_widget(Clear, area);
+    frame.render_widget(Paragraph::new(Line::from(spans)).style(normal), area);
+}
+
+/// Width of the line number column
+const NUMBER_WIDTH: u16 = 5;
+
+/// Render the buffer as the rows of screen cells of the window view,
+/// highlighting search matches.  With a `:s` preview its lines are shown
+/// instead.  Returns the position of the cursor within the text area.
+fn render_buffer(
+    frame: &mut Frame,
+    area: Rect,
+    app: &mut App,
+    preview: Option<&SubstitutePreview>,
+) -> Option<(u16, u16)> {
+    let lines = match preview {
+        Some(preview) => preview.lines.clone(),
+        None => app
+            .editor
+            .buffers
+            .current()
+            .get_lines(0, -1, false)
+            .unwrap_or_default(),
+    };
+    let text_width = (area.width.saturating_sub(2 + NUMBER_WIDTH)) as usize;
     let visible_height = (area.height as usize).saturating_sub(2); // Account for borders
+    let view = app.editor.window_view(&lines, visible_height, text_width);
 
-    // Scroll so cursor is always visible
-    let scroll_offset = if cursor_line > visible_height {
-        cursor_line - visible_height
-    } else {
-        0
+    let highlights = match (preview, view.rows.first(), view.rows.last()) {
+        (Some(preview), _, _) => preview.highlights.clone(),
+        (None, Some(first), Some(last)) => app.editor.search_highlights(first.line, last.line),
+        _ => Vec::new(),
     };
+    let app = &*app;
 
     // Build display text with line numbers
-    let display: Vec<Line> = lines
+    let display: Vec<Line> = view
+        .rows
         .iter()
-        .enumerate()
-        .skip(scroll_offset)
-        .take(visible_height)
-        .map(|(i, line)| {
-            let line_num = format!("{:4} ", i + 1);
-            Line::from(vec![
-                Span::styled(line_num, Style::default().fg(Color::DarkGray)),
-                Span::raw(line.as_str()),
-            ])
+        .map(|row| {
+            // The command-line window shows its type instead of numbers
+            let line_num = match (row.first, app.editor.cmdwin_type()) {
+                (false, _) => " ".repeat(NUMBER_WIDTH as usize),
+                (true, Some(kind)) => format!("{:4} ", kind),
+                (true, None) => format!("{:4} ", row.line.0),
+            };
+            let mut spans = vec![Span::styled(line_num, Style::default().fg(Color::DarkGray))];
+            spans.extend(cell_spans(row, &highlights));
+            Line::from(spans)
         })
         .collect();
 
+    let title = match app.editor.cmdwin_type() {
+        Some(_) => format!(" {} ", CMDWIN_BUFFER_NAME),
+        None => " VXD ".to_string(),
+    };
     let buffer_widget =
-        Paragraph::new(display).block(Block::default().borders(Borders::ALL).title(" VXD "));
+        Paragraph::new(display).block(Block::default().borders(Borders::ALL).title(title));
 
     frame.render_widget(buffer_widget, area);
+    view.cursor.map(|(row, col)| (row as u16, col as u16))
+}
+
+/// The spans of the cells of `row`, grouped by style: search matches with
+/// the incremental search match reversed and others in Vim's default
+/// Search colors, SpecialKey and NonText cells, and closed folds
+fn cell_spans(row: &ViewRow, highlights: &[SearchHighlight]) -> Vec<Span<'static>> {
+    let mut spans: Vec<Span> = Vec::new();
+    let mut text = String::new();
+    let mut current = Style::default();
+    for cell in &row.cells {
+        let matched = highlights
+            .iter()
+            .find(|hl| hl.line == row.line && (hl.start..hl.end).contains(&cell.col));
+        let style = match (row.fold, cell.kind, matched) {
+            (true, _, _) => Style::default().fg(Color::Cyan),
+            (_, CellKind::Continuation, _) => continue,
+            (_, CellKind::NonText, _) => Style::default()
+                .fg(Color::LightBlue)
+                .add_modifier(Modifier::BOLD),
+            (_, _, Some(hl)) if hl.current => Style::default().add_modifier(Modifier::REVERSED),
+            (_, _, Some(_)) => Style::default().bg(Color::Yellow).fg(Color::Black),
+            (_, CellKind::Special, None) => Style::default().fg(Color::Blue),
+            (_, CellKind::Text, None) => Style::default(),
+        };
+        if style != current && !text.is_empty() {
+            spans.push(Span::styled(std::mem::take(&mut text), current));
+        }
+        current = style;
+        text.push_str(&cell.text);
+    }
+    if !text.is_empty() {
+        spans.push(Span::styled(text, current));
+    }
+    spans
+}
+
+/// The cells `text` takes on the command line
+fn cmdline_width(text: &str) -> usize {
+    text.chars()
+        .map(|ch| char_width(ch, AmbiWidth::Single))
+        .sum()
+}
+
+fn render_cmdline(frame: &mut Frame, area: Rect, app: &App) {
+    let text = match app.editor.cmdline_type() {
+        Some(kind) if matches!(app.editor.mode(), Mode::CommandLine(_)) => {
+            format!("{}{}", kind, app.editor.cmdline.getcmdline())
+        }
+        _ => app.editor.message.clone().unwrap_or_default(),
+    };
+    frame.render_widget(Paragraph::new(text), area);
 }
 
-fn render_status(frame: &mut Frame, area: Rect, app: &App) {
+fn render_status(frame: &mut Frame, area: Rect, app: &App, search_count: Option<SearchCount>) {
     let mode = app.editor.mode();
     let mode_str = match mode {
         Mode::Normal => " NORMAL ",
@@ -516,9 +593,18 @@ fn render_status(frame: &mut Frame, area: Rect, app: &App) {
         app.editor.cursor.line().0,
         app.editor.cursor.col() + 1
     );
+    let search_count = search_count
+        .map(|count| format!("{} ", count.display()))
+        .unwrap_or_default();
+    let recording = app
+        .editor
+        .reg_recording()
+        .map(|reg| format!(" recording @{} ", reg))
+        .unwrap_or_default();
 
     let status = Line::from(vec![
         Span::styled(mode_str, mode_style),
+        Span::styled(recording.clone(), Style::default().fg(Color::Yellow)),
         Span::raw(" [No Name] "),
         Span::styled(
             if app.editor.buffers.current().is_modified() {
@@ -528,11 +614,14 @@ fn render_status(frame: &mut Frame, area: Rect, app: &App) {
             },
             Style::default().fg(Color::Red),
         ),
-        Span::raw(" ".repeat(area.width as usize - mode_str.len() - cursor_pos.len() - 15)),
+        Span::raw(" ".repeat((area.width as usize).saturating_sub(
+            mode_str.len() + recording.len() + search_count.len() + cursor_pos.len() + 15,
+        ))),
+        Span::raw(search_count),
         Span::styled(cursor_pos, Style::default().fg(Color::Cyan)),
     ]);
 
     let status_widget = Paragraph::new(status).style(Style::default().bg(Color::DarkGray));
 
     frame.render_widget(status_widget, area);
-}
\ No newline at end of file
+}
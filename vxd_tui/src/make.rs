@@ -0,0 +1,92 @@
+//! `:make` and `:grep`: running a compiler or grep program.
+//!
+//! The program ('makeprg' or 'grepprg') is run through 'shell' with
+//! 'shellcmdflag', its output going to an error file by way of
+//! 'shellpipe'.  The lines of the error file are parsed with
+//! 'errorformat' (for `:grep` 'grepformat') into a new quickfix list, or
+//! location list for `:lmake` and `:lgrep`, and the first error is
+//! jumped to unless `!` is given.
+
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use vxd::autocmd::AutocmdEvent;
+use vxd::buffer::{Buffer, BufferManager};
+use vxd::quickfix::{make_command, ErrorFormat, QuickfixList};
+use vxd::types::{VimError, VimResult};
+
+use crate::editor::Editor;
+use crate::quickfix::read_lines;
+
+impl Editor {
+    /// `:make`, `:lmake`, `:grep` or `:lgrep` (`name`) with the arguments
+    /// `args`.  With `bang` the list is filled without jumping.
+    pub(crate) fn ex_make(&mut self, name: &str, args: &str, bang: bool) -> VimResult<()> {
+        let location = name.starts_with('l');
+        let (prg, format) = if name.ends_with("grep") {
+            ("grepprg", "grepformat")
+        } else {
+            ("makeprg", "errorformat")
+        };
+        let efm = ErrorFormat::parse(self.options.string(format))?;
+        self.quickfix_autocmd(AutocmdEvent::QuickFixCmdPre, name)?;
+
+        let args = self.expand_current_file(args);
+        let errorfile = temp_errorfile();
+        let command = make_command(
+            self.options.string(prg),
+            &args,
+            self.options.string("shellpipe"),
+            &errorfile,
+        );
+        // A shell that cannot be started leaves no error file, which is
+        // reported below
+        let _ = self
+            .command_env(&mut Command::new(self.options.string("shell")))
+            .arg(self.options.string("shellcmdflag"))
+            .arg(&command)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+        let lines = read_lines(&errorfile)
+            .ok_or_else(|| VimError::Error(40, format!("Can't open errorfile {}", errorfile)))?;
+        let _ = std::fs::remove_file(&errorfile);
+
+        let entries = efm.parse_lines(&lines);
+        self.push_quickfix_list(
+            QuickfixList::new(format!(":{}", command), entries),
+            location,
+        );
+        self.quickfix_autocmd(AutocmdEvent::QuickFixCmdPost, name)?;
+        let has_valid = self
+            .quickfix_list(location)
+            .is_ok_and(|list| !list.valid_indices().is_empty());
+        if !bang && has_valid {
+            self.quickfix_jump(location)?;
+        }
+        Ok(())
+    }
+
+    /// Replace each `%` argument in `args` with the current file name
+    fn expand_current_file(&self, args: &str) -> String {
+        let name = self.buffers.current().name();
+        args.split(' ')
+            .map(|arg| match arg {
+                "%" => name,
+                arg => arg,
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// A new error file name in the temporary directory
+fn temp_errorfile() -> String {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+    let count = COUNT.fetch_add(1, Ordering::Relaxed);
+    let name = format!("vxd{}_{}.err", std::process::id(), count);
+    let path = std::env::temp_dir().join(name);
+    let _ = std::fs::remove_file(&path);
+    path.to_string_lossy().into_owned()
+}
@@ -3,9 +3,12 @@
 //! This module provides a concrete implementation of Vim's mark system.
 
 use std::collections::HashMap;
-use vxd::buffer::BufHandle;
+use vxd::buffer::{BufHandle, TextSplice};
 use vxd::cursor::CursorPosition;
-use vxd::marks::{ChangeEntry, ChangeList, JumpEntry, JumpList, Mark, MarkManager, MarkValue};
+use vxd::marks::{
+    adjust_splice, splice_position, ChangeEntry, ChangeList, JumpEntry, JumpList, Mark,
+    MarkManager, MarkValue,
+};
 use vxd::types::{LineNr, VimError, VimResult};
 
 /// Jump list implementation
@@ -217,6 +220,41 @@ impl TuiMarkManager {
     pub fn clear_jump_list(&mut self) {
         self.jump_list.clear();
     }
+
+    /// Move the marks, jump list and change list entries of `buffer` for a
+    /// change in it.  Marks move with text inserted at their position,
+    /// like Vim's `mark_col_adjust()`.
+    pub fn splice(&mut self, buffer: BufHandle, splice: &TextSplice) {
+        let current = self.current_buffer;
+        let marks = self
+            .local_marks
+            .values_mut()
+            .chain(self.global_marks.values_mut())
+            .chain(self.special_marks.values_mut());
+        for value in marks {
+            // Marks without a buffer are in the current one, unless they
+            // only name a file that is not loaded
+            let mark_buffer = match (value.buffer, &value.file) {
+                (Some(handle), _) => handle,
+                (None, None) => current,
+                (None, Some(_)) => continue,
+            };
+            if mark_buffer == buffer {
+                value.position = splice_position(splice, value.position, true);
+            }
+        }
+        for entry in &mut self.jump_list.entries {
+            if entry.buffer == buffer {
+                entry.position = splice_position(splice, entry.position, true);
+            }
+        }
+        if buffer == current {
+            for entry in &mut self.change_list.entries {
+                entry.position = splice_position(splice, entry.position, true);
+                entry.col = entry.position.col;
+            }
+        }
+    }
 }
 
 impl MarkManager for TuiMarkManager {
@@ -292,22 +330,9 @@ impl MarkManager for TuiMarkManager {
         result
     }
 
-    fn adjust(&mut self, line: LineNr, _col: usize, lines_added: i64, _bytes_added: i64) {
-        // Adjust local marks
-        for value in self.local_marks.values_mut() {
-            if value.position.line.0 >= line.0 {
-                let new_line = (value.position.line.0 as i64 + lines_added).max(1) as usize;
-                value.position.line = LineNr(new_line);
-            }
-        }
-
-        // Adjust special marks
-        for value in self.special_marks.values_mut() {
-            if value.position.line.0 >= line.0 {
-                let new_line = (value.position.line.0 as i64 + lines_added).max(1) as usize;
-                value.position.line = LineNr(new_line);
-            }
-        }
+    fn adjust(&mut self, line: LineNr, col: usize, lines_added: i64, bytes_added: i64) {
+        let splice = adjust_splice(line, col, lines_added, bytes_added);
+        self.splice(self.current_buffer, &splice);
     }
 
     fn jump_list(&self) -> &dyn JumpList {
@@ -0,0 +1,579 @@
+//! Motion evaluation.
+//!
+//! Motions are resolved to a target position without moving the cursor, so
+//! the same code serves cursor movement, Visual mode and operators.
+
+use vxd::buffer::{Buffer, BufferManager};
+use vxd::cursor::{Cursor, CursorPosition};
+use vxd::motions::{CharClass, CharFindMotion};
+use vxd::search::{SearchEngine, SearchPattern};
+use vxd::types::{Direction, LineNr, MotionType, VimError, VimResult};
+
+use crate::editor::Editor;
+
+/// A motion command, independent of the keys that typed it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MotionKey {
+    /// `h`, `<Left>`
+    Left,
+    /// `l`, `<Right>`
+    Right,
+    /// `j`, `<Down>`
+    Down,
+    /// `k`, `<Up>`
+    Up,
+    /// `+`, `<CR>`
+    NextLineStart,
+    /// `-`
+    PrevLineStart,
+    /// `0`
+    LineStart,
+    /// `^`
+    FirstNonBlank,
+    /// `$`
+    LineEnd,
+    /// `w` (or `W` when true)
+    WordForward(bool),
+    /// `b` (or `B` when true)
+    WordBackward(bool),
+    /// `e` (or `E` when true)
+    WordEnd(bool),
+    /// `gg`
+    FirstLine,
+    /// `G`
+    LastLine,
+    /// `f`, `F`, `t`, `T`, `;`, `,`
+    FindChar(CharFindMotion),
+    /// `%`
+    MatchPair,
+    /// `n` (or `N` when true)
+    SearchNext(bool),
+    /// `*` and `#` (`g*` and `g#` when not whole words)
+    SearchWord(Direction, bool),
+    /// `[z`
+    FoldStart,
+    /// `]z`
+    FoldEnd,
+    /// `zj`
+    NextFold,
+    /// `zk`
+    PrevFold,
+}
+
+impl MotionKey {
+    /// The 'foldopen' item that opens a closed fold after this motion
+    pub fn foldopen_item(&self) -> Option<&'static str> {
+        match self {
+            MotionKey::Left
+            | MotionKey::Right
+            | MotionKey::LineStart
+            | MotionKey::FirstNonBlank
+            | MotionKey::LineEnd
+            | MotionKey::WordForward(_)
+            | MotionKey::WordBackward(_)
+            | MotionKey::WordEnd(_)
+            | MotionKey::FindChar(_) => Some("hor"),
+            MotionKey::MatchPair => Some("percent"),
+            MotionKey::SearchNext(_) | MotionKey::SearchWord(..) => Some("search"),
+            MotionKey::FirstLine | MotionKey::LastLine => Some("jump"),
+            _ => None,
+        }
+    }
+
+    /// Whether the motion is a jump that is recorded in the jumplist
+    pub fn is_jump(&self) -> bool {
+        matches!(
+            self,
+            MotionKey::FirstLine
+                | MotionKey::LastLine
+                | MotionKey::MatchPair
+                | MotionKey::SearchNext(_)
+                | MotionKey::SearchWord(..)
+        )
+    }
+
+    /// Whether the motion keeps the preferred column ('curswant')
+    pub fn keeps_curswant(&self) -> bool {
+        matches!(
+            self,
+            MotionKey::Down
+                | MotionKey::Up
+                | MotionKey::FirstLine
+                | MotionKey::LastLine
+                | MotionKey::NextFold
+                | MotionKey::PrevFold
+                | MotionKey::FoldStart
+                | MotionKey::FoldEnd
+        )
+    }
+}
+
+/// The result of evaluating a motion from the cursor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MotionTarget {
+    /// Where the motion ends
+    pub position: CursorPosition,
+    /// Linewise or characterwise
+    pub motion_type: MotionType,
+    /// Whether the character at `position` is included by an operator
+    pub inclusive: bool,
+}
+
+impl MotionTarget {
+    fn exclusive(position: CursorPosition) -> Self {
+        MotionTarget {
+            position,
+            motion_type: MotionType::Characterwise,
+            inclusive: false,
+        }
+    }
+
+    fn inclusive(position: CursorPosition) -> Self {
+        MotionTarget {
+            position,
+            motion_type: MotionType::Characterwise,
+            inclusive: true,
+        }
+    }
+
+    fn linewise(position: CursorPosition) -> Self {
+        MotionTarget {
+            position,
+            motion_type: MotionType::Linewise,
+            inclusive: true,
+        }
+    }
+}
+
+impl Editor {
+    /// Resolve `motion` with an optional count to a target position.
+    /// `for_operator` enables the operator special cases of `l` and `w`.
+    /// Returns `None` when the motion fails, which Vim signals with a beep.
+    pub fn evaluate_motion(
+        &mut self,
+        motion: MotionKey,
+        count: Option<usize>,
+        for_operator: bool,
+    ) -> VimResult<Option<MotionTarget>> {
+        let n = count.unwrap_or(1).max(1);
+        let pos = self.cursor.position();
+        let line = pos.line;
+        let lines = self
+            .buffers
+            .current()
+            .get_lines(0, -1, false)
+            .unwrap_or_default();
+        let text = lines.get(line.to_zero_indexed()).map_or("", |l| l.as_str());
+        let at = |line: LineNr, col: usize| CursorPosition::new(line, col);
+
+        let target = match motion {
+            MotionKey::Left => {
+                if pos.col == 0 {
+                    return Ok(None);
+                }
+                let mut col = pos.col;
+                for _ in 0..n {
+                    col = prev_char_start(text, col).unwrap_or(0);
+                }
+                MotionTarget::exclusive(at(line, col))
+            }
+            MotionKey::Right => {
+                let mut col = pos.col;
+                for _ in 0..n {
+                    let Some(ch) = text.get(col..).and_then(|rest| rest.chars().next()) else {
+                        break;
+                    };
+                    // Only an operator may move onto the end of the line
+                    if !for_operator && col + ch.len_utf8() >= text.len() {
+                        break;
+                    }
+                    col += ch.len_utf8();
+                }
+                if col == pos.col {
+                    return Ok(None);
+                }
+                MotionTarget::exclusive(at(line, col))
+            }
+            MotionKey::Down => {
+                let target = self.line_below(line, n);
+                if target == line {
+                    return Ok(None);
+                }
+                MotionTarget::linewise(at(target, self.cursor.curswant().value()))
+            }
+            MotionKey::Up => {
+                let target = self.line_above(line, n);
+                if target == line {
+                    return Ok(None);
+                }
+                MotionTarget::linewise(at(target, self.cursor.curswant().value()))
+            }
+            MotionKey::NextLineStart => {
+                let target = self.line_below(line, n);
+                MotionTarget::linewise(at(target, first_non_blank(&lines, target)))
+            }
+            MotionKey::PrevLineStart => {
+                let target = self.line_above(line, n);
+                MotionTarget::linewise(at(target, first_non_blank(&lines, target)))
+            }
+            MotionKey::LineStart => MotionTarget::exclusive(at(line, 0)),
+            MotionKey::FirstNonBlank => {
+                MotionTarget::exclusive(at(line, first_non_blank(&lines, line)))
+            }
+            MotionKey::LineEnd => {
+                let target = if n > 1 {
+                    self.line_below(line, n - 1)
+                } else {
+                    line
+                };
+                let len = line_len(&lines, target);
+                let col = prev_char_start(&lines[target.to_zero_indexed()], len).unwrap_or(0);
+                MotionTarget::inclusive(at(target, col))
+            }
+            MotionKey::WordForward(big) => {
+                let origin = (line.to_zero_indexed(), pos.col);
+                let mut p = origin;
+                for _ in 0..n {
+                    p = word_forward(&lines, p, big);
+                }
+                let end = &lines[p.0];
+                if p.1 > 0 && p.1 == end.len() {
+                    // Not past the end of the last line but on its last
+                    // character, which an operator then includes
+                    let col = prev_char_start(end, p.1).unwrap_or(0);
+                    MotionTarget::inclusive(at(LineNr(p.0 + 1), col))
+                } else {
+                    if for_operator && p.0 > origin.0 {
+                        // The end of the last word moved over ends the operated text
+                        let prev = p.0 - 1;
+                        let only_blanks = lines[p.0][..p.1].trim().is_empty();
+                        if only_blanks {
+                            p = (prev, lines[prev].len());
+                        }
+                    }
+                    MotionTarget::exclusive(at(LineNr(p.0 + 1), p.1))
+                }
+            }
+            MotionKey::WordBackward(big) => {
+                let mut p = (line.to_zero_indexed(), pos.col);
+                for _ in 0..n {
+                    p = word_backward(&lines, p, big);
+                }
+                MotionTarget::exclusive(at(LineNr(p.0 + 1), p.1))
+            }
+            MotionKey::WordEnd(big) => {
+                let mut p = (line.to_zero_indexed(), pos.col);
+                for _ in 0..n {
+                    p = word_end(&lines, p, big);
+                }
+                MotionTarget::inclusive(at(LineNr(p.0 + 1), p.1))
+            }
+            MotionKey::FirstLine => {
+                let target = LineNr(count.unwrap_or(1).clamp(1, lines.len().max(1)));
+                MotionTarget::linewise(at(target, pos.col))
+            }
+            MotionKey::LastLine => {
+                let target = LineNr(count.unwrap_or(lines.len()).clamp(1, lines.len().max(1)));
+                MotionTarget::linewise(at(target, pos.col))
+            }
+            MotionKey::FindChar(find) => {
+                let Some(col) = self.char_find_col(find, n) else {
+                    return Ok(None);
+                };
+                let inclusive = match find {
+                    CharFindMotion::FindForward(_) | CharFindMotion::TillForward(_) => true,
+                    CharFindMotion::FindBackward(_) | CharFindMotion::TillBackward(_) => false,
+                    CharFindMotion::RepeatForward | CharFindMotion::RepeatBackward => col > pos.col,
+                };
+                MotionTarget {
+                    position: at(line, col),
+                    motion_type: MotionType::Characterwise,
+                    inclusive,
+                }
+            }
+            MotionKey::MatchPair => {
+                let Some(col) = self.bracket_col() else {
+                    return Ok(None);
+                };
+                MotionTarget::inclusive(at(line, col))
+            }
+            MotionKey::SearchNext(reverse) => {
+                let mut pattern = self.search.last_pattern().cloned().ok_or_else(|| {
+                    VimError::Error(35, "No previous regular expression".to_string())
+                })?;
+                if reverse {
+                    pattern.direction = pattern.direction.reverse();
+                }
+                self.search.state_mut().highlighting = true;
+                let (found, wrapped) = self.find_match_repeated(&pattern, n)?;
+                self.show_search_count(&pattern, found, wrapped);
+                MotionTarget::exclusive(found)
+            }
+            MotionKey::SearchWord(direction, whole) => {
+                let pattern = self.start_word_search(direction, whole);
+                let found = pattern
+                    .as_ref()
+                    .map_err(Clone::clone)
+                    .and_then(|pattern| self.find_match_repeated(pattern, n));
+                let ctx = self.cursor_context();
+                let _ = self.cursor.set_position(pos, &ctx);
+                let (pattern, (found, wrapped)) = (pattern?, found?);
+                self.show_search_count(&pattern, found, wrapped);
+                MotionTarget::exclusive(found)
+            }
+            MotionKey::FoldStart => {
+                let Some(target) = self.folds.fold_start(line) else {
+                    return Ok(None);
+                };
+                MotionTarget::linewise(at(target, pos.col))
+            }
+            MotionKey::FoldEnd => {
+                let Some(target) = self.folds.fold_end(line) else {
+                    return Ok(None);
+                };
+                MotionTarget::linewise(at(target, pos.col))
+            }
+            MotionKey::NextFold => {
+                let mut target = line;
+                for _ in 0..n {
+                    let Some(next) = self.folds.next_fold_start(target) else {
+                        return Ok(None);
+                    };
+                    target = next;
+                }
+                MotionTarget::linewise(at(target, pos.col))
+            }
+            MotionKey::PrevFold => {
+                let mut target = line;
+                for _ in 0..n {
+                    let Some(prev) = self.folds.prev_fold_end(target) else {
+                        return Ok(None);
+                    };
+                    target = prev;
+                }
+                MotionTarget::linewise(at(target, pos.col))
+            }
+        };
+        Ok(Some(target))
+    }
+
+    /// The [count]th match of `pattern` from the cursor, leaving the cursor
+    /// where it is, and whether any of the searches wrapped around
+    fn find_match_repeated(
+        &mut self,
+        pattern: &SearchPattern,
+        n: usize,
+    ) -> VimResult<(CursorPosition, bool)> {
+        let saved = self.cursor.position();
+        let mut found = self.find_match(pattern);
+        let mut wrapped = false;
+        for _ in 1..n {
+            let Ok((from, wrap)) = found else { break };
+            wrapped |= wrap;
+            let ctx = self.cursor_context();
+            let _ = self.cursor.set_position(from, &ctx);
+            found = self.find_match(pattern);
+        }
+        let ctx = self.cursor_context();
+        let _ = self.cursor.set_position(saved, &ctx);
+        found.map(|(found, wrap)| (found, wrapped || wrap))
+    }
+
+    /// Target of `cw`: like `ce`, but staying on the current word when the
+    /// cursor is on its last character.
+    pub fn change_word_target(&self, count: usize, big: bool) -> Option<MotionTarget> {
+        let lines = self
+            .buffers
+            .current()
+            .get_lines(0, -1, false)
+            .unwrap_or_default();
+        let pos = self.cursor.position();
+        let start = (pos.line.to_zero_indexed(), pos.col);
+        let class = char_class(&lines, start, big);
+        if !matches!(class, Class::Word | Class::Punct) {
+            return None;
+        }
+        let mut p = start;
+        while let Some(q) = next_pos(&lines, p) {
+            if q.0 != p.0 || char_class(&lines, q, big) != class {
+                break;
+            }
+            p = q;
+        }
+        for _ in 1..count.max(1) {
+            p = word_end(&lines, p, big);
+        }
+        Some(MotionTarget::inclusive(CursorPosition::new(
+            LineNr(p.0 + 1),
+            p.1,
+        )))
+    }
+}
+
+/// Column of the first non-blank character of `line`
+pub fn first_non_blank(lines: &[String], line: LineNr) -> usize {
+    lines
+        .get(line.to_zero_indexed())
+        .and_then(|text| text.find(|c: char| c != ' ' && c != '\t'))
+        .unwrap_or(0)
+}
+
+fn line_len(lines: &[String], line: LineNr) -> usize {
+    lines.get(line.to_zero_indexed()).map_or(0, |l| l.len())
+}
+
+fn prev_char_start(text: &str, col: usize) -> Option<usize> {
+    text.get(..col.min(text.len()))?
+        .char_indices()
+        .next_back()
+        .map(|(idx, _)| idx)
+}
+
+// ============================================================================
+// Word Motions
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Class {
+    /// Whitespace or the end of a non-empty line
+    Blank,
+    /// An empty line, which counts as a word
+    EmptyLine,
+    Word,
+    Punct,
+}
+
+type Pos = (usize, usize);
+
+fn char_class(lines: &[String], (line, col): Pos, big: bool) -> Class {
+    let text = &lines[line];
+    if text.is_empty() {
+        return Class::EmptyLine;
+    }
+    let Some(ch) = text.get(col..).and_then(|rest| rest.chars().next()) else {
+        return Class::Blank;
+    };
+    let class = if big {
+        CharClass::classify_word(ch)
+    } else {
+        CharClass::classify(ch)
+    };
+    match class {
+        CharClass::Whitespace => Class::Blank,
+        CharClass::Word => Class::Word,
+        CharClass::Punctuation => Class::Punct,
+    }
+}
+
+/// Next position, visiting the end of each non-empty line once
+fn next_pos(lines: &[String], (line, col): Pos) -> Option<Pos> {
+    let text = &lines[line];
+    match text.get(col..).and_then(|rest| rest.chars().next()) {
+        Some(ch) => Some((line, col + ch.len_utf8())),
+        None if line + 1 < lines.len() => Some((line + 1, 0)),
+        None => None,
+    }
+}
+
+fn prev_pos(lines: &[String], (line, col): Pos) -> Option<Pos> {
+    if col == 0 {
+        if line == 0 {
+            return None;
+        }
+        return Some((line - 1, lines[line - 1].len()));
+    }
+    prev_char_start(&lines[line], col).map(|col| (line, col))
+}
+
+fn word_forward(lines: &[String], start: Pos, big: bool) -> Pos {
+    let mut p = start;
+    let class = char_class(lines, p, big);
+    if matches!(class, Class::Word | Class::Punct) {
+        while char_class(lines, p, big) == class {
+            match next_pos(lines, p) {
+                Some(q) => p = q,
+                None => return p,
+            }
+        }
+    } else if let Some(q) = next_pos(lines, p).filter(|_| class == Class::EmptyLine) {
+        p = q;
+    }
+    loop {
+        match char_class(lines, p, big) {
+            Class::Blank => match next_pos(lines, p) {
+                Some(q) => p = q,
+                None => return p,
+            },
+            _ => return p,
+        }
+    }
+}
+
+fn word_backward(lines: &[String], start: Pos, big: bool) -> Pos {
+    let Some(mut p) = prev_pos(lines, start) else {
+        return start;
+    };
+    while char_class(lines, p, big) == Class::Blank {
+        match prev_pos(lines, p) {
+            Some(q) => p = q,
+            None => return p,
+        }
+    }
+    let class = char_class(lines, p, big);
+    if class == Class::EmptyLine {
+        return p;
+    }
+    while let Some(q) = prev_pos(lines, p) {
+        if q.0 != p.0 || char_class(lines, q, big) != class {
+            break;
+        }
+        p = q;
+    }
+    p
+}
+
+fn word_end(lines: &[String], start: Pos, big: bool) -> Pos {
+    let Some(mut p) = next_pos(lines, start) else {
+        return start;
+    };
+    while matches!(char_class(lines, p, big), Class::Blank | Class::EmptyLine) {
+        match next_pos(lines, p) {
+            Some(q) => p = q,
+            None => return p,
+        }
+    }
+    let class = char_class(lines, p, big);
+    while let Some(q) = next_pos(lines, p) {
+        if q.0 != p.0 || char_class(lines, q, big) != class {
+            break;
+        }
+        p = q;
+    }
+    p
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &[&str]) -> Vec<String> {
+        text.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_word_forward_crosses_lines_and_stops_on_empty_line() {
+        let buf = lines(&["foo bar", "", "  baz"]);
+        assert_eq!(word_forward(&buf, (0, 0), false), (0, 4));
+        assert_eq!(word_forward(&buf, (0, 4), false), (1, 0));
+        assert_eq!(word_forward(&buf, (1, 0), false), (2, 2));
+    }
+
+    #[test]
+    fn test_word_classes_split_punctuation() {
+        let buf = lines(&["foo.bar baz"]);
+        assert_eq!(word_forward(&buf, (0, 0), false), (0, 3));
+        assert_eq!(word_forward(&buf, (0, 0), true), (0, 8));
+        assert_eq!(word_end(&buf, (0, 0), false), (0, 2));
+        assert_eq!(word_backward(&buf, (0, 8), false), (0, 4));
+    }
+}
@@ -0,0 +1,259 @@
+//! Operator application.
+//!
+//! Operators act on a [`MotionRange`] produced by a motion, a doubled
+//! operator (`dd`) or a Visual selection.
+
+use vxd::buffer::{Buffer, BufferManager};
+use vxd::cursor::{Cursor, CursorPosition};
+use vxd::marks::MarkManager;
+use vxd::operators::Operator;
+use vxd::registers::{Register, RegisterBank, RegisterContent, RegisterType};
+use vxd::types::{LineNr, MotionType, VimError, VimResult};
+
+use crate::editor::Editor;
+use crate::motion::first_non_blank;
+
+/// The text an operator acts on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct MotionRange {
+    pub start: CursorPosition,
+    pub end: CursorPosition,
+    pub motion_type: MotionType,
+    pub inclusive: bool,
+}
+
+impl MotionRange {
+    /// A linewise range over `start..=end`
+    pub fn lines(start: LineNr, end: LineNr) -> Self {
+        MotionRange {
+            start: CursorPosition::new(start, 0),
+            end: CursorPosition::new(end, 0),
+            motion_type: MotionType::Linewise,
+            inclusive: true,
+        }
+    }
+}
+
+impl Editor {
+    /// Apply `op` to `range`, storing removed or yanked text in `register`
+    /// (the unnamed register when `None`).
+    pub(crate) fn apply_operator(
+        &mut self,
+        op: Operator,
+        register: Option<char>,
+        range: MotionRange,
+    ) -> VimResult<()> {
+        let range = self.normalize_range(range);
+        let lines = self
+            .buffers
+            .current()
+            .get_lines(0, -1, false)
+            .unwrap_or_default();
+
+        match range.motion_type {
+            MotionType::Linewise => {
+                let first = range.start.line.to_zero_indexed();
+                let last = range
+                    .end
+                    .line
+                    .to_zero_indexed()
+                    .min(lines.len().saturating_sub(1));
+                let text = lines[first..=last].to_vec();
+                match op {
+                    Operator::Yank => {
+                        self.store_register(register, RegisterContent::linewise(text), true)?;
+                        if self.cursor.line() != range.start.line {
+                            let ctx = self.cursor_context();
+                            self.cursor.set_line(range.start.line, &ctx)?;
+                        }
+                    }
+                    Operator::Delete => {
+                        self.store_register(register, RegisterContent::linewise(text), false)?;
+                        self.buffers.current_mut().set_lines(
+                            first as i64,
+                            last as i64 + 1,
+                            false,
+                            Vec::new(),
+                        )?;
+                        self.sync_cursor_with_buffer();
+                        let line_count = self.buffers.current().line_count().max(1);
+                        let line = LineNr(range.start.line.0.min(line_count));
+                        let lines = self
+                            .buffers
+                            .current()
+                            .get_lines(0, -1, false)
+                            .unwrap_or_default();
+                        self.move_cursor(CursorPosition::new(line, first_non_blank(&lines, line)))?;
+                        self.marks.record_change(self.cursor.position());
+                    }
+                    Operator::Change => {
+                        self.store_register(register, RegisterContent::linewise(text), false)?;
+                        let indent = if self.options.flag("autoindent") {
+                            let line = &lines[first];
+                            line[..first_non_blank(&lines, range.start.line)].to_string()
+                        } else {
+                            String::new()
+                        };
+                        let col = indent.len();
+                        self.buffers.current_mut().set_lines(
+                            first as i64,
+                            last as i64 + 1,
+                            false,
+                            vec![indent],
+                        )?;
+                        self.sync_cursor_with_buffer();
+                        self.enter_insert()?;
+                        self.move_cursor(CursorPosition::new(range.start.line, col))?;
+                        self.marks.record_change(self.cursor.position());
+                    }
+                    Operator::CreateFold => {
+                        self.create_fold(range.start.line, range.end.line)?;
+                        self.move_cursor(CursorPosition::new(range.start.line, self.cursor.col()))?;
+                    }
+                    _ => return Err(operator_not_supported(op)),
+                }
+            }
+            _ => {
+                let (start, end) = (range.start, range.end);
+                let end_col = if range.inclusive {
+                    let text = &lines[end.line.to_zero_indexed()];
+                    text.get(end.col..)
+                        .and_then(|rest| rest.chars().next())
+                        .map_or(text.len(), |ch| end.col + ch.len_utf8())
+                } else {
+                    end.col
+                };
+                let first = start.line.to_zero_indexed();
+                let last = end.line.to_zero_indexed();
+                let start_col = start.col.min(lines[first].len());
+                let end_col = end_col.min(lines[last].len());
+                let text = if first == last {
+                    lines[first][start_col..end_col.max(start_col)].to_string()
+                } else {
+                    let mut text = lines[first][start_col..].to_string();
+                    for line in &lines[first + 1..last] {
+                        text.push('\n');
+                        text.push_str(line);
+                    }
+                    text.push('\n');
+                    text.push_str(&lines[last][..end_col]);
+                    text
+                };
+                match op {
+                    Operator::Yank => {
+                        self.store_register(register, RegisterContent::characterwise(text), true)?;
+                        self.move_cursor(start)?;
+                    }
+                    Operator::Delete | Operator::Change => {
+                        self.store_register(register, RegisterContent::characterwise(text), false)?;
+                        let joined =
+                            format!("{}{}", &lines[first][..start_col], &lines[last][end_col..]);
+                        self.buffers.current_mut().set_lines(
+                            first as i64,
+                            last as i64 + 1,
+                            false,
+                            vec![joined],
+                        )?;
+                        self.sync_cursor_with_buffer();
+                        if op == Operator::Change {
+                            self.enter_insert()?;
+                        }
+                        self.move_cursor(CursorPosition::new(start.line, start_col))?;
+                        self.marks.record_change(self.cursor.position());
+                    }
+                    Operator::CreateFold => {
+                        self.create_fold(start.line, end.line)?;
+                        self.move_cursor(start)?;
+                    }
+                    _ => return Err(operator_not_supported(op)),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Order the range and apply the linewise and exclusive adjustments
+    /// Vim makes before an operator runs.
+    fn normalize_range(&self, range: MotionRange) -> MotionRange {
+        let mut range = range;
+        if (range.end.line, range.end.col) < (range.start.line, range.start.col) {
+            std::mem::swap(&mut range.start, &mut range.end);
+        }
+        if range.motion_type == MotionType::Linewise {
+            // Closed folds are included as a whole
+            if let Some((start, _)) = self.closed_fold(range.start.line) {
+                range.start.line = start;
+            }
+            if let Some((_, end)) = self.closed_fold(range.end.line) {
+                range.end.line = end;
+            }
+            return range;
+        }
+        // An exclusive motion that ends in column zero stops at the end of
+        // the previous line (`:help exclusive`)
+        if !range.inclusive && range.end.col == 0 && range.end.line > range.start.line {
+            let lines = self
+                .buffers
+                .current()
+                .get_lines(0, -1, false)
+                .unwrap_or_default();
+            let prev = LineNr(range.end.line.0 - 1);
+            if range.start.col <= first_non_blank(&lines, range.start.line) {
+                range.motion_type = MotionType::Linewise;
+                range.end.line = prev;
+                return range;
+            }
+            let len = lines[prev.to_zero_indexed()].len();
+            range.end = CursorPosition::new(prev, len);
+        }
+        range
+    }
+
+    /// Store operator text in `register` the way Vim does: yanks fill `"0`,
+    /// deletes fill `"-` or shift `"1`-`"9`, and named registers also
+    /// update the unnamed register.
+    fn store_register(
+        &mut self,
+        register: Option<char>,
+        content: RegisterContent,
+        yank: bool,
+    ) -> VimResult<()> {
+        let is_small = content.reg_type == RegisterType::Characterwise
+            && !content.text.iter().any(|text| text.contains('\n'));
+        let Some(name) = register.filter(|&c| c != '"') else {
+            self.copy_unnamed_to_clipboard(&content)?;
+            return if yank {
+                self.registers.record_yank(content)
+            } else {
+                self.registers.record_delete(content, is_small)
+            };
+        };
+        let reg = Register::from_char(name)?;
+        if reg == Register::BlackHole {
+            return Ok(());
+        }
+        if !yank && !is_small {
+            self.registers.record_delete(content.clone(), false)?;
+        }
+        if Register::is_append(name) {
+            self.registers.append(reg, content)?;
+        } else {
+            self.set_register(reg, content)?;
+        }
+        if let Some(stored) = self.registers.get(reg).cloned() {
+            self.registers.set(Register::Unnamed, stored)?;
+        }
+        Ok(())
+    }
+
+    fn move_cursor(&mut self, pos: CursorPosition) -> VimResult<()> {
+        let ctx = self.cursor_context();
+        self.cursor.set_position(pos, &ctx)?;
+        self.cursor.update_curswant();
+        Ok(())
+    }
+}
+
+fn operator_not_supported(op: Operator) -> VimError {
+    VimError::NotAllowedInMode(format!("operator {:?} is not supported", op))
+}
@@ -0,0 +1,1089 @@
+//! Option implementation.
+//!
+//! This module provides a concrete implementation of `vxd::options::OptionManager`
+//! along with the `:set` argument syntax used by the Ex layer.
+//!
+//! Buffer and window options have a global value and a local value for the
+//! current buffer and window.  `:set` sets both, `:setlocal` the local and
+//! `:setglobal` the global one; a buffer edited for the first time starts
+//! with the global values.
+
+use std::collections::HashMap;
+
+use vxd::options::{options, OptionDef, OptionManager, OptionScope, OptionValue};
+use vxd::quickfix::{DEFAULT_ERRORFORMAT, DEFAULT_GREPFORMAT};
+use vxd::search::KeywordChars;
+use vxd::types::{VimError, VimResult};
+
+/// Local values of the options of one buffer or window, keyed by full
+/// option name
+pub type LocalValues = HashMap<String, OptionValue>;
+
+/// The values of an option a `:set` command acts on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetScope {
+    /// `:set`: the global and the local value
+    Both,
+    /// `:setlocal`: the local value, the global one of a global option
+    Local,
+    /// `:setglobal`: the global value
+    Global,
+}
+
+/// Concrete option store for the editor
+#[derive(Debug, Clone)]
+pub struct TuiOptionManager {
+    /// Known option definitions
+    defs: Vec<OptionDef>,
+    /// Global values keyed by full option name
+    values: HashMap<String, OptionValue>,
+    /// Local values of the buffer options for the current buffer
+    buffer: LocalValues,
+    /// Local values of the window options for the current window
+    window: LocalValues,
+}
+
+impl TuiOptionManager {
+    /// Create an option store with every option at its default value
+    pub fn new() -> Self {
+        let defs = default_definitions();
+        let values = defs
+            .iter()
+            .map(|def| (def.name.clone(), def.default.clone()))
+            .collect();
+        let mut manager = TuiOptionManager {
+            defs,
+            values,
+            buffer: LocalValues::new(),
+            window: LocalValues::new(),
+        };
+        manager.buffer = manager.global_values(OptionScope::Buffer);
+        manager.window = manager.global_values(OptionScope::Window);
+        manager
+    }
+
+    /// The global values of the options with `scope`
+    fn global_values(&self, scope: OptionScope) -> LocalValues {
+        self.defs
+            .iter()
+            .filter(|def| def.scope == scope)
+            .filter_map(|def| Some((def.name.clone(), self.values.get(&def.name)?.clone())))
+            .collect()
+    }
+
+    /// Make `values` the local values of the buffer options, the global
+    /// values when `None` for a buffer edited for the first time.  Returns
+    /// the values of the buffer left.
+    pub fn swap_buffer_values(&mut self, values: Option<LocalValues>) -> LocalValues {
+        let values = values.unwrap_or_else(|| self.global_values(OptionScope::Buffer));
+        std::mem::replace(&mut self.buffer, values)
+    }
+
+    /// The local values of the window options
+    pub fn window_values(&self) -> &LocalValues {
+        &self.window
+    }
+
+    /// Make `values` the local values of the window options
+    pub fn set_window_values(&mut self, values: LocalValues) {
+        self.window = values;
+    }
+
+    /// Resolve a full or short option name to its full name
+    pub fn resolve(&self, name: &str) -> Option<&str> {
+        self.defs
+            .iter()
+            .find(|def| def.name == name || def.short_name.as_deref() == Some(name))
+            .map(|def| def.name.as_str())
+    }
+
+    /// The value of option `name` a `:set` command with `scope` sees: the
+    /// global value for [`SetScope::Global`], the current value otherwise
+    pub fn get_in(&self, name: &str, scope: SetScope) -> Option<&OptionValue> {
+        let def = self.definition(name)?;
+        match (scope, def.scope) {
+            (SetScope::Global, _) | (_, OptionScope::Global) => self.values.get(&def.name),
+            (_, OptionScope::Buffer) => self.buffer.get(&def.name),
+            (_, OptionScope::Window) => self.window.get(&def.name),
+        }
+    }
+
+    /// Set the values of option `name` a `:set` command with `scope` sets
+    pub fn set_in(&mut self, name: &str, value: OptionValue, scope: SetScope) -> VimResult<()> {
+        let def = self.definition(name).ok_or_else(|| unknown_option(name))?;
+        let (name, option_scope) = (def.name.clone(), def.scope);
+        let same_type = matches!(
+            (self.values.get(&name), &value),
+            (Some(OptionValue::Boolean(_)), OptionValue::Boolean(_))
+                | (Some(OptionValue::Number(_)), OptionValue::Number(_))
+                | (Some(OptionValue::String(_)), OptionValue::String(_))
+        );
+        if !same_type {
+            return Err(invalid_argument(&name));
+        }
+        let local = match option_scope {
+            OptionScope::Global => None,
+            OptionScope::Buffer => Some(&mut self.buffer),
+            OptionScope::Window => Some(&mut self.window),
+        };
+        if let Some(local) = local.filter(|_| scope != SetScope::Global) {
+            local.insert(name.clone(), value.clone());
+            if scope == SetScope::Local {
+                return Ok(());
+            }
+        }
+        self.values.insert(name, value);
+        Ok(())
+    }
+
+    /// Get a number option, falling back to 0 for unknown names
+    pub fn number(&self, name: &str) -> i64 {
+        self.get_number(name).unwrap_or(0)
+    }
+
+    /// Get a boolean option, falling back to false for unknown names
+    pub fn flag(&self, name: &str) -> bool {
+        self.get_bool(name).unwrap_or(false)
+    }
+
+    /// Get a string option, falling back to "" for unknown names
+    pub fn string(&self, name: &str) -> &str {
+        self.get_string(name).unwrap_or("")
+    }
+
+    /// Whether a comma-separated string option contains `item`
+    pub fn has_item(&self, name: &str, item: &str) -> bool {
+        self.string(name).split(',').any(|part| part == item)
+    }
+
+    /// Get an item of the 'fillchars' option, e.g. `fillchar("fold", '-')`
+    pub fn fillchar(&self, item: &str, default: char) -> char {
+        self.string("fillchars")
+            .split(',')
+            .filter_map(|part| part.split_once(':'))
+            .find(|(key, _)| *key == item)
+            .and_then(|(_, value)| value.chars().next())
+            .unwrap_or(default)
+    }
+
+    /// Apply one `:set` argument (`nu`, `nonu`, `invnu`, `nu!`, `nu&`, `ts=4`,
+    /// `cpo+=x`, `ts?`).  Returns a message for queries.
+    pub fn set_from_str(&mut self, arg: &str) -> VimResult<Option<String>> {
+        self.set_from_str_in(arg, SetScope::Both)
+    }
+
+    /// Apply one argument of a `:set`, `:setlocal` or `:setglobal` command
+    /// to the values of `scope`.  Returns a message for queries.
+    pub fn set_from_str_in(&mut self, arg: &str, scope: SetScope) -> VimResult<Option<String>> {
+        let arg = arg.trim();
+        if arg.is_empty() {
+            return Ok(None);
+        }
+
+        let name_end = arg
+            .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+            .unwrap_or(arg.len());
+        let (raw_name, rest) = arg.split_at(name_end);
+
+        if let Some(op) = ["+=", "-=", "^=", "=", ":"]
+            .iter()
+            .find(|op| rest.starts_with(**op))
+        {
+            let name = self.require(raw_name, arg)?;
+            let value = &rest[op.len()..];
+            return self.assign(&name, op, value, arg, scope).map(|_| None);
+        }
+
+        match rest {
+            "?" => {
+                let name = self.require(raw_name, arg)?;
+                Ok(Some(self.show(&name, scope)))
+            }
+            "&" | "&vim" => {
+                let name = self.require(raw_name, arg)?;
+                let default = self.definition(&name).map(|def| def.default.clone());
+                let default = default.ok_or_else(|| unknown_option(arg))?;
+                self.set_in(&name, default, scope).map(|_| None)
+            }
+            "!" => {
+                let name = self.require(raw_name, arg)?;
+                self.toggle_checked(&name, arg, scope).map(|_| None)
+            }
+            "" => {
+                if let Some(name) = self.resolve(raw_name).map(str::to_string) {
+                    return match self.get_in(&name, scope) {
+                        Some(OptionValue::Boolean(_)) => self
+                            .set_in(&name, OptionValue::Boolean(true), scope)
+                            .map(|_| None),
+                        _ => Ok(Some(self.show(&name, scope))),
+                    };
+                }
+                if let Some(base) = raw_name.strip_prefix("inv") {
+                    let name = self.require(base, arg)?;
+                    return self.toggle_checked(&name, arg, scope).map(|_| None);
+                }
+                if let Some(base) = raw_name.strip_prefix("no") {
+                    let name = self.require(base, arg)?;
+                    if !matches!(self.get_in(&name, scope), Some(OptionValue::Boolean(_))) {
+                        return Err(invalid_argument(arg));
+                    }
+                    return self
+                        .set_in(&name, OptionValue::Boolean(false), scope)
+                        .map(|_| None);
+                }
+                Err(unknown_option(arg))
+            }
+            _ => Err(VimError::Error(518, format!("Unknown option: {}", arg))),
+        }
+    }
+
+    fn require(&self, name: &str, arg: &str) -> VimResult<String> {
+        self.resolve(name)
+            .map(str::to_string)
+            .ok_or_else(|| unknown_option(arg))
+    }
+
+    fn toggle_checked(&mut self, name: &str, arg: &str, scope: SetScope) -> VimResult<()> {
+        match self.get_in(name, scope) {
+            Some(OptionValue::Boolean(value)) => {
+                self.set_in(name, OptionValue::Boolean(!value), scope)
+            }
+            _ => Err(invalid_argument(arg)),
+        }
+    }
+
+    fn assign(
+        &mut self,
+        name: &str,
+        op: &str,
+        value: &str,
+        arg: &str,
+        scope: SetScope,
+    ) -> VimResult<()> {
+        match self.get_in(name, scope).cloned() {
+            Some(OptionValue::Number(current)) => {
+                let n: i64 = value.parse().map_err(|_| {
+                    VimError::Error(521, format!("Number required after =: {}", arg))
+                })?;
+                let new = match op {
+                    "+=" => current + n,
+                    "-=" => current - n,
+                    "^=" => current * n,
+                    _ => n,
+                };
+                self.set_in(name, OptionValue::Number(new), scope)
+            }
+            Some(OptionValue::String(current)) => {
+                let new = match op {
+                    "+=" if current.is_empty() => value.to_string(),
+                    "+=" if current.split(',').any(|part| part == value) => current,
+                    "+=" => format!("{},{}", current, value),
+                    "^=" if current.is_empty() => value.to_string(),
+                    "^=" => format!("{},{}", value, current),
+                    "-=" => current
+                        .split(',')
+                        .filter(|part| *part != value)
+                        .collect::<Vec<_>>()
+                        .join(","),
+                    _ => value.to_string(),
+                };
+                self.set_in(name, OptionValue::String(new), scope)
+            }
+            Some(OptionValue::Boolean(_)) => Err(invalid_argument(arg)),
+            None => Err(unknown_option(arg)),
+        }
+    }
+
+    fn show(&self, name: &str, scope: SetScope) -> String {
+        match self.get_in(name, scope) {
+            Some(OptionValue::Boolean(true)) => format!("  {}", name),
+            Some(OptionValue::Boolean(false)) => format!("no{}", name),
+            Some(OptionValue::Number(n)) => format!("  {}={}", name, n),
+            Some(OptionValue::String(s)) => format!("  {}={}", name, s),
+            None => String::new(),
+        }
+    }
+}
+
+impl Default for TuiOptionManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OptionManager for TuiOptionManager {
+    fn get(&self, name: &str) -> Option<&OptionValue> {
+        self.get_in(name, SetScope::Both)
+    }
+
+    fn set(&mut self, name: &str, value: OptionValue) -> VimResult<()> {
+        self.set_in(name, value, SetScope::Both)
+    }
+
+    fn set_local(&mut self, name: &str, value: OptionValue) -> VimResult<()> {
+        self.set_in(name, value, SetScope::Local)
+    }
+
+    fn reset(&mut self, name: &str) -> VimResult<()> {
+        let def = self
+            .definition(name)
+            .cloned()
+            .ok_or_else(|| unknown_option(name))?;
+        self.set_in(&def.name, def.default, SetScope::Both)
+    }
+
+    fn definitions(&self) -> Vec<&OptionDef> {
+        self.defs.iter().collect()
+    }
+
+    fn definition(&self, name: &str) -> Option<&OptionDef> {
+        let name = self.resolve(name)?;
+        self.defs.iter().find(|def| def.name == name)
+    }
+}
+
+fn unknown_option(arg: &str) -> VimError {
+    VimError::Error(518, format!("Unknown option: {}", arg))
+}
+
+fn invalid_argument(arg: &str) -> VimError {
+    VimError::Error(474, format!("Invalid argument: {}", arg))
+}
+
+fn def(
+    name: &str,
+    short_name: Option<&str>,
+    scope: OptionScope,
+    default: OptionValue,
+    description: &str,
+) -> OptionDef {
+    OptionDef {
+        name: name.to_string(),
+        short_name: short_name.map(str::to_string),
+        scope,
+        default,
+        hidden: false,
+        description: description.to_string(),
+    }
+}
+
+fn default_definitions() -> Vec<OptionDef> {
+    use OptionScope::{Buffer, Global, Window};
+    use OptionValue::{Boolean, Number, String as Str};
+
+    vec![
+        def(
+            options::TABSTOP,
+            Some("ts"),
+            Buffer,
+            Number(8),
+            "Number of spaces a <Tab> counts for",
+        ),
+        def(
+            options::SHIFTWIDTH,
+            Some("sw"),
+            Buffer,
+            Number(8),
+            "Number of spaces for each indent step",
+        ),
+        def(
+            options::EXPANDTAB,
+            Some("et"),
+            Buffer,
+            Boolean(false),
+            "Use spaces when inserting a <Tab>",
+        ),
+        def(
+            options::AUTOINDENT,
+            Some("ai"),
+            Buffer,
+            Boolean(false),
+            "Copy indent from the current line",
+        ),
+        def(
+            options::SMARTINDENT,
+            Some("si"),
+            Buffer,
+            Boolean(false),
+            "Smart autoindenting for C-like code",
+        ),
+        def(
+            "iskeyword",
+            Some("isk"),
+            Buffer,
+            Str(KeywordChars::DEFAULT.into()),
+            "Characters that are part of a keyword",
+        ),
+        def(
+            "comments",
+            Some("com"),
+            Buffer,
+            Str("s1:/*,mb:*,ex:*/,://,b:#,:%,:XCOMM,n:>,fb:-".into()),
+            "Strings that start a comment line",
+        ),
+        def(
+            "errorformat",
+            Some("efm"),
+            Global,
+            Str(DEFAULT_ERRORFORMAT.into()),
+            "Format of error messages for the quickfix list",
+        ),
+        def(
+            "grepformat",
+            Some("gfm"),
+            Global,
+            Str(DEFAULT_GREPFORMAT.into()),
+            "Format of the output of 'grepprg'",
+        ),
+        def(
+            "makeprg",
+            Some("mp"),
+            Global,
+            Str("make".into()),
+            "Program to use for the \":make\" command",
+        ),
+        def(
+            "grepprg",
+            Some("gp"),
+            Global,
+            Str("grep -n $* /dev/null".into()),
+            "Program to use for the \":grep\" command",
+        ),
+        def(
+            "shell",
+            Some("sh"),
+            Global,
+            Str(std::env::var("SHELL").unwrap_or_else(|_| "sh".to_string())),
+            "Shell to use for external commands",
+        ),
+        def(
+            "shellcmdflag",
+            Some("shcf"),
+            Global,
+            Str("-c".into()),
+            "Flag passed to the shell to execute a command",
+        ),
+        def(
+            "shellpipe",
+            Some("sp"),
+            Global,
+            Str("2>&1| tee".into()),
+            "String to put output of \":make\" in the error file",
+        ),
+        def(
+            "clipboard",
+            Some("cb"),
+            Global,
+            Str(String::new()),
+            "Use the clipboard as the unnamed register",
+        ),
+        def(
+            options::NUMBER,
+            Some("nu"),
+            Window,
+            Boolean(false),
+            "Print the line number in front of each line",
+        ),
+        def(
+            options::RELATIVENUMBER,
+            Some("rnu"),
+            Window,
+            Boolean(false),
+            "Show relative line numbers",
+        ),
+        def(
+            options::WRAP,
+            None,
+            Window,
+            Boolean(true),
+            "Long lines wrap",
+        ),
+        def(
+            options::IGNORECASE,
+            Some("ic"),
+            Global,
+            Boolean(false),
+            "Ignore case in search patterns",
+        ),
+        def(
+            options::SMARTCASE,
+            Some("scs"),
+            Global,
+            Boolean(false),
+            "No ignorecase when pattern has uppercase",
+        ),
+        def(
+            options::INCSEARCH,
+            Some("is"),
+            Global,
+            Boolean(true),
+            "Highlight match while typing search pattern",
+        ),
+        def(
+            options::HLSEARCH,
+            Some("hls"),
+            Global,
+            Boolean(true),
+            "Highlight matches with last search pattern",
+        ),
+        def(
+            "wrapscan",
+            Some("ws"),
+            Global,
+            Boolean(true),
+            "Searches wrap around the end of the file",
+        ),
+        def(
+            "magic",
+            None,
+            Global,
+            Boolean(true),
+            "Special characters in search patterns",
+        ),
+        def(
+            "report",
+            None,
+            Global,
+            Number(2),
+            "Threshold for reporting the number of changed lines",
+        ),
+        def(
+            "inccommand",
+            Some("icm"),
+            Global,
+            Str("nosplit".into()),
+            "Show the effect of :substitute while typing it",
+        ),
+        def(
+            "maxsearchcount",
+            Some("msc"),
+            Global,
+            Number(999),
+            "Maximum number of matches counted for the search count",
+        ),
+        def(
+            "shortmess",
+            Some("shm"),
+            Global,
+            Str("ltToOCF".into()),
+            "Flags to shorten or leave out messages",
+        ),
+        def(
+            options::SHOWMATCH,
+            Some("sm"),
+            Global,
+            Boolean(false),
+            "Briefly jump to matching bracket",
+        ),
+        def(
+            options::CURSORLINE,
+            Some("cul"),
+            Window,
+            Boolean(false),
+            "Highlight the screen line of the cursor",
+        ),
+        def(
+            options::CURSORCOLUMN,
+            Some("cuc"),
+            Window,
+            Boolean(false),
+            "Highlight the screen column of the cursor",
+        ),
+        def(
+            options::SCROLLOFF,
+            Some("so"),
+            Global,
+            Number(0),
+            "Minimum nr. of lines above and below cursor",
+        ),
+        def(
+            options::SIDESCROLLOFF,
+            Some("siso"),
+            Global,
+            Number(0),
+            "Min. nr. of columns to left and right of cursor",
+        ),
+        def(
+            options::HIDDEN,
+            Some("hid"),
+            Global,
+            Boolean(true),
+            "Don't unload buffer when it is abandoned",
+        ),
+        def(
+            options::FILEENCODING,
+            Some("fenc"),
+            Buffer,
+            Str(String::new()),
+            "File encoding for writing",
+        ),
+        def(
+            options::FILEFORMAT,
+            Some("ff"),
+            Buffer,
+            Str("unix".into()),
+            "File format used for file I/O",
+        ),
+        def(
+            options::MODIFIED,
+            Some("mod"),
+            Buffer,
+            Boolean(false),
+            "Buffer has been modified",
+        ),
+        def(
+            options::READONLY,
+            Some("ro"),
+            Buffer,
+            Boolean(false),
+            "Disallow writing the buffer",
+        ),
+        def(
+            options::MODIFIABLE,
+            Some("ma"),
+            Buffer,
+            Boolean(true),
+            "Changes to the text are possible",
+        ),
+        def(
+            options::SPELL,
+            None,
+            Window,
+            Boolean(false),
+            "Spell checking",
+        ),
+        def(
+            options::LIST,
+            None,
+            Window,
+            Boolean(false),
+            "Show <Tab> and <EOL>",
+        ),
+        def(
+            "listchars",
+            Some("lcs"),
+            Window,
+            Str("eol:$".into()),
+            "Characters for displaying in list mode",
+        ),
+        def(
+            "vartabstop",
+            Some("vts"),
+            Buffer,
+            Str(String::new()),
+            "Spaces that a <Tab> counts for, for each tab stop",
+        ),
+        def(
+            "display",
+            Some("dy"),
+            Global,
+            Str(String::new()),
+            "How to show the last line and unprintable characters",
+        ),
+        def(
+            "ambiwidth",
+            Some("ambw"),
+            Global,
+            Str("single".into()),
+            "Cells East Asian ambiguous characters take",
+        ),
+        def(
+            options::TIMEOUTLEN,
+            Some("tm"),
+            Global,
+            Number(1000),
+            "Time out time in milliseconds",
+        ),
+        def(
+            options::UPDATETIME,
+            Some("ut"),
+            Global,
+            Number(4000),
+            "After this many milliseconds flush swap file",
+        ),
+        def(
+            options::SIGNCOLUMN,
+            Some("scl"),
+            Window,
+            Str("auto".into()),
+            "When to display the sign column",
+        ),
+        def(
+            options::COLORCOLUMN,
+            Some("cc"),
+            Window,
+            Str(String::new()),
+            "Columns to highlight",
+        ),
+        def(
+            options::TEXTWIDTH,
+            Some("tw"),
+            Buffer,
+            Number(0),
+            "Maximum width of text that is being inserted",
+        ),
+        def(
+            options::VIRTUALEDIT,
+            Some("ve"),
+            Global,
+            Str(String::new()),
+            "When to use virtual editing",
+        ),
+        def(
+            "fillchars",
+            Some("fcs"),
+            Window,
+            Str("vert:|,fold:-,eob:~".into()),
+            "Characters to use for displaying special items",
+        ),
+        def(
+            "foldmethod",
+            Some("fdm"),
+            Window,
+            Str("manual".into()),
+            "Folding type",
+        ),
+        def(
+            "foldlevel",
+            Some("fdl"),
+            Window,
+            Number(0),
+            "Close folds with a level higher than this",
+        ),
+        def(
+            "foldignore",
+            Some("fdi"),
+            Window,
+            Str("#".into()),
+            "Lines ignored for indent folding",
+        ),
+        def(
+            "foldnestmax",
+            Some("fdn"),
+            Window,
+            Number(20),
+            "Maximum fold depth",
+        ),
+        def(
+            "foldminlines",
+            Some("fml"),
+            Window,
+            Number(1),
+            "Minimum number of lines for a fold to be closed",
+        ),
+        def(
+            "foldmarker",
+            Some("fmr"),
+            Window,
+            Str("{{{,}}}".into()),
+            "Markers used when 'foldmethod' is \"marker\"",
+        ),
+        def(
+            "foldexpr",
+            Some("fde"),
+            Window,
+            Str("0".into()),
+            "Expression used when 'foldmethod' is \"expr\"",
+        ),
+        def(
+            "foldopen",
+            Some("fdo"),
+            Global,
+            Str("block,hor,mark,percent,quickfix,search,tag,undo".into()),
+            "For which commands a fold will be opened",
+        ),
+        def(
+            "foldclose",
+            Some("fcl"),
+            Global,
+            Str(String::new()),
+            "When a fold is closed",
+        ),
+        def(
+            "foldenable",
+            Some("fen"),
+            Window,
+            Boolean(true),
+            "Set to display all folds open",
+        ),
+        def(
+            "complete",
+            Some("cpt"),
+            Buffer,
+            Str(".,w,b,u,t,i".into()),
+            "Specify how Insert mode completion works",
+        ),
+        def(
+            "completeopt",
+            Some("cot"),
+            Global,
+            Str("menu,preview".into()),
+            "Options for Insert mode completion",
+        ),
+        def(
+            "completefunc",
+            Some("cfu"),
+            Buffer,
+            Str(String::new()),
+            "Function used for Insert mode user completion",
+        ),
+        def(
+            "omnifunc",
+            Some("ofu"),
+            Buffer,
+            Str(String::new()),
+            "Function used for omni completion",
+        ),
+        def(
+            "dictionary",
+            Some("dict"),
+            Global,
+            Str(String::new()),
+            "List of file names used for keyword completion",
+        ),
+        def(
+            "thesaurus",
+            Some("tsr"),
+            Global,
+            Str(String::new()),
+            "List of thesaurus files for keyword completion",
+        ),
+        def(
+            "spellfile",
+            Some("spf"),
+            Buffer,
+            Str(String::new()),
+            "Files where zg and zw store words",
+        ),
+        def(
+            "tags",
+            Some("tag"),
+            Global,
+            Str("./tags,tags".into()),
+            "List of file names used by the tag command",
+        ),
+        def(
+            "path",
+            Some("pa"),
+            Global,
+            Str(".,/usr/include,,".into()),
+            "List of directories searched with gf and included files",
+        ),
+        def(
+            "pumheight",
+            Some("ph"),
+            Global,
+            Number(0),
+            "Maximum height of the popup menu",
+        ),
+        def(
+            "pumwidth",
+            Some("pw"),
+            Global,
+            Number(15),
+            "Minimum width of the popup menu",
+        ),
+        def(
+            "cedit",
+            None,
+            Global,
+            Str("\x06".into()),
+            "Key used to open the command-line window",
+        ),
+        def(
+            "wildchar",
+            Some("wc"),
+            Global,
+            Number(9),
+            "Key that starts command-line completion",
+        ),
+        def(
+            "wildmenu",
+            Some("wmnu"),
+            Global,
+            Boolean(true),
+            "Show command-line completion matches in a menu",
+        ),
+        def(
+            "wildmode",
+            Some("wim"),
+            Global,
+            Str("full".into()),
+            "What each completion key press does on the command line",
+        ),
+        def(
+            "wildoptions",
+            Some("wop"),
+            Global,
+            Str("pum,tagfile".into()),
+            "How command-line completion matches are shown",
+        ),
+        def(
+            "wildignore",
+            Some("wig"),
+            Global,
+            Str(String::new()),
+            "File patterns skipped by file name completion",
+        ),
+        def(
+            "wildignorecase",
+            Some("wic"),
+            Global,
+            Boolean(false),
+            "Ignore case when completing file names",
+        ),
+        def(
+            "fileignorecase",
+            Some("fic"),
+            Global,
+            Boolean(false),
+            "Ignore case when comparing file names",
+        ),
+        def(
+            "runtimepath",
+            Some("rtp"),
+            Global,
+            Str("~/.config/vxd".into()),
+            "Directories searched for runtime files",
+        ),
+        def(
+            "compatible",
+            Some("cp"),
+            Global,
+            Boolean(false),
+            "Behave like Vi more than Vim",
+        ),
+        def(
+            options::READONLY,
+            Some("ro"),
+            Buffer,
+            Boolean(false),
+            "Warn when writing the buffer",
+        ),
+        def(
+            "swapfile",
+            Some("swf"),
+            Buffer,
+            Boolean(true),
+            "Use a swap file for the buffer",
+        ),
+        def(
+            "diff",
+            None,
+            Window,
+            Boolean(false),
+            "Use the window in diff mode",
+        ),
+        def(
+            "digraph",
+            Some("dg"),
+            Global,
+            Boolean(false),
+            "Enter digraphs with {char1} <BS> {char2} in Insert mode",
+        ),
+        def(
+            "modeline",
+            Some("ml"),
+            Buffer,
+            Boolean(true),
+            "Look for modelines when reading a file",
+        ),
+        def(
+            "modelines",
+            Some("mls"),
+            Global,
+            Number(5),
+            "Number of lines at the start and end checked for modelines",
+        ),
+        def(
+            "modelineexpr",
+            Some("mle"),
+            Global,
+            Boolean(false),
+            "Allow options holding an expression in modelines",
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_short_names_resolve() {
+        let mut opts = TuiOptionManager::new();
+        opts.set_from_str("ts=4").unwrap();
+        assert_eq!(opts.get_number("tabstop"), Some(4));
+        assert_eq!(opts.get_number("ts"), Some(4));
+    }
+
+    #[test]
+    fn test_set_boolean_forms() {
+        let mut opts = TuiOptionManager::new();
+        opts.set_from_str("nu").unwrap();
+        assert!(opts.flag("number"));
+        opts.set_from_str("nonu").unwrap();
+        assert!(!opts.flag("number"));
+        opts.set_from_str("invnu").unwrap();
+        assert!(opts.flag("number"));
+        opts.set_from_str("nu!").unwrap();
+        assert!(!opts.flag("number"));
+    }
+
+    #[test]
+    fn test_set_string_list_operators() {
+        let mut opts = TuiOptionManager::new();
+        opts.set_from_str("foldopen=search").unwrap();
+        opts.set_from_str("fdo+=hor").unwrap();
+        assert_eq!(opts.string("foldopen"), "search,hor");
+        opts.set_from_str("fdo-=search").unwrap();
+        assert_eq!(opts.string("foldopen"), "hor");
+        opts.set_from_str("fdo&").unwrap();
+        assert!(opts.has_item("foldopen", "search"));
+    }
+
+    #[test]
+    fn test_set_errors() {
+        let mut opts = TuiOptionManager::new();
+        assert!(matches!(
+            opts.set_from_str("bogus"),
+            Err(VimError::Error(518, _))
+        ));
+        assert!(matches!(
+            opts.set_from_str("nots"),
+            Err(VimError::Error(474, _))
+        ));
+        assert!(matches!(
+            opts.set_from_str("ts=x"),
+            Err(VimError::Error(521, _))
+        ));
+        assert_eq!(
+            opts.set_from_str("ts?").unwrap(),
+            Some("  tabstop=8".to_string())
+        );
+    }
+
+    #[test]
+    fn test_local_values() {
+        let mut opts = TuiOptionManager::new();
+        opts.set_from_str_in("ts=4", SetScope::Local).unwrap();
+        opts.set_from_str_in("sw=2", SetScope::Global).unwrap();
+        assert_eq!(opts.number("tabstop"), 4);
+        assert_eq!(opts.number("shiftwidth"), 8);
+
+        let previous = opts.swap_buffer_values(None);
+        assert_eq!(opts.number("tabstop"), 8);
+        assert_eq!(opts.number("shiftwidth"), 2);
+        opts.swap_buffer_values(Some(previous));
+        assert_eq!(opts.number("tabstop"), 4);
+    }
+
+    #[test]
+    fn test_fillchar_lookup() {
+        let mut opts = TuiOptionManager::new();
+        assert_eq!(opts.fillchar("fold", '-'), '-');
+        opts.set_from_str("fillchars=fold:·").unwrap();
+        assert_eq!(opts.fillchar("fold", '-'), '·');
+        assert_eq!(opts.fillchar("eob", '~'), '~');
+    }
+}
@@ -0,0 +1,338 @@
+//! Insert mode completion popup menu.
+//!
+//! Vim shows completion matches in a popup menu below (or above) the
+//! cursor, aligned with the column where completion started.  This module
+//! computes where the menu goes and what each row shows; the terminal
+//! front end only draws the result.
+//!
+//! Each row is laid out as `" abbr kind menu "`, padded to at least
+//! 'pumwidth' columns.  When there are more matches than rows a scrollbar
+//! is drawn in an extra column on the right.  The `info` text of the
+//! selected match is shown in a separate float next to the menu.
+
+use vxd::completion::CompletionItem;
+
+/// Where the menu is anchored on the screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PumAnchor {
+    /// Screen row of the cursor line
+    pub row: usize,
+    /// Screen column of the completion start column
+    pub col: usize,
+    /// Number of screen rows the menu can use
+    pub screen_rows: usize,
+    /// Number of screen columns the menu can use
+    pub screen_cols: usize,
+}
+
+/// Size limits from the 'pumheight' and 'pumwidth' options
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PumLimits {
+    /// Maximum number of rows, zero for no limit ('pumheight')
+    pub max_height: usize,
+    /// Minimum width ('pumwidth')
+    pub min_width: usize,
+}
+
+/// A placed popup menu
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PopupMenu {
+    /// Screen row of the first menu row
+    pub row: usize,
+    /// Screen column of the left edge
+    pub col: usize,
+    /// Width of the item rows, without the scrollbar
+    pub width: usize,
+    /// Index of the first visible match
+    pub first: usize,
+    /// Index of the selected match
+    pub selected: Option<usize>,
+    /// The visible rows, top to bottom
+    pub rows: Vec<PumRow>,
+    /// Rows of the scrollbar thumb, when the matches do not all fit
+    pub scrollbar: Option<(usize, usize)>,
+    /// Float with the selected match's `info`
+    pub info: Option<InfoFloat>,
+}
+
+/// One visible row of the menu
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PumRow {
+    /// The row text, exactly `width` columns wide
+    pub text: String,
+    /// The row shows the selected match
+    pub selected: bool,
+}
+
+/// Extra information about the selected match, shown beside the menu
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InfoFloat {
+    /// Screen row of the first line
+    pub row: usize,
+    /// Screen column of the left edge
+    pub col: usize,
+    /// Width of the float
+    pub width: usize,
+    /// The lines shown, each exactly `width` columns wide
+    pub lines: Vec<String>,
+}
+
+impl PopupMenu {
+    /// Number of visible rows
+    pub fn height(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Total width, including the scrollbar column
+    pub fn total_width(&self) -> usize {
+        self.width + usize::from(self.scrollbar.is_some())
+    }
+
+    /// Place a menu for `items`.  `first` is the first match shown last
+    /// time; it is moved as little as needed to show the selection.
+    /// Returns `None` when there is no room for a menu.
+    pub fn layout(
+        items: &[CompletionItem],
+        selected: Option<usize>,
+        first: usize,
+        anchor: PumAnchor,
+        limits: PumLimits,
+        show_info: bool,
+    ) -> Option<Self> {
+        if items.is_empty() || anchor.screen_cols == 0 {
+            return None;
+        }
+        let count = items.len();
+        let wanted = match limits.max_height {
+            0 => count,
+            max => count.min(max),
+        };
+
+        // Below the cursor when the menu fits there or there is at least
+        // as much room as above
+        let below = anchor.screen_rows.saturating_sub(anchor.row + 1);
+        let above = anchor.row.min(anchor.screen_rows);
+        let (row, height) = if below >= wanted || below >= above {
+            (anchor.row + 1, wanted.min(below))
+        } else {
+            let height = wanted.min(above);
+            (anchor.row - height, height)
+        };
+        if height == 0 {
+            return None;
+        }
+
+        let abbr_width = column_width(items, |item| Some(display_word(item)));
+        let kind_width = column_width(items, |item| item.kind.as_deref());
+        let menu_width = column_width(items, |item| item.menu.as_deref());
+        let content = abbr_width
+            + if kind_width > 0 { kind_width + 1 } else { 0 }
+            + if menu_width > 0 { menu_width + 1 } else { 0 };
+        let scrollbar = usize::from(count > height);
+        let max_width = anchor.screen_cols.saturating_sub(scrollbar);
+        let width = (content + 2).max(limits.min_width).min(max_width);
+        if width == 0 {
+            return None;
+        }
+        // One column left of the start column, so the text lines up with
+        // the text in the buffer; shifted left when it does not fit
+        let col = anchor
+            .col
+            .saturating_sub(1)
+            .min(anchor.screen_cols - width - scrollbar);
+
+        let first = scroll_to(first, selected, height, count);
+        let rows = (first..first + height)
+            .map(|idx| {
+                let item = &items[idx];
+                let mut text = format!(" {}", pad(display_word(item), abbr_width));
+                if kind_width > 0 {
+                    text.push(' ');
+                    text.push_str(&pad(item.kind.as_deref().unwrap_or(""), kind_width));
+                }
+                if menu_width > 0 {
+                    text.push(' ');
+                    text.push_str(&pad(item.menu.as_deref().unwrap_or(""), menu_width));
+                }
+                PumRow {
+                    text: pad(&text, width),
+                    selected: selected == Some(idx),
+                }
+            })
+            .collect();
+
+        let mut menu = PopupMenu {
+            row,
+            col,
+            width,
+            first,
+            selected,
+            rows,
+            scrollbar: (scrollbar == 1).then(|| thumb(first, height, count)),
+            info: None,
+        };
+        if show_info {
+            let info = selected
+                .and_then(|idx| items[idx].info.as_deref())
+                .filter(|info| !info.is_empty());
+            if let Some(info) = info {
+                menu.info = menu.info_float(info, anchor);
+            }
+        }
+        Some(menu)
+    }
+
+    /// Place the info float right of the menu, or left when there is no
+    /// room on the right, level with the selected row
+    fn info_float(&self, info: &str, anchor: PumAnchor) -> Option<InfoFloat> {
+        let lines: Vec<&str> = info.lines().collect();
+        let wanted = lines.iter().map(|line| line.chars().count()).max()? + 1;
+        let right = self.col + self.total_width();
+        let room_right = anchor.screen_cols.saturating_sub(right);
+        let (col, width) = if room_right >= wanted || room_right >= self.col {
+            (right, wanted.min(room_right))
+        } else {
+            let width = wanted.min(self.col);
+            (self.col - width, width)
+        };
+        if width == 0 {
+            return None;
+        }
+        let selected_row = self.row + self.selected?.saturating_sub(self.first);
+        let height = lines.len().min(anchor.screen_rows);
+        let row = selected_row.min(anchor.screen_rows - height);
+        Some(InfoFloat {
+            row,
+            col,
+            width,
+            lines: lines[..height]
+                .iter()
+                .map(|line| pad(line, width))
+                .collect(),
+        })
+    }
+}
+
+/// The text shown for a match: its `abbr`, or the word itself
+fn display_word(item: &CompletionItem) -> &str {
+    item.abbr.as_deref().unwrap_or(&item.word)
+}
+
+fn column_width(items: &[CompletionItem], text: impl Fn(&CompletionItem) -> Option<&str>) -> usize {
+    items
+        .iter()
+        .filter_map(text)
+        .map(|text| text.chars().count())
+        .max()
+        .unwrap_or(0)
+}
+
+/// Pad or truncate `text` to exactly `width` characters
+fn pad(text: &str, width: usize) -> String {
+    let mut text: String = text.chars().take(width).collect();
+    let len = text.chars().count();
+    text.extend(std::iter::repeat_n(' ', width - len));
+    text
+}
+
+/// First visible match that keeps `selected` in view, moving `first` as
+/// little as possible
+pub fn scroll_to(first: usize, selected: Option<usize>, height: usize, count: usize) -> usize {
+    let mut first = first.min(count.saturating_sub(height));
+    if let Some(selected) = selected {
+        if selected < first {
+            first = selected;
+        } else if selected >= first + height {
+            first = selected + 1 - height;
+        }
+    }
+    first
+}
+
+/// Top row and length of the scrollbar thumb
+fn thumb(first: usize, height: usize, count: usize) -> (usize, usize) {
+    let len = (height * height / count).max(1);
+    let scrollable = count - height;
+    let top = (first * (height - len) + scrollable / 2) / scrollable;
+    (top, len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items(words: &[&str]) -> Vec<CompletionItem> {
+        words
+            .iter()
+            .map(|word| CompletionItem::new(*word))
+            .collect()
+    }
+
+    fn anchor(row: usize, col: usize) -> PumAnchor {
+        PumAnchor {
+            row,
+            col,
+            screen_rows: 20,
+            screen_cols: 40,
+        }
+    }
+
+    #[test]
+    fn test_menu_below_cursor_at_start_column() {
+        let items = items(&["alpha", "alphabet"]);
+        let menu = PopupMenu::layout(
+            &items,
+            Some(0),
+            0,
+            anchor(3, 10),
+            PumLimits::default(),
+            false,
+        )
+        .unwrap();
+        assert_eq!((menu.row, menu.col, menu.width), (4, 9, 10));
+        assert_eq!(menu.rows[0].text, " alpha    ");
+        assert!(menu.rows[0].selected);
+        assert!(menu.scrollbar.is_none());
+    }
+
+    #[test]
+    fn test_menu_above_cursor_and_shifted_left() {
+        let items = items(&["one", "two", "three"]);
+        let menu = PopupMenu::layout(&items, None, 0, anchor(18, 38), PumLimits::default(), false)
+            .unwrap();
+        assert_eq!((menu.row, menu.height()), (15, 3));
+        assert_eq!(menu.col + menu.width, 40);
+    }
+
+    #[test]
+    fn test_scrolling_keeps_selection_visible() {
+        let words: Vec<String> = (0..10).map(|i| format!("word{}", i)).collect();
+        let words: Vec<&str> = words.iter().map(String::as_str).collect();
+        let limits = PumLimits {
+            max_height: 3,
+            min_width: 15,
+        };
+        let menu =
+            PopupMenu::layout(&items(&words), Some(5), 0, anchor(0, 0), limits, false).unwrap();
+        assert_eq!((menu.first, menu.height(), menu.width), (3, 3, 15));
+        assert!(menu.rows[2].selected);
+        assert!(menu.scrollbar.is_some());
+        assert_eq!(scroll_to(3, Some(4), 3, 10), 3);
+        assert_eq!(scroll_to(3, Some(1), 3, 10), 1);
+    }
+
+    #[test]
+    fn test_kind_menu_columns_and_info_float() {
+        let mut item = CompletionItem::new("foo");
+        item.kind = Some("f".to_string());
+        item.menu = Some("[lib]".to_string());
+        item.info = Some("fn foo()".to_string());
+        let items = vec![item, CompletionItem::new("foobar")];
+        let menu = PopupMenu::layout(&items, Some(0), 0, anchor(0, 1), PumLimits::default(), true)
+            .unwrap();
+        assert_eq!(menu.rows[0].text, " foo    f [lib] ");
+        let info = menu.info.unwrap();
+        assert_eq!((info.row, info.col), (1, 16));
+        assert_eq!(info.lines, vec!["fn foo() "]);
+    }
+}
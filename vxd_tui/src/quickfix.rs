@@ -0,0 +1,551 @@
+//! Quickfix and location lists.
+//!
+//! The editor keeps a stack of quickfix lists and, for each window, a
+//! stack of location lists.  `:cnext`, `:cc` and friends go to an entry of the
+//! current quickfix list, `:lnext`, `:ll` and friends to one of the
+//! location list; each `:c` command has an `:l` twin.  `:copen` shows the
+//! current list in the quickfix window below the text, backed by a buffer
+//! with 'buftype' "quickfix" that is kept up to date as the list changes.
+//! `:cexpr` and friends turn lines of output into entries with
+//! 'errorformat', between the QuickFixCmdPre and QuickFixCmdPost events.
+
+use vxd::autocmd::AutocmdEvent;
+use vxd::buffer::{BufDeleteMode, BufHandle, Buffer, BufferManager, BufferType};
+use vxd::commands::{CommandExecutor, CommandRange, ExCommand, LineSpec};
+use vxd::cursor::{Cursor, CursorPosition};
+use vxd::eval::Value;
+use vxd::quickfix::{ErrorFormat, QuickfixList, QuickfixStack};
+use vxd::types::{LineNr, VimError, VimResult};
+
+use crate::editor::Editor;
+
+/// Name of the buffer shown in the quickfix window
+pub const QUICKFIX_BUFFER_NAME: &str = "[Quickfix List]";
+
+/// Name of the buffer shown in the quickfix window for a location list
+pub const LOCATION_BUFFER_NAME: &str = "[Location List]";
+
+/// Height of the quickfix window when `:copen` gives none
+const DEFAULT_HEIGHT: usize = 10;
+
+/// The quickfix commands: the `:c` name and shortest abbreviation, the
+/// `:l` name and shortest abbreviation, and a description
+pub(crate) const QUICKFIX_COMMANDS: &[(&str, usize, &str, usize, &str)] = &[
+    ("cc", 2, "ll", 2, "Go to an entry of the list"),
+    ("cnext", 2, "lnext", 3, "Go to the next entry of the list"),
+    (
+        "cNext",
+        2,
+        "lNext",
+        2,
+        "Go to the previous entry of the list",
+    ),
+    (
+        "cprevious",
+        2,
+        "lprevious",
+        2,
+        "Go to the previous entry of the list",
+    ),
+    (
+        "cnfile",
+        3,
+        "lnfile",
+        3,
+        "Go to the first entry in the next file",
+    ),
+    (
+        "cNfile",
+        3,
+        "lNfile",
+        3,
+        "Go to the last entry in the previous file",
+    ),
+    (
+        "cpfile",
+        3,
+        "lpfile",
+        3,
+        "Go to the last entry in the previous file",
+    ),
+    (
+        "cfirst",
+        4,
+        "lfirst",
+        4,
+        "Go to the first entry of the list",
+    ),
+    (
+        "crewind",
+        2,
+        "lrewind",
+        2,
+        "Go to the first entry of the list",
+    ),
+    ("clast", 3, "llast", 3, "Go to the last entry of the list"),
+    ("colder", 3, "lolder", 3, "Go to an older list"),
+    ("cnewer", 4, "lnewer", 4, "Go to a newer list"),
+    ("clist", 2, "llist", 3, "List the entries of the list"),
+    ("copen", 4, "lopen", 3, "Open the quickfix window"),
+    ("cclose", 3, "lclose", 3, "Close the quickfix window"),
+    (
+        "cwindow",
+        2,
+        "lwindow",
+        2,
+        "Open the quickfix window when there are entries",
+    ),
+    (
+        "cdo",
+        3,
+        "ldo",
+        3,
+        "Execute a command for each entry of the list",
+    ),
+    (
+        "cfdo",
+        3,
+        "lfdo",
+        3,
+        "Execute a command for each file in the list",
+    ),
+    ("cexpr", 3, "lexpr", 3, "Create a list from an expression"),
+    (
+        "cgetexpr",
+        5,
+        "lgetexpr",
+        5,
+        "Create a list from an expression without jumping",
+    ),
+    (
+        "caddexpr",
+        3,
+        "laddexpr",
+        3,
+        "Add entries from an expression to the list",
+    ),
+];
+
+/// Whether `name` is one of [`QUICKFIX_COMMANDS`]
+pub(crate) fn is_quickfix_command(name: &str) -> bool {
+    QUICKFIX_COMMANDS
+        .iter()
+        .any(|&(c_name, _, l_name, _, _)| name == c_name || name == l_name)
+}
+
+/// The quickfix window below the text
+#[derive(Debug, Clone)]
+pub(crate) struct QuickfixWindow {
+    buffer: BufHandle,
+    location: bool,
+    height: usize,
+}
+
+/// What the quickfix window shows, for drawing it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuickfixView {
+    /// Window title: the buffer name and the title of the list
+    pub title: String,
+    /// One line per entry
+    pub lines: Vec<String>,
+    /// Index of the current entry
+    pub current: usize,
+    /// Height of the window in lines
+    pub height: usize,
+}
+
+impl Editor {
+    /// The location list stack of the current window
+    pub fn loclist(&self) -> &QuickfixStack {
+        const EMPTY: &QuickfixStack = &QuickfixStack::new();
+        self.loclists.get(&self.window).unwrap_or(EMPTY)
+    }
+
+    /// The quickfix list stack, or the location list stack
+    pub fn quickfix_stack(&self, location: bool) -> &QuickfixStack {
+        if location {
+            self.loclist()
+        } else {
+            &self.quickfix
+        }
+    }
+
+    fn quickfix_stack_mut(&mut self, location: bool) -> &mut QuickfixStack {
+        if location {
+            self.loclists.entry(self.window).or_default()
+        } else {
+            &mut self.quickfix
+        }
+    }
+
+    /// The current quickfix or location list
+    pub fn quickfix_list(&self, location: bool) -> VimResult<&QuickfixList> {
+        self.quickfix_stack(location)
+            .current()
+            .ok_or_else(|| no_list(location))
+    }
+
+    fn quickfix_list_mut(&mut self, location: bool) -> VimResult<&mut QuickfixList> {
+        self.quickfix_stack_mut(location)
+            .current_mut()
+            .ok_or_else(|| no_list(location))
+    }
+
+    /// Add `list` as the newest quickfix (location) list
+    pub(crate) fn push_quickfix_list(&mut self, list: QuickfixList, location: bool) {
+        self.quickfix_stack_mut(location).push(list);
+        self.update_quickfix_window();
+    }
+
+    /// Fire QuickFixCmdPre or QuickFixCmdPost for the command `name`
+    pub(crate) fn quickfix_autocmd(&mut self, event: AutocmdEvent, name: &str) -> VimResult<()> {
+        let mut data = self.autocmd_data();
+        data.match_ = Some(name.to_string());
+        self.apply_autocmds(event, &data)
+    }
+
+    /// Execute the quickfix command `name` (a `:c` or `:l` name from
+    /// [`QUICKFIX_COMMANDS`]).  Returns a message to show.
+    pub(crate) fn ex_quickfix(&mut self, name: &str, cmd: &ExCommand) -> VimResult<Option<String>> {
+        let location = name.starts_with('l');
+        let base = match name {
+            "ll" => "cc".to_string(),
+            _ if location => format!("c{}", &name[1..]),
+            _ => name.to_string(),
+        };
+        match base.as_str() {
+            "cdo" | "cfdo" => {
+                return self
+                    .ex_cdo(&cmd.args, location, base == "cfdo")
+                    .map(|_| None)
+            }
+            "cexpr" | "cgetexpr" | "caddexpr" => {
+                let title = format!(":{} {}", cmd.name, cmd.args);
+                return self
+                    .ex_cexpr(name, &base, &cmd.args, &title, location)
+                    .map(|_| None);
+            }
+            "clist" => return self.ex_clist(location, cmd.bang).map(Some),
+            _ => {}
+        }
+
+        let count = command_count(cmd)?;
+        match base.as_str() {
+            "colder" | "cnewer" => {
+                let stack = self.quickfix_stack_mut(location);
+                if stack.is_empty() {
+                    return Err(no_list(location));
+                }
+                let moved = if base == "colder" {
+                    stack.older(count.unwrap_or(1))
+                } else {
+                    stack.newer(count.unwrap_or(1))
+                };
+                self.update_quickfix_window();
+                moved?;
+                return self.quickfix_list_message(location).map(Some);
+            }
+            "copen" => {
+                self.quickfix_list(location)?;
+                self.open_quickfix_window(location, count.unwrap_or(DEFAULT_HEIGHT));
+                return Ok(None);
+            }
+            "cclose" => {
+                self.close_quickfix_window(location);
+                return Ok(None);
+            }
+            "cwindow" => {
+                let has_valid = self
+                    .quickfix_list(location)
+                    .is_ok_and(|list| !list.valid_indices().is_empty());
+                if has_valid {
+                    self.open_quickfix_window(location, count.unwrap_or(DEFAULT_HEIGHT))
+                } else {
+                    self.close_quickfix_window(location)
+                }
+                return Ok(None);
+            }
+            _ => {}
+        }
+
+        let list = self.quickfix_list_mut(location)?;
+        match base.as_str() {
+            "cc" => list.select(count.unwrap_or(list.idx + 1))?,
+            "cnext" => list.go(count.unwrap_or(1), true, false)?,
+            "cNext" | "cprevious" => list.go(count.unwrap_or(1), false, false)?,
+            "cnfile" => list.go(count.unwrap_or(1), true, true)?,
+            "cNfile" | "cpfile" => list.go(count.unwrap_or(1), false, true)?,
+            "cfirst" | "crewind" => match count {
+                Some(nr) => list.select(nr)?,
+                None => list.first()?,
+            },
+            "clast" => match count {
+                Some(nr) => list.select(nr)?,
+                None => list.last()?,
+            },
+            _ => return Err(VimError::NotEditorCommand(name.to_string())),
+        };
+        self.quickfix_jump(location)?;
+        Ok(None)
+    }
+
+    /// "error list N of M; K errors" for `:colder` and `:cnewer`
+    fn quickfix_list_message(&self, location: bool) -> VimResult<String> {
+        let stack = self.quickfix_stack(location);
+        let list = self.quickfix_list(location)?;
+        Ok(format!(
+            "error list {} of {}; {} errors   {}",
+            stack.current_nr(),
+            stack.len(),
+            list.len(),
+            list.title
+        ))
+    }
+
+    /// `:clist`: the valid entries, or with `all` every entry, numbered
+    fn ex_clist(&self, location: bool, all: bool) -> VimResult<String> {
+        let list = self.quickfix_list(location)?;
+        let lines: Vec<String> = list
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| all || entry.valid)
+            .map(|(idx, entry)| entry.format_list(idx + 1))
+            .collect();
+        Ok(lines.join("\n"))
+    }
+
+    /// `:cexpr`, `:cgetexpr` and `:caddexpr` (`name` may be the `:l`
+    /// variant): parse the lines of `expr` with 'errorformat' into a new
+    /// list, or add them to the current one
+    fn ex_cexpr(
+        &mut self,
+        name: &str,
+        base: &str,
+        expr: &str,
+        title: &str,
+        location: bool,
+    ) -> VimResult<()> {
+        let lines = self.quickfix_expr_lines(expr)?;
+        let efm = ErrorFormat::parse(self.options.string("errorformat"))?;
+        self.quickfix_autocmd(AutocmdEvent::QuickFixCmdPre, name)?;
+        let entries = efm.parse_lines(&lines);
+        let add = base == "caddexpr" && !self.quickfix_stack(location).is_empty();
+        if add {
+            let list = self.quickfix_list_mut(location)?;
+            let first_new = list.len();
+            let had_valid = !list.valid_indices().is_empty();
+            list.entries.extend(entries);
+            if !had_valid {
+                list.idx = list.valid_indices().first().copied().unwrap_or(first_new);
+            }
+            self.update_quickfix_window();
+        } else {
+            self.push_quickfix_list(QuickfixList::new(title.trim_end(), entries), location)
+        }
+        self.quickfix_autocmd(AutocmdEvent::QuickFixCmdPost, name)?;
+        let has_valid = self
+            .quickfix_list(location)
+            .is_ok_and(|list| !list.valid_indices().is_empty());
+        if base == "cexpr" && has_valid {
+            self.quickfix_jump(location)?;
+        }
+        Ok(())
+    }
+
+    /// Lines given by the expression of `:cexpr`: a String (split at line
+    /// breaks) or the String items of a List
+    fn quickfix_expr_lines(&mut self, expr: &str) -> VimResult<Vec<String>> {
+        match self.eval_expression(expr)? {
+            Value::String(text) => Ok(text.lines().map(str::to_string).collect()),
+            Value::List(list) => Ok(list
+                .borrow()
+                .iter()
+                .filter_map(|item| match item {
+                    Value::String(text) => Some(text.clone()),
+                    _ => None,
+                })
+                .collect()),
+            _ => Err(VimError::Error(777, "String or List expected".to_string())),
+        }
+    }
+
+    /// `:cdo {cmd}` and `:cfdo {cmd}`: go to each valid entry (with
+    /// `by_file` the first one in each file) and execute `cmd` there
+    fn ex_cdo(&mut self, command: &str, location: bool, by_file: bool) -> VimResult<()> {
+        let list = self.quickfix_list(location)?;
+        let mut targets: Vec<usize> = Vec::new();
+        for idx in list.valid_indices() {
+            let file = &list.entries[idx].filename;
+            if by_file && targets.iter().any(|&t| list.entries[t].filename == *file) {
+                continue;
+            }
+            targets.push(idx);
+        }
+        if targets.is_empty() {
+            return Err(VimError::Error(42, "No Errors".to_string()));
+        }
+        for idx in targets {
+            self.quickfix_list_mut(location)?.idx = idx;
+            self.update_quickfix_window();
+            self.quickfix_jump(location)?;
+            let result = self.run(command);
+            if let Some(err) = result.error {
+                return Err(err);
+            }
+            if result.message.is_some() {
+                self.message = result.message;
+            }
+        }
+        Ok(())
+    }
+
+    /// Go to the current entry of the quickfix list (the location list
+    /// when `location` is set), editing its file if needed, and show
+    /// "(N of M): text"
+    pub(crate) fn quickfix_jump(&mut self, location: bool) -> VimResult<()> {
+        let list = self.quickfix_list(location)?;
+        let entry = list
+            .current()
+            .cloned()
+            .ok_or_else(|| VimError::Error(42, "No Errors".to_string()))?;
+        let kind = match entry.kind_name() {
+            Some(kind) => format!(" {}", kind),
+            None => String::new(),
+        };
+        let message = format!(
+            "({} of {}){}: {}",
+            list.idx + 1,
+            list.len(),
+            kind,
+            entry.text.trim().replace('\n', " ")
+        );
+
+        let handle = self.buffers.current().handle();
+        self.marks.push_jump(handle, self.cursor.position());
+        if !entry.filename.is_empty() && self.buffers.current().name() != entry.filename {
+            self.open_file(&entry.filename)?;
+        }
+        if entry.lnum.0 > 0 {
+            let ctx = self.cursor_context();
+            let last = LineNr(self.buffers.current().line_count().max(1));
+            let pos = CursorPosition::new(entry.lnum.min(last), entry.col.saturating_sub(1));
+            self.cursor.set_position(pos, &ctx)?;
+            self.cursor.update_curswant();
+        }
+        self.open_folds_for("quickfix");
+        self.update_quickfix_window();
+        self.message = Some(message);
+        Ok(())
+    }
+
+    /// Show the current list in the quickfix window, `height` lines high
+    fn open_quickfix_window(&mut self, location: bool, height: usize) {
+        if let Some(window) = self.qf_window.as_mut() {
+            if window.location == location {
+                window.height = height;
+                return;
+            }
+        }
+        self.close_quickfix_window(!location);
+        let name = if location {
+            LOCATION_BUFFER_NAME
+        } else {
+            QUICKFIX_BUFFER_NAME
+        };
+        let Ok(buffer) = self.buffers.create_named(name) else {
+            return;
+        };
+        if let Some(buf) = self.buffers.get_mut(buffer) {
+            let _ = buf.set_buftype(BufferType::Quickfix);
+            let _ = buf.set_listed(false);
+        }
+        self.qf_window = Some(QuickfixWindow {
+            buffer,
+            location,
+            height,
+        });
+        self.update_quickfix_window();
+    }
+
+    /// Close the quickfix window when it shows the quickfix list
+    /// (location list when `location` is set)
+    fn close_quickfix_window(&mut self, location: bool) {
+        let Some(window) = self.qf_window.take_if(|window| window.location == location) else {
+            return;
+        };
+        let _ = self
+            .buffers
+            .delete(window.buffer, BufDeleteMode::Wipe, true);
+    }
+
+    /// Fill the quickfix window's buffer with the entries of its list
+    pub(crate) fn update_quickfix_window(&mut self) {
+        let Some(window) = self.qf_window.clone() else {
+            return;
+        };
+        let lines: Vec<String> = match self.quickfix_list(window.location) {
+            Ok(list) => list.entries.iter().map(|entry| entry.format()).collect(),
+            Err(_) => Vec::new(),
+        };
+        if let Some(buf) = self.buffers.get_mut(window.buffer) {
+            let _ = buf.set_lines(0, -1, false, lines);
+            buf.take_changes();
+            let _ = buf.set_modified(false);
+        }
+    }
+
+    /// The quickfix window, when it is open
+    pub fn quickfix_view(&self) -> Option<QuickfixView> {
+        let window = self.qf_window.as_ref()?;
+        let buffer = self.buffers.get(window.buffer)?;
+        let list = self.quickfix_list(window.location).ok();
+        let title = match list {
+            Some(list) if !list.title.is_empty() => format!("{} {}", buffer.name(), list.title),
+            _ => buffer.name().to_string(),
+        };
+        Some(QuickfixView {
+            title,
+            lines: buffer.get_lines(0, -1, false).unwrap_or_default(),
+            current: list.map_or(0, |list| list.idx),
+            height: window.height,
+        })
+    }
+}
+
+/// E42 for the quickfix list, E776 for a missing location list
+fn no_list(location: bool) -> VimError {
+    if location {
+        VimError::Error(776, "No location list".to_string())
+    } else {
+        VimError::Error(42, "No Errors".to_string())
+    }
+}
+
+/// The count of a quickfix command: its argument, or else the number
+/// given as its range, as in `:3cnext`
+fn command_count(cmd: &ExCommand) -> VimResult<Option<usize>> {
+    if !cmd.args.is_empty() {
+        return cmd
+            .args
+            .parse()
+            .map(Some)
+            .map_err(|_| VimError::TrailingCharacters);
+    }
+    Ok(range_count(&cmd.range))
+}
+
+/// The last line number of `range`, used as a count
+pub(crate) fn range_count(range: &CommandRange) -> Option<usize> {
+    match range.end.as_ref().or(range.start.as_ref()) {
+        Some(LineSpec::Absolute(LineNr(count))) => Some(*count),
+        _ => None,
+    }
+}
+
+/// The lines of the file `name`, if it can be read as text
+pub(crate) fn read_lines(name: &str) -> Option<Vec<String>> {
+    std::fs::read_to_string(name)
+        .ok()
+        .map(|text| text.lines().map(str::to_string).collect())
+}
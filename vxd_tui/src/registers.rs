@@ -1,8 +1,12 @@
 //! Register implementation.
 //!
 //! This module provides a concrete implementation of Vim's register system.
+//! The `+` and `*` registers go through a clipboard provider when one is
+//! set, see [`crate::clipboard`].
 
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use vxd::clipboard::{ClipboardProvider, RegisterTypeChannel};
 use vxd::registers::{Register, RegisterBank, RegisterContent};
 use vxd::types::{VimError, VimResult};
 
@@ -27,6 +31,12 @@ pub struct TuiRegisterBank {
     clipboard: Option<RegisterContent>,
     /// Last inserted text (.)
     last_inserted: Option<RegisterContent>,
+    /// Last expression (=)
+    last_expression: Option<String>,
+    /// Clipboard provider backing "+ and "*
+    provider: Option<Arc<Mutex<Box<dyn ClipboardProvider>>>>,
+    /// Register types of the text copied to the clipboard
+    channel: RegisterTypeChannel,
 }
 
 impl TuiRegisterBank {
@@ -39,6 +49,49 @@ impl TuiRegisterBank {
     pub fn push_delete(&mut self, content: RegisterContent) {
         let _ = self.record_delete(content, false);
     }
+
+    /// Use `provider` for the "+ and "* registers
+    pub fn set_clipboard_provider(&mut self, provider: Box<dyn ClipboardProvider>) {
+        self.provider = Some(Arc::new(Mutex::new(provider)));
+    }
+
+    /// Name of the clipboard provider, if there is one
+    pub fn clipboard_provider_name(&self) -> Option<String> {
+        let provider = self.provider.as_ref()?.lock().ok()?;
+        Some(provider.name().to_string())
+    }
+
+    /// The content of `reg`, reading the "+ and "* registers from the
+    /// clipboard provider first.  When the clipboard cannot be read the
+    /// last copied content is used.
+    pub fn fetch(&mut self, reg: Register) -> Option<RegisterContent> {
+        if reg.is_clipboard() {
+            let pasted = self
+                .provider
+                .as_ref()
+                .and_then(|provider| provider.lock().ok()?.paste(reg).ok().flatten());
+            if let Some(text) = pasted {
+                let content = self.channel.paste(reg, &text);
+                match reg {
+                    Register::Selection => self.selection = Some(content),
+                    _ => self.clipboard = Some(content),
+                }
+            }
+        }
+        self.get(reg).cloned()
+    }
+
+    /// Copy `content` to the clipboard provider for `reg`
+    fn copy_to_provider(&mut self, reg: Register, content: &RegisterContent) -> VimResult<()> {
+        let Some(provider) = self.provider.clone() else {
+            return Ok(());
+        };
+        let text = self.channel.copy(reg, content);
+        let mut provider = provider
+            .lock()
+            .map_err(|_| VimError::CommandFailed("clipboard provider failed".to_string()))?;
+        provider.copy(reg, &text)
+    }
 }
 
 impl RegisterBank for TuiRegisterBank {
@@ -85,11 +138,15 @@ impl RegisterBank for TuiRegisterBank {
             Register::SmallDelete => {
                 self.small_delete = Some(content);
             }
+            // Stored first, so the register keeps the text when the
+            // provider fails
             Register::Selection => {
-                self.selection = Some(content);
+                self.selection = Some(content.clone());
+                return self.copy_to_provider(reg, &content);
             }
             Register::Clipboard => {
-                self.clipboard = Some(content);
+                self.clipboard = Some(content.clone());
+                return self.copy_to_provider(reg, &content);
             }
             Register::BlackHole => {
                 // Silently discard
@@ -174,6 +231,15 @@ impl TuiRegisterBank {
     pub fn set_last_inserted(&mut self, content: RegisterContent) {
         self.last_inserted = Some(content);
     }
+
+    /// The expression of the `=` register
+    pub fn last_expression(&self) -> Option<&str> {
+        self.last_expression.as_deref()
+    }
+
+    pub fn set_last_expression(&mut self, expr: &str) {
+        self.last_expression = Some(expr.to_string());
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,323 @@
+//! Repeating the last change with `.`.
+//!
+//! The keys of each Normal mode command are collected as it is typed or
+//! executed, with the register and count taken apart.  A command that
+//! changes the buffer becomes the last change; one that starts Insert
+//! mode takes in the keys typed until Insert mode ends.  `.` executes the
+//! keys again, with the count given to `.` replacing the original one and
+//! a numbered register advanced by one, so that `"1p...` puts the last
+//! deletes in turn.
+//!
+//! A cursor key or `Ctrl-O` in Insert mode ends the change; text typed
+//! after it is repeated as if inserted with `i`.  An abbreviation expanded
+//! while typing is repeated as its expansion.  Ex commands are not
+//! repeated.
+//!
+//! An operator used in Visual mode is kept with the size of the selection:
+//! `.` selects as many lines (and characters, or columns for a block) from
+//! the cursor and applies the operator again.
+
+use vxd::buffer::{Buffer, BufferManager};
+use vxd::cursor::{Cursor, CursorPosition};
+use vxd::modes::{Mode, ModeManager, VisualMode};
+use vxd::types::{LineNr, VimResult};
+
+use crate::dispatch::CommandKeyKind;
+use crate::editor::Editor;
+use crate::key::Key;
+
+/// A change as the keys that make it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Change {
+    /// Register given with `"`
+    register: Option<char>,
+    /// Count, with the counts typed before and after an operator
+    /// multiplied
+    count: Option<usize>,
+    /// Keys of the command without the register and count
+    keys: Vec<Key>,
+    /// The selection a Visual mode operator worked on
+    visual: Option<VisualArea>,
+}
+
+/// The size of a Visual selection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct VisualArea {
+    mode: VisualMode,
+    /// Number of lines
+    lines: usize,
+    /// Characterwise: the number of characters in a single line, or the
+    /// end column over several lines; blockwise: the number of columns
+    width: usize,
+}
+
+impl Change {
+    /// The keys to type for the change with `count` instead of its own
+    /// count and `register` instead of its own register
+    fn keys_with(&self, count: Option<usize>, register: Option<char>) -> Vec<Key> {
+        let mut keys = Vec::new();
+        if let Some(reg) = register {
+            keys.extend([Key::Char('"'), Key::Char(reg)]);
+        }
+        if let Some(count) = count {
+            keys.extend(count.to_string().chars().map(Key::Char));
+        }
+        keys.extend(self.keys.iter().copied());
+        keys
+    }
+
+    /// Whether `.` repeats the command: not an Ex command, and not a
+    /// command executing other keys
+    fn is_repeatable(&self) -> bool {
+        !matches!(
+            self.keys.first(),
+            None | Some(Key::Char(':' | '@' | 'Q' | '.'))
+        )
+    }
+}
+
+/// State of the change being typed and the last change
+#[derive(Debug, Default)]
+pub(crate) struct RedoState {
+    /// The command being typed
+    typing: Option<Change>,
+    /// `b:changedtick` when the command started
+    start_tick: u64,
+    /// Count digits typed but not yet added to the count
+    digits: Option<usize>,
+    /// Whether the command being typed started Insert mode
+    inserting: bool,
+    /// Set after a cursor key in Insert mode: the text typed next is a
+    /// new change
+    arrow_used: bool,
+    /// The last change, repeated by `.`
+    last: Option<Change>,
+}
+
+impl Editor {
+    /// `[count].`: repeat the last change
+    pub(crate) fn repeat_last_change(&mut self, count: Option<usize>) -> VimResult<()> {
+        let Some(change) = self.redo.last.clone() else {
+            return Ok(());
+        };
+        let register = change.register.map(|reg| match reg {
+            '1'..='8' => (reg as u8 + 1) as char,
+            reg => reg,
+        });
+        let count = match change.visual {
+            Some(area) => {
+                self.select_visual_area(area)?;
+                None
+            }
+            None => count.or(change.count),
+        };
+        let keys = change.keys_with(count, register);
+        self.feed_typeahead(&keys);
+        Ok(())
+    }
+
+    /// Start Visual mode with a selection of the size of `area` from the
+    /// cursor
+    fn select_visual_area(&mut self, area: VisualArea) -> VimResult<()> {
+        let start = self.cursor.position();
+        let last = self.buffers.current().line_count();
+        let line = LineNr((start.line.0 + area.lines - 1).min(last));
+        let col = match area.mode {
+            VisualMode::Char if area.lines == 1 => {
+                let text = self.current_line();
+                text.get(start.col..)
+                    .unwrap_or("")
+                    .char_indices()
+                    .nth(area.width - 1)
+                    .map_or(text.len().saturating_sub(1), |(i, _)| start.col + i)
+            }
+            VisualMode::Char => area.width,
+            VisualMode::Line => start.col,
+            VisualMode::Block => start.col + area.width - 1,
+        };
+        match area.mode {
+            VisualMode::Char => self.enter_visual()?,
+            VisualMode::Line => self.enter_visual_line()?,
+            VisualMode::Block => self.enter_visual_block()?,
+        }
+        let ctx = self.cursor_context();
+        self.cursor
+            .set_position(CursorPosition::new(line, col), &ctx)
+    }
+
+    /// The operator `key` is applied to the Visual selection from `anchor`
+    /// to the cursor: start the change, which keeps the size of the
+    /// selection
+    pub(crate) fn redo_visual_operator(
+        &mut self,
+        anchor: CursorPosition,
+        register: Option<char>,
+        key: Key,
+    ) {
+        let Mode::Visual(mode) = self.modes.mode() else {
+            return;
+        };
+        let cursor = self.cursor.position();
+        let (start, end) = if (anchor.line, anchor.col) <= (cursor.line, cursor.col) {
+            (anchor, cursor)
+        } else {
+            (cursor, anchor)
+        };
+        let width = match mode {
+            VisualMode::Char if start.line == end.line => {
+                let text = self.current_line();
+                let chars = text.get(start.col..).unwrap_or("").char_indices();
+                chars
+                    .take_while(|(i, _)| start.col + i <= end.col)
+                    .count()
+                    .max(1)
+            }
+            VisualMode::Char => end.col,
+            VisualMode::Line => 0,
+            VisualMode::Block => anchor.col.abs_diff(cursor.col) + 1,
+        };
+        self.redo.typing = Some(Change {
+            register,
+            count: None,
+            keys: vec![key],
+            visual: Some(VisualArea {
+                mode,
+                lines: end.line.0 - start.line.0 + 1,
+                width,
+            }),
+        });
+        self.redo.start_tick = self.buffers.current().changedtick();
+        self.redo.inserting = false;
+    }
+
+    /// Take note of `key` before it is handled
+    pub(crate) fn redo_before_key(&mut self, key: Key) {
+        let mode = self.modes.mode();
+        match mode {
+            Mode::Insert | Mode::Replace => return self.redo_insert_key(key),
+            Mode::CommandLine(_) => {
+                if let Some(typing) = &mut self.redo.typing {
+                    typing.keys.push(key);
+                }
+                return;
+            }
+            Mode::Normal | Mode::OperatorPending => {}
+            _ => return,
+        }
+        if mode == Mode::Normal && !self.has_pending_command() {
+            self.redo.typing = Some(Change::default());
+            self.redo.start_tick = self.buffers.current().changedtick();
+            self.redo.digits = None;
+            self.redo.inserting = false;
+        }
+        let kind = self.command_key_kind(key);
+        let redo = &mut self.redo;
+        let Some(typing) = &mut redo.typing else {
+            return;
+        };
+        match kind {
+            CommandKeyKind::Digit(digit) => {
+                redo.digits = Some(redo.digits.unwrap_or(0) * 10 + digit);
+            }
+            CommandKeyKind::RegisterPrefix => {}
+            CommandKeyKind::RegisterName(reg) => typing.register = Some(reg),
+            CommandKeyKind::Other => {
+                if let Some(count) = redo.digits.take() {
+                    typing.count = Some(typing.count.unwrap_or(1) * count);
+                }
+                typing.keys.push(key);
+            }
+        }
+    }
+
+    /// An Insert mode key: add it to the change, or end the change on a
+    /// cursor key or `Ctrl-O`
+    fn redo_insert_key(&mut self, key: Key) {
+        let cursor_key = match key {
+            Key::Left | Key::Right | Key::Home | Key::End => true,
+            Key::Up | Key::Down | Key::PageUp | Key::PageDown => !self.pum_visible(),
+            _ => false,
+        };
+        if cursor_key || key == Key::Ctrl('o') {
+            self.finish_insert_change();
+            self.redo.arrow_used = cursor_key;
+            return;
+        }
+        let redo = &mut self.redo;
+        if redo.typing.is_none() && redo.arrow_used && !matches!(key, Key::Escape | Key::Ctrl('c'))
+        {
+            redo.typing = Some(Change {
+                keys: vec![Key::Char('i')],
+                ..Change::default()
+            });
+            redo.inserting = true;
+            redo.arrow_used = false;
+        }
+        if let Some(typing) = &mut redo.typing {
+            typing.keys.push(key);
+        }
+    }
+
+    /// Take note of the mode after a key was handled: a command that
+    /// finished and changed the buffer becomes the last change.
+    /// `restarted` is set when Insert mode was started again after a
+    /// command typed with `Ctrl-O`.
+    pub(crate) fn redo_after_key(&mut self, ok: bool, restarted: bool) {
+        if restarted {
+            self.finish_command_change();
+            self.redo.arrow_used = true;
+            return;
+        }
+        if self.redo.typing.is_none() {
+            return;
+        }
+        if !ok && !self.redo.inserting {
+            self.redo.typing = None;
+            return;
+        }
+        match self.modes.mode() {
+            Mode::Insert | Mode::Replace => self.redo.inserting = true,
+            Mode::Normal if !self.has_pending_command() => self.finish_command_change(),
+            Mode::Normal | Mode::OperatorPending | Mode::CommandLine(_) => {}
+            _ => self.redo.typing = None,
+        }
+    }
+
+    /// The command typed is complete: keep it when it changed the buffer
+    /// or inserted text
+    fn finish_command_change(&mut self) {
+        let Some(change) = self.redo.typing.take() else {
+            return;
+        };
+        let changed = self.buffers.current().changedtick() != self.redo.start_tick;
+        if (changed || self.redo.inserting) && change.is_repeatable() {
+            self.redo.last = Some(change);
+        }
+        self.redo.inserting = false;
+    }
+
+    /// Insert mode ends the change being typed, as if `<Esc>` was typed
+    fn finish_insert_change(&mut self) {
+        if let Some(typing) = &mut self.redo.typing {
+            typing.keys.push(Key::Escape);
+            self.redo.inserting = true;
+        }
+        self.finish_command_change();
+    }
+
+    /// An abbreviation `word` was expanded to the keys `rhs` when typing
+    /// the last key: repeat the expansion rather than the abbreviation
+    pub(crate) fn redo_abbreviation(&mut self, word: &str, rhs: &[Key]) {
+        let Some(typing) = &mut self.redo.typing else {
+            return;
+        };
+        let Some(trigger) = typing.keys.pop() else {
+            return;
+        };
+        typing
+            .keys
+            .extend(std::iter::repeat_n(Key::Backspace, word.chars().count()));
+        typing.keys.extend(rhs.iter().copied());
+        typing.keys.push(trigger);
+    }
+}
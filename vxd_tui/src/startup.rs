@@ -0,0 +1,167 @@
+//! Starting the editor with its command line arguments.
+//!
+//! [`vxd::startup`] describes the order things are done in.  There is one
+//! window and no swap files are written, so `-o`, `-O`, `-p`, `-r` and
+//! `-L` are refused, see [`check_args`].  An error does not stop the
+//! editor from starting; the error messages are shown when it has
+//! started.
+
+use std::path::Path;
+
+use vxd::autocmd::AutocmdEvent;
+use vxd::buffer::{Buffer, BufferManager};
+use vxd::commands::CommandExecutor;
+use vxd::fileedit::FileEditor;
+use vxd::startup::{config_paths, StartupArgs, Vimrc, WindowLayout};
+use vxd::types::{Direction, VimError, VimResult};
+
+use crate::editor::Editor;
+use crate::exmode::ExMode;
+
+/// Fail for the arguments of Vim the editor cannot honour: `-o`, `-O` and
+/// `-p` open more windows or tab pages, `-r` and `-L` recover from swap
+/// files
+pub fn check_args(args: &StartupArgs) -> VimResult<()> {
+    let layout = args.layout.map(|(layout, _)| match layout {
+        WindowLayout::Horizontal => "-o",
+        WindowLayout::Vertical => "-O",
+        WindowLayout::Tabs => "-p",
+    });
+    match layout.or(args.recover.then_some("-r")) {
+        Some(arg) => Err(VimError::Argument(format!(
+            "Unsupported argument: \"{}\"",
+            arg
+        ))),
+        None => Ok(()),
+    }
+}
+
+impl Editor {
+    /// Start with the command line arguments `args`; `stdin` is the text
+    /// read from stdin for `-`
+    pub fn startup(&mut self, args: &StartupArgs, stdin: Option<&str>) {
+        if args.ex_mode {
+            self.exmode = Some(ExMode {
+                silent: args.silent,
+                error: false,
+            });
+        }
+        if let Some(compatible) = args.compatible {
+            let option = if compatible {
+                "compatible"
+            } else {
+                "nocompatible"
+            };
+            self.startup_command(&format!("set {}", option));
+        }
+        for command in &args.pre_commands {
+            self.startup_command(command);
+        }
+        self.load_config(&args.vimrc);
+        if let Err(err) = self.configure_clipboard() {
+            self.add_error(&err.to_string());
+        }
+
+        match stdin {
+            Some(text) => self.read_stdin(text),
+            None => {
+                for file in &args.files {
+                    if !self.arglist.contains(file) {
+                        self.arglist.push(file.clone());
+                    }
+                }
+                if let Some(first) = args.files.first() {
+                    if let Err(err) = self.edit(first) {
+                        self.add_error(&err.to_string());
+                    }
+                }
+            }
+        }
+        let flags = [
+            (args.readonly, "readonly"),
+            (args.no_swap, "noswapfile"),
+            (args.diff, "diff"),
+        ];
+        for (_, option) in flags.iter().filter(|(set, _)| *set) {
+            self.startup_command(&format!("set {}", option));
+        }
+        for command in &args.commands {
+            // Ex ranges have no patterns, so `+/pat` is a search
+            match command.strip_prefix('/') {
+                Some(pattern) => {
+                    if let Err(err) = self.search_pattern(pattern, Direction::Forward) {
+                        self.add_error(&err.to_string());
+                    }
+                }
+                None => self.startup_command(command),
+            }
+        }
+        let data = self.autocmd_data();
+        if let Err(err) = self.apply_autocmds(AutocmdEvent::VimEnter, &data) {
+            self.add_error(&err.to_string());
+        }
+    }
+
+    /// Execute `command`, adding its message or error to the ones shown
+    fn startup_command(&mut self, command: &str) {
+        let result = self.run(command);
+        match (result.error, result.message) {
+            (Some(err), _) => self.add_error(&err.to_string()),
+            (None, Some(message)) if self.shows_message(command) => {
+                self.add_message(&message, true)
+            }
+            _ => {}
+        }
+    }
+
+    /// Source the user config `vimrc`, setting `$MYVIMRC` to the file
+    fn load_config(&mut self, vimrc: &Vimrc) {
+        let file = match vimrc {
+            Vimrc::None | Vimrc::NoRc => return,
+            Vimrc::File(file) => {
+                if !Path::new(file).is_file() {
+                    let err = VimError::Error(282, format!("Cannot read from \"{}\"", file));
+                    self.add_error(&err.to_string());
+                    return;
+                }
+                file.clone()
+            }
+            // Silent Ex mode only sources the config given with `-u`
+            Vimrc::Default if self.exmode.as_ref().is_some_and(|exmode| exmode.silent) => return,
+            Vimrc::Default => {
+                if let Some(init) = self.env_var("VIMINIT") {
+                    self.startup_command(&init);
+                    return;
+                }
+                let found = config_paths(|name| self.env_var(name))
+                    .into_iter()
+                    .find(|path| path.is_file());
+                match found {
+                    Some(path) => path.to_string_lossy().into_owned(),
+                    None => return,
+                }
+            }
+        };
+        if self.env_var("MYVIMRC").is_none() {
+            self.env.insert("MYVIMRC".to_string(), Some(file.clone()));
+        }
+        if let Err(err) = self.ex_source(&file) {
+            self.add_error(&err.to_string());
+        }
+    }
+
+    /// Edit `text` read from stdin, in the buffer without a name, which is
+    /// modified as the text is not in a file
+    fn read_stdin(&mut self, text: &str) {
+        let lines: Vec<String> = text.lines().map(str::to_string).collect();
+        let buffer = self.buffers.current_mut();
+        let result = buffer
+            .set_lines(0, -1, false, lines)
+            .and_then(|()| buffer.set_modified(true));
+        buffer.take_changes();
+        if let Err(err) = result {
+            self.add_error(&err.to_string());
+        }
+        self.sync_cursor_with_buffer();
+    }
+}
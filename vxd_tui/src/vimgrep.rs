@@ -0,0 +1,218 @@
+//! `:vimgrep` and `:lvimgrep`: searching files for a pattern.
+//!
+//! The file arguments may use `*`, `?`, `[abc]` and `{a,b}` in each path
+//! component and `**` for any number of directories; files matched by
+//! 'wildignore' are left out.  Loaded buffers are searched as they are,
+//! other files are read from disk.  Each matching line (with the `g` flag
+//! each match) becomes an entry of a new quickfix list, or of the location
+//! list for `:lvimgrep`.
+
+use std::path::Path;
+
+use vxd::autocmd::AutocmdEvent;
+use vxd::buffer::{Buffer, BufferLoadState, BufferManager};
+use vxd::cursor::CursorPosition;
+use vxd::quickfix::{QuickfixEntry, QuickfixList, VimgrepSpec};
+use vxd::search::{SearchEngine, SearchPattern, SimpleSearchEngine};
+use vxd::types::{LineNr, VimError, VimResult};
+
+use crate::autocmd::pattern_matches;
+use crate::editor::{no_previous_pattern, Editor};
+use crate::quickfix::read_lines;
+use crate::wildmenu::expand_home;
+
+impl Editor {
+    /// `:vimgrep` (`:lvimgrep` when `location` is set) with the arguments
+    /// `args`.  Jumps to the first match unless the `j` flag is given.
+    pub(crate) fn ex_vimgrep(&mut self, name: &str, args: &str, location: bool) -> VimResult<()> {
+        let spec = VimgrepSpec::parse(args)?;
+        let pattern = if spec.pattern.is_empty() {
+            self.search
+                .last_pattern()
+                .cloned()
+                .ok_or_else(no_previous_pattern)?
+        } else {
+            SearchPattern::forward(spec.pattern.clone())
+        };
+        let files = self.expand_files(&spec.files)?;
+        let options = self.search_options();
+        let event_name = if location { "lvimgrep" } else { "vimgrep" };
+        self.quickfix_autocmd(AutocmdEvent::QuickFixCmdPre, event_name)?;
+
+        let mut entries = Vec::new();
+        for file in files {
+            let Some(lines) = self.file_lines(&file) else {
+                continue;
+            };
+            if lines.is_empty() {
+                continue;
+            }
+            let last = CursorPosition::new(LineNr(lines.len()), usize::MAX);
+            let engine = SimpleSearchEngine::new(lines.clone());
+            let matches =
+                engine.find_all(&pattern, CursorPosition::new(LineNr(1), 0), last, &options)?;
+            let mut prev_line = None;
+            for m in matches {
+                if !spec.global && prev_line == Some(m.start.line) {
+                    continue;
+                }
+                prev_line = Some(m.start.line);
+                entries.push(QuickfixEntry {
+                    filename: file.clone(),
+                    lnum: m.start.line,
+                    col: m.start.col + 1,
+                    text: lines[m.start.line.to_zero_indexed()].clone(),
+                    valid: true,
+                    ..Default::default()
+                });
+            }
+        }
+
+        let found = !entries.is_empty();
+        let list = QuickfixList::new(format!(":{} {}", name, args.trim()), entries);
+        self.push_quickfix_list(list, location);
+        self.quickfix_autocmd(AutocmdEvent::QuickFixCmdPost, event_name)?;
+        if !found {
+            return Err(VimError::Error(
+                480,
+                format!("No match: {}", pattern.pattern),
+            ));
+        }
+        if !spec.no_jump {
+            self.quickfix_jump(location)?;
+        }
+        Ok(())
+    }
+
+    /// Lines of the loaded buffer for `name`, or else of the file
+    fn file_lines(&self, name: &str) -> Option<Vec<String>> {
+        let loaded = self
+            .buffers
+            .get_by_name(name)
+            .and_then(|handle| self.buffers.get(handle))
+            .filter(|buffer| buffer.load_state() == BufferLoadState::Loaded);
+        match loaded {
+            Some(buffer) => buffer.get_lines(0, -1, false).ok(),
+            None => read_lines(name),
+        }
+    }
+
+    /// Expand the file arguments of `:vimgrep`: `%` is the current file
+    /// and `#` the alternate file, wildcards are expanded and files matched
+    /// by 'wildignore' are left out.  Names without wildcards are kept as
+    /// they are.
+    fn expand_files(&self, args: &[String]) -> VimResult<Vec<String>> {
+        let mut files: Vec<String> = Vec::new();
+        for arg in args {
+            let expanded = match arg.as_str() {
+                "%" | "#" => vec![self.special_file_name(arg)?],
+                _ if !arg.contains(['*', '?', '[', '{']) => vec![arg.clone()],
+                _ => {
+                    let arg = match arg.strip_prefix("~/") {
+                        Some(_) => expand_home(arg).to_string_lossy().into_owned(),
+                        None => arg.clone(),
+                    };
+                    let (prefix, rest) = match arg.strip_prefix('/') {
+                        Some(rest) => ("/", rest),
+                        None => ("", arg.as_str()),
+                    };
+                    let components: Vec<&str> =
+                        rest.split('/').filter(|part| !part.is_empty()).collect();
+                    let mut names = Vec::new();
+                    expand_glob(prefix, &components, &mut names);
+                    names.sort();
+                    names
+                }
+            };
+            for name in expanded {
+                if !name.is_empty() && !self.is_wildignored(&name) && !files.contains(&name) {
+                    files.push(name);
+                }
+            }
+        }
+        Ok(files)
+    }
+
+    /// The file name `%` (the current file) or `#` (the alternate file)
+    /// stands for.  No alternate file is kept, so `#` always fails.
+    fn special_file_name(&self, arg: &str) -> VimResult<String> {
+        if arg == "#" {
+            return Err(VimError::Error(
+                194,
+                "No alternate file name to substitute for '#'".to_string(),
+            ));
+        }
+        let name = self.buffers.current().name();
+        if name.is_empty() {
+            return Err(VimError::Error(
+                499,
+                "Empty file name for '%' or '#', only works with \":p:h\"".to_string(),
+            ));
+        }
+        Ok(name.to_string())
+    }
+}
+
+/// Add the files matching the path `components` below the directory
+/// `dir` (empty for the current directory, otherwise ending in `/`)
+fn expand_glob(dir: &str, components: &[&str], names: &mut Vec<String>) {
+    let Some((&first, rest)) = components.split_first() else {
+        if Path::new(dir).is_file() {
+            names.push(dir.to_string());
+        }
+        return;
+    };
+    let path = match dir {
+        "" => Path::new("."),
+        dir => Path::new(dir),
+    };
+    if first == "**" {
+        // Any number of directories, including none; a trailing "**"
+        // stands for all files below.  Links to directories are not
+        // followed, as they may lead back up.
+        if rest.is_empty() {
+            expand_glob(dir, &["*"], names)
+        } else {
+            expand_glob(dir, rest, names)
+        }
+        for sub in read_dir_names(path, "*").into_iter().filter(|sub| {
+            std::fs::symlink_metadata(format!("{}{}", dir, sub)).is_ok_and(|meta| meta.is_dir())
+        }) {
+            expand_glob(&format!("{}{}/", dir, sub), components, names);
+        }
+        return;
+    }
+    if !first.contains(['*', '?', '[', '{']) {
+        let name = format!("{}{}", dir, first);
+        if rest.is_empty() {
+            expand_glob(&name, rest, names)
+        } else {
+            expand_glob(&format!("{}/", name), rest, names)
+        }
+        return;
+    }
+    for entry in read_dir_names(path, first) {
+        let name = format!("{}{}", dir, entry);
+        if rest.is_empty() {
+            expand_glob(&name, rest, names);
+        } else if Path::new(&name).is_dir() {
+            expand_glob(&format!("{}/", name), rest, names);
+        }
+    }
+}
+
+/// Names in the directory `path` matching `pattern`.  Hidden files only
+/// match a pattern starting with a dot.
+fn read_dir_names(path: &Path, pattern: &str) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .flatten()
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|name| !name.starts_with('.') || pattern.starts_with('.'))
+        .filter(|name| pattern_matches(pattern, name))
+        .collect();
+    names.sort();
+    names
+}
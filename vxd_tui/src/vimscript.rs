@@ -0,0 +1,171 @@
+//! Vimscript in the editor.
+//!
+//! The editor is the [`ScriptHost`] of its [`Interpreter`]: the commands
+//! of the language typed on the command line or found in a script run by
+//! `:source` are executed by the interpreter, which gives every other
+//! command back to the editor.  `:echo` and error messages of a script
+//! are added to the message line, one line each.
+
+use std::process::Command;
+
+use vxd::buffer::{Buffer, BufferManager};
+use vxd::commands::{CommandExecutor, CommandRange, ExCommand};
+use vxd::cursor::{Cursor, CursorPosition};
+use vxd::eval::Value;
+use vxd::options::{OptionManager, OptionValue};
+use vxd::registers::{Register, RegisterBank, RegisterContent, RegisterType};
+use vxd::types::{LineNr, VimError, VimResult};
+use vxd::vimscript::ScriptHost;
+
+use crate::editor::Editor;
+
+impl Editor {
+    /// Execute command `cmd` of the interpreter, with the rest of the
+    /// command line after it
+    pub(crate) fn ex_script(
+        &mut self,
+        cmd: &ExCommand,
+        range: Option<(LineNr, LineNr)>,
+    ) -> VimResult<()> {
+        let mut text = match range {
+            Some((first, last)) => format!("{},{}", first.0, last.0),
+            None => String::new(),
+        };
+        text.push_str(&cmd.name);
+        if cmd.bang {
+            text.push('!');
+        }
+        text.push(' ');
+        text.push_str(&cmd.args);
+        self.vimscript.clone().execute(&text, self)
+    }
+
+    /// `:source {file}`: execute the commands in `file`
+    pub(crate) fn ex_source(&mut self, args: &str) -> VimResult<()> {
+        if args.is_empty() {
+            return Err(VimError::ArgumentRequired);
+        }
+        let contents =
+            std::fs::read_to_string(args).map_err(|_| VimError::FileNotFound(args.to_string()))?;
+        let lines: Vec<String> = contents.lines().map(str::to_string).collect();
+        self.vimscript.clone().source(args, &lines, self)
+    }
+
+    /// The value of environment variable `name`, as set in the editor
+    pub fn env_var(&self, name: &str) -> Option<String> {
+        match self.env.get(name) {
+            Some(value) => value.clone(),
+            None => std::env::var(name).ok(),
+        }
+    }
+
+    /// Start `command` with the environment as set in the editor
+    pub(crate) fn command_env<'a>(&self, command: &'a mut Command) -> &'a mut Command {
+        for (name, value) in &self.env {
+            match value {
+                Some(value) => command.env(name, value),
+                None => command.env_remove(name),
+            };
+        }
+        command
+    }
+
+    /// Add `text` to the message shown, on a new line with `newline`
+    pub(crate) fn add_message(&mut self, text: &str, newline: bool) {
+        match &mut self.message {
+            Some(message) if newline => {
+                message.push('\n');
+                message.push_str(text);
+            }
+            Some(message) => message.push_str(text),
+            None => self.message = Some(text.to_string()),
+        }
+    }
+}
+
+impl ScriptHost for Editor {
+    fn execute_command(&mut self, command: &str) -> VimResult<()> {
+        let result = self.run(command);
+        if let Some(err) = result.error {
+            return Err(err);
+        }
+        if let Some(message) = result.message {
+            if self.shows_message(command) {
+                self.add_message(&message, true);
+            }
+        }
+        Ok(())
+    }
+
+    fn echo(&mut self, text: &str, newline: bool) {
+        if self.exmode.as_ref().is_some_and(|exmode| exmode.silent) {
+            return;
+        }
+        self.add_message(text, newline);
+    }
+
+    fn error_message(&mut self, text: &str) {
+        self.add_error(text);
+    }
+
+    fn scope_id(&self, scope: char) -> usize {
+        match scope {
+            'b' => self.buffers.current().handle().0,
+            // There is one window and one tab page
+            _ => 1,
+        }
+    }
+
+    fn command_range(&self, range: &CommandRange) -> VimResult<Option<(LineNr, LineNr)>> {
+        self.resolve_range(range)
+    }
+
+    fn set_cursor_line(&mut self, line: LineNr) -> VimResult<()> {
+        let ctx = self.cursor_context();
+        self.cursor.set_position(CursorPosition::new(line, 0), &ctx)
+    }
+
+    fn let_option(&mut self, name: &str, _scope: Option<char>, value: &Value) -> VimResult<()> {
+        let value = match self.options.get(name) {
+            Some(OptionValue::Boolean(_)) => OptionValue::Boolean(value.as_number()? != 0),
+            Some(OptionValue::Number(_)) => OptionValue::Number(value.as_number()?),
+            Some(OptionValue::String(_)) => OptionValue::String(value.as_string()?),
+            None => return Err(VimError::Error(355, format!("Unknown option: {}", name))),
+        };
+        self.options.set(name, value)?;
+        self.apply_options();
+        Ok(())
+    }
+
+    fn let_register(&mut self, reg: char, text: &str) -> VimResult<()> {
+        if reg == '/' {
+            self.registers.set_last_search(text);
+            return Ok(());
+        }
+        let register = Register::from_char(reg)?;
+        // An uppercase register is appended to
+        let text = if reg.is_ascii_uppercase() {
+            let old = self
+                .registers
+                .fetch(register)
+                .map(|content| content.as_string());
+            format!("{}{}", old.unwrap_or_default(), text)
+        } else {
+            text.to_string()
+        };
+        let content = match text.strip_suffix('\n') {
+            Some(lines) => {
+                RegisterContent::linewise(lines.split('\n').map(str::to_string).collect())
+            }
+            None => RegisterContent {
+                text: text.split('\n').map(str::to_string).collect(),
+                reg_type: RegisterType::Characterwise,
+            },
+        };
+        self.registers.set(register, content)
+    }
+
+    fn let_env(&mut self, name: &str, value: Option<&str>) {
+        self.env.insert(name.to_string(), value.map(str::to_string));
+    }
+}
@@ -0,0 +1,798 @@
+//! Command-line completion and the wildmenu.
+//!
+//! Typing 'wildchar' (`<Tab>`) on the `:` command line completes the
+//! command name or the argument before the cursor.  What an argument
+//! completes to is decided by the command's [`CompleteType`];
+//! [`Editor::getcompletion`] exposes the same expansion, like Vim's
+//! `getcompletion()`.
+//!
+//! 'wildmode' says what each press does: cycle through the matches,
+//! complete their longest common prefix or list them.  The matches are
+//! shown in a popup menu above the command line when 'wildoptions'
+//! contains "pum", or in a bar over the status line otherwise
+//! (`:help cmdline-completion`).
+
+use std::path::PathBuf;
+
+use vxd::autocmd::AutocmdEvent;
+use vxd::buffer::{Buffer, BufferManager};
+use vxd::cmdline::{longest_common_prefix, Cmdline, WildMode};
+use vxd::commands::{parse_command_range, CommandExecutor, CompleteType};
+use vxd::completion::CompletionItem;
+use vxd::mappings::MappingManager;
+use vxd::modes::{CommandLineMode, Mode, VisualMode};
+use vxd::options::{OptionManager, OptionValue};
+use vxd::types::VimResult;
+
+use crate::autocmd::pattern_matches;
+use crate::editor::Editor;
+use crate::ex::lookup;
+use crate::key::Key;
+use crate::popupmenu::{PopupMenu, PumAnchor, PumLimits};
+
+/// Highlight groups every color scheme defines (`:help highlight-groups`)
+const HIGHLIGHT_GROUPS: &[&str] = &[
+    "ColorColumn",
+    "Comment",
+    "Conceal",
+    "Constant",
+    "CurSearch",
+    "Cursor",
+    "CursorColumn",
+    "CursorLine",
+    "CursorLineNr",
+    "DiffAdd",
+    "DiffChange",
+    "DiffDelete",
+    "DiffText",
+    "Directory",
+    "EndOfBuffer",
+    "Error",
+    "ErrorMsg",
+    "FoldColumn",
+    "Folded",
+    "Function",
+    "Identifier",
+    "IncSearch",
+    "LineNr",
+    "MatchParen",
+    "ModeMsg",
+    "MoreMsg",
+    "NonText",
+    "Normal",
+    "Pmenu",
+    "PmenuSbar",
+    "PmenuSel",
+    "PmenuThumb",
+    "PreProc",
+    "Question",
+    "Search",
+    "SignColumn",
+    "Special",
+    "SpecialKey",
+    "Statement",
+    "StatusLine",
+    "StatusLineNC",
+    "String",
+    "Title",
+    "Todo",
+    "Type",
+    "Underlined",
+    "VertSplit",
+    "Visual",
+    "WarningMsg",
+    "WildMenu",
+];
+
+/// Values offered for options that only take a few
+const OPTION_VALUES: &[(&str, &[&str])] = &[
+    (
+        "completeopt",
+        &[
+            "menu", "menuone", "longest", "preview", "popup", "noinsert", "noselect",
+        ],
+    ),
+    ("fileformat", &["dos", "mac", "unix"]),
+    (
+        "foldmethod",
+        &["diff", "expr", "indent", "manual", "marker", "syntax"],
+    ),
+    ("signcolumn", &["auto", "no", "number", "yes"]),
+    (
+        "virtualedit",
+        &["all", "block", "insert", "none", "onemore"],
+    ),
+    (
+        "wildmode",
+        &["full", "lastused", "list", "longest", "noselect"],
+    ),
+    ("wildoptions", &["fuzzy", "pum", "tagfile"]),
+];
+
+/// What the text before the cursor completes to
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum WildContext {
+    /// A command name
+    Command,
+    /// An argument of the given type
+    Arg(CompleteType),
+    /// The value of an option, after `=` in `:set`
+    OptionValue(String),
+    /// The first argument of `:autocmd` and `:doautocmd`: a group or an
+    /// event
+    GroupOrEvent,
+}
+
+/// Command-line completion in progress
+#[derive(Debug, Clone, Default)]
+pub(crate) struct WildSession {
+    /// Byte offset in the command line where the completed text starts
+    start: usize,
+    /// The typed text being completed
+    original: String,
+    /// Text after the cursor, kept while matches are inserted
+    tail: String,
+    /// The matches, escaped for the command line
+    matches: Vec<String>,
+    /// Index of the inserted match, `None` while the original is shown
+    selected: Option<usize>,
+    /// Number of times 'wildchar' was pressed
+    presses: usize,
+    /// Whether the matches are shown
+    shown: bool,
+    /// Matches are file names; the menu only shows their last part
+    files: bool,
+    /// First match shown in the popup menu
+    pum_first: usize,
+}
+
+/// The matches shown while completing on the command line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WildMenu {
+    /// The matches as shown
+    pub items: Vec<String>,
+    /// Index of the selected match
+    pub selected: Option<usize>,
+    /// Byte offset in the command line where the completed text starts
+    pub start: usize,
+    /// Show a popup menu instead of a bar ('wildoptions' contains "pum")
+    pub pum: bool,
+}
+
+impl Editor {
+    /// Matches for `pat` as a `kind` argument, like `getcompletion()`
+    pub fn getcompletion(&self, pat: &str, kind: CompleteType) -> Vec<String> {
+        match kind {
+            CompleteType::Nothing => Vec::new(),
+            CompleteType::File => self.complete_files(pat, false),
+            CompleteType::Dir => self.complete_files(pat, true),
+            CompleteType::Buffer => self.complete_buffers(pat),
+            CompleteType::Option => self.complete_option_names(pat),
+            CompleteType::Command => self.complete_command(pat),
+            CompleteType::Event => {
+                let mut events: Vec<String> = AutocmdEvent::ALL
+                    .iter()
+                    .map(|event| event.name().to_string())
+                    .filter(|name| starts_with_ignore_case(name, pat))
+                    .collect();
+                events.sort();
+                events
+            }
+            CompleteType::Augroup => {
+                let mut groups: Vec<String> = self
+                    .autocmds
+                    .group_names()
+                    .into_iter()
+                    .chain(["END"])
+                    .filter(|name| name.starts_with(pat))
+                    .map(str::to_string)
+                    .collect();
+                groups.sort();
+                groups.dedup();
+                groups
+            }
+            CompleteType::Highlight => HIGHLIGHT_GROUPS
+                .iter()
+                .filter(|name| starts_with_ignore_case(name, pat))
+                .map(|name| name.to_string())
+                .collect(),
+            CompleteType::Mapping => {
+                let modes = [
+                    Mode::Normal,
+                    Mode::Visual(VisualMode::Char),
+                    Mode::OperatorPending,
+                    Mode::Insert,
+                    Mode::CommandLine(CommandLineMode::Normal),
+                ];
+                let mut lhs: Vec<String> = modes
+                    .into_iter()
+                    .flat_map(|mode| self.mappings.list(mode))
+                    .map(|mapping| mapping.lhs.clone())
+                    .filter(|lhs| lhs.starts_with(pat))
+                    .collect();
+                lhs.sort();
+                lhs.dedup();
+                lhs
+            }
+            CompleteType::Help => self.complete_help_tags(pat),
+            CompleteType::Function => {
+                let mut names: Vec<String> = self
+                    .complete_funcs
+                    .keys()
+                    .filter(|name| name.starts_with(pat))
+                    .map(|name| format!("{}(", name))
+                    .collect();
+                names.sort();
+                names
+            }
+        }
+    }
+
+    /// File names starting with `pat`; directories get a trailing `/`.
+    /// Hidden files only match when `pat` starts their name with a dot.
+    fn complete_files(&self, pat: &str, dirs_only: bool) -> Vec<String> {
+        let ignore_case =
+            self.options.flag("wildignorecase") || self.options.flag("fileignorecase");
+        let (dir, prefix) = match pat.rfind('/') {
+            Some(idx) => (&pat[..=idx], &pat[idx + 1..]),
+            None => ("", pat),
+        };
+        let path = match dir {
+            "" => PathBuf::from("."),
+            _ => expand_home(dir),
+        };
+        let Ok(entries) = std::fs::read_dir(&path) else {
+            return Vec::new();
+        };
+
+        let mut names = Vec::new();
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.starts_with('.') && !prefix.starts_with('.') {
+                continue;
+            }
+            let matches = if ignore_case {
+                starts_with_ignore_case(&name, prefix)
+            } else {
+                name.starts_with(prefix)
+            };
+            let is_dir = entry.path().is_dir();
+            if !matches || (dirs_only && !is_dir) {
+                continue;
+            }
+            let full = format!("{}{}", dir, name);
+            if self.is_wildignored(&full) {
+                continue;
+            }
+            names.push(if is_dir { full + "/" } else { full });
+        }
+        names.sort();
+        names
+    }
+
+    /// Whether 'wildignore' hides the file `name`
+    pub(crate) fn is_wildignored(&self, name: &str) -> bool {
+        let fold_case = self.options.flag("fileignorecase");
+        self.options
+            .string("wildignore")
+            .split(',')
+            .filter(|pattern| !pattern.is_empty())
+            .any(|pattern| {
+                if fold_case {
+                    pattern_matches(&pattern.to_lowercase(), &name.to_lowercase())
+                } else {
+                    pattern_matches(pattern, name)
+                }
+            })
+    }
+
+    /// Names of listed buffers containing `pat`.  With "lastused" in
+    /// 'wildmode' the most recently used come first and the current buffer
+    /// last.
+    fn complete_buffers(&self, pat: &str) -> Vec<String> {
+        let lastused = WildMode::parse_list(self.options.string("wildmode"))
+            .is_ok_and(|parts| parts.iter().any(|part| part.lastused));
+        let listed = self.buffers.list_listed();
+        let handles = if lastused {
+            let mut handles = self.buffers.last_used();
+            handles.rotate_left(1);
+            handles
+        } else {
+            listed.clone()
+        };
+        let ignore_case = self.options.flag("fileignorecase");
+        handles
+            .into_iter()
+            .filter(|handle| listed.contains(handle))
+            .filter_map(|handle| self.buffers.get(handle))
+            .map(|buf| buf.name().to_string())
+            .filter(|name| {
+                !name.is_empty()
+                    && if ignore_case {
+                        name.to_lowercase().contains(&pat.to_lowercase())
+                    } else {
+                        name.contains(pat)
+                    }
+            })
+            .collect()
+    }
+
+    /// Option names starting with `pat`, and `no`/`inv` forms of boolean
+    /// options
+    fn complete_option_names(&self, pat: &str) -> Vec<String> {
+        let mut names = Vec::new();
+        for def in self.options.definitions() {
+            if def.hidden {
+                continue;
+            }
+            if def.name.starts_with(pat) {
+                names.push(def.name.clone());
+            }
+            if let OptionValue::Boolean(_) = def.default {
+                for negation in ["no", "inv"] {
+                    if let Some(rest) = pat.strip_prefix(negation) {
+                        if def.name.starts_with(rest) {
+                            names.push(format!("{}{}", negation, def.name));
+                        }
+                    }
+                }
+            }
+        }
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Values for option `name` starting with `pat`.  With nothing typed
+    /// the current value comes first.
+    fn complete_option_value(&self, name: &str, pat: &str) -> Vec<String> {
+        let Some(def) = self.options.definition(name) else {
+            return Vec::new();
+        };
+        let mut values = Vec::new();
+        if pat.is_empty() {
+            match self.options.get(&def.name) {
+                Some(OptionValue::Number(n)) => values.push(n.to_string()),
+                Some(OptionValue::String(s)) if !s.is_empty() => values.push(escape_spaces(s)),
+                _ => {}
+            }
+        }
+        if let Some((_, fixed)) = OPTION_VALUES.iter().find(|(option, _)| *option == def.name) {
+            for value in fixed.iter().filter(|value| value.starts_with(pat)) {
+                if !values.iter().any(|v| v == value) {
+                    values.push(value.to_string());
+                }
+            }
+        }
+        values
+    }
+
+    /// Help tags starting with `pat` from the `doc/tags` files in
+    /// 'runtimepath'
+    fn complete_help_tags(&self, pat: &str) -> Vec<String> {
+        let mut tags = Vec::new();
+        for dir in self.options.string("runtimepath").split(',') {
+            if dir.is_empty() {
+                continue;
+            }
+            let Ok(text) = std::fs::read_to_string(expand_home(dir).join("doc").join("tags"))
+            else {
+                continue;
+            };
+            tags.extend(
+                text.lines()
+                    .filter_map(|line| line.split('\t').next())
+                    .filter(|tag| !tag.is_empty() && tag.starts_with(pat))
+                    .map(str::to_string),
+            );
+        }
+        tags.sort();
+        tags.dedup();
+        tags
+    }
+
+    /// Where completion of the `:` command line `text` starts, and what it
+    /// completes to
+    fn wild_context(&self, text: &str) -> Option<(usize, WildContext)> {
+        let cmd = text.trim_start_matches(|c: char| c == ':' || c.is_whitespace());
+        let (_, rest) = parse_command_range(cmd).ok()?;
+        let rest = rest.trim_start_matches(|c: char| c == ':' || c.is_whitespace());
+        let name_start = text.len() - rest.len();
+        let name_len = rest
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(rest.len());
+        if name_len == rest.len() {
+            return Some((name_start, WildContext::Command));
+        }
+        let def = lookup(&rest[..name_len])?;
+        let args = rest[name_len..]
+            .strip_prefix('!')
+            .unwrap_or(&rest[name_len..]);
+        if !args.starts_with(char::is_whitespace) {
+            return None;
+        }
+        let args_start = text.len() - args.len();
+        let arg_start = last_arg_start(text, args_start);
+        let arg = &text[arg_start..];
+
+        match def.complete {
+            CompleteType::Option => match arg.find(['=', ':']) {
+                Some(idx) => {
+                    let name = arg[..idx].trim_end_matches(['+', '-', '^']);
+                    Some((
+                        arg_start + idx + 1,
+                        WildContext::OptionValue(name.to_string()),
+                    ))
+                }
+                None => Some((arg_start, WildContext::Arg(CompleteType::Option))),
+            },
+            CompleteType::Event => {
+                let before: Vec<&str> = text[args_start..arg_start].split_whitespace().collect();
+                let event_start = arg.rfind(',').map_or(arg_start, |idx| arg_start + idx + 1);
+                match before.as_slice() {
+                    [] if event_start == arg_start => Some((arg_start, WildContext::GroupOrEvent)),
+                    [] => Some((event_start, WildContext::Arg(CompleteType::Event))),
+                    [group] if self.autocmds.find_group(group).is_some() => {
+                        Some((event_start, WildContext::Arg(CompleteType::Event)))
+                    }
+                    _ => None,
+                }
+            }
+            kind => Some((arg_start, WildContext::Arg(kind))),
+        }
+    }
+
+    /// Matches for the `:` command line `text`: where the completed text
+    /// starts, the matches and whether they are file names
+    fn wild_matches(&self, text: &str) -> Option<(usize, Vec<String>, bool)> {
+        let (start, context) = self.wild_context(text)?;
+        let pat = unescape_spaces(&text[start..]);
+        let (matches, files) = match context {
+            WildContext::Command => (self.complete_command(&pat), false),
+            WildContext::Arg(kind) => {
+                let matches = self.getcompletion(&pat, kind);
+                if kind.is_file_name() {
+                    (matches.iter().map(|m| escape_spaces(m)).collect(), true)
+                } else {
+                    (matches, false)
+                }
+            }
+            WildContext::OptionValue(name) => (self.complete_option_value(&name, &pat), false),
+            WildContext::GroupOrEvent => {
+                let mut matches = self.getcompletion(&pat, CompleteType::Augroup);
+                matches.retain(|group| group != "END");
+                matches.extend(self.getcompletion(&pat, CompleteType::Event));
+                (matches, false)
+            }
+        };
+        Some((start, matches, files))
+    }
+
+    /// Completions for the arguments `args` of the command `cmd`
+    pub(crate) fn complete_command_args(&self, cmd: &str, args: &str) -> Vec<String> {
+        let text = format!("{} {}", cmd, args);
+        self.wild_matches(&text)
+            .map(|(_, matches, _)| matches)
+            .unwrap_or_default()
+    }
+
+    /// Whether `key` is 'wildchar'
+    fn is_wildchar(&self, key: Key) -> bool {
+        let wildchar = match self.options.number("wildchar") {
+            9 => Key::Tab,
+            n @ 1..=26 => Key::Ctrl((b'a' + n as u8 - 1) as char),
+            27 => Key::Escape,
+            n => match u32::try_from(n).ok().and_then(char::from_u32) {
+                Some(c) => Key::Char(c),
+                None => return false,
+            },
+        };
+        key == wildchar
+    }
+
+    /// Handle command-line completion keys.  Returns `false` when the key
+    /// should be handled as usual; a key that is not a completion key ends
+    /// the completion in progress.
+    pub(crate) fn wildmenu_key(&mut self, key: Key) -> VimResult<bool> {
+        let wildchar = self.is_wildchar(key);
+        if self.cmdline_type != Some(':') {
+            if wildchar {
+                self.cmdline.insert_cmdline("\t")?;
+                return Ok(true);
+            }
+            return Ok(false);
+        }
+
+        if let Some(session) = &self.wild {
+            let pum = self.wild_uses_pum();
+            match key {
+                _ if wildchar => {
+                    self.wild_next_part()?;
+                    return Ok(true);
+                }
+                Key::Ctrl('n') => return self.wild_select(true).map(|_| true),
+                Key::Ctrl('p') => return self.wild_select(false).map(|_| true),
+                Key::Right | Key::Down if session.shown && pum == (key == Key::Down) => {
+                    return self.wild_select(true).map(|_| true);
+                }
+                Key::Left | Key::Up if session.shown && pum == (key == Key::Up) => {
+                    return self.wild_select(false).map(|_| true);
+                }
+                Key::Ctrl('e') => {
+                    if let Some(session) = self.wild.as_mut() {
+                        session.selected = None;
+                    }
+                    self.wild_insert_selected()?;
+                    self.wild = None;
+                    return Ok(true);
+                }
+                Key::Ctrl('y') => {
+                    self.wild = None;
+                    return Ok(true);
+                }
+                _ => self.wild = None,
+            }
+        }
+
+        match key {
+            _ if wildchar => {
+                if self.wild_start()? {
+                    self.wild_next_part()?;
+                }
+                Ok(true)
+            }
+            Key::Ctrl('d') => {
+                if self.wild_start()? {
+                    if let Some(session) = self.wild.as_mut() {
+                        session.shown = true;
+                    }
+                }
+                Ok(true)
+            }
+            Key::Ctrl('a') => {
+                if self.wild_start()? {
+                    let matches = self.wild.take().map(|s| s.matches).unwrap_or_default();
+                    self.wild_replace(&matches.join(" "))?;
+                }
+                Ok(true)
+            }
+            Key::Ctrl('l') => {
+                if self.wild_start()? {
+                    let ignore_case = self.wild_ignore_case();
+                    let session = self.wild.take().unwrap_or_default();
+                    let longest = longest_common_prefix(&session.matches, ignore_case);
+                    if longest.len() > session.original.len() {
+                        self.wild_replace(&longest)?;
+                    }
+                }
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Start completing the text before the cursor.  Returns `false` when
+    /// nothing matches.
+    fn wild_start(&mut self) -> VimResult<bool> {
+        let text = self.cmdline.getcmdline().to_string();
+        let pos = self.cmdline.getcmdpos() - 1;
+        let Some((start, matches, files)) = self.wild_matches(&text[..pos]) else {
+            return Ok(false);
+        };
+        if matches.is_empty() {
+            self.wild = None;
+            return Ok(false);
+        }
+        self.wild = Some(WildSession {
+            start,
+            original: text[start..pos].to_string(),
+            tail: text[pos..].to_string(),
+            matches,
+            files,
+            ..WildSession::default()
+        });
+        Ok(true)
+    }
+
+    /// Apply the next part of 'wildmode'
+    fn wild_next_part(&mut self) -> VimResult<()> {
+        let parts = WildMode::parse_list(self.options.string("wildmode")).unwrap_or_default();
+        let parts = if parts.is_empty() {
+            vec![WildMode::default()]
+        } else {
+            parts
+        };
+        let wildmenu = self.options.flag("wildmenu");
+        let ignore_case = self.wild_ignore_case();
+        let Some(session) = self.wild.as_mut() else {
+            return Ok(());
+        };
+        if session.matches.len() == 1 {
+            let only = session.matches[0].clone();
+            self.wild = None;
+            return self.wild_replace(&only);
+        }
+
+        let mut press = session.presses;
+        loop {
+            let part = parts[press.min(parts.len() - 1)];
+            let repeated = press >= parts.len();
+            press += 1;
+            if part.longest && session.selected.is_none() {
+                let longest = longest_common_prefix(&session.matches, ignore_case);
+                if longest.len() > session.original.len() {
+                    session.original = longest;
+                } else if !part.full && !part.list && press < parts.len() {
+                    // Nothing longer: use the next part
+                    continue;
+                }
+            }
+            session.shown |= part.list || (part.full && wildmenu);
+            if part.full && (repeated || !(part.longest || part.noselect)) {
+                session.selected = next_selection(session.selected, session.matches.len(), true);
+            }
+            // An empty 'wildmode' only ever completes the first match
+            if !(part.full || part.longest || part.list) {
+                session.selected = Some(0);
+            }
+            break;
+        }
+        session.presses = press;
+        self.wild_insert_selected()
+    }
+
+    /// Select the next or previous match
+    fn wild_select(&mut self, forward: bool) -> VimResult<()> {
+        if let Some(session) = self.wild.as_mut() {
+            session.selected = next_selection(session.selected, session.matches.len(), forward);
+        }
+        self.wild_insert_selected()
+    }
+
+    /// Put the selected match, or the original text, on the command line
+    fn wild_insert_selected(&mut self) -> VimResult<()> {
+        let Some(session) = &self.wild else {
+            return Ok(());
+        };
+        let word = match session.selected {
+            Some(idx) => session.matches[idx].clone(),
+            None => session.original.clone(),
+        };
+        let text = self.cmdline.getcmdline();
+        let line = format!("{}{}{}", &text[..session.start], word, session.tail);
+        let pos = session.start + word.len();
+        self.cmdline.setcmdline(&line)?;
+        self.cmdline.setcmdpos(pos + 1)
+    }
+
+    /// Replace the text being completed with `word`, outside a session
+    fn wild_replace(&mut self, word: &str) -> VimResult<()> {
+        let text = self.cmdline.getcmdline().to_string();
+        let pos = self.cmdline.getcmdpos() - 1;
+        let Some((start, _)) = self.wild_context(&text[..pos]) else {
+            return Ok(());
+        };
+        let line = format!("{}{}{}", &text[..start], word, &text[pos..]);
+        self.cmdline.setcmdline(&line)?;
+        self.cmdline.setcmdpos(start + word.len() + 1)
+    }
+
+    /// Whether the longest common prefix of the matches ignores case
+    fn wild_ignore_case(&self) -> bool {
+        match &self.wild {
+            Some(session) if session.files => {
+                self.options.flag("wildignorecase") || self.options.flag("fileignorecase")
+            }
+            _ => false,
+        }
+    }
+
+    fn wild_uses_pum(&self) -> bool {
+        self.options
+            .string("wildoptions")
+            .split(',')
+            .any(|item| item == "pum")
+    }
+
+    /// The completion matches shown on the command line, if any
+    pub fn wildmenu(&self) -> Option<WildMenu> {
+        let session = self.wild.as_ref().filter(|session| session.shown)?;
+        let items = session
+            .matches
+            .iter()
+            .map(|word| {
+                if session.files {
+                    file_tail(word).to_string()
+                } else {
+                    word.clone()
+                }
+            })
+            .collect();
+        Some(WildMenu {
+            items,
+            selected: session.selected,
+            start: session.start,
+            pum: self.wild_uses_pum(),
+        })
+    }
+
+    /// Place the popup menu for the command-line matches at `anchor`, when
+    /// 'wildoptions' contains "pum"
+    pub fn wildmenu_popup(&mut self, anchor: PumAnchor) -> Option<PopupMenu> {
+        let menu = self.wildmenu().filter(|menu| menu.pum)?;
+        let items: Vec<CompletionItem> = menu.items.into_iter().map(CompletionItem::new).collect();
+        let limits = PumLimits {
+            max_height: self.options.number("pumheight").max(0) as usize,
+            min_width: self.options.number("pumwidth").max(0) as usize,
+        };
+        let session = self.wild.as_mut()?;
+        let menu = PopupMenu::layout(
+            &items,
+            menu.selected,
+            session.pum_first,
+            anchor,
+            limits,
+            false,
+        )?;
+        session.pum_first = menu.first;
+        Some(menu)
+    }
+}
+
+/// Next selection cycling through the matches and the original text
+/// (`None`)
+fn next_selection(selected: Option<usize>, count: usize, forward: bool) -> Option<usize> {
+    match (selected, forward) {
+        (None, true) => Some(0),
+        (None, false) => count.checked_sub(1),
+        (Some(idx), true) if idx + 1 < count => Some(idx + 1),
+        (Some(idx), false) if idx > 0 => Some(idx - 1),
+        _ => None,
+    }
+}
+
+/// Byte offset of the last argument in `text`: after the last whitespace
+/// not escaped with a backslash, at or after `from`
+fn last_arg_start(text: &str, from: usize) -> usize {
+    let mut start = from;
+    let mut escaped = false;
+    for (idx, c) in text.char_indices().skip_while(|&(idx, _)| idx < from) {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c.is_whitespace() {
+            start = idx + c.len_utf8();
+        }
+    }
+    start
+}
+
+fn starts_with_ignore_case(name: &str, prefix: &str) -> bool {
+    name.to_lowercase().starts_with(&prefix.to_lowercase())
+}
+
+fn escape_spaces(text: &str) -> String {
+    text.replace(' ', "\\ ")
+}
+
+fn unescape_spaces(text: &str) -> String {
+    text.replace("\\ ", " ")
+}
+
+/// The last part of a file name match; directories keep their `/`
+fn file_tail(word: &str) -> &str {
+    let trimmed = word.strip_suffix('/').unwrap_or(word);
+    match trimmed.rfind('/') {
+        Some(idx) => &word[idx + 1..],
+        None => word,
+    }
+}
+
+/// Replace a leading `~/` with $HOME
+pub(crate) fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
@@ -0,0 +1,318 @@
+//! Searching for the word under the cursor and for search matches.
+//!
+//! `*` and `#` search forward and backward for the keyword under or after
+//! the cursor as a whole word (`\<word\>`, using 'iskeyword'); `g*` and
+//! `g#` also find it inside other words.  `gd` and `gD` go to the
+//! declaration of the keyword: its first use in the current function or in
+//! the file, skipping comments and strings.  `gn` and `gN` find the match
+//! of the last search pattern under or after (before) the cursor, to be
+//! selected or operated on.
+
+use vxd::cmdline::{CmdlineHistory, CmdlineHistoryKind};
+use vxd::cursor::{Cursor, CursorPosition};
+use vxd::registers::RegisterBank;
+use vxd::search::{SearchEngine, SearchMatch, SearchPattern};
+use vxd::types::{Direction, LineNr, VimError, VimResult};
+
+use crate::editor::{no_previous_pattern, Editor};
+
+/// Text under the cursor that `*`, `#`, `gd` and `gD` search for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CursorWord {
+    /// The keyword, or the non-blank text when there is no keyword
+    pub text: String,
+    /// Byte column where it starts
+    pub start: usize,
+    /// Whether it is a keyword
+    pub keyword: bool,
+}
+
+impl Editor {
+    /// The keyword under or after the cursor on its line, or else the
+    /// non-blank text under or after it
+    pub(crate) fn word_under_cursor(&self) -> Option<CursorWord> {
+        let line = self.current_line();
+        let mut col = self.cursor.col().min(line.len());
+        // A column inside a character means the character
+        while !line.is_char_boundary(col) {
+            col -= 1;
+        }
+        let iskeyword = self.search_options().iskeyword;
+        let find = |is_word: &dyn Fn(char) -> bool| {
+            let mut start = line[col..]
+                .char_indices()
+                .find(|&(_, c)| is_word(c))
+                .map(|(idx, _)| col + idx)?;
+            // Back up to the start of the word the cursor is in
+            if start == col {
+                start = line[..col]
+                    .char_indices()
+                    .rev()
+                    .take_while(|&(_, c)| is_word(c))
+                    .last()
+                    .map_or(col, |(idx, _)| idx);
+            }
+            let end = line[start..]
+                .char_indices()
+                .find(|&(_, c)| !is_word(c))
+                .map_or(line.len(), |(idx, _)| start + idx);
+            Some((start, end))
+        };
+        let word = |(start, end): (usize, usize), keyword| CursorWord {
+            text: line[start..end].to_string(),
+            start,
+            keyword,
+        };
+        match find(&|c| iskeyword.contains(c)) {
+            Some(range) => Some(word(range, true)),
+            None => find(&|c: char| !c.is_whitespace()).map(|range| word(range, false)),
+        }
+    }
+
+    /// Pattern for `*`, `#`, `g*` and `g#`, which becomes the last search
+    /// pattern and is added to the search history.  The cursor moves to
+    /// the start of the word so that the search skips it.
+    pub(crate) fn start_word_search(
+        &mut self,
+        direction: Direction,
+        whole: bool,
+    ) -> VimResult<SearchPattern> {
+        let word = self
+            .word_under_cursor()
+            .ok_or_else(|| VimError::Error(348, "No string under cursor".to_string()))?;
+        let pattern = self
+            .search
+            .search_word(&word.text, direction, whole && word.keyword)?;
+        self.search.set_last_pattern(pattern.clone());
+        self.search.state_mut().last_direction = direction;
+        self.search.state_mut().highlighting = true;
+        self.registers.set_last_search(&pattern.pattern);
+        self.cmdline.hist_add(
+            match direction {
+                Direction::Forward => CmdlineHistoryKind::SearchForward,
+                Direction::Backward => CmdlineHistoryKind::SearchBackward,
+            },
+            &pattern.pattern,
+        );
+        let ctx = self.cursor_context();
+        let start = CursorPosition::new(self.cursor.line(), word.start);
+        self.cursor.set_position(start, &ctx)?;
+        Ok(pattern)
+    }
+
+    /// `gd` and `gD`: the first use of the keyword under the cursor in the
+    /// current function (`gD`: in the file) that is not in a comment or a
+    /// string.  Sets the last search pattern so that `n` finds the next
+    /// use.  Returns `None` when there is no such use.
+    pub(crate) fn find_declaration(&mut self, global: bool) -> VimResult<Option<CursorPosition>> {
+        let word = self
+            .word_under_cursor()
+            .filter(|word| word.keyword)
+            .ok_or_else(|| VimError::Error(349, "No identifier under cursor".to_string()))?;
+        let pattern = self
+            .search
+            .search_word(&word.text, Direction::Forward, true)?;
+        self.search.set_last_pattern(pattern.clone());
+        self.search.state_mut().last_direction = Direction::Forward;
+        self.search.state_mut().highlighting = true;
+        self.registers.set_last_search(&pattern.pattern);
+
+        let lines = self.buffer_lines();
+        // The function starts at a "{" in the first column; its header
+        // goes back to the blank line above that
+        let cursor = self.cursor.line();
+        let par_line = if global {
+            LineNr(1)
+        } else {
+            (1..cursor.0)
+                .rev()
+                .find(|&lnum| lines[lnum - 1].starts_with('{'))
+                .map_or(LineNr(1), LineNr)
+        };
+        let mut start = par_line;
+        while !global && start.0 > 1 && !lines[start.0 - 1].trim().is_empty() {
+            start = LineNr(start.0 - 1);
+        }
+
+        self.search.set_lines(lines.clone());
+        let matches = self.search.find_all(
+            &pattern,
+            CursorPosition::new(start, 0),
+            CursorPosition::new(LineNr(lines.len()), usize::MAX),
+            &self.search_options(),
+        )?;
+        let leaders = comment_leaders(self.options.string("comments"));
+        let mut found: Option<CursorPosition> = None;
+        for m in matches {
+            let line = &lines[m.start.line.to_zero_indexed()];
+            if is_comment_line(line, &leaders) {
+                continue;
+            }
+            let valid = is_ident(line, m.start.col);
+            if !valid && found.is_some() {
+                break;
+            }
+            if valid && (global || m.start.line >= par_line) {
+                return Ok(Some(found.unwrap_or(m.start)));
+            }
+            // A use before the "{" may be a declaration in the header
+            found = valid.then_some(m.start);
+        }
+        Ok(found)
+    }
+
+    /// `gn` and `gN`: the match of the last search pattern under the cursor,
+    /// or else the [count]th one after (before) it, as the positions of its
+    /// first and last character
+    pub(crate) fn find_search_match(
+        &mut self,
+        forward: bool,
+        count: usize,
+    ) -> VimResult<Option<(CursorPosition, CursorPosition)>> {
+        let pattern = self
+            .search
+            .last_pattern()
+            .cloned()
+            .ok_or_else(no_previous_pattern)?;
+        let lines = self.buffer_lines();
+        self.search.set_lines(lines.clone());
+        let options = self.search_options();
+        let matches: Vec<SearchMatch> = self
+            .search
+            .find_all(
+                &pattern,
+                CursorPosition::new(LineNr(1), 0),
+                CursorPosition::new(LineNr(lines.len()), usize::MAX),
+                &options,
+            )?
+            .into_iter()
+            .filter(|m| m.end.col > m.start.col)
+            .collect();
+        if matches.is_empty() {
+            return Err(VimError::PatternNotFound(pattern.pattern));
+        }
+        self.search.state_mut().highlighting = true;
+
+        let pos = self.cursor.position();
+        let cur = (pos.line, pos.col);
+        let under = matches
+            .iter()
+            .position(|m| (m.start.line, m.start.col) <= cur && cur < (m.end.line, m.end.col));
+        let mut idx = match (under, forward) {
+            (Some(idx), _) => idx,
+            (None, true) => match matches
+                .iter()
+                .position(|m| (m.start.line, m.start.col) > cur)
+            {
+                Some(idx) => idx,
+                None if options.wrapscan => 0,
+                None => return Ok(None),
+            },
+            (None, false) => match matches
+                .iter()
+                .rposition(|m| (m.start.line, m.start.col) < cur)
+            {
+                Some(idx) => idx,
+                None if options.wrapscan => matches.len() - 1,
+                None => return Ok(None),
+            },
+        };
+        for _ in 1..count.max(1) {
+            idx = if forward && idx + 1 < matches.len() {
+                idx + 1
+            } else if !forward && idx > 0 {
+                idx - 1
+            } else if !options.wrapscan {
+                return Ok(None);
+            } else if forward {
+                0
+            } else {
+                matches.len() - 1
+            };
+        }
+
+        let found = &matches[idx];
+        let line = &lines[found.end.line.to_zero_indexed()];
+        let last_col = line[..found.end.col.min(line.len())]
+            .char_indices()
+            .next_back()
+            .map_or(0, |(col, _)| col);
+        Ok(Some((
+            found.start,
+            CursorPosition::new(found.end.line, last_col),
+        )))
+    }
+}
+
+/// The comment leaders from 'comments' that start a comment line, such as
+/// "//" and "#".  Parts that only end a comment or only apply to the first
+/// line are left out.  The flag is true when a blank must follow.
+fn comment_leaders(comments: &str) -> Vec<(bool, String)> {
+    comments
+        .split(',')
+        .filter_map(|part| part.split_once(':'))
+        .filter(|(flags, leader)| !leader.is_empty() && !flags.contains(['e', 'f']))
+        .map(|(flags, leader)| (flags.contains('b'), leader.to_string()))
+        .collect()
+}
+
+/// Whether `line` starts with a comment leader, after white space
+fn is_comment_line(line: &str, leaders: &[(bool, String)]) -> bool {
+    let text = line.trim_start();
+    leaders.iter().any(|(blank, leader)| {
+        text.strip_prefix(leader.as_str())
+            .is_some_and(|rest| !blank || rest.is_empty() || rest.starts_with(char::is_whitespace))
+    })
+}
+
+/// Whether the text at byte `col` is outside strings and C comments, like
+/// Vim's `is_ident()`
+fn is_ident(line: &str, col: usize) -> bool {
+    let mut in_comment = false;
+    let mut in_string = None;
+    let mut prev = '\0';
+    for (_, c) in line.char_indices().take_while(|&(idx, _)| idx < col) {
+        if let Some(quote) = in_string {
+            if prev != '\\' && c == quote {
+                in_string = None;
+            }
+        } else if (c == '"' || c == '\'') && !in_comment {
+            in_string = Some(c);
+        } else if in_comment {
+            if prev == '*' && c == '/' {
+                in_comment = false;
+            }
+        } else if prev == '/' && c == '*' {
+            in_comment = true;
+        } else if prev == '/' && c == '/' {
+            return false;
+        }
+        prev = c;
+    }
+    !in_comment && in_string.is_none()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_ident_skips_strings_and_comments() {
+        assert!(is_ident("int foo = 1;", 4));
+        assert!(!is_ident("x = \"foo\";", 5));
+        assert!(!is_ident("x; // foo", 6));
+        assert!(!is_ident("x /* foo */", 5));
+        assert!(is_ident("x /* a */ foo", 10));
+    }
+
+    #[test]
+    fn test_comment_leaders() {
+        let leaders = comment_leaders("s1:/*,mb:*,ex:*/,://,b:#,:%,fb:-");
+        assert!(is_comment_line("  // foo", &leaders));
+        assert!(is_comment_line(" * foo", &leaders));
+        assert!(is_comment_line("# foo", &leaders));
+        assert!(!is_comment_line("#foo", &leaders));
+        assert!(!is_comment_line("- foo", &leaders));
+        assert!(!is_comment_line("foo // bar", &leaders));
+    }
+}
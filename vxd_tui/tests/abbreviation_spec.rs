@@ -1,6 +1,19 @@
+//! Abbreviation tests ported from Vim tests
+//!
+//! These tests verify abbreviations:
+//! - The full-id, end-id and non-id kinds, by 'iskeyword'
+//! - Expansion on a non-keyword character, `<Esc>`, `<CR>` and `Ctrl-]`,
+//!   not after `Ctrl-V` and not of text from before inserting started
+//! - Buffer-local and `<expr>` abbreviations
+//! - `:abbreviate`, `:iabbrev`, `:cabbrev`, `:unabbreviate` and `:abclear`
+//!
+//! Source tests:
+//! - src/testdir/test_mapping.vim
+
 mod common;
 
 use vxd::abbreviations::AbbreviationManager;
+use vxd::cmdline::Cmdline;
 use vxd::modes::Mode;
 use common::TestHarness;
 
@@ -47,3 +60,181 @@ fn test_insert_mode_abbreviation_no_trigger_on_superstring() {
     h.feed("bad "); // 'b' is keyword, so 'ad' is not a whole word
     assert_eq!(h.content(), "bad ");
 }
+
+// ============================================================================
+// Abbreviation Kinds
+// ============================================================================
+
+/// Test: end-id and non-id abbreviations expand
+/// Source: test_mapping.vim "Test_abbreviation"
+#[test]
+fn test_end_id_and_non_id() {
+    let mut h = TestHarness::new();
+    h.feed(":iab #i #include<CR>:iab def# defined<CR>");
+    h.feed("i#i x..#i def# 4def#<Esc>");
+    assert_eq!(h.content(), "#include x..#i defined 4def#");
+}
+
+/// Test: a left-hand side that is of no kind is an error
+/// Source: test_mapping.vim "Test_abbreviation"
+#[test]
+fn test_invalid_lhs() {
+    let mut h = TestHarness::new();
+    for lhs in ["a.b", "#def", "_$r"] {
+        h.feed(&format!(":iab {} x<CR>", lhs));
+        assert_eq!(h.message(), "E474: Invalid argument");
+    }
+    assert!(h.editor.abbreviations.list(Mode::Insert).is_empty());
+}
+
+/// Test: the kind depends on 'iskeyword'
+/// Source: test_mapping.vim "Test_abbreviation"
+#[test]
+fn test_kind_follows_iskeyword() {
+    let mut h = TestHarness::new();
+    h.feed(":iab a-b x<CR>");
+    assert_eq!(h.message(), "E474: Invalid argument");
+    h.feed(":set iskeyword=@,48-57,_,-<CR>:iab a-b AB<CR>");
+    h.feed("ia-b c-a-b.<Esc>");
+    assert_eq!(h.content(), "AB c-a-b.");
+}
+
+// ============================================================================
+// Triggers
+// ============================================================================
+
+/// Test: <Esc>, <CR> and Ctrl-] expand; Ctrl-] inserts nothing
+/// Source: test_mapping.vim "Test_abbreviation_CR"
+#[test]
+fn test_other_triggers() {
+    let mut h = TestHarness::new();
+    h.feed(":iab teh the<CR>");
+    h.feed("iteh<CR>teh<C-]>x<Esc>");
+    assert_lines!(h, "the", "thex");
+    h.feed("oteh<Esc>");
+    assert_lines!(h, "the", "thex", "the");
+}
+
+/// Test: a character typed after Ctrl-V does not expand
+/// Source: test_mapping.vim "Test_abbreviation"
+#[test]
+fn test_ctrl_v_suppresses() {
+    let mut h = TestHarness::new();
+    h.feed(":iab teh the<CR>");
+    h.feed("iteh<C-V> teh.<Esc>");
+    assert_eq!(h.content(), "teh the.");
+}
+
+/// Test: text from before inserting started is not expanded
+/// Source: test_mapping.vim "Test_abbr_after_line_join"
+#[test]
+fn test_not_before_insert_start() {
+    let mut h = TestHarness::with_lines(&["teh"]);
+    h.feed(":iab teh the<CR>");
+    h.feed("A <Esc>");
+    assert_eq!(h.content(), "teh ");
+    h.feed("oteh <Esc>");
+    assert_lines!(h, "teh ", "the ");
+}
+
+/// Test: a multibyte abbreviation expands
+/// Source: test_mapping.vim "Test_abbreviate_multi_byte"
+#[test]
+fn test_multibyte() {
+    let mut h = TestHarness::new();
+    h.feed(":iab foo☺ bar<CR>:iab ça cela<CR>");
+    h.feed("ifoo☺ ça.<Esc>");
+    assert_eq!(h.content(), "bar cela.");
+}
+
+// ============================================================================
+// Buffer-local and Expression Abbreviations
+// ============================================================================
+
+/// Test: a buffer-local abbreviation is found before a global one
+/// Source: test_mapping.vim "Test_abclear"
+#[test]
+fn test_buffer_local_first() {
+    let mut h = TestHarness::new();
+    h.feed(":iab teh global<CR>:iab <lt>buffer> teh local<CR>");
+    h.feed("iteh <Esc>");
+    assert_eq!(h.content(), "local ");
+    h.feed(":iab<CR>");
+    assert_eq!(h.message(), "i  teh          @local\ni  teh           global");
+    h.feed(":iabclear <lt>buffer><CR>");
+    h.feed("A teh <Esc>");
+    assert_eq!(h.content(), "local  global ");
+}
+
+/// Test: the right-hand side of an <expr> abbreviation is evaluated
+/// Source: test_mapping.vim "Test_expr_abbr"
+#[test]
+fn test_expr_abbreviation() {
+    let mut h = TestHarness::new();
+    h.feed(":let g:n = 3<CR>:iab <lt>expr> num 'n' . g:n<CR>");
+    h.feed("inum <Esc>");
+    assert_eq!(h.content(), "n3 ");
+    h.feed(":iab<CR>");
+    assert_eq!(h.message(), "i  num           'n' . g:n");
+}
+
+// ============================================================================
+// Command-line Mode
+// ============================================================================
+
+/// Test: :cabbrev expands on the command line only
+/// Source: test_mapping.vim "Test_cabbr_visual_mode"
+#[test]
+fn test_cabbrev() {
+    let mut h = TestHarness::new();
+    h.feed(":cab xx yy<CR>:iab ii jj<CR>");
+    h.feed(":xx ii ");
+    assert_eq!(h.editor.cmdline.getcmdline(), "yy ii ");
+    h.feed("<Esc>ixx ii <Esc>");
+    assert_eq!(h.content(), "xx jj ");
+}
+
+/// Test: <CR> expands on the command line before executing it
+/// Source: test_mapping.vim "Test_abbreviation_CR"
+#[test]
+fn test_cabbrev_on_enter() {
+    let mut h = TestHarness::new();
+    h.feed(":cnoreab ll let g:x = 1<CR>:ll<CR>");
+    assert_eq!(h.eval("g:x"), vxd::eval::Value::Number(1));
+}
+
+// ============================================================================
+// Listing and Removing
+// ============================================================================
+
+/// Test: :abbreviate lists the abbreviations, the last defined first
+/// Source: test_mapping.vim "Test_abbreviation"
+#[test]
+fn test_listing() {
+    let mut h = TestHarness::new();
+    h.feed(":iab foo bar<CR>:cab cx cy<CR>:ab teh the<CR>:inoreab nr no<CR>");
+    h.feed(":ab<CR>");
+    assert_eq!(
+        h.message(),
+        "i  nr          * no\n!  teh           the\nc  cx            cy\ni  foo           bar"
+    );
+    h.feed(":cab<CR>");
+    assert_eq!(h.message(), "!  teh           the\nc  cx            cy");
+    h.feed(":ab te<CR>");
+    assert_eq!(h.message(), "!  teh           the");
+}
+
+/// Test: :unabbreviate and :abclear remove abbreviations
+/// Source: test_mapping.vim "Test_abclear"
+#[test]
+fn test_unabbreviate_and_abclear() {
+    let mut h = TestHarness::new();
+    h.feed(":ab teh the<CR>:iab foo bar<CR>");
+    // The command line expands teh, the right-hand side is found
+    h.feed(":iuna teh<CR>:ab<CR>");
+    assert_eq!(h.message(), "i  foo           bar\nc  teh           the");
+    h.feed(":iuna teh<CR>");
+    assert_eq!(h.message(), "E24: No such abbreviation");
+    h.feed(":abc<CR>:ab<CR>");
+    assert_eq!(h.message(), "No abbreviation found");
+}
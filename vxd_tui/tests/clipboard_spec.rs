@@ -0,0 +1,389 @@
+//! Clipboard tests ported from Vim/Neovim tests
+//!
+//! These tests verify the `+` and `*` registers going through a
+//! clipboard provider, the register type surviving a copy and paste,
+//! 'clipboard' with "unnamed" and "unnamedplus", and OSC 52 copying.
+//! A fake provider stands in for the system clipboard.
+//!
+//! Source tests:
+//! - test/functional/provider/clipboard_spec.lua
+//! - src/testdir/test_registers.vim
+
+mod common;
+
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use common::{ScratchDir, TestHarness};
+use vxd::clipboard::ClipboardProvider;
+use vxd::cmdline::Cmdline;
+use vxd::registers::{Register, RegisterBank, RegisterType};
+use vxd::startup::parse_args;
+use vxd::types::{VimError, VimResult};
+use vxd_tui::clipboard::{CommandClipboard, FakeClipboard, Osc52Clipboard};
+
+/// A harness whose clipboard is a fake shared with the test
+fn with_clipboard(lines: &[&str]) -> (TestHarness, FakeClipboard) {
+    let mut h = TestHarness::with_lines(lines);
+    let clipboard = FakeClipboard::new();
+    h.editor
+        .registers
+        .set_clipboard_provider(Box::new(clipboard.clone()));
+    (h, clipboard)
+}
+
+// ============================================================================
+// "+ and "*
+// ============================================================================
+
+/// Test: yanking to "+ copies the text, linewise with a trailing newline
+/// Source: clipboard_spec.lua "clipboard: with a provider"
+#[test]
+fn test_yank_copies_to_provider() {
+    let (mut h, clipboard) = with_clipboard(&["first line", "second"]);
+    h.feed("\"+yy");
+    assert_eq!(
+        clipboard.text(Register::Clipboard).as_deref(),
+        Some("first line\n")
+    );
+    h.feed("j\"*ye");
+    assert_eq!(
+        clipboard.text(Register::Selection).as_deref(),
+        Some("second")
+    );
+    assert_eq!(
+        clipboard.text(Register::Clipboard).as_deref(),
+        Some("first line\n")
+    );
+    assert_eq!(
+        h.editor.registers.clipboard_provider_name().as_deref(),
+        Some("fake")
+    );
+}
+
+/// Test: putting "+ reads the provider, guessing the type of text copied
+/// elsewhere
+/// Source: clipboard_spec.lua "clipboard: with a provider"
+#[test]
+fn test_put_reads_provider() {
+    let (mut h, clipboard) = with_clipboard(&["abc"]);
+    clipboard.set_text(Register::Clipboard, "XY");
+    h.feed("\"+p");
+    assert_lines!(h, "aXYbc");
+    clipboard.set_text(Register::Clipboard, "new line\n");
+    h.feed("\"+p");
+    assert_lines!(h, "aXYbc", "new line");
+    assert_cursor!(h, 2, 0);
+
+    clipboard.set_text(Register::Clipboard, "echo");
+    h.feed(":<C-r>+");
+    assert_eq!(h.editor.cmdline.getcmdline(), "echo");
+}
+
+/// Test: the register type survives a copy and paste through the
+/// clipboard that holds only text
+/// Source: clipboard_spec.lua "supports blockwise operations"
+#[test]
+fn test_register_type_side_channel() {
+    let (mut h, clipboard) = with_clipboard(&["abcd", "efgh", ""]);
+    h.set("clipboard=unnamedplus");
+    h.feed("<C-v>jly");
+    assert_eq!(
+        clipboard.text(Register::Clipboard).as_deref(),
+        Some("ab\nef")
+    );
+    let content = h.editor.registers.fetch(Register::Clipboard).unwrap();
+    assert_eq!(content.reg_type, RegisterType::Blockwise { width: 2 });
+
+    // Text copied by another program is characterwise
+    clipboard.set_text(Register::Clipboard, "ab\nxx");
+    let content = h.editor.registers.fetch(Register::Clipboard).unwrap();
+    assert_eq!(content.reg_type, RegisterType::Characterwise);
+}
+
+/// Test: without a provider "+ and "* are kept in the editor
+/// Source: test_registers.vim "Test_clipboard_regs"
+#[test]
+fn test_no_provider() {
+    let mut h = TestHarness::with_lines(&["word"]);
+    h.feed("\"+yy\"+p");
+    assert_lines!(h, "word", "word");
+    assert!(h.editor.registers.clipboard_provider_name().is_none());
+}
+
+/// A provider whose copy command cannot be run
+#[derive(Debug)]
+struct BrokenClipboard;
+
+impl ClipboardProvider for BrokenClipboard {
+    fn name(&self) -> &str {
+        "broken"
+    }
+
+    fn copy(&mut self, _reg: Register, _text: &str) -> VimResult<()> {
+        Err(VimError::CommandFailed(
+            "clipboard: error invoking xclip".to_string(),
+        ))
+    }
+
+    fn paste(&mut self, _reg: Register) -> VimResult<Option<String>> {
+        Ok(None)
+    }
+}
+
+/// Test: when the provider fails the register still gets the text and
+/// the error is shown
+/// Source: clipboard_spec.lua "clipboard: with a provider"
+#[test]
+fn test_provider_fails() {
+    let mut h = TestHarness::with_lines(&["word"]);
+    h.editor
+        .registers
+        .set_clipboard_provider(Box::new(BrokenClipboard));
+    h.feed("\"+yy");
+    assert!(h
+        .editor
+        .message
+        .as_deref()
+        .unwrap_or("")
+        .contains("clipboard: error invoking xclip"));
+    assert_eq!(
+        h.editor.registers.get(Register::Clipboard).unwrap().text,
+        vec!["word"]
+    );
+    assert_eq!(
+        h.editor.registers.get(Register::Unnamed).unwrap().text,
+        vec!["word"]
+    );
+    h.feed("\"+p");
+    assert_lines!(h, "word", "word");
+}
+
+/// Test: a provider without a command gives an error instead of failing
+/// Source: clipboard_spec.lua "clipboard: with a provider"
+#[test]
+fn test_provider_without_command() {
+    let mut h = TestHarness::with_lines(&["word"]);
+    h.editor
+        .registers
+        .set_clipboard_provider(Box::new(CommandClipboard::new("none", &[], &[])));
+    h.feed("\"+yy");
+    assert!(h
+        .editor
+        .message
+        .as_deref()
+        .unwrap_or("")
+        .contains("clipboard: no command given"));
+    h.feed("\"+p");
+    assert_lines!(h, "word", "word");
+}
+
+/// A provider that drops the trailing newline when pasting, like
+/// `wl-paste --no-newline`
+#[derive(Debug, Clone, Default)]
+struct StrippingClipboard(FakeClipboard);
+
+impl ClipboardProvider for StrippingClipboard {
+    fn name(&self) -> &str {
+        "stripping"
+    }
+
+    fn copy(&mut self, reg: Register, text: &str) -> VimResult<()> {
+        self.0.copy(reg, text)
+    }
+
+    fn paste(&mut self, reg: Register) -> VimResult<Option<String>> {
+        let text = self.0.paste(reg)?;
+        Ok(text.map(|text| text.strip_suffix('\n').unwrap_or(&text).to_string()))
+    }
+}
+
+/// Test: a linewise yank stays linewise when the provider drops the
+/// trailing newline
+/// Source: clipboard_spec.lua "supports blockwise operations"
+#[test]
+fn test_provider_strips_newline() {
+    let mut h = TestHarness::with_lines(&["one", "two"]);
+    h.editor
+        .registers
+        .set_clipboard_provider(Box::new(StrippingClipboard::default()));
+    h.feed("\"+yyj\"+p");
+    assert_lines!(h, "one", "two", "one");
+    let content = h.editor.registers.fetch(Register::Clipboard).unwrap();
+    assert_eq!(content.reg_type, RegisterType::Linewise);
+}
+
+// ============================================================================
+// 'clipboard'
+// ============================================================================
+
+/// Test: with "unnamedplus" yank, delete and put use "+
+/// Source: clipboard_spec.lua "clipboard=unnamedplus"
+#[test]
+fn test_clipboard_unnamedplus() {
+    let (mut h, clipboard) = with_clipboard(&["one", "two"]);
+    h.set("clipboard=unnamedplus");
+    h.feed("yy");
+    assert_eq!(
+        clipboard.text(Register::Clipboard).as_deref(),
+        Some("one\n")
+    );
+    assert!(clipboard.text(Register::Selection).is_none());
+    h.feed("jdd");
+    assert_eq!(
+        clipboard.text(Register::Clipboard).as_deref(),
+        Some("two\n")
+    );
+    // The numbered registers are still filled
+    assert_eq!(
+        h.editor.registers.get(Register::Numbered(0)).unwrap().text,
+        vec!["one"]
+    );
+
+    clipboard.set_text(Register::Clipboard, "pasted\n");
+    h.feed("p");
+    assert_lines!(h, "one", "pasted");
+}
+
+/// Test: with "unnamed" the unnamed register is "*, and a named register
+/// leaves the clipboard alone
+/// Source: clipboard_spec.lua "clipboard=unnamed"
+#[test]
+fn test_clipboard_unnamed() {
+    let (mut h, clipboard) = with_clipboard(&["one two"]);
+    h.set("clipboard=unnamed");
+    h.feed("ye");
+    assert_eq!(clipboard.text(Register::Selection).as_deref(), Some("one"));
+    h.feed("w\"aye");
+    assert_eq!(clipboard.text(Register::Selection).as_deref(), Some("one"));
+    clipboard.set_text(Register::Selection, "SEL");
+    h.feed("0P");
+    assert_lines!(h, "SELone two");
+
+    h.set("clipboard=unnamed,unnamedplus");
+    h.feed("yy");
+    assert_eq!(
+        clipboard.text(Register::Selection).as_deref(),
+        Some("SELone two\n")
+    );
+    assert_eq!(
+        clipboard.text(Register::Clipboard).as_deref(),
+        Some("SELone two\n")
+    );
+}
+
+/// Test: the X11 selection items of 'clipboard' are accepted and ignored
+/// Source: test_options.vim "Test_set_values"
+#[test]
+fn test_clipboard_x11_items() {
+    let (mut h, clipboard) = with_clipboard(&["one"]);
+    h.set("clipboard=autoselect,unnamedplus,html,exclude:cons\\|linux");
+    h.feed("yy");
+    assert_eq!(
+        clipboard.text(Register::Clipboard).as_deref(),
+        Some("one\n")
+    );
+}
+
+// ============================================================================
+// g:clipboard
+// ============================================================================
+
+/// Test: g:clipboard set before the editor has started gives the copy and
+/// paste commands
+/// Source: clipboard_spec.lua "g:clipboard using lists"
+#[test]
+fn test_g_clipboard() {
+    let dir = ScratchDir::new("g-clipboard");
+    let file = dir.join("clip").to_string_lossy().into_owned();
+    let config = format!(
+        "let g:clipboard = {{'name': 'file', 'copy': {{'+': ['sh', '-c', 'cat > {0}']}}, \
+         'paste': {{'+': ['cat', '{0}']}}}}",
+        file
+    );
+    let mut h = TestHarness::with_lines(&["word"]);
+    h.editor
+        .startup(&parse_args(&["--cmd", &config]).unwrap(), None);
+    assert_eq!(
+        h.editor.registers.clipboard_provider_name().as_deref(),
+        Some("file")
+    );
+    h.feed("\"*yy");
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), "word\n");
+    std::fs::write(&file, "other\n").unwrap();
+    h.feed("\"+p");
+    assert_lines!(h, "word", "other");
+}
+
+/// Test: a g:clipboard without the commands gives an error
+/// Source: clipboard_spec.lua "g:clipboard using lists"
+#[test]
+fn test_g_clipboard_invalid() {
+    let mut h = TestHarness::new();
+    let config = "let g:clipboard = {'copy': {'+': []}, 'paste': {}}";
+    h.editor
+        .startup(&parse_args(&["--cmd", config]).unwrap(), None);
+    assert!(h
+        .editor
+        .message
+        .as_deref()
+        .unwrap_or("")
+        .contains("clipboard: invalid g:clipboard"));
+    assert!(h.editor.registers.clipboard_provider_name().is_none());
+}
+
+// ============================================================================
+// OSC 52
+// ============================================================================
+
+/// Output shared between the test and the provider
+#[derive(Clone, Default)]
+struct SharedOutput(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedOutput {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl SharedOutput {
+    fn take(&self) -> String {
+        String::from_utf8(std::mem::take(&mut *self.0.lock().unwrap())).unwrap()
+    }
+}
+
+/// Test: OSC 52 sends the text to the terminal, passed through tmux
+/// when running inside it
+/// Source: clipboard/osc52.lua
+#[test]
+fn test_osc52_copy() {
+    let mut h = TestHarness::with_lines(&["hello"]);
+    let out = SharedOutput::default();
+    h.editor
+        .registers
+        .set_clipboard_provider(Box::new(Osc52Clipboard::with_writer(
+            Box::new(out.clone()),
+            false,
+        )));
+    h.feed("\"+yy");
+    assert_eq!(out.take(), "\x1b]52;c;aGVsbG8K\x07");
+    h.feed("\"*ye");
+    assert_eq!(out.take(), "\x1b]52;p;aGVsbG8=\x07");
+    // The terminal is not read, a put gives what was copied
+    h.feed("\"+p");
+    assert_lines!(h, "hello", "hello");
+
+    h.editor
+        .registers
+        .set_clipboard_provider(Box::new(Osc52Clipboard::with_writer(
+            Box::new(out.clone()),
+            true,
+        )));
+    h.feed("\"+ye");
+    assert_eq!(out.take(), "\x1bPtmux;\x1b\x1b]52;c;aGVsbG8=\x07\x1b\\");
+}
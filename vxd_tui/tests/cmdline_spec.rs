@@ -1,6 +1,9 @@
 //! Cmdline tests ported from Neovim's test/functional/editor/mode_cmdline_spec.lua.
 
-use vxd::cmdline::{Cmdline, CmdlineHistory};
+mod common;
+
+use common::TestHarness;
+use vxd::cmdline::{Cmdline, CmdlineHistory, CmdlineHistoryKind};
 use vxd::cmdline::behavior::{CmdlineBehaviorTests, CmdlineHistoryBehaviorTests};
 use vxd::types::VimResult;
 use vxd_tui::cmdline::TuiCmdline;
@@ -25,6 +28,14 @@ impl Cmdline for CmdlineHarness {
     fn setcmdline(&mut self, text: &str) -> VimResult<()> {
         self.inner.setcmdline(text)
     }
+
+    fn getcmdpos(&self) -> usize {
+        self.inner.getcmdpos()
+    }
+
+    fn setcmdpos(&mut self, pos: usize) -> VimResult<()> {
+        self.inner.setcmdpos(pos)
+    }
 }
 
 impl CmdlineHistory for CmdlineHarness {
@@ -85,8 +96,6 @@ fn test_cmdline_history_behavior() {
 /// Test: history limit evicts oldest entries.
 #[test]
 fn test_cmdline_history_limit_eviction() {
-    use vxd::cmdline::CmdlineHistoryKind;
-
     let mut cmdline = CmdlineHarness::new();
     cmdline.set_history_limit(2);
     assert_eq!(cmdline.history_limit(), 2);
@@ -99,3 +108,118 @@ fn test_cmdline_history_limit_eviction() {
     assert_eq!(cmdline.hist_get(CmdlineHistoryKind::Command, 1), "third");
     assert_eq!(cmdline.hist_get(CmdlineHistoryKind::Command, -1), "third");
 }
+
+// ============================================================================
+// Editing Keys
+// ============================================================================
+
+fn cmdline_state(h: &TestHarness) -> (String, usize) {
+    let cmdline = &h.editor.cmdline;
+    (cmdline.getcmdline().to_string(), cmdline.getcmdpos())
+}
+
+/// Test: characters are inserted at the cursor, which the arrow keys,
+/// Ctrl-B and Ctrl-E move
+/// Source: mode_cmdline_spec.lua, :help c_CTRL-B
+#[test]
+fn test_cursor_movement_and_insert() {
+    let mut h = TestHarness::new();
+    h.feed(":echo<Left><Left>X");
+    assert_eq!(cmdline_state(&h), ("ecXho".to_string(), 4));
+    h.feed("<C-b>\"<C-e>\"");
+    assert_eq!(cmdline_state(&h), ("\"ecXho\"".to_string(), 8));
+    h.feed("<Home><Right><Del>");
+    assert_eq!(cmdline_state(&h), ("\"cXho\"".to_string(), 2));
+}
+
+/// Test: Ctrl-W and Ctrl-U delete before the cursor only
+/// Source: :help c_CTRL-W, :help c_CTRL-U
+#[test]
+fn test_ctrl_w_and_ctrl_u() {
+    let mut h = TestHarness::new();
+    h.feed(":s/foo.bar/baz<C-w>");
+    assert_eq!(cmdline_state(&h).0, "s/foo.bar/");
+    h.feed("<C-w><C-w>");
+    assert_eq!(cmdline_state(&h).0, "s/foo.");
+    h.feed("<Left><Left><C-u>");
+    assert_eq!(cmdline_state(&h), ("o.".to_string(), 1));
+}
+
+/// Test: <BS> only abandons an empty command line
+/// Source: :help c_<BS>
+#[test]
+fn test_backspace_at_start() {
+    let mut h = TestHarness::new();
+    h.feed(":ab<Home><BS>");
+    assert_mode!(h, vxd::modes::Mode::CommandLine(_));
+    h.feed("<End><BS><BS><BS>");
+    assert_mode!(h, vxd::modes::Mode::Normal);
+}
+
+/// Test: Ctrl-R inserts registers, Ctrl-R Ctrl-W the word under the cursor
+/// Source: mode_cmdline_spec.lua (Ctrl-R)
+#[test]
+fn test_ctrl_r_register_and_word() {
+    let mut h = TestHarness::with_lines(&["hello world.txt"]);
+    h.feed("ye");
+    h.feed(":<C-r>\"");
+    assert_eq!(cmdline_state(&h).0, "hello");
+    h.feed("<Esc>w:<C-r><C-w> <C-r><C-a> <C-r><C-l>");
+    assert_eq!(cmdline_state(&h).0, "world world.txt hello world.txt");
+    h.feed("<Esc>:<C-r><C-f>");
+    assert_eq!(cmdline_state(&h).0, "world.txt");
+    h.feed("<Esc>:<C-r>x");
+    assert_eq!(cmdline_state(&h).0, "");
+}
+
+/// Test: Ctrl-V inserts keys and character codes literally
+/// Source: :help c_CTRL-V, :help i_CTRL-V_digit
+#[test]
+fn test_ctrl_v_literal() {
+    let mut h = TestHarness::new();
+    h.feed(":<C-v><Tab><C-v>065<C-v>u20ac<C-v>x41<C-v>9x");
+    assert_eq!(cmdline_state(&h).0, "\tA\u{20ac}A\tx");
+    h.feed("<C-u><C-v><Esc><C-v><C-a>");
+    assert_eq!(cmdline_state(&h).0, "\x1b\x01");
+}
+
+/// Test: Ctrl-K inserts digraphs in either order
+/// Source: :help c_CTRL-K
+#[test]
+fn test_ctrl_k_digraph() {
+    let mut h = TestHarness::new();
+    h.feed(":<C-k>ae<C-k>ea<C-k>zq");
+    assert_eq!(cmdline_state(&h).0, "\u{e6}\u{e6}q");
+}
+
+/// Test: <Up> and <Down> recall history starting with the typed text
+/// Source: :help c_<Up>
+#[test]
+fn test_history_prefix_recall() {
+    let mut h = TestHarness::new();
+    for entry in ["echo 1", "set nu", "echo 2"] {
+        h.editor.cmdline.hist_add(CmdlineHistoryKind::Command, entry);
+    }
+    h.feed(":ec<Up>");
+    assert_eq!(cmdline_state(&h).0, "echo 2");
+    h.feed("<Up>");
+    assert_eq!(cmdline_state(&h).0, "echo 1");
+    h.feed("<Up>");
+    assert_eq!(cmdline_state(&h).0, "echo 1");
+    h.feed("<Down><Down>");
+    assert_eq!(cmdline_state(&h).0, "ec");
+    h.feed("<C-u><C-p><C-p>");
+    assert_eq!(cmdline_state(&h).0, "set nu");
+}
+
+/// Test: search command lines use the search history
+/// Source: :help cmdline-history
+#[test]
+fn test_search_history_recall() {
+    let mut h = TestHarness::with_lines(&["foo bar", "bar foo"]);
+    h.feed("/bar<CR>");
+    h.feed(":<Up>");
+    assert_eq!(cmdline_state(&h).0, "");
+    h.feed("<Esc>/<Up>");
+    assert_eq!(cmdline_state(&h).0, "bar");
+}
@@ -0,0 +1,163 @@
+//! Command-line window tests ported from Vim/Neovim tests
+//!
+//! These tests verify the command-line window opened with `q:`, `q/`, `q?`
+//! and the 'cedit' key: its contents, executing and editing lines from it,
+//! and the E11 restrictions while it is open.
+//!
+//! Source tests:
+//! - src/testdir/test_cmdwin.vim
+
+mod common;
+
+use common::TestHarness;
+use vxd::buffer::{Buffer, BufferManager, BufferType};
+use vxd::cmdline::Cmdline;
+use vxd::modes::Mode;
+use vxd::options::OptionManager;
+use vxd::types::VimError;
+
+fn tabstop(h: &TestHarness) -> i64 {
+    h.editor.options.get_number("tabstop").unwrap()
+}
+
+// ============================================================================
+// Opening the Window
+// ============================================================================
+
+/// Test: q: shows the command history with an empty line to type in
+/// Source: test_cmdwin.vim "Test_cmdwin_bug"
+#[test]
+fn test_cmdwin_shows_history() {
+    let mut h = TestHarness::with_lines(&["text"]);
+    h.feed(":set ts=4<CR>:set ts=6<CR>q:");
+    assert_eq!(h.editor.cmdwin_type(), Some(':'));
+    assert_lines!(h, "set ts=4", "set ts=6", "");
+    assert_cursor!(h, 3, 0);
+    assert_mode!(h, Mode::Normal);
+    let buffer = h.editor.buffers.current();
+    assert_eq!(buffer.buftype(), BufferType::Nofile);
+    assert_eq!(buffer.name(), "[Command Line]");
+}
+
+/// Test: q/ uses the search history
+/// Source: :help q/
+#[test]
+fn test_cmdwin_search_history() {
+    let mut h = TestHarness::with_lines(&["foo", "bar", "foo"]);
+    h.feed("/bar<CR>q/");
+    assert_eq!(h.editor.cmdwin_type(), Some('/'));
+    assert_lines!(h, "bar", "");
+    h.feed("ifoo<CR>");
+    assert_eq!(h.editor.cmdwin_type(), None);
+    assert_lines!(h, "foo", "bar", "foo");
+    assert_cursor!(h, 3, 0);
+}
+
+/// Test: the 'cedit' key opens the window with the typed command line
+/// Source: test_cmdwin.vim "Test_cmdwin_cedit"
+#[test]
+fn test_cedit_opens_window_with_cmdline() {
+    let mut h = TestHarness::new();
+    h.feed(":set ts=3<C-f>");
+    assert_eq!(h.editor.cmdwin_type(), Some(':'));
+    assert_lines!(h, "set ts=3");
+    h.feed("<CR>");
+    assert_eq!(tabstop(&h), 3);
+
+    h.set("cedit=<C-y>");
+    h.feed(":abc<C-f>");
+    assert_eq!(h.editor.cmdwin_type(), None);
+    assert_eq!(h.editor.cmdline.getcmdline(), "abc");
+    h.feed("<C-y>");
+    assert_eq!(h.editor.cmdwin_type(), Some(':'));
+}
+
+// ============================================================================
+// Executing and Editing
+// ============================================================================
+
+/// Test: <CR> executes the line under the cursor in the previous buffer
+/// Source: :help cmdwin-char
+#[test]
+fn test_enter_executes_line_under_cursor() {
+    let mut h = TestHarness::with_lines(&["one", "two"]);
+    h.feed(":set ts=4<CR>:set ts=6<CR>");
+    h.set_cursor(2, 1);
+    h.feed("q:gg<CR>");
+    assert_eq!(h.editor.cmdwin_type(), None);
+    assert_eq!(tabstop(&h), 4);
+    assert_lines!(h, "one", "two");
+    assert_cursor!(h, 2, 1);
+    assert_eq!(h.editor.buffers.list().len(), 1);
+}
+
+/// Test: lines are edited with Normal and Insert mode commands
+/// Source: test_cmdwin.vim
+#[test]
+fn test_edit_line_before_executing() {
+    let mut h = TestHarness::new();
+    h.feed(":set ts=4<CR>q:k$xa7<Esc><CR>");
+    assert_eq!(tabstop(&h), 7);
+    h.feed("q:iset ts=5<CR>");
+    assert_eq!(tabstop(&h), 5);
+}
+
+/// Test: Ctrl-C continues with the line under the cursor on the command line
+/// Source: :help cmdwin-char
+#[test]
+fn test_ctrl_c_returns_to_cmdline() {
+    let mut h = TestHarness::new();
+    h.feed(":set ts=4<CR>q:k<C-c>");
+    assert_eq!(h.editor.cmdwin_type(), None);
+    assert_mode!(h, Mode::CommandLine(_));
+    assert_eq!(h.editor.cmdline.getcmdline(), "set ts=4");
+    h.feed("<BS>2<CR>");
+    assert_eq!(tabstop(&h), 2);
+}
+
+/// Test: :quit closes the window without executing anything
+/// Source: :help cmdwin-char
+#[test]
+fn test_quit_closes_window() {
+    let mut h = TestHarness::with_lines(&["text"]);
+    h.feed("q::q<CR>");
+    assert_eq!(h.editor.cmdwin_type(), None);
+    assert!(!h.editor.quit_requested);
+    assert_lines!(h, "text");
+}
+
+// ============================================================================
+// Restrictions and Events
+// ============================================================================
+
+/// Test: other buffers and command-line windows cannot be opened
+/// Source: test_cmdwin.vim "Test_cmdwin_restore"
+#[test]
+fn test_e11_in_cmdwin() {
+    let mut h = TestHarness::with_lines(&["text"]);
+    let previous = h.editor.buffers.current().handle();
+    h.feed("q:");
+    let err = h.editor.switch_buffer(previous).unwrap_err();
+    assert!(matches!(err, VimError::Error(11, _)));
+    assert_eq!(
+        err.to_string(),
+        "E11: Invalid in command-line window; <CR> executes, CTRL-C quits"
+    );
+    h.feed("q/");
+    assert_eq!(h.editor.cmdwin_type(), Some(':'));
+    assert!(h.editor.message.as_deref().unwrap().starts_with("E11"));
+}
+
+/// Test: CmdwinEnter and CmdwinLeave fire with the window type
+/// Source: test_cmdwin.vim "Test_cmdwin_autocmd"
+#[test]
+fn test_cmdwin_autocmds() {
+    let mut h = TestHarness::new();
+    h.feed(":autocmd CmdwinEnter : set ts=3<CR>");
+    h.feed(":autocmd CmdwinLeave * set sw=5<CR>");
+    h.feed("q/");
+    assert_eq!(tabstop(&h), 8);
+    h.feed("<C-c><Esc>q:");
+    assert_eq!(tabstop(&h), 3);
+    assert_eq!(h.editor.options.get_number("shiftwidth"), Some(5));
+}
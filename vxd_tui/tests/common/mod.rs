@@ -3,11 +3,14 @@
 //! This module provides a test harness similar to Neovim's functional testing framework,
 //! allowing tests to simulate user input and verify buffer/cursor state.
 
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
 use vxd::buffer::{Buffer, BufferManager};
 use vxd::cursor::Cursor;
-use vxd::modes::{Mode, ModeManager, VisualMode};
-use vxd::motions::CharFindMotion;
-use vxd::registers::{Register, RegisterBank};
+use vxd::eval::Value;
+use vxd::modes::Mode;
 use vxd::types::LineNr;
 use vxd_tui::editor::Editor;
 use vxd_tui::input::InputHandler;
@@ -17,8 +20,6 @@ use vxd_tui::key::{Key, parse_keys};
 pub struct TestHarness {
     pub editor: Editor,
     pub input_handler: InputHandler,
-    pending_g: bool,
-    pending_char_find: Option<PendingCharFind>,
 }
 
 #[allow(dead_code)]
@@ -28,8 +29,6 @@ impl TestHarness {
         TestHarness {
             editor: Editor::new(),
             input_handler: InputHandler::new(),
-            pending_g: false,
-            pending_char_find: None,
         }
     }
 
@@ -126,257 +125,31 @@ impl TestHarness {
         let parsed = parse_keys(keys);
         for key in parsed {
             // Process via input handler for mappings
-            let mapped_keys = self.input_handler.handle_key(key, &self.editor);
+            let mapped_keys = self.input_handler.handle_key(key, &mut self.editor);
             for k in mapped_keys {
                 self.process_key(k);
             }
         }
     }
 
-    /// Process a single key (internal, post-mapping).
-    fn process_key(&mut self, key: Key) {
-        match self.editor.mode() {
-            Mode::Normal => self.process_normal_key(key),
-            Mode::Insert | Mode::Replace => self.process_insert_key(key),
-            Mode::Visual(_) => self.process_visual_key(key),
-            _ => {}
-        }
+    /// The message shown, or "" when there is none.
+    pub fn message(&self) -> &str {
+        self.editor.message.as_deref().unwrap_or("")
     }
 
-    fn process_normal_key(&mut self, key: Key) {
-        if self.pending_g && !matches!(key, Key::Char('g')) {
-            self.pending_g = false;
-        }
-        if let Some(pending) = self.pending_char_find.take() {
-            if let Key::Char(target) = key {
-                let motion = match pending {
-                    PendingCharFind::FindForward => CharFindMotion::FindForward(target),
-                    PendingCharFind::FindBackward => CharFindMotion::FindBackward(target),
-                    PendingCharFind::TillForward => CharFindMotion::TillForward(target),
-                    PendingCharFind::TillBackward => CharFindMotion::TillBackward(target),
-                };
-                let _ = self.editor.find_char(motion);
-            }
-            return;
-        }
-        match key {
-            Key::Char('i') => {
-                let _ = self.editor.enter_insert();
-            }
-            Key::Char('a') => {
-                let _ = self.editor.cursor_right(1);
-                let _ = self.editor.enter_insert();
-            }
-            Key::Char('I') => {
-                let ctx = self.editor.cursor_context();
-                let _ = self.editor.cursor.set_col(0, &ctx);
-                let _ = self.editor.enter_insert();
-            }
-            Key::Char('A') => {
-                let line_len = self.editor.current_line().len();
-                let _ = self.editor.enter_insert();
-                let ctx = self.editor.cursor_context();
-                let _ = self.editor.cursor.set_col(line_len, &ctx);
-            }
-            Key::Char('o') => {
-                let line = self.editor.cursor.line().0 as i64;
-                let _ = self.editor.buffers.current_mut().set_lines(
-                    line,
-                    line,
-                    false,
-                    vec!["".to_string()],
-                );
-                self.editor.sync_cursor_with_buffer();
-                let _ = self.editor.cursor_down(1);
-                let ctx = self.editor.cursor_context();
-                let _ = self.editor.cursor.set_col(0, &ctx);
-                let _ = self.editor.enter_insert();
-            }
-            Key::Char('O') => {
-                let line = self.editor.cursor.line().0 as i64 - 1;
-                let _ = self.editor.buffers.current_mut().set_lines(
-                    line,
-                    line,
-                    false,
-                    vec!["".to_string()],
-                );
-                self.editor.sync_cursor_with_buffer();
-                let ctx = self.editor.cursor_context();
-                let _ = self.editor.cursor.set_col(0, &ctx);
-                let _ = self.editor.enter_insert();
-            }
-            Key::Char('v') => {
-                let _ = self.editor.enter_visual();
-            }
-            Key::Char('V') => {
-                let _ = self.editor.enter_visual_line();
-            }
-            Key::Ctrl('v') => {
-                let _ = self.editor.enter_visual_block();
-            }
-            Key::Char('h') | Key::Left => {
-                let _ = self.editor.cursor_left(1);
-            }
-            Key::Char('j') | Key::Down => {
-                let _ = self.editor.cursor_down(1);
-            }
-            Key::Char('k') | Key::Up => {
-                let _ = self.editor.cursor_up(1);
-            }
-            Key::Char('l') | Key::Right => {
-                let _ = self.editor.cursor_right(1);
-            }
-            Key::Char('0') => {
-                let ctx = self.editor.cursor_context();
-                let _ = self.editor.cursor.set_col(0, &ctx);
-                self.editor.cursor.update_curswant();
-            }
-            Key::Char('$') => {
-                let line_len = self.editor.current_line().len();
-                let ctx = self.editor.cursor_context();
-                let col = if line_len > 0 { line_len - 1 } else { 0 };
-                let _ = self.editor.cursor.set_col(col, &ctx);
-                self.editor.cursor.set_curswant_eol();
-            }
-            Key::Char('x') => {
-                let _ = self.editor.delete_char();
-            }
-            Key::Char('G') => {
-                let line_count = self.editor.buffers.current().line_count();
-                let ctx = self.editor.cursor_context();
-                let _ = self.editor.cursor.set_line(LineNr(line_count), &ctx);
-            }
-            Key::Char('g') => {
-                if self.pending_g {
-                    self.pending_g = false;
-                    let ctx = self.editor.cursor_context();
-                    let _ = self.editor.cursor.set_line(LineNr(1), &ctx);
-                } else {
-                    self.pending_g = true;
-                }
-            }
-            Key::Char('f') => {
-                self.pending_char_find = Some(PendingCharFind::FindForward);
-            }
-            Key::Char('F') => {
-                self.pending_char_find = Some(PendingCharFind::FindBackward);
-            }
-            Key::Char('t') => {
-                self.pending_char_find = Some(PendingCharFind::TillForward);
-            }
-            Key::Char('T') => {
-                self.pending_char_find = Some(PendingCharFind::TillBackward);
-            }
-            Key::Char(';') => {
-                let _ = self.editor.find_char(CharFindMotion::RepeatForward);
-            }
-            Key::Char(',') => {
-                let _ = self.editor.find_char(CharFindMotion::RepeatBackward);
-            }
-            Key::Char('R') => {
-                let _ = self.editor.enter_replace();
-            }
-            Key::Char('p') => {
-                let _ = self.editor.put_register(Register::Unnamed, true);
-            }
-            Key::Char('P') => {
-                let _ = self.editor.put_register(Register::Unnamed, false);
-            }
-            Key::Char('%') => {
-                let _ = self.editor.match_bracket();
-            }
-            _ => {}
-        }
+    /// Set options as with `:set {arg}`.
+    pub fn set(&mut self, arg: &str) {
+        self.editor.set_option(arg).unwrap();
     }
 
-    fn process_insert_key(&mut self, key: Key) {
-        match key {
-            Key::Escape => {
-                let _ = self.editor.escape();
-            }
-            Key::Ctrl('a') => {
-                if let Some(content) = self.editor.registers.get(Register::LastInserted) {
-                    let _ = self.editor.insert_text(&content.as_string());
-                }
-            }
-            Key::Ctrl('y') => {
-                let _ = self.editor.insert_from_adjacent_line(-1);
-            }
-            Key::Ctrl('e') => {
-                let _ = self.editor.insert_from_adjacent_line(1);
-            }
-            Key::Char(c) => {
-                let _ = self.editor.insert_char(c);
-            }
-            Key::Backspace => {
-                let col = self.editor.cursor.col();
-                if col > 0 {
-                    let _ = self.editor.cursor_left(1);
-                    let _ = self.editor.delete_char();
-                }
-            }
-            Key::Enter => {
-                let _ = self.editor.insert_newline();
-            }
-            Key::Left => {
-                let _ = self.editor.cursor_left(1);
-            }
-            Key::Right => {
-                let _ = self.editor.cursor_right(1);
-            }
-            Key::Up => {
-                let _ = self.editor.cursor_up(1);
-            }
-            Key::Down => {
-                let _ = self.editor.cursor_down(1);
-            }
-            _ => {}
-        }
+    /// Evaluate the expression `expr`.
+    pub fn eval(&mut self, expr: &str) -> Value {
+        self.editor.eval_expression(expr).unwrap()
     }
 
-    fn process_visual_key(&mut self, key: Key) {
-        match key {
-            Key::Escape => {
-                self.editor.escape().unwrap();
-            }
-            Key::Char('v') => {
-                self.editor.modes.transition_to(Mode::Normal).unwrap();
-            }
-            Key::Char('V') => {
-                self.editor.modes.transition_to(Mode::Visual(VisualMode::Line)).unwrap();
-            }
-            Key::Ctrl('v') => {
-                self.editor.modes.transition_to(Mode::Visual(VisualMode::Block)).unwrap();
-            }
-            Key::Char('x') | Key::Char('d') => {
-                self.editor.visual_delete().unwrap();
-            }
-            Key::Char('c') => {
-                self.editor.visual_change().unwrap();
-            }
-            Key::Char('I') => {
-                self.editor.visual_insert().unwrap();
-            }
-            Key::Char('A') => {
-                self.editor.visual_append().unwrap();
-            }
-            Key::Char('y') => {
-                self.editor.visual_yank().unwrap();
-            }
-            Key::Char('h') | Key::Left => {
-                self.editor.cursor_left(1).unwrap();
-            }
-            Key::Char('j') | Key::Down => {
-                self.editor.cursor_down(1).unwrap();
-            }
-            Key::Char('k') | Key::Up => {
-                self.editor.cursor_up(1).unwrap();
-            }
-            Key::Char('l') | Key::Right => {
-                self.editor.cursor_right(1).unwrap();
-            }
-            _ => {}
-        }
+    /// Process a single key (internal, post-mapping).
+    fn process_key(&mut self, key: Key) {
+        let _ = self.editor.handle_key(key);
     }
 }
 impl Default for TestHarness {
@@ -385,12 +158,50 @@ impl Default for TestHarness {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum PendingCharFind {
-    FindForward,
-    FindBackward,
-    TillForward,
-    TillBackward,
+/// A directory for the files of a test, removed with everything in it
+/// when dropped.
+pub struct ScratchDir {
+    path: PathBuf,
+}
+
+#[allow(dead_code)]
+impl ScratchDir {
+    /// Create an empty directory, named after `name` and the test process.
+    pub fn new(name: &str) -> Self {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "vxd_{}_{}_{}",
+            name,
+            std::process::id(),
+            COUNT.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        ScratchDir { path }
+    }
+
+    /// Write `text` to the file `name` in the directory, creating the
+    /// directories it is in, and return its path.
+    pub fn file(&self, name: &str, text: &str) -> String {
+        let path = self.path.join(name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, text).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+}
+
+impl Deref for ScratchDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
 }
 
 fn normalize_line_range(start: i64, end: i64, line_count: usize) -> (usize, usize) {
@@ -0,0 +1,467 @@
+//! Insert mode completion tests ported from Vim/Neovim tests
+//!
+//! These tests verify `Ctrl-N`/`Ctrl-P` keyword completion, the `Ctrl-X`
+//! sub-modes, the 'complete' and 'completeopt' options and the
+//! CompleteChanged/CompleteDone events.
+//!
+//! Source tests:
+//! - test/functional/editor/completion_spec.lua
+//! - src/testdir/test_ins_complete.vim
+
+mod common;
+
+use common::{ScratchDir, TestHarness};
+use vxd::buffer::{Buffer, BufferManager};
+use vxd::completion::{CompletionEngine, CompletionItem};
+use vxd::modes::Mode;
+use vxd::options::OptionManager;
+use vxd::registers::RegisterBank;
+
+// ============================================================================
+// Keyword Completion
+// ============================================================================
+
+/// Test: Ctrl-N completes the next matching keyword
+/// Source: test_ins_complete.vim
+#[test]
+fn test_ctrl_n_completes_keyword() {
+    let mut h = TestHarness::with_lines(&["foobar", ""]);
+    h.set_cursor(2, 0);
+    h.feed("ifo<C-n><Esc>");
+    assert_lines!(h, "foobar", "foobar");
+    assert_mode!(h, Mode::Normal);
+}
+
+/// Test: repeated Ctrl-N cycles through matches and back to the typed text
+/// Source: :help ins-completion
+#[test]
+fn test_ctrl_n_cycles_back_to_original() {
+    let mut h = TestHarness::with_lines(&["", "food fool"]);
+    h.feed("ifo<C-n>");
+    assert_eq!(h.get_lines()[0], "food");
+    h.feed("<C-n>");
+    assert_eq!(h.get_lines()[0], "fool");
+    h.feed("<C-n>");
+    assert_eq!(h.get_lines()[0], "fo");
+    assert_eq!(
+        h.editor.message.as_deref(),
+        Some("-- Keyword completion (^N^P) Back at original")
+    );
+    h.feed("<C-n><Esc>");
+    assert_lines!(h, "food", "food fool");
+}
+
+/// Test: Ctrl-P completes the nearest keyword before the cursor
+/// Source: :help i_CTRL-P
+#[test]
+fn test_ctrl_p_finds_previous_match() {
+    let mut h = TestHarness::with_lines(&["alpha", "altitude", "", "alto"]);
+    h.set_cursor(3, 0);
+    h.feed("ial<C-p>");
+    assert_eq!(h.get_lines()[2], "altitude");
+    h.feed("<C-p>");
+    assert_eq!(h.get_lines()[2], "alpha");
+    h.feed("<C-p><Esc>");
+    assert_lines!(h, "alpha", "altitude", "alto", "alto");
+}
+
+/// Test: no match reports "Pattern not found" and inserts nothing
+/// Source: test_ins_complete.vim
+#[test]
+fn test_completion_pattern_not_found() {
+    let mut h = TestHarness::with_lines(&["abc"]);
+    h.feed("Ozz<C-n>");
+    assert_eq!(
+        h.editor.message.as_deref(),
+        Some("-- Keyword completion (^N^P) Pattern not found")
+    );
+    h.feed("x<Esc>");
+    assert_lines!(h, "zzx", "abc");
+}
+
+/// Test: typing after a match ends completion and keeps the match
+/// Source: :help popupmenu-keys
+#[test]
+fn test_typing_after_match_keeps_it() {
+    let mut h = TestHarness::with_lines(&["", "variable"]);
+    h.feed("iva<C-n> = 1<Esc>");
+    assert_lines!(h, "variable = 1", "variable");
+    assert!(!h.editor.completion_active());
+}
+
+/// Test: Ctrl-E ends completion and restores the typed text
+/// Source: :help complete_CTRL-E
+#[test]
+fn test_ctrl_e_restores_typed_text() {
+    let mut h = TestHarness::with_lines(&["", "testing"]);
+    h.feed("ite<C-n>");
+    assert_eq!(h.get_lines()[0], "testing");
+    h.feed("<C-e>");
+    assert_eq!(h.get_lines()[0], "te");
+    assert!(!h.editor.completion_active());
+    h.feed("<Esc>");
+    assert_mode!(h, Mode::Normal);
+}
+
+/// Test: the completed text is part of the inserted text for "."
+/// Source: :help ins-completion
+#[test]
+fn test_completed_text_in_last_insert() {
+    let mut h = TestHarness::with_lines(&["", "keyword"]);
+    h.feed("ike<C-n><Esc>");
+    assert_eq!(
+        h.editor
+            .registers
+            .get(vxd::registers::Register::LastInserted)
+            .map(|content| content.as_string()),
+        Some("keyword".to_string())
+    );
+}
+
+// ============================================================================
+// The 'complete' Option
+// ============================================================================
+
+/// Test: keywords from other listed buffers are found with "b"
+/// Source: :help 'complete'
+#[test]
+fn test_complete_scans_other_buffers() {
+    let mut h = TestHarness::with_lines(&[""]);
+    let other = h.editor.buffers.create_named("other.txt").unwrap();
+    h.editor
+        .buffers
+        .get_mut(other)
+        .unwrap()
+        .set_lines(0, -1, false, vec!["elsewhere".to_string()])
+        .unwrap();
+    h.set("complete=.");
+    h.feed("iel<C-n>");
+    assert_eq!(h.get_lines()[0], "el");
+    h.feed("<Esc>");
+
+    h.set("complete&");
+    h.feed("a<C-n>");
+    assert_eq!(h.get_lines()[0], "elsewhere");
+    assert_eq!(
+        h.editor
+            .completion
+            .selected_item()
+            .and_then(|item| item.menu.as_deref()),
+        Some("other.txt")
+    );
+}
+
+/// Test: Ctrl-X Ctrl-N only searches the current buffer
+/// Source: :help i_CTRL-X_CTRL-N
+#[test]
+fn test_ctrl_x_ctrl_n_current_buffer_only() {
+    let mut h = TestHarness::with_lines(&["", "local_word"]);
+    let other = h.editor.buffers.create().unwrap();
+    h.editor
+        .buffers
+        .get_mut(other)
+        .unwrap()
+        .set_lines(0, -1, false, vec!["local_other".to_string()])
+        .unwrap();
+    h.feed("ilo<C-x><C-n>");
+    assert_eq!(h.get_lines()[0], "local_word");
+    assert_eq!(
+        h.editor.message.as_deref(),
+        Some("-- Keyword Local completion (^N^P) The only match")
+    );
+}
+
+/// Test: "k" and "s" scan the dictionary and thesaurus files
+/// Source: :help 'complete'
+#[test]
+fn test_complete_dictionary_item() {
+    let dir = ScratchDir::new("complete_k");
+    let dict = dir.file("words", "zebra\nzeppelin\n");
+    let mut h = TestHarness::with_lines(&[""]);
+    h.set(&format!("complete=.,k{}", dict));
+    h.feed("izep<C-n><Esc>");
+    assert_lines!(h, "zeppelin");
+}
+
+// ============================================================================
+// Ctrl-X Sub-modes
+// ============================================================================
+
+/// Test: Ctrl-X Ctrl-L completes whole lines, searching backwards
+/// Source: :help i_CTRL-X_CTRL-L
+#[test]
+fn test_ctrl_x_ctrl_l_whole_line() {
+    let mut h = TestHarness::with_lines(&["if (x) {", "  if (y) {", ""]);
+    h.set_cursor(3, 0);
+    h.feed("a  if<C-x><C-l>");
+    assert_eq!(h.get_lines()[2], "  if (y) {");
+    h.feed("<C-l>");
+    assert_eq!(h.get_lines()[2], "  if (x) {");
+    h.feed("<Esc>");
+    assert_mode!(h, Mode::Normal);
+}
+
+/// Test: Ctrl-X Ctrl-F completes file names
+/// Source: :help i_CTRL-X_CTRL-F
+#[test]
+fn test_ctrl_x_ctrl_f_file_names() {
+    let dir = ScratchDir::new("files");
+    dir.file("readme.txt", "");
+    std::fs::create_dir(dir.join("render")).unwrap();
+    dir.file(".hidden", "");
+    let mut h = TestHarness::new();
+    let base = format!("{}/re", dir.display());
+    h.feed(&format!("i{}<C-x><C-f>", base));
+    assert_eq!(h.get_lines()[0], format!("{}/readme.txt", dir.display()));
+    h.feed("<C-n>");
+    assert_eq!(h.get_lines()[0], format!("{}/render/", dir.display()));
+    assert_eq!(h.editor.completion.state().items.len(), 2);
+}
+
+/// Test: Ctrl-X Ctrl-K completes from 'dictionary'
+/// Source: :help i_CTRL-X_CTRL-K
+#[test]
+fn test_ctrl_x_ctrl_k_dictionary() {
+    let dir = ScratchDir::new("dictionary");
+    let dict = dir.file("words", "apple\napricot\nbanana\n");
+    let mut h = TestHarness::new();
+    h.set(&format!("dictionary={}", dict));
+    h.feed("iap<C-x><C-k>");
+    assert_eq!(h.get_lines()[0], "apple");
+    h.feed("<C-k><Esc>");
+    assert_lines!(h, "apricot");
+
+    let mut h = TestHarness::new();
+    h.feed("iap<C-x><C-k>");
+    assert_eq!(
+        h.editor.message.as_deref(),
+        Some("-- Dictionary completion (^K^N^P) Pattern not found")
+    );
+}
+
+/// Test: Ctrl-X Ctrl-T offers the other words of matching thesaurus lines
+/// Source: :help i_CTRL-X_CTRL-T
+#[test]
+fn test_ctrl_x_ctrl_t_thesaurus() {
+    let dir = ScratchDir::new("thesaurus");
+    let file = dir.file("thesaurus", "angry furious mad\nhappy glad\n");
+    let mut h = TestHarness::new();
+    h.set(&format!("thesaurus={}", file));
+    h.feed("iangry<C-x><C-t>");
+    assert_eq!(h.get_lines()[0], "furious");
+    h.feed("<C-n><Esc>");
+    assert_lines!(h, "mad");
+}
+
+/// Test: Ctrl-X Ctrl-I completes keywords from included files
+/// Source: :help i_CTRL-X_CTRL-I
+#[test]
+fn test_ctrl_x_ctrl_i_included_files() {
+    let dir = ScratchDir::new("include");
+    dir.file("defs.h", "int value_from_header;\n");
+    let mut h = TestHarness::with_lines(&["#include \"defs.h\"", ""]);
+    h.set(&format!("path={}", dir.display()));
+    h.set_cursor(2, 0);
+    h.feed("ival<C-x><C-i><Esc>");
+    assert_lines!(h, "#include \"defs.h\"", "value_from_header");
+}
+
+/// Test: Ctrl-X Ctrl-] completes tag names from 'tags'
+/// Source: :help i_CTRL-X_CTRL-]
+#[test]
+fn test_ctrl_x_ctrl_bracket_tags() {
+    let dir = ScratchDir::new("tags");
+    let tags = dir.file(
+        "tags",
+        "!_TAG_FILE_SORTED\t1\t//\nparse_args\tmain.c\t/^int parse_args(/;\"\tf\nparse_line\tio.c\t10;\"\tkind:f\n",
+    );
+    let mut h = TestHarness::new();
+    h.set(&format!("tags={}", tags));
+    h.feed("ipar<C-x><C-]>");
+    assert_eq!(h.get_lines()[0], "parse_args");
+    let item = h.editor.completion.selected_item().unwrap();
+    assert_eq!(item.menu.as_deref(), Some("main.c"));
+    assert_eq!(item.kind.as_deref(), Some("f"));
+    h.feed("<C-]>");
+    assert_eq!(h.get_lines()[0], "parse_line");
+}
+
+fn month_complete(line: &str, col: usize) -> Option<(usize, Vec<CompletionItem>)> {
+    let start = line[..col].rfind(' ').map_or(0, |idx| idx + 1);
+    let base = &line[start..col];
+    let items = ["January", "June", "July"]
+        .iter()
+        .filter(|month| month.starts_with(base))
+        .map(|month| CompletionItem::new(*month).with_menu("month"))
+        .collect();
+    Some((start, items))
+}
+
+/// Test: Ctrl-X Ctrl-U calls 'completefunc'
+/// Source: :help complete-functions
+#[test]
+fn test_ctrl_x_ctrl_u_user_function() {
+    let mut h = TestHarness::new();
+    h.feed("iJu<C-x><C-u>");
+    assert_eq!(
+        h.editor.message.as_deref(),
+        Some("E764: Option 'completefunc' is not set")
+    );
+
+    let mut h = TestHarness::new();
+    h.editor
+        .register_complete_func("MonthComplete", month_complete);
+    h.set("completefunc=MonthComplete");
+    h.feed("iJu<C-x><C-u>");
+    assert_eq!(h.get_lines()[0], "June");
+    h.feed("<C-u><Esc>");
+    assert_lines!(h, "July");
+}
+
+/// Test: Ctrl-X Ctrl-O calls 'omnifunc'
+/// Source: :help compl-omni
+#[test]
+fn test_ctrl_x_ctrl_o_omni_function() {
+    let mut h = TestHarness::new();
+    h.editor
+        .register_complete_func("MonthComplete", month_complete);
+    h.set("omnifunc=MonthComplete");
+    h.feed("iin Jan<C-x><C-o><Esc>");
+    assert_lines!(h, "in January");
+
+    h.set("omnifunc=Missing");
+    h.feed("o<C-x><C-o>");
+    assert_eq!(
+        h.editor.message.as_deref(),
+        Some("E117: Unknown function: Missing")
+    );
+}
+
+/// Test: Ctrl-X s suggests spelling corrections for the word before the cursor
+/// Source: :help i_CTRL-X_s
+#[test]
+fn test_ctrl_x_s_spelling() {
+    let dir = ScratchDir::new("spell");
+    let words = dir.file("en.add", "receive\nrecipe\nbelieve\n");
+    let mut h = TestHarness::new();
+    h.set(&format!("spellfile={}", words));
+    h.feed("irecieve<C-x>s<Esc>");
+    assert_lines!(h, "receive");
+}
+
+/// Test: an unknown key after Ctrl-X is used as usual
+/// Source: :help i_CTRL-X
+#[test]
+fn test_ctrl_x_other_key_inserted() {
+    let mut h = TestHarness::new();
+    h.feed("ia<C-x>");
+    assert_eq!(
+        h.editor.message.as_deref(),
+        Some("-- ^X mode (^]^D^E^F^I^K^L^N^O^Ps^U^V^Y)")
+    );
+    h.feed("b<Esc>");
+    assert_lines!(h, "ab");
+}
+
+// ============================================================================
+// The 'completeopt' Option
+// ============================================================================
+
+/// Test: "longest" inserts the longest common text only
+/// Source: :help 'completeopt'
+#[test]
+fn test_completeopt_longest() {
+    let mut h = TestHarness::with_lines(&["", "function functor"]);
+    h.set("completeopt=menu,longest");
+    h.feed("ifu<C-n>");
+    assert_eq!(h.get_lines()[0], "funct");
+    assert!(h.editor.completion.selected_item().is_none());
+    h.feed("o");
+    assert_eq!(h.editor.completion.state().items.len(), 1);
+    h.feed("<C-n><Esc>");
+    assert_lines!(h, "functor", "function functor");
+}
+
+/// Test: "noselect" inserts nothing until a match is selected
+/// Source: :help 'completeopt'
+#[test]
+fn test_completeopt_noselect() {
+    let mut h = TestHarness::with_lines(&["", "buffer buffered"]);
+    h.set("completeopt=menu,noselect");
+    h.feed("ibu<C-n>");
+    assert_eq!(h.get_lines()[0], "bu");
+    assert!(h.editor.pum_visible());
+    h.feed("<C-n>");
+    assert_eq!(h.get_lines()[0], "buffer");
+}
+
+/// Test: "noinsert" selects the first match without inserting it; typing
+/// narrows the matches and Ctrl-Y inserts the selection
+/// Source: :help 'completeopt'
+#[test]
+fn test_completeopt_noinsert() {
+    let mut h = TestHarness::with_lines(&["", "reader render"]);
+    h.set("completeopt=menu,noinsert");
+    h.feed("ire<C-n>");
+    assert_eq!(h.get_lines()[0], "re");
+    assert_eq!(h.editor.completion.selected_item().unwrap().word, "reader");
+    h.feed("n");
+    assert_eq!(h.get_lines()[0], "ren");
+    assert_eq!(h.editor.completion.selected_item().unwrap().word, "render");
+    h.feed("<C-y>");
+    assert_eq!(h.get_lines()[0], "render");
+    assert_eq!(h.editor.completed_item().unwrap().word, "render");
+}
+
+/// Test: the menu is shown for several matches, or one with "menuone"
+/// Source: :help 'completeopt'
+#[test]
+fn test_completeopt_menu_and_menuone() {
+    let mut h = TestHarness::with_lines(&["", "single"]);
+    h.feed("isi<C-n>");
+    assert!(h.editor.completion_active());
+    assert!(!h.editor.pum_visible());
+    h.feed("<C-e>");
+
+    h.set("completeopt=menuone");
+    h.feed("<C-n>");
+    assert!(h.editor.pum_visible());
+    assert_eq!(h.editor.options.get_string("completeopt"), Some("menuone"));
+}
+
+// ============================================================================
+// Completion Events
+// ============================================================================
+
+/// Test: CompleteDone fires when completion ends and sets v:completed_item
+/// Source: :help CompleteDone
+#[test]
+fn test_complete_done_event() {
+    let mut h = TestHarness::with_lines(&["", "alphabet"]);
+    h.feed(":autocmd CompleteDone * set ts=3<CR>");
+    h.feed("ial<C-n>");
+    assert_eq!(h.editor.options.number("tabstop"), 8);
+    h.feed("<C-y>");
+    assert_eq!(h.editor.options.number("tabstop"), 3);
+    assert_eq!(h.editor.completed_item().unwrap().word, "alphabet");
+
+    h.feed(" al<C-n><C-e>");
+    assert!(h.editor.completed_item().is_none());
+}
+
+/// Test: CompleteChanged fires while the popup menu is visible
+/// Source: :help CompleteChanged
+#[test]
+fn test_complete_changed_event() {
+    let mut h = TestHarness::with_lines(&["", "one only"]);
+    h.feed(":au CompleteChanged * set sw+=1<CR>");
+    h.feed("io<C-n>");
+    assert_eq!(h.editor.options.number("shiftwidth"), 9);
+    h.feed("<C-n>");
+    assert_eq!(h.editor.options.number("shiftwidth"), 10);
+    h.feed("<Esc>");
+
+    // No popup menu for a single match with the default 'completeopt'
+    h.feed("Gowor<C-n>");
+    assert_eq!(h.editor.options.number("shiftwidth"), 10);
+}
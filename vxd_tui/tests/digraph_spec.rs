@@ -0,0 +1,165 @@
+//! Digraph tests ported from Vim tests
+//!
+//! These tests verify digraphs and character inspection:
+//! - `Ctrl-K {char1}{char2}` in Insert and Command-line mode
+//! - `{char1}<BS>{char2}` with the 'digraph' option set
+//! - `:digraphs` listing and defining digraphs
+//! - `ga`, `g8` and `:ascii` on the character under the cursor
+//!
+//! Source tests:
+//! - src/testdir/test_digraph.vim
+//! - src/testdir/test_ga.vim
+//! - src/testdir/test_utf8.vim
+
+mod common;
+
+use common::TestHarness;
+use vxd::cmdline::Cmdline;
+
+// ============================================================================
+// Entering Digraphs
+// ============================================================================
+
+/// Test: Ctrl-K inserts digraphs, in either order
+/// Source: test_digraph.vim "Test_digraphs"
+#[test]
+fn test_ctrl_k_insert() {
+    let mut h = TestHarness::new();
+    h.feed("i<C-K>Eu<C-K>a*<C-K>ea<C-K>->x<Esc>");
+    assert_lines!(h, "€αæ→x");
+}
+
+/// Test: without a digraph the second character is inserted; <Esc>
+/// cancels the digraph
+/// Source: test_digraph.vim "Test_digraphs_option"
+#[test]
+fn test_ctrl_k_no_digraph() {
+    let mut h = TestHarness::new();
+    h.feed("i<C-K>xya<C-K><Esc>b<Esc>");
+    assert_lines!(h, "yab");
+}
+
+/// Test: {char1}<BS>{char2} enters a digraph only with 'digraph' set
+/// Source: test_digraph.vim "Test_digraphs_option"
+#[test]
+fn test_digraph_option() {
+    let mut h = TestHarness::new();
+    h.feed("ia<BS>e<Esc>");
+    assert_lines!(h, "e");
+    h.feed(":set dg<CR>");
+    h.feed("o-<BS>>q<BS>z<Esc>");
+    assert_lines!(h, "e", "→z");
+    h.feed(":set nodg<CR>");
+    h.feed("oa<BS>e<Esc>");
+    assert_lines!(h, "e", "→z", "e");
+}
+
+/// Test: Ctrl-K in Command-line mode inserts a digraph
+/// Source: test_digraph.vim "Test_digraphs_cmdline"
+#[test]
+fn test_ctrl_k_cmdline() {
+    let mut h = TestHarness::new();
+    h.feed(":<C-K>a:<C-K>:o");
+    assert_eq!(h.editor.cmdline.getcmdline(), "äö");
+    h.feed("<Esc>");
+}
+
+// ============================================================================
+// :digraphs
+// ============================================================================
+
+/// Test: :digraphs defines digraphs, several at once
+/// Source: test_digraph.vim "Test_digraphs_define"
+#[test]
+fn test_define() {
+    let mut h = TestHarness::new();
+    h.feed(":digraphs xx 8594 yy 9829<CR>");
+    h.feed("i<C-K>xx<C-K>yy<C-K>ae<Esc>");
+    assert_lines!(h, "→♥æ");
+    h.feed(":dig ae 1234<CR>");
+    h.feed("a<C-K>ae<Esc>");
+    assert_lines!(h, "→♥æӒ");
+}
+
+/// Test: the errors of :digraphs
+/// Source: test_digraph.vim "Test_digraph_define_error"
+#[test]
+fn test_define_errors() {
+    let mut h = TestHarness::new();
+    h.feed(":digraphs a<CR>");
+    assert_eq!(h.message(), "E1214: Digraph must be just two characters: a");
+    h.feed(":digraphs ab<CR>");
+    assert_eq!(h.message(), "E39: Number expected");
+    h.feed(":digraphs abc 1<CR>");
+    assert_eq!(h.message(), "E39: Number expected");
+}
+
+/// Test: :digraphs lists the digraphs; with ! the groups have headers
+/// and user digraphs come last
+/// Source: test_digraph.vim "Test_digraph_cmndline"
+#[test]
+fn test_listing() {
+    let mut h = TestHarness::new();
+    h.feed(":digraphs<CR>");
+    let listing = h.message().to_string();
+    assert!(listing.starts_with("NU ^@   0    SH ^A   1"));
+    assert!(listing.contains("Eu €  8364"));
+    assert!(!listing.contains("Latin supplement"));
+
+    h.feed(":dig xx 8594<CR>");
+    h.feed(":digraphs!<CR>");
+    let listing = h.message().to_string();
+    assert!(listing.contains("\nLatin supplement\n!I ¡  161"));
+    assert!(listing.contains("\nGreek and Coptic\n"));
+    assert!(listing.ends_with("\nCustom\nxx →  8594"));
+}
+
+// ============================================================================
+// Character Inspection
+// ============================================================================
+
+/// Test: ga shows the value of the character and its digraph
+/// Source: test_ga.vim "Test_ga_command"
+#[test]
+fn test_ga() {
+    let mut h = TestHarness::with_lines(&["a#æ", "e\u{301}x", ""]);
+    h.feed("ga");
+    assert_eq!(h.message(), "<a>  97,  Hex 61,  Octal 141");
+    h.feed("lga");
+    assert_eq!(h.message(), "<#>  35,  Hex 23,  Oct 043, Digr Nb");
+    h.feed("lga");
+    assert_eq!(h.message(), "<æ> 230, Hex 00e6, Oct 346, Digr ae");
+    h.feed("j0ga");
+    assert_eq!(
+        h.message(),
+        "<e>  101,  Hex 65,  Octal 145 < \u{301}> 769, Hex 0301, Octal 1401"
+    );
+    h.feed("jga");
+    assert_eq!(h.message(), "NUL");
+    h.feed("gg:ascii<CR>");
+    assert_eq!(h.message(), "<a>  97,  Hex 61,  Octal 141");
+}
+
+/// Test: ga shows a user digraph
+/// Source: test_ga.vim "Test_ga_command"
+#[test]
+fn test_ga_user_digraph() {
+    let mut h = TestHarness::with_lines(&["→"]);
+    h.feed(":dig xx 8594<CR>ga");
+    assert_eq!(h.message(), "<→> 8594, Hex 2192, Oct 20622, Digr xx");
+}
+
+/// Test: g8 shows the bytes of the character with its composing ones
+/// Source: test_utf8.vim "Test_g8"
+#[test]
+fn test_g8() {
+    let mut h = TestHarness::with_lines(&["aæ", "e\u{301}", ""]);
+    h.feed("g8");
+    assert_eq!(h.message(), "61 ");
+    h.feed("lg8");
+    assert_eq!(h.message(), "c3 a6 ");
+    h.feed("j0g8");
+    assert_eq!(h.message(), "65 + cc 81 ");
+    h.feed("jg8");
+    assert_eq!(h.message(), "NUL");
+}
@@ -0,0 +1,191 @@
+//! Display tests ported from Vim tests
+//!
+//! These tests verify the screen cells buffer lines are shown in:
+//! - Tabs up to the next stop of 'tabstop' or 'vartabstop', the cursor on
+//!   their last cell in Normal mode
+//! - Double-width and composing characters, and 'ambiwidth'
+//! - Control characters as `^X`, `<xx>` with "uhex" in 'display'
+//! - 'list' and 'listchars', with "extends" and "precedes" for 'nowrap'
+//! - A last line that does not fit, with "lastline" and "truncate"
+//! - The virtual column of the cursor
+//!
+//! Source tests:
+//! - src/testdir/test_listchars.vim
+//! - src/testdir/test_display.vim
+//! - src/testdir/test_vartabs.vim
+//! - src/testdir/test_utf8.vim
+
+mod common;
+
+use common::TestHarness;
+use vxd::cursor::Cursor;
+use vxd_tui::display::WindowView;
+
+fn view(h: &TestHarness, height: usize, width: usize) -> WindowView {
+    let lines = h.get_lines();
+    h.editor.window_view(&lines, height, width)
+}
+
+/// The text of the rows of a window `height` rows of `width` cells
+fn rows(h: &TestHarness, height: usize, width: usize) -> Vec<String> {
+    view(h, height, width)
+        .rows
+        .iter()
+        .map(|row| row.cells.iter().map(|cell| cell.text.as_str()).collect())
+        .collect()
+}
+
+// ============================================================================
+// Tabs
+// ============================================================================
+
+/// Test: a tab takes the cells up to the next tab stop, and the cursor is
+/// on its last cell in Normal mode, its first in Insert mode
+/// Source: test_display.vim "Test_display_tab_cursor"
+#[test]
+fn test_tab_cells_and_cursor() {
+    let mut h = TestHarness::with_lines(&["a\tb"]);
+    assert_eq!(rows(&h, 1, 20), ["a       b"]);
+    h.feed("l");
+    assert_eq!(view(&h, 1, 20).cursor, Some((0, 7)));
+    h.feed("i");
+    assert_eq!(view(&h, 1, 20).cursor, Some((0, 1)));
+    h.feed("<Esc>:set ts=4<CR>");
+    assert_eq!(rows(&h, 1, 20), ["a   b"]);
+}
+
+/// Test: 'vartabstop' sets the width of each tab stop, the last repeated
+/// Source: test_vartabs.vim "Test_vartabs"
+#[test]
+fn test_vartabstop() {
+    let mut h = TestHarness::with_lines(&["\t\t\tx"]);
+    h.feed(":set vts=4,2<CR>$");
+    assert_eq!(h.editor.cursor.virtcol(), 8);
+    h.feed(":set vts=<CR>$");
+    assert_eq!(h.editor.cursor.virtcol(), 24);
+
+    h.feed(":set et vts=4,2<CR>ccx<Tab><Tab>y<Esc>");
+    assert_eq!(h.content(), "x     y");
+}
+
+// ============================================================================
+// Wide and Unprintable Characters
+// ============================================================================
+
+/// Test: a double-width character that does not fit at the end of a row
+/// starts the next one
+/// Source: test_utf8.vim "Test_screenchar_utf8"
+#[test]
+fn test_double_width() {
+    let mut h = TestHarness::with_lines(&["漢字x"]);
+    assert_eq!(rows(&h, 2, 4), ["漢字", "x"]);
+    assert_eq!(rows(&h, 2, 3), ["漢>", "字x"]);
+    h.feed("$");
+    assert_eq!(h.editor.cursor.virtcol(), 4);
+    assert_eq!(view(&h, 2, 3).cursor, Some((1, 2)));
+}
+
+/// Test: composing characters take no cell; 'ambiwidth' decides the cells
+/// of ambiguous ones
+/// Source: test_utf8.vim "Test_strwidth"
+#[test]
+fn test_composing_and_ambiwidth() {
+    let mut h = TestHarness::with_lines(&["e\u{301}→x"]);
+    h.feed("$");
+    assert_eq!(h.editor.cursor.virtcol(), 2);
+    h.feed(":set ambw=double<CR>$");
+    assert_eq!(h.editor.cursor.virtcol(), 3);
+}
+
+/// Test: control characters are shown as ^X, or as <xx> with "uhex"
+/// Source: test_display.vim "Test_display_uhex"
+#[test]
+fn test_unprintable() {
+    let mut h = TestHarness::with_lines(&["a\u{1}b\u{7f}"]);
+    assert_eq!(rows(&h, 1, 20), ["a^Ab^?"]);
+    h.feed(":set display=uhex<CR>");
+    assert_eq!(rows(&h, 1, 20), ["a<01>b<7f>"]);
+    h.feed("$");
+    assert_eq!(h.editor.cursor.virtcol(), 6);
+}
+
+// ============================================================================
+// 'list' and 'listchars'
+// ============================================================================
+
+/// Test: with 'list' a tab without "tab" in 'listchars' is ^I, and the
+/// items show tabs, trailing spaces and the end of the line
+/// Source: test_listchars.vim "Test_listchars"
+#[test]
+fn test_listchars() {
+    let mut h = TestHarness::with_lines(&["\tx  "]);
+    h.feed(":set list<CR>");
+    assert_eq!(rows(&h, 1, 20), ["^Ix  $"]);
+    h.feed(":set listchars=tab:>-,trail:~<CR>");
+    assert_eq!(rows(&h, 1, 20), [">-------x~~"]);
+    // With 'list' the cursor is on the first cell of a tab
+    assert_eq!(view(&h, 1, 20).cursor, Some((0, 0)));
+}
+
+/// Test: without 'wrap' "precedes" and "extends" show the line goes on
+/// Source: test_listchars.vim "Test_listchars_unicode"
+#[test]
+fn test_nowrap_extends_precedes() {
+    let mut h = TestHarness::with_lines(&["abcdefghij"]);
+    h.feed(":set nowrap list listchars=extends:>,precedes:<lt><CR>");
+    assert_eq!(rows(&h, 1, 5), ["abcd>"]);
+    h.feed("$");
+    assert_eq!(rows(&h, 1, 5), ["<ghij"]);
+    assert_eq!(view(&h, 1, 5).cursor, Some((0, 4)));
+}
+
+/// Test: an invalid value of a display option is refused
+/// Source: test_listchars.vim "Test_listchars_invalid"
+#[test]
+fn test_invalid_values() {
+    let mut h = TestHarness::new();
+    for arg in [
+        "lcs=tab:x",
+        "lcs=eol:漢",
+        "dy=bogus",
+        "vts=4,0",
+        "ambw=wide",
+    ] {
+        h.feed(&format!(":set {}<CR>", arg));
+        assert_eq!(h.message(), format!("E474: Invalid argument: {}", arg));
+    }
+    assert_eq!(h.editor.options.string("listchars"), "eol:$");
+    assert_eq!(h.editor.options.string("display"), "");
+    assert_eq!(h.editor.options.string("ambiwidth"), "single");
+}
+
+// ============================================================================
+// 'display'
+// ============================================================================
+
+/// Test: a last line that does not fit is shown as @ rows, or cut off
+/// with @@@ for "lastline" and "truncate"
+/// Source: test_display.vim "Test_display_lastline"
+#[test]
+fn test_lastline() {
+    let mut h = TestHarness::with_lines(&["short", "abcdefghijkl"]);
+    assert_eq!(rows(&h, 2, 5), ["short", "@"]);
+    h.feed(":set display=lastline<CR>");
+    assert_eq!(rows(&h, 2, 5), ["short", "ab@@@"]);
+    h.feed(":set display=truncate<CR>");
+    assert_eq!(rows(&h, 2, 5), ["short", "@@@"]);
+}
+
+/// Test: the line with the cursor is scrolled into view, its rows up to
+/// the cursor when it is taller than the window
+/// Source: test_display.vim "Test_display_scroll_at_topline"
+#[test]
+fn test_scroll_to_cursor() {
+    let mut h = TestHarness::with_lines(&["one", "two", "abcdefghij"]);
+    h.feed("G$");
+    let view = view(&h, 2, 4);
+    assert_eq!(view.rows.len(), 2);
+    assert_eq!(view.rows[1].line.0, 3);
+    assert!(!view.rows[0].first);
+    assert_eq!(view.cursor, Some((1, 1)));
+}
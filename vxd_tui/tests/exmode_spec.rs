@@ -0,0 +1,315 @@
+//! Ex mode tests ported from Vim tests
+//!
+//! These tests verify the editor started with `-e` and `-es`:
+//! - Command lines read from the input are executed in order
+//! - `:write`, `:wq` and `:x` write real files
+//! - `:global`, `:delete`, `:move` and `:edit` in scripts
+//! - `:qall` refuses to lose changes, `:qa!` and `:wqall` do not
+//! - Silent mode writes only the output of `:print`, `:number` and `:set`
+//! - The exit status: `:cquit`, or 1 after an error
+//!
+//! Source tests:
+//! - src/testdir/test_startup.vim
+//! - src/testdir/test_ex_mode.vim
+//! - src/testdir/test_writefile.vim
+//! - src/testdir/test_global.vim
+
+mod common;
+
+use common::{ScratchDir, TestHarness};
+use vxd::buffer::{Buffer, BufferManager};
+use vxd::eval::Value;
+use vxd::startup::parse_args;
+
+/// Start an editor with `args`, run Ex mode with the command lines
+/// `input` and return the exit status and the output
+fn run_ex(args: &[&str], input: &str) -> (TestHarness, i32, String) {
+    let mut h = TestHarness::new();
+    h.editor.startup(&parse_args(args).unwrap(), None);
+    let mut output = Vec::new();
+    let status = h.editor.ex_mode(input.as_bytes(), &mut output).unwrap();
+    (h, status, String::from_utf8(output).unwrap())
+}
+
+// ============================================================================
+// Batch edits
+// ============================================================================
+
+/// Test: commands from the input edit a file that :wq writes
+/// Source: test_startup.vim "Test_silent_ex_mode"
+#[test]
+fn test_batch_edit_and_write() {
+    let dir = ScratchDir::new("batch");
+    let file = dir.file("f.txt", "one foo\ntwo\nthree foo\n");
+    let (_, status, output) = run_ex(
+        &["-es", "-u", "NONE", &file],
+        "%s/foo/bar/\nnormal! Gdd\nwq\n",
+    );
+    assert_eq!(status, 0);
+    assert_eq!(output, "");
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), "one bar\ntwo\n");
+}
+
+/// Test: the end of the input ends Ex mode without writing
+/// Source: test_ex_mode.vim "Test_ex_mode_errors"
+#[test]
+fn test_end_of_input() {
+    let dir = ScratchDir::new("end");
+    let file = dir.file("f.txt", "keep\n");
+    let (h, status, _) = run_ex(&["-es", "-u", "NONE", &file], "s/keep/changed/\n");
+    assert_eq!(status, 0);
+    assert!(h.editor.buffers.current().is_modified());
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), "keep\n");
+}
+
+/// Test: :write to another file, which must not exist without !
+/// Source: test_writefile.vim "Test_write_file_exists"
+#[test]
+fn test_write_other_file() {
+    let dir = ScratchDir::new("other");
+    let file = dir.file("f.txt", "a\nb\nc\n");
+    let copy = dir.join("copy.txt").to_string_lossy().into_owned();
+    let (h, status, output) = run_ex(
+        &["-e", "-u", "NONE", &file],
+        &format!("2,3write {}\nwrite {}\n", copy, copy),
+    );
+    assert_eq!(status, 1);
+    assert_eq!(
+        output,
+        format!(
+            "\"{}\" [New] 2L, 4B written\nE13: File exists (add ! to override)\n",
+            copy
+        )
+    );
+    assert_eq!(std::fs::read_to_string(&copy).unwrap(), "b\nc\n");
+    assert!(!h.editor.buffers.current().is_modified());
+}
+
+/// Test: :write on a 'readonly' file needs !
+/// Source: test_writefile.vim "Test_write_readonly"
+#[test]
+fn test_write_readonly() {
+    let dir = ScratchDir::new("readonly");
+    let file = dir.file("f.txt", "text\n");
+    let (_, status, output) = run_ex(&["-e", "-R", "-u", "NONE", &file], "s/t/T/\nw\nw!\n");
+    assert_eq!(status, 1);
+    assert!(output.starts_with("E45: 'readonly' option is set (add ! to override)\n"));
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), "Text\n");
+}
+
+// ============================================================================
+// Line commands
+// ============================================================================
+
+/// Run `script` with `-es` on a file with `text`, returning the exit
+/// status and what the file has after it
+fn run_script(name: &str, text: &str, script: &str) -> (i32, String) {
+    let dir = ScratchDir::new(name);
+    let file = dir.file("f.txt", text);
+    let (_, status, _) = run_ex(&["-es", "-u", "NONE", &file], script);
+    (status, std::fs::read_to_string(&file).unwrap())
+}
+
+/// Test: :global executes the command on each matching line, :vglobal
+/// and :global! on the others
+/// Source: test_global.vim "Test_global_print"
+#[test]
+fn test_global_delete() {
+    let text = "a foo\nb\nfoo c\nd\n";
+    assert_eq!(
+        run_script("gdel", text, "g/foo/d\nwq\n"),
+        (0, "b\nd\n".into())
+    );
+    assert_eq!(
+        run_script("vdel", text, "v/foo/d\nwq\n"),
+        (0, "a foo\nfoo c\n".into())
+    );
+    assert_eq!(
+        run_script("gbang", text, "g!/foo/d\nwq\n"),
+        (0, "a foo\nfoo c\n".into())
+    );
+}
+
+/// Test: the lines stay marked while the command moves and deletes
+/// lines; a marked line deleted before its turn is skipped
+/// Source: test_global.vim "Test_global_error"
+#[test]
+fn test_global_follows_lines() {
+    let (status, text) = run_script("gmove", "x1\nx2\nx3\n", "g/x/m0\nwq\n");
+    assert_eq!((status, text.as_str()), (0, "x3\nx2\nx1\n"));
+    let (status, text) = run_script("gskip", "x1\nx2\ny\nx3\n", "g/x/1d 2\nwq\n");
+    assert_eq!((status, text.as_str()), (0, ""));
+    // A line without a match for :s is no error
+    let (status, text) = run_script("gsub", "x1\ny\n", "g/1/s/y/z/\ng/y/s/q/z/\nwq\n");
+    assert_eq!((status, text.as_str()), (0, "x1\ny\n"));
+}
+
+/// Test: :global without a command prints the lines, and cannot be
+/// nested
+/// Source: test_global.vim "Test_nested_global"
+#[test]
+fn test_global_print() {
+    let dir = ScratchDir::new("gprint");
+    let file = dir.file("f.txt", "one\ntwo\nthree\n");
+    let (_, status, output) = run_ex(&["-es", "-u", "NONE", &file], "g/o/\n");
+    assert_eq!(status, 0);
+    assert_eq!(output, "one\ntwo\n");
+    let (_, status, output) = run_ex(&["-e", "-u", "NONE", &file], "g/o/g/t/d\ng/xyz/d\n");
+    assert_eq!(status, 1);
+    assert_eq!(
+        output,
+        "E147: Cannot do :global recursive\nPattern not found: xyz\n"
+    );
+}
+
+/// Test: :delete and :move on a range
+/// Source: test_ex_mode.vim "Test_ex_mode_errors"
+#[test]
+fn test_delete_and_move() {
+    let text = "1\n2\n3\n4\n";
+    assert_eq!(run_script("d2", text, "2d\nwq\n"), (0, "1\n3\n4\n".into()));
+    assert_eq!(run_script("dall", text, "%d\nwq\n"), (0, "".into()));
+    assert_eq!(
+        run_script("mlast", text, "1,2m$\nwq\n"),
+        (0, "3\n4\n1\n2\n".into())
+    );
+    assert_eq!(
+        run_script("mtop", text, "4m0\nwq\n"),
+        (0, "4\n1\n2\n3\n".into())
+    );
+    let (status, text) = run_script("minto", text, "1,3m2\nwq\n");
+    assert_eq!((status, text.as_str()), (1, "1\n2\n3\n4\n"));
+}
+
+/// Test: :delete into a register, and with a count
+/// Source: test_ex_mode.vim "Test_ex_mode_errors"
+#[test]
+fn test_delete_register_count() {
+    let dir = ScratchDir::new("dreg");
+    let file = dir.file("f.txt", "1\n2\n3\n4\n5\n");
+    let (mut h, status, _) = run_ex(&["-es", "-u", "NONE", &file], "2d a 2\n");
+    assert_eq!(status, 0);
+    assert_lines!(h, "1", "4", "5");
+    assert_eq!(h.eval("@a"), Value::string("2\n3\n"));
+    assert_cursor!(h, 2, 0);
+}
+
+/// Test: :edit another file needs the changes written, :edit! reads the
+/// file again
+/// Source: test_edit.vim "Test_edit_08"
+#[test]
+fn test_edit() {
+    let dir = ScratchDir::new("edit");
+    let file = dir.file("f.txt", "one\n");
+    let other = dir.file("g.txt", "two\n");
+    let (h, status, output) = run_ex(
+        &["-e", "-u", "NONE", &file],
+        &format!("s/one/1/\nedit {}\nedit!\nedit {}\n", other, other),
+    );
+    assert_eq!(status, 1);
+    assert_eq!(
+        output,
+        "E37: No write since last change (add ! to override)\n"
+    );
+    assert_eq!(h.editor.buffers.current().name(), other);
+    assert_lines!(h, "two");
+}
+
+// ============================================================================
+// Output
+// ============================================================================
+
+/// Test: silent mode writes only the output of :print, :number and :set
+/// Source: test_startup.vim "Test_silent_ex_mode"
+#[test]
+fn test_silent_output() {
+    let (_, status, output) = run_ex(
+        &["-es", "-u", "NONE", "-c", "echo 'from -c'"],
+        "normal! ia\nnormal! ob\necho 'hidden'\n%print\n2number\nset ts?\n",
+    );
+    assert_eq!(status, 0);
+    assert_eq!(output, "a\nb\n  2 b\n  tabstop=8\n");
+}
+
+/// Test: without silent mode messages and errors are written
+/// Source: test_ex_mode.vim "Test_Ex_echo_backslash"
+#[test]
+fn test_messages() {
+    let (h, status, output) = run_ex(
+        &["-e", "-u", "NONE"],
+        "echo 'shown'\nnosuch\nnormal! ix\nnormal! oy\n1print\n",
+    );
+    assert_eq!(status, 1);
+    assert_eq!(output, "shown\nE492: Not an editor command: nosuch\nx\n");
+    assert_cursor!(h, 1, 0);
+}
+
+// ============================================================================
+// Exit status
+// ============================================================================
+
+/// Test: :cquit exits with its status and stops reading the input
+/// Source: test_startup.vim "Test_cq_zero_exmode"
+#[test]
+fn test_cquit() {
+    let (mut h, status, _) = run_ex(&["-es", "-u", "NONE"], "cquit 3\nlet g:after = 1\n");
+    assert_eq!(status, 3);
+    assert!(h.editor.quit_requested);
+    assert_eq!(h.eval("exists('g:after')"), Value::Number(0));
+    let (_, status, _) = run_ex(&["-es", "-u", "NONE"], "cq\n");
+    assert_eq!(status, 1);
+    let (_, status, _) = run_ex(&["-es", "-u", "NONE"], "0cq\n");
+    assert_eq!(status, 0);
+}
+
+/// Test: :qall refuses to quit with changes, :qa! quits without writing
+/// and :wqall writes the changes
+/// Source: test_ex_mode.vim "Test_ex_mode_errors"
+#[test]
+fn test_qall() {
+    let dir = ScratchDir::new("qall");
+    let file = dir.file("f.txt", "text\n");
+    let (h, status, output) = run_ex(&["-e", "-u", "NONE", &file], "s/t/T/\nqa\n");
+    assert_eq!(status, 1);
+    assert_eq!(
+        output,
+        format!("E162: No write since last change for buffer \"{}\"\n", file)
+    );
+    assert!(!h.editor.quit_requested);
+    let (h, status, _) = run_ex(&["-es", "-u", "NONE", &file], "s/t/T/\nqa!\nw\n");
+    assert_eq!(status, 0);
+    assert!(h.editor.quit_requested);
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), "text\n");
+    let (_, status, _) = run_ex(&["-es", "-u", "NONE", &file], "s/t/T/\nwqa\n");
+    assert_eq!(status, 0);
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), "Text\n");
+}
+
+/// Test: a -c command quitting ends Ex mode before reading the input, and
+/// quits without a user interface
+/// Source: test_startup.vim "Test_progpath"
+#[test]
+fn test_quit_from_command() {
+    let (mut h, status, output) = run_ex(&["-es", "-u", "NONE", "-c", "qa"], "let g:after = 1\n");
+    assert_eq!((status, output.as_str()), (0, ""));
+    assert_eq!(h.eval("exists('g:after')"), Value::Number(0));
+
+    let mut h = TestHarness::new();
+    h.editor.startup(
+        &parse_args(&["--headless", "-u", "NONE", "-c", "qa"]).unwrap(),
+        None,
+    );
+    assert!(h.editor.quit_requested);
+    assert_eq!(h.editor.message, None);
+}
+
+/// Test: an error in a -c command gives exit status 1
+/// Source: test_startup.vim "Test_exit_code"
+#[test]
+fn test_error_status() {
+    let (_, status, output) = run_ex(&["-es", "-u", "NONE", "-c", "nosuch"], "");
+    assert_eq!(status, 1);
+    assert_eq!(output, "");
+    let (_, status, _) = run_ex(&["-es", "-u", "NONE"], "let g:x = 1\nquit\n");
+    assert_eq!(status, 0);
+}
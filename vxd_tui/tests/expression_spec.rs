@@ -0,0 +1,270 @@
+//! Expression tests ported from Vim's test_registers.vim,
+//! test_substitute.vim and test_mapping.vim
+//!
+//! These tests verify expressions used in the editor:
+//! - The `=` register with `"=`, `Ctrl-R =` in Insert mode and on the
+//!   command line
+//! - Options, registers and editor functions in expressions
+//! - `\=` in the replacement of `:s`
+//! - `<expr>` mappings
+//!
+//! Source tests:
+//! - test_registers.vim
+//! - test_substitute.vim
+//! - test_mapping.vim
+
+mod common;
+
+use common::TestHarness;
+use vxd::cmdline::CmdlineHistoryKind;
+use vxd::cursor::CursorPosition;
+use vxd::eval::Value;
+use vxd::mappings::MappingManager;
+use vxd::marks::{Mark, MarkManager, MarkValue};
+use vxd::modes::Mode;
+use vxd::types::LineNr;
+
+// ============================================================================
+// The = register
+// ============================================================================
+
+/// Test: "= puts the result of an expression
+/// Source: test_registers.vim "Test_put_expr"
+#[test]
+fn test_put_expression_register() {
+    let mut h = TestHarness::with_lines(&["x"]);
+    h.feed("\"=5*5<CR>p");
+    assert_lines!(h, "x25");
+    assert_mode!(h, Mode::Normal);
+
+    // An empty expression uses the last one
+    h.feed("\"=<CR>P");
+    assert_lines!(h, "x2255");
+}
+
+/// Test: a List or a String ending in a line break is put as lines
+/// Source: test_registers.vim "Test_put_expr"
+#[test]
+fn test_put_expression_lines() {
+    let mut h = TestHarness::with_lines(&["x"]);
+    h.feed("\"=['a', 'b']<CR>p");
+    assert_lines!(h, "x", "a", "b");
+    h.feed("gg\"=\"c\\n\"<CR>P");
+    assert_lines!(h, "c", "x", "a", "b");
+}
+
+/// Test: a count puts the result several times
+/// Source: test_registers.vim "Test_put_expr"
+#[test]
+fn test_put_expression_count() {
+    let mut h = TestHarness::with_lines(&[""]);
+    h.feed("3\"='ab'<CR>p");
+    assert_lines!(h, "ababab");
+}
+
+/// Test: an error in the expression is shown and nothing is put
+/// Source: test_registers.vim "Test_put_expr"
+#[test]
+fn test_expression_error() {
+    let mut h = TestHarness::with_lines(&["x"]);
+    h.feed("\"=1 +<CR>p");
+    assert_lines!(h, "x");
+    assert!(h.message().contains("E15"));
+
+    h.feed("\"=nosuch(1)<CR>p");
+    assert_lines!(h, "x");
+    assert_eq!(
+        h.editor.message.as_deref(),
+        Some("E117: Unknown function: nosuch")
+    );
+}
+
+/// Test: <Esc> on the expression line cancels the command
+/// Source: test_registers.vim "Test_put_expr"
+#[test]
+fn test_expression_cancel() {
+    let mut h = TestHarness::with_lines(&["x"]);
+    h.feed("\"=5<Esc>p");
+    assert_lines!(h, "x");
+    assert_mode!(h, Mode::Normal);
+}
+
+/// Test: typed expressions go into the expression history
+/// Source: test_history.vim "Test_History"
+#[test]
+fn test_expression_history() {
+    let mut h = TestHarness::with_lines(&["x"]);
+    h.feed("\"=1+1<CR>p\"=2+2<CR>p");
+    assert_eq!(
+        h.editor.cmdline.history_entries(CmdlineHistoryKind::Expression),
+        vec!["1+1", "2+2"]
+    );
+}
+
+// ============================================================================
+// Values in expressions
+// ============================================================================
+
+/// Test: options, registers and editor functions
+/// Source: test_functions.vim "Test_col", "Test_getline"
+#[test]
+fn test_expression_values() {
+    let mut h = TestHarness::with_lines(&["one", "two", "three"]);
+    assert_eq!(h.eval("&tabstop"), Value::Number(8));
+    assert_eq!(h.eval("&ts + 1"), Value::Number(9));
+    h.feed("j\"ayy");
+    assert_eq!(h.eval("@a"), Value::string("two\n"));
+    h.feed("l");
+    assert_eq!(
+        h.eval("[line('.'), col('.'), col('$'), line('$')]"),
+        Value::list(vec![
+            Value::Number(2),
+            Value::Number(2),
+            Value::Number(4),
+            Value::Number(3)
+        ])
+    );
+    assert_eq!(
+        h.eval("getline(1) . getline('$')"),
+        Value::string("onethree")
+    );
+    assert_eq!(
+        h.eval("getline(2, '$')"),
+        Value::string_list(["two", "three"])
+    );
+    assert_eq!(h.eval("getline(9)"), Value::string(""));
+    h.editor
+        .marks
+        .set(Mark::Local('a'), MarkValue::new(CursorPosition::new(LineNr(2), 0)))
+        .unwrap();
+    assert_eq!(h.eval("line(\"'a\")"), Value::Number(2));
+    assert_eq!(h.eval("mode()"), Value::string("n"));
+    assert_eq!(
+        h.eval("printf('%s:%03d', toupper(getline('.')), 7)"),
+        Value::string("TWO:007")
+    );
+}
+
+// ============================================================================
+// Ctrl-R in Insert mode and on the command line
+// ============================================================================
+
+/// Test: Ctrl-R inserts a register in Insert mode
+/// Source: test_registers.vim "Test_insert_register"
+#[test]
+fn test_insert_ctrl_r_register() {
+    let mut h = TestHarness::with_lines(&["word"]);
+    h.feed("yeA <C-R>\"<Esc>");
+    assert_lines!(h, "word word");
+}
+
+/// Test: Ctrl-R = inserts the result of an expression in Insert mode
+/// Source: test_registers.vim "Test_insert_expr"
+#[test]
+fn test_insert_ctrl_r_expression() {
+    let mut h = TestHarness::with_lines(&[""]);
+    h.feed("ia<C-R>=toupper('b')<CR>");
+    assert_mode!(h, Mode::Insert);
+    h.feed("c<Esc>");
+    assert_lines!(h, "aBc");
+    assert_eq!(h.editor.registers.last_expression(), Some("toupper('b')"));
+
+    // <Esc> goes back to Insert mode without inserting
+    h.feed("A<C-R>=1<Esc>d<Esc>");
+    assert_lines!(h, "aBcd");
+
+    // The expression is evaluated back in Insert mode
+    h.feed("o<C-R>=mode()<CR><Esc>");
+    assert_lines!(h, "aBcd", "i");
+}
+
+/// Test: Ctrl-R = inserts the result of an expression on the command line
+/// Source: test_cmdline.vim "Test_cmdline_paste"
+#[test]
+fn test_cmdline_ctrl_r_expression() {
+    let mut h = TestHarness::with_lines(&["x"]);
+    h.feed(":s/x/<C-R>=3*4<CR>/<CR>");
+    assert_lines!(h, "12");
+    assert_mode!(h, Mode::Normal);
+}
+
+// ============================================================================
+// \= in :substitute
+// ============================================================================
+
+/// Test: \= uses the result of an expression as the replacement
+/// Source: test_substitute.vim "Test_sub_replace_1"
+#[test]
+fn test_substitute_expression() {
+    let mut h = TestHarness::with_lines(&["foo", "xax", "xbx"]);
+    h.feed(":1s/o/\\=toupper(submatch(0))/g<CR>");
+    assert_lines!(h, "fOO", "xax", "xbx");
+    h.feed(":2,3s/x/\\=line('.') * 10/g<CR>");
+    assert_lines!(h, "fOO", "20a20", "30b30");
+}
+
+/// Test: an error in a \= expression leaves the text unchanged
+/// Source: test_substitute.vim "Test_sub_replace_1"
+#[test]
+fn test_substitute_expression_error() {
+    let mut h = TestHarness::with_lines(&["foo"]);
+    h.feed(":s/o/\\=nosuch/<CR>");
+    assert_lines!(h, "foo");
+    assert_eq!(
+        h.editor.message.as_deref(),
+        Some("E121: Undefined variable: nosuch")
+    );
+}
+
+// ============================================================================
+// <expr> mappings
+// ============================================================================
+
+/// Test: the keys of an <expr> mapping are the result of its expression
+/// Source: test_mapping.vim "Test_map_expr"
+#[test]
+fn test_expr_mapping() {
+    let mut h = TestHarness::with_lines(&[""]);
+    h.editor
+        .mappings
+        .add_expr(Mode::Insert, "<F2>", "repeat('ab', 2)", true)
+        .unwrap();
+    h.editor
+        .mappings
+        .add_expr(Mode::Insert, "<F3>", "\"()\\<Left>\"", true)
+        .unwrap();
+    h.feed("i<F2><F3>x<Esc>");
+    assert_lines!(h, "abab(x)");
+}
+
+/// Test: an <expr> mapping can depend on the cursor
+/// Source: test_mapping.vim "Test_map_expr"
+#[test]
+fn test_expr_mapping_uses_cursor() {
+    let mut h = TestHarness::with_lines(&["  text"]);
+    h.editor
+        .mappings
+        .add_expr(Mode::Normal, "H", "col('.') == 1 ? '^' : '0'", true)
+        .unwrap();
+    h.feed("H");
+    assert_cursor!(h, 1, 2);
+    h.feed("H");
+    assert_cursor!(h, 1, 0);
+}
+
+/// Test: an error in an <expr> mapping gives no keys
+/// Source: test_mapping.vim "Test_map_expr"
+#[test]
+fn test_expr_mapping_error() {
+    let mut h = TestHarness::with_lines(&["x"]);
+    h.editor
+        .mappings
+        .add_expr(Mode::Normal, "H", "nosuch()", true)
+        .unwrap();
+    h.feed("H");
+    assert_lines!(h, "x");
+    assert_eq!(
+        h.editor.message.as_deref(),
+        Some("E117: Unknown function: nosuch")
+    );
+}
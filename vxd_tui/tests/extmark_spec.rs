@@ -0,0 +1,183 @@
+//! Extmark tests ported from Neovim's test/functional/api/extmark_spec.lua
+//!
+//! These tests verify that extmarks follow the text they were placed on
+//! through edits made with keys, `set_lines()` and `set_text()`, including
+//! line splits and joins, and that gravity and end positions behave like
+//! Neovim's.
+
+mod common;
+
+use common::TestHarness;
+use vxd::buffer::{Buffer, BufferManager};
+use vxd::cursor::CursorPosition;
+use vxd::marks::{ExtmarkId, ExtmarkManager, ExtmarkOpts, NamespaceId};
+use vxd::types::{LineNr, VimError};
+
+fn pos(line: usize, col: usize) -> CursorPosition {
+    CursorPosition::new(LineNr(line), col)
+}
+
+fn setup(lines: &[&str]) -> (TestHarness, NamespaceId) {
+    let mut h = TestHarness::new();
+    h.set_lines(lines);
+    let ns = h.editor.extmarks.create_namespace("test");
+    (h, ns)
+}
+
+fn extmark(h: &TestHarness, ns: NamespaceId, id: ExtmarkId) -> (usize, usize) {
+    let handle = h.editor.buffers.current().handle();
+    let mark = h.editor.extmarks.get_extmark(handle, ns, id).unwrap();
+    (mark.start.line.0, mark.start.col)
+}
+
+fn set_text(h: &mut TestHarness, start: (i64, i64), end: (i64, i64), text: &[&str]) {
+    h.editor
+        .buffers
+        .current_mut()
+        .set_text(
+            start.0,
+            start.1,
+            end.0,
+            end.1,
+            text.iter().map(|s| s.to_string()).collect(),
+        )
+        .unwrap();
+    h.editor.sync_cursor_with_buffer();
+}
+
+// ============================================================================
+// Tracking Edits
+// ============================================================================
+
+/// Test: extmarks move with text inserted before them on their line
+/// Source: extmark_spec.lua "adds, updates and deletes marks"
+#[test]
+fn test_extmark_follows_insert_on_line() {
+    let (mut h, ns) = setup(&["12345"]);
+    let id = h
+        .editor
+        .set_extmark(ns, None, pos(1, 3), ExtmarkOpts::default())
+        .unwrap();
+    h.set_cursor(1, 1);
+    h.feed("iab<Esc>");
+    assert_eq!(extmark(&h, ns, id), (1, 5));
+    h.feed("0xx");
+    assert_eq!(extmark(&h, ns, id), (1, 3));
+}
+
+/// Test: extmarks survive set_text() splitting and joining lines
+/// Source: extmark_spec.lua "marks move with line splits (using set_text)"
+#[test]
+fn test_extmark_survives_set_text_split_and_join() {
+    let (mut h, ns) = setup(&["12345", "abcde"]);
+    let id = h
+        .editor
+        .set_extmark(ns, None, pos(1, 3), ExtmarkOpts::default())
+        .unwrap();
+    let below = h
+        .editor
+        .set_extmark(ns, None, pos(2, 2), ExtmarkOpts::default())
+        .unwrap();
+
+    set_text(&mut h, (0, 1), (0, 1), &["", ""]);
+    assert_eq!(h.get_lines(), vec!["1", "2345", "abcde"]);
+    assert_eq!(extmark(&h, ns, id), (2, 2));
+    assert_eq!(extmark(&h, ns, below), (3, 2));
+
+    set_text(&mut h, (0, 1), (1, 0), &[]);
+    assert_eq!(h.get_lines(), vec!["12345", "abcde"]);
+    assert_eq!(extmark(&h, ns, id), (1, 3));
+    assert_eq!(extmark(&h, ns, below), (2, 2));
+}
+
+/// Test: extmarks in deleted text move to the start of the deletion
+/// Source: extmark_spec.lua "marks move with char deletes"
+#[test]
+fn test_extmark_in_deleted_text() {
+    let (mut h, ns) = setup(&["12345", "abcde", "xyz"]);
+    let id = h
+        .editor
+        .set_extmark(ns, None, pos(2, 3), ExtmarkOpts::default())
+        .unwrap();
+    set_text(&mut h, (0, 2), (1, 4), &[]);
+    assert_eq!(h.get_lines(), vec!["12e", "xyz"]);
+    assert_eq!(extmark(&h, ns, id), (1, 2));
+
+    let last = h
+        .editor
+        .set_extmark(ns, None, pos(2, 1), ExtmarkOpts::default())
+        .unwrap();
+    h.set_cursor(1, 0);
+    h.feed("dd");
+    assert_eq!(extmark(&h, ns, last), (1, 1));
+}
+
+// ============================================================================
+// Gravity and Ranges
+// ============================================================================
+
+/// Test: gravity decides which side of inserted text the extmark ends up
+/// Source: extmark_spec.lua "left gravity"
+#[test]
+fn test_extmark_gravity() {
+    let (mut h, ns) = setup(&["abc"]);
+    let right = h
+        .editor
+        .set_extmark(ns, None, pos(1, 1), ExtmarkOpts::default())
+        .unwrap();
+    let left_opts = ExtmarkOpts {
+        right_gravity: false,
+        ..ExtmarkOpts::default()
+    };
+    let left = h
+        .editor
+        .set_extmark(ns, None, pos(1, 1), left_opts)
+        .unwrap();
+    set_text(&mut h, (0, 1), (0, 1), &["XY"]);
+    assert_eq!(extmark(&h, ns, right), (1, 3));
+    assert_eq!(extmark(&h, ns, left), (1, 1));
+}
+
+/// Test: a range extmark grows with text typed inside it
+/// Source: extmark_spec.lua "end_right_gravity"
+#[test]
+fn test_extmark_range_end() {
+    let (mut h, ns) = setup(&["foo bar baz"]);
+    let opts = ExtmarkOpts {
+        end: Some(pos(1, 7)),
+        end_right_gravity: true,
+        ..ExtmarkOpts::default()
+    };
+    let id = h.editor.set_extmark(ns, None, pos(1, 4), opts).unwrap();
+    h.set_cursor(1, 5);
+    h.feed("iXX<Esc>");
+    let handle = h.editor.buffers.current().handle();
+    let mark = h.editor.extmarks.get_extmark(handle, ns, id).unwrap();
+    assert_eq!(mark.start, pos(1, 4));
+    assert_eq!(mark.end, Some(pos(1, 9)));
+
+    let found = h
+        .editor
+        .extmarks
+        .get_extmarks(handle, Some(ns), pos(1, 6), pos(1, 6), true);
+    assert_eq!(found.len(), 1);
+    let found = h
+        .editor
+        .extmarks
+        .get_extmarks(handle, Some(ns), pos(1, 6), pos(1, 6), false);
+    assert!(found.is_empty());
+}
+
+/// Test: extmarks outside the buffer are rejected
+/// Source: extmark_spec.lua "throws consistent error codes"
+#[test]
+fn test_extmark_position_checked() {
+    let (mut h, ns) = setup(&["abc"]);
+    let opts = ExtmarkOpts::default();
+    assert_eq!(
+        h.editor.set_extmark(ns, None, pos(2, 0), opts),
+        Err(VimError::InvalidLine(LineNr(2)))
+    );
+    assert!(h.editor.set_extmark(ns, None, pos(1, 4), opts).is_err());
+    assert!(h.editor.set_extmark(ns, None, pos(1, 3), opts).is_ok());
+}
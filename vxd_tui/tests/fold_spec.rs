@@ -10,9 +10,33 @@
 
 mod common;
 
-use common::TestHarness;
-use vxd::folds::{Fold, FoldMethod, FoldState};
-use vxd::types::LineNr;
+use common::{ScratchDir, TestHarness};
+use vxd::fileedit::FileEditor;
+use vxd::folds::{BufferFoldManager, Fold, FoldManager, FoldMethod, FoldOptions, FoldState};
+use vxd::types::{LineNr, VimError};
+
+fn fold_manager(lines: &[&str], method: FoldMethod, options: FoldOptions) -> BufferFoldManager {
+    let mut mgr = BufferFoldManager::new(lines.iter().map(|s| s.to_string()).collect());
+    mgr.set_options(options);
+    mgr.set_method(method);
+    mgr
+}
+
+fn ranges(folds: &[Fold]) -> Vec<(usize, usize, usize)> {
+    let mut out = Vec::new();
+    for fold in folds {
+        out.push((fold.start.0, fold.end.0, fold.level));
+        out.extend(ranges(&fold.nested));
+    }
+    out
+}
+
+fn sw2() -> FoldOptions {
+    FoldOptions {
+        shiftwidth: 2,
+        ..FoldOptions::default()
+    }
+}
 
 // ============================================================================
 // Basic Fold Tests
@@ -615,3 +639,651 @@ fn test_fold_text() {
     let fold_text = format!("+-- {} lines", fold.line_count());
     assert!(fold_text.contains("5 lines"));
 }
+
+// ============================================================================
+// Indent Method Engine Tests
+// ============================================================================
+
+/// Test: indent folds follow shiftwidth levels
+/// Source: fold_spec.lua - foldlevel() with foldmethod=indent
+#[test]
+fn test_indent_engine_levels() {
+    let mgr = fold_manager(
+        &["a", "  a", "  a", "    a", "  a", "a"],
+        FoldMethod::Indent,
+        sw2(),
+    );
+
+    assert_eq!(ranges(mgr.folds()), vec![(2, 5, 1), (4, 4, 2)]);
+    let levels: Vec<usize> = (1..=6).map(|l| mgr.level(LineNr(l))).collect();
+    assert_eq!(levels, vec![0, 1, 1, 2, 1, 0]);
+}
+
+/// Test: blank and foldignore lines take the lower neighbouring level
+/// Source: fold.txt - fold-indent, 'foldignore'
+#[test]
+fn test_indent_engine_foldignore_and_blank_lines() {
+    let mgr = fold_manager(
+        &["a", "    b", "", "# note", "    c", "d"],
+        FoldMethod::Indent,
+        FoldOptions {
+            shiftwidth: 4,
+            ..FoldOptions::default()
+        },
+    );
+
+    assert_eq!(ranges(mgr.folds()), vec![(2, 5, 1)]);
+
+    let mgr = fold_manager(
+        &["a", "    b", "# note", "    c", "d"],
+        FoldMethod::Indent,
+        FoldOptions {
+            shiftwidth: 4,
+            foldignore: String::new(),
+            ..FoldOptions::default()
+        },
+    );
+    assert_eq!(ranges(mgr.folds()), vec![(2, 2, 1), (4, 4, 1)]);
+}
+
+/// Test: foldnestmax caps indent nesting
+/// Source: fold.txt - 'foldnestmax'
+#[test]
+fn test_indent_engine_foldnestmax() {
+    let mgr = fold_manager(
+        &["a", "  b", "    c", "      d", "e"],
+        FoldMethod::Indent,
+        FoldOptions {
+            shiftwidth: 2,
+            foldnestmax: 1,
+            ..FoldOptions::default()
+        },
+    );
+
+    assert_eq!(ranges(mgr.folds()), vec![(2, 4, 1)]);
+    assert_eq!(mgr.level(LineNr(4)), 1);
+}
+
+/// Test: foldminlines keeps small folds displayed open
+/// Source: fold.txt - 'foldminlines'
+#[test]
+fn test_engine_foldminlines() {
+    let mut mgr = fold_manager(
+        &["a", "  b", "c", "  d", "  e", "f"],
+        FoldMethod::Indent,
+        sw2(),
+    );
+
+    // foldlevel=0 closes everything, but the one-line fold cannot display closed.
+    assert!(!mgr.is_folded(LineNr(2)));
+    assert!(mgr.is_folded(LineNr(4)));
+
+    mgr.set_options(FoldOptions {
+        foldminlines: 0,
+        ..sw2()
+    });
+    assert!(mgr.is_folded(LineNr(2)));
+}
+
+/// Test: recomputing keeps the state of unchanged folds
+/// Source: fold_spec.lua - "updates correctly on :read"
+#[test]
+fn test_indent_engine_recompute_keeps_state() {
+    let mut mgr = fold_manager(
+        &["a", "  b", "  c", "d", "  e", "  f"],
+        FoldMethod::Indent,
+        sw2(),
+    );
+    mgr.open(LineNr(2)).unwrap();
+
+    mgr.set_lines(
+        ["a", "  b", "  c", "d", "  e", "  f", "  g"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect(),
+    );
+
+    assert_eq!(ranges(mgr.folds()), vec![(2, 3, 1), (5, 7, 1)]);
+    assert!(!mgr.is_folded(LineNr(2)));
+    assert!(mgr.is_folded(LineNr(7)));
+}
+
+// ============================================================================
+// Marker Method Engine Tests
+// ============================================================================
+
+/// Test: unnumbered markers nest
+/// Source: fold.txt - fold-marker
+#[test]
+fn test_marker_engine_nesting() {
+    let mgr = fold_manager(
+        &["one {{{", "two {{{", "three", "}}}", "four", "}}}", "five"],
+        FoldMethod::Marker,
+        FoldOptions::default(),
+    );
+
+    assert_eq!(ranges(mgr.folds()), vec![(1, 6, 1), (2, 4, 2)]);
+}
+
+/// Test: numbered markers set explicit levels
+/// Source: fold.txt - "{{{1" and "}}}1"
+#[test]
+fn test_marker_engine_numbered() {
+    let mgr = fold_manager(
+        &[
+            "{{{1", "a", "{{{2", "b", "}}}2", "c", "{{{1", "d", "}}}1", "e",
+        ],
+        FoldMethod::Marker,
+        FoldOptions::default(),
+    );
+
+    assert_eq!(ranges(mgr.folds()), vec![(1, 6, 1), (3, 5, 2), (7, 9, 1)]);
+    assert_eq!(mgr.level(LineNr(10)), 0);
+}
+
+/// Test: unclosed markers end at the last line
+/// Source: fold.txt - fold-marker
+#[test]
+fn test_marker_engine_unclosed() {
+    let mgr = fold_manager(
+        &["a", "b {{{", "c", "d"],
+        FoldMethod::Marker,
+        FoldOptions::default(),
+    );
+    assert_eq!(ranges(mgr.folds()), vec![(2, 4, 1)]);
+}
+
+/// Test: multibyte fold markers work
+/// Source: fold_spec.lua - "multibyte fold markers work"
+#[test]
+fn test_marker_engine_multibyte_foldmarker() {
+    let mgr = fold_manager(
+        &["«", "é", "»", "{{{", "x", "}}}"],
+        FoldMethod::Marker,
+        FoldOptions {
+            foldmarker: "«,»".to_string(),
+            ..FoldOptions::default()
+        },
+    );
+
+    assert_eq!(ranges(mgr.folds()), vec![(1, 3, 1)]);
+}
+
+// ============================================================================
+// Expr Method Engine Tests
+// ============================================================================
+
+/// Test: foldexpr results build folds
+/// Source: fold.txt - fold-expr
+#[test]
+fn test_expr_engine() {
+    let mut mgr = fold_manager(
+        &["# a", "x", "y", "# b", "z"],
+        FoldMethod::Expr,
+        FoldOptions::default(),
+    );
+    mgr.set_foldexpr(Box::new(|lnum, lines| {
+        if lines[lnum.to_zero_indexed()].starts_with('#') {
+            ">1".to_string()
+        } else {
+            "=".to_string()
+        }
+    }));
+
+    assert_eq!(ranges(mgr.folds()), vec![(1, 3, 1), (4, 5, 1)]);
+}
+
+// ============================================================================
+// Manual Method Engine Tests
+// ============================================================================
+
+/// Test: zf creates closed folds and nests contained folds
+/// Source: fold_spec.lua - zf command
+#[test]
+fn test_manual_engine_create_nests() {
+    let mut mgr = fold_manager(
+        &["1", "2", "3", "4", "5", "6"],
+        FoldMethod::Manual,
+        FoldOptions::default(),
+    );
+
+    mgr.create(LineNr(2), LineNr(3)).unwrap();
+    mgr.create(LineNr(1), LineNr(5)).unwrap();
+    assert_eq!(ranges(mgr.folds()), vec![(1, 5, 1), (2, 3, 2)]);
+    assert!(mgr.is_folded(LineNr(4)));
+
+    // A fold inside an existing one becomes nested in it.
+    mgr.create(LineNr(4), LineNr(5)).unwrap();
+    assert_eq!(ranges(mgr.folds()), vec![(1, 5, 1), (2, 3, 2), (4, 5, 2)]);
+}
+
+/// Test: zd deletes one fold and moves nested folds up a level
+/// Source: fold_spec.lua - zd command
+#[test]
+fn test_manual_engine_delete() {
+    let mut mgr = fold_manager(
+        &["1", "2", "3", "4", "5"],
+        FoldMethod::Manual,
+        FoldOptions::default(),
+    );
+    mgr.create(LineNr(2), LineNr(3)).unwrap();
+    mgr.create(LineNr(1), LineNr(5)).unwrap();
+
+    mgr.delete(LineNr(2)).unwrap();
+    assert_eq!(ranges(mgr.folds()), vec![(2, 3, 1)]);
+
+    assert!(matches!(
+        mgr.delete(LineNr(5)),
+        Err(VimError::Error(490, _))
+    ));
+
+    mgr.delete_all();
+    assert!(mgr.folds().is_empty());
+}
+
+/// Test: zf and zd fail for computed methods
+/// Source: fold.txt - E350, E351
+#[test]
+fn test_engine_create_delete_need_manual() {
+    let mut mgr = fold_manager(&["a", "  b"], FoldMethod::Indent, sw2());
+    assert!(matches!(
+        mgr.create(LineNr(1), LineNr(2)),
+        Err(VimError::Error(350, _))
+    ));
+    assert!(matches!(
+        mgr.delete(LineNr(2)),
+        Err(VimError::Error(351, _))
+    ));
+}
+
+/// Test: manual folds stay anchored when lines are inserted or deleted
+/// Source: fold_spec.lua - "manual folding adjusts with filter"
+#[test]
+fn test_manual_engine_adjust() {
+    let mut mgr = fold_manager(
+        &["1", "2", "3", "4", "5", "6", "7", "8", "9", "10"],
+        FoldMethod::Manual,
+        FoldOptions::default(),
+    );
+    mgr.create(LineNr(4), LineNr(7)).unwrap();
+
+    // Two lines inserted above the fold shift it down.
+    mgr.adjust(LineNr(1), 2);
+    assert_eq!(ranges(mgr.folds()), vec![(6, 9, 1)]);
+
+    // A line inserted inside the fold extends it.
+    mgr.adjust(LineNr(7), 1);
+    assert_eq!(ranges(mgr.folds()), vec![(6, 10, 1)]);
+
+    // Lines inserted just after the fold do not.
+    mgr.adjust(LineNr(11), 3);
+    assert_eq!(ranges(mgr.folds()), vec![(6, 10, 1)]);
+
+    // Deleting lines overlapping the start shrinks it.
+    mgr.adjust(LineNr(5), -2);
+    assert_eq!(ranges(mgr.folds()), vec![(5, 8, 1)]);
+
+    // Deleting the whole fold removes it.
+    mgr.adjust(LineNr(4), -6);
+    assert!(mgr.folds().is_empty());
+}
+
+/// Test: switching to manual keeps the computed folds
+/// Source: fold.txt - fold-manual
+#[test]
+fn test_engine_switch_to_manual_keeps_folds() {
+    let mut mgr = fold_manager(&["a", "  b", "  c", "d"], FoldMethod::Indent, sw2());
+    mgr.set_method(FoldMethod::Manual);
+    assert_eq!(ranges(mgr.folds()), vec![(2, 3, 1)]);
+    mgr.adjust(LineNr(1), 1);
+    assert_eq!(ranges(mgr.folds()), vec![(3, 4, 1)]);
+}
+
+// ============================================================================
+// Open/Close Command Engine Tests
+// ============================================================================
+
+fn nested_manager() -> BufferFoldManager {
+    // 1-8 level 1, 2-6 level 2, 3-4 level 3
+    fold_manager(
+        &[
+            "a", "  b", "    c", "      d", "      e", "    f", "  g", "  h", "i",
+        ],
+        FoldMethod::Indent,
+        sw2(),
+    )
+}
+
+/// Test: zo opens the outermost closed fold, zc closes one level at a time
+/// Source: fold.txt - zo, zc
+#[test]
+fn test_engine_zo_zc() {
+    let mut mgr = nested_manager();
+    assert_eq!(
+        mgr.closed_fold_at(LineNr(4)).map(|f| f.start),
+        Some(LineNr(2))
+    );
+
+    mgr.open(LineNr(4)).unwrap();
+    assert_eq!(
+        mgr.closed_fold_at(LineNr(4)).map(|f| f.start),
+        Some(LineNr(3))
+    );
+    mgr.open_count(LineNr(4), 2).unwrap();
+    assert!(!mgr.is_folded(LineNr(4)));
+
+    mgr.close(LineNr(4)).unwrap();
+    assert_eq!(
+        mgr.closed_fold_at(LineNr(4)).map(|f| f.start),
+        Some(LineNr(4))
+    );
+    mgr.close(LineNr(4)).unwrap();
+    assert_eq!(
+        mgr.closed_fold_at(LineNr(4)).map(|f| f.start),
+        Some(LineNr(3))
+    );
+
+    assert!(matches!(mgr.open(LineNr(9)), Err(VimError::Error(490, _))));
+}
+
+/// Test: za toggles, zO/zC/zA act recursively
+/// Source: fold.txt - za, zO, zC, zA
+#[test]
+fn test_engine_recursive_commands() {
+    let mut mgr = nested_manager();
+
+    mgr.open_recursive(LineNr(4)).unwrap();
+    assert!((1..=9).all(|l| !mgr.is_folded(LineNr(l))));
+
+    mgr.toggle(LineNr(5)).unwrap();
+    assert_eq!(
+        mgr.closed_fold_at(LineNr(5)).map(|f| f.start),
+        Some(LineNr(4))
+    );
+    mgr.toggle(LineNr(5)).unwrap();
+    assert!(!mgr.is_folded(LineNr(5)));
+
+    mgr.close_recursive(LineNr(4)).unwrap();
+    assert_eq!(
+        mgr.closed_fold_at(LineNr(4)).map(|f| f.start),
+        Some(LineNr(2))
+    );
+    mgr.open(LineNr(4)).unwrap();
+    assert_eq!(
+        mgr.closed_fold_at(LineNr(4)).map(|f| f.start),
+        Some(LineNr(3))
+    );
+
+    mgr.toggle_recursive(LineNr(4)).unwrap();
+    assert!(!mgr.is_folded(LineNr(4)));
+    mgr.toggle_recursive(LineNr(4)).unwrap();
+    assert_eq!(
+        mgr.closed_fold_at(LineNr(4)).map(|f| f.start),
+        Some(LineNr(2))
+    );
+}
+
+/// Test: zv opens just enough folds, zx re-applies foldlevel first
+/// Source: fold.txt - zv, zx
+#[test]
+fn test_engine_zv_zx() {
+    let mut mgr = nested_manager();
+    mgr.view_line(LineNr(4));
+    assert!(!mgr.is_folded(LineNr(4)));
+    assert!(!mgr.is_folded(LineNr(7)));
+
+    mgr.open_all();
+    mgr.set_foldlevel(1);
+    mgr.update(LineNr(7));
+    assert!(!mgr.is_folded(LineNr(7)));
+    assert!(mgr.is_folded(LineNr(3)));
+}
+
+/// Test: zM, zR, zm and zr change foldlevel
+/// Source: fold.txt - zM, zR, zm, zr
+#[test]
+fn test_engine_foldlevel_commands() {
+    let mut mgr = nested_manager();
+
+    mgr.open_all();
+    assert_eq!(mgr.foldlevel(), 3);
+    assert!((1..=9).all(|l| !mgr.is_folded(LineNr(l))));
+
+    mgr.fold_more(1);
+    assert_eq!(mgr.foldlevel(), 2);
+    assert!(mgr.is_folded(LineNr(4)));
+    assert!(!mgr.is_folded(LineNr(3)));
+
+    mgr.close_all();
+    assert_eq!(mgr.foldlevel(), 0);
+    assert_eq!(
+        mgr.closed_fold_at(LineNr(4)).map(|f| f.start),
+        Some(LineNr(2))
+    );
+
+    mgr.reduce_folding(1);
+    assert_eq!(mgr.foldlevel(), 1);
+    assert_eq!(
+        mgr.closed_fold_at(LineNr(4)).map(|f| f.start),
+        Some(LineNr(3))
+    );
+
+    mgr.reduce_folding(10);
+    assert_eq!(mgr.foldlevel(), 3);
+}
+
+/// Test: foldclose=all closes folds the cursor leaves
+/// Source: fold.txt - 'foldclose'
+#[test]
+fn test_engine_foldclose_all() {
+    let mut mgr = fold_manager(
+        &["a", "  b", "  c", "d"],
+        FoldMethod::Indent,
+        FoldOptions {
+            foldclose: "all".to_string(),
+            ..sw2()
+        },
+    );
+    mgr.open(LineNr(2)).unwrap();
+
+    mgr.cursor_moved(LineNr(2), LineNr(3));
+    assert!(!mgr.is_folded(LineNr(2)));
+    mgr.cursor_moved(LineNr(3), LineNr(4));
+    assert!(mgr.is_folded(LineNr(2)));
+}
+
+/// Test: foldopen lists the commands that open folds
+/// Source: fold.txt - 'foldopen'
+#[test]
+fn test_engine_foldopen_items() {
+    let options = FoldOptions::default();
+    assert!(options.opens_for("search"));
+    assert!(options.opens_for("hor"));
+    assert!(!options.opens_for("jump"));
+
+    let all = FoldOptions {
+        foldopen: "all".to_string(),
+        ..FoldOptions::default()
+    };
+    assert!(all.opens_for("jump"));
+}
+
+// ============================================================================
+// Fold Navigation Engine Tests
+// ============================================================================
+
+/// Test: [z and ]z move to the start and end of the open fold
+/// Source: fold.txt - [z, ]z
+#[test]
+fn test_engine_fold_start_end() {
+    let mut mgr = nested_manager();
+    mgr.open_all();
+
+    assert_eq!(mgr.fold_start(LineNr(4)), Some(LineNr(3)));
+    assert_eq!(mgr.fold_start(LineNr(3)), Some(LineNr(2)));
+    assert_eq!(mgr.fold_start(LineNr(2)), None);
+    assert_eq!(mgr.fold_end(LineNr(4)), Some(LineNr(5)));
+    assert_eq!(mgr.fold_end(LineNr(5)), Some(LineNr(6)));
+    assert_eq!(mgr.fold_end(LineNr(8)), None);
+}
+
+/// Test: zj and zk move between folds, closed folds count as one
+/// Source: fold.txt - zj, zk
+#[test]
+fn test_engine_zj_zk() {
+    let mut mgr = fold_manager(
+        &["a", "  b", "  c", "d", "  e", "    f", "    g", "h"],
+        FoldMethod::Indent,
+        sw2(),
+    );
+    mgr.open_all();
+
+    assert_eq!(mgr.next_fold_start(LineNr(1)), Some(LineNr(2)));
+    assert_eq!(mgr.next_fold_start(LineNr(2)), Some(LineNr(5)));
+    assert_eq!(mgr.next_fold_start(LineNr(5)), Some(LineNr(6)));
+    assert_eq!(mgr.prev_fold_end(LineNr(8)), Some(LineNr(7)));
+    assert_eq!(mgr.prev_fold_end(LineNr(5)), Some(LineNr(3)));
+
+    mgr.close(LineNr(5)).unwrap();
+    assert_eq!(mgr.next_fold_start(LineNr(5)), None);
+    assert_eq!(mgr.prev_fold_end(LineNr(8)), Some(LineNr(7)));
+}
+
+// ============================================================================
+// Fold Text Engine Tests
+// ============================================================================
+
+/// Test: default fold text shows dashes, count and the cleaned first line
+/// Source: fold.txt - 'foldtext', foldtext()
+#[test]
+fn test_engine_fold_text() {
+    let mgr = fold_manager(
+        &["/* section {{{1 */", "body", "  nested {{{2", "x", "}}}2"],
+        FoldMethod::Marker,
+        FoldOptions::default(),
+    );
+
+    let outer = mgr.get(LineNr(1)).unwrap();
+    assert_eq!(mgr.fold_text(outer), "+--  5 lines: section");
+    let inner = mgr.get(LineNr(4)).unwrap();
+    assert_eq!(mgr.fold_text(inner), "+---  3 lines: nested");
+}
+
+/// Test: no folds remain when the buffer becomes empty
+/// Source: fold_spec.lua - "no folds remain if :delete makes buffer empty"
+#[test]
+fn test_engine_no_folds_after_delete_all() {
+    let mut mgr = fold_manager(&["a", "b", "c"], FoldMethod::Manual, FoldOptions::default());
+    mgr.create(LineNr(2), LineNr(3)).unwrap();
+    mgr.adjust(LineNr(1), -3);
+    assert!(mgr.folds().is_empty());
+}
+
+// ============================================================================
+// Editor Integration Tests
+// ============================================================================
+
+/// Test: j and k treat a closed fold as a single line
+/// Source: fold_spec.lua - cursor movement over closed folds
+#[test]
+fn test_editor_jk_skip_closed_fold() {
+    let mut h = TestHarness::with_lines(&["a", "b", "c", "d", "e"]);
+    h.set_cursor(2, 0);
+    h.feed("zfj");
+    assert_cursor!(h, 2, 0);
+
+    h.feed("k");
+    assert_cursor!(h, 1, 0);
+    h.feed("j");
+    assert_cursor!(h, 2, 0);
+    h.feed("j");
+    assert_cursor!(h, 4, 0);
+    h.feed("k");
+    assert_cursor!(h, 2, 0);
+}
+
+/// Test: dd on a closed fold deletes every line in it
+/// Source: fold_spec.lua - "dd" on closed fold
+#[test]
+fn test_editor_dd_deletes_closed_fold() {
+    let mut h = TestHarness::with_lines(&["a", "b", "c", "d", "e"]);
+    h.set_cursor(2, 0);
+    h.feed("zf2j");
+    h.feed("dd");
+    assert_lines!(h, "a", "e");
+    assert_cursor!(h, 2, 0);
+    assert!(h.editor.folds.folds().is_empty());
+}
+
+/// Test: 'foldopen' decides whether a search opens the fold it lands in
+/// Source: fold_spec.lua / options.txt - 'foldopen' search item
+#[test]
+fn test_editor_search_opens_fold() {
+    let mut h = TestHarness::with_lines(&["a", "b", "target", "d", "e"]);
+    h.set_cursor(2, 0);
+    h.feed("zf2j");
+    h.feed("gg/target<CR>");
+    assert_cursor!(h, 3, 0);
+    assert!(h.editor.closed_fold(LineNr(3)).is_none());
+
+    h.feed("zc");
+    h.feed(":set foldopen-=search<CR>");
+    h.feed("gg/target<CR>");
+    assert_eq!(h.editor.closed_fold(LineNr(3)), Some((LineNr(2), LineNr(4))));
+    assert_cursor!(h, 2, 0);
+}
+
+/// Test: :set foldmethod recomputes folds through the option layer
+/// Source: fold_spec.lua - foldmethod=indent
+#[test]
+fn test_editor_set_foldmethod_indent() {
+    let mut h = TestHarness::with_lines(&["a", "\tb", "\tc", "d"]);
+    h.feed(":set fdm=indent sw=8<CR>");
+    assert_eq!(h.editor.folds.method(), FoldMethod::Indent);
+    assert_eq!(h.editor.closed_fold(LineNr(2)), Some((LineNr(2), LineNr(3))));
+
+    h.feed("zR");
+    assert_eq!(h.editor.options.number("foldlevel"), 1);
+    assert!(h.editor.closed_fold(LineNr(2)).is_none());
+    h.feed("zM");
+    assert_eq!(h.editor.options.number("foldlevel"), 0);
+}
+
+/// Test: the fold line is the fold text padded with the 'fillchars' fold item
+/// Source: options.txt - 'fillchars' fold:c
+#[test]
+fn test_editor_fold_line_fillchars() {
+    let mut h = TestHarness::with_lines(&["a", "b", "c"]);
+    h.feed("zfj");
+    assert_eq!(
+        h.editor.fold_line(LineNr(1), 20).as_deref(),
+        Some("+--  2 lines: a-----")
+    );
+
+    h.feed(":set fillchars=fold:.<CR>");
+    assert_eq!(
+        h.editor.fold_line(LineNr(1), 20).as_deref(),
+        Some("+--  2 lines: a.....")
+    );
+    assert_eq!(h.editor.fold_line(LineNr(3), 20), None);
+}
+
+/// Test: the window keeps its folds for each buffer it shows
+/// Source: fold.txt - "When switching to another buffer and back"
+#[test]
+fn test_editor_folds_per_buffer() {
+    let dir = ScratchDir::new("fold_buffers");
+    let a = dir.file("a.txt", "a1\na2\na3\n");
+    let b = dir.file("b.txt", "b1\nb2\nb3\n");
+    let mut h = TestHarness::new();
+    h.editor.edit(&a).unwrap();
+    h.feed("zfj");
+    assert_eq!(h.editor.closed_fold(LineNr(1)), Some((LineNr(1), LineNr(2))));
+
+    h.editor.edit(&b).unwrap();
+    assert!(h.editor.folds.folds().is_empty());
+    assert_eq!(h.editor.closed_fold(LineNr(1)), None);
+
+    h.editor.edit(&a).unwrap();
+    assert_eq!(h.editor.closed_fold(LineNr(1)), Some((LineNr(1), LineNr(2))));
+}
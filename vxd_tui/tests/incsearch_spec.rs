@@ -0,0 +1,272 @@
+//! Incremental search, search highlighting and `:s` tests ported from
+//! Vim/Neovim tests
+//!
+//! These tests verify moving the cursor while a search pattern is typed
+//! with 'incsearch', stepping with `Ctrl-G`/`Ctrl-T`, the matches
+//! highlighted with 'hlsearch' and `:nohlsearch`, the `:substitute`
+//! command and its 'inccommand' preview.
+//!
+//! Source tests:
+//! - src/testdir/test_search.vim
+//! - src/testdir/test_substitute.vim
+//! - test/functional/ui/inccommand_spec.lua
+
+mod common;
+
+use common::TestHarness;
+use vxd::modes::Mode;
+use vxd::types::LineNr;
+use vxd_tui::incsearch::SearchHighlight;
+
+/// The highlighted matches on all lines, as (line, start, end, current)
+fn highlights(h: &mut TestHarness) -> Vec<(usize, usize, usize, bool)> {
+    let last = LineNr(h.get_lines().len());
+    h.editor
+        .search_highlights(LineNr(1), last)
+        .iter()
+        .map(|hl: &SearchHighlight| (hl.line.0, hl.start, hl.end, hl.current))
+        .collect()
+}
+
+// ============================================================================
+// Incremental Search
+// ============================================================================
+
+/// Test: typing a pattern moves the cursor to the first match
+/// Source: test_search.vim "Test_search_cmdline"
+#[test]
+fn test_incsearch_moves_cursor_while_typing() {
+    let mut h = TestHarness::with_lines(&["foo", "bar", "foobar", "baz"]);
+    h.feed("/ba");
+    assert_mode!(h, Mode::CommandLine(_));
+    assert_cursor!(h, 2, 0);
+    h.feed("z");
+    assert_cursor!(h, 4, 0);
+    h.feed("x");
+    assert_cursor!(h, 1, 0);
+}
+
+/// Test: <Esc> puts the cursor back where the search started
+/// Source: test_search.vim "Test_search_cmdline"
+#[test]
+fn test_incsearch_escape_restores_cursor() {
+    let mut h = TestHarness::with_lines(&["one", "two", "three"]);
+    h.set_cursor(1, 1);
+    h.feed("/thr");
+    assert_cursor!(h, 3, 0);
+    h.feed("<Esc>");
+    assert_mode!(h, Mode::Normal);
+    assert_cursor!(h, 1, 1);
+}
+
+/// Test: <CR> stays on the match that was shown
+/// Source: test_search.vim "Test_search_cmdline"
+#[test]
+fn test_incsearch_enter_lands_on_match() {
+    let mut h = TestHarness::with_lines(&["foo", "bar", "foobar"]);
+    h.feed("/bar<CR>");
+    assert_mode!(h, Mode::Normal);
+    assert_cursor!(h, 2, 0);
+}
+
+/// Test: Ctrl-G and Ctrl-T step to the next and previous match
+/// Source: test_search.vim "Test_search_cmdline3"
+#[test]
+fn test_incsearch_ctrl_g_ctrl_t() {
+    let mut h = TestHarness::with_lines(&["the", "xthe", "xxthe", "xxxthe"]);
+    h.feed("/the");
+    assert_cursor!(h, 2, 1);
+    h.feed("<C-g>");
+    assert_cursor!(h, 3, 2);
+    h.feed("<C-g>");
+    assert_cursor!(h, 4, 3);
+    h.feed("<C-t>");
+    assert_cursor!(h, 3, 2);
+    h.feed("<CR>");
+    assert_cursor!(h, 3, 2);
+}
+
+/// Test: backward search with ? moves to the previous match
+/// Source: test_search.vim "Test_search_cmdline"
+#[test]
+fn test_incsearch_backward() {
+    let mut h = TestHarness::with_lines(&["abc", "xyz", "abc", "xyz"]);
+    h.set_cursor(4, 0);
+    h.feed("?abc");
+    assert_cursor!(h, 3, 0);
+    h.feed("<C-g>");
+    assert_cursor!(h, 1, 0);
+    h.feed("<CR>");
+    assert_cursor!(h, 1, 0);
+}
+
+/// Test: with 'noincsearch' the cursor does not move while typing
+/// Source: test_search.vim "Test_search_cmdline"
+#[test]
+fn test_noincsearch_keeps_cursor() {
+    let mut h = TestHarness::with_lines(&["foo", "bar"]);
+    h.set("noincsearch");
+    h.feed("/bar");
+    assert_cursor!(h, 1, 0);
+    h.feed("<CR>");
+    assert_cursor!(h, 2, 0);
+}
+
+// ============================================================================
+// Search Highlighting
+// ============================================================================
+
+/// Test: the current match and all other matches are highlighted
+/// Source: test_search.vim "Test_incsearch_search_dump"
+#[test]
+fn test_highlights_while_typing() {
+    let mut h = TestHarness::with_lines(&["foo foo", "bar", "foo"]);
+    h.feed("/foo");
+    assert_eq!(
+        highlights(&mut h),
+        vec![(1, 0, 3, false), (1, 4, 7, true), (3, 0, 3, false)]
+    );
+}
+
+/// Test: with 'nohlsearch' only the current match is highlighted
+/// Source: test_search.vim "Test_incsearch_search_dump"
+#[test]
+fn test_nohlsearch_highlights_current_match() {
+    let mut h = TestHarness::with_lines(&["foo foo", "foo"]);
+    h.set("nohlsearch");
+    h.feed("/foo");
+    assert_eq!(highlights(&mut h), vec![(1, 4, 7, true)]);
+    h.feed("<CR>");
+    assert!(highlights(&mut h).is_empty());
+}
+
+/// Test: 'hlsearch' keeps highlighting the last pattern until :nohlsearch
+/// Source: test_search.vim "Test_nohlsearch"
+#[test]
+fn test_hlsearch_and_nohlsearch() {
+    let mut h = TestHarness::with_lines(&["ab ab", "cd", "ab"]);
+    h.feed("/ab<CR>");
+    assert_eq!(
+        highlights(&mut h),
+        vec![(1, 0, 2, false), (1, 3, 5, false), (3, 0, 2, false)]
+    );
+    h.feed(":noh<CR>");
+    assert!(highlights(&mut h).is_empty());
+    h.feed("n");
+    assert_eq!(highlights(&mut h).len(), 3);
+    h.feed(":nohlsearch<CR>");
+    h.set("hlsearch");
+    assert_eq!(highlights(&mut h).len(), 3);
+}
+
+// ============================================================================
+// :substitute
+// ============================================================================
+
+/// Test: :s replaces the first match on the cursor line
+/// Source: test_substitute.vim "Test_sub_cmd_1"
+#[test]
+fn test_substitute_current_line() {
+    let mut h = TestHarness::with_lines(&["aaa", "aaa"]);
+    h.feed(":s/a/b/<CR>");
+    assert_lines!(h, "baa", "aaa");
+}
+
+/// Test: the g flag replaces every match and % covers the whole buffer
+/// Source: test_substitute.vim "Test_sub_cmd_1"
+#[test]
+fn test_substitute_global_whole_buffer() {
+    let mut h = TestHarness::with_lines(&["a-a", "b", "a"]);
+    h.feed(":%s/a/xy/g<CR>");
+    assert_lines!(h, "xy-xy", "b", "xy");
+    assert_cursor!(h, 3, 0);
+}
+
+/// Test: a range limits the lines and any delimiter can be used
+/// Source: test_substitute.vim "Test_sub_cmd_2"
+#[test]
+fn test_substitute_range_and_delimiter() {
+    let mut h = TestHarness::with_lines(&["x/y", "x/y", "x/y"]);
+    h.feed(":2,3s#/#-#<CR>");
+    assert_lines!(h, "x/y", "x-y", "x-y");
+}
+
+/// Test: an empty pattern uses the last search pattern
+/// Source: test_substitute.vim "Test_sub_vi_compatibility"
+#[test]
+fn test_substitute_last_pattern() {
+    let mut h = TestHarness::with_lines(&["one two", "two"]);
+    h.feed("/two<CR>");
+    h.feed(":%s//2/<CR>");
+    assert_lines!(h, "one 2", "2");
+}
+
+/// Test: no match gives E486 and leaves the buffer alone
+/// Source: test_substitute.vim "Test_sub_cmd_1"
+#[test]
+fn test_substitute_pattern_not_found() {
+    let mut h = TestHarness::with_lines(&["abc"]);
+    h.feed(":s/zzz/y/<CR>");
+    assert_lines!(h, "abc");
+    assert!(h.message().contains("E486"));
+}
+
+/// Test: the n flag reports the number of matches without changing text
+/// Source: test_substitute.vim "Test_sub_cmd_count"
+#[test]
+fn test_substitute_count_flag() {
+    let mut h = TestHarness::with_lines(&["aa", "a", "b"]);
+    h.feed(":%s/a//gn<CR>");
+    assert_lines!(h, "aa", "a", "b");
+    assert_eq!(h.editor.message.as_deref(), Some("3 matches on 2 lines"));
+}
+
+// ============================================================================
+// 'inccommand'
+// ============================================================================
+
+/// Test: "nosplit" shows the substitution in the buffer while typing
+/// Source: inccommand_spec.lua "shows replacement in the buffer"
+#[test]
+fn test_inccommand_nosplit_preview() {
+    let mut h = TestHarness::with_lines(&["foo bar", "bar"]);
+    h.feed(":%s/bar/baz");
+    let preview = h.editor.substitute_preview().unwrap();
+    assert_eq!(preview.lines, vec!["foo baz", "baz"]);
+    assert_eq!(preview.highlights.len(), 2);
+    assert_eq!(
+        (preview.highlights[0].start, preview.highlights[0].end),
+        (4, 7)
+    );
+    assert!(preview.split.is_none());
+    assert_lines!(h, "foo bar", "bar");
+    h.feed("<Esc>");
+    assert!(h.editor.substitute_preview().is_none());
+    assert_lines!(h, "foo bar", "bar");
+}
+
+/// Test: "split" also lists the changed lines for the preview window
+/// Source: inccommand_spec.lua "shows preview window"
+#[test]
+fn test_inccommand_split_preview() {
+    let mut h = TestHarness::with_lines(&["a", "b", "a"]);
+    h.set("inccommand=split");
+    h.feed(":%s/a/c");
+    let preview = h.editor.substitute_preview().unwrap();
+    assert_eq!(
+        preview.split,
+        Some(vec!["|1| c".to_string(), "|3| c".to_string()])
+    );
+    h.feed("<CR>");
+    assert_lines!(h, "c", "b", "c");
+}
+
+/// Test: with 'inccommand' empty there is no preview
+/// Source: inccommand_spec.lua "'inccommand' off"
+#[test]
+fn test_inccommand_off() {
+    let mut h = TestHarness::with_lines(&["a"]);
+    h.set("inccommand=");
+    h.feed(":s/a/b");
+    assert!(h.editor.substitute_preview().is_none());
+}
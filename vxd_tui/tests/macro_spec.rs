@@ -6,10 +6,15 @@
 //! - Repeat last macro with @@
 //! - Macro storage in registers
 //! - Macro execution context
+//! - RecordingEnter and RecordingLeave
+//! - Aborting a macro when a command fails
+//! - `:normal` and `:@`
 
 mod common;
 
 use common::TestHarness;
+use vxd::modes::Mode;
+use vxd::options::OptionManager;
 use vxd::registers::{Register, RegisterBank, RegisterContent, RegisterType};
 
 // ============================================================================
@@ -443,3 +448,183 @@ fn test_macro_unnamed_register() {
     let content = h.editor.registers.get(Register::Unnamed).unwrap();
     assert_eq!(content.text, vec!["p"]);
 }
+
+// ============================================================================
+// Recording
+// ============================================================================
+
+/// Test: q{reg} records the typed keys until q, and @{reg} and @@ replay
+/// them
+/// Source: macro_spec.lua "can be recorded and replayed"
+#[test]
+fn test_record_and_replay() {
+    let mut h = TestHarness::with_lines(&["a", "b", "c"]);
+    h.feed("qqAX<Esc>jq");
+    let content = h.editor.registers.get(Register::Named('q')).unwrap();
+    assert_eq!(content.text, vec!["AX\x1bj"]);
+    assert_lines!(h, "aX", "b", "c");
+
+    h.feed("@q");
+    assert_lines!(h, "aX", "bX", "c");
+    assert_cursor!(h, 3, 0);
+    h.feed("@@");
+    assert_lines!(h, "aX", "bX", "cX");
+}
+
+/// Test: q{A-Z} appends the recorded keys to the register
+/// Source: test_registers.vim "Test_recording_with_select_mode"
+#[test]
+fn test_record_append_uppercase() {
+    let mut h = TestHarness::with_lines(&["abc"]);
+    h.feed("qaxq");
+    h.feed("qA<Right>q");
+    let content = h.editor.registers.get(Register::Named('a')).unwrap();
+    assert_eq!(content.text.join(""), "x\u{80}<Right>");
+    assert_eq!(content.reg_type, RegisterType::Characterwise);
+}
+
+/// Test: RecordingEnter and RecordingLeave fire, and reg_recording()
+/// gives the register while recording
+/// Source: autocmd/recording_spec.lua
+#[test]
+fn test_recording_autocmds() {
+    let mut h = TestHarness::with_lines(&["text"]);
+    h.feed(":autocmd RecordingEnter * set ts=3<CR>");
+    h.feed(":autocmd RecordingLeave * set sw=5<CR>");
+    h.feed("qb");
+    assert_eq!(h.editor.reg_recording(), Some('b'));
+    assert_eq!(h.editor.options.get_number("tabstop"), Some(3));
+    assert_ne!(h.editor.options.get_number("shiftwidth"), Some(5));
+    h.feed("q");
+    assert_eq!(h.editor.reg_recording(), None);
+    assert_eq!(h.editor.reg_recorded(), Some('b'));
+    assert_eq!(h.editor.options.get_number("shiftwidth"), Some(5));
+}
+
+/// Test: Q executes the register last recorded into
+/// Source: macro_spec.lua "can be replayed with Q"
+#[test]
+fn test_q_replays_last_recorded() {
+    let mut h = TestHarness::with_lines(&["a"]);
+    h.feed("qqA1<Esc>q");
+    h.feed("2Q");
+    assert_lines!(h, "a111");
+}
+
+// ============================================================================
+// Execution
+// ============================================================================
+
+/// Test: a count executes the register that many times
+/// Source: macro_spec.lua "G3Q"
+#[test]
+fn test_execute_with_count() {
+    let mut h = TestHarness::with_lines(&["ab"]);
+    let _ = h
+        .editor
+        .registers
+        .set(Register::Named('a'), RegisterContent::characterwise("AX\x1b"));
+    h.feed("3@a");
+    assert_lines!(h, "abXXX");
+}
+
+/// Test: a recursive macro runs until a motion fails
+/// Source: test_registers.vim "Test_recursive_macro"
+#[test]
+fn test_recursive_macro_stops_on_failed_motion() {
+    let mut h = TestHarness::with_lines(&["1", "2", "3"]);
+    let _ = h.editor.registers.set(
+        Register::Named('a'),
+        RegisterContent::characterwise("A!\x1bj@a"),
+    );
+    h.feed("@a");
+    assert_lines!(h, "1!", "2!", "3!");
+    assert_eq!(h.editor.reg_executing(), None);
+    // Keys typed after the macro are not flushed
+    h.feed("x");
+    assert_lines!(h, "1!", "2!", "3");
+}
+
+/// Test: an error in a macro aborts the rest of it
+/// Source: test_registers.vim "Test_execute_register"
+#[test]
+fn test_error_aborts_macro() {
+    let mut h = TestHarness::with_lines(&["one"]);
+    let _ = h.editor.registers.set(
+        Register::Named('a'),
+        RegisterContent::characterwise(":nosuchcmd\rAX\x1b"),
+    );
+    h.feed("@a");
+    assert_lines!(h, "one");
+    assert!(h.message().contains("nosuchcmd"));
+}
+
+/// Test: @: repeats the last command line, and @@ repeats it again
+/// Source: test_registers.vim "Test_execute_register"
+#[test]
+fn test_at_colon_repeats_command_line() {
+    let mut h = TestHarness::with_lines(&["a a", "a a", "a a"]);
+    h.feed(":s/a/b/<CR>");
+    h.feed("j@:");
+    assert_lines!(h, "b a", "b a", "a a");
+    h.feed("j@@");
+    assert_lines!(h, "b a", "b a", "b a");
+}
+
+/// Test: @@ and @: without a previous register or command line fail
+/// Source: test_registers.vim "Test_execute_register"
+#[test]
+fn test_at_without_previous() {
+    let mut h = TestHarness::with_lines(&["x"]);
+    h.feed("@@");
+    assert!(h.message().contains("E748"));
+    h.feed("@:");
+    assert!(h.message().contains("E30"));
+}
+
+// ============================================================================
+// :normal and :@
+// ============================================================================
+
+/// Test: :{range}normal executes the keys on each line, ending Insert
+/// mode
+/// Source: test_normal.vim "Test_normal_command"
+#[test]
+fn test_normal_range() {
+    let mut h = TestHarness::with_lines(&["a", "b", "c"]);
+    h.feed(":2,3normal Ax<CR>");
+    assert_lines!(h, "a", "bx", "cx");
+    assert_eq!(h.mode(), Mode::Normal);
+
+    // Keys in the command line are literal, <Esc> is the character
+    h.feed(":1norm ihi\x1b0x<CR>");
+    assert_lines!(h, "ia", "bx", "cx");
+}
+
+/// Test: a failing motion aborts :normal for that line only
+/// Source: test_normal.vim "Test_normal_command"
+#[test]
+fn test_normal_aborts_line() {
+    let mut h = TestHarness::with_lines(&["a,b", "cd", "e,f"]);
+    h.feed(":%norm f,x<CR>");
+    assert_lines!(h, "ab", "cd", "ef");
+}
+
+/// Test: :[addr]@{reg} executes the lines of the register as Ex commands
+/// on line addr
+/// Source: test_registers.vim "Test_execute_reg_as_ex_cmd"
+#[test]
+fn test_ex_at_register() {
+    let mut h = TestHarness::with_lines(&["x", "x", "x"]);
+    let _ = h.editor.registers.set(
+        Register::Named('c'),
+        RegisterContent::linewise(vec!["s/x/y/".into(), "normal Az".into()]),
+    );
+    h.feed(":2@c<CR>");
+    assert_lines!(h, "x", "yz", "x");
+    h.feed(":3@@<CR>");
+    assert_lines!(h, "x", "yz", "yz");
+    h.feed(":1normal Aw<CR>");
+    h.feed(":@:<CR>");
+    assert_lines!(h, "xww", "yz", "yz");
+}
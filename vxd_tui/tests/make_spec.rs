@@ -0,0 +1,176 @@
+//! `:make` and `:grep` tests ported from Vim/Neovim tests
+//!
+//! These tests verify running 'makeprg' and 'grepprg' through 'shell',
+//! collecting their output with 'shellpipe', and turning it into a
+//! quickfix or location list with 'errorformat' and 'grepformat'.  Local
+//! shell scripts print canned compiler output.
+//!
+//! Source tests:
+//! - src/testdir/test_makeencoding.vim
+//! - src/testdir/test_quickfix.vim
+
+mod common;
+
+use common::{ScratchDir, TestHarness};
+use vxd::buffer::{Buffer, BufferManager};
+use vxd::options::OptionManager;
+
+/// A directory with a source file and a script printing compiler errors
+/// for it, with 'makeprg' set to run the script
+fn with_compiler(name: &str) -> (TestHarness, ScratchDir, String) {
+    let dir = ScratchDir::new(name);
+    let source = dir.file("main.c", "int main()\n{\n  return x;\n}\n");
+    let script = dir.file(
+        "compile.sh",
+        &format!(
+            "echo 'Compiling main.c'\n\
+             echo '{0}:3:10: error: x undeclared' >&2\n\
+             echo '{0}:1:5: warning: old style'\n\
+             echo \"args: $*\"\n",
+            source
+        ),
+    );
+    let mut h = TestHarness::with_lines(&["start"]);
+    h.feed(&format!(":set makeprg=sh\\ {}<CR>", script));
+    (h, dir, source)
+}
+
+// ============================================================================
+// :make
+// ============================================================================
+
+/// Test: :make parses the output with 'errorformat' and goes to the
+/// first error
+/// Source: test_quickfix.vim "Test_make"
+#[test]
+fn test_make_jumps_to_first_error() {
+    let (mut h, _dir, source) = with_compiler("jump");
+    h.feed(":make<CR>");
+    assert_eq!(h.editor.buffers.current().name(), source);
+    assert_cursor!(h, 3, 9);
+    assert_eq!(h.message(), "(2 of 4): error: x undeclared");
+
+    let list = h.editor.quickfix.current().unwrap();
+    assert_eq!(list.len(), 4);
+    assert_eq!(list.valid_indices(), vec![1, 2]);
+    assert!(list.title.starts_with(":sh "));
+    h.feed(":cnext<CR>");
+    assert_cursor!(h, 1, 4);
+}
+
+/// Test: :make! fills the list without jumping
+/// Source: test_quickfix.vim "Test_make"
+#[test]
+fn test_make_bang_no_jump() {
+    let (mut h, _dir, _) = with_compiler("bang");
+    h.feed(":make!<CR>");
+    assert_lines!(h, "start");
+    assert_eq!(h.editor.quickfix.current().unwrap().idx, 1);
+}
+
+/// Test: the arguments replace $* in 'makeprg' or are appended, and %
+/// is the current file name
+/// Source: test_quickfix.vim "Test_make"
+#[test]
+fn test_make_arguments() {
+    let (mut h, dir, _) = with_compiler("args");
+    h.editor.buffers.current_mut().set_name("cur.txt").unwrap();
+    h.feed(":make! all %<CR>");
+    let list = h.editor.quickfix.current().unwrap();
+    assert_eq!(list.entries[3].text, "args: all cur.txt");
+
+    let script = dir.join("compile.sh");
+    h.feed(&format!(":set mp=sh\\ {}\\ $*\\ end<CR>", script.display()));
+    h.feed(":make! first<CR>");
+    let list = h.editor.quickfix.current().unwrap();
+    assert_eq!(list.entries[3].text, "args: first end");
+}
+
+/// Test: the environment set with :let $NAME is passed to 'makeprg'
+/// Source: :help :let-environment
+#[test]
+fn test_make_environment() {
+    let (mut h, dir, _) = with_compiler("env");
+    let script = dir.file("env.sh", "echo \"env: $VXD_MAKE_ENV\"\n");
+    h.feed(&format!(":set makeprg=sh\\ {}<CR>", script));
+    h.feed(":let $VXD_MAKE_ENV = 'from vxd'<CR>");
+    h.feed(":make!<CR>");
+    let list = h.editor.quickfix.current().unwrap();
+    assert_eq!(list.entries[0].text, "env: from vxd");
+}
+
+/// Test: 'shellpipe' with %s gives where the error file goes
+/// Source: test_quickfix.vim "Test_make"
+#[test]
+fn test_make_shellpipe() {
+    let (mut h, _dir, _) = with_compiler("pipe");
+    h.set("shellpipe=>%s");
+    h.feed(":make!<CR>");
+    let list = h.editor.quickfix.current().unwrap();
+    // Without 2>&1 the error on stderr is not collected
+    assert_eq!(list.len(), 3);
+    assert_eq!(list.valid_indices(), vec![1]);
+}
+
+/// Test: :lmake fills the location list
+/// Source: test_quickfix.vim "Test_make"
+#[test]
+fn test_lmake() {
+    let (mut h, _dir, _) = with_compiler("lmake");
+    h.feed(":lmake<CR>");
+    assert!(h.editor.quickfix.is_empty());
+    assert_eq!(h.editor.loclist().current().unwrap().len(), 4);
+    assert_cursor!(h, 3, 9);
+}
+
+/// Test: a shell that cannot be run leaves no error file
+/// Source: test_quickfix.vim "Test_make"
+#[test]
+fn test_make_no_shell() {
+    let (mut h, _dir, _) = with_compiler("noshell");
+    h.set("shell=/nonexistent/shell");
+    h.feed(":make<CR>");
+    assert!(h.message().contains("E40: Can't open errorfile"));
+    assert!(h.editor.quickfix.is_empty());
+}
+
+/// Test: QuickFixCmdPre and QuickFixCmdPost fire for :make
+/// Source: test_quickfix.vim "Test_Autocmd"
+#[test]
+fn test_make_autocmds() {
+    let (mut h, _dir, _) = with_compiler("autocmd");
+    h.feed(":autocmd QuickFixCmdPre make set ts=3<CR>");
+    h.feed(":autocmd QuickFixCmdPost make set sw=5<CR>");
+    h.feed(":make!<CR>");
+    assert_eq!(h.editor.options.get_number("tabstop"), Some(3));
+    assert_eq!(h.editor.options.get_number("shiftwidth"), Some(5));
+}
+
+// ============================================================================
+// :grep
+// ============================================================================
+
+/// Test: :grep runs 'grepprg' and parses its output with 'grepformat'
+/// Source: test_quickfix.vim "Test_grep"
+#[test]
+fn test_grep() {
+    let dir = ScratchDir::new("grep");
+    let a = dir.file("a.txt", "one\nneedle here\n");
+    dir.file("b.txt", "needle again\n");
+    let mut h = TestHarness::with_lines(&["start"]);
+    h.feed(&format!(
+        ":grep needle {}/a.txt {}/b.txt<CR>",
+        dir.display(),
+        dir.display()
+    ));
+    let list = h.editor.quickfix.current().unwrap();
+    assert_eq!(list.len(), 2);
+    assert_eq!(list.entries[1].text, "needle again");
+    assert_eq!(h.editor.buffers.current().name(), a);
+    assert_cursor!(h, 2, 0);
+    assert_eq!(h.message(), "(1 of 2): needle here");
+
+    h.feed(&format!(":lgrep! again {}/b.txt<CR>", dir.display()));
+    assert_eq!(h.editor.loclist().current().unwrap().len(), 1);
+    assert_eq!(h.editor.quickfix.len(), 1);
+}
@@ -368,3 +368,76 @@ fn test_change_list_basic() {
 
     assert_eq!(h.editor.marks.change_list().len(), 2);
 }
+
+fn set_mark(h: &mut TestHarness, name: char, line: usize, col: usize) {
+    h.editor
+        .marks
+        .set(
+            Mark::Local(name),
+            MarkValue::new(CursorPosition::new(LineNr(line), col)),
+        )
+        .unwrap();
+}
+
+fn mark_position(h: &TestHarness, name: char) -> (usize, usize) {
+    let value = h.editor.marks.get(Mark::Local(name)).unwrap();
+    (value.position.line.0, value.position.col)
+}
+
+/// Test: marks follow text inserted or deleted before them in their line
+/// Source: Vim's mark_col_adjust()
+#[test]
+fn test_marks_follow_edits_within_line() {
+    let mut h = TestHarness::new();
+    h.set_lines(&["hello world"]);
+    set_mark(&mut h, 'a', 1, 6);
+    set_mark(&mut h, 'b', 1, 2);
+
+    h.set_cursor(1, 4);
+    h.feed("iab<Esc>");
+    assert_eq!(mark_position(&h, 'a'), (1, 8));
+    assert_eq!(mark_position(&h, 'b'), (1, 2));
+
+    h.feed("0x");
+    assert_eq!(mark_position(&h, 'a'), (1, 7));
+    assert_eq!(mark_position(&h, 'b'), (1, 1));
+}
+
+/// Test: marks follow their text when a line is split and joined again
+/// Source: Vim's mark_col_adjust()
+#[test]
+fn test_marks_follow_split_and_join() {
+    let mut h = TestHarness::new();
+    h.set_lines(&["hello world", "last"]);
+    set_mark(&mut h, 'a', 1, 8);
+    set_mark(&mut h, 'b', 2, 1);
+
+    h.set_cursor(1, 5);
+    h.feed("i<CR><Esc>");
+    assert_lines!(h, "hello", " world", "last");
+    assert_eq!(mark_position(&h, 'a'), (2, 3));
+    assert_eq!(mark_position(&h, 'b'), (3, 1));
+
+    h.set_cursor(2, 0);
+    h.feed("i<BS><Esc>");
+    assert_lines!(h, "hello world", "last");
+    assert_eq!(mark_position(&h, 'a'), (1, 8));
+    assert_eq!(mark_position(&h, 'b'), (2, 1));
+}
+
+/// Test: adjust() moves marks on the edited line by column
+/// Source: Vim's mark_col_adjust()
+#[test]
+fn test_adjust_moves_marks_by_column() {
+    let mut h = TestHarness::new();
+    h.set_lines(&["abcdef"]);
+    set_mark(&mut h, 'a', 1, 4);
+
+    // Three bytes inserted at column 1
+    h.editor.marks.adjust(LineNr(1), 1, 0, 3);
+    assert_eq!(mark_position(&h, 'a'), (1, 7));
+
+    // Line split at column 2
+    h.editor.marks.adjust(LineNr(1), 2, 1, 0);
+    assert_eq!(mark_position(&h, 'a'), (2, 5));
+}
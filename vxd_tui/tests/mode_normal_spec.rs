@@ -179,6 +179,21 @@ fn test_dollar_moves_to_end() {
     assert_eq!(col, 4); // Last char
 }
 
+/// Test: 'w' on the last word stops on its last character, and 'dw'
+/// deletes up to the end
+/// Source: test_normal.vim "Test_normal_word_move"
+#[test]
+fn test_w_on_last_word() {
+    let mut h = TestHarness::new();
+    h.set_lines(&["one café"]);
+    h.set_cursor(1, 4);
+
+    h.feed("w");
+    assert_eq!(h.cursor(), (1, 7)); // Start of "é"
+    h.feed("0wdw");
+    assert_eq!(h.content(), "one ");
+}
+
 /// Test: 'G' moves to last line
 /// Source: basic Vim behavior
 #[test]
@@ -0,0 +1,189 @@
+//! Modeline tests ported from Vim tests
+//!
+//! These tests verify modelines in the files edited:
+//! - Both forms, `vi:`, `vim:`, `Vim:` and `ex:`, and `vim{version}:`
+//! - Only buffer and window options are set; others are refused with E520,
+//!   expression options with E992 unless 'modelineexpr' is set
+//! - 'modeline' and 'modelines'
+//! - Modelines are applied after the `BufRead` autocommands and after
+//!   `:doautocmd` without `<nomodeline>`
+//!
+//! Source tests:
+//! - src/testdir/test_modeline.vim
+//! - src/testdir/test_autocmd.vim
+
+mod common;
+
+use common::{ScratchDir, TestHarness};
+use vxd::fileedit::FileEditor;
+
+/// Edit a new file in `dir` with `lines`
+fn edit(h: &mut TestHarness, dir: &std::path::Path, name: &str, lines: &[&str]) {
+    let path = dir.join(name);
+    std::fs::write(&path, format!("{}\n", lines.join("\n"))).unwrap();
+    h.editor.edit(&path.to_string_lossy()).unwrap();
+}
+
+// ============================================================================
+// Forms
+// ============================================================================
+
+/// Test: both forms set options, the set form up to its colon; only the
+/// local values of the buffer are set
+/// Source: test_modeline.vim "Test_modeline_basic"
+#[test]
+fn test_forms() {
+    let dir = ScratchDir::new("forms");
+    let mut h = TestHarness::new();
+    edit(&mut h, &dir, "a.txt", &["vi:noai:sw=3 ts=6", "text"]);
+    assert_eq!(h.editor.options.number("shiftwidth"), 3);
+    assert_eq!(h.editor.options.number("tabstop"), 6);
+    assert_lines!(h, "vi:noai:sw=3 ts=6", "text");
+
+    edit(&mut h, &dir, "b.txt", &["text", "/* vim: set ai tw=75: ts=2 */"]);
+    assert!(h.editor.options.flag("autoindent"));
+    assert_eq!(h.editor.options.number("textwidth"), 75);
+    assert_eq!(h.editor.options.number("tabstop"), 8);
+    h.feed(":setglobal ts?<CR>");
+    assert_eq!(h.message(), "  tabstop=8");
+
+    h.editor.edit(&dir.join("a.txt").to_string_lossy()).unwrap();
+    assert_eq!(h.editor.options.number("tabstop"), 6);
+}
+
+/// Test: a colon in a value is written `\:`
+/// Source: test_modeline.vim "Test_modeline_colon"
+#[test]
+fn test_escaped_colon() {
+    let dir = ScratchDir::new("colon");
+    let mut h = TestHarness::new();
+    edit(&mut h, &dir, "a.txt", &["# vim: set cc=1 fdm=marker fmr=a\\:,b:"]);
+    assert_eq!(h.editor.options.string("colorcolumn"), "1");
+    assert_eq!(h.editor.options.string("foldmarker"), "a:,b");
+}
+
+/// Test: a versioned modeline only applies to the versions it names;
+/// `Vim:` needs `set` and `ex:` white space before it
+/// Source: test_modeline.vim "Test_modeline_version"
+#[test]
+fn test_versions() {
+    let dir = ScratchDir::new("version");
+    let mut h = TestHarness::new();
+    edit(&mut h, &dir, "a.txt", &["# vim600: ts=3", "# vim<701: sw=3"]);
+    assert_eq!(h.editor.options.number("tabstop"), 3);
+    assert_eq!(h.editor.options.number("shiftwidth"), 8);
+
+    edit(&mut h, &dir, "b.txt", &["Vim: tw=20", "ex:sw=5", " Vim: set tw=30:"]);
+    assert_eq!(h.editor.options.number("textwidth"), 30);
+    assert_eq!(h.editor.options.number("shiftwidth"), 8);
+}
+
+// ============================================================================
+// Security
+// ============================================================================
+
+/// Test: global options and ones that run programs are not set
+/// Source: test_modeline.vim "Test_modeline_fails_always"
+#[test]
+fn test_not_allowed() {
+    let dir = ScratchDir::new("secure");
+    let mut h = TestHarness::new();
+    edit(&mut h, &dir, "a.txt", &["vim: set makeprg=rm\\ -rf ts=2:"]);
+    assert_eq!(h.message(), "E520: Not allowed in a modeline: makeprg=rm -rf");
+    assert_eq!(h.editor.options.string("makeprg"), "make");
+    assert_eq!(h.editor.options.number("tabstop"), 8);
+
+    edit(&mut h, &dir, "b.txt", &["vim: set ic:", "vim: set nomodeline:"]);
+    assert_eq!(h.message(), "E520: Not allowed in a modeline: ic");
+    assert!(!h.editor.options.flag("ignorecase"));
+    assert!(h.editor.options.flag("modeline"));
+}
+
+/// Test: an error stops applying modelines, also the ones in the next
+/// lines
+/// Source: test_modeline.vim "Test_modeline_fails_always"
+#[test]
+fn test_error_stops() {
+    let dir = ScratchDir::new("stop");
+    let mut h = TestHarness::new();
+    let lines = ["vim: set ic ts=2:", "vim: set sw=3:", "vim: set mp=x:"];
+    edit(&mut h, &dir, "a.txt", &lines);
+    assert_eq!(h.message(), "E520: Not allowed in a modeline: ic");
+    assert_eq!(h.editor.options.number("tabstop"), 8);
+    assert_eq!(h.editor.options.number("shiftwidth"), 8);
+}
+
+/// Test: an expression option needs 'modelineexpr'
+/// Source: test_modeline.vim "Test_modeline_fails_modelineexpr"
+#[test]
+fn test_modelineexpr() {
+    let dir = ScratchDir::new("expr");
+    let mut h = TestHarness::new();
+    edit(&mut h, &dir, "a.txt", &["vim: set fde=v\\:lnum:"]);
+    assert_eq!(
+        h.message(),
+        "E992: Not allowed in a modeline when 'modelineexpr' is off: fde=v:lnum"
+    );
+    assert_eq!(h.editor.options.string("foldexpr"), "0");
+
+    h.feed(":set modelineexpr<CR>");
+    edit(&mut h, &dir, "b.txt", &["vim: set fde=v\\:lnum:"]);
+    assert_eq!(h.editor.options.string("foldexpr"), "v:lnum");
+}
+
+// ============================================================================
+// Options
+// ============================================================================
+
+/// Test: 'nomodeline' and 'modelines' limit the lines looked at
+/// Source: test_modeline.vim "Test_modeline_disable"
+#[test]
+fn test_modeline_options() {
+    let dir = ScratchDir::new("options");
+    let mut h = TestHarness::new();
+    h.feed(":set nomodeline<CR>");
+    edit(&mut h, &dir, "a.txt", &["vim: ts=2"]);
+    assert_eq!(h.editor.options.number("tabstop"), 8);
+
+    h.feed(":set modeline modelines=1<CR>");
+    edit(&mut h, &dir, "b.txt", &["x", "vim: ts=2", "vim: sw=2", "x"]);
+    assert_eq!(h.editor.options.number("tabstop"), 8);
+    assert_eq!(h.editor.options.number("shiftwidth"), 8);
+    edit(&mut h, &dir, "c.txt", &["x", "vim: ts=2", "vim: sw=2"]);
+    assert_eq!(h.editor.options.number("shiftwidth"), 2);
+}
+
+// ============================================================================
+// Autocommands
+// ============================================================================
+
+/// Test: modelines overrule the BufRead autocommands, and are applied
+/// after :doautocmd unless <nomodeline> is given
+/// Source: test_autocmd.vim "Test_doautocmd"
+#[test]
+fn test_after_autocommands() {
+    let dir = ScratchDir::new("autocmd");
+    let mut h = TestHarness::new();
+    h.feed(":au BufRead *.txt set ts=3 sw=3<CR>");
+    edit(&mut h, &dir, "a.txt", &["vim: ts=4"]);
+    assert_eq!(h.editor.options.number("tabstop"), 4);
+    assert_eq!(h.editor.options.number("shiftwidth"), 3);
+
+    h.feed(":doautocmd <lt>nomodeline> BufRead<CR>");
+    assert_eq!(h.editor.options.number("tabstop"), 3);
+    h.feed(":doautocmd BufRead<CR>");
+    assert_eq!(h.editor.options.number("tabstop"), 4);
+}
+
+/// Test: modelines are applied also when a BufRead autocommand fails,
+/// and the error of the autocommand is given
+/// Source: test_autocmd.vim "Test_doautocmd"
+#[test]
+fn test_after_failing_autocommand() {
+    let dir = ScratchDir::new("autocmd-error");
+    let mut h = TestHarness::new();
+    h.feed(":au BufRead *.txt set bogus<CR>");
+    edit(&mut h, &dir, "a.txt", &["vim: ts=4"]);
+    assert_eq!(h.editor.options.number("tabstop"), 4);
+    assert_eq!(h.message(), "E518: Unknown option: bogus");
+}
@@ -15,6 +15,8 @@
 
 mod common;
 
+use common::{ScratchDir, TestHarness};
+use vxd::fileedit::FileEditor;
 use vxd::options::{options, OptionDef, OptionScope, OptionValue};
 
 // ============================================================================
@@ -530,6 +532,56 @@ fn test_buffer_option_concept() {
     assert_eq!(scope, OptionScope::Buffer);
 }
 
+// ============================================================================
+// :setlocal and :setglobal
+// ============================================================================
+
+/// Test: :setlocal sets the local value, :setglobal the global one, and
+/// both set the value of a global option
+/// Source: Vim :h :setlocal
+#[test]
+fn test_setlocal_setglobal() {
+    let mut h = TestHarness::new();
+    h.feed(":setlocal ts=4<CR>");
+    assert_eq!(h.editor.options.number("tabstop"), 4);
+    h.feed(":setglobal ts?<CR>");
+    assert_eq!(h.message(), "  tabstop=8");
+
+    h.feed(":setglobal sw=2 ic<CR>");
+    assert_eq!(h.editor.options.number("shiftwidth"), 8);
+    assert!(h.editor.options.flag("ignorecase"));
+    h.feed(":setglobal sw?<CR>");
+    assert_eq!(h.message(), "  shiftwidth=2");
+    h.feed(":setlocal noic<CR>");
+    assert!(!h.editor.options.flag("ignorecase"));
+}
+
+/// Test: each buffer keeps its local values, a buffer edited for the
+/// first time starts with the global values; window options stay with
+/// the window
+/// Source: Vim :h local-options
+#[test]
+fn test_local_values_per_buffer() {
+    let dir = ScratchDir::new("local-values");
+    let a = dir.file("a.txt", "a\n");
+    let b = dir.file("b.txt", "b\n");
+    let mut h = TestHarness::new();
+    h.editor.edit(&a).unwrap();
+    h.feed(":setlocal ts=4 nu<CR>:setglobal sw=2<CR>");
+
+    h.editor.edit(&b).unwrap();
+    assert_eq!(h.editor.options.number("tabstop"), 8);
+    assert_eq!(h.editor.options.number("shiftwidth"), 2);
+    assert!(h.editor.options.flag("number"));
+    h.feed(":set ts=6<CR>");
+
+    h.editor.edit(&a).unwrap();
+    assert_eq!(h.editor.options.number("tabstop"), 4);
+    assert_eq!(h.editor.options.number("shiftwidth"), 8);
+    h.editor.edit(&b).unwrap();
+    assert_eq!(h.editor.options.number("tabstop"), 6);
+}
+
 // ============================================================================
 // Common Option Default Value Tests (Conceptual)
 // ============================================================================
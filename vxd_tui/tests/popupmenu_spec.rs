@@ -0,0 +1,205 @@
+//! Completion popup menu tests ported from Vim/Neovim tests
+//!
+//! These tests verify where the popup menu is placed, the 'pumheight' and
+//! 'pumwidth' options, scrolling, the arrow keys and the info float.
+//!
+//! Source tests:
+//! - test/functional/ui/popupmenu_spec.lua
+//! - src/testdir/test_popup.vim
+
+mod common;
+
+use common::TestHarness;
+use vxd::completion::{CompletionEngine, CompletionItem};
+use vxd::cursor::Cursor;
+use vxd_tui::popupmenu::{PopupMenu, PumAnchor};
+
+/// Anchor the menu with the buffer drawn at the top left of a 20x60 screen
+fn popup_menu(h: &mut TestHarness) -> Option<PopupMenu> {
+    let line = h.editor.current_line();
+    let start = h.editor.completion.state().start_col.min(line.len());
+    let anchor = PumAnchor {
+        row: h.editor.cursor.line().to_zero_indexed(),
+        col: line[..start].chars().count(),
+        screen_rows: 20,
+        screen_cols: 60,
+    };
+    h.editor.popup_menu(anchor)
+}
+
+fn numbered_words(count: usize) -> String {
+    (0..count)
+        .map(|i| format!("word{:02}", i))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// ============================================================================
+// Placement
+// ============================================================================
+
+/// Test: the menu is shown below the cursor at the completion start column
+/// Source: popupmenu_spec.lua
+#[test]
+fn test_menu_at_completion_start_column() {
+    let mut h = TestHarness::with_lines(&["", "food fool"]);
+    h.feed("ix fo<C-n>");
+    let menu = popup_menu(&mut h).unwrap();
+    assert_eq!((menu.row, menu.col), (1, 1));
+    assert_eq!(menu.height(), 2);
+    assert_eq!(menu.rows[0].text.trim_end(), " food");
+    assert!(menu.rows[0].selected);
+    assert!(!menu.rows[1].selected);
+}
+
+/// Test: no menu for a single match with the default 'completeopt'
+/// Source: :help 'completeopt'
+#[test]
+fn test_no_menu_for_single_match() {
+    let mut h = TestHarness::with_lines(&["", "alphabet"]);
+    h.feed("ial<C-n>");
+    assert!(popup_menu(&mut h).is_none());
+    h.feed("<C-e><Esc>");
+    h.set("completeopt=menuone");
+    h.feed("a<C-n>");
+    assert_eq!(popup_menu(&mut h).unwrap().height(), 1);
+}
+
+/// Test: 'pumwidth' sets the minimum width of the menu
+/// Source: test_popup.vim
+#[test]
+fn test_pumwidth_minimum() {
+    let mut h = TestHarness::with_lines(&["", "ab abc"]);
+    h.feed("ia<C-n>");
+    assert_eq!(popup_menu(&mut h).unwrap().width, 15);
+    h.set("pumwidth=4");
+    assert_eq!(popup_menu(&mut h).unwrap().width, 5);
+}
+
+// ============================================================================
+// Scrolling
+// ============================================================================
+
+/// Test: 'pumheight' limits the rows and the menu scrolls with the selection
+/// Source: test_popup.vim
+#[test]
+fn test_pumheight_scrolls_with_selection() {
+    let words = numbered_words(10);
+    let mut h = TestHarness::with_lines(&["", &words]);
+    h.set("pumheight=3");
+    h.feed("iwo<C-n>");
+    let menu = popup_menu(&mut h).unwrap();
+    assert_eq!((menu.height(), menu.first), (3, 0));
+    assert!(menu.scrollbar.is_some());
+
+    h.feed("<C-n><C-n><C-n>");
+    let menu = popup_menu(&mut h).unwrap();
+    assert_eq!(menu.first, 1);
+    assert!(menu.rows[2].selected);
+    assert_eq!(h.get_lines()[0], "word03");
+}
+
+/// Test: <PageDown> and <PageUp> move the selection by a page
+/// Source: :help popupmenu-keys
+#[test]
+fn test_page_keys_move_by_page() {
+    let words = numbered_words(10);
+    let mut h = TestHarness::with_lines(&["", &words]);
+    h.set("pumheight=4");
+    h.feed("iwo<C-n>");
+    popup_menu(&mut h);
+    h.feed("<PageDown>");
+    assert_eq!(h.editor.completion.state().selected, Some(4));
+    assert_eq!(popup_menu(&mut h).unwrap().first, 1);
+    h.feed("<PageDown><PageDown>");
+    assert_eq!(h.editor.completion.state().selected, Some(9));
+    h.feed("<PageUp>");
+    assert_eq!(h.editor.completion.state().selected, Some(5));
+    // The text is not changed by the page keys
+    assert_eq!(h.get_lines()[0], "word00");
+}
+
+// ============================================================================
+// Popup Menu Keys
+// ============================================================================
+
+/// Test: <Down> and <Up> select without inserting, <CR> inserts
+/// Source: :help popupmenu-keys
+#[test]
+fn test_arrow_keys_select_and_enter_inserts() {
+    let mut h = TestHarness::with_lines(&["", "food fool"]);
+    h.feed("ifo<C-n>");
+    h.feed("<Down>");
+    assert_eq!(h.get_lines()[0], "food");
+    assert_eq!(h.editor.completion.state().selected, Some(1));
+    h.feed("<CR>");
+    assert_eq!(h.get_lines()[0], "fool");
+    assert!(!h.editor.completion_active());
+    assert_eq!(h.editor.completed_item().unwrap().word, "fool");
+}
+
+/// Test: <CR> on an inserted match ends completion and starts a new line
+/// Source: :help popupmenu-keys
+#[test]
+fn test_enter_after_inserted_match() {
+    let mut h = TestHarness::with_lines(&["", "food fool"]);
+    h.feed("ifo<C-n><CR>x<Esc>");
+    assert_lines!(h, "food", "x", "food fool");
+}
+
+/// Test: <Up> from the first match goes back to the original text
+/// Source: :help popupmenu-keys
+#[test]
+fn test_up_selects_original() {
+    let mut h = TestHarness::with_lines(&["", "food fool"]);
+    h.set("completeopt=menu,noinsert");
+    h.feed("ifo<C-n><Up>");
+    assert_eq!(h.editor.completion.state().selected, None);
+    h.feed("<Up><C-y><Esc>");
+    assert_eq!(h.get_lines()[0], "fool");
+}
+
+/// Test: arrow keys move the cursor when no menu is shown
+/// Source: :help popupmenu-keys
+#[test]
+fn test_arrow_keys_without_menu() {
+    let mut h = TestHarness::with_lines(&["", "alphabet"]);
+    h.feed("ial<C-n><Down>");
+    assert!(!h.editor.completion_active());
+    assert_eq!(h.editor.cursor.line().0, 2);
+}
+
+// ============================================================================
+// Info Float
+// ============================================================================
+
+fn documented(line: &str, col: usize) -> Option<(usize, Vec<CompletionItem>)> {
+    let start = line[..col].rfind(' ').map_or(0, |idx| idx + 1);
+    let mut item = CompletionItem::new("format");
+    item.kind = Some("f".to_string());
+    item.menu = Some("[std]".to_string());
+    item.info = Some("format(fmt, ...)\nFormat a string".to_string());
+    Some((start, vec![item, CompletionItem::new("forget")]))
+}
+
+/// Test: the selected match's info is shown beside the menu
+/// Source: popupmenu_spec.lua
+#[test]
+fn test_info_float_for_selected_item() {
+    let mut h = TestHarness::with_lines(&[""]);
+    h.editor.register_complete_func("Documented", documented);
+    h.set("completefunc=Documented");
+    h.feed("ifo<C-x><C-u>");
+    let menu = popup_menu(&mut h).unwrap();
+    assert_eq!(menu.rows[0].text, " format f [std] ");
+    let info = menu.info.unwrap();
+    assert_eq!((info.row, info.col), (1, 16));
+    assert_eq!(info.lines.len(), 2);
+
+    h.feed("<Down>");
+    assert!(popup_menu(&mut h).unwrap().info.is_none());
+
+    h.set("completeopt=menu");
+    h.feed("<Up>");
+    assert!(popup_menu(&mut h).unwrap().info.is_none());
+}
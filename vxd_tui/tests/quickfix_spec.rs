@@ -0,0 +1,365 @@
+//! Quickfix and location list tests ported from Vim/Neovim tests
+//!
+//! These tests verify moving through the quickfix and location lists, the
+//! list stacks, the quickfix window, `:cdo` and `:cfdo`, creating lists
+//! with `:cexpr` and 'errorformat', and the QuickFixCmdPre and
+//! QuickFixCmdPost events.
+//!
+//! Source tests:
+//! - src/testdir/test_quickfix.vim
+
+mod common;
+
+use std::path::Path;
+
+use common::{ScratchDir, TestHarness};
+use vxd::buffer::{Buffer, BufferManager, BufferType};
+use vxd::options::OptionManager;
+
+/// A harness with a quickfix list of five entries in two files:
+/// a.txt lines 1, 2, 3 and b.txt lines 1, 2, each starting with "-",
+/// and the directory they are in
+fn with_list(name: &str) -> (TestHarness, ScratchDir, String, String) {
+    let dir = ScratchDir::new(name);
+    let a = dir.file("a.txt", "- a1\n- a2\n- a3\n");
+    let b = dir.file("b.txt", "- b1\n- b2\n");
+    let mut h = TestHarness::with_lines(&["start"]);
+    h.feed(&format!(
+        ":cgetexpr ['{a}:1:1:one', '{a}:2:1:two', '{a}:3:1:three', '{b}:1:1:four', '{b}:2:2:five']<CR>"
+    ));
+    (h, dir, a, b)
+}
+
+fn current_name(h: &TestHarness) -> String {
+    let name = h.editor.buffers.current().name().to_string();
+    Path::new(&name)
+        .file_name()
+        .map_or(name.clone(), |name| name.to_string_lossy().into_owned())
+}
+
+// ============================================================================
+// Navigation
+// ============================================================================
+
+/// Test: :cnext, :cprevious, :cfirst and :clast go through the list
+/// Source: test_quickfix.vim "Test_cbuffer_cnext"
+#[test]
+fn test_quickfix_next_previous() {
+    let (mut h, _dir, _, _) = with_list("nav");
+    assert_lines!(h, "start");
+    h.feed(":cc<CR>");
+    assert_eq!(current_name(&h), "a.txt");
+    assert_cursor!(h, 1, 0);
+    assert_eq!(h.message(), "(1 of 5): one");
+    h.feed(":cnext<CR>");
+    assert_cursor!(h, 2, 0);
+    h.feed(":clast<CR>");
+    assert_eq!(current_name(&h), "b.txt");
+    assert_cursor!(h, 2, 1);
+    h.feed(":cn<CR>");
+    assert!(h.message().contains("E553: No more items"));
+    h.feed(":cprev<CR>");
+    assert_cursor!(h, 1, 0);
+    h.feed(":cfirst<CR>");
+    assert_eq!(current_name(&h), "a.txt");
+    assert_cursor!(h, 1, 0);
+    h.feed(":cN<CR>");
+    assert!(h.message().contains("E553"));
+}
+
+/// Test: a count as range or argument moves that many entries
+/// Source: test_quickfix.vim "Xtest_browse"
+#[test]
+fn test_quickfix_counts() {
+    let (mut h, _dir, _, _) = with_list("count");
+    h.feed(":2cnext<CR>");
+    assert_eq!(h.message(), "(3 of 5): three");
+    h.feed(":cc 5<CR>");
+    assert_eq!(h.message(), "(5 of 5): five");
+    h.feed(":2cc<CR>");
+    assert_eq!(h.message(), "(2 of 5): two");
+    h.feed(":10cprev<CR>");
+    assert_eq!(h.message(), "(1 of 5): one");
+    h.feed(":cfirst 4<CR>");
+    assert_eq!(h.message(), "(4 of 5): four");
+    h.feed(":cc x<CR>");
+    assert!(h.message().contains("E488"));
+}
+
+/// Test: :cnfile and :cpfile go to another file in the list
+/// Source: test_quickfix.vim "Xtest_browse"
+#[test]
+fn test_quickfix_next_file() {
+    let (mut h, _dir, _, _) = with_list("nfile");
+    h.feed(":cnfile<CR>");
+    assert_eq!(h.message(), "(4 of 5): four");
+    assert_eq!(current_name(&h), "b.txt");
+    h.feed(":cnf<CR>");
+    assert!(h.message().contains("E553"));
+    h.feed(":cpfile<CR>");
+    assert_eq!(h.message(), "(3 of 5): three");
+    assert_eq!(current_name(&h), "a.txt");
+}
+
+/// Test: a file not loaded yet is read like with :edit, with the BufRead
+/// autocommands and its modelines
+/// Source: test_quickfix.vim "Test_cbuffer_cnext"
+#[test]
+fn test_quickfix_reads_file() {
+    let dir = ScratchDir::new("bufread");
+    let a = dir.file("a.txt", "- a1\n# vim: ts=3\n");
+    let mut h = TestHarness::with_lines(&["start"]);
+    h.feed(":au BufRead *.txt set sw=5<CR>");
+    h.feed(&format!(":cexpr '{a}:1:1:one'<CR>"));
+    assert_eq!(current_name(&h), "a.txt");
+    assert_eq!(h.editor.options.number("shiftwidth"), 5);
+    assert_eq!(h.editor.options.number("tabstop"), 3);
+}
+
+/// Test: commands on an empty list give E42
+/// Source: test_quickfix.vim "XlistTests"
+#[test]
+fn test_quickfix_no_errors() {
+    let mut h = TestHarness::with_lines(&["x"]);
+    h.feed(":cnext<CR>");
+    assert!(h.message().contains("E42: No Errors"));
+    h.feed(":cgetexpr 'no match here'<CR>");
+    h.feed(":cc<CR>");
+    assert!(h.message().contains("E42"));
+}
+
+// ============================================================================
+// List Stacks
+// ============================================================================
+
+/// Test: :colder and :cnewer go through the stack of lists
+/// Source: test_quickfix.vim "Xolder_newer_tests"
+#[test]
+fn test_quickfix_older_newer() {
+    let mut h = TestHarness::with_lines(&["x"]);
+    h.feed(":cgetexpr ['a:1:1:one']<CR>");
+    h.feed(":cgetexpr ['b:1:1:two', 'b:2:1:three']<CR>");
+    h.feed(":colder<CR>");
+    assert_eq!(
+        h.message(),
+        "error list 1 of 2; 1 errors   :cgetexpr ['a:1:1:one']"
+    );
+    h.feed(":colder<CR>");
+    assert!(h.message().contains("E380: At bottom of quickfix stack"));
+    h.feed(":cnewer<CR>");
+    assert!(h.message().starts_with("error list 2 of 2; 2 errors"));
+    h.feed(":cnewer<CR>");
+    assert!(h.message().contains("E381: At top of quickfix stack"));
+
+    // A new list replaces the newer ones
+    h.feed(":colder<CR>");
+    h.feed(":cgetexpr ['c:1:1:four']<CR>");
+    assert_eq!(h.editor.quickfix.len(), 2);
+    assert_eq!(h.editor.quickfix.current().unwrap().entries[0].text, "four");
+}
+
+/// Test: the location list is separate from the quickfix list
+/// Source: test_quickfix.vim "Test_locationlist"
+#[test]
+fn test_location_list() {
+    let (mut h, _dir, a, _) = with_list("loclist");
+    h.feed(":lnext<CR>");
+    assert!(h.message().contains("E776: No location list"));
+    h.feed(&format!(":lexpr '{}:3:2:here'<CR>", a));
+    assert_eq!(h.message(), "(1 of 1): here");
+    assert_cursor!(h, 3, 1);
+    assert_eq!(h.editor.quickfix.current().unwrap().idx, 0);
+    h.feed(":ll<CR>");
+    assert_cursor!(h, 3, 1);
+    h.feed(":lolder<CR>");
+    assert!(h.message().contains("E380"));
+}
+
+/// Test: the location list belongs to the window; the command-line window
+/// has none and the list is back after closing it
+/// Source: test_quickfix.vim "Test_locationlist"
+#[test]
+fn test_location_list_per_window() {
+    let (mut h, _dir, a, _) = with_list("loclist_win");
+    h.feed(&format!(":lexpr '{}:3:2:here'<CR>", a));
+    h.feed("q:");
+    assert!(h.editor.loclist().is_empty());
+    h.feed(":q<CR>");
+    assert_eq!(h.editor.cmdwin_type(), None);
+    assert_eq!(h.editor.loclist().len(), 1);
+    h.feed(":ll<CR>");
+    assert_eq!(h.message(), "(1 of 1): here");
+}
+
+// ============================================================================
+// Quickfix Window
+// ============================================================================
+
+/// Test: :copen shows the list in a quickfix buffer that follows changes
+/// Source: test_quickfix.vim "Test_quickfix_window_fails_to_open"
+#[test]
+fn test_copen_cclose() {
+    let mut h = TestHarness::with_lines(&["x"]);
+    h.feed(":copen<CR>");
+    assert!(h.message().contains("E42"));
+    h.feed(":cgetexpr ['f.c:3:5:error 12: bad', 'in f.c']<CR>");
+    h.feed(":copen 5<CR>");
+    let view = h.editor.quickfix_view().unwrap();
+    assert_eq!(view.lines, vec!["f.c|3 col 5| error 12: bad", "|| in f.c"]);
+    assert_eq!(view.height, 5);
+    assert!(view.title.starts_with("[Quickfix List] :cgetexpr"));
+    let handle = h.editor.buffers.get_by_name("[Quickfix List]").unwrap();
+    let buffer = h.editor.buffers.get(handle).unwrap();
+    assert_eq!(buffer.buftype(), BufferType::Quickfix);
+    assert!(!buffer.is_listed());
+
+    h.feed(":cgetexpr 'g.c:1:1:other'<CR>");
+    assert_eq!(
+        h.editor.quickfix_view().unwrap().lines,
+        vec!["g.c|1 col 1| other"]
+    );
+    h.feed(":cclose<CR>");
+    assert!(h.editor.quickfix_view().is_none());
+    assert!(h.editor.buffers.get_by_name("[Quickfix List]").is_none());
+}
+
+/// Test: :cwindow opens the window only when there are valid entries
+/// Source: test_quickfix.vim "Test_cwindow"
+#[test]
+fn test_cwindow() {
+    let mut h = TestHarness::with_lines(&["x"]);
+    h.feed(":cgetexpr 'no error'<CR>");
+    h.feed(":cwindow<CR>");
+    assert!(h.editor.quickfix_view().is_none());
+    h.feed(":cgetexpr 'f.c:1:1:error'<CR>");
+    h.feed(":cwindow<CR>");
+    assert!(h.editor.quickfix_view().is_some());
+    h.feed(":colder<CR>");
+    h.feed(":cwindow<CR>");
+    assert!(h.editor.quickfix_view().is_none());
+}
+
+/// Test: :clist lists the valid entries, :clist! all of them
+/// Source: test_quickfix.vim "XlistTests"
+#[test]
+fn test_clist() {
+    let mut h = TestHarness::with_lines(&["x"]);
+    h.feed(":cgetexpr ['f.c:3:5:error: bad', 'note', 'g.c:7:1:warning']<CR>");
+    h.feed(":clist<CR>");
+    assert_eq!(
+        h.message(),
+        " 1 f.c:3 col 5: error: bad\n 3 g.c:7 col 1: warning"
+    );
+    h.feed(":clist!<CR>");
+    assert_eq!(
+        h.message(),
+        " 1 f.c:3 col 5: error: bad\n 2: note\n 3 g.c:7 col 1: warning"
+    );
+}
+
+// ============================================================================
+// :cdo and :cfdo
+// ============================================================================
+
+/// Test: :cdo runs a command at each entry, :cfdo once in each file
+/// Source: test_quickfix.vim "Test_cdo"
+#[test]
+fn test_cdo_cfdo() {
+    let (mut h, _dir, a, b) = with_list("cdo");
+    h.feed(":cdo s/-/+/<CR>");
+    assert_eq!(current_name(&h), "b.txt");
+    assert_lines!(h, "+ b1", "+ b2");
+    h.feed(":cfdo s/+/*/<CR>");
+    assert_lines!(h, "* b1", "+ b2");
+    let handle = h.editor.buffers.get_by_name(&a).unwrap();
+    let lines = h
+        .editor
+        .buffers
+        .get(handle)
+        .unwrap()
+        .get_lines(0, -1, false)
+        .unwrap();
+    assert_eq!(lines, vec!["* a1", "+ a2", "+ a3"]);
+    assert!(h.editor.buffers.get_by_name(&b).is_some());
+
+    h.feed(":cdo s/zzz/y/<CR>");
+    assert!(h.message().contains("E486"));
+}
+
+// ============================================================================
+// :cexpr and 'errorformat'
+// ============================================================================
+
+/// Test: :cexpr jumps to the first error, :caddexpr adds to the list
+/// Source: test_quickfix.vim "Test_cexpr"
+#[test]
+fn test_cexpr_caddexpr() {
+    let dir = ScratchDir::new("cexpr");
+    let a = dir.file("a.txt", "one\ntwo\nthree\n");
+    let mut h = TestHarness::with_lines(&["start"]);
+    h.feed(&format!(":cexpr \"compiling\\n{}:2:3:warning\"<CR>", a));
+    assert_eq!(current_name(&h), "a.txt");
+    assert_cursor!(h, 2, 2);
+    assert_eq!(h.message(), "(2 of 2): warning");
+    h.feed(&format!(":caddexpr '{}:3:1:more'<CR>", a));
+    let list = h.editor.quickfix.current().unwrap();
+    assert_eq!(list.len(), 3);
+    assert_eq!(list.idx, 1);
+    assert_eq!(h.editor.quickfix.len(), 1);
+    h.feed(":cnext<CR>");
+    assert_cursor!(h, 3, 0);
+
+    h.feed(":cexpr 1 + 1<CR>");
+    assert!(h.message().contains("E777"));
+}
+
+/// Test: a register holds the lines for :cexpr
+/// Source: test_quickfix.vim "Test_cexpr"
+#[test]
+fn test_cexpr_register() {
+    let mut h = TestHarness::with_lines(&["f.c:4:2:from register", "x"]);
+    h.feed("\"ayy");
+    h.feed(":cgetexpr @a<CR>");
+    let list = h.editor.quickfix.current().unwrap();
+    assert_eq!(list.entries[0].filename, "f.c");
+    assert_eq!(list.entries[0].text, "from register");
+}
+
+/// Test: 'errorformat' with multi-line messages and error types
+/// Source: test_quickfix.vim "Test_efm_multiline"
+#[test]
+fn test_errorformat_option() {
+    let mut h = TestHarness::with_lines(&["x"]);
+    h.set("errorformat=%E%f:%l:\\ error:\\ %m,%C\\ \\ %m,%Z");
+    h.feed(":cgetexpr ['f.c:3: error: first', '  second', '']<CR>");
+    let list = h.editor.quickfix.current().unwrap();
+    assert_eq!(list.len(), 1);
+    let entry = &list.entries[0];
+    assert_eq!(entry.kind_name(), Some("error"));
+    assert_eq!(entry.text, "first\nsecond");
+    h.feed(":clist<CR>");
+    assert_eq!(h.message(), " 1 f.c:3 error: first second");
+}
+
+// ============================================================================
+// Autocommands
+// ============================================================================
+
+/// Test: QuickFixCmdPre and QuickFixCmdPost fire with the command name
+/// Source: test_quickfix.vim "Test_Autocmd"
+#[test]
+fn test_quickfix_cmd_autocmds() {
+    let mut h = TestHarness::with_lines(&["x"]);
+    h.feed(":autocmd QuickFixCmdPre cgetexpr set ts=3<CR>");
+    h.feed(":autocmd QuickFixCmdPost *expr set sw+=1<CR>");
+    h.feed(":cgetexpr 'a'<CR>");
+    assert_eq!(h.editor.options.get_number("tabstop"), Some(3));
+    assert_eq!(h.editor.options.get_number("shiftwidth"), Some(9));
+    h.feed(":lgetexpr 'a'<CR>");
+    assert_eq!(h.editor.options.get_number("shiftwidth"), Some(10));
+
+    // The list is already made when QuickFixCmdPost fires
+    h.feed(":autocmd QuickFixCmdPost lgetexpr lclose<CR>");
+    h.feed(":lgetexpr 'a'<CR>");
+    assert_eq!(h.editor.loclist().len(), 2);
+}
@@ -0,0 +1,270 @@
+//! Dot-repeat tests ported from Vim/Neovim tests
+//!
+//! These tests verify repeating the last change with `.`: an operator
+//! with its motion, count and register, text typed in Insert mode, a new
+//! count replacing the original one, numbered registers advancing with
+//! `"1p...`, how `Ctrl-O`, cursor keys and abbreviations in Insert
+//! mode end up in the repeated change, and Visual mode operators repeated
+//! on an area of the same size.
+//!
+//! Source tests:
+//! - src/testdir/test_normal.vim
+//! - src/testdir/test_put.vim
+//! - test/functional/normal/count_spec.lua
+
+mod common;
+
+use common::TestHarness;
+use vxd::abbreviations::AbbreviationManager;
+use vxd::modes::Mode;
+use vxd::registers::{Register, RegisterBank};
+
+// ============================================================================
+// Operators and Counts
+// ============================================================================
+
+/// Test: . repeats an operator with its motion
+/// Source: test_normal.vim "Test_normal_dot_repeat"
+#[test]
+fn test_repeat_operator() {
+    let mut h = TestHarness::with_lines(&["one two three four"]);
+    h.feed("dw");
+    h.feed(".");
+    assert_lines!(h, "three four");
+}
+
+/// Test: . repeats the count, and a count given to . replaces it
+/// Source: test_normal.vim "Test_normal_dot_repeat"
+#[test]
+fn test_repeat_count_replaced() {
+    let mut h = TestHarness::with_lines(&["a b c d e f g h i j k"]);
+    h.feed("d2w");
+    assert_lines!(h, "c d e f g h i j k");
+    h.feed(".");
+    assert_lines!(h, "e f g h i j k");
+    h.feed("3.");
+    assert_lines!(h, "h i j k");
+    // The new count is kept for the next .
+    h.feed(".");
+    assert_lines!(h, "k");
+}
+
+/// Test: the counts before and after the operator are multiplied
+/// Source: test_normal.vim "Test_normal_dot_repeat"
+#[test]
+fn test_repeat_multiplied_count() {
+    let mut h = TestHarness::with_lines(&["abcdefghijklmnop"]);
+    h.feed("2d2l");
+    assert_lines!(h, "efghijklmnop");
+    h.feed(".");
+    assert_lines!(h, "ijklmnop");
+    h.feed("3x.");
+    assert_lines!(h, "op");
+}
+
+/// Test: yanks, motions and Ex commands are not repeated
+/// Source: test_normal.vim "Test_normal_dot_repeat"
+#[test]
+fn test_repeat_skips_non_changes() {
+    let mut h = TestHarness::with_lines(&["abcdef", "abcdef"]);
+    h.feed("x");
+    h.feed("yw");
+    h.feed("l");
+    h.feed(":s/f/F/<CR>");
+    h.feed(".");
+    assert_lines!(h, "cdeF", "abcdef");
+}
+
+// ============================================================================
+// Insert Mode
+// ============================================================================
+
+/// Test: . repeats the text typed in Insert mode
+/// Source: test_normal.vim "Test_normal_dot_repeat"
+#[test]
+fn test_repeat_insert() {
+    let mut h = TestHarness::with_lines(&["a", "b"]);
+    h.feed("Afoo<Esc>j.");
+    assert_lines!(h, "afoo", "bfoo");
+
+    h.feed("ohello<Esc>.");
+    assert_lines!(h, "afoo", "bfoo", "hello", "hello");
+}
+
+/// Test: . repeats a change with the text typed for it
+/// Source: test_normal.vim "Test_normal_dot_repeat"
+#[test]
+fn test_repeat_change() {
+    let mut h = TestHarness::with_lines(&["x y z"]);
+    h.feed("cwnew<Esc>w.");
+    assert_lines!(h, "new new z");
+}
+
+/// Test: a count inserts the text typed that many times
+/// Source: test_normal.vim "Test_normal_count_insert"
+#[test]
+fn test_insert_count() {
+    let mut h = TestHarness::with_lines(&["abc"]);
+    h.feed("3ix<Esc>");
+    assert_lines!(h, "xxxabc");
+    let inserted = h.editor.registers.get(Register::LastInserted);
+    assert_eq!(
+        inserted.map(|content| content.as_string()),
+        Some("x".into())
+    );
+
+    let mut h = TestHarness::with_lines(&["a", "b"]);
+    h.feed("2oxy<Esc>");
+    assert_lines!(h, "a", "xy", "xy", "b");
+}
+
+/// Test: . repeats the count of an insert, and a count given to . replaces
+/// it
+/// Source: test/functional/normal/count_spec.lua
+#[test]
+fn test_repeat_insert_count() {
+    let mut h = TestHarness::with_lines(&["abc"]);
+    h.feed("ix<Esc>3.");
+    assert_lines!(h, "xxxxabc");
+
+    let mut h = TestHarness::with_lines(&["abc"]);
+    h.feed("3Ax<Esc>.");
+    assert_lines!(h, "abcxxxxxx");
+}
+
+/// Test: a cursor key in Insert mode starts a new change; . repeats the
+/// text typed after it
+/// Source: test_normal.vim "Test_normal_dot_repeat"
+#[test]
+fn test_repeat_after_cursor_key() {
+    let mut h = TestHarness::with_lines(&["ab", "cd"]);
+    h.feed("Axy<Left>Z<Esc>");
+    assert_lines!(h, "abxZy", "cd");
+    h.feed("j0.");
+    assert_lines!(h, "abxZy", "Zcd");
+}
+
+/// Test: Ctrl-O runs one command and goes back to Insert mode; the text
+/// typed after it is repeated on its own
+/// Source: test_normal.vim "Test_normal_dot_repeat"
+#[test]
+fn test_repeat_after_ctrl_o() {
+    let mut h = TestHarness::with_lines(&["x", "y"]);
+    h.feed("ifoo<C-o>0");
+    assert_eq!(h.mode(), Mode::Insert);
+    assert_cursor!(h, 1, 0);
+    h.feed("bar<Esc>");
+    assert_lines!(h, "barfoox", "y");
+    h.feed("j0.");
+    assert_lines!(h, "barfoox", "bary");
+}
+
+/// Test: after Ctrl-O at the end of the line the cursor goes back there
+/// Source: test_edit.vim "Test_edit_CTRL_O"
+#[test]
+fn test_ctrl_o_at_end_of_line() {
+    let mut h = TestHarness::with_lines(&["xy"]);
+    h.feed("A1<C-o>:set ts=3<CR>2<Esc>");
+    assert_lines!(h, "xy12");
+    assert_eq!(h.mode(), Mode::Normal);
+}
+
+/// Test: a command typed with Ctrl-O that changes the buffer is the last
+/// change
+/// Source: test_edit.vim "Test_edit_CTRL_O"
+#[test]
+fn test_ctrl_o_change() {
+    let mut h = TestHarness::with_lines(&["abcd"]);
+    h.feed("i<C-o>x<Esc>");
+    assert_lines!(h, "bcd");
+    h.feed(".");
+    assert_lines!(h, "cd");
+}
+
+/// Test: an abbreviation is repeated as its expansion
+/// Source: test_mapping.vim "Test_abbreviation_dot_repeat"
+#[test]
+fn test_repeat_abbreviation() {
+    let mut h = TestHarness::with_lines(&[""]);
+    h.editor
+        .abbreviations
+        .add(Mode::Insert, "teh", "the", false, false)
+        .unwrap();
+    h.feed("Ateh <Esc>");
+    assert_lines!(h, "the ");
+    h.editor.abbreviations.remove(Mode::Insert, "teh").unwrap();
+    h.feed("$.");
+    assert_lines!(h, "the the ");
+}
+
+// ============================================================================
+// Registers
+// ============================================================================
+
+/// Test: "1p... puts the numbered registers in turn
+/// Source: test_put.vim "Test_put_numbered_register_dot"
+#[test]
+fn test_repeat_advances_numbered_register() {
+    let mut h = TestHarness::with_lines(&["1", "2", "3", "4"]);
+    h.feed("dddddd");
+    assert_lines!(h, "4");
+    h.feed("\"1p..");
+    assert_lines!(h, "4", "3", "2", "1");
+}
+
+/// Test: . repeats a change with a named register
+/// Source: test_normal.vim "Test_normal_dot_repeat"
+#[test]
+fn test_repeat_named_register() {
+    let mut h = TestHarness::with_lines(&["a", "b"]);
+    h.feed("\"ayyj\"ap.");
+    assert_lines!(h, "a", "b", "a", "a");
+}
+
+/// Test: a change made by a macro or :normal is repeated
+/// Source: test_normal.vim "Test_normal_dot_repeat"
+#[test]
+fn test_repeat_change_from_normal() {
+    let mut h = TestHarness::with_lines(&["abcd"]);
+    h.feed(":normal x<CR>");
+    h.feed(".");
+    assert_lines!(h, "cd");
+}
+
+// ============================================================================
+// Visual Mode
+// ============================================================================
+
+/// Test: . repeats a Visual mode operator on as many characters
+/// Source: test_normal.vim "Test_normal_dot_repeat"
+#[test]
+fn test_repeat_visual_delete() {
+    let mut h = TestHarness::with_lines(&["abcdefg"]);
+    h.feed("vld");
+    assert_lines!(h, "cdefg");
+    h.feed(".");
+    assert_lines!(h, "efg");
+    assert_eq!(h.mode(), Mode::Normal);
+}
+
+/// Test: . repeats a linewise Visual operator on as many lines
+/// Source: test_normal.vim "Test_normal_dot_repeat"
+#[test]
+fn test_repeat_visual_line_delete() {
+    let mut h = TestHarness::with_lines(&["a", "b", "c", "d", "e"]);
+    h.feed("Vjd");
+    assert_lines!(h, "c", "d", "e");
+    h.feed(".");
+    assert_lines!(h, "e");
+}
+
+/// Test: . repeats a Visual mode change with the text typed for it
+/// Source: test_visual.vim "Test_visual_mode_op"
+#[test]
+fn test_repeat_visual_change() {
+    let mut h = TestHarness::with_lines(&["abc abc"]);
+    h.feed("vlcX<Esc>");
+    assert_lines!(h, "Xc abc");
+    h.feed("w.");
+    assert_lines!(h, "Xc Xc");
+}
@@ -0,0 +1,159 @@
+//! Search count tests ported from Vim/Neovim tests
+//!
+//! These tests verify the `[N/M]` count shown after a search unless
+//! 'shortmess' contains "S", the 'maxsearchcount' limit and
+//! `searchcount()`, also used by the status line.
+//!
+//! Source tests:
+//! - src/testdir/test_search_stat.vim
+//! - test/functional/ui/searchhl_spec.lua
+
+mod common;
+
+use common::TestHarness;
+use vxd::cursor::CursorPosition;
+use vxd::search::{SearchCount, SearchCountIncomplete};
+use vxd::types::LineNr;
+
+// ============================================================================
+// Count Message
+// ============================================================================
+
+/// Test: a search shows the pattern and the index of the match
+/// Source: test_search_stat.vim "Test_search_stat"
+#[test]
+fn test_search_shows_count() {
+    let mut h = TestHarness::with_lines(&["foo", "bar foo", "foo"]);
+    h.feed("/foo<CR>");
+    assert_eq!(h.message(), "/foo  [2/3]");
+    h.feed("n");
+    assert_eq!(h.message(), "/foo  [3/3]");
+}
+
+/// Test: N shows the reversed direction and counts backwards
+/// Source: test_search_stat.vim "Test_search_stat"
+#[test]
+fn test_search_backward_count() {
+    let mut h = TestHarness::with_lines(&["foo", "foo", "foo"]);
+    h.set_cursor(3, 0);
+    h.feed("?foo<CR>");
+    assert_eq!(h.message(), "?foo  [2/3]");
+    h.feed("N");
+    assert_eq!(h.message(), "/foo  [3/3]");
+}
+
+/// Test: wrapping around the end of the buffer is marked with "W"
+/// Source: test_search_stat.vim "Test_search_stat"
+#[test]
+fn test_search_count_wrapped() {
+    let mut h = TestHarness::with_lines(&["foo", "bar", "foo"]);
+    h.set_cursor(3, 0);
+    h.feed("/foo<CR>");
+    assert_cursor!(h, 1, 0);
+    assert_eq!(h.message(), "/foo  W [1/2]");
+
+    // A message shown before the search is not taken for a wrap
+    h.editor.message = Some("older message".to_string());
+    h.feed("n");
+    assert_cursor!(h, 3, 0);
+    assert_eq!(h.message(), "/foo  [2/2]");
+}
+
+/// Test: 'shortmess' with "S" leaves out the count
+/// Source: test_search_stat.vim "Test_search_stat"
+#[test]
+fn test_shortmess_s_hides_count() {
+    let mut h = TestHarness::with_lines(&["foo", "foo"]);
+    h.set("shortmess+=S");
+    h.feed("/foo<CR>");
+    assert!(h.editor.message.is_none());
+}
+
+/// Test: 'maxsearchcount' limits the count shown
+/// Source: test_search_stat.vim "Test_search_stat"
+#[test]
+fn test_maxsearchcount() {
+    let mut h = TestHarness::with_lines(&["a", "a", "a", "a"]);
+    h.set("maxsearchcount=2");
+    h.feed("/a<CR>");
+    assert_eq!(h.message(), "/a  [2/>2]");
+    h.feed("n");
+    assert_eq!(h.message(), "/a  [>2/>2]");
+}
+
+// ============================================================================
+// searchcount()
+// ============================================================================
+
+/// Test: the count at the cursor, on and between matches
+/// Source: test_search_stat.vim "Test_searchcount"
+#[test]
+fn test_searchcount_at_cursor() {
+    let mut h = TestHarness::with_lines(&["x y x", "y", "x"]);
+    h.feed("/x<CR>");
+    let options = h.editor.search_count_options();
+    let count = h.editor.searchcount(&options).unwrap();
+    assert_eq!(count.current, 2);
+    assert_eq!(count.total, 3);
+    assert!(count.exact_match);
+    assert_eq!(count.incomplete, SearchCountIncomplete::Complete);
+
+    h.set_cursor(2, 0);
+    let options = h.editor.search_count_options();
+    let count = h.editor.searchcount(&options).unwrap();
+    assert_eq!((count.current, count.exact_match), (2, false));
+
+    let mut options = h.editor.search_count_options();
+    options.pos = CursorPosition::new(LineNr(1), 0);
+    options.maxcount = 1;
+    let count = h.editor.searchcount(&options).unwrap();
+    assert_eq!((count.current, count.total), (1, 2));
+    assert_eq!(count.incomplete, SearchCountIncomplete::MaxCountExceeded);
+}
+
+/// Test: searchcount() without a previous pattern gives E35
+/// Source: test_search_stat.vim "Test_searchcount"
+#[test]
+fn test_searchcount_no_pattern() {
+    let mut h = TestHarness::with_lines(&["x"]);
+    let options = h.editor.search_count_options();
+    assert!(h.editor.searchcount(&options).is_err());
+}
+
+/// Test: searchcount() in an expression gives a Dictionary, for the last
+/// pattern or the "pattern", "pos" and "maxcount" given
+/// Source: test_search_stat.vim "Test_searchcount_fails"
+#[test]
+fn test_searchcount_function() {
+    let mut h = TestHarness::with_lines(&["x y x", "y", "x"]);
+    assert_eq!(h.eval("searchcount()").to_display(), "{}");
+    h.feed("/x<CR>");
+    assert_eq!(
+        h.eval("searchcount()").to_display(),
+        "{'current': 2, 'exact_match': 1, 'incomplete': 0, 'maxcount': 999, 'total': 3}"
+    );
+    assert_eq!(
+        h.eval("searchcount({'pattern': 'y', 'pos': [3, 1, 0], 'maxcount': 1})")
+            .to_display(),
+        "{'current': 2, 'exact_match': 0, 'incomplete': 2, 'maxcount': 1, 'total': 2}"
+    );
+    assert!(h.editor.eval_expression("searchcount('x')").is_err());
+}
+
+/// Test: a count that timed out is shown as unknown
+/// Source: test_search_stat.vim "Test_search_stat"
+#[test]
+fn test_search_count_display() {
+    let count = SearchCount {
+        current: 3,
+        total: 10,
+        maxcount: 99,
+        ..SearchCount::default()
+    };
+    assert_eq!(count.display(), "[3/10]");
+    let timed_out = SearchCount {
+        incomplete: SearchCountIncomplete::TimedOut,
+        ..count
+    };
+    assert_eq!(timed_out.display(), "[?/??]");
+}
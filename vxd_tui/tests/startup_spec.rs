@@ -0,0 +1,217 @@
+//! Startup tests ported from Vim tests
+//!
+//! These tests verify starting the editor with command line arguments:
+//! - The files are put in the argument list and the first one is edited
+//! - `+N`, `+/pat` and `-c` commands run after the file is loaded,
+//!   `--cmd` commands before the user config
+//! - `-u` sources a config file, `-u NONE` none
+//! - `VimEnter` autocommands run last
+//! - `-` edits the text read from stdin
+//! - `-o`, `-O`, `-p` and `-r` are refused
+//!
+//! Source tests:
+//! - src/testdir/test_startup.vim
+//! - src/testdir/test_arglist.vim
+
+mod common;
+
+use common::{ScratchDir, TestHarness};
+use vxd::buffer::{Buffer, BufferManager};
+use vxd::eval::Value;
+use vxd::fileedit::FileEditor;
+use vxd::startup::parse_args;
+use vxd_tui::startup::check_args;
+
+/// Start a new editor with `args`
+fn start(args: &[&str], stdin: Option<&str>) -> TestHarness {
+    let mut h = TestHarness::new();
+    let args = parse_args(args).unwrap();
+    h.editor.startup(&args, stdin);
+    h
+}
+
+// ============================================================================
+// Files
+// ============================================================================
+
+/// Test: the files are in the argument list and the first one is edited
+/// Source: test_arglist.vim "Test_argument"
+#[test]
+fn test_files_in_arglist() {
+    let dir = ScratchDir::new("files");
+    let one = dir.file("one.txt", "first\nfile\n");
+    let two = dir.file("two.txt", "second\n");
+    let mut h = start(&["-u", "NONE", &one, &two], None);
+    assert_lines!(h, "first", "file");
+    assert_eq!(h.editor.arglist(), [one.clone(), two.clone()]);
+    assert_eq!(h.editor.current_file(), Some(one.as_str()));
+    assert!(!h.editor.buffers.current().is_modified());
+
+    h.editor.next_file().unwrap();
+    assert_lines!(h, "second");
+    assert_eq!(
+        h.editor.next_file().unwrap_err().to_string(),
+        "E165: Cannot go beyond last file"
+    );
+    h.editor.prev_file().unwrap();
+    assert_lines!(h, "first", "file");
+    assert_eq!(
+        h.editor.prev_file().unwrap_err().to_string(),
+        "E164: Cannot go before first file"
+    );
+}
+
+/// Test: a file that does not exist is a new file
+/// Source: test_startup.vim "Test_edit_nonexistent_file"
+#[test]
+fn test_new_file() {
+    let dir = ScratchDir::new("new");
+    let name = dir.join("new.txt").to_string_lossy().into_owned();
+    let h = start(&["-u", "NONE", &name], None);
+    assert_lines!(h, "");
+    assert_eq!(h.message(), format!("\"{}\" [New]", name));
+}
+
+/// Test: - edits the text read from stdin
+/// Source: test_startup.vim "Test_read_stdin"
+#[test]
+fn test_read_stdin() {
+    let h = start(&["-u", "NONE", "-"], Some("from\nstdin\n"));
+    assert_lines!(h, "from", "stdin");
+    assert!(h.editor.buffers.current().is_modified());
+    assert!(h.editor.arglist().is_empty());
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+/// Test: +N and +/pat move the cursor after the file is loaded
+/// Source: test_startup.vim "Test_o_arg"
+#[test]
+fn test_plus_commands() {
+    let dir = ScratchDir::new("plus");
+    let file = dir.file("f.txt", "a\nb\nc\nfind me\n");
+    let h = start(&["-u", "NONE", "+3", &file], None);
+    assert_cursor!(h, 3, 0);
+    let h = start(&["-u", "NONE", "+/me", &file], None);
+    assert_cursor!(h, 4, 5);
+    let h = start(&["-u", "NONE", "+", &file], None);
+    assert_cursor!(h, 4, 0);
+}
+
+/// Test: --cmd runs before the config, -c after the file is loaded
+/// Source: test_startup.vim "Test_cmd_option"
+#[test]
+fn test_command_order() {
+    let dir = ScratchDir::new("order");
+    let config = dir.file("init.vim", "call add(g:order, 'config')\n");
+    let file = dir.file("f.txt", "text\n");
+    let mut h = start(
+        &[
+            "-c",
+            "call add(g:order, 'c:' . getline(1))",
+            "--cmd",
+            "let g:order = ['cmd']",
+            "-u",
+            &config,
+            &file,
+        ],
+        None,
+    );
+    assert_eq!(
+        h.eval("g:order"),
+        Value::list(vec![
+            Value::string("cmd"),
+            Value::string("config"),
+            Value::string("c:text"),
+        ])
+    );
+    assert_eq!(h.message(), "");
+}
+
+/// Test: the error of a command is shown when the editor has started
+/// Source: test_startup.vim "Test_progpath"
+#[test]
+fn test_command_error() {
+    let h = start(&["-u", "NONE", "-c", "nosuch"], None);
+    assert_eq!(h.message(), "E492: Not an editor command: nosuch");
+}
+
+// ============================================================================
+// User config
+// ============================================================================
+
+/// Test: -u sources the config, -u NONE sources nothing
+/// Source: test_startup.vim "Test_V_arg"
+#[test]
+fn test_config_file() {
+    let dir = ScratchDir::new("config");
+    let config = dir.file("my.vim", "set shiftwidth=3\nlet g:loaded = 1\n");
+    let mut h = start(&["-u", &config], None);
+    assert_eq!(h.editor.options.number("shiftwidth"), 3);
+    assert_eq!(h.eval("loaded"), Value::Number(1));
+    assert_eq!(h.eval("$MYVIMRC"), Value::string(config.as_str()));
+    assert!(std::env::var_os("MYVIMRC").is_none());
+
+    let h = start(&["-u", "NONE"], None);
+    assert_ne!(h.editor.options.number("shiftwidth"), 3);
+
+    let missing = dir.join("missing.vim").to_string_lossy().into_owned();
+    let h = start(&["-u", &missing], None);
+    assert_eq!(
+        h.message(),
+        format!("E282: Cannot read from \"{}\"", missing)
+    );
+}
+
+// ============================================================================
+// Flags and VimEnter
+// ============================================================================
+
+/// Test: -R sets 'readonly' and -N resets 'compatible'
+/// Source: test_startup.vim "Test_readonly_arg"
+#[test]
+fn test_flags() {
+    let dir = ScratchDir::new("flags");
+    let file = dir.file("f.txt", "text\n");
+    let h = start(&["-u", "NONE", "-RnN", &file], None);
+    assert!(h.editor.options.flag("readonly"));
+    assert!(!h.editor.options.flag("swapfile"));
+    assert!(!h.editor.options.flag("compatible"));
+    let h = start(&["-u", "NONE", "-C"], None);
+    assert!(h.editor.options.flag("compatible"));
+}
+
+/// Test: VimEnter autocommands run after the -c commands
+/// Source: test_startup.vim "Test_VimEnter_commands"
+#[test]
+fn test_vim_enter() {
+    let mut h = start(
+        &[
+            "-u",
+            "NONE",
+            "--cmd",
+            "autocmd VimEnter * let g:entered = g:c",
+            "-c",
+            "let g:c = 'done'",
+        ],
+        None,
+    );
+    assert_eq!(h.eval("entered"), Value::string("done"));
+}
+
+/// Test: the window layout and recovery arguments are refused
+/// Source: test_startup.vim "Test_o_arg"
+#[test]
+fn test_unsupported_args() {
+    for arg in ["-o", "-O", "-p", "-r"] {
+        let args = parse_args(&[arg, "f.txt"]).unwrap();
+        assert_eq!(
+            check_args(&args).unwrap_err().to_string(),
+            format!("Unsupported argument: \"{}\"", arg)
+        );
+    }
+    let args = parse_args(&["-u", "NONE", "f.txt"]).unwrap();
+    assert!(check_args(&args).is_ok());
+}
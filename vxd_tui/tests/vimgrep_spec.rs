@@ -0,0 +1,224 @@
+//! `:vimgrep` tests ported from Vim/Neovim tests
+//!
+//! These tests verify `:vimgrep` and `:lvimgrep` searching files on disk
+//! and loaded buffers, the `g` and `j` flags, `**` and 'wildignore' in the
+//! file arguments, and the quickfix and location lists they fill.
+//!
+//! Source tests:
+//! - src/testdir/test_quickfix.vim
+
+mod common;
+
+use std::path::Path;
+
+use common::{ScratchDir, TestHarness};
+use vxd::buffer::{Buffer, BufferManager};
+use vxd::types::LineNr;
+
+/// The quickfix entries as (file name, line, column)
+fn entries(h: &TestHarness) -> Vec<(String, usize, usize)> {
+    h.editor
+        .quickfix
+        .current()
+        .unwrap()
+        .entries
+        .iter()
+        .map(|entry| {
+            let name = Path::new(&entry.filename).file_name().unwrap();
+            (name.to_string_lossy().into_owned(), entry.lnum.0, entry.col)
+        })
+        .collect()
+}
+
+// ============================================================================
+// :vimgrep
+// ============================================================================
+
+/// Test: each matching line is added once and the first match is shown
+/// Source: test_quickfix.vim "Test_vimgrep"
+#[test]
+fn test_vimgrep_jumps_to_first_match() {
+    let dir = ScratchDir::new("first");
+    let a = dir.file("a.txt", "foo foo\nbar\n");
+    dir.file("b.txt", "x\nx foo\n");
+    let mut h = TestHarness::with_lines(&["start"]);
+    h.feed(&format!(":vimgrep /foo/ {}/*.txt<CR>", dir.display()));
+    assert_eq!(
+        entries(&h),
+        vec![("a.txt".to_string(), 1, 1), ("b.txt".to_string(), 2, 3)]
+    );
+    assert_eq!(
+        h.editor.quickfix.current().unwrap().entries[0].text,
+        "foo foo"
+    );
+    assert_eq!(h.editor.buffers.current().name(), a);
+    assert_lines!(h, "foo foo", "bar");
+    assert_cursor!(h, 1, 0);
+    assert_eq!(h.message(), "(1 of 2): foo foo");
+}
+
+/// Test: the g flag adds every match in a line
+/// Source: test_quickfix.vim "Test_vimgrep"
+#[test]
+fn test_vimgrep_global_flag() {
+    let dir = ScratchDir::new("global");
+    dir.file("a.txt", "foo foo\nbar foo\n");
+    let mut h = TestHarness::with_lines(&["start"]);
+    h.feed(&format!(":vim /foo/g {}/a.txt<CR>", dir.display()));
+    assert_eq!(
+        entries(&h),
+        vec![
+            ("a.txt".to_string(), 1, 1),
+            ("a.txt".to_string(), 1, 5),
+            ("a.txt".to_string(), 2, 5),
+        ]
+    );
+}
+
+/// Test: the j flag fills the list without jumping
+/// Source: test_quickfix.vim "Test_vimgrep_with_no_last_search_pat"
+#[test]
+fn test_vimgrep_no_jump_flag() {
+    let dir = ScratchDir::new("nojump");
+    dir.file("a.txt", "foo\n");
+    let mut h = TestHarness::with_lines(&["start"]);
+    h.feed(&format!(":vimgrep /foo/j {}/a.txt<CR>", dir.display()));
+    assert_eq!(h.editor.quickfix.current().unwrap().len(), 1);
+    assert_lines!(h, "start");
+    assert_eq!(
+        h.editor.quickfix.current().unwrap().title,
+        format!(":vimgrep /foo/j {}/a.txt", dir.display())
+    );
+}
+
+/// Test: a pattern without delimiters ends at white space
+/// Source: test_quickfix.vim "Test_vimgrep"
+#[test]
+fn test_vimgrep_pattern_without_delimiters() {
+    let dir = ScratchDir::new("bare");
+    dir.file("a.txt", "one\ntwo\n");
+    let mut h = TestHarness::with_lines(&["start"]);
+    h.feed(&format!(":vimgrep two {}/a.txt<CR>", dir.display()));
+    assert_eq!(entries(&h), vec![("a.txt".to_string(), 2, 1)]);
+    assert_cursor!(h, 2, 0);
+}
+
+/// Test: an empty pattern uses the last search pattern
+/// Source: test_quickfix.vim "Test_vimgrep_with_no_last_search_pat"
+#[test]
+fn test_vimgrep_last_search_pattern() {
+    let dir = ScratchDir::new("lastpat");
+    dir.file("a.txt", "abc\nxyz\n");
+    let mut h = TestHarness::with_lines(&["xyz"]);
+    h.feed("/xyz<CR>");
+    h.feed(&format!(":vimgrep //j {}/a.txt<CR>", dir.display()));
+    assert_eq!(entries(&h), vec![("a.txt".to_string(), 2, 1)]);
+
+    let mut h = TestHarness::with_lines(&["x"]);
+    h.feed(&format!(":vimgrep //j {}/a.txt<CR>", dir.display()));
+    assert!(h.message().contains("E35"));
+}
+
+/// Test: no match gives E480
+/// Source: test_quickfix.vim "Test_vimgrep"
+#[test]
+fn test_vimgrep_no_match() {
+    let dir = ScratchDir::new("nomatch");
+    dir.file("a.txt", "abc\n");
+    let mut h = TestHarness::with_lines(&["start"]);
+    h.feed(&format!(":vimgrep /zzz/ {}/a.txt<CR>", dir.display()));
+    assert!(h.message().contains("E480: No match: zzz"));
+    assert!(h.editor.quickfix.current().unwrap().is_empty());
+    assert_lines!(h, "start");
+}
+
+// ============================================================================
+// Files and Buffers
+// ============================================================================
+
+/// Test: % in a buffer without a name gives E499, # without an alternate
+/// file E194
+/// Source: test_quickfix.vim "Test_vimgrep"
+#[test]
+fn test_vimgrep_empty_file_name() {
+    let mut h = TestHarness::with_lines(&["abc"]);
+    h.feed(":lvimgrep /a/ %<CR>");
+    assert_eq!(
+        h.message(),
+        "E499: Empty file name for '%' or '#', only works with \":p:h\""
+    );
+    assert!(h.editor.loclist().current().is_none());
+    h.feed(":vimgrep /a/ #<CR>");
+    assert_eq!(
+        h.message(),
+        "E194: No alternate file name to substitute for '#'"
+    );
+}
+
+/// Test: ** searches all directories below and 'wildignore' skips files
+/// Source: test_quickfix.vim "Test_vimgrep_wildignore"
+#[test]
+fn test_vimgrep_starstar_and_wildignore() {
+    let dir = ScratchDir::new("starstar");
+    dir.file("a.txt", "foo\n");
+    dir.file("sub/deep/b.txt", "foo\n");
+    dir.file("sub/c.o", "foo\n");
+    dir.file(".hidden/d.txt", "foo\n");
+    let mut h = TestHarness::with_lines(&["start"]);
+    h.set("wildignore=*.o");
+    h.feed(&format!(":vimgrep /foo/j {}/**<CR>", dir.display()));
+    assert_eq!(
+        entries(&h),
+        vec![("a.txt".to_string(), 1, 1), ("b.txt".to_string(), 1, 1)]
+    );
+
+    h.feed(&format!(":vimgrep /foo/j {}/**/*.o<CR>", dir.display()));
+    assert!(h.message().contains("E480"));
+}
+
+/// Test: ** does not follow a link back up to a directory it is in
+/// Source: :help starstar
+#[cfg(unix)]
+#[test]
+fn test_vimgrep_starstar_symlink_loop() {
+    let dir = ScratchDir::new("symlink");
+    dir.file("sub/a.txt", "foo\n");
+    std::os::unix::fs::symlink(&*dir, dir.join("sub/up")).unwrap();
+    let mut h = TestHarness::with_lines(&["start"]);
+    h.feed(&format!(":vimgrep /foo/j {}/**/*.txt<CR>", dir.display()));
+    assert_eq!(entries(&h), vec![("a.txt".to_string(), 1, 1)]);
+}
+
+/// Test: a loaded buffer is searched rather than its file
+/// Source: test_quickfix.vim "Test_vimgrep_existing_swapfile"
+#[test]
+fn test_vimgrep_searches_loaded_buffer() {
+    let dir = ScratchDir::new("loaded");
+    let a = dir.file("a.txt", "old\n");
+    let mut h = TestHarness::with_lines(&["x", "new"]);
+    h.editor.buffers.current_mut().set_name(&a).unwrap();
+    h.feed(":vimgrep /new/ %<CR>");
+    assert_eq!(entries(&h), vec![("a.txt".to_string(), 2, 1)]);
+    assert_cursor!(h, 2, 0);
+    h.feed(":vimgrep /old/ %<CR>");
+    assert!(h.message().contains("E480"));
+}
+
+// ============================================================================
+// :lvimgrep
+// ============================================================================
+
+/// Test: :lvimgrep fills the location list and leaves the quickfix list
+/// Source: test_quickfix.vim "Test_lvimgrep_crash"
+#[test]
+fn test_lvimgrep_location_list() {
+    let dir = ScratchDir::new("loclist");
+    dir.file("a.txt", "a\nfoo\n");
+    let mut h = TestHarness::with_lines(&["start"]);
+    h.feed(&format!(":lvimgrep /foo/ {}/a.txt<CR>", dir.display()));
+    assert!(h.editor.quickfix.is_empty());
+    let loclist = h.editor.loclist().current().unwrap();
+    assert_eq!(loclist.len(), 1);
+    assert_eq!(loclist.entries[0].lnum, LineNr(2));
+    assert_cursor!(h, 2, 0);
+}
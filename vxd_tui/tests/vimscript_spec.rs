@@ -0,0 +1,206 @@
+//! Vimscript tests ported from Vim tests
+//!
+//! These tests verify scripts run in the editor:
+//! - `:let`, `:if`, `:while` and `:for` typed on the command line
+//! - User functions called from expressions and with a range
+//! - `:try` and errors made into exceptions
+//! - `:source` of a script file, mixing script and editor commands
+//!
+//! Source tests:
+//! - src/testdir/test_vimscript.vim
+//! - src/testdir/test_let.vim
+//! - src/testdir/test_source.vim
+
+mod common;
+
+use common::{ScratchDir, TestHarness};
+use vxd::eval::Value;
+
+// ============================================================================
+// Commands on the command line
+// ============================================================================
+
+/// Test: :let sets variables that expressions see
+/// Source: test_let.vim "Test_let"
+#[test]
+fn test_let_on_command_line() {
+    let mut h = TestHarness::with_lines(&["x"]);
+    h.feed(":let g:n = 3<CR>");
+    h.feed(":let n += 4<CR>");
+    assert_eq!(h.eval("g:n"), Value::Number(7));
+    h.feed("\"=n * 2<CR>p");
+    assert_lines!(h, "x14");
+}
+
+/// Test: commands separated by | with a block on one line
+/// Source: test_vimscript.vim "Test_if_bar_fail"
+#[test]
+fn test_if_on_one_line() {
+    let mut h = TestHarness::with_lines(&["x"]);
+    h.feed(":if 1 | let g:a = 'yes' | else | let g:a = 'no' | endif<CR>");
+    assert_eq!(h.eval("a"), Value::string("yes"));
+    h.feed(":let i = 0 | while i <lt> 3 | let i += 1 | endwhile<CR>");
+    assert_eq!(h.eval("i"), Value::Number(3));
+}
+
+/// Test: :echo shows values on the message line
+/// Source: test_vimscript.vim "Test_echo"
+#[test]
+fn test_echo() {
+    let mut h = TestHarness::with_lines(&["x"]);
+    h.feed(":echo 'a' [1, 2] {'k': 'v'}<CR>");
+    assert_eq!(h.message(), "a [1, 2] {'k': 'v'}");
+    h.feed(":echo nosuch<CR>");
+    assert_eq!(h.message(), "E121: Undefined variable: nosuch");
+}
+
+/// Test: :let &option sets an option
+/// Source: test_let.vim "Test_let_option_error"
+#[test]
+fn test_let_option_and_register() {
+    let mut h = TestHarness::with_lines(&["x"]);
+    h.feed(":let &tabstop = 4<CR>");
+    assert_eq!(h.editor.options.number("tabstop"), 4);
+    h.feed(":let &ts += 2 | let @a = 'yz'<CR>");
+    assert_eq!(h.editor.options.number("tabstop"), 6);
+    h.feed("\"ap");
+    assert_lines!(h, "xyz");
+    h.feed(":let &nosuch = 1<CR>");
+    assert_eq!(h.message(), "E355: Unknown option: nosuch");
+}
+
+/// Test: :let $NAME and :unlet $NAME change the environment of the editor,
+/// not the one of the process running it
+/// Source: :help :let-environment
+#[test]
+fn test_let_environment() {
+    let mut h = TestHarness::with_lines(&["x"]);
+    h.feed(":let $VXD_LET_ENV = 'set'<CR>");
+    assert_eq!(h.eval("$VXD_LET_ENV"), Value::string("set"));
+    assert!(std::env::var_os("VXD_LET_ENV").is_none());
+
+    h.feed(":unlet $PATH<CR>");
+    assert_eq!(h.eval("$PATH"), Value::string(""));
+    assert!(std::env::var_os("PATH").is_some());
+    assert_eq!(TestHarness::new().editor.env_var("VXD_LET_ENV"), None);
+}
+
+/// Test: :execute runs a built command, editor commands included
+/// Source: test_vimscript.vim "Test_execute_cmd"
+#[test]
+fn test_execute_editor_commands() {
+    let mut h = TestHarness::with_lines(&["one", "two"]);
+    h.feed(":let cmd = 'normal! dd' | execute cmd<CR>");
+    assert_lines!(h, "two");
+    h.feed(":for w in ['a', 'b'] | execute 'normal! A' . w | endfor<CR>");
+    assert_lines!(h, "twoab");
+}
+
+// ============================================================================
+// Functions
+// ============================================================================
+
+/// Test: a user function defined by :execute and used in expressions
+/// Source: test_vimscript.vim "Test_function_defaults"
+#[test]
+fn test_user_function_in_expressions() {
+    let mut h = TestHarness::with_lines(&["x"]);
+    h.feed(
+        ":execute \"function! Greet(name, greeting = 'hello')\\n\
+         return a:greeting . ' ' . a:name\\nendfunction\"<CR>",
+    );
+    assert_eq!(h.message(), "");
+    assert_eq!(h.eval("Greet('you')"), Value::string("hello you"));
+    assert!(h.editor.vimscript.has_function("Greet"));
+    h.feed("i<C-R>=Greet('x', 'hi')<CR><Esc>");
+    assert_lines!(h, "hi xx");
+}
+
+/// Test: :call with a range calls a function on each line
+/// Source: test_vimscript.vim "Test_range_function"
+#[test]
+fn test_call_with_range() {
+    let mut h = TestHarness::with_lines(&["a", "b", "c"]);
+    let dir = ScratchDir::new("range");
+    let path = dir.file(
+        "script.vim",
+        "let g:seen = []\n\
+         function! Each()\n  call add(g:seen, line('.'))\nendfunction\n\
+         function! Count() range\n  let g:count = a:lastline - a:firstline + 1\nendfunction\n",
+    );
+    h.feed(&format!(":source {}<CR>", path));
+    h.feed(":%call Count()<CR>");
+    assert_eq!(h.eval("count"), Value::Number(3));
+    // Without `range` the function is called with the cursor on each line
+    h.feed(":2,3call Each()<CR>");
+    assert_eq!(
+        h.eval("seen"),
+        Value::list(vec![Value::Number(2), Value::Number(3)])
+    );
+    assert_cursor!(h, 3, 0);
+}
+
+// ============================================================================
+// Exceptions
+// ============================================================================
+
+/// Test: an error inside :try is caught as an exception
+/// Source: test_vimscript.vim "Test_catch_number_err"
+#[test]
+fn test_try_catch_editor_error() {
+    let mut h = TestHarness::with_lines(&["x"]);
+    h.feed(":try | badcommand | catch /E492/ | let g:caught = v:exception | endtry<CR>");
+    assert_eq!(
+        h.eval("caught"),
+        Value::string("Vim:E492: Not an editor command: badcommand")
+    );
+    h.feed(":throw 'oops'<CR>");
+    assert_eq!(h.message(), "E605: Exception not caught: oops");
+}
+
+// ============================================================================
+// :source
+// ============================================================================
+
+/// Test: a sourced script mixes script and editor commands
+/// Source: test_source.vim "Test_source_cmd"
+#[test]
+fn test_source_script() {
+    let mut h = TestHarness::with_lines(&["one", "two", "three"]);
+    let dir = ScratchDir::new("source");
+    let path = dir.file(
+        "script.vim",
+        "\" a comment\n\
+         let s:lines = []\n\
+         for nr in range(1, line('$'))\n\
+         \x20 call add(s:lines, getline(nr))\n\
+         endfor\n\
+         let g:joined = join(s:lines,\n\
+         \x20     \\ '-')\n\
+         set shiftwidth=2\n\
+         normal! gg\"ayy\n\
+         let g:after_error = nosuch\n\
+         let g:done = 1\n",
+    );
+    h.feed(&format!(":source {}<CR>", path));
+    assert_eq!(h.eval("joined"), Value::string("one-two-three"));
+    assert_eq!(h.editor.options.number("shiftwidth"), 2);
+    assert_eq!(h.eval("@a"), Value::string("one\n"));
+    // An error is shown and the script goes on
+    assert_eq!(h.eval("done"), Value::Number(1));
+    assert_eq!(h.message(), "E121: Undefined variable: nosuch");
+    // Script variables are not global
+    assert_eq!(
+        h.editor.eval_expression("lines").unwrap_err().to_string(),
+        "E121: Undefined variable: lines"
+    );
+}
+
+/// Test: :source of a missing file
+/// Source: test_source.vim "Test_source_error"
+#[test]
+fn test_source_missing_file() {
+    let mut h = TestHarness::with_lines(&["x"]);
+    h.feed(":source /nonexistent/script.vim<CR>");
+    assert_eq!(h.message(), "E484: Can't open file /nonexistent/script.vim");
+}
@@ -0,0 +1,371 @@
+//! Command-line completion tests ported from Vim/Neovim tests
+//!
+//! These tests verify completing command names and arguments on the `:`
+//! command line by the command's completion type, the 'wildmode' variants,
+//! the wildmenu keys, 'wildignore'/'wildignorecase' and `getcompletion()`.
+//!
+//! Source tests:
+//! - src/testdir/test_cmdline.vim
+//! - test/functional/ui/wildmode_spec.lua
+
+mod common;
+
+use std::path::Path;
+
+use common::{ScratchDir, TestHarness};
+use vxd::buffer::BufferManager;
+use vxd::cmdline::Cmdline;
+use vxd::commands::{CommandExecutor, CompleteType};
+use vxd::mappings::MappingManager;
+use vxd::modes::Mode;
+
+fn cmdline(h: &TestHarness) -> &str {
+    h.editor.cmdline.getcmdline()
+}
+
+fn in_dir(dir: &Path, names: &[&str]) -> Vec<String> {
+    names
+        .iter()
+        .map(|name| format!("{}/{}", dir.display(), name))
+        .collect()
+}
+
+// ============================================================================
+// Command Names and Arguments
+// ============================================================================
+
+/// Test: <Tab> cycles through command names and back to the typed text
+/// Source: test_cmdline.vim "Test_complete_wildmenu"
+#[test]
+fn test_tab_cycles_command_names() {
+    let mut h = TestHarness::new();
+    h.feed(":se<Tab>");
+    assert_eq!(cmdline(&h), "set");
+    h.feed("<Tab>");
+    assert_eq!(cmdline(&h), "setglobal");
+    h.feed("<Tab>");
+    assert_eq!(cmdline(&h), "setlocal");
+    h.feed("<Tab>");
+    assert_eq!(cmdline(&h), "se");
+}
+
+/// Test: the command name is completed after a range
+/// Source: test_cmdline.vim "Test_complete_cmdline"
+#[test]
+fn test_complete_command_after_range() {
+    let mut h = TestHarness::new();
+    h.feed(":1,2fo<Tab>");
+    assert_eq!(cmdline(&h), "1,2fold");
+}
+
+/// Test: :set completes option names, negated booleans and current values
+/// Source: test_cmdline.vim "Test_getcompletion"
+#[test]
+fn test_complete_set_arguments() {
+    let mut h = TestHarness::new();
+    h.feed(":set tabs<Tab>");
+    assert_eq!(cmdline(&h), "set tabstop");
+    h.feed("<Esc>:set nofolde<Tab>");
+    assert_eq!(cmdline(&h), "set nofoldenable");
+    h.feed("<Esc>:set ts=<Tab>");
+    assert_eq!(cmdline(&h), "set ts=8");
+    h.feed("<Esc>:set fdm=ma<Tab>");
+    assert_eq!(cmdline(&h), "set fdm=manual");
+}
+
+/// Test: :autocmd completes groups and events, then events after a group
+/// Source: test_cmdline.vim "Test_getcompletion"
+#[test]
+fn test_complete_autocmd_arguments() {
+    let mut h = TestHarness::new();
+    h.feed(":augroup Mine<CR>:augroup END<CR>");
+    h.feed(":autocmd Mi<Tab>");
+    assert_eq!(cmdline(&h), "autocmd Mine");
+    h.feed(" bufwritep<Tab>");
+    assert_eq!(cmdline(&h), "autocmd Mine BufWritePost");
+    h.feed("<Esc>:doautocmd BufRead,FileTy<Tab>");
+    assert_eq!(cmdline(&h), "doautocmd BufRead,FileType");
+    h.feed("<Esc>:augroup M<Tab>");
+    assert_eq!(cmdline(&h), "augroup Mine");
+}
+
+/// Test: complete_args expands by the command's completion type
+/// Source: test_cmdline.vim "Test_getcompletion"
+#[test]
+fn test_complete_args_by_type() {
+    let h = TestHarness::new();
+    assert_eq!(h.editor.complete_args("set", "tabs"), vec!["tabstop"]);
+    assert_eq!(h.editor.complete_args("aug", "E"), vec!["END"]);
+    assert!(h.editor.complete_args("fold", "x").is_empty());
+    assert_eq!(
+        h.editor.complete_command("fold"),
+        ["fold", "foldclose", "foldopen"]
+    );
+}
+
+// ============================================================================
+// 'wildmode'
+// ============================================================================
+
+/// Test: "longest:full" completes the common prefix and shows the menu
+/// Source: test_cmdline.vim "Test_wildmode"
+#[test]
+fn test_wildmode_longest_full() {
+    let mut h = TestHarness::new();
+    h.set("wildmode=longest:full");
+    h.feed(":set wildig<Tab>");
+    assert_eq!(cmdline(&h), "set wildignore");
+    let menu = h.editor.wildmenu().unwrap();
+    assert_eq!(menu.items, ["wildignore", "wildignorecase"]);
+    assert_eq!(menu.selected, None);
+    h.feed("<Tab>");
+    assert_eq!(cmdline(&h), "set wildignore");
+    assert_eq!(h.editor.wildmenu().unwrap().selected, Some(0));
+    h.feed("<Tab>");
+    assert_eq!(cmdline(&h), "set wildignorecase");
+}
+
+/// Test: "list:longest" lists the matches without selecting one
+/// Source: test_cmdline.vim "Test_wildmode"
+#[test]
+fn test_wildmode_list_longest() {
+    let mut h = TestHarness::new();
+    h.set("wildmode=list:longest");
+    h.feed(":fol<Tab>");
+    assert_eq!(cmdline(&h), "fold");
+    let menu = h.editor.wildmenu().unwrap();
+    assert_eq!(menu.items, ["fold", "foldclose", "foldopen"]);
+    assert_eq!(menu.selected, None);
+}
+
+/// Test: "longest,full" cycles once the prefix cannot grow
+/// Source: test_cmdline.vim "Test_wildmode"
+#[test]
+fn test_wildmode_longest_then_full() {
+    let mut h = TestHarness::new();
+    h.set("wildmode=longest,full");
+    h.feed(":set wildmo<Tab>");
+    assert_eq!(cmdline(&h), "set wildmode");
+    h.feed("<Esc>:fol<Tab>");
+    assert_eq!(cmdline(&h), "fold");
+    h.feed("<Tab>");
+    assert_eq!(cmdline(&h), "fold");
+    h.feed("<Tab>");
+    assert_eq!(cmdline(&h), "foldclose");
+}
+
+/// Test: "lastused" puts recently used buffers first
+/// Source: test_cmdline.vim "Test_wildmode_lastused"
+#[test]
+fn test_wildmode_lastused_buffers() {
+    let mut h = TestHarness::new();
+    let one = h.editor.buffers.create_named("one.txt").unwrap();
+    let two = h.editor.buffers.create_named("two.txt").unwrap();
+    let three = h.editor.buffers.create_named("three.txt").unwrap();
+    h.editor.switch_buffer(two).unwrap();
+    h.editor.switch_buffer(one).unwrap();
+    h.editor.switch_buffer(three).unwrap();
+    assert_eq!(
+        h.editor.getcompletion("t", CompleteType::Buffer),
+        ["one.txt", "two.txt", "three.txt"]
+    );
+    h.set("wildmode=full:lastused");
+    assert_eq!(
+        h.editor.getcompletion("t", CompleteType::Buffer),
+        ["one.txt", "two.txt", "three.txt"]
+    );
+    h.editor.switch_buffer(two).unwrap();
+    assert_eq!(
+        h.editor.getcompletion("t", CompleteType::Buffer),
+        ["three.txt", "one.txt", "two.txt"]
+    );
+}
+
+// ============================================================================
+// Wildmenu Keys
+// ============================================================================
+
+/// Test: Ctrl-N and Ctrl-P move through the matches
+/// Source: test_cmdline.vim "Test_complete_wildmenu"
+#[test]
+fn test_ctrl_n_ctrl_p_select_matches() {
+    let mut h = TestHarness::new();
+    h.feed(":se<Tab><C-p>");
+    assert_eq!(cmdline(&h), "se");
+    h.feed("<C-p>");
+    assert_eq!(cmdline(&h), "setlocal");
+    h.feed("<C-n><C-n>");
+    assert_eq!(cmdline(&h), "set");
+}
+
+/// Test: Ctrl-E restores the typed text and Ctrl-Y keeps the match
+/// Source: test_cmdline.vim "Test_wildmenu_pum"
+#[test]
+fn test_ctrl_e_and_ctrl_y() {
+    let mut h = TestHarness::new();
+    h.feed(":se<Tab><Tab><C-e>");
+    assert_eq!(cmdline(&h), "se");
+    assert!(h.editor.wildmenu().is_none());
+    h.feed("<Tab><Tab><C-y>");
+    assert_eq!(cmdline(&h), "setglobal");
+    assert!(h.editor.wildmenu().is_none());
+}
+
+/// Test: Ctrl-A inserts all matches, Ctrl-L the longest common part and
+/// Ctrl-D lists them
+/// Source: :help c_CTRL-A, c_CTRL-L, c_CTRL-D
+#[test]
+fn test_ctrl_a_ctrl_l_ctrl_d() {
+    let mut h = TestHarness::new();
+    h.feed(":se<C-a>");
+    assert_eq!(cmdline(&h), "set setglobal setlocal");
+    h.feed("<Esc>:set wildig<C-l>");
+    assert_eq!(cmdline(&h), "set wildignore");
+    h.feed("<Esc>:fol<C-d>");
+    assert_eq!(cmdline(&h), "fol");
+    assert_eq!(
+        h.editor.wildmenu().unwrap().items,
+        ["fold", "foldclose", "foldopen"]
+    );
+}
+
+/// Test: completion only replaces the text before the cursor
+/// Source: test_cmdline.vim "Test_cmdline_complete_wildoptions"
+#[test]
+fn test_completion_keeps_text_after_cursor() {
+    let mut h = TestHarness::new();
+    h.feed(":set tabs ts=4<Home><Right><Right><Right><Right><Right><Right><Right><Right>");
+    h.feed("<Tab>");
+    assert_eq!(cmdline(&h), "set tabstop ts=4");
+    assert_eq!(h.editor.cmdline.getcmdpos(), 12);
+    h.feed("<CR>");
+    assert_eq!(h.editor.options.number("tabstop"), 4);
+}
+
+/// Test: 'wildoptions' without "pum" shows a bar; <Tab> in a search
+/// command line is inserted
+/// Source: test_cmdline.vim "Test_wildmenu_pum"
+#[test]
+fn test_wildoptions_and_search_tab() {
+    let mut h = TestHarness::new();
+    h.feed(":se<Tab>");
+    assert!(h.editor.wildmenu().unwrap().pum);
+    h.feed("<Esc>");
+    h.set("wildoptions=");
+    h.feed(":se<Tab>");
+    assert!(!h.editor.wildmenu().unwrap().pum);
+    h.feed("<Esc>/a<Tab>");
+    assert_eq!(cmdline(&h), "a\t");
+}
+
+// ============================================================================
+// getcompletion()
+// ============================================================================
+
+/// Test: file names, hidden files and directories
+/// Source: test_cmdline.vim "Test_getcompletion"
+#[test]
+fn test_getcompletion_files() {
+    let h = TestHarness::new();
+    let dir = ScratchDir::new("files");
+    for name in ["alpha.rs", "alpha.o", "Beta.txt", ".hidden"] {
+        dir.file(name, "");
+    }
+    std::fs::create_dir(dir.join("sub")).unwrap();
+    let base = dir.display().to_string();
+
+    assert_eq!(
+        h.editor
+            .getcompletion(&format!("{}/a", base), CompleteType::File),
+        in_dir(&dir, &["alpha.o", "alpha.rs"])
+    );
+    assert_eq!(
+        h.editor
+            .getcompletion(&format!("{}/", base), CompleteType::File),
+        in_dir(&dir, &["Beta.txt", "alpha.o", "alpha.rs", "sub/"])
+    );
+    assert_eq!(
+        h.editor
+            .getcompletion(&format!("{}/.", base), CompleteType::File),
+        in_dir(&dir, &[".hidden"])
+    );
+    assert_eq!(
+        h.editor
+            .getcompletion(&format!("{}/", base), CompleteType::Dir),
+        in_dir(&dir, &["sub/"])
+    );
+}
+
+/// Test: 'wildignore' hides files and 'wildignorecase' ignores case
+/// Source: test_cmdline.vim "Test_getcompletion", "Test_wildignorecase"
+#[test]
+fn test_getcompletion_wildignore() {
+    let mut h = TestHarness::new();
+    let dir = ScratchDir::new("wildignore");
+    for name in ["alpha.rs", "alpha.o", "Beta.txt"] {
+        dir.file(name, "");
+    }
+    let base = dir.display().to_string();
+
+    h.set("wildignore=*.o,*.bak");
+    assert_eq!(
+        h.editor
+            .getcompletion(&format!("{}/a", base), CompleteType::File),
+        in_dir(&dir, &["alpha.rs"])
+    );
+    assert!(h
+        .editor
+        .getcompletion(&format!("{}/b", base), CompleteType::File)
+        .is_empty());
+    h.set("wildignorecase");
+    assert_eq!(
+        h.editor
+            .getcompletion(&format!("{}/b", base), CompleteType::File),
+        in_dir(&dir, &["Beta.txt"])
+    );
+}
+
+/// Test: highlight groups, mappings, functions and help tags
+/// Source: test_cmdline.vim "Test_getcompletion"
+#[test]
+fn test_getcompletion_other_types() {
+    let mut h = TestHarness::new();
+    assert_eq!(
+        h.editor.getcompletion("pmenu", CompleteType::Highlight),
+        ["Pmenu", "PmenuSbar", "PmenuSel", "PmenuThumb"]
+    );
+
+    h.editor
+        .mappings
+        .add(Mode::Normal, "<F2>", ":w<CR>", true)
+        .unwrap();
+    h.editor
+        .mappings
+        .add(Mode::Insert, "<F3>", "x", true)
+        .unwrap();
+    h.editor
+        .mappings
+        .add(Mode::Insert, "jk", "<Esc>", true)
+        .unwrap();
+    assert_eq!(
+        h.editor.getcompletion("<F", CompleteType::Mapping),
+        ["<F2>", "<F3>"]
+    );
+
+    h.editor.register_complete_func("MyComplete", |_, _| None);
+    assert_eq!(
+        h.editor.getcompletion("My", CompleteType::Function),
+        ["MyComplete("]
+    );
+
+    let dir = ScratchDir::new("help");
+    dir.file(
+        "doc/tags",
+        "'tabstop'\toptions.txt\t/*'tabstop'*\n:tab\ttabpage.txt\t/*:tab*\n",
+    );
+    h.set(&format!("runtimepath={}", dir.display()));
+    assert_eq!(
+        h.editor.getcompletion("'tab", CompleteType::Help),
+        ["'tabstop'"]
+    );
+}
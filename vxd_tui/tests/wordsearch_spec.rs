@@ -0,0 +1,263 @@
+//! Word search tests ported from Vim/Neovim tests
+//!
+//! These tests verify `*`, `#`, `g*` and `g#` with 'iskeyword' word
+//! boundaries, `gd` and `gD` skipping comments and strings, and `gn`/`gN`
+//! in Normal, Visual and Operator-pending mode.
+//!
+//! Source tests:
+//! - src/testdir/test_normal.vim
+//! - src/testdir/test_gn.vim
+//! - src/testdir/test_goto.vim
+
+mod common;
+
+use common::TestHarness;
+use vxd::modes::{Mode, VisualMode};
+use vxd::registers::{Register, RegisterBank};
+use vxd::search::SearchEngine;
+
+fn last_pattern(h: &TestHarness) -> String {
+    h.editor.search.last_pattern().unwrap().pattern.clone()
+}
+
+// ============================================================================
+// Star and Hash
+// ============================================================================
+
+/// Test: * finds the next whole-word match of the keyword under the cursor
+/// Source: test_normal.vim "Test_normal_star_hash"
+#[test]
+fn test_star_whole_word() {
+    let mut h = TestHarness::with_lines(&["foo bar", "foobar", "x foo"]);
+    h.feed("*");
+    assert_cursor!(h, 3, 2);
+    assert_eq!(last_pattern(&h), "\\<foo\\>");
+    h.feed("n");
+    assert_cursor!(h, 1, 0);
+}
+
+/// Test: # searches backward, skipping the word under the cursor
+/// Source: test_normal.vim "Test_normal_star_hash"
+#[test]
+fn test_hash_backward() {
+    let mut h = TestHarness::with_lines(&["foo", "foobar", "a foo b"]);
+    h.set_cursor(3, 3);
+    h.feed("#");
+    assert_cursor!(h, 1, 0);
+    h.feed("n");
+    assert_cursor!(h, 3, 2);
+}
+
+/// Test: g* and g# also match inside other words
+/// Source: test_normal.vim "Test_normal_gstar_gpound"
+#[test]
+fn test_gstar_partial_match() {
+    let mut h = TestHarness::with_lines(&["foo", "foobar", "xfoo"]);
+    h.feed("g*");
+    assert_cursor!(h, 2, 0);
+    assert_eq!(last_pattern(&h), "foo");
+
+    h.set_cursor(1, 0);
+    h.feed("g#");
+    assert_cursor!(h, 3, 1);
+}
+
+/// Test: the keyword after the cursor is used when not on one, and a count
+/// skips matches
+/// Source: test_normal.vim "Test_normal_star_hash"
+#[test]
+fn test_star_keyword_after_cursor_with_count() {
+    let mut h = TestHarness::with_lines(&["  ab", "ab", "ab", "ab"]);
+    h.feed("2*");
+    assert_cursor!(h, 3, 0);
+}
+
+/// Test: 'iskeyword' decides where the word ends
+/// Source: test_normal.vim "Test_normal_star_hash"
+#[test]
+fn test_star_uses_iskeyword() {
+    let mut h = TestHarness::with_lines(&["foo-bar", "foo", "foo-bar"]);
+    h.feed("*");
+    assert_cursor!(h, 2, 0);
+
+    h.set("iskeyword+=-");
+    h.set_cursor(1, 0);
+    h.feed("*");
+    assert_eq!(last_pattern(&h), "\\<foo-bar\\>");
+    assert_cursor!(h, 3, 0);
+}
+
+/// Test: * uses a keyword after the cursor, and otherwise searches for
+/// the non-blank text without word boundaries
+/// Source: test_normal.vim "Test_normal_star_hash"
+#[test]
+fn test_star_non_keyword() {
+    let mut h = TestHarness::with_lines(&["+= x", "a +="]);
+    h.feed("*");
+    assert_eq!(last_pattern(&h), "\\<x\\>");
+
+    h.set_lines(&["+= -", "a +="]);
+    h.set_cursor(1, 0);
+    h.feed("*");
+    assert_eq!(last_pattern(&h), "+=");
+    assert_cursor!(h, 2, 2);
+}
+
+/// Test: * on a multibyte keyword uses the whole character, also with the
+/// cursor column inside it
+/// Source: test_normal.vim "Test_normal_star_hash"
+#[test]
+fn test_star_multibyte() {
+    let mut h = TestHarness::with_lines(&["café x", "café"]);
+    h.feed("w*");
+    assert_eq!(last_pattern(&h), "\\<x\\>");
+
+    h.set_cursor(1, 4);
+    h.feed("*");
+    assert_eq!(last_pattern(&h), "\\<café\\>");
+    assert_cursor!(h, 2, 0);
+}
+
+/// Test: * on an empty line gives E348
+/// Source: test_normal.vim "Test_normal_star_hash"
+#[test]
+fn test_star_empty_line() {
+    let mut h = TestHarness::with_lines(&[""]);
+    h.feed("*");
+    assert!(h.message().contains("E348"));
+}
+
+/// Test: d* deletes up to the next match
+/// Source: test_normal.vim "Test_normal_star_hash"
+#[test]
+fn test_star_with_operator() {
+    let mut h = TestHarness::with_lines(&["ab cd ab ef"]);
+    h.feed("d*");
+    assert_lines!(h, "ab ef");
+    assert_eq!(
+        h.editor.registers.get(Register::Unnamed).unwrap().text,
+        vec!["ab cd ".to_string()]
+    );
+}
+
+// ============================================================================
+// Go to Declaration
+// ============================================================================
+
+/// Test: gd finds the first use in the function, skipping comments
+/// Source: test_goto.vim "Test_gd_ignore_comments"
+#[test]
+fn test_gd_local_declaration() {
+    let mut h = TestHarness::with_lines(&[
+        "int x;",
+        "",
+        "void f(void)",
+        "{",
+        "  // x is used below",
+        "  int x = 1;",
+        "  x++;",
+        "}",
+    ]);
+    h.set_cursor(7, 2);
+    h.feed("gd");
+    assert_cursor!(h, 6, 6);
+    assert_eq!(last_pattern(&h), "\\<x\\>");
+}
+
+/// Test: gD finds the first use in the file
+/// Source: test_goto.vim "Test_gD"
+#[test]
+fn test_gd_global_declaration() {
+    let mut h = TestHarness::with_lines(&["int x;", "", "void f(void)", "{", "  x++;", "}"]);
+    h.set_cursor(5, 2);
+    h.feed("gD");
+    assert_cursor!(h, 1, 4);
+}
+
+/// Test: gd skips uses in strings and after // on the same line
+/// Source: test_goto.vim "Test_gd_string"
+#[test]
+fn test_gd_skips_strings() {
+    let mut h =
+        TestHarness::with_lines(&["f()", "{", "  s = \"y\"; // y", "  int y;", "  y = 2;", "}"]);
+    h.set_cursor(5, 2);
+    h.feed("gd");
+    assert_cursor!(h, 4, 6);
+}
+
+/// Test: gd uses a K&R style declaration between the header and "{"
+/// Source: test_goto.vim "Test_gd_kr_style"
+#[test]
+fn test_gd_function_header() {
+    let mut h = TestHarness::with_lines(&["", "int f(a)", "int a;", "{", "  a++;", "}"]);
+    h.set_cursor(5, 2);
+    h.feed("gd");
+    assert_cursor!(h, 3, 4);
+}
+
+// ============================================================================
+// gn and gN
+// ============================================================================
+
+/// Test: gn selects the next match in Visual mode
+/// Source: test_gn.vim "Test_gn_command"
+#[test]
+fn test_gn_selects_match() {
+    let mut h = TestHarness::with_lines(&["abc foo def foo"]);
+    h.feed("/foo<CR>");
+    h.set_cursor(1, 0);
+    h.feed("gn");
+    assert_mode!(h, Mode::Visual(VisualMode::Char));
+    assert_cursor!(h, 1, 6);
+    h.feed("y");
+    assert_eq!(
+        h.editor.registers.get(Register::Unnamed).unwrap().text,
+        vec!["foo".to_string()]
+    );
+}
+
+/// Test: gN selects the previous match with the cursor at its start
+/// Source: test_gn.vim "Test_gn_command"
+#[test]
+fn test_gn_backward() {
+    let mut h = TestHarness::with_lines(&["foo x foo y"]);
+    h.feed("/foo<CR>");
+    h.set_cursor(1, 10);
+    h.feed("gN");
+    assert_mode!(h, Mode::Visual(VisualMode::Char));
+    assert_cursor!(h, 1, 6);
+}
+
+/// Test: dgn and cgn operate on the match under or after the cursor
+/// Source: test_gn.vim "Test_gn_command"
+#[test]
+fn test_gn_with_operator() {
+    let mut h = TestHarness::with_lines(&["a foo b foo c"]);
+    h.feed("/foo<CR>");
+    h.set_cursor(1, 0);
+    h.feed("dgn");
+    assert_lines!(h, "a  b foo c");
+    h.feed("cgnbar<Esc>");
+    assert_lines!(h, "a  b bar c");
+}
+
+/// Test: gn with the cursor inside a match uses that match
+/// Source: test_gn.vim "Test_gn_command"
+#[test]
+fn test_gn_cursor_in_match() {
+    let mut h = TestHarness::with_lines(&["xx hello yy hello"]);
+    h.feed("/hello<CR>");
+    h.set_cursor(1, 5);
+    h.feed("dgn");
+    assert_lines!(h, "xx  yy hello");
+}
+
+/// Test: gn without a previous pattern gives E35
+/// Source: test_gn.vim "Test_gn_command"
+#[test]
+fn test_gn_no_pattern() {
+    let mut h = TestHarness::with_lines(&["abc"]);
+    h.feed("gn");
+    assert_mode!(h, Mode::Normal);
+    assert!(h.message().contains("E35"));
+}